@@ -25,6 +25,7 @@ import {
   SidebarMenuItem,
   SidebarProvider,
 } from "@/components/ui/sidebar"
+import { useDeepLinks } from "@/hooks/use-deep-links"
 import { useFolderStore } from "@/store/folder-store"
 import { Link, useLocation } from "react-router-dom"
 
@@ -38,6 +39,7 @@ const data = {
 function ApplicationModal({ children }: React.PropsWithChildren) {
   const [open, setOpen] = React.useState(true)
   const location = useLocation()
+  useDeepLinks()
   const startScan = useFolderStore((state) => state.startScan)
   const scanStatus = useFolderStore((state) => state.scan.status)
   const isScanning = scanStatus === "running" || scanStatus === "queued"
@@ -267,11 +267,26 @@ export function FolderItem({
         {/* <div className="mt-1 w-full truncate text-xs text-muted-foreground" title={folder.path}>
           {folder.path}
         </div> */}
-        {!folder.isAccessible ? (
+        {folder.isOffline ? (
+          <span
+            className="mt-2 rounded-full bg-muted px-2 py-0.5 text-[11px] font-medium text-muted-foreground"
+            title="Files are kept and come back when the drive is reconnected"
+          >
+            Drive offline
+          </span>
+        ) : !folder.isAccessible ? (
           <span className="mt-2 rounded-full bg-destructive/10 px-2 py-0.5 text-[11px] font-medium text-destructive">
             Not accessible
           </span>
         ) : null}
+        {folder.isAccessible && folder.volumeKind && folder.volumeKind !== "local" ? (
+          <span
+            className="mt-2 rounded-full bg-amber-500/10 px-2 py-0.5 text-[11px] font-medium text-amber-600"
+            title="Scanned with partial hashes and no live watcher"
+          >
+            {folder.volumeKind === "network" ? "Network drive" : "Removable drive"}
+          </span>
+        ) : null}
         {folder.stats ? (
           <span className="mt-2 text-xs text-muted-foreground">
             {folder.stats.items} items
@@ -0,0 +1,44 @@
+import { useEffect } from "react"
+import { useNavigate } from "react-router-dom"
+
+import { DEEP_LINK_EVENT, takePendingDeepLink, type DeepLinkTarget } from "@/lib/ipc"
+
+// Opens whitespace:// links from notifications and reports on the matching view
+export function useDeepLinks() {
+  const navigate = useNavigate()
+
+  useEffect(() => {
+    let isCancelled = false
+    let unlisten: (() => void) | undefined
+
+    const open = (target: DeepLinkTarget | null) => {
+      if (!target || isCancelled) return
+      const search = new URLSearchParams(target.params).toString()
+      navigate(search ? `${target.route}?${search}` : target.route)
+    }
+
+    ;(async () => {
+      try {
+        const { listen } = await import("@tauri-apps/api/event")
+        const off = await listen<DeepLinkTarget>(DEEP_LINK_EVENT, () => {
+          // Taking the link clears it so a reload doesn't open it again
+          void takePendingDeepLink().then(open)
+        })
+        if (isCancelled) {
+          off()
+        } else {
+          unlisten = off
+        }
+        // A link that launched the app arrived before the listener existed
+        open(await takePendingDeepLink())
+      } catch (error) {
+        console.error("Failed to register deep link listener", error)
+      }
+    })()
+
+    return () => {
+      isCancelled = true
+      unlisten?.()
+    }
+  }, [navigate])
+}
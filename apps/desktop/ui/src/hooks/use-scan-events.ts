@@ -1,12 +1,14 @@
 import { useEffect } from "react"
 
-import { useFolderStore } from "@/store/folder-store"
+import { useFolderStore, type BackendGaugeState } from "@/store/folder-store"
 
 interface ScanProgressPayload {
   scanned: number
   skipped: number
   errors: number
   path_sample?: string | null
+  hash_bytes_done?: number
+  hash_bytes_total?: number
 }
 
 interface ScanFinishedPayload {
@@ -27,6 +29,7 @@ export function useScanEvents() {
   const handleDone = useFolderStore((state) => state.handleScanDone)
   const handleQueued = useFolderStore((state) => state.handleScanQueued)
   const handleError = useFolderStore((state) => state.handleScanError)
+  const applyGauge = useFolderStore((state) => state.applyGauge)
   const refreshStatus = useFolderStore((state) => state.refreshScanStatus)
   const loadCandidates = useFolderStore((state) => state.loadCandidates)
   const loadDir = useFolderStore((state) => state.loadDir)
@@ -75,6 +78,16 @@ export function useScanEvents() {
           unsubs.push(offQueued)
         }
 
+        // The backend pushes the gauge whenever scans or file operations move it
+        const offGauge = await listen<BackendGaugeState>("gauge://updated", (event) => {
+          applyGauge(event.payload)
+        })
+        if (isCancelled) {
+          offGauge()
+        } else {
+          unsubs.push(offGauge)
+        }
+
         // Roots changed -> refresh folders, gauge, candidates
         const offRoots = await listen<{ count: number }>("roots://changed", async () => {
           try {
@@ -90,6 +103,26 @@ export function useScanEvents() {
         } else {
           unsubs.push(offRoots)
         }
+
+        // Damaged database replaced -> the index is empty until the roots rescan
+        const offRecovered = await listen<{ corrupt_path: string; roots: string[] }>(
+          "database://recovered",
+          async (event) => {
+            console.warn("Database was rebuilt; damaged copy kept at", event.payload.corrupt_path)
+            try {
+              await useFolderStore.getState().loadFolders()
+              await useFolderStore.getState().loadGauge()
+              await useFolderStore.getState().loadCandidates()
+            } catch (e) {
+              // ignore
+            }
+          }
+        )
+        if (isCancelled) {
+          offRecovered()
+        } else {
+          unsubs.push(offRecovered)
+        }
       } catch (error) {
         console.error("Failed to register scan event listeners", error)
       }
@@ -107,7 +140,7 @@ export function useScanEvents() {
         }
       }
     }
-  }, [handleDone, handleError, handleProgress, handleQueued, refreshStatus])
+  }, [applyGauge, handleDone, handleError, handleProgress, handleQueued, refreshStatus])
 
   useEffect(() => {
     if (scanStatus !== "running") {
@@ -11,6 +11,7 @@ export async function invokeCommand<T = unknown>(
 export type StageOptions = {
   cooloffDays?: number
   note?: string | null
+  label?: string | null
 }
 
 export type StageOutcome = {
@@ -22,6 +23,7 @@ export type StageOutcome = {
   errors: string[]
   expiresAt: string | null
   note: string | null
+  remaining: number
 }
 
 export type StagedFileRecord = {
@@ -36,6 +38,8 @@ export type StagedFileRecord = {
   batchId: string | null
   note: string | null
   cooloffUntil: string | null
+  archivePath: string | null
+  statusMessage: { key: string; params?: Record<string, string> } | null
 }
 
 export type DuplicateGroupFile = {
@@ -64,6 +68,14 @@ export type UndoResult = {
   rollbackPerformed: boolean
 }
 
+export type CorruptedEntry = {
+  fileId: number
+  path: string
+  expectedHash: string
+  actualHash: string | null
+  reason: string
+}
+
 export type DeleteOutcome = {
   success: boolean
   filesProcessed: number
@@ -71,6 +83,7 @@ export type DeleteOutcome = {
   durationMs: number
   errors: string[]
   toTrash: boolean
+  corrupted: CorruptedEntry[]
 }
 
 const toSnakeOptions = (options?: StageOptions) => {
@@ -82,6 +95,9 @@ const toSnakeOptions = (options?: StageOptions) => {
   if (options.note !== undefined) {
     payload.note = options.note
   }
+  if (options.label !== undefined) {
+    payload.label = options.label
+  }
   return payload
 }
 
@@ -94,6 +110,7 @@ const mapStageOutcome = (response: any): StageOutcome => ({
   errors: Array.isArray(response?.errors) ? response.errors : [],
   expiresAt: response?.expires_at ?? null,
   note: response?.note ?? null,
+  remaining: response?.remaining ?? 0,
 })
 
 const mapStagedFile = (response: any): StagedFileRecord => ({
@@ -108,6 +125,8 @@ const mapStagedFile = (response: any): StagedFileRecord => ({
   batchId: response?.batch_id ?? null,
   note: response?.note ?? null,
   cooloffUntil: response?.cooloff_until ?? null,
+  archivePath: response?.archive_path ?? null,
+  statusMessage: response?.status_message ?? null,
 })
 
 const mapDuplicateGroupFile = (response: any): DuplicateGroupFile => ({
@@ -145,6 +164,13 @@ const mapDeleteOutcome = (response: any): DeleteOutcome => ({
   durationMs: response?.duration_ms ?? 0,
   errors: Array.isArray(response?.errors) ? response.errors : [],
   toTrash: Boolean(response?.to_trash),
+  corrupted: (response?.corrupted ?? []).map((entry: any) => ({
+    fileId: entry.file_id ?? 0,
+    path: entry.path ?? "",
+    expectedHash: entry.expected_hash ?? "",
+    actualHash: entry.actual_hash ?? null,
+    reason: entry.reason ?? "",
+  })),
 })
 
 const compactArgs = (base: Record<string, unknown>) => {
@@ -159,20 +185,217 @@ export async function listStaged(statuses?: string[]): Promise<StagedFileRecord[
   return response.map(mapStagedFile)
 }
 
+export async function getReviewItems(
+  minAgeDays: number,
+  expiringWithinDays?: number
+): Promise<StagedFileRecord[]> {
+  const args = compactArgs({ minAgeDays, expiringWithinDays })
+  const response = await invokeCommand<any[]>("get_review_items", args)
+  if (!Array.isArray(response)) return []
+  return response.map(mapStagedFile)
+}
+
 export async function stageFiles(
   fileIds: number[],
-  options?: StageOptions
+  options?: StageOptions,
+  idempotencyKey?: string
 ): Promise<StageOutcome> {
-  const args = compactArgs({ fileIds, options: toSnakeOptions(options) })
+  const args = compactArgs({ fileIds, options: toSnakeOptions(options), idempotencyKey })
   const response = await invokeCommand<Record<string, unknown>>("stage_files", args)
   return mapStageOutcome(response)
 }
 
+export type StageBucketFilters = {
+  rootPath?: string
+  minConfidence?: number
+}
+
+export async function stageBucket(
+  bucketKey: string,
+  filters?: StageBucketFilters,
+  options?: StageOptions
+): Promise<StageOutcome> {
+  const args = compactArgs({
+    bucketKey,
+    filters: filters
+      ? compactArgs({ root_path: filters.rootPath, min_confidence: filters.minConfidence })
+      : undefined,
+    options: toSnakeOptions(options),
+  })
+  const response = await invokeCommand<Record<string, unknown>>("stage_bucket", args)
+  return mapStageOutcome(response)
+}
+
+export type SavedViewFilters = {
+  min_size_bytes?: number
+  max_size_bytes?: number
+  mime?: string
+  root_path?: string
+  staged?: boolean
+  include_deleted?: boolean
+  min_age_days?: number
+}
+
+export type SavedViewInput = {
+  name: string
+  query?: string
+  filters?: SavedViewFilters
+  notify_threshold?: number | null
+}
+
+export async function listSavedViews(): Promise<any[]> {
+  return invokeCommand<any[]>("list_saved_views")
+}
+
+export async function addSavedView(view: SavedViewInput): Promise<any> {
+  return invokeCommand<any>("add_saved_view", { view })
+}
+
+export async function updateSavedView(id: number, view: SavedViewInput): Promise<any> {
+  return invokeCommand<any>("update_saved_view", { id, view })
+}
+
+export async function removeSavedView(id: number): Promise<void> {
+  await invokeCommand("remove_saved_view", { id })
+}
+
+export async function runView(
+  viewId: number,
+  paging?: { limit?: number; offset?: number; cursor?: string }
+): Promise<any> {
+  return invokeCommand<any>("run_view", compactArgs({ viewId, ...paging }))
+}
+
+export type BatchFilters = {
+  action_type?: "archive" | "delete"
+  origin?: string
+  label?: string
+  since?: string
+  limit?: number
+}
+
+export async function listBatches(filters?: BatchFilters): Promise<any[]> {
+  return invokeCommand<any[]>("list_batches", compactArgs({ filters }))
+}
+
+export async function updateBatchLabel(batchId: string, label: string | null): Promise<any> {
+  return invokeCommand<any>("update_batch_label", { batchId, label })
+}
+
+export type AgeHistogramBin = {
+  minDays: number
+  maxDays: number | null
+  fileCount: number
+  totalBytes: number
+}
+
+export async function getAgeHistogram(
+  rootPath: string,
+  metric?: "modified" | "accessed"
+): Promise<AgeHistogramBin[]> {
+  const response = await invokeCommand<any[]>(
+    "get_age_histogram",
+    compactArgs({ rootPath, metric })
+  )
+  return response.map((bin) => ({
+    minDays: bin.min_days,
+    maxDays: bin.max_days ?? null,
+    fileCount: bin.file_count ?? 0,
+    totalBytes: bin.total_bytes ?? 0,
+  }))
+}
+
+export type GaugeBreakdown = {
+  buckets: {
+    key: string
+    label: { key: string; params?: Record<string, string> }
+    fileCount: number
+    potentialBytes: number
+  }[]
+  roots: {
+    rootPath: string
+    stagedFiles: number
+    stagedBytes: number
+    freedFiles: number
+    freedBytes: number
+  }[]
+  windowStart: string
+  windowEnd: string
+}
+
+export async function getGaugeBreakdown(): Promise<GaugeBreakdown> {
+  const response = await invokeCommand<any>("gauge_breakdown")
+  return {
+    buckets: (response?.buckets ?? []).map((bucket: any) => ({
+      key: bucket.key,
+      label: bucket.label,
+      fileCount: bucket.file_count ?? 0,
+      potentialBytes: bucket.potential_bytes ?? 0,
+    })),
+    roots: (response?.roots ?? []).map((root: any) => ({
+      rootPath: root.root_path,
+      stagedFiles: root.staged_files ?? 0,
+      stagedBytes: root.staged_bytes ?? 0,
+      freedFiles: root.freed_files ?? 0,
+      freedBytes: root.freed_bytes ?? 0,
+    })),
+    windowStart: response?.window_start ?? "",
+    windowEnd: response?.window_end ?? "",
+  }
+}
+
+export type BucketStat = {
+  key: string
+  count: number
+  totalBytes: number
+}
+
+export async function getBucketStats(rootPath?: string): Promise<BucketStat[]> {
+  const response = await invokeCommand<any[]>("get_bucket_stats", compactArgs({ rootPath }))
+  return response.map((stat) => ({
+    key: stat.key,
+    count: stat.count ?? 0,
+    totalBytes: stat.total_bytes ?? 0,
+  }))
+}
+
+export type ActiveOperation = {
+  id: number
+  kind: string
+  fileIds: number[]
+  startedAt: number
+}
+
+export async function listActiveOperations(): Promise<ActiveOperation[]> {
+  const response = await invokeCommand<any[]>("list_active_operations")
+  return response.map((op) => ({
+    id: op.id,
+    kind: op.kind,
+    fileIds: op.file_ids ?? [],
+    startedAt: op.started_at,
+  }))
+}
+
 export async function restoreStaged(batchId: string): Promise<UndoResult> {
   const response = await invokeCommand<Record<string, unknown>>("restore_staged", { batchId })
   return mapUndoResult(response)
 }
 
+export type StagedReconcileResult = {
+  checked: number
+  lostFileIds: number[]
+  foundFileIds: number[]
+}
+
+export async function reconcileStaged(): Promise<StagedReconcileResult> {
+  const response = await invokeCommand<any>("reconcile_staged")
+  return {
+    checked: response?.checked ?? 0,
+    lostFileIds: response?.lost_file_ids ?? [],
+    foundFileIds: response?.found_file_ids ?? [],
+  }
+}
+
 export async function emptyStaged(
   fileIds: number[],
   toTrash: boolean
@@ -188,6 +411,229 @@ export async function getDuplicateGroups(limit?: number): Promise<DuplicateGroup
   return response.map(mapDuplicateGroup)
 }
 
+export type DuplicatePolicy = "keep_newest" | "keep_oldest" | "prefer_paths"
+
+export async function previewDuplicatePolicy(
+  policy?: DuplicatePolicy,
+  preferredPaths?: string[]
+): Promise<any> {
+  return invokeCommand<any>("preview_duplicate_policy", compactArgs({ policy, preferredPaths }))
+}
+
+export async function applyDuplicatePolicy(
+  policy?: DuplicatePolicy,
+  preferredPaths?: string[],
+  options?: StageOptions
+): Promise<StageOutcome> {
+  const args = compactArgs({ policy, preferredPaths, options: toSnakeOptions(options) })
+  const response = await invokeCommand<Record<string, unknown>>("apply_duplicate_policy", args)
+  return mapStageOutcome(response)
+}
+
 export async function fetchScanStatus<T>(): Promise<T> {
   return invokeCommand<T>("scan_status")
 }
+
+export type SelectionSummary = {
+  id: number
+  name: string
+  createdAt: string
+  fileCount: number
+  totalBytes: number
+}
+
+export type SelectionFilters = {
+  minSizeBytes?: number
+  maxSizeBytes?: number
+  mime?: string
+  rootPath?: string
+}
+
+const mapSelectionSummary = (response: any): SelectionSummary => ({
+  id: response?.id ?? 0,
+  name: response?.name ?? "",
+  createdAt: response?.created_at ?? "",
+  fileCount: response?.file_count ?? 0,
+  totalBytes: response?.total_bytes ?? 0,
+})
+
+export async function createSelection(name: string): Promise<SelectionSummary> {
+  return mapSelectionSummary(await invokeCommand("create_selection", { name }))
+}
+
+export async function addToSelection(
+  selectionId: number,
+  add: { fileIds?: number[]; query?: string; filters?: SelectionFilters }
+): Promise<SelectionSummary> {
+  const filters = add.filters
+    ? compactArgs({
+        min_size_bytes: add.filters.minSizeBytes,
+        max_size_bytes: add.filters.maxSizeBytes,
+        mime: add.filters.mime,
+        root_path: add.filters.rootPath,
+      })
+    : undefined
+  const args = compactArgs({ selectionId, fileIds: add.fileIds, query: add.query, filters })
+  return mapSelectionSummary(await invokeCommand("add_to_selection", args))
+}
+
+export async function removeFromSelection(
+  selectionId: number,
+  fileIds: number[]
+): Promise<SelectionSummary> {
+  const response = await invokeCommand("remove_from_selection", { selectionId, fileIds })
+  return mapSelectionSummary(response)
+}
+
+export async function getSelectionSummary(selectionId: number): Promise<SelectionSummary> {
+  return mapSelectionSummary(await invokeCommand("get_selection_summary", { selectionId }))
+}
+
+export async function discardSelection(selectionId: number): Promise<boolean> {
+  return Boolean(await invokeCommand("discard_selection", { selectionId }))
+}
+
+export async function stageSelection(
+  selectionId: number,
+  options?: StageOptions
+): Promise<StageOutcome> {
+  const args = compactArgs({ selectionId, options: toSnakeOptions(options) })
+  const response = await invokeCommand<Record<string, unknown>>("stage_selection", args)
+  return mapStageOutcome(response)
+}
+
+export async function deleteSelectionFiles(
+  selectionId: number,
+  toTrash: boolean
+): Promise<DeleteOutcome> {
+  const response = await invokeCommand<Record<string, unknown>>("delete_selection_files", {
+    selectionId,
+    toTrash,
+  })
+  return mapDeleteOutcome(response)
+}
+
+export type FeedbackVerdict = "kept" | "deleted" | "wrong_suggestion"
+
+export type FeedbackResult = {
+  bucket: string
+  totals: { kept: number; deleted: number; wrongSuggestion: number }
+  weight: number
+}
+
+export async function recordFeedback(
+  fileId: number,
+  verdict: FeedbackVerdict,
+  bucket?: string
+): Promise<FeedbackResult> {
+  const response = await invokeCommand<any>(
+    "record_feedback",
+    compactArgs({ fileId, verdict, bucket })
+  )
+  return {
+    bucket: response?.bucket ?? "",
+    totals: {
+      kept: response?.totals?.kept ?? 0,
+      deleted: response?.totals?.deleted ?? 0,
+      wrongSuggestion: response?.totals?.wrong_suggestion ?? 0,
+    },
+    weight: response?.weight ?? 1,
+  }
+}
+
+export type DatabaseRecovery = {
+  corruptPath: string
+  problems: string[]
+  roots: string[]
+  recoveredAt: string
+}
+
+export type RepairReport = {
+  problems: string[]
+  reindexed: boolean
+  recovery: DatabaseRecovery | null
+}
+
+const mapDatabaseRecovery = (response: any): DatabaseRecovery | null =>
+  response
+    ? {
+        corruptPath: response.corrupt_path ?? "",
+        problems: response.problems ?? [],
+        roots: response.roots ?? [],
+        recoveredAt: response.recovered_at ?? "",
+      }
+    : null
+
+export async function repairDatabase(): Promise<RepairReport> {
+  const response = await invokeCommand<any>("repair_database")
+  return {
+    problems: response?.problems ?? [],
+    reindexed: Boolean(response?.reindexed),
+    recovery: mapDatabaseRecovery(response?.recovery),
+  }
+}
+
+export async function getDatabaseRecovery(): Promise<DatabaseRecovery | null> {
+  return mapDatabaseRecovery(await invokeCommand<any>("get_database_recovery"))
+}
+
+export type DeepLinkTarget = {
+  view: "review" | "bucket" | "staged" | "history"
+  route: string
+  params: Record<string, string>
+}
+
+export const DEEP_LINK_EVENT = "deep-link://open"
+
+export async function takePendingDeepLink(): Promise<DeepLinkTarget | null> {
+  const response = await invokeCommand<any>("take_pending_deep_link")
+  if (!response) return null
+  return { view: response.view, route: response.route, params: response.params ?? {} }
+}
+
+export type WeeklyDigestReport = {
+  format: string
+  content: string
+  writtenTo: string | null
+}
+
+export async function generateWeeklyDigest(
+  windowDays?: number,
+  format?: "markdown" | "html" | "csv",
+  outputPath?: string
+): Promise<WeeklyDigestReport> {
+  const response = await invokeCommand<any>(
+    "generate_weekly_digest",
+    compactArgs({ windowDays, format, outputPath })
+  )
+  return {
+    format: response?.format ?? "markdown",
+    content: response?.content ?? "",
+    writtenTo: response?.written_to ?? null,
+  }
+}
+
+export type SettingsImportSummary = {
+  rootsAdded: string[]
+  rootsUpdated: string[]
+  exclusionsAdded: number
+  rulesAdded: number
+  protectedPathsAdded: number
+  skipped: string[]
+}
+
+export async function exportSettings(destPath: string): Promise<string> {
+  return invokeCommand<string>("export_settings", { destPath })
+}
+
+export async function importSettings(srcPath: string): Promise<SettingsImportSummary> {
+  const response = await invokeCommand<any>("import_settings", { srcPath })
+  return {
+    rootsAdded: response?.roots_added ?? [],
+    rootsUpdated: response?.roots_updated ?? [],
+    exclusionsAdded: response?.exclusions_added ?? 0,
+    rulesAdded: response?.rules_added ?? 0,
+    protectedPathsAdded: response?.protected_paths_added ?? 0,
+    skipped: response?.skipped ?? [],
+  }
+}
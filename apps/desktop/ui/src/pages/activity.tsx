@@ -650,7 +650,13 @@ function CandidateGroupsView({
 
                       <div className="flex items-center gap-2">
 
-                        <span className="text-xs text-muted-foreground tabular-nums">
+                        <span
+
+                          className="text-xs text-muted-foreground tabular-nums"
+
+                          title={file.explanation || undefined}
+
+                        >
 
                           {formatBytes(file.sizeBytes)}
 
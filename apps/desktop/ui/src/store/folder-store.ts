@@ -12,7 +12,13 @@ import {
   type StagedFileRecord as StageRecord,
   type DuplicateGroup as DuplicateGroupResult,
 } from "@/lib/ipc"
-import type { DirectoryEntry, ScanCandidate, WatchedFolder } from "@/types/folders"
+import type {
+  DirectoryEntry,
+  ScanCandidate,
+  ScoreFactors,
+  VolumeKind,
+  WatchedFolder,
+} from "@/types/folders"
 
 import { notifySweepReady } from "@/lib/notify"
 import { toast } from "@/components/ui/use-toast"
@@ -42,6 +48,8 @@ type BackendWatchedFolder = {
   name: string
   path: string
   is_accessible: boolean
+  volume_kind?: VolumeKind
+  is_offline?: boolean
 }
 
 type BackendDirectoryEntry = {
@@ -62,6 +70,8 @@ type ScanProgressPayload = {
   skipped: number
   errors: number
   path_sample?: string | null
+  hash_bytes_done?: number
+  hash_bytes_total?: number
 }
 
 type ScanFinishedPayload = {
@@ -89,7 +99,7 @@ type ScanStatusPayload = {
   last_error?: string | null
 }
 
-type BackendGaugeState = {
+export type BackendGaugeState = {
   potential_today_bytes: number
   staged_week_bytes: number
   freed_week_bytes: number
@@ -108,6 +118,19 @@ type BackendCandidate = {
   confidence: number
   preview_hint: string
   age_days: number
+  is_cloud_placeholder?: boolean
+  factors?: BackendScoreFactors | null
+  explanation?: string | null
+}
+
+type BackendScoreFactors = {
+  size_bytes: number
+  age_days: number
+  is_duplicate: boolean
+  is_unopened: boolean
+  has_keyword_flag: boolean
+  in_git_repo: boolean
+  recent_sibling_burst: boolean
 }
 
 // Bucketed endpoint response types (minimal)
@@ -117,9 +140,23 @@ type UiCandidate = {
   parent: string
   size: number
   reason: string
+  is_cloud_placeholder?: boolean
+  confidence?: number
+  factors?: BackendScoreFactors | null
+  explanation?: string | null
+}
+type BucketPaging = {
+  limit: number
+  offset: number
+  has_more: boolean
+  next_cursor?: string | null
 }
 type CandidatesResponse = {
   by_bucket: Record<string, UiCandidate[]>
+  paging?: BucketPaging
+  summaries?: { key: string; count: number; total_bytes: number }[]
+  bucket_paging?: Record<string, BucketPaging>
+  pending?: boolean
 }
 
 type BackendUndoBatchSummary = {
@@ -127,6 +164,8 @@ type BackendUndoBatchSummary = {
   action_type: string
   file_count: number
   created_at: number
+  label?: string | null
+  origin?: string | null
 }
 
 type BackendUndoResult = {
@@ -199,6 +238,8 @@ function mapWatchedFolder(folder: BackendWatchedFolder): WatchedFolder {
     name: folder.name,
     path: folder.path,
     isAccessible: folder.is_accessible,
+    volumeKind: folder.volume_kind ?? "local",
+    isOffline: folder.is_offline ?? false,
   }
 }
 
@@ -224,6 +265,21 @@ function mapCandidate(candidate: BackendCandidate): ScanCandidate {
     confidence: candidate.confidence,
     previewHint: candidate.preview_hint,
     ageDays: candidate.age_days,
+    isCloudPlaceholder: candidate.is_cloud_placeholder ?? false,
+    factors: candidate.factors ? mapScoreFactors(candidate.factors) : null,
+    explanation: candidate.explanation ?? "",
+  }
+}
+
+function mapScoreFactors(factors: BackendScoreFactors): ScoreFactors {
+  return {
+    sizeBytes: factors.size_bytes,
+    ageDays: factors.age_days,
+    isDuplicate: factors.is_duplicate,
+    isUnopened: factors.is_unopened,
+    hasKeywordFlag: factors.has_keyword_flag,
+    inGitRepo: factors.in_git_repo,
+    recentSiblingBurst: factors.recent_sibling_burst,
   }
 }
 
@@ -247,6 +303,8 @@ function normalizeReason(value: string): string {
   if (lower === "old desktop") return "old_desktop"
   if (lower === "duplicates" || lower === "duplicate") return "duplicates"
   if (lower === "executables" || lower === ".exe" || lower === "exe") return "executable"
+  if (lower === "installers" || lower === "installer") return "installer"
+  if (lower === "broken symlinks" || lower === "broken_symlink") return "broken_symlink"
   return lower.replace(/\s+/g, "_")
 }
 
@@ -281,6 +339,7 @@ interface FolderStoreState {
   selectFolder: (id: string | null) => Promise<void>
   loadDir: (folderId: string, pathOverride?: string) => Promise<void>
   loadGauge: () => Promise<void>
+  applyGauge: (result: BackendGaugeState) => void
   openInSystem: (path: string) => Promise<void>
   startScan: (paths?: string[]) => Promise<void>
   rescanAll: () => Promise<void>
@@ -380,30 +439,34 @@ export const useFolderStore = create<FolderStoreState>((set, get) => ({
   async loadGauge() {
     try {
       const result = await invokeCommand<BackendGaugeState>("gauge_state")
-      const stagedBytes = result.staged_week_bytes ?? 0
-      const nextLevel = determineSweepLevel(stagedBytes)
-      const currentLevel = get().gauge.sweepLevel
-
-      if (nextLevel !== "none" && nextLevel !== currentLevel) {
-        void notifySweepReady(stagedBytes)
-      }
-
-      set({
-        gauge: {
-          potentialBytes: result.potential_today_bytes ?? 0,
-          stagedBytes,
-          freedBytes: result.freed_week_bytes ?? 0,
-          computedAt: result.computed_at ?? new Date().toISOString(),
-          windowStart: result.window_start ?? null,
-          windowEnd: result.window_end ?? null,
-          sweepLevel: nextLevel,
-        },
-      })
+      get().applyGauge(result)
     } catch (error) {
       console.error("Failed to load gauge state", error)
     }
   },
 
+  applyGauge(result) {
+    const stagedBytes = result.staged_week_bytes ?? 0
+    const nextLevel = determineSweepLevel(stagedBytes)
+    const currentLevel = get().gauge.sweepLevel
+
+    if (nextLevel !== "none" && nextLevel !== currentLevel) {
+      void notifySweepReady(stagedBytes)
+    }
+
+    set({
+      gauge: {
+        potentialBytes: result.potential_today_bytes ?? 0,
+        stagedBytes,
+        freedBytes: result.freed_week_bytes ?? 0,
+        computedAt: result.computed_at ?? new Date().toISOString(),
+        windowStart: result.window_start ?? null,
+        windowEnd: result.window_end ?? null,
+        sweepLevel: nextLevel,
+      },
+    })
+  },
+
   async addFolder() {
     try {
       const selection = await openDirectoryDialog()
@@ -636,7 +699,7 @@ export const useFolderStore = create<FolderStoreState>((set, get) => ({
             offset: 0, 
             sort: "size_desc",
             // Include all bucket types
-            buckets: ["duplicate", "big_download", "old_desktop", "screenshot", "executable", "other"]
+            buckets: ["duplicate", "big_download", "old_desktop", "screenshot", "installer", "broken_symlink", "executable", "other"]
           } 
         }
       )
@@ -650,9 +713,12 @@ export const useFolderStore = create<FolderStoreState>((set, get) => ({
             size_bytes: c.size,
             reason: bucket,
             score: 0,
-            confidence: 0,
-            preview_hint: "",
-            age_days: 0,
+            confidence: c.confidence ?? 0,
+            preview_hint: c.is_cloud_placeholder ? "cloud-only" : "",
+            age_days: c.factors?.age_days ?? 0,
+            is_cloud_placeholder: c.is_cloud_placeholder,
+            factors: c.factors,
+            explanation: c.explanation,
           })
         }
       }
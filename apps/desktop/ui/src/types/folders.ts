@@ -1,8 +1,12 @@
+export type VolumeKind = "local" | "network" | "removable"
+
 export type WatchedFolder = {
   id: string
   name: string
   path: string
   isAccessible: boolean
+  volumeKind?: VolumeKind
+  isOffline?: boolean
   platformStyle?: "win" | "mac"
   stats?: { items: number; bytes: number }
 }
@@ -25,4 +29,17 @@ export type ScanCandidate = {
   confidence: number
   previewHint: string
   ageDays: number
+  isCloudPlaceholder: boolean
+  factors: ScoreFactors | null
+  explanation: string
+}
+
+export type ScoreFactors = {
+  sizeBytes: number
+  ageDays: number
+  isDuplicate: boolean
+  isUnopened: boolean
+  hasKeywordFlag: boolean
+  inGitRepo: boolean
+  recentSiblingBurst: boolean
 }
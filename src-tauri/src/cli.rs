@@ -0,0 +1,312 @@
+use crate::commands::build_weekly_digest;
+use crate::db::{self, Database, DbPool};
+use crate::ops::space::SpaceManager;
+use crate::reports::{self, DigestFormat};
+use crate::scanner;
+use crate::selector::FileSelector;
+use std::io::Write;
+use std::path::{Component, PathBuf};
+
+/// First argument that runs a command against the database instead of
+/// opening the window.
+pub const HEADLESS_FLAG: &str = "--headless";
+const DEFAULT_CANDIDATE_LIMIT: usize = 50;
+const MAX_CANDIDATE_LIMIT: usize = 1000;
+const DEFAULT_REPORT_DAYS: i64 = 7;
+
+const USAGE: &str = "\
+Usage: white-space --headless <command> [options]
+
+Commands:
+  scan [PATH...]           Scan the given folders, or every watched folder
+  candidates [--json] [--limit N]
+                           List today's cleanup candidates (default 50)
+  report [--csv | --markdown | --html] [--days N] [--output FILE]
+                           Print the disk hygiene report for the last N days
+                           (default 7), or save it to FILE
+";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Scan {
+        paths: Vec<PathBuf>,
+    },
+    Candidates {
+        json: bool,
+        limit: usize,
+    },
+    Report {
+        format: DigestFormat,
+        days: i64,
+        output: Option<PathBuf>,
+    },
+    Help,
+}
+
+/// Runs `args` (everything after [`HEADLESS_FLAG`]) and returns the process
+/// exit code: 0 on success, 1 when the command failed, 2 for bad arguments.
+pub fn run(args: &[String]) -> i32 {
+    attach_console();
+    let command = match parse_args(args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("white-space: {message}\n\n{USAGE}");
+            return 2;
+        }
+    };
+    if command == Command::Help {
+        print!("{USAGE}");
+        return 0;
+    }
+
+    let app_dir = crate::app_dir();
+    if let Err(err) = crate::logging::init(
+        &crate::logging::log_dir(&app_dir),
+        crate::logging::DEFAULT_LOG_LEVEL,
+    ) {
+        eprintln!("Failed to initialize logging: {err}");
+    }
+    match open_database().and_then(|pool| execute(command, &pool)) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("white-space: {message}");
+            1
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(Command::Help);
+    };
+    let mut rest = rest.iter();
+    let value = |flag: &str, rest: &mut std::slice::Iter<String>| {
+        rest.next()
+            .cloned()
+            .ok_or_else(|| format!("{flag} needs a value"))
+    };
+
+    match name.as_str() {
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "scan" => {
+            let mut paths = Vec::new();
+            for arg in rest {
+                if arg.starts_with("--") {
+                    return Err(format!("unknown option for scan: {arg}"));
+                }
+                paths.push(absolute_dir(arg)?);
+            }
+            Ok(Command::Scan { paths })
+        }
+        "candidates" => {
+            let (mut json, mut limit) = (false, DEFAULT_CANDIDATE_LIMIT);
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    "--limit" => {
+                        limit = value(arg, &mut rest)?
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=MAX_CANDIDATE_LIMIT).contains(n))
+                            .ok_or_else(|| {
+                                format!("--limit must be 1-{MAX_CANDIDATE_LIMIT}")
+                            })?;
+                    }
+                    _ => return Err(format!("unknown option for candidates: {arg}")),
+                }
+            }
+            Ok(Command::Candidates { json, limit })
+        }
+        "report" => {
+            let (mut format, mut days, mut output) =
+                (DigestFormat::Markdown, DEFAULT_REPORT_DAYS, None);
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--csv" => format = DigestFormat::Csv,
+                    "--markdown" => format = DigestFormat::Markdown,
+                    "--html" => format = DigestFormat::Html,
+                    "--days" => {
+                        days = value(arg, &mut rest)?
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=365).contains(n))
+                            .ok_or("--days must be 1-365")?;
+                    }
+                    "--output" => output = Some(PathBuf::from(value(arg, &mut rest)?)),
+                    _ => return Err(format!("unknown option for report: {arg}")),
+                }
+            }
+            Ok(Command::Report {
+                format,
+                days,
+                output,
+            })
+        }
+        _ => Err(format!("unknown command: {name}")),
+    }
+}
+
+/// `path` made absolute against the working directory, so `scan .` works
+/// from a shell. Like the scan command, `..` is refused.
+fn absolute_dir(path: &str) -> Result<PathBuf, String> {
+    let raw = PathBuf::from(path);
+    if raw.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(format!("path traversal not allowed: {path}"));
+    }
+    let joined = if raw.is_absolute() {
+        raw
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("working directory: {e}"))?
+            .join(raw)
+    };
+    // Drops `.` components so the root is stored as the user would type it.
+    let clean: PathBuf = joined.components().collect();
+    if !clean.is_dir() {
+        return Err(format!("not a directory: {}", clean.display()));
+    }
+    Ok(clean)
+}
+
+/// The app's own database, checked and migrated as at launch.
+fn open_database() -> Result<DbPool, String> {
+    let (pool, recovery) = db::recovery::open_checked(&crate::app_db_path());
+    if let Some(recovery) = recovery {
+        tracing::warn!(?recovery, "database was replaced before a headless run");
+    }
+    let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+    Database::new(conn)
+        .run_migrations()
+        .map_err(|e| format!("database migration: {e}"))?;
+    Ok(pool)
+}
+
+fn execute(command: Command, pool: &DbPool) -> Result<(), String> {
+    let db = Database::new(pool.get().map_err(|e| format!("db pool: {e}"))?);
+    let size = |bytes: u64| SpaceManager::new().format_bytes(bytes);
+    let mut out = std::io::stdout().lock();
+
+    match command {
+        Command::Help => write!(out, "{USAGE}").map_err(|e| e.to_string())?,
+        Command::Scan { paths } => {
+            let roots: Vec<String> = if paths.is_empty() {
+                db.list_watched_roots()
+                    .map_err(|e| format!("database: {e}"))?
+                    .into_iter()
+                    .filter(|root| !root.is_offline)
+                    .map(|root| root.path)
+                    .collect()
+            } else {
+                paths.iter().map(|p| p.to_string_lossy().to_string()).collect()
+            };
+            if roots.is_empty() {
+                return Err("no folders to scan; pass a path or watch a folder".to_string());
+            }
+            drop(db);
+            let summary =
+                scanner::run_headless_scan(pool, roots).map_err(|e| format!("scan: {e}"))?;
+            for error in &summary.errors {
+                eprintln!("{error}");
+            }
+            writeln!(
+                out,
+                "Scanned {} files, skipped {}, {} errors in {:.1}s",
+                summary.counted,
+                summary.skipped,
+                summary.errors.len(),
+                summary.duration_ms as f64 / 1000.0
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        Command::Candidates { json, limit } => {
+            let candidates = FileSelector::from_db(&db)
+                .daily_candidates(Some(limit), &db)
+                .map_err(|e| format!("selector: {e}"))?;
+            if json {
+                serde_json::to_writer_pretty(&mut out, &candidates)
+                    .map_err(|e| e.to_string())?;
+                writeln!(out).map_err(|e| e.to_string())?;
+            } else {
+                for candidate in &candidates {
+                    writeln!(
+                        out,
+                        "{:>10}  {:<24}  {}",
+                        size(candidate.size_bytes),
+                        candidate.reason,
+                        candidate.path
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        Command::Report {
+            format,
+            days,
+            output,
+        } => {
+            let digest = build_weekly_digest(&db, days)?;
+            let content = reports::render_digest(&digest, format);
+            match output {
+                Some(path) => std::fs::write(&path, &content)
+                    .map_err(|e| format!("failed to write {}: {e}", path.display()))?,
+                None => out.write_all(content.as_bytes()).map_err(|e| e.to_string())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Release builds on Windows have no console of their own; borrow the one
+/// the command was typed into so output shows up there.
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_reads_commands_and_rejects_bad_options() {
+        assert_eq!(parse_args(&[]), Ok(Command::Help));
+        assert_eq!(
+            parse_args(&args(&["candidates", "--json", "--limit", "5"])),
+            Ok(Command::Candidates {
+                json: true,
+                limit: 5
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["report", "--csv", "--days", "30"])),
+            Ok(Command::Report {
+                format: DigestFormat::Csv,
+                days: 30,
+                output: None
+            })
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        assert_eq!(
+            parse_args(&args(&["scan", &path])),
+            Ok(Command::Scan {
+                paths: vec![dir.path().to_path_buf()]
+            })
+        );
+
+        assert!(parse_args(&args(&["candidates", "--limit", "0"])).is_err());
+        assert!(parse_args(&args(&["candidates", "--limit"])).is_err());
+        assert!(parse_args(&args(&["report", "--days", "400"])).is_err());
+        assert!(parse_args(&args(&["scan", "../elsewhere"])).is_err());
+        assert!(parse_args(&args(&["purge"])).is_err());
+    }
+}
@@ -1,11 +1,45 @@
-use crate::db::{Database, DbPool};
-use crate::gauge::{GaugeManager, GaugeState};
-use crate::models::{ActionType, File, NewStagedFile, StagedFileRecord, WatchedRoot};
-use crate::ops::{ArchiveManager, DeleteManager, UndoManager, UndoResult};
+use crate::db::recovery::{corrupt_path_for, salvage_roots};
+use crate::db::{Database, DatabaseRecovery, DbPool, RecoveryState, DATABASE_RECOVERED_EVENT};
+use crate::deep_link::{self, DeepLinkTarget};
+use crate::gauge::{
+    self, GaugeBreakdown, GaugeInvalidatedPayload, GaugeManager, GaugeState,
+    GAUGE_INVALIDATED_EVENT, GAUGE_UPDATED_EVENT,
+};
+use crate::i18n::{self, Message};
+use crate::models::{
+    Action, ActionType, AgeHistogramBin, AgeMetric, BatchFilters, BatchRecord, BucketFeedback,
+    BucketPrefilter, DismissedFile, Exclusion, FeedbackVerdict, File, FileSearchFilters, HashAlgo,
+    IdempotencyClaim, MimeFamilyTotals, NewFile, NewSavedView, NewUserRule,
+    PageCursor, ProtectedPath, RootSettings, SavedView, ScanRecord, SelectionSummary,
+    StagedFileRecord, UserRule, VolumeKind, WatchedRoot,
+};
+use crate::ops::system_paths::is_system_path;
+use crate::ops::{
+    self, ArchiveConfig, ArchiveManager, DeleteManager, OpsDonePayload, OpsProgressPayload,
+    ProgressCallback, SpaceManager, UndoManager, UndoResult, OPS_DONE_EVENT, OPS_PROGRESS_EVENT,
+};
+use crate::scanner::active_project::{ActiveProjectDetector, ArtifactProject};
 use crate::scanner::{self, ScanResult, Scanner};
+use crate::scanner::file_walker::{compile_exclusion, FileWalker, DIRECTORY_MIME};
+use crate::scanner::schedule::{MAX_SCAN_INTERVAL_HOURS, MIN_SCAN_INTERVAL_HOURS};
 use crate::scanner::watcher::{register_root, unregister_root};
-use crate::selector::{scoring::Candidate, FileSelector};
-use chrono::{DateTime, Duration, Utc};
+use crate::selector::{
+    bucket_key, is_protected,
+    rules::compile_rule_glob,
+    scoring::{feedback_weight, Candidate, Explanation, ScoreFactors},
+    FileSelector, SelectorConfig, DEFAULT_RESTORE_GRACE_DAYS, INSTALLERS_BUCKET_PREF,
+    MAX_RESTORE_GRACE_DAYS, RESTORE_GRACE_DAYS_PREF,
+};
+use crate::logging;
+use crate::maintenance;
+use crate::notifications;
+use crate::preview::{self, FilePreview};
+use crate::reports::{
+    self, DigestFile, DigestFormat, FolderSize, FreedSpacePoint, MetricAggregation, MetricSeries,
+    MetricWindow, ScreenshotMonth, SimilarImageGroup, StaleGroup, WeeklyDigest,
+};
+use crate::thumbnail::ThumbnailCache;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use std::collections::HashSet;
 use std::fs;
 use std::io::ErrorKind;
@@ -15,27 +49,50 @@ use tauri::Emitter;
 use walkdir::WalkDir;
 
 // Command result types
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ArchiveOutcome {
     pub success: bool,
+    pub batch_id: Option<String>,
     pub files_processed: usize,
     pub total_bytes: u64,
     pub duration_ms: u64,
     pub errors: Vec<String>,
+    /// `errors` for individual files as catalog messages.
+    pub error_messages: Vec<Message>,
     pub dry_run: bool,
+    /// Zip holding the batch when compression is on.
+    #[serde(default)]
+    pub archive_file: Option<String>,
+    /// Bytes compression saved; 0 for uncompressed batches.
+    #[serde(default)]
+    pub bytes_saved: u64,
 }
 
+/// Where new archive batches go and how they are stored.
 #[derive(Debug, Clone, serde::Serialize)]
+pub struct ArchiveSettings {
+    pub base_path: String,
+    pub default_path: String,
+    pub is_default: bool,
+    pub compression: ops::ArchiveCompression,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DeleteOutcome {
     pub success: bool,
+    pub batch_id: Option<String>,
     pub files_processed: usize,
     pub total_bytes_freed: u64,
     pub duration_ms: u64,
     pub errors: Vec<String>,
+    /// `errors` for individual files as catalog messages.
+    pub error_messages: Vec<Message>,
     pub to_trash: bool,
+    /// Archived copies that failed verification and were left in place.
+    pub corrupted: Vec<ops::verify::CorruptedEntry>,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StageOutcome {
     pub success: bool,
     pub batch_id: Option<String>,
@@ -45,12 +102,35 @@ pub struct StageOutcome {
     pub errors: Vec<String>,
     pub expires_at: Option<String>,
     pub note: Option<String>,
+    /// Files held back by the per-batch cap; only `stage_bucket` leaves any.
+    pub remaining: usize,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, Default)]
 pub struct StageOptions {
     pub cooloff_days: Option<i64>,
     pub note: Option<String>,
+    /// User's name for the batch, shown in undo and exports.
+    pub label: Option<String>,
+    /// Set by the command staging on the UI's behalf; never read from IPC.
+    #[serde(skip)]
+    pub origin: Option<String>,
+}
+
+/// Tags `options` with the flow that is staging, for the batch record.
+fn with_origin(options: Option<StageOptions>, origin: &str) -> Option<StageOptions> {
+    let mut opts = options.unwrap_or_default();
+    opts.origin = Some(origin.to_string());
+    Some(opts)
+}
+
+/// Narrows the files `stage_bucket` picks from a bucket.
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+pub struct StageBucketFilters {
+    /// Only files under this folder
+    pub root_path: Option<String>,
+    /// Skip candidates the selector is less sure about
+    pub min_confidence: Option<f64>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -72,6 +152,25 @@ pub struct DuplicateGroup {
     pub files: Vec<DuplicateGroupFile>,
 }
 
+/// One duplicate group as a duplicate policy resolves it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicatePolicyGroup {
+    pub hash: String,
+    pub keep: DuplicateGroupFile,
+    pub stage: Vec<DuplicateGroupFile>,
+    /// Bytes freed once the staged copies are emptied.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicatePolicyPreview {
+    pub policy: String,
+    pub preferred_paths: Vec<String>,
+    pub groups: Vec<DuplicatePolicyGroup>,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct StagedFile {
     pub record_id: i64,
@@ -85,6 +184,10 @@ pub struct StagedFile {
     pub batch_id: Option<String>,
     pub note: Option<String>,
     pub cooloff_until: Option<String>,
+    /// Where the archived copy lives; only review items look it up.
+    pub archive_path: Option<String>,
+    /// Why the file is in its status, e.g. for `lost` copies.
+    pub status_message: Option<Message>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -93,6 +196,11 @@ pub struct WatchedFolder {
     pub path: String,
     pub name: String,
     pub is_accessible: bool,
+    /// Network and removable roots default to lighter scanning; the UI
+    /// warns about them.
+    pub volume_kind: VolumeKind,
+    /// The drive or share is not mounted; its files are kept, not deleted.
+    pub is_offline: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -125,7 +233,18 @@ pub struct UiCandidate {
     pub partial_sha1: Option<String>,
     pub sha1: Option<String>,
     pub reason: String,
+    /// Bucket display name as a catalog message.
+    pub reason_message: Message,
     pub group_key: Option<String>,
+    /// Cloud-only file; shown with a badge and worth no freed space.
+    pub is_cloud_placeholder: bool,
+    /// How sure the selector is that the file can go, from 0 to 1.
+    pub confidence: f64,
+    /// Scoring inputs and their summary; absent for filesystem fallbacks.
+    pub factors: Option<ScoreFactors>,
+    pub explanation: Option<String>,
+    pub hint_messages: Vec<Message>,
+    pub explanation_messages: Option<Explanation>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -134,6 +253,11 @@ pub struct CandidatesResponse {
     pub summaries: Vec<BucketSummary>,
     pub total_count: usize,
     pub paging: Paging,
+    /// Paging of each bucket's list, filled when `bucket_limit` was given.
+    pub bucket_paging: std::collections::HashMap<String, Paging>,
+    /// Set while nothing is indexed yet and a scan is running or was just
+    /// started; the listing fills in once it finishes.
+    pub pending: bool,
     pub errors: Vec<String>,
 }
 
@@ -142,6 +266,8 @@ pub struct Paging {
     pub limit: usize,
     pub offset: usize,
     pub has_more: bool,
+    /// Token for the next page, when the listing supports cursors.
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -150,6 +276,9 @@ pub struct UndoBatchSummary {
     pub action_type: String,
     pub file_count: usize,
     pub created_at: i64,
+    /// From the batch record; batches made before labels existed have none.
+    pub label: Option<String>,
+    pub origin: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -170,9 +299,45 @@ pub struct UserPrefs {
     pub scan_interval_hours: u32,
     pub archive_age_threshold_days: u32,
     pub delete_age_threshold_days: u32,
+    pub scan_concurrency: usize,
+    pub auto_empty_after_cooloff: bool,
+    pub notifications_enabled: bool,
+    /// GB of new candidates after a scan that triggers a notification; 0 = off.
+    pub notify_scan_threshold_gb: u32,
+    pub notify_expiry: bool,
+    pub notify_auto_empty: bool,
+    pub notify_tidy_reminder: bool,
+    /// "error", "warn", "info", "debug" or "trace".
+    pub log_level: String,
+    /// Days of actions, metrics and scan history to keep; 0 = forever.
+    pub history_retention_days: u32,
+    pub purge_removed_roots: bool,
+    /// Days after the cooloff before archived files are removed; 0 = never.
+    pub archive_retention_days: u32,
+    /// "trash" or "purge".
+    pub archive_retention_mode: String,
+    pub installers_bucket_enabled: bool,
+    /// Index symlinks so broken ones can be suggested. Links are never followed.
+    pub record_symlinks: bool,
+    /// "auto" throttles scans on battery; "normal" or "throttled" force a mode.
+    pub scan_power_mode: String,
+    /// BCP 47 tag the UI renders catalog messages in.
+    pub locale: String,
+    /// "keep_newest", "keep_oldest" or "prefer_paths".
+    pub duplicate_policy: String,
+    /// Folders whose copy is kept under "prefer_paths", most preferred first.
+    pub duplicate_preferred_paths: Vec<String>,
+    /// Files larger than this many GB are never fully hashed; 0 = no limit.
+    pub max_full_hash_gb: u32,
+    /// Scheduled scans and automatic emptying wait for idle input.
+    pub only_clean_when_idle: bool,
+    /// Minutes without input that count as idle.
+    pub idle_minutes: u32,
+    /// Days a restored file is kept off the candidate list; 0 = not at all.
+    pub restore_grace_days: u32,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Deserialize)]
 pub struct PartialUserPrefs {
     pub dry_run_default: Option<bool>,
     pub tidy_day: Option<String>,
@@ -184,6 +349,28 @@ pub struct PartialUserPrefs {
     pub scan_interval_hours: Option<u32>,
     pub archive_age_threshold_days: Option<u32>,
     pub delete_age_threshold_days: Option<u32>,
+    pub scan_concurrency: Option<usize>,
+    pub auto_empty_after_cooloff: Option<bool>,
+    pub notifications_enabled: Option<bool>,
+    pub notify_scan_threshold_gb: Option<u32>,
+    pub notify_expiry: Option<bool>,
+    pub notify_auto_empty: Option<bool>,
+    pub notify_tidy_reminder: Option<bool>,
+    pub log_level: Option<String>,
+    pub history_retention_days: Option<u32>,
+    pub purge_removed_roots: Option<bool>,
+    pub archive_retention_days: Option<u32>,
+    pub archive_retention_mode: Option<String>,
+    pub installers_bucket_enabled: Option<bool>,
+    pub record_symlinks: Option<bool>,
+    pub scan_power_mode: Option<String>,
+    pub locale: Option<String>,
+    pub duplicate_policy: Option<String>,
+    pub duplicate_preferred_paths: Option<Vec<String>>,
+    pub max_full_hash_gb: Option<u32>,
+    pub only_clean_when_idle: Option<bool>,
+    pub idle_minutes: Option<u32>,
+    pub restore_grace_days: Option<u32>,
 }
 
 /// Parameters for querying bucketed candidates
@@ -216,6 +403,19 @@ pub struct GetCandidatesBucketedParams {
 
     /// Sorting criteria (e.g., "size_desc", "age_desc", "name_asc")
     pub sort: Option<String>,
+
+    /// Page size for each bucket's list; when set, every bucket is paged on
+    /// its own instead of slicing the combined list with `limit`/`offset`
+    #[serde(default)]
+    pub bucket_limit: Option<usize>,
+
+    /// Offsets into each bucket's list, keyed by bucket (missing means 0)
+    #[serde(default)]
+    pub bucket_offsets: Option<std::collections::HashMap<String, usize>>,
+
+    /// Token from a previous `paging.next_cursor`; takes the place of `offset`
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 // Error handling
@@ -248,6 +448,61 @@ fn command_error_to_string(err: CommandError) -> String {
     err.to_string()
 }
 
+fn ops_progress_emitter(app: &tauri::AppHandle) -> ProgressCallback {
+    let app = app.clone();
+    Box::new(move |payload: &OpsProgressPayload| {
+        let _ = app.emit(OPS_PROGRESS_EVENT, payload);
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn emit_ops_done(
+    app: &tauri::AppHandle,
+    operation: &str,
+    batch_id: &str,
+    files_processed: usize,
+    bytes: u64,
+    duration_ms: u64,
+    errors: &[String],
+    cancelled: bool,
+) {
+    let payload = OpsDonePayload {
+        batch_id: batch_id.to_string(),
+        operation: operation.to_string(),
+        files_processed,
+        bytes,
+        duration_ms,
+        errors: errors.to_vec(),
+        cancelled,
+    };
+    let _ = app.emit(OPS_DONE_EVENT, payload);
+    gauge::spawn_gauge_refresh(app);
+}
+
+/// Recomputes the gauge after a watched-root change. A failed recompute still
+/// produces a payload so the UI knows to refetch.
+fn gauge_invalidated_payload(
+    db: &Database,
+    reason: &str,
+    root_path: &str,
+    files_removed: usize,
+) -> GaugeInvalidatedPayload {
+    gauge::mark_gauge_dirty();
+    let state = match gauge::cached_gauge_state(db) {
+        Ok(state) => Some(state),
+        Err(err) => {
+            tracing::warn!("Failed to recompute gauge after {}: {}", reason, err);
+            None
+        }
+    };
+    GaugeInvalidatedPayload {
+        reason: reason.to_string(),
+        root_path: root_path.to_string(),
+        files_removed,
+        state,
+    }
+}
+
 fn map_io_error(action: &str, path: &Path, err: std::io::Error) -> CommandError {
     match err.kind() {
         ErrorKind::NotFound => CommandError::NotFound(format!("{}: {}", action, path.display())),
@@ -324,6 +579,8 @@ fn watched_root_to_folder(root: WatchedRoot) -> WatchedFolder {
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| root.path.clone()),
         is_accessible: Path::new(&root.path).exists(),
+        volume_kind: root.volume_kind,
+        is_offline: root.is_offline,
     }
 }
 
@@ -345,6 +602,11 @@ fn staged_payload(record: &StagedFileRecord, file: &File) -> StagedFile {
         batch_id: record.batch_id.clone(),
         note: record.note.clone(),
         cooloff_until: file.cooloff_until.map(|dt| dt.to_rfc3339()),
+        archive_path: None,
+        status_message: record
+            .status
+            .eq_ignore_ascii_case(ops::expiry::LOST_STATUS)
+            .then(|| Message::new("staged.lost")),
     }
 }
 
@@ -358,11 +620,14 @@ fn ensure_within_watched(path: &Path, roots: &[WatchedRoot]) -> Result<(), Comma
     }
 }
 
+/// Most files one command may act on at once.
+const MAX_FILES_PER_BATCH: usize = 1000;
+
 fn validate_file_ids(file_ids: &[i64]) -> Result<(), CommandError> {
     if file_ids.is_empty() {
         return Err(CommandError::Validation("No file IDs provided".to_string()));
     }
-    if file_ids.len() > 1000 {
+    if file_ids.len() > MAX_FILES_PER_BATCH {
         return Err(CommandError::Validation(
             "Too many files selected (max 1000)".to_string(),
         ));
@@ -373,6 +638,96 @@ fn validate_file_ids(file_ids: &[i64]) -> Result<(), CommandError> {
     Ok(())
 }
 
+/// Longest idempotency key accepted; a UUID takes 36 characters.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
+/// Keys become part of batch ids and archive folder names, so only ASCII
+/// letters, digits, `-` and `_` are allowed.
+fn validate_idempotency_key(key: Option<&str>) -> Result<(), CommandError> {
+    match key {
+        Some(key)
+            if key.is_empty()
+                || key.len() > MAX_IDEMPOTENCY_KEY_LEN
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            Err(CommandError::Validation(format!(
+                "idempotency_key must be 1-{MAX_IDEMPOTENCY_KEY_LEN} letters, digits, - or _"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Claims `file_ids` for the length of one batch. A conflicting request fails
+/// with `ERR_FILE_BUSY: {FileBusy json}` naming the files and the operation
+/// holding them.
+fn lock_files(kind: &str, file_ids: &[i64]) -> Result<ops::locks::OperationGuard, String> {
+    ops::locks::lock_files(kind, file_ids).map_err(|busy| {
+        format!(
+            "ERR_FILE_BUSY: {}",
+            serde_json::to_string(&busy).unwrap_or_default()
+        )
+    })
+}
+
+/// Runs `op` at most once per idempotency key. A retry of a finished call
+/// gets the stored outcome back; a failed call frees its key so it can be
+/// retried. Without a key `op` simply runs.
+fn run_idempotent<T, F>(
+    db: &Database,
+    key: Option<&str>,
+    command: &str,
+    batch_id: impl Fn(&T) -> Option<String>,
+    op: F,
+) -> Result<T, String>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+    F: FnOnce() -> Result<T, String>,
+{
+    let Some(key) = key else {
+        return op();
+    };
+    match db
+        .claim_idempotency_key(key, command)
+        .map_err(|e| format!("ERR_DATABASE: {e}"))?
+    {
+        IdempotencyClaim::Claimed => {}
+        IdempotencyClaim::Completed { command: used_by, .. }
+        | IdempotencyClaim::Pending { command: used_by }
+            if used_by != command =>
+        {
+            return Err(format!(
+                "ERR_VALIDATION: idempotency key was already used for {used_by}"
+            ));
+        }
+        IdempotencyClaim::Pending { .. } => {
+            return Err(format!(
+                "ERR_CONFLICT: a {command} call with this idempotency key is still running"
+            ));
+        }
+        IdempotencyClaim::Completed { outcome, .. } => {
+            tracing::info!(command, key, "replaying idempotent outcome");
+            return serde_json::from_str(&outcome)
+                .map_err(|e| format!("ERR_INTERNAL: stored outcome: {e}"));
+        }
+    }
+
+    let result = op();
+    let stored = match result.as_ref().map(|outcome| (outcome, serde_json::to_string(outcome))) {
+        Ok((outcome, Ok(raw))) => {
+            db.complete_idempotency_key(key, batch_id(outcome).as_deref(), &raw)
+        }
+        // Nothing replayable to store; let the retry run again.
+        Ok((_, Err(_))) | Err(_) => db.release_idempotency_key(key),
+    };
+    if let Err(e) = stored {
+        tracing::warn!(command, key, "failed to record idempotency key: {e}");
+    }
+    result
+}
+
 fn validate_path(path: &str) -> Result<PathBuf, CommandError> {
     let path_buf = PathBuf::from(path);
     if path_buf
@@ -434,6 +789,32 @@ fn validate_scan_path(path: &str) -> Result<PathBuf, CommandError> {
     Ok(path_buf)
 }
 
+/// Checks the roots handed to `scan_roots` and returns them sanitized, with
+/// duplicates dropped.
+fn validate_scan_roots(roots: &[String]) -> Result<Vec<String>, String> {
+    if roots.is_empty() {
+        return Err("ERR_VALIDATION: No scan roots provided".to_string());
+    }
+
+    if roots.len() > 10 {
+        return Err("ERR_VALIDATION: Too many scan roots (max 10)".to_string());
+    }
+
+    let mut unique = HashSet::new();
+    let mut sanitized_roots = Vec::new();
+    for root in roots {
+        validate_scan_path(root).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+        if !Path::new(root).is_dir() {
+            return Err(format!("ERR_VALIDATION: Path is not a directory: {}", root));
+        }
+        let clean = sanitize_string(root);
+        if unique.insert(clean.clone()) {
+            sanitized_roots.push(clean);
+        }
+    }
+    Ok(sanitized_roots)
+}
+
 fn open_path_with_system(path: &Path, reveal: bool) -> Result<(), CommandError> {
     #[cfg(target_os = "windows")]
     {
@@ -550,6 +931,38 @@ fn open_path_with_system(path: &Path, reveal: bool) -> Result<(), CommandError>
     ))
 }
 
+/// Opens `path` in its default application, unlike `open_path_with_system`
+/// which only ever shows it in the file manager.
+fn launch_default_app(path: &Path) -> Result<(), CommandError> {
+    use std::process::Command;
+
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| CommandError::Validation("Path contains invalid UTF-8".to_string()))?;
+
+    #[cfg(target_os = "windows")]
+    let (program, status) = (
+        "Explorer",
+        Command::new("explorer").arg(path_str.replace('/', "\\")).status(),
+    );
+    #[cfg(target_os = "macos")]
+    let (program, status) = ("open", Command::new("open").arg(path_str).status());
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let (program, status) = ("xdg-open", Command::new("xdg-open").arg(path_str).status());
+
+    let status = status
+        .map_err(|e| CommandError::FileSystem(format!("Failed to launch {}: {}", program, e)))?;
+
+    // Explorer exits with 1 even when the file opened.
+    if !(status.success() || (cfg!(windows) && status.code() == Some(1))) {
+        return Err(CommandError::FileSystem(format!(
+            "{} returned an error",
+            program
+        )));
+    }
+    Ok(())
+}
+
 fn canonicalize_or_clone(path: &Path) -> PathBuf {
     match path.canonicalize() {
         Ok(canonical) => canonical,
@@ -633,26 +1046,83 @@ pub async fn add_folder(path: String, app: tauri::AppHandle, db: State<'_, DbPoo
 
     let db_clone = db.inner().clone();
     let path_for_db = normalized_path.clone();
-    let root = tokio::task::spawn_blocking(move || {
+    let (root, merged, invalidated, watcher_enabled) = tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
+        // A folder inside a watched root is already indexed; a folder around
+        // watched roots absorbs them so nothing is scanned or counted twice.
+        let (ancestors, nested) = db_instance
+            .overlapping_watched_roots(&path_for_db)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if let Some(parent) = ancestors.first() {
+            return Err(format!(
+                "ERR_VALIDATION: Folder is already watched as part of {}",
+                parent
+            ));
+        }
+        for child in &nested {
+            db_instance
+                .delete_watched_root(child)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        }
+        let is_new = db_instance
+            .get_root_settings(&path_for_db)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .is_none();
         let id = db_instance
             .upsert_watched_root(&path_for_db)
             .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let volume_kind = scanner::volume::detect_volume_kind(Path::new(&path_for_db));
         db_instance
+            .set_root_volume_kind(&path_for_db, volume_kind)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        // Hashing whole files over the network or USB is slow and live
+        // watching is unreliable there, so new roots on them start light.
+        if is_new && !volume_kind.is_local() {
+            if let Some(settings) = db_instance
+                .get_root_settings(&path_for_db)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            {
+                db_instance
+                    .update_root_settings(&RootSettings {
+                        watcher_enabled: false,
+                        partial_hash_only: true,
+                        ..settings
+                    })
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            }
+        }
+        let watcher_enabled = db_instance
+            .get_root_settings(&path_for_db)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .is_some_and(|settings| settings.watcher_enabled);
+        let root = db_instance
             .get_watched_root_by_id(id)
             .map_err(|e| format!("ERR_DATABASE: {}", e))?
-            .ok_or_else(|| "ERR_DATABASE: Watched folder not found after insert".to_string())
+            .ok_or_else(|| "ERR_DATABASE: Watched folder not found after insert".to_string())?;
+        let invalidated = gauge_invalidated_payload(&db_instance, "root_added", &path_for_db, 0);
+        Ok::<_, String>((root, nested, invalidated, watcher_enabled))
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
+    for child in &merged {
+        if let Err(err) = unregister_root(child) {
+            tracing::warn!("Failed to unregister watcher for {}: {}", child, err);
+        }
+    }
     let folder = watched_root_to_folder(root);
-    if let Err(err) = register_root(folder.path.as_str()) {
-        eprintln!("Failed to register watcher for {}: {}", folder.path, err);
+    if watcher_enabled {
+        if let Err(err) = register_root(folder.path.as_str()) {
+            tracing::warn!("Failed to register watcher for {}: {}", folder.path, err);
+        }
     }
     // Notify UI roots changed
     let _ = app.emit("roots://changed", serde_json::json!({ "count": 1 }));
+    if let Some(state) = &invalidated.state {
+        let _ = app.emit(GAUGE_UPDATED_EVENT, state);
+    }
+    let _ = app.emit(GAUGE_INVALIDATED_EVENT, invalidated);
     Ok(folder)
 }
 
@@ -691,1337 +1161,5521 @@ pub async fn list_folders(db: State<'_, DbPool>) -> Result<Vec<WatchedFolder>, S
     Ok(folders.into_iter().map(watched_root_to_folder).collect())
 }
 
-#[tauri::command]
-pub async fn remove_folder(id: i64, app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
-    if id <= 0 {
-        return Err("ERR_VALIDATION: Invalid folder id".to_string());
+/// A standard folder offered during onboarding.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RootSuggestion {
+    /// "downloads", "desktop", "screenshots" or "documents".
+    pub kind: String,
+    pub path: String,
+    /// Bytes seen by a shallow walk; a lower bound when `truncated`.
+    pub estimated_bytes: u64,
+    pub file_count: u64,
+    pub truncated: bool,
+    pub already_watched: bool,
+}
+
+const SUGGEST_WALK_DEPTH: usize = 3;
+const SUGGEST_WALK_LIMIT: u64 = 10_000;
+
+/// Sums file sizes a few levels deep, stopping after `SUGGEST_WALK_LIMIT`
+/// entries so onboarding never waits on a huge Documents folder.
+fn shallow_size(root: &Path) -> (u64, u64, bool) {
+    let mut bytes = 0;
+    let mut files = 0;
+    let mut seen = 0;
+    for entry in WalkDir::new(root)
+        .max_depth(SUGGEST_WALK_DEPTH)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        seen += 1;
+        if seen > SUGGEST_WALK_LIMIT {
+            return (bytes, files, true);
+        }
+        if entry.file_type().is_file() {
+            files += 1;
+            bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
     }
+    (bytes, files, false)
+}
+
+fn default_root_candidates() -> Vec<(&'static str, PathBuf)> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = dirs::download_dir() {
+        candidates.push(("downloads", dir));
+    }
+    if let Some(dir) = dirs::desktop_dir() {
+        candidates.push(("desktop", dir));
+    }
+    // macOS saves screenshots to the Desktop by default; Windows and most
+    // Linux desktops use Pictures/Screenshots.
+    let screenshots = [
+        dirs::picture_dir().map(|dir| dir.join("Screenshots")),
+        dirs::desktop_dir().map(|dir| dir.join("Screenshots")),
+    ];
+    if let Some(dir) = screenshots.into_iter().flatten().find(|dir| dir.is_dir()) {
+        candidates.push(("screenshots", dir));
+    }
+    if let Some(dir) = dirs::document_dir() {
+        candidates.push(("documents", dir));
+    }
+    candidates
+}
 
+/// Existing Downloads/Desktop/Screenshots/Documents folders, folders not yet
+/// watched first and then largest first.
+#[tauri::command]
+pub async fn suggest_default_roots(db: State<'_, DbPool>) -> Result<Vec<RootSuggestion>, String> {
     let db_clone = db.inner().clone();
-    let removed_path = tokio::task::spawn_blocking(move || -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let root = db_instance
-            .get_watched_root_by_id(id)
-            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
-        match root {
-            Some(r) => {
-                let path = r.path.clone();
-                db_instance
-                    .delete_watched_root(&r.path)
-                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
-                Ok(path)
+        let watched: Vec<PathBuf> = db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .into_iter()
+            .map(|path| canonicalize_or_clone(Path::new(&path)))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+        for (kind, dir) in default_root_candidates() {
+            if !dir.is_dir() || is_system_root(&dir) {
+                continue;
+            }
+            let canonical = canonicalize_or_clone(&dir);
+            if !seen.insert(canonical.clone()) {
+                continue;
             }
-            None => Err("ERR_NOT_FOUND: Watched folder not found".to_string()),
+            let (estimated_bytes, file_count, truncated) = shallow_size(&dir);
+            suggestions.push(RootSuggestion {
+                kind: kind.to_string(),
+                path: dir.to_string_lossy().to_string(),
+                estimated_bytes,
+                file_count,
+                truncated,
+                already_watched: watched.iter().any(|root| canonical.starts_with(root)),
+            });
         }
+
+        suggestions.sort_by(|a, b| {
+            a.already_watched
+                .cmp(&b.already_watched)
+                .then_with(|| b.estimated_bytes.cmp(&a.estimated_bytes))
+        });
+        Ok(suggestions)
     })
     .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn remove_folder(id: i64, app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid folder id".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    let (removed_path, invalidated) = tokio::task::spawn_blocking(
+        move || -> Result<(String, GaugeInvalidatedPayload), String> {
+            let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+            let db_instance = Database::new(conn);
+            let purge = db_instance
+                .get_preference(maintenance::PURGE_REMOVED_ROOTS_PREF)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false);
+            // Files are matched by root id, so rows under other watched roots
+            // are never touched.
+            let removed = db_instance
+                .remove_watched_root(id, purge)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            match removed {
+                Some((path, files_removed)) => {
+                    let invalidated = gauge_invalidated_payload(
+                        &db_instance,
+                        "root_removed",
+                        &path,
+                        files_removed,
+                    );
+                    Ok((path, invalidated))
+                }
+                None => Err("ERR_NOT_FOUND: Watched folder not found".to_string()),
+            }
+        },
+    )
+    .await
     .map_err(|e| format!("join error: {e}"))??;
 
     if let Err(err) = unregister_root(removed_path.as_str()) {
-        eprintln!("Failed to unregister watcher for {}: {}", removed_path, err);
+        tracing::warn!("Failed to unregister watcher for {}: {}", removed_path, err);
     }
 
     // Notify UI roots changed
     let _ = app.emit("roots://changed", serde_json::json!({ "count": 1 }));
+    if let Some(state) = &invalidated.state {
+        let _ = app.emit(GAUGE_UPDATED_EVENT, state);
+    }
+    let _ = app.emit(GAUGE_INVALIDATED_EVENT, invalidated);
     Ok(())
 }
 
-#[tauri::command]
-pub fn get_platform_info() -> PlatformInfo {
-    #[cfg(target_os = "windows")]
-    {
-        return PlatformInfo {
-            os: "windows".to_string(),
-            open_label: "Open in File Explorer".to_string(),
-        };
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        return PlatformInfo {
-            os: "macos".to_string(),
-            open_label: "Open in Finder".to_string(),
-        };
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        return PlatformInfo {
-            os: "linux".to_string(),
-            open_label: "Open in File Manager".to_string(),
-        };
-    }
-
-    #[allow(unreachable_code)]
-    PlatformInfo {
-        os: std::env::consts::OS.to_string(),
-        open_label: "Open in File Manager".to_string(),
+/// Path of the watched root `id`.
+fn watched_root_path(db: &Database, id: i64) -> Result<String, String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid folder id".to_string());
     }
+    db.get_watched_root_by_id(id)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .map(|root| root.path)
+        .ok_or_else(|| "ERR_NOT_FOUND: Watched folder not found".to_string())
 }
 
 #[tauri::command]
-pub async fn list_dir(
-    root_path: String,
-    db: State<'_, DbPool>,
-) -> Result<Vec<DirectoryEntry>, String> {
-    if root_path.trim().is_empty() {
-        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
-    }
-
-    let normalized =
-        normalize_directory_path(Path::new(&root_path)).map_err(command_error_to_string)?;
-    let path_for_listing = normalized.clone();
-
+pub async fn get_root_settings(id: i64, db: State<'_, DbPool>) -> Result<RootSettings, String> {
     let db_clone = db.inner().clone();
-    let watched_roots = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
+        let path = watched_root_path(&db_instance, id)?;
         db_instance
-            .list_watched_roots()
-            .map_err(|e| format!("ERR_DATABASE: {}", e))
+            .get_root_settings(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_NOT_FOUND: Watched folder not found".to_string())
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    ensure_within_watched(&normalized, &watched_roots).map_err(command_error_to_string)?;
+/// Sets how often root `id` is rescanned (`None` for on demand only), whether
+/// its watcher runs, whether scans of it use background I/O priority and
+/// whether its files are only partially hashed.
+#[tauri::command]
+pub async fn update_root_settings(
+    id: i64,
+    scan_interval_hours: Option<u32>,
+    watcher_enabled: bool,
+    low_priority_io: bool,
+    partial_hash_only: bool,
+    db: State<'_, DbPool>,
+) -> Result<RootSettings, String> {
+    if let Some(hours) = scan_interval_hours {
+        if !(MIN_SCAN_INTERVAL_HOURS..=MAX_SCAN_INTERVAL_HOURS).contains(&hours) {
+            return Err(format!(
+                "ERR_VALIDATION: scan_interval_hours must be between {} and {}",
+                MIN_SCAN_INTERVAL_HOURS, MAX_SCAN_INTERVAL_HOURS
+            ));
+        }
+    }
 
-    let entries = tokio::task::spawn_blocking(move || {
-        list_directory_entries(&path_for_listing).map_err(command_error_to_string)
+    let db_clone = db.inner().clone();
+    let settings = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let path = watched_root_path(&db_instance, id)?;
+        let current = db_instance
+            .get_root_settings(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_NOT_FOUND: Watched folder not found".to_string())?;
+        let settings = RootSettings {
+            scan_interval_hours,
+            watcher_enabled,
+            low_priority_io,
+            partial_hash_only,
+            ..current
+        };
+        db_instance
+            .update_root_settings(&settings)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        Ok::<_, String>(settings)
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    Ok(entries)
+    let watcher_result = if settings.watcher_enabled {
+        register_root(&settings.path)
+    } else {
+        unregister_root(&settings.path)
+    };
+    if let Err(err) = watcher_result {
+        tracing::warn!("Failed to update watcher for {}: {}", settings.path, err);
+    }
+    Ok(settings)
 }
 
 #[tauri::command]
-pub async fn open_in_system(
-    path: String,
-    reveal: Option<bool>,
+pub async fn add_exclusion(
+    pattern: String,
+    root_path: Option<String>,
+    app: tauri::AppHandle,
     db: State<'_, DbPool>,
-) -> Result<(), String> {
-    if path.trim().is_empty() {
-        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+) -> Result<Exclusion, String> {
+    let pattern = sanitize_string(pattern.trim());
+    if pattern.is_empty() {
+        return Err("ERR_VALIDATION: Pattern cannot be empty".to_string());
     }
+    compile_exclusion(&pattern).map_err(|e| format!("ERR_VALIDATION: Invalid pattern: {e}"))?;
 
-    let db_clone = db.inner().clone();
-    tokio::task::spawn_blocking(move || -> Result<(), String> {
-        let normalized =
-            normalize_existing_path(Path::new(&path)).map_err(command_error_to_string)?;
-        let metadata = fs::metadata(&normalized)
-            .map_err(|err| map_io_error("access path", &normalized, err))
-            .map_err(command_error_to_string)?;
-        let check_path = if metadata.is_dir() {
-            normalized.clone()
-        } else {
-            normalized
-                .parent()
-                .map(|p| p.to_path_buf())
-                .unwrap_or_else(|| normalized.clone())
-        };
-        let is_file = metadata.is_file();
+    let root = match root_path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(raw) => {
+            let validated = validate_path(raw).map_err(command_error_to_string)?;
+            let normalized = normalize_directory_path(&validated).map_err(command_error_to_string)?;
+            Some(normalized.to_string_lossy().to_string())
+        }
+        None => None,
+    };
 
+    let db_clone = db.inner().clone();
+    let (exclusion, pruned) = tokio::task::spawn_blocking(move || -> Result<(Exclusion, usize), String> {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let roots = db_instance
-            .list_watched_roots()
-            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
 
-        ensure_within_watched(&check_path, &roots).map_err(command_error_to_string)?;
+        if let Some(root) = root.as_deref() {
+            let roots = db_instance
+                .list_watched_roots()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            ensure_within_watched(Path::new(root), &roots).map_err(command_error_to_string)?;
+        }
 
-        let reveal_flag = reveal.unwrap_or(is_file);
-        open_path_with_system(&normalized, reveal_flag).map_err(command_error_to_string)
+        let id = db_instance
+            .insert_exclusion(root.as_deref(), &pattern)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let exclusion = db_instance
+            .get_exclusion_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_DATABASE: Exclusion not found after insert".to_string())?;
+
+        // Drop already-indexed files that the new rule hides so candidates and
+        // the gauge stop counting them straight away.
+        let mut walker = FileWalker::new();
+        walker.set_exclusions(std::slice::from_ref(&exclusion));
+        let excluded: Vec<i64> = db_instance
+            .list_active_paths(exclusion.root_path.as_deref())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .into_iter()
+            .filter(|(_, path)| {
+                let path = Path::new(path);
+                walker.is_excluded(path) || path.ancestors().skip(1).any(|dir| walker.is_excluded(dir))
+            })
+            .map(|(id, _)| id)
+            .collect();
+        db_instance
+            .mark_files_missing(&excluded)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+
+        Ok((exclusion, excluded.len()))
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    Ok(())
+    let _ = app.emit(
+        "exclusions://changed",
+        serde_json::json!({ "added": exclusion.id, "pruned": pruned }),
+    );
+    Ok(exclusion)
 }
 
 #[tauri::command]
-pub async fn start_scan(
-    paths: Option<Vec<String>>,
-    app: tauri::AppHandle,
-    db: State<'_, DbPool>,
-) -> Result<(), String> {
-    let provided = paths.unwrap_or_default();
+pub async fn list_exclusions(db: State<'_, DbPool>) -> Result<Vec<Exclusion>, String> {
     let db_clone = db.inner().clone();
-    let roots = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        if provided.is_empty() {
-            db_instance
+        db_instance
+            .list_exclusions()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn remove_exclusion(id: i64, app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid exclusion id".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    let rescan_roots = tokio::task::spawn_blocking(move || -> Result<Vec<String>, String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let exclusion = db_instance
+            .get_exclusion_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_NOT_FOUND: Exclusion not found".to_string())?;
+        db_instance
+            .delete_exclusion(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+
+        match exclusion.root_path {
+            Some(root) if Path::new(&root).is_dir() => Ok(vec![root]),
+            Some(_) => Ok(Vec::new()),
+            None => db_instance
                 .list_watched_paths()
-                .map_err(|e| format!("ERR_DATABASE: {}", e))
-        } else {
-            Ok(provided)
+                .map_err(|e| format!("ERR_DATABASE: {}", e)),
         }
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    if roots.is_empty() {
-        return Err("ERR_VALIDATION: No scan roots configured".to_string());
-    }
+    let _ = app.emit("exclusions://changed", serde_json::json!({ "removed": id }));
 
-    let mut unique = HashSet::new();
-    let mut sanitized = Vec::new();
-    for root in roots {
-        validate_scan_path(&root).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-        if !Path::new(&root).is_dir() {
-            return Err(format!("ERR_VALIDATION: Path is not a directory: {}", root));
-        }
-        let clean = sanitize_string(&root);
-        if unique.insert(clean.clone()) {
-            sanitized.push(clean);
-        }
+    // Previously hidden files only come back through a fresh walk.
+    if !rescan_roots.is_empty() {
+        scanner::start_scan(app, db.inner().clone(), rescan_roots)
+            .map_err(|e| format!("ERR_SCAN: {e}"))?;
     }
+    Ok(())
+}
 
-    scanner::start_scan(app, db.inner().clone(), sanitized)
-        .map_err(|e| format!("ERR_SCAN: {e}"))?;
+fn load_protected_paths(db: &Database) -> Result<Vec<String>, String> {
+    db.list_protected_paths()
+        .map(|paths| paths.into_iter().map(|p| p.path).collect())
+        .map_err(|e| format!("ERR_DATABASE: {}", e))
+}
+
+/// Hard stop for archive and delete: protected files and system locations
+/// are never touched.
+fn ensure_not_protected(path: &str, protected: &[String]) -> Result<(), String> {
+    if is_protected(Path::new(path), protected) {
+        return Err(format!("ERR_VALIDATION: {} is protected", path));
+    }
+    if is_system_path(Path::new(path)) {
+        return Err(format!("ERR_SYSTEM_LOCATION: {} is a system or app file", path));
+    }
     Ok(())
 }
 
+/// Paths of the files archive and delete will act on, refusing ids that are
+/// unknown, outside the allowed locations or protected.
+fn resolve_file_paths(db: &Database, file_ids: &[i64]) -> Result<Vec<String>, String> {
+    let protected = load_protected_paths(db)?;
+    let mut file_paths = Vec::new();
+    for file_id in file_ids {
+        match db.get_file_by_id(*file_id) {
+            Ok(Some(file)) => {
+                validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+                ensure_not_protected(&file.path, &protected)?;
+                file_paths.push(file.path);
+            }
+            Ok(None) => {
+                return Err(format!("ERR_NOT_FOUND: File with ID {} not found", file_id));
+            }
+            Err(e) => {
+                return Err(format!("ERR_DATABASE: {}", e));
+            }
+        }
+    }
+    Ok(file_paths)
+}
+
 #[tauri::command]
-pub async fn rescan_all(app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
+pub async fn add_protected_path(
+    path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<ProtectedPath, String> {
+    let validated = validate_path(path.trim()).map_err(command_error_to_string)?;
+    let normalized = normalize_existing_path(&validated).map_err(command_error_to_string)?;
+    if is_system_root(&normalized) {
+        return Err("ERR_VALIDATION: Protecting the system root is not supported".to_string());
+    }
+    let normalized_path = normalized.to_string_lossy().to_string();
+
     let db_clone = db.inner().clone();
-    let roots = tokio::task::spawn_blocking(move || {
+    let protected = tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
+        let id = db_instance
+            .insert_protected_path(&normalized_path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
         db_instance
-            .list_watched_paths()
-            .map_err(|e| format!("ERR_DATABASE: {}", e))
+            .get_protected_path_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_DATABASE: Protected path not found after insert".to_string())
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    if roots.is_empty() {
-        return Err("ERR_VALIDATION: No scan roots configured".to_string());
-    }
+    gauge::mark_gauge_dirty();
+    let _ = app.emit("protected://changed", serde_json::json!({ "added": protected.id }));
+    Ok(protected)
+}
 
-    scanner::start_scan(app, db.inner().clone(), roots).map_err(|e| format!("ERR_SCAN: {e}"))?;
-    Ok(())
+#[tauri::command]
+pub async fn list_protected_paths(db: State<'_, DbPool>) -> Result<Vec<ProtectedPath>, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .list_protected_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn rescan_folder(
-    path: String,
+pub async fn remove_protected_path(
+    id: i64,
     app: tauri::AppHandle,
     db: State<'_, DbPool>,
 ) -> Result<(), String> {
-    if path.trim().is_empty() {
-        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid protected path id".to_string());
     }
 
-    let normalized = normalize_directory_path(Path::new(&path)).map_err(command_error_to_string)?;
-    let root = normalized.to_string_lossy().to_string();
-
-    // Ensure it's one of the watched roots
     let db_clone = db.inner().clone();
-    let watched = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        db_instance
-            .list_watched_paths()
-            .map_err(|e| format!("ERR_DATABASE: {}", e))
+        let removed = db_instance
+            .delete_protected_path(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if removed == 0 {
+            return Err("ERR_NOT_FOUND: Protected path not found".to_string());
+        }
+        Ok(())
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    if !watched
-        .iter()
-        .any(|p| canonicalize_or_clone(Path::new(p)) == canonicalize_or_clone(Path::new(&root)))
-    {
-        return Err("ERR_PERMISSION: Path is not a watched root".to_string());
-    }
-
-    scanner::start_scan(app, db.inner().clone(), vec![root])
-        .map_err(|e| format!("ERR_SCAN: {e}"))?;
+    gauge::mark_gauge_dirty();
+    let _ = app.emit("protected://changed", serde_json::json!({ "removed": id }));
     Ok(())
 }
-#[tauri::command]
-pub fn scan_status() -> Result<scanner::ScanStatusPayload, String> {
-    Ok(scanner::current_status())
+
+/// Checks that `root_path` lies inside a watched root and returns it trimmed.
+fn ensure_within_watched_root(db: &Database, root_path: &str) -> Result<String, String> {
+    let trimmed = root_path.trim();
+    let root = match trimmed.trim_end_matches(['/', '\\']) {
+        "" => trimmed.to_string(),
+        stripped => stripped.to_string(),
+    };
+    if root.is_empty() {
+        return Err("ERR_VALIDATION: root_path is required".to_string());
+    }
+    let watched = db
+        .list_watched_paths()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    if !watched.iter().any(|w| Path::new(&root).starts_with(w)) {
+        return Err(format!("ERR_VALIDATION: {} is not within a watched root", root));
+    }
+    Ok(root)
 }
 
 #[tauri::command]
-pub async fn get_candidates(
-    max_total: usize,
+pub async fn get_folder_sizes(
+    root_path: String,
+    max_depth: Option<usize>,
+    top_n: Option<usize>,
     db: State<'_, DbPool>,
-) -> Result<Vec<Candidate>, String> {
-    daily_candidates(max_total, db).await
-}
-
-fn normalize_bucket_key(reason: &str) -> String {
-    let lower = reason.to_lowercase();
-    match lower.as_str() {
-        "screenshots" => "screenshot".to_string(),
-        "big downloads" => "big_download".to_string(),
-        "old desktop" => "old_desktop".to_string(),
-        "executable" | "executables" => "executable".to_string(),
-        "duplicates" => "duplicate".to_string(),
-        other => other.replace(' ', "_"),
+) -> Result<FolderSize, String> {
+    let max_depth = max_depth.unwrap_or(3).min(10);
+    let top_n = top_n.unwrap_or(10).min(100);
+    if top_n == 0 {
+        return Err("ERR_VALIDATION: top_n must be > 0".to_string());
     }
-}
-
-pub(crate) fn filter_candidates_by_root_path(
-    candidates: &mut Vec<Candidate>,
-    root_path: &str,
-    errors: &mut Vec<String>,
-) {
-    let root_path_buf = PathBuf::from(root_path);
-    let root_path = if let Ok(canonical) = root_path_buf.canonicalize() {
-        canonical
-    } else {
-        errors.push(format!(
-            "Warning: Could not canonicalize path: {}",
-            root_path_buf.display()
-        ));
-        root_path_buf
-    };
-
-    let root_path_str = root_path.to_string_lossy().to_string();
 
-    candidates.retain(|candidate| {
-        let candidate_path = Path::new(&candidate.path);
-        if let Ok(canon_candidate) = candidate_path.canonicalize() {
-            let canon_str = canon_candidate.to_string_lossy().to_string();
-            canon_str.starts_with(&root_path_str)
-        } else {
-            candidate.path.starts_with(&root_path_str)
-        }
-    });
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let root = ensure_within_watched_root(&db_instance, &root_path)?;
+        let totals = db_instance
+            .folder_size_totals(&root)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        Ok(reports::build_folder_tree(
+            Path::new(&root),
+            &totals,
+            max_depth,
+            top_n,
+        ))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn get_candidates_bucketed(
-    params: Option<GetCandidatesBucketedParams>,
+pub async fn get_mime_breakdown(
+    root_path: String,
     db: State<'_, DbPool>,
-) -> Result<CandidatesResponse, String> {
-    let params = params.unwrap_or(GetCandidatesBucketedParams {
-        root_path: None,
-        buckets: None,
-        limit: Some(100),
-        offset: Some(0),
-        sort: Some("size_desc".to_string()),
-        min_confidence: None,
-        max_results_per_bucket: None,
-        include_archived: None,
-        include_deleted: None,
-    });
-
-    let limit = params.limit.unwrap_or(100).min(1000);
-    let offset = params.offset.unwrap_or(0);
-    if limit == 0 {
-        return Err("ERR_VALIDATION: limit must be > 0".to_string());
-    }
+) -> Result<Vec<MimeFamilyTotals>, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let root = ensure_within_watched_root(&db_instance, &root_path)?;
+        db_instance
+            .mime_family_totals(&root)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
+/// How stale a folder is: file counts and bytes aged 0-30 days, 30-90 days,
+/// 90-365 days and a year or more, by modified (default) or accessed time.
+#[tauri::command]
+pub async fn get_age_histogram(
+    root_path: String,
+    metric: Option<AgeMetric>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<AgeHistogramBin>, String> {
     let db_clone = db.inner().clone();
-    // If root_path is provided, pull a larger pool to avoid filtering away all results
-    let fetch_size = if params.root_path.is_some() {
-        (limit + offset).saturating_mul(50).min(10_000)
-    } else {
-        limit + offset
-    };
-    let (mut candidates, mut errors) = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let selector = FileSelector::new();
         let db_instance = Database::new(conn);
-        let mut items = selector
-            .daily_candidates(Some(fetch_size), &db_instance)
-            .map_err(|e| format!("ERR_SELECTOR: {}", e))?;
-        Ok::<(Vec<Candidate>, Vec<String>), String>((items.drain(..).collect(), Vec::new()))
+        let root = ensure_within_watched_root(&db_instance, &root_path)?;
+        db_instance
+            .age_histogram(&root, metric.unwrap_or_default())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    // Filter by root path if provided
-    if let Some(root_path) = params.root_path.as_deref() {
-        filter_candidates_by_root_path(&mut candidates, root_path, &mut errors);
+/// Groups near-identical images (screenshots, burst photos) by the Hamming
+/// distance between their perceptual hashes.
+#[tauri::command]
+pub async fn get_similar_images(
+    max_distance: Option<u32>,
+    root_path: Option<String>,
+    limit: Option<usize>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<SimilarImageGroup>, String> {
+    let max_distance = max_distance.unwrap_or(6);
+    if max_distance > 16 {
+        return Err("ERR_VALIDATION: max_distance must be <= 16".to_string());
     }
+    let limit = limit.unwrap_or(50).min(500);
 
-    // Filter by requested buckets if provided
-    let requested_buckets: std::collections::HashSet<String> = params
-        .buckets
-        .as_ref()
-        .map(|buckets| buckets.iter().map(|s| normalize_bucket_key(s)).collect())
-        .unwrap_or_default();
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let roots = match root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(root) => vec![ensure_within_watched_root(&db_instance, root)?],
+            None => db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?,
+        };
+        let protected = load_protected_paths(&db_instance)?;
+        let mut images = db_instance
+            .images_with_phash_under_roots(&roots)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        images.retain(|(file, _)| !is_protected(Path::new(&file.path), &protected));
 
-    if !requested_buckets.is_empty() {
-        candidates.retain(|c| requested_buckets.contains(&normalize_bucket_key(&c.reason)));
-    }
+        let mut groups = reports::group_similar_images(images, max_distance);
+        groups.truncate(limit);
+        Ok(groups)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    // Sort
-    match params.sort.as_deref() {
-        Some("size_desc") => candidates.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes)),
-        Some("age_desc") => candidates.sort_by(|a, b| {
-            b.age_days
-                .partial_cmp(&a.age_days)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        }),
-        Some("name_asc") => {
-            candidates.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()))
-        }
-        _ => {}
+#[tauri::command]
+pub async fn top_largest_files(
+    limit: Option<usize>,
+    root_path: Option<String>,
+    min_size_bytes: Option<i64>,
+    include_staged: Option<bool>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<File>, String> {
+    let limit = limit.unwrap_or(50).min(1000);
+    if limit == 0 {
+        return Err("ERR_VALIDATION: limit must be > 0".to_string());
+    }
+    let min_size_bytes = min_size_bytes.unwrap_or(0);
+    if min_size_bytes < 0 {
+        return Err("ERR_VALIDATION: min_size_bytes must be >= 0".to_string());
     }
 
-    // Recompute total_count AFTER filtering and sorting
-    let mut total_count = candidates.len();
-    let slice_end = (offset + limit).min(total_count);
-    let paged = if offset < total_count {
-        candidates[offset..slice_end].to_vec()
-    } else {
-        Vec::new()
-    };
-    let has_more = slice_end < total_count;
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let roots = match root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(root) => vec![ensure_within_watched_root(&db_instance, root)?],
+            None => db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?,
+        };
+        db_instance
+            .largest_files_under_roots(
+                &roots,
+                min_size_bytes,
+                include_staged.unwrap_or(false),
+                limit,
+            )
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    let mut by_bucket: std::collections::HashMap<String, Vec<UiCandidate>> =
-        std::collections::HashMap::new();
-    let mut summaries_acc: std::collections::HashMap<String, (usize, u64)> =
-        std::collections::HashMap::new();
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FreedSpaceTimeline {
+    pub window_days: i64,
+    pub bucket: String,
+    pub since: DateTime<Utc>,
+    pub total_bytes: u64,
+    pub total_files: usize,
+    pub points: Vec<FreedSpacePoint>,
+}
 
-    for c in paged {
-        let key = normalize_bucket_key(&c.reason);
-        let entry = UiCandidate {
-            id: c.file_id,
-            path: c.path.clone(),
-            parent: c.parent_dir.clone(),
-            size: c.size_bytes,
-            mime: None,
-            created_at: None,
-            modified_at: None,
-            accessed_at: None,
-            partial_sha1: None,
-            sha1: None,
-            reason: key.clone(),
-            group_key: None,
-        };
-        by_bucket.entry(key.clone()).or_default().push(entry);
-        let e = summaries_acc.entry(key).or_insert((0, 0));
-        e.0 += 1;
-        e.1 += c.size_bytes;
+/// Bytes freed by deletes over the last `window_days` (default 90), per
+/// local `day` or `week` (default), with a running total.
+#[tauri::command]
+pub async fn freed_space_timeline(
+    window_days: Option<i64>,
+    bucket: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<FreedSpaceTimeline, String> {
+    let window_days = window_days.unwrap_or(90);
+    if window_days <= 0 || window_days > 3650 {
+        return Err("ERR_VALIDATION: window_days must be 1-3650".to_string());
     }
+    let bucket = bucket
+        .map(|b| b.trim().to_ascii_lowercase())
+        .unwrap_or_else(|| "week".to_string());
+    let window = match bucket.as_str() {
+        "day" => MetricWindow::Day,
+        "week" => MetricWindow::Week,
+        _ => return Err("ERR_VALIDATION: bucket must be day or week".to_string()),
+    };
+    let since = Utc::now() - Duration::days(window_days);
 
-    // Fallback: if we have no candidates yet (e.g., first run, scan not completed),
-    // surface a shallow pass of obvious executables and old desktop/download items
-    // Skip fallback if we have a specific root_path and buckets filter
-    if total_count == 0 && params.root_path.is_none() {
-        // Use watched roots for fallback
-        let db_clone = db.inner().clone();
-        let roots = {
-            tokio::task::spawn_blocking(move || {
-                let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-                let db_instance = Database::new(conn);
-                db_instance
-                    .list_watched_paths()
-                    .map_err(|e| format!("ERR_DATABASE: {}", e))
-            })
-            .await
-            .map_err(|e| format!("join error: {e}"))??
-        };
-
-        let now = std::time::SystemTime::now();
-        let thirty_days = std::time::Duration::from_secs(30 * 24 * 3600);
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        // Hard links and cloud placeholders free nothing, as in the gauge.
+        let unreclaimable = db_instance
+            .unreclaimable_file_ids()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let deletes: Vec<(DateTime<Utc>, u64)> = db_instance
+            .deletes_with_sizes_since(&since)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .into_iter()
+            .filter(|(_, file_id, _)| !unreclaimable.contains(file_id))
+            .map(|(at, _, bytes)| (at, bytes))
+            .collect();
+        let points = reports::freed_space_points(&deletes, window, &chrono::Local);
+        Ok(FreedSpaceTimeline {
+            window_days,
+            bucket,
+            since,
+            total_bytes: points.last().map_or(0, |p| p.cumulative_bytes),
+            total_files: deletes.len(),
+            points,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-        for root in roots {
-            let walker = WalkDir::new(&root).max_depth(2).into_iter();
-            for entry in walker.filter_map(|e| e.ok()) {
-                let path = entry.path();
-                if !path.is_file() {
-                    continue;
-                }
-                let path_str = path.to_string_lossy().to_string();
-                let name_lower = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                let parent = path
-                    .parent()
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|| root.clone());
-                let meta = match std::fs::metadata(path) {
-                    Ok(m) => m,
-                    Err(_) => continue,
-                };
-                let size = meta.len();
-                let modified = meta.modified().ok();
-                let is_old = modified
-                    .and_then(|m| now.duration_since(m).ok())
-                    .map(|d| d >= thirty_days)
-                    .unwrap_or(false);
-
-                let parent_lower = parent.to_lowercase();
-                let in_downloads = parent_lower.contains("downloads");
-                let in_desktop = parent_lower.contains("desktop");
-
-                let mut bucket: Option<&str> = None;
-                if name_lower.ends_with(".exe") {
-                    if in_downloads || is_old {
-                        bucket = Some("executable");
-                    }
-                } else if in_downloads && is_old {
-                    bucket = Some("big_download");
-                } else if in_desktop && is_old {
-                    bucket = Some("old_desktop");
-                }
+/// Files listed in each section of the weekly digest.
+const DIGEST_LIST_LIMIT: usize = 10;
 
-                if let Some(key) = bucket {
-                    let entry = UiCandidate {
-                        id: 0,
-                        path: path_str.clone(),
-                        parent: parent.clone(),
-                        size,
-                        mime: None,
-                        created_at: None,
-                        modified_at: None,
-                        accessed_at: None,
-                        partial_sha1: None,
-                        sha1: None,
-                        reason: key.to_string(),
-                        group_key: None,
-                    };
-                    by_bucket.entry(key.to_string()).or_default().push(entry);
-                    let e = summaries_acc.entry(key.to_string()).or_insert((0, 0));
-                    e.0 += 1;
-                    e.1 += size;
-                    total_count += 1;
-                }
-            }
-        }
-    }
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WeeklyDigestReport {
+    pub digest: WeeklyDigest,
+    /// "markdown", "html" or "csv".
+    pub format: String,
+    pub content: String,
+    /// Where the report was saved, when a path was given.
+    pub written_to: Option<String>,
+}
 
-    let summaries = summaries_acc
+pub(crate) fn build_weekly_digest(db: &Database, window_days: i64) -> Result<WeeklyDigest, String> {
+    let generated_at = Utc::now();
+    let since = generated_at - Duration::days(window_days);
+    let unreclaimable = db
+        .unreclaimable_file_ids()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let freed: Vec<u64> = db
+        .deletes_with_sizes_since(&since)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
         .into_iter()
-        .map(|(k, (count, bytes))| BucketSummary {
-            key: k,
-            count,
-            total_bytes: bytes,
+        .filter(|(_, file_id, _)| !unreclaimable.contains(file_id))
+        .map(|(_, _, bytes)| bytes)
+        .collect();
+    let staged = db
+        .review_staged(&generated_at, None)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let horizon = generated_at + Duration::days(window_days);
+    let digest_file = |file: &File, at: Option<DateTime<Utc>>| DigestFile {
+        path: file.path.clone(),
+        size_bytes: file.size_bytes.max(0) as u64,
+        at,
+    };
+    // Soonest first, as `review_staged` returns them.
+    let expiring_soon = staged
+        .iter()
+        .filter(|(record, _, _)| record.expires_at.is_some_and(|at| at <= horizon))
+        .take(DIGEST_LIST_LIMIT)
+        .map(|(record, file, _)| digest_file(file, record.expires_at))
+        .collect();
+    let biggest_new_files = db
+        .largest_new_files(&since, DIGEST_LIST_LIMIT)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .iter()
+        .map(|file| digest_file(file, Some(file.first_seen_at)))
+        .collect();
+    let batches = db
+        .list_batches(&BatchFilters {
+            since: Some(since),
+            limit: Some(DIGEST_LIST_LIMIT),
+            ..Default::default()
         })
-        .collect::<Vec<_>>();
-
-    Ok(CandidatesResponse {
-        by_bucket,
-        summaries,
-        total_count,
-        paging: Paging {
-            limit,
-            offset,
-            has_more: has_more,
-        },
-        errors,
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+
+    Ok(WeeklyDigest {
+        generated_at,
+        since,
+        window_days,
+        freed_bytes: freed.iter().sum(),
+        freed_files: freed.len(),
+        staged_files: staged.len(),
+        staged_bytes: staged
+            .iter()
+            .map(|(_, file, _)| file.size_bytes.max(0) as u64)
+            .sum(),
+        biggest_new_files,
+        expiring_soon,
+        batches,
     })
 }
 
+/// Disk hygiene summary of the last `window_days` (default 7): space freed,
+/// staged files awaiting review, the biggest new files and cooloffs ending
+/// soon. Rendered as `markdown` (default), `html` or `csv`; with
+/// `output_path` (a file, or a folder to put a dated file in) it is also saved
+/// to disk.
 #[tauri::command]
-pub async fn scan_roots(
-    roots: Vec<String>,
-    app: tauri::AppHandle,
+pub async fn generate_weekly_digest(
+    window_days: Option<i64>,
+    format: Option<String>,
+    output_path: Option<String>,
     db: State<'_, DbPool>,
-) -> Result<ScanResult, String> {
-    println!("scan_roots called with roots: {:?}", roots);
-
-    if roots.is_empty() {
-        return Err("ERR_VALIDATION: No scan roots provided".to_string());
-    }
-
-    if roots.len() > 10 {
-        return Err("ERR_VALIDATION: Too many scan roots (max 10)".to_string());
+) -> Result<WeeklyDigestReport, String> {
+    let window_days = window_days.unwrap_or(7);
+    if window_days <= 0 || window_days > 365 {
+        return Err("ERR_VALIDATION: window_days must be 1-365".to_string());
     }
-
-    let mut unique = HashSet::new();
-    let mut sanitized_roots = Vec::new();
-    for root in &roots {
-        validate_scan_path(root).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-        if !Path::new(root).is_dir() {
-            return Err(format!("ERR_VALIDATION: Path is not a directory: {}", root));
-        }
-        let clean = sanitize_string(root);
-        if unique.insert(clean.clone()) {
-            sanitized_roots.push(clean);
+    let format = match format.as_deref() {
+        None => DigestFormat::Markdown,
+        Some(value) => DigestFormat::parse(value).ok_or_else(|| {
+            format!("ERR_VALIDATION: format must be one of {}", DigestFormat::NAMES.join(", "))
+        })?,
+    };
+    let dest = match output_path.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(path) => {
+            let dest = PathBuf::from(path);
+            let has_parent_dir = dest
+                .components()
+                .any(|component| matches!(component, std::path::Component::ParentDir));
+            if !dest.is_absolute() || has_parent_dir {
+                return Err("ERR_VALIDATION: Report path must be absolute".to_string());
+            }
+            let dest = if dest.is_dir() {
+                dest.join(format!(
+                    "white-space-digest-{}.{}",
+                    Utc::now().format("%Y-%m-%d"),
+                    format.extension()
+                ))
+            } else {
+                dest
+            };
+            if !dest.parent().is_some_and(Path::is_dir) {
+                return Err(format!(
+                    "ERR_VALIDATION: Folder does not exist: {}",
+                    dest.parent().unwrap_or(&dest).display()
+                ));
+            }
+            Some(dest)
         }
-    }
+    };
 
     let db_clone = db.inner().clone();
-    let app_handle = app.clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let db_instance = Database::new(conn);
-        let mut scanner = Scanner::new();
-        scanner
-            .run_scan(&app_handle, sanitized_roots, &db_instance)
-            .map_err(|e| format!("ERR_SCAN: {e}"))
+        let digest = build_weekly_digest(&Database::new(conn), window_days)?;
+        let content = reports::render_digest(&digest, format);
+        if let Some(dest) = dest.as_ref() {
+            fs::write(dest, &content).map_err(|e| {
+                format!("ERR_INTERNAL: Failed to write {}: {}", dest.display(), e)
+            })?;
+        }
+        Ok(WeeklyDigestReport {
+            digest,
+            format: format.name().to_string(),
+            content,
+            written_to: dest.map(|d| d.to_string_lossy().to_string()),
+        })
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    Ok(result)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StaleFilesReport {
+    pub days: i64,
+    pub cutoff: DateTime<Utc>,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub groups: Vec<StaleGroup>,
 }
 
+/// Time-bucketed series for each of `metric_names` over the last `days`
+/// (default 30). `window` is sample, hour, day (default), week or month in
+/// local time; `aggregation` is sum, avg (default), min, max, count or last.
 #[tauri::command]
-pub async fn daily_candidates(
-    max_total: usize,
+pub async fn get_metrics(
+    metric_names: Vec<String>,
+    window: Option<String>,
+    aggregation: Option<String>,
+    days: Option<i64>,
     db: State<'_, DbPool>,
-) -> Result<Vec<Candidate>, String> {
-    println!("daily_candidates called with max_total: {}", max_total);
-
-    // Validate input
-    if max_total == 0 {
-        return Err("ERR_VALIDATION: max_total must be greater than 0".to_string());
+) -> Result<Vec<MetricSeries>, String> {
+    let metric_names: Vec<String> = metric_names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if metric_names.is_empty() || metric_names.len() > 20 {
+        return Err("ERR_VALIDATION: metric_names must list 1-20 metrics".to_string());
     }
-
-    if max_total > 1000 {
-        return Err("ERR_VALIDATION: max_total too large (max 1000)".to_string());
+    let window = match window.as_deref() {
+        Some(raw) => MetricWindow::parse(raw).ok_or_else(|| {
+            format!("ERR_VALIDATION: window must be one of {}", MetricWindow::NAMES.join(", "))
+        })?,
+        None => MetricWindow::Day,
+    };
+    let aggregation = match aggregation.as_deref() {
+        Some(raw) => MetricAggregation::parse(raw).ok_or_else(|| {
+            format!(
+                "ERR_VALIDATION: aggregation must be one of {}",
+                MetricAggregation::NAMES.join(", ")
+            )
+        })?,
+        None => MetricAggregation::Avg,
+    };
+    let days = days.unwrap_or(30);
+    if days <= 0 || days > 3650 {
+        return Err("ERR_VALIDATION: days must be 1-3650".to_string());
     }
+    let since = Utc::now() - Duration::days(days);
 
-    // Get candidates using spawn_blocking for database operations
     let db_clone = db.inner().clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let selector = FileSelector::new();
         let db_instance = Database::new(conn);
-        selector
-            .daily_candidates(Some(max_total), &db_instance)
-            .map_err(|e| format!("ERR_SELECTOR: {}", e))
+        metric_names
+            .into_iter()
+            .map(|metric| {
+                let samples = db_instance
+                    .metric_samples(&metric, &since)
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+                let points =
+                    reports::bucket_metrics(&samples, window, aggregation, &chrono::Local);
+                Ok(MetricSeries { metric, points })
+            })
+            .collect()
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
-
-    Ok(result)
+    .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn gauge_state(db: State<'_, DbPool>) -> Result<GaugeState, String> {
-    println!("gauge_state called");
+pub async fn stale_files_report(
+    days: i64,
+    root_path: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<StaleFilesReport, String> {
+    if days <= 0 {
+        return Err("ERR_VALIDATION: days must be > 0".to_string());
+    }
+    let cutoff = Utc::now() - Duration::days(days);
+
     let db_clone = db.inner().clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let gauge_manager = GaugeManager::new();
         let db_instance = Database::new(conn);
-        gauge_manager
-            .gauge_state(&db_instance)
-            .map_err(|e| format!("ERR_GAUGE: {}", e))
+        let roots = match root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(root) => vec![ensure_within_watched_root(&db_instance, root)?],
+            None => db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?,
+        };
+        let protected = load_protected_paths(&db_instance)?;
+        let mut files = db_instance
+            .stale_files_under_roots(&roots, &cutoff)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        files.retain(|file| !is_protected(Path::new(&file.path), &protected));
+
+        let groups = reports::group_by_parent(files);
+        Ok(StaleFilesReport {
+            days,
+            cutoff,
+            total_files: groups.iter().map(|g| g.file_count).sum(),
+            total_bytes: groups.iter().map(|g| g.total_bytes).sum(),
+            groups,
+        })
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    Ok(result)
+/// Months whose screenshots are all older than this are flagged by default.
+const DEFAULT_SCREENSHOT_AGE_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ScreenshotsReport {
+    pub older_than_days: i64,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub months: Vec<ScreenshotMonth>,
+}
+
+/// Capture date for a screenshot: the date in its name, then EXIF, then the
+/// earlier of its created and modified times.
+fn screenshot_date(file: &File) -> NaiveDate {
+    let name = Path::new(&file.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    reports::filename_date(&name)
+        .or_else(|| preview::exif_capture_date(Path::new(&file.path)).map(|at| at.date()))
+        .unwrap_or_else(|| {
+            let stamp = file.modified_at.map_or(file.created_at, |m| m.min(file.created_at));
+            stamp.with_timezone(&chrono::Local).date_naive()
+        })
+}
+
+fn screenshot_months(
+    db: &Database,
+    root_path: Option<&str>,
+    older_than_days: i64,
+) -> Result<Vec<ScreenshotMonth>, String> {
+    let roots = match root_path.filter(|p| !p.trim().is_empty()) {
+        Some(root) => vec![ensure_within_watched_root(db, root)?],
+        None => db
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?,
+    };
+    let protected = load_protected_paths(db)?;
+    let only_screenshots = BucketPrefilter {
+        screenshots: true,
+        ..Default::default()
+    };
+    let shots = db
+        .bucket_prefiltered_files(&roots, &only_screenshots)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .filter(|file| !file.is_staged && FileSelector::looks_like_screenshot(file))
+        .filter(|file| !is_protected(Path::new(&file.path), &protected))
+        .map(|file| {
+            let taken = screenshot_date(&file);
+            (file, taken)
+        })
+        .collect();
+    let old_before = (chrono::Local::now() - Duration::days(older_than_days)).date_naive();
+    Ok(reports::group_screenshots_by_month(shots, old_before))
 }
 
+/// Screenshots grouped by capture month, newest first, with months older
+/// than `older_than_days` (default 90) flagged for cleanup.
 #[tauri::command]
-pub async fn list_staged(
-    statuses: Option<Vec<String>>,
+pub async fn screenshots_report(
+    older_than_days: Option<i64>,
+    root_path: Option<String>,
     db: State<'_, DbPool>,
-) -> Result<Vec<StagedFile>, String> {
-    let status_filter = statuses.map(|items| {
-        items
-            .into_iter()
-            .map(|s| s.to_lowercase())
-            .collect::<Vec<_>>()
-    });
+) -> Result<ScreenshotsReport, String> {
+    let older_than_days = older_than_days.unwrap_or(DEFAULT_SCREENSHOT_AGE_DAYS);
+    if older_than_days < 0 {
+        return Err("ERR_VALIDATION: older_than_days must be >= 0".to_string());
+    }
+
     let db_clone = db.inner().clone();
     tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let pairs = match status_filter {
-            Some(filters) => db_instance
-                .list_staged_with_files(Some(filters.as_slice()))
-                .map_err(|e| format!("ERR_DATABASE: {e}"))?,
-            None => db_instance
-                .list_staged_with_files(None)
-                .map_err(|e| format!("ERR_DATABASE: {e}"))?,
-        };
-        let mut results = Vec::with_capacity(pairs.len());
-        for (record, file) in pairs {
-            results.push(staged_payload(&record, &file));
-        }
-        Ok(results)
+        let months = screenshot_months(&db_instance, root_path.as_deref(), older_than_days)?;
+        Ok(ScreenshotsReport {
+            older_than_days,
+            total_files: months.iter().map(|m| m.file_count).sum(),
+            total_bytes: months.iter().map(|m| m.total_bytes).sum(),
+            months,
+        })
     })
     .await
     .map_err(|e| format!("join error: {e}"))?
 }
 
+/// Stages every screenshot from `month` (`YYYY-MM`) as one batch.
 #[tauri::command]
-pub async fn stage_files(
-    file_ids: Vec<i64>,
+pub async fn stage_screenshot_month(
+    month: String,
+    root_path: Option<String>,
     options: Option<StageOptions>,
+    app: tauri::AppHandle,
     db: State<'_, DbPool>,
 ) -> Result<StageOutcome, String> {
-    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
-    if file_ids.is_empty() {
-        return Err("ERR_VALIDATION: No file IDs provided".to_string());
+    if NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").is_err() {
+        return Err("ERR_VALIDATION: month must be YYYY-MM".to_string());
     }
 
-    let mut opts = options.unwrap_or_default();
-    let mut cooloff_days = opts.cooloff_days.take().unwrap_or(7);
-    if cooloff_days < 0 {
-        cooloff_days = 0;
-    }
-    if cooloff_days > 30 {
-        cooloff_days = 30;
-    }
-    let note = sanitize_note(opts.note.take());
     let db_clone = db.inner().clone();
-    tokio::task::spawn_blocking(move || {
+    let file_ids = tokio::task::spawn_blocking(move || -> Result<Vec<i64>, String> {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let mut db_instance = Database::new(conn);
-        let mut archive_manager = ArchiveManager::new();
-
-        let mut unique_ids = HashSet::new();
-        let mut file_paths = Vec::new();
-        for file_id in &file_ids {
-            if !unique_ids.insert(*file_id) {
-                continue;
-            }
-            let file = db_instance
-                .get_file_by_id(*file_id)
-                .map_err(|e| format!("ERR_DATABASE: {e}"))?
-                .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
-            if file.is_deleted {
-                return Err(format!(
-                    "ERR_VALIDATION: File with ID {} has been deleted",
-                    file_id
-                ));
-            }
-            let file_path = Path::new(&file.path);
-            if !file_path.exists() {
-                return Err(format!(
-                    "ERR_NOT_FOUND: File with ID {} not found on disk",
-                    file_id
-                ));
-            }
-            file_paths.push(file.path.clone());
-        }
+        let db_instance = Database::new(conn);
+        screenshot_months(&db_instance, root_path.as_deref(), DEFAULT_SCREENSHOT_AGE_DAYS)?
+            .into_iter()
+            .find(|m| m.month == month.trim())
+            .map(|m| m.file_ids)
+            .ok_or_else(|| format!("ERR_NOT_FOUND: No screenshots for {}", month.trim()))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
 
-        if file_paths.is_empty() {
-            return Err("ERR_VALIDATION: No unique file paths to stage".to_string());
-        }
+    stage_files(file_ids, with_origin(options, "screenshots"), None, app, db).await
+}
 
-        let archive_result = archive_manager
-            .archive_files(file_paths, &db_instance)
-            .map_err(|e| format!("ERR_ARCHIVE: {e}"))?;
+/// Repos untouched for this long have their build artifacts suggested.
+const DEFAULT_ARTIFACT_INACTIVE_DAYS: i64 = 30;
 
-        let actions = db_instance
-            .get_actions_by_batch_id(&archive_result.batch_id)
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BuildArtifactsReport {
+    pub inactive_days: i64,
+    pub total_bytes: u64,
+    pub projects: Vec<ArtifactProject>,
+}
 
-        let archived_actions: Vec<_> = actions
-            .into_iter()
-            .filter(|action| action.action == ActionType::Archive)
-            .collect();
+/// The "build artifacts" bucket: node_modules, target, dist, virtualenvs and
+/// caches of dev repos with no activity in the last `inactive_days`.
+#[tauri::command]
+pub async fn get_build_artifacts(
+    root_path: Option<String>,
+    inactive_days: Option<i64>,
+    db: State<'_, DbPool>,
+) -> Result<BuildArtifactsReport, String> {
+    let inactive_days = inactive_days.unwrap_or(DEFAULT_ARTIFACT_INACTIVE_DAYS);
+    if inactive_days < 0 {
+        return Err("ERR_VALIDATION: inactive_days must be >= 0".to_string());
+    }
 
-        let expires_at_dt = if cooloff_days > 0 {
-            Some(Utc::now() + Duration::days(cooloff_days))
-        } else {
-            None
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let roots = match root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(root) => vec![ensure_within_watched_root(&db_instance, root)?],
+            None => db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?,
         };
-
-        let mut staged_entries = Vec::new();
-        for action in &archived_actions {
-            let batch_id = action
-                .batch_id
-                .clone()
-                .or_else(|| Some(archive_result.batch_id.clone()));
-            staged_entries.push(NewStagedFile {
-                file_id: action.file_id,
-                staged_at: action.created_at,
-                expires_at: expires_at_dt.clone(),
-                batch_id,
-                status: "staged".to_string(),
-                note: note.clone(),
-            });
+        let protected = load_protected_paths(&db_instance)?;
+
+        let mut projects = ActiveProjectDetector::new().build_artifacts(&roots, inactive_days);
+        for project in &mut projects {
+            project
+                .dirs
+                .retain(|dir| !is_protected(Path::new(&dir.path), &protected));
+            project.total_bytes = project.dirs.iter().map(|d| d.size_bytes).sum();
         }
+        projects.retain(|project| !project.dirs.is_empty());
+        Ok(BuildArtifactsReport {
+            inactive_days,
+            total_bytes: projects.iter().map(|p| p.total_bytes).sum(),
+            projects,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-        if !staged_entries.is_empty() {
-            db_instance
-                .stage_files(&staged_entries)
-                .map_err(|e| format!("ERR_DATABASE: {e}"))?;
-        }
+/// Indexes `path` as a single directory entry so it can be staged and
+/// restored as one item instead of file by file.
+fn index_directory(db: &Database, path: &str) -> Result<i64, String> {
+    let normalized = normalize_existing_path(Path::new(path)).map_err(command_error_to_string)?;
+    if !normalized.is_dir() {
+        return Err(format!("ERR_VALIDATION: {} is not a directory", path));
+    }
+    let roots = db
+        .list_watched_roots()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    ensure_within_watched(&normalized, &roots).map_err(command_error_to_string)?;
+    if roots.iter().any(|root| Path::new(&root.path) == normalized) {
+        return Err("ERR_VALIDATION: Cannot stage a watched folder itself".to_string());
+    }
+    let dir_str = normalized.to_string_lossy().to_string();
+    ensure_not_protected(&dir_str, &load_protected_paths(db)?)?;
+    let root_id = roots
+        .iter()
+        .filter(|root| normalized.starts_with(&root.path))
+        .max_by_key(|root| root.path.len())
+        .map(|root| root.id);
+
+    let size_bytes = SpaceManager::new()
+        .calculate_directory_size(&normalized)
+        .map_err(|e| format!("ERR_INTERNAL: {e}"))?;
+    let parent_dir = normalized
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    db.upsert_file(&NewFile {
+        path: dir_str.clone(),
+        parent_dir,
+        mime: Some(DIRECTORY_MIME.to_string()),
+        size_bytes: size_bytes as i64,
+        created_at: None,
+        modified_at: None,
+        accessed_at: None,
+        partial_sha1: None,
+        sha1: None,
+        inode_key: None,
+        link_count: 1,
+        phash: None,
+        is_cloud_placeholder: false,
+        root_id,
+        is_symlink: false,
+        symlink_target: None,
+        hash_algo: HashAlgo::CURRENT,
+    })
+    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    db.get_file_id_by_path(&dir_str)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .ok_or_else(|| format!("ERR_INTERNAL: {} was not indexed", dir_str))
+}
 
-        let outcome = StageOutcome {
-            success: archive_result.errors.is_empty(),
-            batch_id: if staged_entries.is_empty() {
-                None
-            } else {
-                Some(archive_result.batch_id.clone())
-            },
-            staged_files: staged_entries.len(),
-            total_bytes: archive_result.total_bytes,
-            duration_ms: archive_result.duration_ms,
-            errors: archive_result.errors,
-            expires_at: expires_at_dt.map(|dt| dt.to_rfc3339()),
-            note,
-        };
+/// Stages a whole directory, such as a project's node_modules, as one
+/// item. Directories are moved, never zipped, so the archive must live on
+/// the same volume.
+#[tauri::command]
+pub async fn stage_directory(
+    path: String,
+    options: Option<StageOptions>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<StageOutcome, String> {
+    if path.trim().is_empty() {
+        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+    }
 
-        Ok(outcome)
+    let db_clone = db.inner().clone();
+    let file_id = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        index_directory(&Database::new(conn), &path)
     })
     .await
-    .map_err(|e| format!("join error: {e}"))?
+    .map_err(|e| format!("join error: {e}"))??;
+
+    stage_files(vec![file_id], with_origin(options, "directory"), None, app, db).await
+}
+
+/// The fixed order of `search_files`: largest first, then by path.
+const SEARCH_SORT: &str = "search";
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchFilesResponse {
+    pub items: Vec<File>,
+    pub total_count: usize,
+    pub paging: Paging,
 }
 
 #[tauri::command]
-pub async fn restore_staged(batch_id: String, db: State<'_, DbPool>) -> Result<UndoResult, String> {
-    if batch_id.trim().is_empty() {
-        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
-    }
+pub async fn search_files(
+    query: String,
+    filters: Option<FileSearchFilters>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<SearchFilesResponse, String> {
+    let filters = filters.unwrap_or_default();
+    validate_search_filters(&filters)?;
+    let (limit, offset, after) = search_paging(limit, offset, cursor.as_deref())?;
 
     let db_clone = db.inner().clone();
     tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
+        search_page(&db_instance, &query, &filters, limit, offset, after)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-        let actions = db_instance
-            .get_actions_by_batch_id(&batch_id)
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
-        let archived_ids: Vec<i64> = actions
-            .iter()
-            .filter(|action| action.action == ActionType::Archive)
-            .map(|action| action.file_id)
-            .collect();
+fn validate_search_filters(filters: &FileSearchFilters) -> Result<(), String> {
+    if let (Some(min), Some(max)) = (filters.min_size_bytes, filters.max_size_bytes) {
+        if min > max {
+            return Err("ERR_VALIDATION: min_size_bytes exceeds max_size_bytes".to_string());
+        }
+    }
+    if filters.min_age_days.is_some_and(|days| !(0..=36500).contains(&days)) {
+        return Err("ERR_VALIDATION: min_age_days must be 0-36500".to_string());
+    }
+    Ok(())
+}
 
-        if archived_ids.is_empty() {
-            return Err(format!(
-                "ERR_NOT_FOUND: No archived files associated with batch {batch_id}"
-            ));
+/// Page size, offset and decoded cursor for a search listing.
+fn search_paging(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<&str>,
+) -> Result<(usize, usize, Option<PageCursor>), String> {
+    let limit = limit.unwrap_or(50).min(500);
+    if limit == 0 {
+        return Err("ERR_VALIDATION: limit must be > 0".to_string());
+    }
+    let after = match cursor {
+        Some(token) => {
+            let cursor = PageCursor::decode(token).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+            if cursor.sort != SEARCH_SORT {
+                return Err("ERR_VALIDATION: cursor belongs to another listing".to_string());
+            }
+            Some(cursor)
         }
+        None => None,
+    };
+    Ok((limit, offset.unwrap_or(0), after))
+}
 
-        let mut undo_manager = UndoManager::new();
-        let result = undo_manager
-            .undo_batch(&batch_id, &db_instance)
-            .map_err(|e| format!("ERR_UNDO: {e}"))?;
+/// Watched roots a search covers, or just `root_path` when it lies inside one.
+fn search_roots(db: &Database, filters: &FileSearchFilters) -> Result<Vec<String>, String> {
+    match filters.root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(root) => Ok(vec![ensure_within_watched_root(db, root)?]),
+        None => db
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e)),
+    }
+}
 
-        db_instance
-            .update_staged_status(&archived_ids, "restored")
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
-        db_instance
-            .mark_files_unstaged(&archived_ids)
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+/// One page of search results, shared by `search_files` and saved views.
+fn search_page(
+    db: &Database,
+    query: &str,
+    filters: &FileSearchFilters,
+    limit: usize,
+    offset: usize,
+    after: Option<PageCursor>,
+) -> Result<SearchFilesResponse, String> {
+    let roots = search_roots(db, filters)?;
+    // One extra row tells whether another page follows.
+    let (total_count, mut items) = db
+        .search_files(query, filters, &roots, limit + 1, offset, after.as_ref())
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    let snapshot_ms = after
+        .as_ref()
+        .map_or_else(|| Utc::now().timestamp_millis(), |c| c.snapshot_ms);
+    let next_cursor = items.last().filter(|_| has_more).map(|last| {
+        PageCursor {
+            sort: SEARCH_SORT.to_string(),
+            key: Some(last.size_bytes as f64),
+            text: Some(last.path.clone()),
+            id: last.id.unwrap_or(0),
+            snapshot_ms,
+        }
+        .encode()
+    });
+    Ok(SearchFilesResponse {
+        items,
+        total_count,
+        paging: Paging {
+            limit,
+            offset: if after.is_some() { 0 } else { offset },
+            has_more,
+            next_cursor,
+        },
+    })
+}
 
-        Ok(result)
+fn validate_saved_view(view: NewSavedView) -> Result<NewSavedView, String> {
+    let name = sanitize_string(view.name.trim());
+    if name.is_empty() {
+        return Err("ERR_VALIDATION: View name cannot be empty".to_string());
+    }
+    if name.chars().count() > 100 {
+        return Err("ERR_VALIDATION: View name must be at most 100 characters".to_string());
+    }
+    if view.notify_threshold.is_some_and(|threshold| threshold <= 0) {
+        return Err("ERR_VALIDATION: notify_threshold must be > 0".to_string());
+    }
+    validate_search_filters(&view.filters)?;
+    Ok(NewSavedView {
+        name,
+        query: sanitize_string(view.query.trim()),
+        ..view
+    })
+}
+
+#[tauri::command]
+pub async fn add_saved_view(
+    view: NewSavedView,
+    db: State<'_, DbPool>,
+) -> Result<SavedView, String> {
+    let view = validate_saved_view(view)?;
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let id = db_instance
+            .insert_saved_view(&view)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        db_instance
+            .get_saved_view_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_DATABASE: View not found after insert".to_string())
     })
     .await
     .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn empty_staged(
-    file_ids: Vec<i64>,
-    to_trash: bool,
+pub async fn update_saved_view(
+    id: i64,
+    view: NewSavedView,
     db: State<'_, DbPool>,
-) -> Result<DeleteOutcome, String> {
-    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
-    if file_ids.is_empty() {
-        return Err("ERR_VALIDATION: No file IDs provided".to_string());
+) -> Result<SavedView, String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid view id".to_string());
     }
-
+    let view = validate_saved_view(view)?;
     let db_clone = db.inner().clone();
     tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-
-        let mut file_paths = Vec::new();
-        for file_id in &file_ids {
-            let file = db_instance
-                .get_file_by_id(*file_id)
-                .map_err(|e| format!("ERR_DATABASE: {e}"))?
-                .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
-            validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
-            file_paths.push(file.path);
+        let changed = db_instance
+            .update_saved_view(id, &view)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if changed == 0 {
+            return Err("ERR_NOT_FOUND: View not found".to_string());
         }
-
-        let mut delete_manager = DeleteManager::new();
-        delete_manager.set_use_trash(to_trash);
-        let delete_result = delete_manager
-            .delete_files(file_paths, &db_instance)
-            .map_err(|e| format!("ERR_DELETE: {e}"))?;
-
-        db_instance
-            .update_staged_status(&file_ids, "emptied")
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
         db_instance
-            .mark_files_unstaged(&file_ids)
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
-
-        Ok(DeleteOutcome {
-            success: delete_result.errors.is_empty(),
-            files_processed: delete_result.files_deleted,
-            total_bytes_freed: delete_result.total_bytes_freed,
-            duration_ms: delete_result.duration_ms,
-            errors: delete_result.errors,
-            to_trash,
-        })
+            .get_saved_view_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_NOT_FOUND: View not found".to_string())
     })
     .await
     .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn get_duplicate_groups(
-    limit: Option<usize>,
-    db: State<'_, DbPool>,
-) -> Result<Vec<DuplicateGroup>, String> {
-    let fetch_limit = limit.unwrap_or(20).min(200);
+pub async fn list_saved_views(db: State<'_, DbPool>) -> Result<Vec<SavedView>, String> {
     let db_clone = db.inner().clone();
     tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let groups = db_instance
-            .duplicate_groups(Some(fetch_limit))
-            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
-        let mut response = Vec::with_capacity(groups.len());
-        for (hash, files) in groups {
-            let mut total_size = 0u64;
-            let mut group_files = Vec::with_capacity(files.len());
-            for file in files {
-                let file_id = file.id.unwrap_or(0);
-                let size = if file.size_bytes < 0 {
-                    0
-                } else {
-                    file.size_bytes as u64
-                };
-                total_size = total_size.saturating_add(size);
-                group_files.push(DuplicateGroupFile {
-                    id: file_id,
-                    path: file.path.clone(),
-                    parent_dir: file.parent_dir.clone(),
-                    size_bytes: size,
-                    last_seen_at: file.last_seen_at.to_rfc3339(),
-                    is_staged: file.is_staged,
-                    cooloff_until: file.cooloff_until.map(|dt| dt.to_rfc3339()),
-                });
-            }
-            response.push(DuplicateGroup {
-                hash,
-                total_size,
-                count: group_files.len(),
-                files: group_files,
-            });
-        }
-        Ok(response)
+        db_instance
+            .list_saved_views()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
     })
     .await
     .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn archive_files(
-    file_ids: Vec<i64>,
-    db: State<'_, DbPool>,
-) -> Result<ArchiveOutcome, String> {
-    // Validate input
-    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-
-    // Perform archive operation using spawn_blocking for database operations
+pub async fn remove_saved_view(id: i64, db: State<'_, DbPool>) -> Result<(), String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid view id".to_string());
+    }
     let db_clone = db.inner().clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-
-        // Get file paths from database
-        let mut file_paths = Vec::new();
-        for file_id in &file_ids {
-            match db_instance.get_file_by_id(*file_id) {
-                Ok(Some(file)) => {
-                    validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-                    file_paths.push(file.path);
-                }
-                Ok(None) => {
-                    return Err(format!("ERR_NOT_FOUND: File with ID {} not found", file_id));
-                }
-                Err(e) => {
-                    return Err(format!("ERR_DATABASE: {}", e));
-                }
-            }
+        let removed = db_instance
+            .delete_saved_view(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if removed == 0 {
+            return Err("ERR_NOT_FOUND: View not found".to_string());
         }
-
-        // Perform archive operation
-        let mut archive_manager = ArchiveManager::new();
-        archive_manager
-            .archive_files(file_paths, &db_instance)
-            .map_err(|e| format!("ERR_ARCHIVE: {}", e))
+        Ok(())
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
-
-    Ok(ArchiveOutcome {
-        success: result.errors.is_empty(),
-        files_processed: result.files_archived,
-        total_bytes: result.total_bytes,
-        duration_ms: result.duration_ms,
-        errors: result.errors,
-        dry_run: false, // TODO: Get from user preferences
-    })
+    .map_err(|e| format!("join error: {e}"))?
 }
 
+/// Runs a saved view against the index, paged like `search_files`.
 #[tauri::command]
-pub async fn delete_files(
-    file_ids: Vec<i64>,
-    to_trash: bool,
+pub async fn run_view(
+    view_id: i64,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
     db: State<'_, DbPool>,
-) -> Result<DeleteOutcome, String> {
-    // Validate input
-    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-
-    // Perform delete operation using spawn_blocking for database operations
+) -> Result<SearchFilesResponse, String> {
+    let (limit, offset, after) = search_paging(limit, offset, cursor.as_deref())?;
     let db_clone = db.inner().clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-
-        // Get file paths from database
-        let mut file_paths = Vec::new();
-        for file_id in &file_ids {
-            match db_instance.get_file_by_id(*file_id) {
-                Ok(Some(file)) => {
-                    validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
-                    file_paths.push(file.path);
-                }
-                Ok(None) => {
-                    return Err(format!("ERR_NOT_FOUND: File with ID {} not found", file_id));
-                }
-                Err(e) => {
-                    return Err(format!("ERR_DATABASE: {}", e));
-                }
-            }
-        }
-
-        // Perform delete operation
-        let mut delete_manager = DeleteManager::new();
-        delete_manager.set_use_trash(to_trash);
-
-        delete_manager
-            .delete_files(file_paths, &db_instance)
-            .map_err(|e| format!("ERR_DELETE: {}", e))
+        let view = db_instance
+            .get_saved_view_by_id(view_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| format!("ERR_NOT_FOUND: View {view_id} not found"))?;
+        search_page(&db_instance, &view.query, &view.filters, limit, offset, after)
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-    Ok(DeleteOutcome {
-        success: result.errors.is_empty(),
-        files_processed: result.files_deleted,
-        total_bytes_freed: result.total_bytes_freed,
-        duration_ms: result.duration_ms,
-        errors: result.errors,
-        to_trash,
-    })
+/// Accepts an RFC 3339 timestamp or a plain `YYYY-MM-DD` date (midnight UTC).
+fn parse_dismiss_until(raw: &str) -> Result<DateTime<Utc>, String> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|naive| DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc))
+        .ok_or_else(|| format!("ERR_VALIDATION: Invalid date: {}", raw))
 }
 
 #[tauri::command]
-pub async fn undo_last(db: State<'_, DbPool>) -> Result<UndoResult, String> {
+pub async fn dismiss_candidate(
+    file_id: i64,
+    until: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<DismissedFile, String> {
+    if file_id <= 0 {
+        return Err("ERR_VALIDATION: Invalid file id".to_string());
+    }
+    let until = match until.as_deref().filter(|u| !u.trim().is_empty()) {
+        Some(raw) => {
+            let parsed = parse_dismiss_until(raw)?;
+            if parsed <= Utc::now() {
+                return Err("ERR_VALIDATION: until must be in the future".to_string());
+            }
+            Some(parsed)
+        }
+        None => None,
+    };
+
     let db_clone = db.inner().clone();
-    let result = tokio::task::spawn_blocking(move || {
+    let dismissed = tokio::task::spawn_blocking(move || -> Result<DismissedFile, String> {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let mut undo_manager = UndoManager::new();
-        undo_manager
-            .undo_last(&db_instance)
-            .map_err(|e| format!("ERR_UNDO: {}", e))
+        let file = db_instance
+            .get_file_by_id(file_id)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+        db_instance
+            .dismiss_file(file_id, until.as_ref())
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        Ok(DismissedFile {
+            file_id,
+            path: file.path,
+            dismissed_at: Utc::now(),
+            until,
+        })
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    Ok(result)
+    gauge::mark_gauge_dirty();
+    Ok(dismissed)
 }
 
 #[tauri::command]
-pub async fn list_undoable_batches(db: State<'_, DbPool>) -> Result<Vec<UndoBatchSummary>, String> {
+pub async fn list_dismissed(db: State<'_, DbPool>) -> Result<Vec<DismissedFile>, String> {
     let db_clone = db.inner().clone();
-    let batches = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let undo = UndoManager::new();
-        undo.get_undoable_batches(&db_instance)
-            .map_err(|e| format!("ERR_UNDO: {}", e))
+        db_instance
+            .list_dismissed()
+            .map_err(|e| format!("ERR_DATABASE: {e}"))
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
-
-    let summaries = batches
-        .into_iter()
-        .map(|b| UndoBatchSummary {
-            batch_id: b.batch_id,
-            action_type: b.action_type.to_string(),
-            file_count: b.file_count,
-            created_at: b.created_at.timestamp(),
-        })
-        .collect();
-
-    Ok(summaries)
+    .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn undo_batch(batch_id: String, db: State<'_, DbPool>) -> Result<UndoResult, String> {
-    if batch_id.trim().is_empty() {
-        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+pub async fn undismiss_candidate(file_id: i64, db: State<'_, DbPool>) -> Result<(), String> {
+    if file_id <= 0 {
+        return Err("ERR_VALIDATION: Invalid file id".to_string());
     }
 
     let db_clone = db.inner().clone();
-    let target = batch_id.clone();
-    let result = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || -> Result<(), String> {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        let mut undo_manager = UndoManager::new();
-        undo_manager
-            .undo_batch(&target, &db_instance)
-            .map_err(|e| format!("ERR_UNDO: {}", e))
+        let removed = db_instance
+            .undismiss_file(file_id)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        if removed == 0 {
+            return Err(format!("ERR_NOT_FOUND: File {} is not dismissed", file_id));
+        }
+        Ok(())
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    Ok(result)
+    gauge::mark_gauge_dirty();
+    Ok(())
 }
 
-#[tauri::command]
-pub async fn get_review_items(
-    min_age_days: u32,
-    db: State<'_, DbPool>,
-) -> Result<Vec<StagedFile>, String> {
-    // Validate input
-    if min_age_days > 365 {
-        return Err("ERR_VALIDATION: min_age_days too large (max 365)".to_string());
-    }
-
-    // Get staged files (archived but not deleted)
-    let cutoff_date = Utc::now() - Duration::days(min_age_days as i64);
+/// Where a bucket's feedback stands after a verdict was recorded.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FeedbackResult {
+    pub bucket: String,
+    pub totals: BucketFeedback,
+    /// Multiplier now applied to the bucket's scores.
+    pub weight: f64,
+}
 
-    // This would query the database for staged files
-    // For now, return empty vector as placeholder
-    Ok(Vec::new())
+/// Bucket key `file` is suggested under, looked up among the candidates in
+/// its folder. Staged, deleted and dismissed files count: feedback usually
+/// arrives right after the user acted on the suggestion.
+pub(crate) fn feedback_bucket(db: &Database, file: &File) -> Result<Option<String>, String> {
+    let mut selector = FileSelector::from_db(db);
+    selector.set_dismissed(HashSet::new());
+    selector.set_include_staged(true);
+    selector.set_include_deleted(true);
+    let candidates = score_candidates(db, &selector, Some(file.parent_dir.clone()))?;
+    Ok(candidates
+        .into_iter()
+        .find(|c| Some(c.file_id) == file.id)
+        .map(|c| bucket_key(&c.reason)))
 }
 
+/// Records what the user made of a suggestion. Later candidate lists weight
+/// each bucket by its record, so a bucket whose picks keep getting kept
+/// sinks down the list. `bucket` defaults to the one the file is in now.
 #[tauri::command]
-pub async fn get_thumbnail(
+pub async fn record_feedback(
     file_id: i64,
-    max_px: u32,
+    verdict: String,
+    bucket: Option<String>,
     db: State<'_, DbPool>,
-) -> Result<String, String> {
-    // Validate input
+) -> Result<FeedbackResult, String> {
     if file_id <= 0 {
-        return Err("ERR_VALIDATION: Invalid file ID".to_string());
+        return Err("ERR_VALIDATION: Invalid file id".to_string());
     }
+    let verdict: FeedbackVerdict = verdict
+        .parse()
+        .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+    let bucket = bucket.map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
 
-    if max_px == 0 || max_px > 2048 {
-        return Err("ERR_VALIDATION: Invalid thumbnail size (1-2048px)".to_string());
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let file = db_instance
+            .get_file_by_id(file_id)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+        let bucket = match bucket {
+            Some(bucket) => bucket_key(&bucket),
+            None => feedback_bucket(&db_instance, &file)?.ok_or_else(|| {
+                format!("ERR_VALIDATION: File {} is not in any bucket", file_id)
+            })?,
+        };
+        db_instance
+            .record_feedback(file_id, &bucket, verdict)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        let totals = db_instance
+            .feedback_by_bucket()
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .remove(&bucket)
+            .unwrap_or_default();
+        Ok(FeedbackResult {
+            weight: feedback_weight(&totals),
+            bucket,
+            totals,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+fn validate_user_rule(rule: NewUserRule) -> Result<NewUserRule, String> {
+    let name = sanitize_string(rule.name.trim());
+    if name.is_empty() {
+        return Err("ERR_VALIDATION: Rule name cannot be empty".to_string());
     }
+    let path_glob = rule
+        .path_glob
+        .map(|p| sanitize_string(p.trim()))
+        .filter(|p| !p.is_empty());
+    if let Some(pattern) = path_glob.as_deref() {
+        compile_rule_glob(pattern).map_err(|e| format!("ERR_VALIDATION: Invalid path glob: {e}"))?;
+    }
+    if rule.min_size_bytes.is_some_and(|size| size < 0) {
+        return Err("ERR_VALIDATION: min_size_bytes cannot be negative".to_string());
+    }
+    if rule.min_age_days.is_some_and(|days| days < 0) {
+        return Err("ERR_VALIDATION: min_age_days cannot be negative".to_string());
+    }
+    if let Some(max_per_day) = rule.max_per_day {
+        if max_per_day <= 0 || max_per_day > 1000 {
+            return Err("ERR_VALIDATION: max_per_day must be 1-1000".to_string());
+        }
+    }
+    let mime_filter = rule
+        .mime_filter
+        .map(|m| sanitize_string(m.trim()))
+        .filter(|m| !m.is_empty());
+
+    Ok(NewUserRule {
+        name,
+        path_glob,
+        mime_filter,
+        ..rule
+    })
+}
+
+#[tauri::command]
+pub async fn add_user_rule(
+    rule: NewUserRule,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<UserRule, String> {
+    let rule = validate_user_rule(rule)?;
 
-    // Get file from database using spawn_blocking
     let db_clone = db.inner().clone();
-    let file = tokio::task::spawn_blocking(move || {
+    let created = tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
-        match db_instance.get_file_by_id(file_id) {
-            Ok(Some(file)) => Ok(file),
-            Ok(None) => Err(format!("ERR_NOT_FOUND: File with ID {} not found", file_id)),
-            Err(e) => Err(format!("ERR_DATABASE: {}", e)),
-        }
+        let id = db_instance
+            .insert_user_rule(&rule)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        db_instance
+            .get_user_rule_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_DATABASE: Rule not found after insert".to_string())
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
 
-    // Validate file path
-    validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    gauge::mark_gauge_dirty();
+    let _ = app.emit("rules://changed", serde_json::json!({ "added": created.id }));
+    Ok(created)
+}
 
-    // Check if file exists
-    if !Path::new(&file.path).exists() {
-        return Err("ERR_NOT_FOUND: File does not exist on disk".to_string());
+#[tauri::command]
+pub async fn update_user_rule(
+    id: i64,
+    rule: NewUserRule,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<UserRule, String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid rule id".to_string());
     }
+    let rule = validate_user_rule(rule)?;
 
-    // Generate thumbnail (placeholder implementation)
-    // In a real implementation, this would:
-    // 1. Check if thumbnail already exists in cache
-    // 2. Generate thumbnail if needed
-    // 3. Return base64 encoded thumbnail or file path
+    let db_clone = db.inner().clone();
+    let updated = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let changed = db_instance
+            .update_user_rule(id, &rule)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if changed == 0 {
+            return Err("ERR_NOT_FOUND: Rule not found".to_string());
+        }
+        db_instance
+            .get_user_rule_by_id(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_NOT_FOUND: Rule not found".to_string())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
 
-    Ok("data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8/5+hHgAHggJ/PchI7wAAAABJRU5ErkJggg==".to_string())
+    gauge::mark_gauge_dirty();
+    let _ = app.emit("rules://changed", serde_json::json!({ "updated": id }));
+    Ok(updated)
 }
 
 #[tauri::command]
-pub async fn get_prefs(db: State<'_, DbPool>) -> Result<UserPrefs, String> {
-    // Get preferences from database using spawn_blocking
+pub async fn list_user_rules(db: State<'_, DbPool>) -> Result<Vec<UserRule>, String> {
     let db_clone = db.inner().clone();
-    let prefs = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
         let db_instance = Database::new(conn);
         db_instance
-            .get_all_preferences()
+            .list_user_rules()
             .map_err(|e| format!("ERR_DATABASE: {}", e))
     })
     .await
-    .map_err(|e| format!("join error: {e}"))??;
-
-    // Convert to UserPrefs struct
-    Ok(UserPrefs {
-        dry_run_default: prefs
-            .get("dry_run_default")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(true),
-        tidy_day: prefs
-            .get("tidy_day")
-            .cloned()
-            .unwrap_or_else(|| "Fri".to_string()),
-        tidy_hour: prefs
-            .get("tidy_hour")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(17),
-        rolling_window_days: prefs
-            .get("rolling_window_days")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(7),
-        max_candidates_per_day: prefs
-            .get("max_candidates_per_day")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(12),
-        thumbnail_max_size: prefs
-            .get("thumbnail_max_size")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(256),
-        auto_scan_enabled: prefs
-            .get("auto_scan_enabled")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(false),
-        scan_interval_hours: prefs
-            .get("scan_interval_hours")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(24),
-        archive_age_threshold_days: prefs
-            .get("archive_age_threshold_days")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(7),
-        delete_age_threshold_days: prefs
-            .get("delete_age_threshold_days")
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(30),
-    })
+    .map_err(|e| format!("join error: {e}"))?
 }
 
 #[tauri::command]
-pub async fn set_prefs(prefs: PartialUserPrefs, db: State<'_, DbPool>) -> Result<(), String> {
-    // Validate input
-    if let Some(tidy_hour) = prefs.tidy_hour {
-        if tidy_hour > 23 {
-            return Err("ERR_VALIDATION: tidy_hour must be 0-23".to_string());
-        }
+pub async fn remove_user_rule(id: i64, app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
+    if id <= 0 {
+        return Err("ERR_VALIDATION: Invalid rule id".to_string());
     }
 
-    if let Some(rolling_window_days) = prefs.rolling_window_days {
-        if rolling_window_days <= 0 || rolling_window_days > 365 {
-            return Err("ERR_VALIDATION: rolling_window_days must be 1-365".to_string());
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let removed = db_instance
+            .delete_user_rule(id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if removed == 0 {
+            return Err("ERR_NOT_FOUND: Rule not found".to_string());
         }
-    }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
 
-    if let Some(max_candidates_per_day) = prefs.max_candidates_per_day {
-        if max_candidates_per_day == 0 || max_candidates_per_day > 1000 {
-            return Err("ERR_VALIDATION: max_candidates_per_day must be 1-1000".to_string());
-        }
-    }
+    gauge::mark_gauge_dirty();
+    let _ = app.emit("rules://changed", serde_json::json!({ "removed": id }));
+    Ok(())
+}
 
-    if let Some(thumbnail_max_size) = prefs.thumbnail_max_size {
-        if thumbnail_max_size == 0 || thumbnail_max_size > 2048 {
-            return Err("ERR_VALIDATION: thumbnail_max_size must be 1-2048".to_string());
-        }
+#[tauri::command]
+pub fn get_platform_info() -> PlatformInfo {
+    #[cfg(target_os = "windows")]
+    {
+        return PlatformInfo {
+            os: "windows".to_string(),
+            open_label: "Open in File Explorer".to_string(),
+        };
     }
 
-    if let Some(scan_interval_hours) = prefs.scan_interval_hours {
-        if scan_interval_hours == 0 || scan_interval_hours > 168 {
-            return Err("ERR_VALIDATION: scan_interval_hours must be 1-168".to_string());
-        }
+    #[cfg(target_os = "macos")]
+    {
+        return PlatformInfo {
+            os: "macos".to_string(),
+            open_label: "Open in Finder".to_string(),
+        };
     }
 
-    if let Some(archive_age_threshold_days) = prefs.archive_age_threshold_days {
-        if archive_age_threshold_days > 365 {
-            return Err("ERR_VALIDATION: archive_age_threshold_days must be 0-365".to_string());
-        }
+    #[cfg(target_os = "linux")]
+    {
+        return PlatformInfo {
+            os: "linux".to_string(),
+            open_label: "Open in File Manager".to_string(),
+        };
     }
 
-    if let Some(delete_age_threshold_days) = prefs.delete_age_threshold_days {
-        if delete_age_threshold_days > 365 {
-            return Err("ERR_VALIDATION: delete_age_threshold_days must be 0-365".to_string());
-        }
+    #[allow(unreachable_code)]
+    PlatformInfo {
+        os: std::env::consts::OS.to_string(),
+        open_label: "Open in File Manager".to_string(),
+    }
+}
+
+#[tauri::command]
+pub async fn list_dir(
+    root_path: String,
+    db: State<'_, DbPool>,
+) -> Result<Vec<DirectoryEntry>, String> {
+    if root_path.trim().is_empty() {
+        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+    }
+
+    let normalized =
+        normalize_directory_path(Path::new(&root_path)).map_err(command_error_to_string)?;
+    let path_for_listing = normalized.clone();
+
+    let db_clone = db.inner().clone();
+    let watched_roots = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .list_watched_roots()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    ensure_within_watched(&normalized, &watched_roots).map_err(command_error_to_string)?;
+
+    let entries = tokio::task::spawn_blocking(move || {
+        list_directory_entries(&path_for_listing).map_err(command_error_to_string)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn open_in_system(
+    path: String,
+    reveal: Option<bool>,
+    db: State<'_, DbPool>,
+) -> Result<(), String> {
+    if path.trim().is_empty() {
+        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let normalized =
+            normalize_existing_path(Path::new(&path)).map_err(command_error_to_string)?;
+        let metadata = fs::metadata(&normalized)
+            .map_err(|err| map_io_error("access path", &normalized, err))
+            .map_err(command_error_to_string)?;
+        let check_path = if metadata.is_dir() {
+            normalized.clone()
+        } else {
+            normalized
+                .parent()
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| normalized.clone())
+        };
+        let is_file = metadata.is_file();
+
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let roots = db_instance
+            .list_watched_roots()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+
+        ensure_within_watched(&check_path, &roots).map_err(command_error_to_string)?;
+
+        let reveal_flag = reveal.unwrap_or(is_file);
+        open_path_with_system(&normalized, reveal_flag).map_err(command_error_to_string)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(())
+}
+
+/// Launches the default application for an indexed file and records the
+/// open, so the selector counts it as recent activity.
+#[tauri::command]
+pub async fn open_file(file_id: i64, db: State<'_, DbPool>) -> Result<(), String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let file = db_instance
+            .get_file_by_id(file_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .filter(|file| !file.is_deleted)
+            .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+
+        let path = PathBuf::from(&file.path);
+        let metadata = fs::metadata(&path)
+            .map_err(|err| map_io_error("access path", &path, err))
+            .map_err(command_error_to_string)?;
+        if !metadata.is_file() {
+            return Err(format!("ERR_VALIDATION: {} is not a file", file.path));
+        }
+        let roots = db_instance
+            .list_watched_roots()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let parent = path.parent().unwrap_or(&path).to_path_buf();
+        ensure_within_watched(&parent, &roots).map_err(command_error_to_string)?;
+
+        launch_default_app(&path).map_err(command_error_to_string)?;
+        db_instance
+            .record_file_opened(file_id, Utc::now())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn start_scan(
+    paths: Option<Vec<String>>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<(), String> {
+    let provided = paths.unwrap_or_default();
+    let db_clone = db.inner().clone();
+    let roots = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        if provided.is_empty() {
+            db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))
+        } else {
+            Ok(provided)
+        }
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if roots.is_empty() {
+        return Err("ERR_VALIDATION: No scan roots configured".to_string());
+    }
+
+    let mut unique = HashSet::new();
+    let mut sanitized = Vec::new();
+    for root in roots {
+        validate_scan_path(&root).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+        if !Path::new(&root).is_dir() {
+            return Err(format!("ERR_VALIDATION: Path is not a directory: {}", root));
+        }
+        let clean = sanitize_string(&root);
+        if unique.insert(clean.clone()) {
+            sanitized.push(clean);
+        }
+    }
+
+    scanner::start_scan(app, db.inner().clone(), sanitized)
+        .map_err(|e| format!("ERR_SCAN: {e}"))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn rescan_all(app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<(), String> {
+    let db_clone = db.inner().clone();
+    let roots = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if roots.is_empty() {
+        return Err("ERR_VALIDATION: No scan roots configured".to_string());
+    }
+
+    scanner::start_scan(app, db.inner().clone(), roots).map_err(|e| format!("ERR_SCAN: {e}"))?;
+    Ok(())
+}
+
+/// Rescans a watched root or any folder inside one. Only that subtree is
+/// walked, and only files under it are reconciled as missing.
+#[tauri::command]
+pub async fn rescan_folder(
+    path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<(), String> {
+    if path.trim().is_empty() {
+        return Err("ERR_VALIDATION: Path cannot be empty".to_string());
+    }
+
+    let normalized = normalize_directory_path(Path::new(&path)).map_err(command_error_to_string)?;
+    let target = canonicalize_or_clone(&normalized);
+
+    // Ensure it's a watched root or lies inside one
+    let db_clone = db.inner().clone();
+    let watched = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    let Some((stored_root, root)) = watched
+        .iter()
+        .map(|p| (p, canonicalize_or_clone(Path::new(p))))
+        .find(|(_, root)| target.starts_with(root))
+    else {
+        return Err("ERR_PERMISSION: Path is not inside a watched root".to_string());
+    };
+
+    // A folder the scanner would never descend into cannot be refreshed either.
+    let walker = FileWalker::new();
+    if target
+        .ancestors()
+        .take_while(|ancestor| *ancestor != root.as_path())
+        .any(|ancestor| walker.should_skip_dir(ancestor))
+    {
+        return Err("ERR_VALIDATION: Path is inside a skipped folder".to_string());
+    }
+
+    // Indexed paths are spelled from the stored root, so the subtree must be too.
+    let relative = target.strip_prefix(&root).unwrap_or(Path::new(""));
+    let subtree = Path::new(stored_root).join(relative);
+    let subtree = subtree.to_string_lossy().trim_end_matches(['/', '\\']).to_string();
+    scanner::start_scan(app, db.inner().clone(), vec![subtree])
+        .map_err(|e| format!("ERR_SCAN: {e}"))?;
+    Ok(())
+}
+#[tauri::command]
+pub fn scan_status() -> Result<scanner::ScanStatusPayload, String> {
+    Ok(scanner::current_status())
+}
+
+/// Recent scans, newest first. `limit` defaults to 50.
+#[tauri::command]
+pub async fn scan_history(
+    limit: Option<usize>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<ScanRecord>, String> {
+    let limit = limit.unwrap_or(50).clamp(1, scanner::SCAN_HISTORY_MAX_ROWS);
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .list_scan_records(limit)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn cancel_scan(db: State<'_, DbPool>) -> Result<bool, String> {
+    Ok(scanner::cancel_scan(db.inner()))
+}
+
+#[tauri::command]
+pub fn pause_scan() -> Result<bool, String> {
+    Ok(scanner::pause_scan())
+}
+
+#[tauri::command]
+pub async fn resume_scan(app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<bool, String> {
+    scanner::resume_scan(app, db.inner().clone()).map_err(|e| format!("ERR_SCAN: {e}"))
+}
+
+#[tauri::command]
+pub async fn get_candidates(
+    max_total: usize,
+    db: State<'_, DbPool>,
+) -> Result<Vec<Candidate>, String> {
+    daily_candidates(max_total, db).await
+}
+
+pub(crate) fn filter_candidates_by_root_path(
+    candidates: &mut Vec<Candidate>,
+    root_path: &str,
+    errors: &mut Vec<String>,
+) {
+    let root_path_buf = PathBuf::from(root_path);
+    let root_path = if let Ok(canonical) = root_path_buf.canonicalize() {
+        canonical
+    } else {
+        errors.push(format!(
+            "Warning: Could not canonicalize path: {}",
+            root_path_buf.display()
+        ));
+        root_path_buf
+    };
+
+    let root_path_str = root_path.to_string_lossy().to_string();
+
+    candidates.retain(|candidate| {
+        let candidate_path = Path::new(&candidate.path);
+        if let Ok(canon_candidate) = candidate_path.canonicalize() {
+            let canon_str = canon_candidate.to_string_lossy().to_string();
+            canon_str.starts_with(&root_path_str)
+        } else {
+            candidate.path.starts_with(&root_path_str)
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_candidates_bucketed(
+    params: Option<GetCandidatesBucketedParams>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<CandidatesResponse, String> {
+    let params = params.unwrap_or(GetCandidatesBucketedParams {
+        root_path: None,
+        buckets: None,
+        limit: Some(100),
+        offset: Some(0),
+        sort: Some("size_desc".to_string()),
+        min_confidence: None,
+        max_results_per_bucket: None,
+        include_archived: None,
+        include_deleted: None,
+        bucket_limit: None,
+        bucket_offsets: None,
+        cursor: None,
+    });
+
+    let limit = params.limit.unwrap_or(100).min(1000);
+    let offset = params.offset.unwrap_or(0);
+    if limit == 0 {
+        return Err("ERR_VALIDATION: limit must be > 0".to_string());
+    }
+    let bucket_limit = params.bucket_limit.map(|l| l.min(1000));
+    if bucket_limit == Some(0) {
+        return Err("ERR_VALIDATION: bucket_limit must be > 0".to_string());
+    }
+    if let Some(min) = params.min_confidence {
+        if !(0.0..=1.0).contains(&min) {
+            return Err("ERR_VALIDATION: min_confidence must be between 0 and 1".to_string());
+        }
+    }
+    if params.max_results_per_bucket == Some(0) {
+        return Err("ERR_VALIDATION: max_results_per_bucket must be > 0".to_string());
+    }
+    let sort = candidate_sort(params.sort.as_deref());
+    let after = match params.cursor.as_deref() {
+        Some(token) => {
+            let cursor = PageCursor::decode(token).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+            if cursor.sort != sort {
+                return Err("ERR_VALIDATION: cursor was issued for another sort".to_string());
+            }
+            Some(cursor)
+        }
+        None => None,
+    };
+    let snapshot_ms = after
+        .as_ref()
+        .map_or_else(|| Utc::now().timestamp_millis(), |c| c.snapshot_ms);
+    let snapshot = after.as_ref().map(PageCursor::snapshot);
+
+    let db_clone = db.inner().clone();
+    let scope_root = params.root_path.clone();
+    let min_confidence = params.min_confidence.unwrap_or(0.0);
+    let max_per_bucket = params.max_results_per_bucket;
+    let include_staged = params.include_archived.unwrap_or(false);
+    let include_deleted = params.include_deleted.unwrap_or(false);
+    let (mut candidates, mut errors) = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut selector = FileSelector::from_db(&db_instance);
+        selector.set_min_confidence(min_confidence);
+        selector.set_max_per_bucket(max_per_bucket);
+        selector.set_include_staged(include_staged);
+        selector.set_include_deleted(include_deleted);
+        let mut items = score_candidates(&db_instance, &selector, scope_root)?;
+        // Files that turned up after the first page wait for a fresh listing.
+        if let Some(snapshot) = snapshot {
+            let newer = db_instance
+                .file_ids_first_seen_after(&snapshot)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            items.retain(|c| !newer.contains(&c.file_id));
+        }
+        Ok::<(Vec<Candidate>, Vec<String>), String>((items.drain(..).collect(), Vec::new()))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    // Filter by root path if provided
+    if let Some(root_path) = params.root_path.as_deref() {
+        filter_candidates_by_root_path(&mut candidates, root_path, &mut errors);
+    }
+
+    // Filter by requested buckets if provided
+    let requested_buckets: std::collections::HashSet<String> = params
+        .buckets
+        .as_ref()
+        .map(|buckets| buckets.iter().map(|s| bucket_key(s)).collect())
+        .unwrap_or_default();
+
+    if !requested_buckets.is_empty() {
+        candidates.retain(|c| requested_buckets.contains(&bucket_key(&c.reason)));
+    }
+
+    // Sort into a total order (ties break on file id) so a cursor resumes exactly
+    candidates.sort_by(|a, b| {
+        compare_cursors(&candidate_cursor(a, sort, 0), &candidate_cursor(b, sort, 0))
+    });
+
+    // Recompute total_count AFTER filtering and sorting
+    let total_count = candidates.len();
+
+    // Summaries cover every filtered candidate, not just the page sent back
+    let summaries_acc = summarize_buckets(&candidates);
+
+    let mut by_bucket: std::collections::HashMap<String, Vec<UiCandidate>> =
+        std::collections::HashMap::new();
+    let mut bucket_paging: std::collections::HashMap<String, Paging> =
+        std::collections::HashMap::new();
+    let mut next_cursor = None;
+    let has_more = match bucket_limit {
+        Some(bucket_limit) => {
+            let offsets = params.bucket_offsets.clone().unwrap_or_default();
+            (by_bucket, bucket_paging) = page_buckets(&candidates, bucket_limit, &offsets);
+            bucket_paging.values().any(|p| p.has_more)
+        }
+        None => {
+            let start = match &after {
+                Some(cursor) => candidates.partition_point(|c| {
+                    compare_cursors(&candidate_cursor(c, sort, 0), cursor).is_le()
+                }),
+                None => offset,
+            };
+            let slice_end = (start + limit).min(total_count);
+            if start < total_count {
+                for c in &candidates[start..slice_end] {
+                    let key = bucket_key(&c.reason);
+                    by_bucket.entry(key.clone()).or_default().push(ui_candidate(c, key));
+                }
+            }
+            let has_more = slice_end < total_count;
+            if has_more && slice_end > 0 {
+                let last = &candidates[slice_end - 1];
+                next_cursor = Some(candidate_cursor(last, sort, snapshot_ms).encode());
+            }
+            has_more
+        }
+    };
+
+    // Nothing indexed under the watched roots yet (first run, or the first
+    // scan still underway): report a pending listing and make sure a scan is
+    // on its way rather than guessing from the disk.
+    let mut pending = false;
+    if total_count == 0 && params.root_path.is_none() {
+        let db_clone = db.inner().clone();
+        let unindexed_roots = tokio::task::spawn_blocking(move || {
+            let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+            let db_instance = Database::new(conn);
+            let roots = db_instance
+                .list_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            for root in &roots {
+                let indexed = db_instance
+                    .count_active_files_under(root)
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+                if indexed > 0 {
+                    return Ok(Vec::new());
+                }
+            }
+            Ok::<Vec<String>, String>(roots)
+        })
+        .await
+        .map_err(|e| format!("join error: {e}"))??;
+
+        if !unindexed_roots.is_empty() {
+            pending = true;
+            if scanner::current_status().state == "idle" {
+                if let Err(e) = scanner::start_scan(app, db.inner().clone(), unindexed_roots) {
+                    pending = false;
+                    errors.push(format!("ERR_SCAN: {e}"));
+                }
+            }
+        }
+    }
+
+    let summaries = summaries_acc
+        .into_iter()
+        .map(|(k, (count, bytes))| BucketSummary {
+            key: k,
+            count,
+            total_bytes: bytes,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(CandidatesResponse {
+        by_bucket,
+        summaries,
+        total_count,
+        paging: Paging {
+            limit,
+            offset,
+            has_more: has_more,
+            next_cursor,
+        },
+        bucket_paging,
+        pending,
+        errors,
+    })
+}
+
+/// Count and bytes per built-in bucket straight from SQL aggregates, cheap
+/// enough to refresh the Home chips after every scan. A `root_path` narrows
+/// the counts when it lies inside a watched root.
+#[tauri::command]
+pub async fn get_bucket_stats(
+    root_path: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<BucketSummary>, String> {
+    let db_clone = db.inner().clone();
+    let stats = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut selector = FileSelector::from_db(&db_instance);
+        if let Some(root) = root_path {
+            let watched = db_instance
+                .list_online_watched_paths()
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            let inside = watched.iter().any(|w| Path::new(&root).starts_with(w));
+            selector.set_roots(if inside { vec![root] } else { Vec::new() });
+        }
+        selector
+            .get_bucket_stats(&db_instance)
+            .map_err(|e| format!("ERR_SELECTOR: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    let mut summaries: Vec<BucketSummary> = stats
+        .into_iter()
+        .map(|(key, (count, total_bytes))| BucketSummary {
+            key,
+            count,
+            total_bytes,
+        })
+        .collect();
+    summaries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(summaries)
+}
+
+/// Scores every candidate, not just a day's worth, so summaries and paging
+/// don't shift from page to page. A `scope_root` only narrows the pool when
+/// it lies inside a watched root.
+fn score_candidates(
+    db: &Database,
+    selector: &FileSelector,
+    scope_root: Option<String>,
+) -> Result<Vec<Candidate>, String> {
+    let watched = db
+        .list_watched_paths()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let roots = match scope_root {
+        Some(root) if watched.iter().any(|w| Path::new(&root).starts_with(w)) => vec![root],
+        Some(_) => Vec::new(),
+        None => watched,
+    };
+    selector
+        .daily_candidates_under_roots(&roots, Some(usize::MAX), db)
+        .map_err(|e| format!("ERR_SELECTOR: {}", e))
+}
+
+/// Ids of the files in `bucket` still on disk, best first, capped at one
+/// batch, along with how many the cap held back. Protected and dismissed
+/// files never reach a bucket in the first place.
+pub(crate) fn bucket_file_ids(
+    db: &Database,
+    bucket: &str,
+    filters: &StageBucketFilters,
+) -> Result<(Vec<i64>, usize), String> {
+    let mut selector = FileSelector::from_db(db);
+    selector.set_min_confidence(filters.min_confidence.unwrap_or(0.0));
+    let mut candidates = score_candidates(db, &selector, filters.root_path.clone())?;
+    if let Some(root_path) = filters.root_path.as_deref() {
+        filter_candidates_by_root_path(&mut candidates, root_path, &mut Vec::new());
+    }
+
+    let key = bucket_key(bucket);
+    let mut file_ids: Vec<i64> = candidates
+        .into_iter()
+        .filter(|c| bucket_key(&c.reason) == key)
+        .filter(|c| c.file_id > 0 && Path::new(&c.path).exists())
+        .map(|c| c.file_id)
+        .collect();
+    let remaining = file_ids.len().saturating_sub(MAX_FILES_PER_BATCH);
+    file_ids.truncate(MAX_FILES_PER_BATCH);
+    Ok((file_ids, remaining))
+}
+
+/// Stages a whole bucket as one batch, resolving its files here so the UI
+/// doesn't have to page through it and send every id back.
+#[tauri::command]
+pub async fn stage_bucket(
+    bucket_key: String,
+    filters: Option<StageBucketFilters>,
+    options: Option<StageOptions>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<StageOutcome, String> {
+    let bucket_key = bucket_key.trim().to_string();
+    if bucket_key.is_empty() {
+        return Err("ERR_VALIDATION: bucket_key cannot be empty".to_string());
+    }
+    let filters = filters.unwrap_or_default();
+    if let Some(min) = filters.min_confidence {
+        if !(0.0..=1.0).contains(&min) {
+            return Err("ERR_VALIDATION: min_confidence must be between 0 and 1".to_string());
+        }
+    }
+
+    let db_clone = db.inner().clone();
+    let key = bucket_key.clone();
+    let (file_ids, remaining) = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        bucket_file_ids(&Database::new(conn), &key, &filters)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+    if file_ids.is_empty() {
+        return Err(format!("ERR_NOT_FOUND: Nothing to stage in {}", bucket_key));
+    }
+
+    let mut outcome = stage_files(file_ids, with_origin(options, "bucket"), None, app, db).await?;
+    outcome.remaining = remaining;
+    Ok(outcome)
+}
+
+/// Sort orders `get_candidates_bucketed` accepts; anything else keeps the
+/// selector's score order.
+fn candidate_sort(sort: Option<&str>) -> &'static str {
+    match sort {
+        Some("size_desc") => "size_desc",
+        Some("age_desc") => "age_desc",
+        Some("name_asc") => "name_asc",
+        _ => "score",
+    }
+}
+
+/// Where `c` sits in the candidate list ordered by `sort`.
+pub(crate) fn candidate_cursor(c: &Candidate, sort: &str, snapshot_ms: i64) -> PageCursor {
+    let (key, text) = match sort {
+        "size_desc" => (Some(c.size_bytes as f64), None),
+        "age_desc" => (Some(c.age_days), None),
+        "name_asc" => (None, Some(c.path.to_lowercase())),
+        _ => (Some(c.score), None),
+    };
+    PageCursor {
+        sort: sort.to_string(),
+        key,
+        text,
+        id: c.file_id,
+        snapshot_ms,
+    }
+}
+
+/// Names ascend, every other key descends; ties break on file id.
+pub(crate) fn compare_cursors(a: &PageCursor, b: &PageCursor) -> std::cmp::Ordering {
+    let by_key = if a.sort == "name_asc" {
+        a.text.cmp(&b.text)
+    } else {
+        let (a_key, b_key) = (a.key.unwrap_or(0.0), b.key.unwrap_or(0.0));
+        b_key.partial_cmp(&a_key).unwrap_or(std::cmp::Ordering::Equal)
+    };
+    by_key.then(a.id.cmp(&b.id))
+}
+
+/// Count and reclaimable bytes per bucket; cloud placeholders free nothing.
+pub(crate) fn summarize_buckets(
+    candidates: &[Candidate],
+) -> std::collections::HashMap<String, (usize, u64)> {
+    let mut acc: std::collections::HashMap<String, (usize, u64)> =
+        std::collections::HashMap::new();
+    for c in candidates {
+        let e = acc.entry(bucket_key(&c.reason)).or_insert((0, 0));
+        e.0 += 1;
+        if !c.is_cloud_placeholder {
+            e.1 += c.size_bytes;
+        }
+    }
+    acc
+}
+
+/// Pages each bucket on its own, so the UI can load more of one bucket
+/// without refetching the others. Candidates keep their sorted order.
+pub(crate) fn page_buckets(
+    candidates: &[Candidate],
+    bucket_limit: usize,
+    offsets: &std::collections::HashMap<String, usize>,
+) -> (
+    std::collections::HashMap<String, Vec<UiCandidate>>,
+    std::collections::HashMap<String, Paging>,
+) {
+    let mut per_bucket: std::collections::HashMap<String, Vec<&Candidate>> =
+        std::collections::HashMap::new();
+    for c in candidates {
+        per_bucket.entry(bucket_key(&c.reason)).or_default().push(c);
+    }
+
+    let mut by_bucket = std::collections::HashMap::new();
+    let mut paging = std::collections::HashMap::new();
+    for (key, items) in per_bucket {
+        let offset = offsets.get(&key).copied().unwrap_or(0);
+        let page: Vec<UiCandidate> = items
+            .iter()
+            .skip(offset)
+            .take(bucket_limit)
+            .map(|c| ui_candidate(c, key.clone()))
+            .collect();
+        paging.insert(
+            key.clone(),
+            Paging {
+                limit: bucket_limit,
+                offset,
+                has_more: offset + page.len() < items.len(),
+                next_cursor: None,
+            },
+        );
+        by_bucket.insert(key, page);
+    }
+    (by_bucket, paging)
+}
+
+fn ui_candidate(c: &Candidate, key: String) -> UiCandidate {
+    UiCandidate {
+        id: c.file_id,
+        path: c.path.clone(),
+        parent: c.parent_dir.clone(),
+        size: c.size_bytes,
+        mime: None,
+        created_at: None,
+        modified_at: None,
+        accessed_at: None,
+        partial_sha1: None,
+        sha1: None,
+        reason: key,
+        reason_message: c.reason_message.clone(),
+        group_key: None,
+        is_cloud_placeholder: c.is_cloud_placeholder,
+        confidence: c.confidence,
+        factors: Some(c.factors.clone()),
+        explanation: Some(c.explanation.clone()),
+        hint_messages: c.hint_messages.clone(),
+        explanation_messages: Some(c.explanation_messages.clone()),
+    }
+}
+
+#[tauri::command]
+pub async fn scan_roots(
+    roots: Vec<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<ScanResult, String> {
+    tracing::debug!("scan_roots called with roots: {:?}", roots);
+    let sanitized_roots = validate_scan_roots(&roots)?;
+
+    let db_clone = db.inner().clone();
+    let app_handle = app.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut scanner = Scanner::new();
+        scanner
+            .run_scan(&app_handle, sanitized_roots, &db_instance)
+            .map_err(|e| format!("ERR_SCAN: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn daily_candidates(
+    max_total: usize,
+    db: State<'_, DbPool>,
+) -> Result<Vec<Candidate>, String> {
+    tracing::debug!("daily_candidates called with max_total: {}", max_total);
+
+    // Validate input
+    if max_total == 0 {
+        return Err("ERR_VALIDATION: max_total must be greater than 0".to_string());
+    }
+
+    if max_total > 1000 {
+        return Err("ERR_VALIDATION: max_total too large (max 1000)".to_string());
+    }
+
+    // Get candidates using spawn_blocking for database operations
+    let db_clone = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let selector = FileSelector::from_db(&db_instance);
+        selector
+            .daily_candidates(Some(max_total), &db_instance)
+            .map_err(|e| format!("ERR_SELECTOR: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn gauge_state(db: State<'_, DbPool>) -> Result<GaugeState, String> {
+    tracing::debug!("gauge_state called");
+    let db_clone = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        gauge::cached_gauge_state(&db_instance).map_err(|e| format!("ERR_GAUGE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(result)
+}
+
+/// Per-bucket and per-root parts of the gauge, computed fresh rather than
+/// cached since the UI only asks for it when the breakdown is opened.
+#[tauri::command]
+pub async fn gauge_breakdown(db: State<'_, DbPool>) -> Result<GaugeBreakdown, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut gauge_manager = GaugeManager::new();
+        gauge_manager.set_selector(FileSelector::from_db(&db_instance));
+        gauge_manager
+            .gauge_breakdown(&db_instance)
+            .map_err(|e| format!("ERR_GAUGE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn get_selector_config(db: State<'_, DbPool>) -> Result<SelectorConfig, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        Ok(SelectorConfig::load(&db_instance))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_selector_config(
+    config: SelectorConfig,
+    db: State<'_, DbPool>,
+) -> Result<SelectorConfig, String> {
+    config
+        .validate()
+        .map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+
+    let db_clone = db.inner().clone();
+    let saved = tokio::task::spawn_blocking(move || -> Result<SelectorConfig, String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        config
+            .save(&db_instance)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        Ok(config)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::mark_gauge_dirty();
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn list_staged(
+    statuses: Option<Vec<String>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<StagedFile>, String> {
+    let status_filter = statuses.map(|items| {
+        items
+            .into_iter()
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>()
+    });
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let pairs = match status_filter {
+            Some(filters) => db_instance
+                .list_staged_with_files(Some(filters.as_slice()))
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?,
+            None => db_instance
+                .list_staged_with_files(None)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?,
+        };
+        let mut results = Vec::with_capacity(pairs.len());
+        for (record, file) in pairs {
+            results.push(staged_payload(&record, &file));
+        }
+        Ok(results)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn stage_files(
+    file_ids: Vec<i64>,
+    options: Option<StageOptions>,
+    idempotency_key: Option<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<StageOutcome, String> {
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+    if file_ids.is_empty() {
+        return Err("ERR_VALIDATION: No file IDs provided".to_string());
+    }
+    validate_idempotency_key(idempotency_key.as_deref())
+        .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+
+    let mut opts = options.unwrap_or_default();
+    let mut cooloff_days = opts.cooloff_days.take().unwrap_or(7);
+    if cooloff_days < 0 {
+        cooloff_days = 0;
+    }
+    if cooloff_days > 30 {
+        cooloff_days = 30;
+    }
+    let note = sanitize_note(opts.note.take());
+    let label = sanitize_note(opts.label.take());
+    let origin = opts.origin.take().unwrap_or_else(|| "stage".to_string());
+    let db_clone = db.inner().clone();
+    let outcome = tokio::task::spawn_blocking(move || -> Result<StageOutcome, String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let key = idempotency_key.as_deref();
+        let batch_id = |outcome: &StageOutcome| outcome.batch_id.clone();
+        run_idempotent(&db_instance, key, "stage_files", batch_id, || {
+            let _lock = lock_files("stage", &file_ids)?;
+            let mut archive_manager = ArchiveManager::new();
+            archive_manager.set_idempotency_key(idempotency_key.clone());
+            archive_manager.set_batch_origin(&origin);
+            archive_manager.set_batch_label(label.clone());
+            archive_manager.update_config(
+                ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_ARCHIVE: {e}"))?,
+            );
+
+            let protected = load_protected_paths(&db_instance)?;
+            let mut unique_ids = HashSet::new();
+            let mut file_paths = Vec::new();
+            for file_id in &file_ids {
+                if !unique_ids.insert(*file_id) {
+                    continue;
+                }
+                let file = db_instance
+                    .get_file_by_id(*file_id)
+                    .map_err(|e| format!("ERR_DATABASE: {e}"))?
+                    .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+                if file.is_deleted {
+                    return Err(format!(
+                        "ERR_VALIDATION: File with ID {} has been deleted",
+                        file_id
+                    ));
+                }
+                ensure_not_protected(&file.path, &protected)?;
+                let file_path = Path::new(&file.path);
+                if !file_path.exists() {
+                    return Err(format!(
+                        "ERR_NOT_FOUND: File with ID {} not found on disk",
+                        file_id
+                    ));
+                }
+                file_paths.push(file.path.clone());
+            }
+
+            if file_paths.is_empty() {
+                return Err("ERR_VALIDATION: No unique file paths to stage".to_string());
+            }
+            ensure_archive_space(&archive_manager, &file_paths)?;
+
+            let expires_at_dt = if cooloff_days > 0 {
+                Some(Utc::now() + Duration::days(cooloff_days))
+            } else {
+                None
+            };
+            archive_manager.set_staging(expires_at_dt, note.clone());
+
+            let archive_result = archive_manager
+                .archive_files(file_paths, &db_instance)
+                .map_err(|e| format!("ERR_ARCHIVE: {e}"))?;
+            let staged_count = archive_result.files_archived;
+
+            let outcome = StageOutcome {
+                success: archive_result.errors.is_empty(),
+                batch_id: if staged_count == 0 {
+                    None
+                } else {
+                    Some(archive_result.batch_id.clone())
+                },
+                staged_files: staged_count,
+                total_bytes: archive_result.total_bytes,
+                duration_ms: archive_result.duration_ms,
+                errors: archive_result.errors,
+                expires_at: expires_at_dt.map(|dt| dt.to_rfc3339()),
+                note,
+                remaining: 0,
+            };
+
+            Ok(outcome)
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::spawn_gauge_refresh(&app);
+    Ok(outcome)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicateResolution {
+    pub success: bool,
+    pub hash: String,
+    pub mode: String,
+    pub kept_file_id: i64,
+    pub batch_id: String,
+    pub files_processed: usize,
+    pub total_bytes: u64,
+    pub duration_ms: u64,
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Keeps one copy of a duplicate group and stages or trashes the others as a
+/// single batch. Every member is re-hashed first so nothing is removed on the
+/// strength of a stale index entry.
+#[tauri::command]
+pub async fn resolve_duplicate_group(
+    hash: String,
+    keep_file_id: i64,
+    mode: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<DuplicateResolution, String> {
+    let hash = hash.trim().to_lowercase();
+    if hash.is_empty() {
+        return Err("ERR_VALIDATION: hash cannot be empty".to_string());
+    }
+    let mode = mode.trim().to_lowercase();
+    if mode != "stage" && mode != "delete" {
+        return Err(format!(
+            "ERR_VALIDATION: Unknown mode {}, expected stage or delete",
+            mode
+        ));
+    }
+
+    let db_clone = db.inner().clone();
+    let progress = ops_progress_emitter(&app);
+    let resolution = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+
+        let members = db_instance
+            .files_with_sha1(&hash)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        if members.len() < 2 {
+            return Err(format!("ERR_NOT_FOUND: No duplicate group for hash {}", hash));
+        }
+        if !members.iter().any(|f| f.id == Some(keep_file_id)) {
+            return Err(format!(
+                "ERR_VALIDATION: File with ID {} is not in the duplicate group",
+                keep_file_id
+            ));
+        }
+        // The kept copy is held too, so nothing stages or deletes it meanwhile.
+        let member_ids: Vec<i64> = members.iter().filter_map(|f| f.id).collect();
+        let _lock = lock_files("dedupe", &member_ids)?;
+
+        let protected = load_protected_paths(&db_instance)?;
+        let mut file_paths = Vec::new();
+        for file in &members {
+            let file_id = file.id.unwrap_or(0);
+            let on_disk = scanner::hash::hash_full_with(Path::new(&file.path), file.hash_algo)
+                .map_err(|_| {
+                    format!("ERR_NOT_FOUND: File with ID {} not found on disk", file_id)
+                })?;
+            if on_disk != hash {
+                return Err(format!(
+                    "ERR_VALIDATION: File with ID {} changed since it was indexed",
+                    file_id
+                ));
+            }
+            // Copies already staged are on their way out.
+            if file_id == keep_file_id || file.is_staged {
+                continue;
+            }
+            validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+            ensure_not_protected(&file.path, &protected)?;
+            file_paths.push(file.path.clone());
+        }
+        if file_paths.is_empty() {
+            return Err("ERR_VALIDATION: No other copies left to resolve".to_string());
+        }
+
+        let note = Some(format!("duplicate_of:{}", keep_file_id));
+        let resolution = if mode == "stage" {
+            let mut archive_manager = ArchiveManager::new();
+            archive_manager.update_config(
+                ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_ARCHIVE: {e}"))?,
+            );
+            archive_manager.set_progress_callback(progress);
+            archive_manager.set_action_note(note.clone());
+            archive_manager.set_batch_origin("dedupe");
+            archive_manager.set_staging(Some(Utc::now() + Duration::days(7)), note);
+            ensure_archive_space(&archive_manager, &file_paths)?;
+            let result = archive_manager
+                .archive_files(file_paths, &db_instance)
+                .map_err(|e| format!("ERR_ARCHIVE: {e}"))?;
+            let staged = result.files_archived;
+            DuplicateResolution {
+                success: result.errors.is_empty(),
+                hash,
+                mode,
+                kept_file_id: keep_file_id,
+                batch_id: result.batch_id,
+                files_processed: staged,
+                total_bytes: result.total_bytes,
+                duration_ms: result.duration_ms,
+                errors: result.errors,
+                cancelled: result.cancelled,
+            }
+        } else {
+            let mut delete_manager = DeleteManager::new();
+            delete_manager.set_use_trash(true);
+            delete_manager.set_progress_callback(progress);
+            delete_manager.set_action_note(note);
+            delete_manager.set_batch_origin("dedupe");
+            let result = delete_manager
+                .delete_files(file_paths, &db_instance)
+                .map_err(|e| format!("ERR_DELETE: {e}"))?;
+            DuplicateResolution {
+                success: result.errors.is_empty(),
+                hash,
+                mode,
+                kept_file_id: keep_file_id,
+                batch_id: result.batch_id,
+                files_processed: result.files_deleted,
+                total_bytes: result.total_bytes_freed,
+                duration_ms: result.duration_ms,
+                errors: result.errors,
+                cancelled: result.cancelled,
+            }
+        };
+        Ok(resolution)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    let operation = if resolution.mode == "stage" {
+        "archive"
+    } else {
+        "delete"
+    };
+    emit_ops_done(
+        &app,
+        operation,
+        &resolution.batch_id,
+        resolution.files_processed,
+        resolution.total_bytes,
+        resolution.duration_ms,
+        &resolution.errors,
+        resolution.cancelled,
+    );
+
+    Ok(resolution)
+}
+
+#[tauri::command]
+pub async fn restore_staged(
+    batch_id: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<UndoResult, String> {
+    if batch_id.trim().is_empty() {
+        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<UndoResult, String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+
+        let actions = db_instance
+            .get_actions_by_batch_id(&batch_id)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        let archived_ids: Vec<i64> = actions
+            .iter()
+            .filter(|action| action.action == ActionType::Archive)
+            .map(|action| action.file_id)
+            .collect();
+
+        if archived_ids.is_empty() {
+            return Err(format!(
+                "ERR_NOT_FOUND: No archived files associated with batch {batch_id}"
+            ));
+        }
+        let _lock = lock_files("restore", &archived_ids)?;
+
+        // Lost copies have nothing to move back; restore the rest.
+        let lost: Vec<(i64, String)> = db_instance
+            .list_staged_with_files(Some(&[ops::expiry::LOST_STATUS.to_string()]))
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .into_iter()
+            .filter(|(record, _)| archived_ids.contains(&record.file_id))
+            .map(|(record, file)| (record.file_id, file.path))
+            .collect();
+        let restorable: Vec<i64> = archived_ids
+            .iter()
+            .copied()
+            .filter(|id| !lost.iter().any(|(lost_id, _)| lost_id == id))
+            .collect();
+        if restorable.is_empty() {
+            return Err(format!(
+                "ERR_NOT_FOUND: Every archived copy in batch {batch_id} is missing"
+            ));
+        }
+
+        let mut undo_manager = UndoManager::new();
+        let mut result = if lost.is_empty() {
+            undo_manager.undo_batch(&batch_id, &db_instance)
+        } else {
+            undo_manager.undo_files(&batch_id, &restorable, &db_instance)
+        }
+        .map_err(|e| format!("ERR_UNDO: {e}"))?;
+        // Each file was unstaged in the same transaction that moved it back.
+        result
+            .errors
+            .extend(lost.iter().map(|(_, path)| format!("{path}: archived copy is missing")));
+
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::spawn_gauge_refresh(&app);
+    Ok(result)
+}
+
+/// Checks every staged file's archived copy now rather than waiting for the
+/// next launch, marking missing ones `lost` and returning reappeared ones to
+/// staging.
+#[tauri::command]
+pub async fn reconcile_staged(
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<ops::expiry::StagedReconcilePayload, String> {
+    let db_clone = db.inner().clone();
+    let payload = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        ops::expiry::reconcile_staged(&db_instance, Utc::now())
+            .map_err(|e| format!("ERR_DATABASE: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if payload.changed() {
+        gauge::spawn_gauge_refresh(&app);
+    }
+    Ok(payload)
+}
+
+/// Deletes staged files. With `verify` (the default for permanent deletes)
+/// each archived copy is rehashed first; copies that no longer match the
+/// index are left staged and reported in `corrupted`.
+#[tauri::command]
+pub async fn empty_staged(
+    file_ids: Vec<i64>,
+    to_trash: bool,
+    verify: Option<bool>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<DeleteOutcome, String> {
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+    if file_ids.is_empty() {
+        return Err("ERR_VALIDATION: No file IDs provided".to_string());
+    }
+    let verify = verify.unwrap_or(!to_trash);
+
+    let db_clone = db.inner().clone();
+    let progress = ops_progress_emitter(&app);
+    let app_handle = app.clone();
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock_files("empty", &file_ids)?;
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+
+        let protected = load_protected_paths(&db_instance)?;
+        let mut files = Vec::new();
+        for file_id in &file_ids {
+            let file = db_instance
+                .get_file_by_id(*file_id)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?
+                .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+            validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+            ensure_not_protected(&file.path, &protected)?;
+            files.push(file);
+        }
+
+        let corrupted = if verify {
+            ops::verify::find_corrupted(&files)
+        } else {
+            Vec::new()
+        };
+        for entry in &corrupted {
+            tracing::warn!(
+                path = %entry.path,
+                reason = %entry.reason,
+                "archived copy failed verification"
+            );
+        }
+        let refused: HashSet<i64> = corrupted.iter().map(|entry| entry.file_id).collect();
+        let file_ids: Vec<i64> = file_ids
+            .into_iter()
+            .filter(|file_id| !refused.contains(file_id))
+            .collect();
+        let file_paths: Vec<String> = files
+            .into_iter()
+            .filter(|file| !file.id.is_some_and(|id| refused.contains(&id)))
+            .map(|file| file.path)
+            .collect();
+        if file_paths.is_empty() {
+            return Ok(DeleteOutcome {
+                success: false,
+                batch_id: None,
+                files_processed: 0,
+                total_bytes_freed: 0,
+                duration_ms: 0,
+                errors: Vec::new(),
+                error_messages: Vec::new(),
+                to_trash,
+                corrupted,
+            });
+        }
+
+        let mut delete_manager = DeleteManager::new();
+        delete_manager.set_use_trash(to_trash);
+        delete_manager.set_progress_callback(progress);
+        delete_manager.set_batch_origin("empty");
+        let delete_result = delete_manager
+            .delete_files(file_paths, &db_instance)
+            .map_err(|e| format!("ERR_DELETE: {e}"))?;
+        emit_ops_done(
+            &app_handle,
+            "empty_staged",
+            &delete_result.batch_id,
+            delete_result.files_deleted,
+            delete_result.total_bytes_freed,
+            delete_result.duration_ms,
+            &delete_result.errors,
+            delete_result.cancelled,
+        );
+
+        db_instance
+            .mark_files_unstaged(&file_ids)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+
+        Ok(DeleteOutcome {
+            success: delete_result.errors.is_empty() && corrupted.is_empty(),
+            batch_id: Some(delete_result.batch_id),
+            files_processed: delete_result.files_deleted,
+            total_bytes_freed: delete_result.total_bytes_freed,
+            duration_ms: delete_result.duration_ms,
+            errors: delete_result.errors,
+            error_messages: delete_result.error_messages,
+            to_trash,
+            corrupted,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Removes archived files right away instead of waiting for the retention
+/// sweep: the given archive batches, or every file whose cooloff has ended.
+/// `to_trash` defaults to the `archive_retention_mode` pref.
+#[tauri::command]
+pub async fn purge_archives(
+    batch_ids: Option<Vec<String>>,
+    to_trash: Option<bool>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<Option<ops::expiry::ArchivePurgedPayload>, String> {
+    let batch_ids: Option<HashSet<String>> = batch_ids
+        .map(|ids| {
+            ids.into_iter()
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect::<HashSet<_>>()
+        })
+        .filter(|ids| !ids.is_empty());
+
+    let db_clone = db.inner().clone();
+    let payload = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let to_trash = match to_trash {
+            Some(value) => value,
+            None => ops::expiry::retention_to_trash(&db_instance)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?,
+        };
+        let records = match batch_ids {
+            Some(batch_ids) => {
+                let statuses = vec!["staged".to_string(), "expired".to_string()];
+                db_instance
+                    .list_staged_with_files(Some(statuses.as_slice()))
+                    .map_err(|e| format!("ERR_DATABASE: {e}"))?
+                    .into_iter()
+                    .filter(|(record, _)| {
+                        record.batch_id.as_ref().is_some_and(|id| batch_ids.contains(id))
+                    })
+                    .collect()
+            }
+            None => {
+                let now = Utc::now();
+                let mut records = db_instance
+                    .list_expired_staged("staged", &now)
+                    .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+                records.extend(
+                    db_instance
+                        .list_expired_staged("expired", &now)
+                        .map_err(|e| format!("ERR_DATABASE: {e}"))?,
+                );
+                records
+            }
+        };
+        let file_ids: Vec<i64> = records.iter().map(|(record, _)| record.file_id).collect();
+        let _lock = lock_files("purge", &file_ids)?;
+        ops::expiry::purge_staged(&db_instance, records, to_trash)
+            .map_err(|e| format!("ERR_DELETE: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if let Some(payload) = payload.as_ref() {
+        emit_ops_done(
+            &app,
+            "purge_archives",
+            &payload.batch_id,
+            payload.files_purged,
+            payload.bytes_freed,
+            0,
+            &payload.errors,
+            false,
+        );
+        let _ = app.emit(ops::expiry::STAGED_PURGED_EVENT, payload.clone());
+    }
+    Ok(payload)
+}
+
+fn duplicate_group_file(file: &File) -> DuplicateGroupFile {
+    DuplicateGroupFile {
+        id: file.id.unwrap_or(0),
+        path: file.path.clone(),
+        parent_dir: file.parent_dir.clone(),
+        size_bytes: file.size_bytes.max(0) as u64,
+        last_seen_at: file.last_seen_at.to_rfc3339(),
+        is_staged: file.is_staged,
+        cooloff_until: file.cooloff_until.map(|dt| dt.to_rfc3339()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_duplicate_groups(
+    limit: Option<usize>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let fetch_limit = limit.unwrap_or(20).min(200);
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let groups = db_instance
+            .duplicate_groups(Some(fetch_limit))
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        let protected = load_protected_paths(&db_instance)?;
+        let mut response = Vec::with_capacity(groups.len());
+        for (hash, files) in groups {
+            let mut total_size = 0u64;
+            let mut group_files = Vec::with_capacity(files.len());
+            // Protected copies are never offered for removal; the rest of the
+            // group still duplicates them.
+            for file in files
+                .into_iter()
+                .filter(|f| !is_protected(Path::new(&f.path), &protected))
+            {
+                let entry = duplicate_group_file(&file);
+                total_size = total_size.saturating_add(entry.size_bytes);
+                group_files.push(entry);
+            }
+            if group_files.is_empty() {
+                continue;
+            }
+            response.push(DuplicateGroup {
+                hash,
+                total_size,
+                count: group_files.len(),
+                files: group_files,
+            });
+        }
+        Ok(response)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// The policy given for this call, or the saved one.
+fn resolve_duplicate_policy(
+    db: &Database,
+    policy: Option<String>,
+    preferred_paths: Option<Vec<String>>,
+) -> Result<(ops::dedupe::DuplicatePolicy, Vec<String>), String> {
+    let (saved_policy, saved_paths) = ops::dedupe::load_policy(db);
+    let policy = match policy {
+        Some(name) => ops::dedupe::DuplicatePolicy::parse(&name).ok_or_else(|| {
+            format!(
+                "ERR_VALIDATION: duplicate_policy must be one of {}",
+                ops::dedupe::DUPLICATE_POLICIES.join(", ")
+            )
+        })?,
+        None => saved_policy,
+    };
+    let preferred_paths = preferred_paths
+        .map(|paths| paths.into_iter().filter(|p| !p.trim().is_empty()).collect())
+        .unwrap_or(saved_paths);
+    Ok((policy, preferred_paths))
+}
+
+/// Applies `policy` to every duplicate group in the index. Staged copies are
+/// already on their way out and offline ones can't be moved, so neither is
+/// considered; protected copies are kept alongside the chosen one. A group
+/// whose chosen copy is missing on disk is skipped rather than left with none.
+fn duplicate_policy_plan(
+    db: &Database,
+    policy: ops::dedupe::DuplicatePolicy,
+    preferred_paths: Vec<String>,
+) -> Result<DuplicatePolicyPreview, String> {
+    let groups = db
+        .duplicate_groups(None)
+        .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+    let protected = load_protected_paths(db)?;
+    let mut planned = Vec::new();
+    for (hash, files) in groups {
+        let files: Vec<File> = files
+            .into_iter()
+            .filter(|f| !f.is_staged && !f.is_offline)
+            .collect();
+        if files.len() < 2 {
+            continue;
+        }
+        let Some(keep) = ops::dedupe::pick_keeper(policy, &preferred_paths, &files) else {
+            continue;
+        };
+        if !Path::new(&files[keep].path).exists() {
+            continue;
+        }
+        let stage: Vec<DuplicateGroupFile> = files
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| *i != keep && !is_protected(Path::new(&f.path), &protected))
+            .map(|(_, f)| duplicate_group_file(f))
+            .collect();
+        if stage.is_empty() {
+            continue;
+        }
+        planned.push(DuplicatePolicyGroup {
+            hash,
+            keep: duplicate_group_file(&files[keep]),
+            bytes: stage.iter().map(|f| f.size_bytes).sum(),
+            stage,
+        });
+    }
+    Ok(DuplicatePolicyPreview {
+        policy: policy.name().to_string(),
+        preferred_paths,
+        file_count: planned.iter().map(|g| g.stage.len()).sum(),
+        total_bytes: planned.iter().map(|g| g.bytes).sum(),
+        groups: planned,
+    })
+}
+
+/// What `apply_duplicate_policy` would stage: each duplicate group with the
+/// copy it keeps. `policy` and `preferred_paths` override the saved prefs so
+/// a policy can be tried before it is saved.
+#[tauri::command]
+pub async fn preview_duplicate_policy(
+    policy: Option<String>,
+    preferred_paths: Option<Vec<String>>,
+    db: State<'_, DbPool>,
+) -> Result<DuplicatePolicyPreview, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let (policy, preferred_paths) =
+            resolve_duplicate_policy(&db_instance, policy, preferred_paths)?;
+        duplicate_policy_plan(&db_instance, policy, preferred_paths)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Stages the copies the policy doesn't keep, across all duplicate groups,
+/// as one batch. Past the per-batch cap `remaining` says how many are left
+/// for the next run.
+#[tauri::command]
+pub async fn apply_duplicate_policy(
+    policy: Option<String>,
+    preferred_paths: Option<Vec<String>>,
+    options: Option<StageOptions>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<StageOutcome, String> {
+    let db_clone = db.inner().clone();
+    let (file_ids, remaining) = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let (policy, preferred_paths) =
+            resolve_duplicate_policy(&db_instance, policy, preferred_paths)?;
+        let plan = duplicate_policy_plan(&db_instance, policy, preferred_paths)?;
+        let mut file_ids: Vec<i64> = plan
+            .groups
+            .iter()
+            .flat_map(|group| group.stage.iter().map(|f| f.id))
+            .collect();
+        let remaining = file_ids.len().saturating_sub(MAX_FILES_PER_BATCH);
+        file_ids.truncate(MAX_FILES_PER_BATCH);
+        Ok::<_, String>((file_ids, remaining))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+    if file_ids.is_empty() {
+        return Err("ERR_NOT_FOUND: No duplicates left to resolve".to_string());
+    }
+
+    let options = with_origin(options, "dedupe_policy");
+    let mut outcome = stage_files(file_ids, options, None, app, db).await?;
+    outcome.remaining = remaining;
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub async fn archive_files(
+    file_ids: Vec<i64>,
+    idempotency_key: Option<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<ArchiveOutcome, String> {
+    // Validate input
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    validate_idempotency_key(idempotency_key.as_deref())
+        .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+
+    // Perform archive operation using spawn_blocking for database operations
+    let db_clone = db.inner().clone();
+    let progress = ops_progress_emitter(&app);
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let key = idempotency_key.as_deref();
+        let batch_id = |outcome: &ArchiveOutcome| outcome.batch_id.clone();
+        run_idempotent(&db_instance, key, "archive_files", batch_id, || {
+            let _lock = lock_files("archive", &file_ids)?;
+            let file_paths = resolve_file_paths(&db_instance, &file_ids)?;
+
+            // Perform archive operation
+            let mut archive_manager = ArchiveManager::new();
+            archive_manager.set_idempotency_key(idempotency_key.clone());
+            archive_manager.update_config(
+                ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_ARCHIVE: {}", e))?,
+            );
+            archive_manager.set_progress_callback(progress);
+            ensure_archive_space(&archive_manager, &file_paths)?;
+            let result = archive_manager
+                .archive_files(file_paths, &db_instance)
+                .map_err(|e| format!("ERR_ARCHIVE: {}", e))?;
+
+            emit_ops_done(
+                &app,
+                "archive",
+                &result.batch_id,
+                result.files_archived,
+                result.total_bytes,
+                result.duration_ms,
+                &result.errors,
+                result.cancelled,
+            );
+
+            Ok(ArchiveOutcome {
+                success: result.errors.is_empty(),
+                batch_id: Some(result.batch_id),
+                files_processed: result.files_archived,
+                total_bytes: result.total_bytes,
+                duration_ms: result.duration_ms,
+                errors: result.errors,
+                error_messages: result.error_messages,
+                dry_run: false, // TODO: Get from user preferences
+                archive_file: result.archive_file,
+                bytes_saved: result.bytes_saved,
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Fails with `ERR_SPACE: {SpaceCheck json}` when the archive volume cannot
+/// take the batch, so the UI can show required and available bytes.
+fn ensure_archive_space(manager: &ArchiveManager, file_paths: &[String]) -> Result<(), String> {
+    let check = manager
+        .check_space(file_paths)
+        .map_err(|e| format!("ERR_ARCHIVE: {e}"))?;
+    if check.sufficient {
+        return Ok(());
+    }
+    Err(format!(
+        "ERR_SPACE: {}",
+        serde_json::to_string(&check).unwrap_or_default()
+    ))
+}
+
+/// Lets the UI warn before staging when the archive volume is short on space.
+#[tauri::command]
+pub async fn check_space_for_files(
+    file_ids: Vec<i64>,
+    db: State<'_, DbPool>,
+) -> Result<ops::SpaceCheck, String> {
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut file_paths = Vec::new();
+        for file_id in &file_ids {
+            let file = db_instance
+                .get_file_by_id(*file_id)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?
+                .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+            if !file.is_deleted && !file.is_staged && Path::new(&file.path).exists() {
+                file_paths.push(file.path);
+            }
+        }
+
+        let mut archive_manager = ArchiveManager::new();
+        archive_manager.update_config(
+            ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_DATABASE: {e}"))?,
+        );
+        archive_manager
+            .check_space(&file_paths)
+            .map_err(|e| format!("ERR_ARCHIVE: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+fn archive_settings(config: &ArchiveConfig) -> ArchiveSettings {
+    let default_path = ArchiveConfig::default_base_path();
+    ArchiveSettings {
+        base_path: config.base_path.to_string_lossy().to_string(),
+        default_path: default_path.to_string_lossy().to_string(),
+        is_default: config.base_path == default_path,
+        compression: config.compression,
+    }
+}
+
+#[tauri::command]
+pub async fn get_archive_config(db: State<'_, DbPool>) -> Result<ArchiveSettings, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let config = ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        Ok(archive_settings(&config))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Updates the archive location and/or compression for future batches.
+/// Existing batches stay where they are until `migrate_archive` moves them.
+/// An empty `base_path` resets to the default location.
+#[tauri::command]
+pub async fn set_archive_config(
+    base_path: Option<String>,
+    compression: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<ArchiveSettings, String> {
+    let compression = compression
+        .map(|value| value.parse::<ops::ArchiveCompression>())
+        .transpose()
+        .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+
+        if let Some(base_path) = base_path {
+            let base_path = base_path.trim().to_string();
+            if !base_path.is_empty() {
+                let roots = db_instance
+                    .list_watched_paths()
+                    .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+                ArchiveConfig::validate_base_path(Path::new(&base_path), &roots)
+                    .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+            }
+            db_instance
+                .set_preference(ops::archive::ARCHIVE_PATH_PREF, &base_path)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        }
+        if let Some(compression) = compression {
+            db_instance
+                .set_preference(
+                    ops::archive::ARCHIVE_COMPRESSION_PREF,
+                    &compression.to_string(),
+                )
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        }
+
+        let config = ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        Ok(archive_settings(&config))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Moves existing archived batches to `target_path`, rewrites their recorded
+/// locations and makes it the archive location for new batches.
+#[tauri::command]
+pub async fn migrate_archive(
+    target_path: String,
+    db: State<'_, DbPool>,
+) -> Result<ops::ArchiveMigration, String> {
+    let target_path = target_path.trim().to_string();
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let roots = db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        let target = Path::new(&target_path);
+        ArchiveConfig::validate_base_path(target, &roots)
+            .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+
+        let config = ArchiveConfig::load(&db_instance).map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        // Undo, restore and expiry must not move an archived file mid-migration.
+        let archived_ids: Vec<i64> = db_instance
+            .get_all_active_files_under_roots(&[config.base_path.to_string_lossy().to_string()])
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .iter()
+            .filter_map(|file| file.id)
+            .collect();
+        let _lock = lock_files("migrate", &archived_ids)?;
+
+        let mut archive_manager = ArchiveManager::new();
+        archive_manager.update_config(config);
+        let migration = archive_manager
+            .migrate_to(target, &db_instance)
+            .map_err(|e| format!("ERR_ARCHIVE: {e}"))?;
+        db_instance
+            .set_preference(ops::archive::ARCHIVE_PATH_PREF, &target_path)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        Ok(migration)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn delete_files(
+    file_ids: Vec<i64>,
+    to_trash: bool,
+    idempotency_key: Option<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<DeleteOutcome, String> {
+    // Validate input
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    validate_idempotency_key(idempotency_key.as_deref())
+        .map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+
+    // Perform delete operation using spawn_blocking for database operations
+    let db_clone = db.inner().clone();
+    let progress = ops_progress_emitter(&app);
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let key = idempotency_key.as_deref();
+        let batch_id = |outcome: &DeleteOutcome| outcome.batch_id.clone();
+        run_idempotent(&db_instance, key, "delete_files", batch_id, || {
+            let _lock = lock_files("delete", &file_ids)?;
+            let file_paths = resolve_file_paths(&db_instance, &file_ids)?;
+
+            // Perform delete operation
+            let mut delete_manager = DeleteManager::new();
+            delete_manager.set_idempotency_key(idempotency_key.clone());
+            delete_manager.set_use_trash(to_trash);
+            delete_manager.set_progress_callback(progress);
+
+            let result = delete_manager
+                .delete_files(file_paths, &db_instance)
+                .map_err(|e| format!("ERR_DELETE: {}", e))?;
+
+            emit_ops_done(
+                &app,
+                "delete",
+                &result.batch_id,
+                result.files_deleted,
+                result.total_bytes_freed,
+                result.duration_ms,
+                &result.errors,
+                result.cancelled,
+            );
+
+            Ok(DeleteOutcome {
+                success: result.errors.is_empty(),
+                batch_id: Some(result.batch_id),
+                files_processed: result.files_deleted,
+                total_bytes_freed: result.total_bytes_freed,
+                duration_ms: result.duration_ms,
+                errors: result.errors,
+                error_messages: result.error_messages,
+                to_trash,
+                corrupted: Vec::new(),
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Starts an empty named selection for gathering files across views.
+#[tauri::command]
+pub async fn create_selection(
+    name: String,
+    db: State<'_, DbPool>,
+) -> Result<SelectionSummary, String> {
+    let name = sanitize_string(name.trim());
+    if name.is_empty() {
+        return Err("ERR_VALIDATION: Selection name cannot be empty".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let id = db_instance
+            .create_selection(&name)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        selection_summary(&db_instance, id)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Adds `file_ids`, and every file matching `query`/`filters` the way
+/// `search_files` would, to a selection.
+#[tauri::command]
+pub async fn add_to_selection(
+    selection_id: i64,
+    file_ids: Option<Vec<i64>>,
+    query: Option<String>,
+    filters: Option<FileSearchFilters>,
+    db: State<'_, DbPool>,
+) -> Result<SelectionSummary, String> {
+    if let Some(ids) = file_ids.as_deref() {
+        validate_file_ids(ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    }
+    if file_ids.is_none() && query.is_none() && filters.is_none() {
+        return Err("ERR_VALIDATION: Provide file_ids or a query/filters".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        selection_summary(&db_instance, selection_id)?;
+        if let Some(ids) = file_ids {
+            db_instance
+                .add_files_to_selection(selection_id, &ids)
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        }
+        if query.is_some() || filters.is_some() {
+            let filters = filters.unwrap_or_default();
+            let roots = search_roots(&db_instance, &filters)?;
+            db_instance
+                .add_search_matches_to_selection(
+                    selection_id,
+                    query.as_deref().unwrap_or(""),
+                    &filters,
+                    &roots,
+                )
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        }
+        selection_summary(&db_instance, selection_id)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn remove_from_selection(
+    selection_id: i64,
+    file_ids: Vec<i64>,
+    db: State<'_, DbPool>,
+) -> Result<SelectionSummary, String> {
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .remove_files_from_selection(selection_id, &file_ids)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        selection_summary(&db_instance, selection_id)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn get_selection_summary(
+    selection_id: i64,
+    db: State<'_, DbPool>,
+) -> Result<SelectionSummary, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .prune_selection(selection_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        selection_summary(&db_instance, selection_id)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn discard_selection(selection_id: i64, db: State<'_, DbPool>) -> Result<bool, String> {
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        Database::new(conn)
+            .discard_selection(selection_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Stages the selection as one batch. Past the per-batch cap the rest stays
+/// selected and `remaining` says how many; staged files leave the selection.
+#[tauri::command]
+pub async fn stage_selection(
+    selection_id: i64,
+    options: Option<StageOptions>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<StageOutcome, String> {
+    let pool = db.inner().clone();
+    let (file_ids, remaining) = selection_batch(pool.clone(), selection_id).await?;
+    let options = with_origin(options, "selection");
+    let mut outcome = stage_files(file_ids, options, None, app, db).await?;
+    outcome.remaining = remaining;
+    prune_selection(pool, selection_id).await?;
+    Ok(outcome)
+}
+
+/// Deletes the selected files, up to the per-batch cap; deleted files leave
+/// the selection.
+#[tauri::command]
+pub async fn delete_selection_files(
+    selection_id: i64,
+    to_trash: bool,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<DeleteOutcome, String> {
+    let pool = db.inner().clone();
+    let (file_ids, _) = selection_batch(pool.clone(), selection_id).await?;
+    let outcome = delete_files(file_ids, to_trash, None, app, db).await?;
+    prune_selection(pool, selection_id).await?;
+    Ok(outcome)
+}
+
+fn selection_summary(db: &Database, selection_id: i64) -> Result<SelectionSummary, String> {
+    db.selection_summary(selection_id)
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .ok_or_else(|| format!("ERR_NOT_FOUND: Selection {} not found", selection_id))
+}
+
+/// The next batch of a selection's files, and how many are left after it.
+async fn selection_batch(pool: DbPool, selection_id: i64) -> Result<(Vec<i64>, usize), String> {
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .prune_selection(selection_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let summary = selection_summary(&db_instance, selection_id)?;
+        let file_ids = db_instance
+            .selection_file_ids(selection_id, MAX_FILES_PER_BATCH)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if file_ids.is_empty() {
+            return Err("ERR_VALIDATION: Selection is empty".to_string());
+        }
+        Ok((file_ids.clone(), summary.file_count.saturating_sub(file_ids.len())))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+async fn prune_selection(pool: DbPool, selection_id: i64) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+        Database::new(conn)
+            .prune_selection(selection_id)
+            .map(|_| ())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn cancel_operation(batch_id: String) -> Result<bool, String> {
+    let batch_id = sanitize_string(batch_id.trim());
+    if batch_id.is_empty() {
+        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+    }
+    Ok(ops::cancel::cancel_batch(&batch_id))
+}
+
+#[tauri::command]
+pub async fn undo_last(app: tauri::AppHandle, db: State<'_, DbPool>) -> Result<UndoResult, String> {
+    let db_clone = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let mut undo_manager = UndoManager::new();
+        let last = undo_manager
+            .get_last_batch(&db_instance)
+            .map_err(|e| format!("ERR_UNDO: {}", e))?;
+        let _lock = lock_files("undo", &batch_file_ids(&last.actions))?;
+        // Undo the batch that was locked, even if a newer one landed since.
+        undo_manager
+            .undo_batch(&last.batch_id, &db_instance)
+            .map_err(|e| format!("ERR_UNDO: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::spawn_gauge_refresh(&app);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_undoable_batches(db: State<'_, DbPool>) -> Result<Vec<UndoBatchSummary>, String> {
+    let db_clone = db.inner().clone();
+    let (batches, mut records) = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let undo = UndoManager::new();
+        let batches = undo
+            .get_undoable_batches(&db_instance)
+            .map_err(|e| format!("ERR_UNDO: {}", e))?;
+        let mut records = std::collections::HashMap::new();
+        for batch in &batches {
+            if let Some(record) = db_instance
+                .get_batch(&batch.batch_id)
+                .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            {
+                records.insert(batch.batch_id.clone(), record);
+            }
+        }
+        Ok::<_, String>((batches, records))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    let summaries = batches
+        .into_iter()
+        .map(|b| {
+            let record = records.remove(&b.batch_id);
+            UndoBatchSummary {
+                batch_id: b.batch_id,
+                action_type: b.action_type.to_string(),
+                file_count: b.file_count,
+                created_at: b.created_at.timestamp(),
+                label: record.as_ref().and_then(|r| r.label.clone()),
+                origin: record.map(|r| r.origin),
+            }
+        })
+        .collect();
+
+    Ok(summaries)
+}
+
+/// Names a batch so it can be found again in undo and exports; an empty
+/// label clears it.
+#[tauri::command]
+pub async fn update_batch_label(
+    batch_id: String,
+    label: Option<String>,
+    db: State<'_, DbPool>,
+) -> Result<BatchRecord, String> {
+    let batch_id = batch_id.trim().to_string();
+    if batch_id.is_empty() {
+        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+    }
+    let label = sanitize_note(label);
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let updated = db_instance
+            .update_batch_label(&batch_id, label.as_deref())
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        if updated == 0 {
+            return Err(format!("ERR_NOT_FOUND: Batch {} not found", batch_id));
+        }
+        db_instance
+            .get_batch(&batch_id)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?
+            .ok_or_else(|| format!("ERR_NOT_FOUND: Batch {} not found", batch_id))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Recorded batches, newest first, optionally narrowed by type, origin,
+/// label text and start time.
+#[tauri::command]
+pub async fn list_batches(
+    filters: Option<BatchFilters>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<BatchRecord>, String> {
+    let mut filters = filters.unwrap_or_default();
+    if let Some(action_type) = &filters.action_type {
+        if action_type.parse::<ActionType>().is_err() {
+            return Err(format!("ERR_VALIDATION: Unknown action type {}", action_type));
+        }
+    }
+    filters.limit = Some(filters.limit.unwrap_or(100).clamp(1, 500));
+    filters.label = filters.label.map(|label| label.trim().to_string()).filter(|l| !l.is_empty());
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        Database::new(conn)
+            .list_batches(&filters)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Restores only `file_ids` from `batch_id`; the rest of the batch stays
+/// undoable.
+#[tauri::command]
+pub async fn undo_files(
+    batch_id: String,
+    file_ids: Vec<i64>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<UndoResult, String> {
+    if batch_id.trim().is_empty() {
+        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+    }
+    validate_file_ids(&file_ids).map_err(|e| format!("ERR_VALIDATION: {e}"))?;
+
+    let db_clone = db.inner().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let _lock = lock_files("undo", &file_ids)?;
+        let mut undo_manager = UndoManager::new();
+        undo_manager
+            .undo_files(batch_id.trim(), &file_ids, &db_instance)
+            .map_err(|e| format!("ERR_UNDO: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::spawn_gauge_refresh(&app);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn undo_batch(
+    batch_id: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<UndoResult, String> {
+    if batch_id.trim().is_empty() {
+        return Err("ERR_VALIDATION: batch_id cannot be empty".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    let target = batch_id.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let actions = db_instance
+            .get_actions_by_batch_id(&target)
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        let _lock = lock_files("undo", &batch_file_ids(&actions))?;
+        let mut undo_manager = UndoManager::new();
+        undo_manager
+            .undo_batch(&target, &db_instance)
+            .map_err(|e| format!("ERR_UNDO: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    gauge::spawn_gauge_refresh(&app);
+    Ok(result)
+}
+
+fn batch_file_ids(actions: &[Action]) -> Vec<i64> {
+    actions.iter().map(|action| action.file_id).collect()
+}
+
+/// Stage, delete, restore and undo batches currently holding files, so the UI
+/// can explain an `ERR_FILE_BUSY` rejection.
+#[tauri::command]
+pub async fn list_active_operations() -> Result<Vec<ops::locks::ActiveOperation>, String> {
+    Ok(ops::locks::active_operations())
+}
+
+/// Staged files at least `min_age_days` old, optionally only those whose
+/// cooloff ends within `expiring_within_days`, soonest expiry first.
+#[tauri::command]
+pub async fn get_review_items(
+    min_age_days: u32,
+    expiring_within_days: Option<u32>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<StagedFile>, String> {
+    // Validate input
+    if min_age_days > 365 {
+        return Err("ERR_VALIDATION: min_age_days too large (max 365)".to_string());
+    }
+    if expiring_within_days.is_some_and(|days| days > 365) {
+        return Err("ERR_VALIDATION: expiring_within_days too large (max 365)".to_string());
+    }
+
+    let now = Utc::now();
+    let staged_before = now - Duration::days(min_age_days as i64);
+    let expiring_by = expiring_within_days.map(|days| now + Duration::days(days as i64));
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let items = db_instance
+            .review_staged(&staged_before, expiring_by.as_ref())
+            .map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        Ok(items
+            .into_iter()
+            .map(|(record, file, archive_path)| StagedFile {
+                archive_path,
+                ..staged_payload(&record, &file)
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn get_thumbnail(
+    file_id: i64,
+    max_px: u32,
+    db: State<'_, DbPool>,
+) -> Result<String, String> {
+    // Validate input
+    if file_id <= 0 {
+        return Err("ERR_VALIDATION: Invalid file ID".to_string());
+    }
+
+    if max_px == 0 || max_px > 2048 {
+        return Err("ERR_VALIDATION: Invalid thumbnail size (1-2048px)".to_string());
+    }
+
+    // Get file from database using spawn_blocking
+    let db_clone = db.inner().clone();
+    let file = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        match db_instance.get_file_by_id(file_id) {
+            Ok(Some(file)) => Ok(file),
+            Ok(None) => Err(format!("ERR_NOT_FOUND: File with ID {} not found", file_id)),
+            Err(e) => Err(format!("ERR_DATABASE: {}", e)),
+        }
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    // Validate file path
+    validate_path(&file.path).map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+
+    // Check if file exists
+    if !Path::new(&file.path).exists() {
+        return Err("ERR_NOT_FOUND: File does not exist on disk".to_string());
+    }
+
+    let path = PathBuf::from(&file.path);
+    tokio::task::spawn_blocking(move || {
+        ThumbnailCache::new()
+            .get_or_create(file_id, &path, max_px)
+            .map_err(|e| format!("ERR_INTERNAL: Thumbnail generation failed: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Typed preview for the review UI; `max_bytes` caps the text read and
+/// defaults to 64 KB.
+#[tauri::command]
+pub async fn get_file_preview(
+    file_id: i64,
+    max_bytes: Option<usize>,
+    db: State<'_, DbPool>,
+) -> Result<FilePreview, String> {
+    let max_bytes = max_bytes.unwrap_or(preview::DEFAULT_PREVIEW_BYTES);
+    if max_bytes == 0 || max_bytes > preview::MAX_PREVIEW_BYTES {
+        return Err(format!(
+            "ERR_VALIDATION: max_bytes must be 1-{}",
+            preview::MAX_PREVIEW_BYTES
+        ));
+    }
+
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || -> Result<FilePreview, String> {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        let file = db_instance
+            .get_file_by_id(file_id)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .filter(|file| !file.is_deleted)
+            .ok_or_else(|| format!("ERR_NOT_FOUND: File with ID {} not found", file_id))?;
+
+        let path = PathBuf::from(&file.path);
+        if !path.is_file() {
+            return Err("ERR_NOT_FOUND: File does not exist on disk".to_string());
+        }
+        preview::build_preview(file_id, &path, file.mime.as_deref(), max_bytes)
+            .map_err(|e| format!("ERR_INTERNAL: Preview failed: {e}"))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LogTail {
+    /// Folder holding the rotated log files, for attaching to support requests.
+    pub dir: Option<String>,
+    pub lines: Vec<String>,
+}
+
+/// The last `tail_lines` log lines (default 200), oldest first.
+#[tauri::command]
+pub async fn get_logs(tail_lines: Option<usize>) -> Result<LogTail, String> {
+    let count = tail_lines.unwrap_or(200).clamp(1, logging::MAX_TAIL_LINES);
+    let Some(dir) = logging::current_dir() else {
+        return Ok(LogTail {
+            dir: None,
+            lines: Vec::new(),
+        });
+    };
+    let lines = tokio::task::spawn_blocking({
+        let dir = dir.clone();
+        move || logging::tail(&dir, count)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+    .map_err(|e| format!("ERR_INTERNAL: failed to read logs: {e}"))?;
+    Ok(LogTail {
+        dir: Some(dir.to_string_lossy().to_string()),
+        lines,
+    })
+}
+
+#[tauri::command]
+pub async fn get_prefs(db: State<'_, DbPool>) -> Result<UserPrefs, String> {
+    // Get preferences from database using spawn_blocking
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        read_prefs(&Database::new(conn))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Stored prefs with defaults filled in for anything unset or invalid.
+fn read_prefs(db: &Database) -> Result<UserPrefs, String> {
+    let prefs = db
+        .get_all_preferences()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+
+    // Convert to UserPrefs struct
+    Ok(UserPrefs {
+        dry_run_default: prefs
+            .get("dry_run_default")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        tidy_day: prefs
+            .get("tidy_day")
+            .cloned()
+            .unwrap_or_else(|| "Fri".to_string()),
+        tidy_hour: prefs
+            .get("tidy_hour")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(17),
+        rolling_window_days: prefs
+            .get("rolling_window_days")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(7),
+        max_candidates_per_day: prefs
+            .get("max_candidates_per_day")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(12),
+        thumbnail_max_size: prefs
+            .get("thumbnail_max_size")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(256),
+        auto_scan_enabled: prefs
+            .get("auto_scan_enabled")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false),
+        scan_interval_hours: prefs
+            .get("scan_interval_hours")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24),
+        archive_age_threshold_days: prefs
+            .get("archive_age_threshold_days")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(7),
+        delete_age_threshold_days: prefs
+            .get("delete_age_threshold_days")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30),
+        scan_concurrency: prefs
+            .get("scan_concurrency")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(scanner::default_scan_concurrency),
+        auto_empty_after_cooloff: prefs
+            .get(ops::expiry::AUTO_EMPTY_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false),
+        notifications_enabled: prefs
+            .get(notifications::NOTIFICATIONS_ENABLED_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        notify_scan_threshold_gb: prefs
+            .get(notifications::NOTIFY_SCAN_THRESHOLD_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1),
+        notify_expiry: prefs
+            .get(notifications::NOTIFY_EXPIRY_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        notify_auto_empty: prefs
+            .get(notifications::NOTIFY_AUTO_EMPTY_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        notify_tidy_reminder: prefs
+            .get(notifications::NOTIFY_TIDY_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        log_level: prefs
+            .get(logging::LOG_LEVEL_PREF)
+            .filter(|v| logging::parse_level(v).is_some())
+            .cloned()
+            .unwrap_or_else(|| logging::DEFAULT_LOG_LEVEL.to_string()),
+        history_retention_days: prefs
+            .get(maintenance::HISTORY_RETENTION_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        purge_removed_roots: prefs
+            .get(maintenance::PURGE_REMOVED_ROOTS_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false),
+        archive_retention_days: prefs
+            .get(ops::expiry::ARCHIVE_RETENTION_DAYS_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        archive_retention_mode: prefs
+            .get(ops::expiry::ARCHIVE_RETENTION_MODE_PREF)
+            .filter(|v| ops::expiry::ARCHIVE_RETENTION_MODES.contains(&v.as_str()))
+            .cloned()
+            .unwrap_or_else(|| "trash".to_string()),
+        installers_bucket_enabled: prefs
+            .get(INSTALLERS_BUCKET_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true),
+        record_symlinks: prefs
+            .get(scanner::RECORD_SYMLINKS_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false),
+        scan_power_mode: prefs
+            .get(scanner::power::SCAN_POWER_MODE_PREF)
+            .filter(|v| scanner::power::SCAN_POWER_MODES.contains(&v.as_str()))
+            .cloned()
+            .unwrap_or_else(|| "auto".to_string()),
+        locale: prefs
+            .get(i18n::LOCALE_PREF)
+            .filter(|v| i18n::is_valid_locale(v))
+            .cloned()
+            .unwrap_or_else(|| i18n::DEFAULT_LOCALE.to_string()),
+        duplicate_policy: prefs
+            .get(ops::dedupe::DUPLICATE_POLICY_PREF)
+            .filter(|v| ops::dedupe::DUPLICATE_POLICIES.contains(&v.as_str()))
+            .cloned()
+            .unwrap_or_else(|| "keep_newest".to_string()),
+        duplicate_preferred_paths: prefs
+            .get(ops::dedupe::DUPLICATE_PREFERRED_PATHS_PREF)
+            .and_then(|v| serde_json::from_str(v).ok())
+            .unwrap_or_default(),
+        max_full_hash_gb: prefs
+            .get(scanner::MAX_FULL_HASH_GB_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        only_clean_when_idle: prefs
+            .get(scanner::activity::ONLY_CLEAN_WHEN_IDLE_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false),
+        idle_minutes: prefs
+            .get(scanner::activity::IDLE_MINUTES_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(scanner::activity::DEFAULT_IDLE_MINUTES),
+        restore_grace_days: prefs
+            .get(RESTORE_GRACE_DAYS_PREF)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RESTORE_GRACE_DAYS),
+    })
+}
+
+#[tauri::command]
+pub async fn set_prefs(prefs: PartialUserPrefs, db: State<'_, DbPool>) -> Result<(), String> {
+    validate_prefs(&prefs)?;
+
+    // Set preferences in database using spawn_blocking
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        write_prefs(&Database::new(conn), prefs)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    Ok(())
+}
+
+fn validate_prefs(prefs: &PartialUserPrefs) -> Result<(), String> {
+    // Validate input
+    if let Some(tidy_hour) = prefs.tidy_hour {
+        if tidy_hour > 23 {
+            return Err("ERR_VALIDATION: tidy_hour must be 0-23".to_string());
+        }
+    }
+
+    if let Some(rolling_window_days) = prefs.rolling_window_days {
+        if rolling_window_days <= 0 || rolling_window_days > 365 {
+            return Err("ERR_VALIDATION: rolling_window_days must be 1-365".to_string());
+        }
+    }
+
+    if let Some(max_candidates_per_day) = prefs.max_candidates_per_day {
+        if max_candidates_per_day == 0 || max_candidates_per_day > 1000 {
+            return Err("ERR_VALIDATION: max_candidates_per_day must be 1-1000".to_string());
+        }
+    }
+
+    if let Some(thumbnail_max_size) = prefs.thumbnail_max_size {
+        if thumbnail_max_size == 0 || thumbnail_max_size > 2048 {
+            return Err("ERR_VALIDATION: thumbnail_max_size must be 1-2048".to_string());
+        }
+    }
+
+    if let Some(scan_interval_hours) = prefs.scan_interval_hours {
+        if scan_interval_hours == 0 || scan_interval_hours > 168 {
+            return Err("ERR_VALIDATION: scan_interval_hours must be 1-168".to_string());
+        }
+    }
+
+    if let Some(archive_age_threshold_days) = prefs.archive_age_threshold_days {
+        if archive_age_threshold_days > 365 {
+            return Err("ERR_VALIDATION: archive_age_threshold_days must be 0-365".to_string());
+        }
+    }
+
+    if let Some(delete_age_threshold_days) = prefs.delete_age_threshold_days {
+        if delete_age_threshold_days > 365 {
+            return Err("ERR_VALIDATION: delete_age_threshold_days must be 0-365".to_string());
+        }
+    }
+
+    if let Some(notify_scan_threshold_gb) = prefs.notify_scan_threshold_gb {
+        if notify_scan_threshold_gb > 1024 {
+            return Err("ERR_VALIDATION: notify_scan_threshold_gb must be 0-1024".to_string());
+        }
+    }
+
+    if let Some(log_level) = prefs.log_level.as_deref() {
+        if logging::parse_level(log_level).is_none() {
+            return Err(format!(
+                "ERR_VALIDATION: log_level must be one of {}",
+                logging::LOG_LEVELS.join(", ")
+            ));
+        }
+    }
+
+    if let Some(history_retention_days) = prefs.history_retention_days {
+        if history_retention_days > 3650 {
+            return Err("ERR_VALIDATION: history_retention_days must be 0-3650".to_string());
+        }
+    }
+
+    if let Some(archive_retention_days) = prefs.archive_retention_days {
+        if archive_retention_days > 3650 {
+            return Err("ERR_VALIDATION: archive_retention_days must be 0-3650".to_string());
+        }
+    }
+
+    if let Some(mode) = prefs.archive_retention_mode.as_deref() {
+        if !ops::expiry::ARCHIVE_RETENTION_MODES.contains(&mode) {
+            return Err("ERR_VALIDATION: archive_retention_mode must be trash or purge".to_string());
+        }
+    }
+
+    if let Some(mode) = prefs.scan_power_mode.as_deref() {
+        if !scanner::power::SCAN_POWER_MODES.contains(&mode) {
+            return Err(format!(
+                "ERR_VALIDATION: scan_power_mode must be one of {}",
+                scanner::power::SCAN_POWER_MODES.join(", ")
+            ));
+        }
+    }
+
+    if let Some(locale) = prefs.locale.as_deref() {
+        if !i18n::is_valid_locale(locale) {
+            return Err(
+                "ERR_VALIDATION: locale must be a language tag like en or pt-BR".to_string(),
+            );
+        }
+    }
+
+    if let Some(policy) = prefs.duplicate_policy.as_deref() {
+        if ops::dedupe::DuplicatePolicy::parse(policy).is_none() {
+            return Err(format!(
+                "ERR_VALIDATION: duplicate_policy must be one of {}",
+                ops::dedupe::DUPLICATE_POLICIES.join(", ")
+            ));
+        }
+    }
+
+    if let Some(paths) = &prefs.duplicate_preferred_paths {
+        if paths.len() > 20 {
+            return Err("ERR_VALIDATION: at most 20 preferred duplicate folders".to_string());
+        }
+        if paths.iter().any(|path| path.trim().is_empty()) {
+            return Err("ERR_VALIDATION: preferred duplicate folders cannot be empty".to_string());
+        }
+    }
+
+    if let Some(max_full_hash_gb) = prefs.max_full_hash_gb {
+        if max_full_hash_gb > 100_000 {
+            return Err("ERR_VALIDATION: max_full_hash_gb must be 0-100000".to_string());
+        }
+    }
+
+    if let Some(idle_minutes) = prefs.idle_minutes {
+        if idle_minutes == 0 || idle_minutes > scanner::activity::MAX_IDLE_MINUTES {
+            return Err(format!(
+                "ERR_VALIDATION: idle_minutes must be 1-{}",
+                scanner::activity::MAX_IDLE_MINUTES
+            ));
+        }
+    }
+
+    if let Some(restore_grace_days) = prefs.restore_grace_days {
+        if restore_grace_days > MAX_RESTORE_GRACE_DAYS {
+            return Err(format!(
+                "ERR_VALIDATION: restore_grace_days must be 0-{}",
+                MAX_RESTORE_GRACE_DAYS
+            ));
+        }
+    }
+
+    if let Some(scan_concurrency) = prefs.scan_concurrency {
+        if scan_concurrency == 0 || scan_concurrency > scanner::MAX_SCAN_CONCURRENCY {
+            return Err(format!(
+                "ERR_VALIDATION: scan_concurrency must be 1-{}",
+                scanner::MAX_SCAN_CONCURRENCY
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores the prefs that are set; they must have passed [`validate_prefs`].
+fn write_prefs(db: &Database, prefs: PartialUserPrefs) -> Result<(), String> {
+    if let Some(dry_run_default) = prefs.dry_run_default {
+        db.set_preference("dry_run_default", &dry_run_default.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(tidy_day) = prefs.tidy_day {
+        let sanitized_day = sanitize_string(&tidy_day);
+        db.set_preference("tidy_day", &sanitized_day)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(tidy_hour) = prefs.tidy_hour {
+        db.set_preference("tidy_hour", &tidy_hour.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(rolling_window_days) = prefs.rolling_window_days {
+        db.set_preference("rolling_window_days", &rolling_window_days.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(max_candidates_per_day) = prefs.max_candidates_per_day {
+        db.set_preference("max_candidates_per_day", &max_candidates_per_day.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(thumbnail_max_size) = prefs.thumbnail_max_size {
+        db.set_preference("thumbnail_max_size", &thumbnail_max_size.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(auto_scan_enabled) = prefs.auto_scan_enabled {
+        db.set_preference("auto_scan_enabled", &auto_scan_enabled.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(scan_interval_hours) = prefs.scan_interval_hours {
+        db.set_preference("scan_interval_hours", &scan_interval_hours.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(archive_age_threshold_days) = prefs.archive_age_threshold_days {
+        db.set_preference("archive_age_threshold_days", &archive_age_threshold_days.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(delete_age_threshold_days) = prefs.delete_age_threshold_days {
+        db.set_preference("delete_age_threshold_days", &delete_age_threshold_days.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(scan_concurrency) = prefs.scan_concurrency {
+        db.set_preference("scan_concurrency", &scan_concurrency.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(auto_empty_after_cooloff) = prefs.auto_empty_after_cooloff {
+        db.set_preference(ops::expiry::AUTO_EMPTY_PREF, &auto_empty_after_cooloff.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    for (key, value) in [
+        (notifications::NOTIFICATIONS_ENABLED_PREF, prefs.notifications_enabled),
+        (notifications::NOTIFY_EXPIRY_PREF, prefs.notify_expiry),
+        (notifications::NOTIFY_AUTO_EMPTY_PREF, prefs.notify_auto_empty),
+        (notifications::NOTIFY_TIDY_PREF, prefs.notify_tidy_reminder),
+    ] {
+        if let Some(value) = value {
+            db.set_preference(key, &value.to_string())
+                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        }
+    }
+
+    if let Some(notify_scan_threshold_gb) = prefs.notify_scan_threshold_gb {
+        db.set_preference(
+            notifications::NOTIFY_SCAN_THRESHOLD_PREF,
+            &notify_scan_threshold_gb.to_string(),
+        )
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(log_level) = prefs.log_level {
+        let log_level = log_level.trim().to_ascii_lowercase();
+        db.set_preference(logging::LOG_LEVEL_PREF, &log_level)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if let Err(err) = logging::set_level(&log_level) {
+            tracing::warn!("Failed to apply log level: {err}");
+        }
+    }
+
+    if let Some(history_retention_days) = prefs.history_retention_days {
+        db.set_preference(maintenance::HISTORY_RETENTION_PREF, &history_retention_days.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(purge_removed_roots) = prefs.purge_removed_roots {
+        db.set_preference(maintenance::PURGE_REMOVED_ROOTS_PREF, &purge_removed_roots.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(archive_retention_days) = prefs.archive_retention_days {
+        db.set_preference(
+            ops::expiry::ARCHIVE_RETENTION_DAYS_PREF,
+            &archive_retention_days.to_string(),
+        )
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(mode) = prefs.archive_retention_mode {
+        db.set_preference(ops::expiry::ARCHIVE_RETENTION_MODE_PREF, &mode)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(installers_bucket_enabled) = prefs.installers_bucket_enabled {
+        db.set_preference(INSTALLERS_BUCKET_PREF, &installers_bucket_enabled.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(record_symlinks) = prefs.record_symlinks {
+        db.set_preference(scanner::RECORD_SYMLINKS_PREF, &record_symlinks.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(mode) = prefs.scan_power_mode {
+        db.set_preference(scanner::power::SCAN_POWER_MODE_PREF, &mode)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(locale) = prefs.locale {
+        db.set_preference(i18n::LOCALE_PREF, &locale)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(max_full_hash_gb) = prefs.max_full_hash_gb {
+        db.set_preference(scanner::MAX_FULL_HASH_GB_PREF, &max_full_hash_gb.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(only_clean_when_idle) = prefs.only_clean_when_idle {
+        db.set_preference(
+            scanner::activity::ONLY_CLEAN_WHEN_IDLE_PREF,
+            &only_clean_when_idle.to_string(),
+        )
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(idle_minutes) = prefs.idle_minutes {
+        db.set_preference(scanner::activity::IDLE_MINUTES_PREF, &idle_minutes.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(restore_grace_days) = prefs.restore_grace_days {
+        db.set_preference(RESTORE_GRACE_DAYS_PREF, &restore_grace_days.to_string())
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(policy) = prefs.duplicate_policy {
+        let policy = ops::dedupe::DuplicatePolicy::parse(&policy)
+            .map(|p| p.name())
+            .unwrap_or("keep_newest");
+        db.set_preference(ops::dedupe::DUPLICATE_POLICY_PREF, policy)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    if let Some(paths) = prefs.duplicate_preferred_paths {
+        let paths: Vec<String> = paths.iter().map(|p| sanitize_string(p.trim())).collect();
+        let value = serde_json::to_string(&paths).map_err(|e| format!("ERR_DATABASE: {e}"))?;
+        db.set_preference(ops::dedupe::DUPLICATE_PREFERRED_PATHS_PREF, &value)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Version of the settings file layout; files from a newer app are refused.
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
+/// Largest settings file `import_settings` reads.
+const MAX_SETTINGS_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// A watched root and its settings as they appear in a settings file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExportedRoot {
+    pub path: String,
+    pub scan_interval_hours: Option<u32>,
+    pub watcher_enabled: bool,
+    pub low_priority_io: bool,
+    pub partial_hash_only: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExportedExclusion {
+    pub root_path: Option<String>,
+    pub pattern: String,
+}
+
+/// Everything that configures the app and nothing about the indexed files:
+/// prefs, bucket thresholds, watched roots, exclusions, custom buckets and
+/// protected paths. Exports carry full [`UserPrefs`]; imports read them as
+/// [`PartialUserPrefs`] so a hand-trimmed file only changes what it lists.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SettingsFile<P> {
+    pub schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub prefs: P,
+    #[serde(default)]
+    pub selector_config: Option<SelectorConfig>,
+    #[serde(default)]
+    pub roots: Vec<ExportedRoot>,
+    #[serde(default)]
+    pub exclusions: Vec<ExportedExclusion>,
+    #[serde(default)]
+    pub user_rules: Vec<NewUserRule>,
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SettingsImportSummary {
+    pub roots_added: Vec<String>,
+    pub roots_updated: Vec<String>,
+    pub exclusions_added: usize,
+    pub rules_added: usize,
+    pub protected_paths_added: usize,
+    /// Entries left out and why, e.g. a folder that doesn't exist here.
+    pub skipped: Vec<String>,
+}
+
+/// Writes the app's configuration to `dest_path` as JSON. A folder gets a
+/// timestamped file inside it.
+#[tauri::command]
+pub async fn export_settings(dest_path: String, db: State<'_, DbPool>) -> Result<String, String> {
+    let dest = PathBuf::from(dest_path.trim());
+    let has_parent_dir = dest
+        .components()
+        .any(|component| matches!(component, std::path::Component::ParentDir));
+    if !dest.is_absolute() || has_parent_dir {
+        return Err("ERR_VALIDATION: Settings path must be absolute".to_string());
+    }
+    let exported_at = Utc::now();
+    let dest = if dest.is_dir() {
+        dest.join(format!("white-space-settings-{}.json", exported_at.format("%Y%m%d-%H%M%S")))
+    } else {
+        dest
+    };
+    if !dest.parent().is_some_and(Path::is_dir) {
+        return Err(format!(
+            "ERR_VALIDATION: Folder does not exist: {}",
+            dest.parent().unwrap_or(&dest).display()
+        ));
     }
 
-    // Set preferences in database using spawn_blocking
     let db_clone = db.inner().clone();
     tokio::task::spawn_blocking(move || {
         let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
-        let db_instance = Database::new(conn);
+        let settings = collect_settings(&Database::new(conn), exported_at)?;
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("ERR_INTERNAL: {e}"))?;
+        fs::write(&dest, json)
+            .map_err(|e| format!("ERR_INTERNAL: Failed to write {}: {}", dest.display(), e))?;
+        Ok(dest.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
 
-        if let Some(dry_run_default) = prefs.dry_run_default {
-            db_instance
-                .set_preference("dry_run_default", &dry_run_default.to_string())
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+fn collect_settings(
+    db: &Database,
+    exported_at: DateTime<Utc>,
+) -> Result<SettingsFile<UserPrefs>, String> {
+    let roots = db
+        .list_root_settings()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .map(|root| ExportedRoot {
+            path: root.path,
+            scan_interval_hours: root.scan_interval_hours,
+            watcher_enabled: root.watcher_enabled,
+            low_priority_io: root.low_priority_io,
+            partial_hash_only: root.partial_hash_only,
+        })
+        .collect();
+    let exclusions = db
+        .list_exclusions()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .map(|exclusion| ExportedExclusion {
+            root_path: exclusion.root_path,
+            pattern: exclusion.pattern,
+        })
+        .collect();
+    let user_rules = db
+        .list_user_rules()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .map(|rule| NewUserRule {
+            name: rule.name,
+            path_glob: rule.path_glob,
+            min_size_bytes: rule.min_size_bytes,
+            min_age_days: rule.min_age_days,
+            mime_filter: rule.mime_filter,
+            max_per_day: Some(rule.max_per_day),
+            enabled: Some(rule.enabled),
+        })
+        .collect();
+
+    Ok(SettingsFile {
+        schema_version: SETTINGS_SCHEMA_VERSION,
+        exported_at,
+        prefs: read_prefs(db)?,
+        selector_config: Some(SelectorConfig::load(db)),
+        roots,
+        exclusions,
+        user_rules,
+        protected_paths: load_protected_paths(db)?,
+    })
+}
+
+/// Applies a file written by `export_settings` on top of the current
+/// configuration. The whole file is validated before anything is written;
+/// roots, exclusions and protected paths that don't exist on this machine
+/// are skipped, as are rules whose name is already taken.
+#[tauri::command]
+pub async fn import_settings(
+    src_path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<SettingsImportSummary, String> {
+    let src = PathBuf::from(src_path.trim());
+    if !src.is_absolute() {
+        return Err("ERR_VALIDATION: Settings path must be absolute".to_string());
+    }
+    let metadata = fs::metadata(&src)
+        .map_err(|_| format!("ERR_NOT_FOUND: Settings file not found: {}", src.display()))?;
+    if !metadata.is_file() || metadata.len() > MAX_SETTINGS_FILE_BYTES {
+        return Err("ERR_VALIDATION: Not a settings file".to_string());
+    }
+    let raw = fs::read_to_string(&src)
+        .map_err(|e| format!("ERR_INTERNAL: Failed to read {}: {}", src.display(), e))?;
+    let settings = parse_settings(&raw)?;
+
+    let db_clone = db.inner().clone();
+    let (summary, watchers) = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        apply_settings(&Database::new(conn), settings)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    for (path, enabled) in &watchers {
+        let result = if *enabled {
+            register_root(path)
+        } else {
+            unregister_root(path)
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to update watcher for {}: {}", path, err);
         }
+    }
+    let _ = app.emit("roots://changed", serde_json::json!({ "count": watchers.len() }));
+    let _ = app.emit("exclusions://changed", serde_json::json!({ "imported": true }));
+    let _ = app.emit("rules://changed", serde_json::json!({ "imported": true }));
+    let _ = app.emit("protected://changed", serde_json::json!({ "imported": true }));
+    gauge::spawn_gauge_refresh(&app);
+    Ok(summary)
+}
 
-        if let Some(tidy_day) = prefs.tidy_day {
-            let sanitized_day = sanitize_string(&tidy_day);
-            db_instance
-                .set_preference("tidy_day", &sanitized_day)
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+/// Reads and validates a settings file, normalizing rules and patterns the
+/// way the matching commands do.
+fn parse_settings(raw: &str) -> Result<SettingsFile<PartialUserPrefs>, String> {
+    let mut settings: SettingsFile<PartialUserPrefs> = serde_json::from_str(raw)
+        .map_err(|e| format!("ERR_VALIDATION: Not a settings file: {e}"))?;
+    if settings.schema_version == 0 || settings.schema_version > SETTINGS_SCHEMA_VERSION {
+        return Err(format!(
+            "ERR_VALIDATION: Unsupported settings version {}",
+            settings.schema_version
+        ));
+    }
+    validate_prefs(&settings.prefs)?;
+    if let Some(config) = &settings.selector_config {
+        config
+            .validate()
+            .map_err(|e| format!("ERR_VALIDATION: {}", e))?;
+    }
+    for root in &settings.roots {
+        if let Some(hours) = root.scan_interval_hours {
+            if !(MIN_SCAN_INTERVAL_HOURS..=MAX_SCAN_INTERVAL_HOURS).contains(&hours) {
+                return Err(format!(
+                    "ERR_VALIDATION: scan_interval_hours for {} must be between {} and {}",
+                    root.path, MIN_SCAN_INTERVAL_HOURS, MAX_SCAN_INTERVAL_HOURS
+                ));
+            }
+        }
+    }
+    for exclusion in &mut settings.exclusions {
+        exclusion.pattern = sanitize_string(exclusion.pattern.trim());
+        if exclusion.pattern.is_empty() {
+            return Err("ERR_VALIDATION: Pattern cannot be empty".to_string());
         }
+        compile_exclusion(&exclusion.pattern)
+            .map_err(|e| format!("ERR_VALIDATION: Invalid pattern: {e}"))?;
+    }
+    settings.user_rules = std::mem::take(&mut settings.user_rules)
+        .into_iter()
+        .map(validate_user_rule)
+        .collect::<Result<_, _>>()?;
+    Ok(settings)
+}
 
-        if let Some(tidy_hour) = prefs.tidy_hour {
-            db_instance
-                .set_preference("tidy_hour", &tidy_hour.to_string())
+/// Writes a validated settings file. Returns the summary and the roots whose
+/// watcher should be started (`true`) or stopped.
+fn apply_settings(
+    db: &Database,
+    settings: SettingsFile<PartialUserPrefs>,
+) -> Result<(SettingsImportSummary, Vec<(String, bool)>), String> {
+    let mut summary = SettingsImportSummary::default();
+    let mut watchers = Vec::new();
+
+    write_prefs(db, settings.prefs)?;
+    if let Some(config) = settings.selector_config {
+        config
+            .save(db)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+
+    for root in settings.roots {
+        let normalized = validate_path(&root.path)
+            .ok()
+            .filter(|path| path.is_dir())
+            .and_then(|path| normalize_directory_path(&path).ok())
+            .filter(|path| !is_system_root(path));
+        let Some(normalized) = normalized else {
+            summary.skipped.push(format!("{}: folder not found", root.path));
+            continue;
+        };
+        let path = normalized.to_string_lossy().to_string();
+        let (ancestors, nested) = db
+            .overlapping_watched_roots(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if let Some(parent) = ancestors.first() {
+            summary
+                .skipped
+                .push(format!("{}: already watched as part of {}", root.path, parent));
+            continue;
+        }
+        for child in nested {
+            db.delete_watched_root(&child)
                 .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+            watchers.push((child, false));
         }
-
-        if let Some(rolling_window_days) = prefs.rolling_window_days {
-            db_instance
-                .set_preference("rolling_window_days", &rolling_window_days.to_string())
+        let existed = db
+            .get_root_settings(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .is_some();
+        db.upsert_watched_root(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        if !existed {
+            let volume_kind = scanner::volume::detect_volume_kind(&normalized);
+            db.set_root_volume_kind(&path, volume_kind)
                 .map_err(|e| format!("ERR_DATABASE: {}", e))?;
         }
+        let current = db
+            .get_root_settings(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?
+            .ok_or_else(|| "ERR_DATABASE: Watched folder not found after insert".to_string())?;
+        db.update_root_settings(&RootSettings {
+            scan_interval_hours: root.scan_interval_hours,
+            watcher_enabled: root.watcher_enabled,
+            low_priority_io: root.low_priority_io,
+            partial_hash_only: root.partial_hash_only,
+            ..current
+        })
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        watchers.push((path.clone(), root.watcher_enabled));
+        if existed {
+            summary.roots_updated.push(path);
+        } else {
+            summary.roots_added.push(path);
+        }
+    }
 
-        if let Some(max_candidates_per_day) = prefs.max_candidates_per_day {
-            db_instance
-                .set_preference(
-                    "max_candidates_per_day",
-                    &max_candidates_per_day.to_string(),
-                )
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let watched = db
+        .list_watched_roots()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let existing: HashSet<(Option<String>, String)> = db
+        .list_exclusions()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .map(|exclusion| (exclusion.root_path, exclusion.pattern))
+        .collect();
+    for exclusion in settings.exclusions {
+        if let Some(root) = exclusion.root_path.as_deref() {
+            if !is_within_watched_roots(Path::new(root), &watched) {
+                summary
+                    .skipped
+                    .push(format!("{} in {}: folder not watched", exclusion.pattern, root));
+                continue;
+            }
+        }
+        if existing.contains(&(exclusion.root_path.clone(), exclusion.pattern.clone())) {
+            continue;
         }
+        db.insert_exclusion(exclusion.root_path.as_deref(), &exclusion.pattern)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        summary.exclusions_added += 1;
+    }
 
-        if let Some(thumbnail_max_size) = prefs.thumbnail_max_size {
-            db_instance
-                .set_preference("thumbnail_max_size", &thumbnail_max_size.to_string())
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let rule_names: HashSet<String> = db
+        .list_user_rules()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .into_iter()
+        .map(|rule| rule.name)
+        .collect();
+    for rule in settings.user_rules {
+        if rule_names.contains(&rule.name) {
+            summary.skipped.push(format!("{}: a rule with this name exists", rule.name));
+            continue;
         }
+        db.insert_user_rule(&rule)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        summary.rules_added += 1;
+    }
 
-        if let Some(auto_scan_enabled) = prefs.auto_scan_enabled {
-            db_instance
-                .set_preference("auto_scan_enabled", &auto_scan_enabled.to_string())
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let protected: HashSet<String> = load_protected_paths(db)?.into_iter().collect();
+    for raw in settings.protected_paths {
+        let normalized = validate_path(raw.trim())
+            .ok()
+            .and_then(|path| normalize_existing_path(&path).ok())
+            .filter(|path| !is_system_root(path));
+        let Some(normalized) = normalized else {
+            summary.skipped.push(format!("{}: path not found", raw));
+            continue;
+        };
+        let path = normalized.to_string_lossy().to_string();
+        if protected.contains(&path) {
+            continue;
         }
+        db.insert_protected_path(&path)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        summary.protected_paths_added += 1;
+    }
 
-        if let Some(scan_interval_hours) = prefs.scan_interval_hours {
-            db_instance
-                .set_preference("scan_interval_hours", &scan_interval_hours.to_string())
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    Ok((summary, watchers))
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DatabaseBackup {
+    pub path: String,
+    pub size_bytes: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Scans and file operations write to the database; a restore underneath
+/// them would leave both the index and the restored copy inconsistent.
+fn ensure_database_idle() -> Result<(), String> {
+    if scanner::current_status().state != "idle" {
+        return Err("ERR_VALIDATION: Wait for the current scan to finish or cancel it".to_string());
+    }
+    if !ops::cancel::active_batch_ids().is_empty() {
+        return Err("ERR_VALIDATION: Wait for the running file operation to finish".to_string());
+    }
+    if maintenance::is_compacting() {
+        return Err("ERR_VALIDATION: Wait for database maintenance to finish".to_string());
+    }
+    Ok(())
+}
+
+/// Writes a consistent copy of the live database to `dest_path`. A folder
+/// gets a timestamped file inside it.
+#[tauri::command]
+pub async fn backup_database(
+    dest_path: String,
+    db: State<'_, DbPool>,
+) -> Result<DatabaseBackup, String> {
+    let dest = PathBuf::from(dest_path.trim());
+    let has_parent_dir = dest
+        .components()
+        .any(|component| matches!(component, std::path::Component::ParentDir));
+    if !dest.is_absolute() || has_parent_dir {
+        return Err("ERR_VALIDATION: Backup path must be absolute".to_string());
+    }
+    let created_at = chrono::Utc::now();
+    let dest = if dest.is_dir() {
+        dest.join(format!("white-space-{}.db", created_at.format("%Y%m%d-%H%M%S")))
+    } else {
+        dest
+    };
+    if !dest.parent().is_some_and(Path::is_dir) {
+        return Err(format!(
+            "ERR_VALIDATION: Folder does not exist: {}",
+            dest.parent().unwrap_or(&dest).display()
+        ));
+    }
+    let live = get_db_path().map_err(command_error_to_string)?;
+    if canonicalize_or_clone(&dest) == canonicalize_or_clone(&live) {
+        return Err("ERR_VALIDATION: Cannot back up the database onto itself".to_string());
+    }
+
+    let db_clone = db.inner().clone();
+    let dest_for_backup = dest.clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        db_instance
+            .backup_to(&dest_for_backup)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    let size_bytes = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+    tracing::info!(path = %dest.display(), size_bytes, "database backed up");
+    Ok(DatabaseBackup {
+        path: dest.to_string_lossy().to_string(),
+        size_bytes,
+        created_at,
+    })
+}
+
+/// Replaces the index, action history and prefs with a backup made by
+/// `backup_database`, then migrates it and re-points the file watchers.
+#[tauri::command]
+pub async fn restore_database(
+    src_path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<(), String> {
+    let src = PathBuf::from(src_path.trim());
+    if !src.is_absolute() || !src.is_file() {
+        return Err(format!("ERR_NOT_FOUND: Backup file not found: {}", src.display()));
+    }
+    ensure_database_idle()?;
+
+    let db_clone = db.inner().clone();
+    let (previous_roots, restored_roots, watcher_roots) = tokio::task::spawn_blocking(move || {
+        let problems = Database::validate_backup(&src)
+            .map_err(|e| format!("ERR_VALIDATION: Not a valid backup: {}", e))?;
+        if !problems.is_empty() {
+            return Err(format!("ERR_VALIDATION: Not a valid backup: {}", problems.join("; ")));
         }
 
-        if let Some(archive_age_threshold_days) = prefs.archive_age_threshold_days {
-            db_instance
-                .set_preference(
-                    "archive_age_threshold_days",
-                    &archive_age_threshold_days.to_string(),
-                )
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let mut db_instance = Database::new(conn);
+        let previous_roots = db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        // Checked again now that the restore is about to start.
+        ensure_database_idle()?;
+        db_instance
+            .restore_from(&src)
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        db_instance
+            .run_migrations()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let restored_roots = db_instance
+            .list_watched_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        let watcher_roots = db_instance
+            .list_watcher_paths()
+            .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+        Ok::<_, String>((previous_roots, restored_roots, watcher_roots))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    for root in previous_roots.iter().filter(|root| !watcher_roots.contains(root)) {
+        if let Err(err) = unregister_root(root) {
+            tracing::warn!("Failed to unregister watcher for {}: {}", root, err);
         }
+    }
+    for root in &watcher_roots {
+        if let Err(err) = register_root(root) {
+            tracing::warn!("Failed to register watcher for {}: {}", root, err);
+        }
+    }
+    tracing::info!(roots = restored_roots.len(), "database restored");
 
-        if let Some(delete_age_threshold_days) = prefs.delete_age_threshold_days {
-            db_instance
-                .set_preference(
-                    "delete_age_threshold_days",
-                    &delete_age_threshold_days.to_string(),
-                )
-                .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let _ = app.emit("roots://changed", serde_json::json!({ "count": restored_roots.len() }));
+    gauge::spawn_gauge_refresh(&app);
+    Ok(())
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RepairReport {
+    /// Problems the full integrity check found before any repair.
+    pub problems: Vec<String>,
+    /// Whether rebuilding the indexes was enough.
+    pub reindexed: bool,
+    /// Set when the database had to be replaced with a fresh one.
+    pub recovery: Option<DatabaseRecovery>,
+}
+
+/// Replaces the live database with an empty, migrated one, keeping a copy of
+/// the damaged file and the watched roots.
+fn rebuild_database(db: &mut Database, problems: Vec<String>) -> Result<DatabaseRecovery, String> {
+    let live = db
+        .file_path()
+        .map_err(|e| format!("ERR_DATABASE: {}", e))?
+        .ok_or_else(|| "ERR_DATABASE: The database is not stored in a file".to_string())?;
+    let recovered_at = Utc::now();
+    let corrupt = corrupt_path_for(&live, &recovered_at);
+    fs::copy(&live, &corrupt)
+        .map_err(|e| format!("ERR_DATABASE: Failed to keep the damaged database: {}", e))?;
+    let roots = db.list_watched_paths().unwrap_or_else(|_| salvage_roots(&corrupt));
+
+    let fresh = live.with_extension("rebuild.db");
+    let _ = fs::remove_file(&fresh);
+    rusqlite::Connection::open(&fresh).map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    let restored = db.restore_from(&fresh);
+    let _ = fs::remove_file(&fresh);
+    restored.map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    db.run_migrations().map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    for root in &roots {
+        db.upsert_watched_root(root).map_err(|e| format!("ERR_DATABASE: {}", e))?;
+    }
+    tracing::warn!(path = %corrupt.display(), "database rebuilt, damaged copy kept");
+    Ok(DatabaseRecovery {
+        corrupt_path: corrupt.to_string_lossy().to_string(),
+        problems,
+        roots,
+        recovered_at,
+    })
+}
+
+/// Runs a full integrity check and repairs what it finds: rebuilding the
+/// indexes first, and only if that is not enough replacing the database
+/// with a fresh one. A rebuild emits `database://recovered` so the UI can
+/// rescan.
+#[tauri::command]
+pub async fn repair_database(
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+    recovery_state: State<'_, RecoveryState>,
+) -> Result<RepairReport, String> {
+    ensure_database_idle()?;
+    let db_clone = db.inner().clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let mut db_instance = Database::new(conn);
+        let problems = db_instance
+            .integrity_problems(true)
+            .unwrap_or_else(|e| vec![e.to_string()]);
+        if problems.is_empty() {
+            return Ok(RepairReport {
+                problems,
+                reindexed: false,
+                recovery: None,
+            });
+        }
+        tracing::error!(?problems, "database failed its integrity check");
+        ensure_database_idle()?;
+        let reindexed = db_instance.reindex().is_ok()
+            && db_instance
+                .integrity_problems(true)
+                .is_ok_and(|left| left.is_empty());
+        let recovery = if reindexed {
+            None
+        } else {
+            Some(rebuild_database(&mut db_instance, problems.clone())?)
+        };
+        Ok::<_, String>(RepairReport {
+            problems,
+            reindexed,
+            recovery,
+        })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if let Some(recovery) = &report.recovery {
+        let _ = app.emit(DATABASE_RECOVERED_EVENT, recovery);
+        recovery_state.set(recovery.clone());
+        gauge::spawn_gauge_refresh(&app);
+    }
+    Ok(report)
+}
+
+/// The database replacement made this session, if any, so a window that
+/// missed `database://recovered` can still offer the rescan.
+#[tauri::command]
+pub async fn get_database_recovery(
+    recovery_state: State<'_, RecoveryState>,
+) -> Result<Option<DatabaseRecovery>, String> {
+    Ok(recovery_state.get())
+}
+
+/// The `whitespace://` link the app was last opened with, if the UI has not
+/// taken it yet. Called on load, since a link that launches the app arrives
+/// before `deep-link://open` can be heard.
+#[tauri::command]
+pub async fn take_pending_deep_link() -> Result<Option<DeepLinkTarget>, String> {
+    Ok(deep_link::take_pending())
+}
+
+/// Rebuilds the database file to return space left by deleted rows, then
+/// refreshes query planner statistics. Progress arrives as
+/// `maintenance://compact-progress` events.
+#[tauri::command]
+pub async fn compact_database(
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<maintenance::CompactReport, String> {
+    ensure_database_idle()?;
+    let db_clone = db.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        maintenance::run_compaction(&app, &db_clone, "manual")
+            .map_err(|e| format!("ERR_DATABASE: {}", e))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Deletes actions, metrics and scan history older than `older_than_days`,
+/// or all of it when omitted. With `root_path`, instead removes every index
+/// row for a folder that is no longer watched.
+#[tauri::command]
+pub async fn clear_history(
+    older_than_days: Option<u32>,
+    root_path: Option<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<maintenance::HistoryCleared, String> {
+    let root = root_path
+        .map(|raw| raw.trim().to_string())
+        .filter(|raw| !raw.is_empty());
+    if let Some(root) = root.as_deref() {
+        if !Path::new(root).is_absolute() {
+            return Err("ERR_VALIDATION: Folder path must be absolute".to_string());
         }
+    }
+    ensure_database_idle()?;
 
-        Ok::<_, String>(())
+    let db_clone = db.inner().clone();
+    let cleared = tokio::task::spawn_blocking(move || {
+        let conn = db_clone.get().map_err(|e| format!("db pool: {e}"))?;
+        let db_instance = Database::new(conn);
+        match root {
+            Some(root) => {
+                let watched = db_instance
+                    .list_watched_paths()
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+                let overlapping = watched.iter().find(|w| {
+                    let watched_path = Path::new(w.as_str());
+                    Path::new(&root).starts_with(watched_path) || watched_path.starts_with(&root)
+                });
+                if let Some(watched_root) = overlapping {
+                    return Err(format!(
+                        "ERR_VALIDATION: {} is still watched; remove it first",
+                        watched_root
+                    ));
+                }
+                let files_removed = db_instance
+                    .purge_root_index(&root)
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))?;
+                Ok(maintenance::HistoryCleared {
+                    files_removed,
+                    ..Default::default()
+                })
+            }
+            None => {
+                let cutoff = match older_than_days {
+                    Some(days) => Utc::now() - Duration::days(days as i64),
+                    None => Utc::now(),
+                };
+                maintenance::clear_history(&db_instance, &cutoff)
+                    .map_err(|e| format!("ERR_DATABASE: {}", e))
+            }
+        }
     })
     .await
     .map_err(|e| format!("join error: {e}"))??;
+    tracing::info!(?cleared, "history cleared");
 
-    Ok(())
+    if cleared.total() > 0 {
+        let pool = db.inner().clone();
+        std::thread::spawn(move || {
+            if let Err(err) = maintenance::maybe_auto_compact(&app, &pool) {
+                tracing::warn!("Compaction after clearing history failed: {err}");
+            }
+        });
+    }
+    Ok(cleared)
 }
 
 // Helper function to get database path
@@ -1,654 +1,3530 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::Database;
-    use crate::models::{ActionType, NewAction, NewFile, WatchedRoot};
-    use chrono::Utc;
-    use std::fs;
-    use std::path::PathBuf;
-    use tempfile::TempDir;
-
-    fn setup_test_db() -> (TempDir, Database) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let db = Database::open_db(&db_path).unwrap();
-        db.run_migrations().unwrap();
-        (temp_dir, db)
+use super::*;
+use crate::db::pool::init_pool;
+use crate::db::Database;
+use crate::models::{ActionType, HashAlgo, NewAction, NewBatch, NewFile, NewStagedFile, WatchedRoot};
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use tauri::async_runtime::block_on;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::Manager;
+use tempfile::TempDir;
+
+fn setup_test_db() -> (TempDir, Database) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::open_db(&db_path).unwrap();
+    db.run_migrations().unwrap();
+    (temp_dir, db)
+}
+
+/// A mock app managing a pool on a migrated temp database, for calling
+/// commands that only take database state.
+fn setup_test_app() -> (TempDir, tauri::App<MockRuntime>) {
+    let (temp_dir, _db) = setup_test_db();
+    let app = mock_app();
+    app.manage(init_pool(&temp_dir.path().join("test.db")));
+    (temp_dir, app)
+}
+
+/// An unhashed 1 KiB file at `path` in its parent folder; tests override
+/// what they care about with struct update syntax.
+fn new_file(path: impl Into<String>) -> NewFile {
+    let path = path.into();
+    let parent_dir = Path::new(&path)
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default();
+    NewFile {
+        path,
+        parent_dir,
+        mime: None,
+        size_bytes: 1024,
+        created_at: None,
+        modified_at: None,
+        accessed_at: None,
+        partial_sha1: None,
+        sha1: None,
+        inode_key: None,
+        link_count: 1,
+        phash: None,
+        is_cloud_placeholder: false,
+        root_id: None,
+        is_symlink: false,
+        symlink_target: None,
+        hash_algo: HashAlgo::CURRENT,
     }
+}
+
+#[test]
+fn test_validate_path_valid() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_path = temp_dir.path().join("test.txt");
+    fs::write(&test_path, b"test").unwrap();
+
+    let result = validate_path(test_path.to_str().unwrap());
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_validate_path_traversal() {
+    let result = validate_path("/home/../etc/passwd");
+    assert!(result.is_err());
+    assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
+}
+
+#[test]
+fn test_validate_path_not_allowed() {
+    let result = validate_path("/");
+    assert!(result.is_err());
+    assert!(matches!(result.unwrap_err(), CommandError::Permission(_)));
+}
+
+#[test]
+fn test_validate_file_ids_empty() {
+    let result = validate_file_ids(&[]);
+    assert!(result.is_err());
+    assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
+}
+
+#[test]
+fn test_validate_file_ids_too_many() {
+    let ids: Vec<i64> = (1..=1001).collect();
+    let result = validate_file_ids(&ids);
+    assert!(result.is_err());
+    assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
+}
+
+#[test]
+fn test_validate_file_ids_invalid() {
+    let result = validate_file_ids(&[0, -1, 5]);
+    assert!(result.is_err());
+    assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
+}
+
+#[test]
+fn test_validate_file_ids_valid() {
+    let result = validate_file_ids(&[1, 2, 3]);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_sanitize_string() {
+    let input = "Hello\x00World\x01Test";
+    let result = sanitize_string(input);
+    assert_eq!(result, "HelloWorldTest");
+}
+
+#[test]
+fn test_sanitize_string_length_limit() {
+    let input = "a".repeat(2000);
+    let result = sanitize_string(&input);
+    assert_eq!(result.len(), 1024);
+}
+
+#[test]
+fn test_scan_roots_empty() {
+    let result = validate_scan_roots(&[]);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_scan_roots_too_many() {
+    let roots: Vec<String> = (1..=11).map(|i| format!("/test/root{}", i)).collect();
+    let result = validate_scan_roots(&roots);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_scan_roots_invalid_path() {
+    let result = validate_scan_roots(&["/etc/passwd".to_string()]);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_daily_candidates_zero_max() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let result = block_on(daily_candidates(0, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_daily_candidates_too_large() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let result = block_on(daily_candidates(1001, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    fn create_test_file(db: &Database, path: &str) -> i64 {
-        let new_file = NewFile {
-            path: path.to_string(),
-            parent_dir: PathBuf::from(path)
-                .parent()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
-            mime: "text/plain".to_string(),
+#[test]
+fn test_filter_candidates_by_root_path_keeps_matching_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root_dir = temp_dir.path().join("root");
+    let nested = root_dir.join("nested");
+    fs::create_dir_all(&nested).unwrap();
+
+    let matching_path = nested.join("Screenshot 2024-01-01.png");
+    fs::write(&matching_path, b"content").unwrap();
+
+    let outside_dir = temp_dir.path().join("outside");
+    fs::create_dir_all(&outside_dir).unwrap();
+    let outside_path = outside_dir.join("Screenshot 2023-01-01.png");
+    fs::write(&outside_path, b"content").unwrap();
+
+    let mut candidates = vec![
+        Candidate {
+            file_id: 1,
+            path: matching_path.to_string_lossy().to_string(),
+            parent_dir: nested.to_string_lossy().to_string(),
             size_bytes: 1024,
-            sha1: "test_hash".to_string(),
-        };
-        db.insert_file(new_file).unwrap()
-    }
+            reason: "Screenshots".to_string(),
+            reason_message: Message::new("bucket.screenshot"),
+            score: 0.9,
+            confidence: 0.9,
+            preview_hint: "".to_string(),
+            hint_messages: Vec::new(),
+            age_days: 10.0,
+            is_cloud_placeholder: false,
+            factors: Default::default(),
+            explanation: String::new(),
+            explanation_messages: Default::default(),
+        },
+        Candidate {
+            file_id: 2,
+            path: outside_path.to_string_lossy().to_string(),
+            parent_dir: outside_dir.to_string_lossy().to_string(),
+            size_bytes: 1024,
+            reason: "Screenshots".to_string(),
+            reason_message: Message::new("bucket.screenshot"),
+            score: 0.8,
+            confidence: 0.8,
+            preview_hint: "".to_string(),
+            hint_messages: Vec::new(),
+            age_days: 20.0,
+            is_cloud_placeholder: false,
+            factors: Default::default(),
+            explanation: String::new(),
+            explanation_messages: Default::default(),
+        },
+    ];
+
+    let mut errors = Vec::new();
+    let root_str = root_dir.to_string_lossy().to_string();
+    filter_candidates_by_root_path(&mut candidates, &root_str, &mut errors);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].path, matching_path.to_string_lossy());
+    assert!(errors.is_empty());
+}
 
-    fn create_test_action(db: &Database, file_id: i64, action: ActionType) -> i64 {
-        let new_action = NewAction {
-            file_id,
-            action,
-            batch_id: Some("test_batch".to_string()),
-            src_path: Some("/test/src".to_string()),
-            dst_path: Some("/test/dst".to_string()),
-            origin: None,
-            note: None,
-        };
-        db.insert_action(new_action).unwrap()
-    }
+#[test]
+fn test_archive_files_empty() {
+    let result = validate_file_ids(&[]).map_err(|e| format!("ERR_VALIDATION: {e}"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_validate_path_valid() {
-        let home = dirs::home_dir().unwrap();
-        let test_path = home.join("Desktop").join("test.txt");
+#[test]
+fn test_archive_files_nonexistent() {
+    let (_temp_dir, db) = setup_test_db();
 
-        let result = validate_path(test_path.to_str().unwrap());
-        assert!(result.is_ok());
-    }
+    let result = resolve_file_paths(&db, &[999]);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
+}
 
-    #[test]
-    fn test_validate_path_traversal() {
-        let result = validate_path("/home/../etc/passwd");
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
-    }
+#[test]
+fn test_delete_files_empty() {
+    let result = validate_file_ids(&[]).map_err(|e| format!("ERR_VALIDATION: {e}"));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_validate_path_not_allowed() {
-        let result = validate_path("/etc/passwd");
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CommandError::Permission(_)));
-    }
+#[test]
+fn test_delete_files_nonexistent() {
+    let (_temp_dir, db) = setup_test_db();
 
-    #[test]
-    fn test_validate_file_ids_empty() {
-        let result = validate_file_ids(&[]);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
-    }
+    let result = resolve_file_paths(&db, &[999]);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
+}
 
-    #[test]
-    fn test_validate_file_ids_too_many() {
-        let ids: Vec<i64> = (1..=1001).collect();
-        let result = validate_file_ids(&ids);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
-    }
+#[test]
+fn test_get_review_items_too_large() {
+    let (_temp_dir, app) = setup_test_app();
 
-    #[test]
-    fn test_validate_file_ids_invalid() {
-        let result = validate_file_ids(&[0, -1, 5]);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), CommandError::Validation(_)));
-    }
+    let result = block_on(get_review_items(366, None, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_validate_file_ids_valid() {
-        let result = validate_file_ids(&[1, 2, 3]);
-        assert!(result.is_ok());
-    }
+#[test]
+fn test_get_thumbnail_invalid_id() {
+    let (_temp_dir, app) = setup_test_app();
 
-    #[test]
-    fn test_sanitize_string() {
-        let input = "Hello\x00World\x01Test";
-        let result = sanitize_string(input);
-        assert_eq!(result, "HelloWorldTest");
-    }
+    let result = block_on(get_thumbnail(0, 256, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_sanitize_string_length_limit() {
-        let input = "a".repeat(2000);
-        let result = sanitize_string(&input);
-        assert_eq!(result.len(), 1000);
-    }
+#[test]
+fn test_get_thumbnail_nonexistent() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let result = block_on(get_thumbnail(999, 256, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
+}
 
-    #[test]
-    fn test_app_state_new() {
-        let (temp_dir, db) = setup_test_db();
-        let db_path = temp_dir.path().join("test.db");
+#[test]
+fn test_get_thumbnail_invalid_size() {
+    let (_temp_dir, app) = setup_test_app();
 
-        let app_state = AppState::new(&db_path);
-        assert!(app_state.is_ok());
-    }
+    let result = block_on(get_thumbnail(1, 0, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_scan_roots_empty() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_get_thumbnail_too_large() {
+    let (_temp_dir, app) = setup_test_app();
 
-        let result = scan_roots(vec![], tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
-    }
+    let result = block_on(get_thumbnail(1, 3000, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_scan_roots_too_many() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_set_prefs_invalid_tidy_hour() {
+    let (_temp_dir, app) = setup_test_app();
 
-        let roots: Vec<String> = (1..=11).map(|i| format!("/test/root{}", i)).collect();
-        let result = scan_roots(roots, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
-    }
+    let prefs = PartialUserPrefs {
+        tidy_hour: Some(24),
+        ..Default::default()
+    };
 
-    #[test]
-    fn test_scan_roots_invalid_path() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
 
-        let result = scan_roots(
-            vec!["/etc/passwd".to_string()],
-            tauri::State::from(&app_state),
-        );
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
-    }
+#[test]
+fn test_set_prefs_invalid_rolling_window() {
+    let (_temp_dir, app) = setup_test_app();
 
-    #[test]
-    fn test_daily_candidates_zero_max() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let prefs = PartialUserPrefs {
+        rolling_window_days: Some(0),
+        ..Default::default()
+    };
 
-        let result = daily_candidates(0, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_set_prefs_invalid_max_candidates() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        max_candidates_per_day: Some(0),
+        ..Default::default()
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_set_prefs_invalid_thumbnail_size() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        thumbnail_max_size: Some(0),
+        ..Default::default()
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_set_prefs_invalid_scan_interval() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        scan_interval_hours: Some(0),
+        ..Default::default()
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_set_prefs_invalid_age_thresholds() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        archive_age_threshold_days: Some(366),
+        delete_age_threshold_days: Some(366),
+        ..Default::default()
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_set_prefs_valid() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        dry_run_default: Some(true),
+        tidy_day: Some("Mon".to_string()),
+        tidy_hour: Some(9),
+        rolling_window_days: Some(7),
+        max_candidates_per_day: Some(10),
+        thumbnail_max_size: Some(512),
+        auto_scan_enabled: Some(true),
+        scan_interval_hours: Some(12),
+        archive_age_threshold_days: Some(7),
+        delete_age_threshold_days: Some(30),
+        scan_concurrency: Some(4),
+        auto_empty_after_cooloff: Some(false),
+        notifications_enabled: Some(true),
+        notify_scan_threshold_gb: Some(2),
+        notify_expiry: Some(true),
+        notify_auto_empty: Some(false),
+        notify_tidy_reminder: Some(true),
+        log_level: Some("debug".to_string()),
+        history_retention_days: Some(90),
+        purge_removed_roots: Some(false),
+        archive_retention_days: Some(30),
+        archive_retention_mode: Some("trash".to_string()),
+        installers_bucket_enabled: Some(true),
+        record_symlinks: Some(false),
+        scan_power_mode: Some("auto".to_string()),
+        locale: Some("en".to_string()),
+        duplicate_policy: Some("keep_newest".to_string()),
+        duplicate_preferred_paths: Some(vec![]),
+        max_full_hash_gb: Some(4),
+        only_clean_when_idle: Some(false),
+        idle_minutes: Some(5),
+        restore_grace_days: Some(14),
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_set_prefs_invalid_scan_concurrency() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let prefs = PartialUserPrefs {
+        scan_concurrency: Some(0),
+        ..Default::default()
+    };
+
+    let result = block_on(set_prefs(prefs, app.state()));
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_get_prefs_defaults() {
+    let (_temp_dir, app) = setup_test_app();
+
+    let result = block_on(get_prefs(app.state()));
+    assert!(result.is_ok());
+
+    let prefs = result.unwrap();
+    assert!(prefs.dry_run_default);
+    assert_eq!(prefs.tidy_day, "Fri");
+    assert_eq!(prefs.tidy_hour, 17);
+    assert_eq!(prefs.rolling_window_days, 7);
+    assert_eq!(prefs.max_candidates_per_day, 12);
+    assert_eq!(prefs.thumbnail_max_size, 256);
+    assert!(!prefs.auto_scan_enabled);
+    assert_eq!(prefs.scan_interval_hours, 24);
+    assert_eq!(prefs.archive_age_threshold_days, 7);
+    assert_eq!(prefs.delete_age_threshold_days, 30);
+}
+
+#[test]
+fn test_get_db_path() {
+    let result = get_db_path();
+    assert!(result.is_ok());
+
+    let path = result.unwrap();
+    assert!(path.to_string_lossy().contains("white-space"));
+    assert!(path.to_string_lossy().contains("database.db"));
+}
+
+#[test]
+fn normalize_directory_path_handles_existing_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let nested = temp_dir.path().join("nested");
+    fs::create_dir(&nested).unwrap();
+
+    let normalized = normalize_directory_path(&nested).expect("should normalize directory");
+
+    assert!(normalized.ends_with("nested"));
+}
+
+#[test]
+fn watched_root_membership_checks_descendants() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    let child = root.join("child");
+    fs::create_dir_all(&child).unwrap();
+
+    let normalized_root = normalize_directory_path(&root).expect("normalize root");
+    let normalized_child = normalize_directory_path(&child).expect("normalize child");
+
+    let roots = vec![WatchedRoot {
+        id: 1,
+        path: normalized_root.to_string_lossy().to_string(),
+        created_at: Utc::now(),
+        volume_kind: Default::default(),
+        is_offline: false,
+    }];
+
+    assert!(is_within_watched_roots(&normalized_child, &roots));
+
+    let outside = temp_dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    let normalized_outside = normalize_directory_path(&outside).expect("normalize outside");
+
+    assert!(!is_within_watched_roots(&normalized_outside, &roots));
+}
+
+#[test]
+fn normalize_existing_path_handles_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("file.txt");
+    fs::write(&file_path, "hello").unwrap();
+
+    let normalized = normalize_existing_path(&file_path).expect("normalize file");
+
+    assert!(normalized.ends_with("file.txt"));
+}
+
+#[test]
+fn ensure_within_watched_rejects_outside() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir(&root).unwrap();
+    let normalized_root = normalize_directory_path(&root).expect("normalize root");
+
+    let roots = vec![WatchedRoot {
+        id: 1,
+        path: normalized_root.to_string_lossy().to_string(),
+        created_at: Utc::now(),
+        volume_kind: Default::default(),
+        is_offline: false,
+    }];
+
+    ensure_within_watched(&normalized_root, &roots).expect("root allowed");
+
+    let outside = temp_dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    let normalized_outside = normalize_directory_path(&outside).expect("normalize outside");
+
+    assert!(ensure_within_watched(&normalized_outside, &roots).is_err());
+}
+
+#[test]
+fn test_command_error_display() {
+    let errors = vec![
+        CommandError::Database("test".to_string()),
+        CommandError::FileSystem("test".to_string()),
+        CommandError::Validation("test".to_string()),
+        CommandError::Permission("test".to_string()),
+        CommandError::NotFound("test".to_string()),
+        CommandError::Internal("test".to_string()),
+    ];
+
+    for error in errors {
+        let display = format!("{}", error);
+        assert!(display.contains("test"));
     }
+}
+
+#[test]
+fn test_archive_outcome_serialization() {
+    let outcome = ArchiveOutcome {
+        success: true,
+        batch_id: Some("archive_1".to_string()),
+        files_processed: 5,
+        total_bytes: 1024 * 1024,
+        duration_ms: 1000,
+        errors: vec!["test error".to_string()],
+        error_messages: vec![crate::ops::OpsError::PermissionError("denied".to_string())
+            .to_message()
+            .with("path", "/home/me/a.txt")],
+        dry_run: false,
+        archive_file: Some("/archive/2024-01-01/archive_1.zip".to_string()),
+        bytes_saved: 2048,
+    };
+
+    let json = serde_json::to_string(&outcome).unwrap();
+    assert!(json.contains("success"));
+    assert!(json.contains("files_processed"));
+    assert!(json.contains(r#""bytes_saved":2048"#));
+    assert!(json.contains(r#""key":"error.permission""#));
+}
+
+#[test]
+fn test_delete_outcome_serialization() {
+    let outcome = DeleteOutcome {
+        success: true,
+        batch_id: Some("delete_1".to_string()),
+        files_processed: 3,
+        total_bytes_freed: 512 * 1024,
+        duration_ms: 500,
+        errors: vec![],
+        error_messages: vec![],
+        to_trash: true,
+        corrupted: vec![],
+    };
+
+    let json = serde_json::to_string(&outcome).unwrap();
+    assert!(json.contains("success"));
+    assert!(json.contains("to_trash"));
+}
+
+#[test]
+fn test_staged_file_serialization() {
+    let staged_file = StagedFile {
+        record_id: 1,
+        file_id: 1,
+        path: "/test/path".to_string(),
+        parent_dir: "/test".to_string(),
+        size_bytes: 1024,
+        status: "staged".to_string(),
+        staged_at: chrono::Utc::now().to_rfc3339(),
+        expires_at: None,
+        batch_id: Some("batch".to_string()),
+        note: None,
+        cooloff_until: None,
+        archive_path: None,
+        status_message: None,
+    };
+
+    let json = serde_json::to_string(&staged_file).unwrap();
+    assert!(json.contains("file_id"));
+    assert!(json.contains("path"));
+}
+
+#[test]
+fn test_user_prefs_serialization() {
+    let prefs = UserPrefs {
+        dry_run_default: true,
+        tidy_day: "Mon".to_string(),
+        tidy_hour: 9,
+        rolling_window_days: 7,
+        max_candidates_per_day: 10,
+        thumbnail_max_size: 512,
+        auto_scan_enabled: true,
+        scan_interval_hours: 12,
+        archive_age_threshold_days: 7,
+        delete_age_threshold_days: 30,
+        scan_concurrency: 4,
+        auto_empty_after_cooloff: false,
+        notifications_enabled: true,
+        notify_scan_threshold_gb: 1,
+        notify_expiry: true,
+        notify_auto_empty: true,
+        notify_tidy_reminder: true,
+        log_level: "info".to_string(),
+        history_retention_days: 0,
+        purge_removed_roots: false,
+        archive_retention_days: 0,
+        archive_retention_mode: "trash".to_string(),
+        installers_bucket_enabled: true,
+        record_symlinks: false,
+        scan_power_mode: "auto".to_string(),
+        locale: "en".to_string(),
+        duplicate_policy: "keep_newest".to_string(),
+        duplicate_preferred_paths: vec![],
+        max_full_hash_gb: 4,
+        only_clean_when_idle: false,
+        idle_minutes: 5,
+        restore_grace_days: 14,
+    };
+
+    let json = serde_json::to_string(&prefs).unwrap();
+    assert!(json.contains("dry_run_default"));
+    assert!(json.contains("tidy_day"));
+}
+
+#[test]
+fn test_partial_user_prefs_deserialization() {
+    let json = r#"{
+        "dry_run_default": true,
+        "tidy_day": "Mon",
+        "tidy_hour": 9,
+        "rolling_window_days": 7,
+        "max_candidates_per_day": 10,
+        "thumbnail_max_size": 512,
+        "auto_scan_enabled": true,
+        "scan_interval_hours": 12,
+        "archive_age_threshold_days": 7,
+        "delete_age_threshold_days": 30
+    }"#;
+
+    let prefs: PartialUserPrefs = serde_json::from_str(json).unwrap();
+    assert_eq!(prefs.dry_run_default, Some(true));
+    assert_eq!(prefs.tidy_day, Some("Mon".to_string()));
+}
+
+#[test]
+fn test_selector_config_partial_deserialization() {
+    let json = r#"{ "buckets": { "old_desktop_min_age_days": 30 } }"#;
+
+    let config: SelectorConfig = serde_json::from_str(json).unwrap();
+    assert_eq!(config.buckets.old_desktop_min_age_days, 30);
+    assert_eq!(config.buckets.big_download_min_mb, 100);
+    assert_eq!(config.scoring.stale_age_days, 30);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_selector_config_rejects_zero_cap() {
+    let mut config = SelectorConfig::default();
+    config.buckets.daily_total_max = 0;
+    assert!(config.validate().is_err());
+}
 
-    #[test]
-    fn test_daily_candidates_too_large() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_ensure_not_protected() {
+    let protected = vec!["/Users/test/Documents/Taxes".to_string()];
+
+    assert!(ensure_not_protected("/Users/test/Documents/Taxes/2023.pdf", &protected)
+        .unwrap_err()
+        .contains("ERR_VALIDATION"));
+    assert!(ensure_not_protected("/Users/test/Documents/Taxes", &protected).is_err());
+    assert!(ensure_not_protected("/Users/test/Documents/TaxesOld/a.pdf", &protected).is_ok());
+}
+
+#[test]
+fn test_parse_dismiss_until() {
+    let date = parse_dismiss_until("2030-01-15").unwrap();
+    assert_eq!(date.to_rfc3339(), "2030-01-15T00:00:00+00:00");
+
+    let ts = parse_dismiss_until("2030-01-15T12:30:00+02:00").unwrap();
+    assert_eq!(ts.to_rfc3339(), "2030-01-15T10:30:00+00:00");
+
+    assert!(parse_dismiss_until("next week").is_err());
+}
 
-        let result = daily_candidates(1001, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_validate_user_rule() {
+    let rule = NewUserRule {
+        name: "  Disk images ".to_string(),
+        path_glob: Some("*.dmg".to_string()),
+        min_size_bytes: None,
+        min_age_days: Some(30),
+        mime_filter: Some(" ".to_string()),
+        max_per_day: Some(10),
+        enabled: None,
+    };
+
+    let validated = validate_user_rule(rule.clone()).unwrap();
+    assert_eq!(validated.name, "Disk images");
+    assert_eq!(validated.mime_filter, None);
+
+    let invalid = NewUserRule {
+        max_per_day: Some(0),
+        ..rule
+    };
+    assert!(validate_user_rule(invalid).unwrap_err().contains("ERR_VALIDATION"));
+}
+
+#[test]
+fn test_search_files_matches_name_and_filters() {
+    let (_temp_dir, db) = setup_test_db();
+    for (path, mime, size) in [
+        ("/watch/docs/Quarterly Report.pdf", "application/pdf", 4096),
+        ("/watch/videos/report-recording.mp4", "video/mp4", 1 << 30),
+        ("/watch/videos/holiday.mov", "video/quicktime", 1 << 20),
+        ("/elsewhere/report.txt", "text/plain", 10),
+    ] {
+        db.upsert_file(&NewFile {
+            mime: Some(mime.to_string()),
+            size_bytes: size,
+            ..new_file(path)
+        })
+        .unwrap();
     }
+    let roots = vec!["/watch".to_string()];
+
+    let (total, items) = db
+        .search_files("REPORT", &FileSearchFilters::default(), &roots, 10, 0, None)
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(items[0].path, "/watch/videos/report-recording.mp4");
+
+    let videos = FileSearchFilters {
+        mime: Some("video/*".to_string()),
+        ..Default::default()
+    };
+    let (total, _) = db.search_files("", &videos, &roots, 10, 0, None).unwrap();
+    assert_eq!(total, 2);
+
+    let (total, items) = db
+        .search_files("report", &FileSearchFilters::default(), &roots, 1, 1, None)
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(items.len(), 1);
+
+    // A file indexed after the first page neither shifts nor joins later pages.
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let cursor = PageCursor {
+        sort: "search".to_string(),
+        key: Some((1i64 << 30) as f64),
+        text: Some("/watch/videos/report-recording.mp4".to_string()),
+        id: 0,
+        snapshot_ms: Utc::now().timestamp_millis(),
+    };
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    db.upsert_file(&NewFile {
+        size_bytes: 1 << 31,
+        ..new_file("/watch/new/report-huge.bin")
+    })
+    .unwrap();
+    let cursor = PageCursor::decode(&cursor.encode()).unwrap();
+    let (total, items) = db
+        .search_files("report", &FileSearchFilters::default(), &roots, 10, 0, Some(&cursor))
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(
+        items.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+        vec!["/watch/docs/Quarterly Report.pdf"]
+    );
+}
 
-    #[test]
-    fn test_filter_candidates_by_root_path_keeps_matching_files() {
-        let temp_dir = TempDir::new().unwrap();
-        let root_dir = temp_dir.path().join("root");
-        let nested = root_dir.join("nested");
-        fs::create_dir_all(&nested).unwrap();
-
-        let matching_path = nested.join("Screenshot 2024-01-01.png");
-        fs::write(&matching_path, b"content").unwrap();
-
-        let outside_dir = temp_dir.path().join("outside");
-        fs::create_dir_all(&outside_dir).unwrap();
-        let outside_path = outside_dir.join("Screenshot 2023-01-01.png");
-        fs::write(&outside_path, b"content").unwrap();
-
-        let mut candidates = vec![
-            Candidate {
-                file_id: 1,
-                path: matching_path.to_string_lossy().to_string(),
-                parent_dir: nested.to_string_lossy().to_string(),
-                size_bytes: 1024,
-                reason: "Screenshots".to_string(),
-                score: 0.9,
-                confidence: 0.9,
-                preview_hint: "".to_string(),
-                age_days: 10.0,
-            },
-            Candidate {
-                file_id: 2,
-                path: outside_path.to_string_lossy().to_string(),
-                parent_dir: outside_dir.to_string_lossy().to_string(),
-                size_bytes: 1024,
-                reason: "Screenshots".to_string(),
-                score: 0.8,
-                confidence: 0.8,
-                preview_hint: "".to_string(),
-                age_days: 20.0,
-            },
-        ];
-
-        let mut errors = Vec::new();
-        let root_str = root_dir.to_string_lossy().to_string();
-        filter_candidates_by_root_path(&mut candidates, &root_str, &mut errors);
-
-        assert_eq!(candidates.len(), 1);
-        assert_eq!(candidates[0].path, matching_path.to_string_lossy());
-        assert!(errors.is_empty());
+#[test]
+fn test_relocate_archived_path_rewrites_files_and_actions() {
+    let (_temp_dir, db) = setup_test_db();
+    let mut ids = Vec::new();
+    for path in [
+        "/old/2024-05-01/a.txt",
+        "/old/2024-05-01/archive_1.zip/b.txt",
+        "/old/2024-05-01/archiveX1.zip/c.txt",
+        "/OLD/2024-05-01/archive_1.zip/d.txt",
+    ] {
+        db.upsert_file(&NewFile {
+            size_bytes: 10,
+            ..new_file(path)
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(path).unwrap().unwrap();
+        ids.push(file_id);
+        db.insert_action(&NewAction {
+            file_id,
+            action: ActionType::Archive,
+            batch_id: Some("batch".to_string()),
+            src_path: Some(format!("/watch/{file_id}")),
+            dst_path: Some(path.to_string()),
+            origin: None,
+            note: None,
+        })
+        .unwrap();
     }
+    let (plain, member, siblings) = (ids[0], ids[1], &ids[2..]);
+
+    let updated = db
+        .relocate_archived_path("/old/2024-05-01/a.txt", "/new/2024-05-01/a.txt")
+        .unwrap();
+    assert_eq!(updated, 2);
+    let updated = db
+        .relocate_archived_path(
+            "/old/2024-05-01/archive_1.zip",
+            "/new/2024-05-01/archive_1.zip",
+        )
+        .unwrap();
+    assert_eq!(updated, 2);
+
+    let moved = db.get_file_by_id(member).unwrap().unwrap();
+    assert_eq!(moved.path, "/new/2024-05-01/archive_1.zip/b.txt");
+    assert_eq!(moved.parent_dir, "/new/2024-05-01/archive_1.zip");
+    assert_eq!(
+        db.get_file_id_by_path("/new/2024-05-01/archive_1.zip/b.txt").unwrap(),
+        Some(member)
+    );
+    assert_eq!(db.get_file_id_by_path("/old/2024-05-01/a.txt").unwrap(), None);
+    assert_eq!(db.get_file_by_id(plain).unwrap().unwrap().parent_dir, "/new/2024-05-01");
+    let actions = db.get_actions_by_batch_id("batch").unwrap();
+    let moved_actions: Vec<_> = actions
+        .iter()
+        .filter(|a| a.dst_path.as_deref().unwrap_or("").starts_with("/new/"))
+        .map(|a| a.file_id)
+        .collect();
+    assert_eq!(moved_actions.len(), 2);
+    assert!(moved_actions.contains(&plain) && moved_actions.contains(&member));
+    // `_` and case only match literally.
+    for id in siblings {
+        let file = db.get_file_by_id(*id).unwrap().unwrap();
+        assert!(file.path.starts_with("/old/") || file.path.starts_with("/OLD/"));
+    }
+}
 
-    #[test]
-    fn test_archive_files_empty() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_nested_watched_roots_are_merged() {
+    let (_temp_dir, db) = setup_test_db();
+    db.upsert_watched_root("/home/me/Downloads").unwrap();
+    db.upsert_watched_root("/home/me/Downloadsx").unwrap();
+    db.upsert_watched_root("/home/me").unwrap();
+
+    assert_eq!(db.list_watched_paths().unwrap(), vec!["/home/me".to_string()]);
+    let (ancestors, nested) = db.overlapping_watched_roots("/home/me/Downloads/sub").unwrap();
+    assert_eq!(ancestors.len(), 2);
+    assert!(nested.is_empty());
+
+    let removed = db.merge_nested_watched_roots().unwrap();
+    assert_eq!(removed.len(), 2);
+    let remaining: Vec<String> =
+        db.list_watched_roots().unwrap().into_iter().map(|r| r.path).collect();
+    assert_eq!(remaining, vec!["/home/me".to_string()]);
+
+    assert_eq!(
+        Database::outermost_roots(&["/a/b".to_string(), "/ab".to_string(), "/a".to_string()]),
+        vec!["/ab".to_string(), "/a".to_string()]
+    );
+}
+
+#[test]
+fn test_index_paths_upserts_and_removes_single_files() {
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().join("root");
+    fs::create_dir_all(root.join("nested")).unwrap();
+    let file = root.join("a.txt");
+    fs::write(&file, "hello").unwrap();
+    fs::write(root.join("nested/b.txt"), "world").unwrap();
+
+    let mut scanner = crate::scanner::Scanner::new();
+    let update = scanner
+        .index_paths(&[file.clone(), root.join("nested")], 100, &db)
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.updated, 2);
+    assert_eq!(db.list_active_paths(None).unwrap().len(), 2);
+
+    fs::remove_file(&file).unwrap();
+    fs::remove_dir_all(root.join("nested")).unwrap();
+    let update = scanner
+        .index_paths(&[file, root.join("nested")], 100, &db)
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.removed, 2);
+    assert!(db.list_active_paths(None).unwrap().is_empty());
+
+    // Too many files for a targeted update: the caller rescans the root.
+    fs::write(root.join("c.txt"), "c").unwrap();
+    fs::write(root.join("d.txt"), "d").unwrap();
+    assert!(scanner.index_paths(&[root], 1, &db).unwrap().is_none());
+}
 
-        let result = archive_files(vec![], tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_scan_history_records_and_prunes() {
+    let (_temp_dir, db) = setup_test_db();
+    let now = Utc::now();
+    for days_ago in [120, 2, 1, 0] {
+        let finished_at = now - chrono::Duration::days(days_ago);
+        db.insert_scan_record(&crate::models::NewScanRecord {
+            roots: vec!["/home/me/Downloads".to_string()],
+            trigger: "watcher".to_string(),
+            status: "completed".to_string(),
+            mode: "normal".to_string(),
+            counted: days_ago as u64,
+            skipped: 0,
+            errors: 0,
+            error_sample: None,
+            duration_ms: 10,
+            started_at: finished_at,
+            finished_at,
+        })
+        .unwrap();
     }
 
-    #[test]
-    fn test_archive_files_nonexistent() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let removed = db
+        .prune_scan_records(&(now - chrono::Duration::days(90)), 2)
+        .unwrap();
+    assert_eq!(removed, 2);
+    let history = db.list_scan_records(10).unwrap();
+    assert_eq!(history.iter().map(|s| s.counted).collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(history[0].roots, vec!["/home/me/Downloads".to_string()]);
+}
 
-        let result = archive_files(vec![999], tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
-    }
+#[test]
+fn test_backup_and_restore_round_trip() {
+    let (temp_dir, db) = setup_test_db();
+    db.upsert_watched_root("/home/me/Downloads").unwrap();
+    db.set_preference("tidy_hour", "9").unwrap();
+    let backup = temp_dir.path().join("backup.db");
+    db.backup_to(&backup).unwrap();
+    assert!(Database::validate_backup(&backup).unwrap().is_empty());
+
+    let (_other_dir, mut fresh) = setup_test_db();
+    fresh.restore_from(&backup).unwrap();
+    fresh.run_migrations().unwrap();
+    assert_eq!(
+        fresh.list_watched_paths().unwrap(),
+        vec!["/home/me/Downloads".to_string()]
+    );
+    assert_eq!(fresh.get_preference("tidy_hour").unwrap().as_deref(), Some("9"));
+
+    let foreign = temp_dir.path().join("foreign.db");
+    rusqlite::Connection::open(&foreign)
+        .unwrap()
+        .execute("CREATE TABLE notes (body TEXT)", [])
+        .unwrap();
+    let problems = Database::validate_backup(&foreign).unwrap();
+    assert!(problems.iter().any(|p| p.contains("missing table files")));
+}
 
-    #[test]
-    fn test_delete_files_empty() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_migrations_are_versioned_and_idempotent() {
+    let (temp_dir, db) = setup_test_db();
+    assert_eq!(db.schema_version().unwrap(), crate::db::database::SCHEMA_VERSION);
+    db.upsert_watched_root("/home/me/Downloads").unwrap();
+
+    db.run_migrations().unwrap();
+    assert_eq!(db.schema_version().unwrap(), crate::db::database::SCHEMA_VERSION);
+    assert_eq!(db.list_watched_paths().unwrap().len(), 1);
+
+    rusqlite::Connection::open(temp_dir.path().join("test.db"))
+        .unwrap()
+        .execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, 'future', ?2)",
+            rusqlite::params![crate::db::database::SCHEMA_VERSION + 1, Utc::now()],
+        )
+        .unwrap();
+    assert!(matches!(
+        db.run_migrations(),
+        Err(crate::db::database::MigrationError::NewerSchema { .. })
+    ));
+}
 
-        let result = delete_files(vec![], true, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_unique_staged_files_migration_keeps_the_latest_staging() {
+    let (temp_dir, db) = setup_test_db();
+    let file_id = db.upsert_file(&new_file("/archive/a.txt")).unwrap();
+    let other_id = db.upsert_file(&new_file("/archive/b.txt")).unwrap();
+    let now = Utc::now();
+    // Written the way builds before the unique index could leave them: the
+    // later insert is not always the later staging.
+    let conn = rusqlite::Connection::open(temp_dir.path().join("test.db")).unwrap();
+    conn.execute_batch(
+        "DROP INDEX idx_staged_files_file_id;
+         DELETE FROM schema_version WHERE version = 20;",
+    )
+    .unwrap();
+    for (id, days_ago, batch) in [
+        (file_id, 1, "newest"),
+        (file_id, 9, "oldest"),
+        (other_id, 3, "same"),
+        (other_id, 3, "same_later"),
+    ] {
+        conn.execute(
+            "INSERT INTO staged_files (file_id, staged_at, batch_id, status) VALUES (?1, ?2, ?3, 'staged')",
+            rusqlite::params![id, (now - chrono::Duration::days(days_ago)).to_rfc3339(), batch],
+        )
+        .unwrap();
     }
 
-    #[test]
-    fn test_delete_files_nonexistent() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    db.run_migrations().unwrap();
+    let mut kept: Vec<(i64, Option<String>)> = db
+        .list_staged_with_files(None)
+        .unwrap()
+        .into_iter()
+        .map(|(record, _)| (record.file_id, record.batch_id))
+        .collect();
+    kept.sort();
+    assert_eq!(
+        kept,
+        vec![
+            (file_id, Some("newest".to_string())),
+            (other_id, Some("same_later".to_string())),
+        ]
+    );
+}
 
-        let result = delete_files(vec![999], true, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
+#[test]
+fn test_compact_reclaims_free_pages() {
+    let (_temp_dir, db) = setup_test_db();
+    let now = Utc::now();
+    for _ in 0..500 {
+        db.insert_scan_record(&crate::models::NewScanRecord {
+            roots: vec!["/home/me/Downloads".to_string()],
+            trigger: "manual".to_string(),
+            status: "failed".to_string(),
+            mode: "normal".to_string(),
+            counted: 0,
+            skipped: 0,
+            errors: 1,
+            error_sample: Some("x".repeat(4096)),
+            duration_ms: 10,
+            started_at: now,
+            finished_at: now,
+        })
+        .unwrap();
     }
+    db.prune_scan_records(&(now + chrono::Duration::days(1)), 0).unwrap();
+    assert!(db.free_space().unwrap().0 > 0);
+
+    let mut steps = Vec::new();
+    let report = crate::maintenance::compact(&db, "manual", |p| steps.push(p.step)).unwrap();
+    assert_eq!(steps, vec!["vacuum", "analyze", "checkpoint"]);
+    assert_eq!(db.free_space().unwrap().0, 0);
+    assert!(report.after_bytes < report.before_bytes);
+    assert_eq!(report.reclaimed_bytes, report.before_bytes - report.after_bytes);
+}
 
-    #[test]
-    fn test_get_review_items_too_large() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_clear_history_and_purge_removed_root() {
+    let (_temp_dir, db) = setup_test_db();
+    let mut ids = Vec::new();
+    for path in ["/home/me/Documents/keep.txt", "/home/me/Old/gone.txt"] {
+        db.upsert_file(&NewFile {
+            size_bytes: 10,
+            ..new_file(path)
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(path).unwrap().unwrap();
+        ids.push(file_id);
+        db.insert_action(&NewAction {
+            file_id,
+            action: ActionType::Archive,
+            batch_id: Some("batch".to_string()),
+            src_path: Some("/src".to_string()),
+            dst_path: Some("/dst".to_string()),
+            origin: None,
+            note: None,
+        })
+        .unwrap();
+    }
+    db.dismiss_file(ids[1], None).unwrap();
+
+    assert_eq!(db.purge_root_index("/home/me/Old").unwrap(), 1);
+    assert!(db.get_file_id_by_path("/home/me/Old/gone.txt").unwrap().is_none());
+    assert!(db.get_file_id_by_path("/home/me/Documents/keep.txt").unwrap().is_some());
+    assert_eq!(db.get_actions_by_batch_id("batch").unwrap().len(), 1);
+
+    let past = Utc::now() - chrono::Duration::days(1);
+    let cleared = crate::maintenance::clear_history(&db, &past).unwrap();
+    assert_eq!(cleared.actions_removed, 0);
+    let cleared = crate::maintenance::clear_history(&db, &Utc::now()).unwrap();
+    assert_eq!(cleared.actions_removed, 1);
+    assert!(db.get_actions_by_batch_id("batch").unwrap().is_empty());
+}
 
-        let result = get_review_items(366, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_metric_samples_filter_by_name() {
+    let (_temp_dir, db) = setup_test_db();
+    for (metric, value) in [
+        ("files_counted", 10.0),
+        ("scan_duration_ms", 250.0),
+        ("files_counted", 12.0),
+    ] {
+        db.insert_metric(&crate::models::NewMetric {
+            metric: metric.to_string(),
+            value,
+            context: Some("scan".to_string()),
+        })
+        .unwrap();
     }
 
-    #[test]
-    fn test_get_thumbnail_invalid_id() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let since = Utc::now() - chrono::Duration::days(1);
+    let samples = db.metric_samples("files_counted", &since).unwrap();
+    assert_eq!(samples.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![10.0, 12.0]);
+    assert!(db
+        .metric_samples("files_counted", &(Utc::now() + chrono::Duration::days(1)))
+        .unwrap()
+        .is_empty());
+}
 
-        let result = get_thumbnail(0, 256, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_archive_retention_purges_old_batches() {
+    let (temp_dir, db) = setup_test_db();
+    let now = Utc::now();
+    for (name, days_expired) in [("old.txt", 40), ("recent.txt", 10)] {
+        let archived = temp_dir.path().join(name);
+        fs::write(&archived, b"archived").unwrap();
+        let path = archived.to_string_lossy().to_string();
+        db.upsert_file(&NewFile {
+            parent_dir: temp_dir.path().to_string_lossy().to_string(),
+            size_bytes: 8,
+            ..new_file(path.clone())
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(&path).unwrap().unwrap();
+        let batch_id = format!("archive_{name}");
+        db.insert_action(&NewAction {
+            file_id,
+            action: ActionType::Archive,
+            batch_id: Some(batch_id.clone()),
+            src_path: Some(format!("/home/me/{name}")),
+            dst_path: Some(path),
+            origin: None,
+            note: None,
+        })
+        .unwrap();
+        db.stage_files(&[crate::models::NewStagedFile {
+            file_id,
+            staged_at: now - chrono::Duration::days(days_expired + 7),
+            expires_at: Some(now - chrono::Duration::days(days_expired)),
+            batch_id: Some(batch_id),
+            status: "expired".to_string(),
+            note: None,
+        }])
+        .unwrap();
     }
 
-    #[test]
-    fn test_get_thumbnail_nonexistent() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let payload = ops::expiry::sweep_retention(&db, now, 30, false)
+        .unwrap()
+        .expect("old archive is past retention");
+    assert_eq!(payload.files_purged, 1);
+    assert_eq!(payload.archive_batch_ids, vec!["archive_old.txt".to_string()]);
+    assert!(!temp_dir.path().join("old.txt").exists());
+    assert!(temp_dir.path().join("recent.txt").exists());
+
+    let undoable = db.get_undoable_batches().unwrap();
+    assert!(!undoable.contains(&"archive_old.txt".to_string()));
+    assert!(undoable.contains(&"archive_recent.txt".to_string()));
+    assert!(ops::expiry::sweep_retention(&db, now, 30, false).unwrap().is_none());
+}
 
-        let result = get_thumbnail(999, 256, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_NOT_FOUND"));
+#[test]
+fn test_undo_files_restores_only_chosen_files() {
+    let (temp_dir, db) = setup_test_db();
+    let archive_dir = temp_dir.path().join("archive");
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir_all(&archive_dir).unwrap();
+    let mut ids = Vec::new();
+    for name in ["a.txt", "b.txt"] {
+        let archived = archive_dir.join(name);
+        fs::write(&archived, name).unwrap();
+        let path = archived.to_string_lossy().to_string();
+        db.upsert_file(&NewFile {
+            parent_dir: archive_dir.to_string_lossy().to_string(),
+            size_bytes: 5,
+            ..new_file(path.clone())
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(&path).unwrap().unwrap();
+        ids.push(file_id);
+        db.insert_action(&NewAction {
+            file_id,
+            action: ActionType::Archive,
+            batch_id: Some("batch".to_string()),
+            src_path: Some(home_dir.join(name).to_string_lossy().to_string()),
+            dst_path: Some(path),
+            origin: None,
+            note: None,
+        })
+        .unwrap();
     }
 
-    #[test]
-    fn test_get_thumbnail_invalid_size() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let mut undo = UndoManager::new();
+    let result = undo.undo_files("batch", &[ids[0]], &db).unwrap();
+    assert_eq!(result.files_restored, 1);
+    assert!(home_dir.join("a.txt").exists());
+    assert!(archive_dir.join("b.txt").exists());
+    assert!(db.get_undoable_batches().unwrap().contains(&"batch".to_string()));
+    let remaining = undo.get_batch_by_id("batch", &db).unwrap();
+    assert_eq!(remaining.actions.len(), 1);
+    assert_eq!(remaining.actions[0].file_id, ids[1]);
+
+    // Already restored, and a file whose archived copy is gone.
+    assert!(undo.undo_files("batch", &[ids[0]], &db).is_err());
+    fs::remove_file(archive_dir.join("b.txt")).unwrap();
+    assert!(undo.undo_files("batch", &[ids[1]], &db).is_err());
+}
 
-        let result = get_thumbnail(1, 0, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_find_corrupted_detects_changed_archives() {
+    let (temp_dir, db) = setup_test_db();
+    let mut files = Vec::new();
+    for name in ["intact.txt", "changed.txt", "unhashed.txt"] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, b"original").unwrap();
+        let sha1 = (name != "unhashed.txt")
+            .then(|| crate::scanner::hash::hash_full(&path).unwrap());
+        let path = path.to_string_lossy().to_string();
+        db.upsert_file(&NewFile {
+            parent_dir: temp_dir.path().to_string_lossy().to_string(),
+            size_bytes: 8,
+            sha1,
+            ..new_file(path.clone())
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(&path).unwrap().unwrap();
+        files.push(db.get_file_by_id(file_id).unwrap().unwrap());
     }
+    fs::write(temp_dir.path().join("changed.txt"), b"bit rot!").unwrap();
+    fs::write(temp_dir.path().join("unhashed.txt"), b"whatever").unwrap();
 
-    #[test]
-    fn test_get_thumbnail_too_large() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let corrupted = ops::verify::find_corrupted(&files);
+    assert_eq!(corrupted.len(), 1);
+    assert!(corrupted[0].path.ends_with("changed.txt"));
+    assert!(corrupted[0].actual_hash.is_some());
+}
 
-        let result = get_thumbnail(1, 3000, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
-    }
+#[test]
+fn test_recorded_open_counts_as_activity() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(120);
+    db.upsert_file(&NewFile {
+        size_bytes: 4096,
+        created_at: Some(old),
+        modified_at: Some(old),
+        accessed_at: Some(old),
+        ..new_file("/test/Downloads/report.pdf")
+    })
+    .unwrap();
+    let file_id = db
+        .get_file_id_by_path("/test/Downloads/report.pdf")
+        .unwrap()
+        .unwrap();
+
+    let scorer = crate::selector::scoring::FileScorer::new();
+    let before = db.get_file_by_id(file_id).unwrap().unwrap();
+    assert!(scorer.calculate_age_days(&before) >= 119.0);
+    assert!(!scorer.opened_recently(&before));
+
+    assert!(db.record_file_opened(file_id, Utc::now()).unwrap());
+    assert!(!db.record_file_opened(file_id + 1, Utc::now()).unwrap());
+    let after = db.get_file_by_id(file_id).unwrap().unwrap();
+    assert!(after.last_opened_at.is_some());
+    assert!(scorer.calculate_age_days(&after) < 1.0);
+    assert!(scorer.opened_recently(&after));
+}
 
-    #[test]
-    fn test_set_prefs_invalid_tidy_hour() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_installers_bucket_respects_age_and_pref() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let recent = Utc::now() - chrono::Duration::days(2);
+    for (name, at) in [
+        ("Setup.EXE", old),
+        ("tool.AppImage", old),
+        ("fresh.dmg", recent),
+        ("notes.txt", old),
+    ] {
+        db.upsert_file(&NewFile {
+            created_at: Some(at),
+            modified_at: Some(at),
+            accessed_at: Some(at),
+            ..new_file(format!("/home/u/Downloads/{name}"))
+        })
+        .unwrap();
+    }
+    let roots = vec!["/home/u".to_string()];
+    let installers = |db: &Database| -> Vec<String> {
+        let mut paths: Vec<String> = FileSelector::from_db(db)
+            .daily_candidates_under_roots(&roots, Some(100), db)
+            .unwrap()
+            .into_iter()
+            .filter(|c| c.reason == "Installers")
+            .map(|c| c.path)
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    assert_eq!(
+        installers(&db),
+        vec!["/home/u/Downloads/Setup.EXE", "/home/u/Downloads/tool.AppImage"]
+    );
+    db.set_preference(INSTALLERS_BUCKET_PREF, "false").unwrap();
+    assert!(installers(&db).is_empty());
+}
 
-        let prefs = PartialUserPrefs {
-            tidy_hour: Some(24),
-            ..Default::default()
-        };
+#[test]
+fn test_build_artifacts_and_directory_staging() {
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().canonicalize().unwrap().join("code");
+    let repo = root.join("old-app");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(repo.join("node_modules/left-pad/dist")).unwrap();
+    fs::write(repo.join("node_modules/left-pad/dist/index.js"), vec![b'x'; 300]).unwrap();
+    fs::create_dir_all(repo.join("packages/web/dist")).unwrap();
+    fs::write(repo.join("packages/web/dist/app.js"), vec![b'y'; 100]).unwrap();
+    fs::write(repo.join("README.md"), "hi").unwrap();
+
+    let detector = ActiveProjectDetector::new();
+    let dirs = detector.find_artifact_dirs(&repo);
+    let kinds: Vec<&str> = dirs.iter().map(|d| d.kind.as_str()).collect();
+    assert_eq!(kinds, vec!["node_modules", "dist"]);
+    assert_eq!((dirs[0].size_bytes, dirs[0].file_count), (300, 1));
+    let roots = vec![root.to_string_lossy().to_string()];
+    // Just created, so the repo still counts as active.
+    assert!(detector.build_artifacts(&roots, 30).is_empty());
+    assert_eq!(detector.build_artifacts(&roots, -1)[0].total_bytes, 400);
+
+    db.upsert_watched_root(&roots[0]).unwrap();
+    let node_modules = repo.join("node_modules");
+    let file_id = index_directory(&db, &node_modules.to_string_lossy()).unwrap();
+    let entry = db.get_file_by_id(file_id).unwrap().unwrap();
+    assert_eq!(entry.mime.as_deref(), Some(DIRECTORY_MIME));
+    assert_eq!(entry.size_bytes, 300);
+    assert!(index_directory(&db, &roots[0]).is_err());
+    assert!(index_directory(&db, &repo.join("README.md").to_string_lossy()).is_err());
+
+    let mut archive_manager = ArchiveManager::new();
+    archive_manager.update_config(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        compression: ops::ArchiveCompression::Zip,
+        ..ArchiveConfig::default()
+    });
+    let result = archive_manager
+        .archive_files(vec![entry.path.clone()], &db)
+        .unwrap();
+    assert!(result.errors.is_empty());
+    assert_eq!(result.total_bytes, 300);
+    assert!(result.archive_file.is_none());
+    assert!(!node_modules.exists());
+    let moved = db.get_file_by_id(file_id).unwrap().unwrap();
+    assert!(Path::new(&moved.path).join("left-pad/dist/index.js").exists());
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_cloud_placeholders_free_no_space() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(200);
+    for (name, placeholder) in [("synced.mov", true), ("local.mov", false)] {
+        db.upsert_file(&NewFile {
+            mime: Some("video/quicktime".to_string()),
+            size_bytes: 500 * 1024 * 1024,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            is_cloud_placeholder: placeholder,
+            ..new_file(format!("/test/Drive/{name}"))
+        })
+        .unwrap();
     }
+    let synced_id = db.get_file_id_by_path("/test/Drive/synced.mov").unwrap().unwrap();
+    let local_id = db.get_file_id_by_path("/test/Drive/local.mov").unwrap().unwrap();
+
+    let synced = db.get_file_by_id(synced_id).unwrap().unwrap();
+    assert!(synced.is_cloud_placeholder);
+    let unreclaimable = db.unreclaimable_file_ids().unwrap();
+    assert!(unreclaimable.contains(&synced_id));
+    assert!(!unreclaimable.contains(&local_id));
+
+    let scorer = crate::selector::scoring::FileScorer::new();
+    let context = crate::selector::scoring::ScoringContext::new();
+    let factors = scorer.extract_score_factors(&synced, &context);
+    assert_eq!(factors.size_bytes, 0);
+    assert!(scorer
+        .generate_preview_hint(&synced, &factors)
+        .contains("cloud-only"));
+}
+
+#[test]
+fn test_root_settings_round_trip() {
+    let (_temp_dir, db) = setup_test_db();
+    db.upsert_watched_root("/test/nas").unwrap();
+    db.upsert_watched_root("/test/desktop").unwrap();
+
+    let defaults = db.get_root_settings("/test/nas").unwrap().unwrap();
+    assert_eq!(defaults.scan_interval_hours, None);
+    assert!(defaults.watcher_enabled);
+    assert!(!defaults.low_priority_io);
+    assert!(defaults.last_scanned_at.is_none());
+
+    let nas = RootSettings {
+        scan_interval_hours: Some(24),
+        watcher_enabled: false,
+        low_priority_io: true,
+        ..defaults
+    };
+    assert!(db.update_root_settings(&nas).unwrap());
+    assert!(!db
+        .update_root_settings(&RootSettings {
+            path: "/test/missing".to_string(),
+            ..nas.clone()
+        })
+        .unwrap());
+    assert_eq!(db.get_root_settings("/test/nas").unwrap().unwrap(), nas);
+    assert_eq!(db.list_watcher_paths().unwrap(), vec!["/test/desktop".to_string()]);
+
+    let now = Utc::now();
+    assert_eq!(
+        crate::scanner::schedule::due_roots(&db.list_root_settings().unwrap(), &now),
+        vec!["/test/nas".to_string()]
+    );
+    db.mark_roots_scanned(&["/test/nas".to_string()], &now).unwrap();
+    let scanned = db.get_root_settings("/test/nas").unwrap().unwrap();
+    assert!(scanned.last_scanned_at.is_some());
+    assert!(crate::scanner::schedule::due_roots(&[scanned], &now).is_empty());
+}
 
-    #[test]
-    fn test_set_prefs_invalid_rolling_window() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[test]
+fn test_partial_hash_roots_skip_full_hashes() {
+    let (temp_dir, db) = setup_test_db();
+    let nas = temp_dir.path().join("nas");
+    let local = temp_dir.path().join("local");
+    fs::create_dir_all(&nas).unwrap();
+    fs::create_dir_all(&local).unwrap();
+    fs::write(nas.join("a.txt"), "same").unwrap();
+    fs::write(local.join("b.txt"), "same").unwrap();
+
+    let nas_path = nas.to_string_lossy().to_string();
+    db.upsert_watched_root(&nas_path).unwrap();
+    db.set_root_volume_kind(&nas_path, VolumeKind::Network).unwrap();
+    let settings = db.get_root_settings(&nas_path).unwrap().unwrap();
+    assert_eq!(settings.volume_kind, VolumeKind::Network);
+    db.update_root_settings(&RootSettings {
+        partial_hash_only: true,
+        ..settings
+    })
+    .unwrap();
+    let roots = db.list_watched_roots().unwrap();
+    assert_eq!(watched_root_to_folder(roots[0].clone()).volume_kind, VolumeKind::Network);
+
+    let partial_roots = crate::scanner::roots_where(&db, |root| root.partial_hash_only);
+    assert_eq!(partial_roots, HashSet::from([nas_path]));
+    crate::scanner::Scanner::new()
+        .with_partial_hash_roots(partial_roots)
+        .index_paths(&[nas.join("a.txt"), local.join("b.txt")], 100, &db)
+        .unwrap()
+        .unwrap();
+
+    let file_for = |path: PathBuf| {
+        let id = db
+            .get_file_id_by_path(&path.to_string_lossy())
+            .unwrap()
+            .unwrap();
+        db.get_file_by_id(id).unwrap().unwrap()
+    };
+    let on_nas = file_for(nas.join("a.txt"));
+    assert!(on_nas.partial_sha1.is_some());
+    assert!(on_nas.sha1.is_none());
+    assert!(file_for(local.join("b.txt")).sha1.is_some());
+}
 
-        let prefs = PartialUserPrefs {
-            rolling_window_days: Some(0),
-            ..Default::default()
-        };
+#[test]
+fn test_unplugged_root_goes_offline_instead_of_deleted() {
+    let (temp_dir, db) = setup_test_db();
+    let drive = temp_dir.path().join("drive");
+    fs::create_dir_all(&drive).unwrap();
+    fs::write(drive.join("movie.mkv"), "frames").unwrap();
+    let drive_path = drive.to_string_lossy().to_string();
+    db.upsert_watched_root(&drive_path).unwrap();
+
+    let mut scanner = crate::scanner::Scanner::new();
+    scanner
+        .index_paths(&[drive.join("movie.mkv")], 100, &db)
+        .unwrap()
+        .unwrap();
+
+    // Unmounted: the mount point is left behind, empty.
+    fs::remove_file(drive.join("movie.mkv")).unwrap();
+    assert!(crate::scanner::root_went_offline(&db, &drive_path));
+    let update = scanner.index_paths(std::slice::from_ref(&drive), 100, &db).unwrap().unwrap();
+    assert_eq!(update.removed, 0);
+    assert_eq!(db.list_offline_root_paths().unwrap(), vec![drive_path.clone()]);
+    assert!(db.list_online_watched_paths().unwrap().is_empty());
+    let id = db
+        .get_file_id_by_path(&drive.join("movie.mkv").to_string_lossy())
+        .unwrap()
+        .unwrap();
+    let file = db.get_file_by_id(id).unwrap().unwrap();
+    assert!(file.is_offline);
+    assert!(!file.is_deleted);
+    let roots = db.list_watched_roots().unwrap();
+    assert!(watched_root_to_folder(roots[0].clone()).is_offline);
+
+    // Remounted.
+    fs::write(drive.join("movie.mkv"), "frames").unwrap();
+    assert!(!crate::scanner::root_went_offline(&db, &drive_path));
+    assert_eq!(db.set_root_offline(&drive_path, false).unwrap(), 1);
+    assert!(!db.get_file_by_id(id).unwrap().unwrap().is_offline);
+    assert_eq!(db.list_online_watched_paths().unwrap(), vec![drive_path]);
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_files_reconcile_by_watched_root_id() {
+    let (temp_dir, db) = setup_test_db();
+    let photos = temp_dir.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    fs::write(photos.join("a.jpg"), "a").unwrap();
+    fs::write(photos.join("b.jpg"), "b").unwrap();
+    let photos_path = photos.to_string_lossy().to_string();
+    let root_id = db.upsert_watched_root(&photos_path).unwrap();
+
+    let mut scanner = crate::scanner::Scanner::new();
+    scanner
+        .index_paths(&[photos.join("a.jpg"), photos.join("b.jpg")], 100, &db)
+        .unwrap()
+        .unwrap();
+
+    // Other spellings of the root resolve to the same id.
+    assert_eq!(db.watched_root_id_for(&photos_path).unwrap(), Some(root_id));
+    let trailing = format!("{}{}", photos_path, std::path::MAIN_SEPARATOR);
+    assert_eq!(db.watched_root_id_for(&trailing).unwrap(), Some(root_id));
+    #[cfg(unix)]
+    {
+        let link = temp_dir.path().join("photos-link");
+        std::os::unix::fs::symlink(&photos, &link).unwrap();
+        let link_path = link.to_string_lossy().to_string();
+        assert_eq!(db.watched_root_id_for(&link_path).unwrap(), Some(root_id));
     }
 
-    #[test]
-    fn test_set_prefs_invalid_max_candidates() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let file_id = |path: &std::path::Path| {
+        db.get_file_id_by_path(&path.to_string_lossy()).unwrap().unwrap()
+    };
+    let a = file_id(&photos.join("a.jpg"));
+    let b = file_id(&photos.join("b.jpg"));
+    let seen: HashSet<String> = [photos.join("a.jpg").to_string_lossy().to_string()].into();
+    db.mark_missing_for_root_id(root_id, &seen).unwrap();
+    assert!(!db.get_file_by_id(a).unwrap().unwrap().is_deleted);
+    assert!(db.get_file_by_id(b).unwrap().unwrap().is_deleted);
+
+    // Files indexed before their root was added are adopted by it.
+    let docs = temp_dir.path().join("docs");
+    fs::create_dir_all(&docs).unwrap();
+    fs::write(docs.join("notes.txt"), "notes").unwrap();
+    scanner.index_paths(&[docs.join("notes.txt")], 100, &db).unwrap().unwrap();
+    let docs_id = db.upsert_watched_root(&docs.to_string_lossy()).unwrap();
+    db.mark_missing_for_root_id(docs_id, &HashSet::new()).unwrap();
+    let notes = file_id(&docs.join("notes.txt"));
+    assert!(db.get_file_by_id(notes).unwrap().unwrap().is_deleted);
+}
 
-        let prefs = PartialUserPrefs {
-            max_candidates_per_day: Some(0),
-            ..Default::default()
-        };
+#[test]
+fn test_removing_root_removes_only_its_files() {
+    let (temp_dir, db) = setup_test_db();
+    let mut scanner = crate::scanner::Scanner::new();
+    let mut add_root = |name: &str| {
+        let root = temp_dir.path().join(name);
+        fs::create_dir_all(&root).unwrap();
+        let files = [root.join("a.txt"), root.join("b.txt")];
+        for file in &files {
+            fs::write(file, name).unwrap();
+        }
+        let id = db.upsert_watched_root(&root.to_string_lossy()).unwrap();
+        scanner.index_paths(&files, 100, &db).unwrap().unwrap();
+        let ids: Vec<i64> = files
+            .iter()
+            .map(|f| db.get_file_id_by_path(&f.to_string_lossy()).unwrap().unwrap())
+            .collect();
+        (id, ids)
+    };
+    let (old_root, old_files) = add_root("old");
+    // A sibling whose name shares the prefix must be left alone.
+    let (kept_root, kept_files) = add_root("old-photos");
+    let (purged_root, purged_files) = add_root("purged");
+    db.stage_files(&[crate::models::NewStagedFile {
+        file_id: old_files[1],
+        staged_at: Utc::now(),
+        expires_at: None,
+        batch_id: Some("batch".to_string()),
+        status: "staged".to_string(),
+        note: None,
+    }])
+    .unwrap();
+    db.dismiss_file(purged_files[0], None).unwrap();
+
+    let (_, removed) = db.remove_watched_root(old_root, false).unwrap().unwrap();
+    assert_eq!(removed, 1);
+    assert!(db.get_file_by_id(old_files[0]).unwrap().unwrap().is_deleted);
+    let staged = db.get_file_by_id(old_files[1]).unwrap().unwrap();
+    assert!(staged.is_staged && !staged.is_deleted);
+    assert!(kept_files
+        .iter()
+        .all(|id| !db.get_file_by_id(*id).unwrap().unwrap().is_deleted));
+
+    let (_, removed) = db.remove_watched_root(purged_root, true).unwrap().unwrap();
+    assert_eq!(removed, 2);
+    assert!(purged_files.iter().all(|id| db.get_file_by_id(*id).unwrap().is_none()));
+
+    let remaining: Vec<i64> = db.list_watched_roots().unwrap().iter().map(|r| r.id).collect();
+    assert_eq!(remaining, vec![kept_root]);
+    assert!(db.remove_watched_root(old_root, false).unwrap().is_none());
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
-    }
+#[test]
+fn test_path_keys_merge_spellings_of_one_file() {
+    let (_temp_dir, db) = setup_test_db();
+    let nfd = "/Users/me/Cafe\u{301}.txt";
+    let nfc_upper = "/Users/me/CAF\u{c9}.txt";
+    // Only case- and normalization-insensitive filesystems fold paths.
+    let folds = cfg!(any(target_os = "macos", windows));
+    assert_eq!(Database::path_key(nfd) == Database::path_key(nfc_upper), folds);
+
+    let upsert = |path: &str| {
+        db.upsert_file(&NewFile {
+            parent_dir: "/Users/me".to_string(),
+            mime: Some("text/plain".to_string()),
+            size_bytes: 10,
+            sha1: Some("same".to_string()),
+            ..new_file(path)
+        })
+        .unwrap()
+    };
+    let first = upsert(nfd);
+    let second = upsert(nfc_upper);
+    assert_eq!(first == second, folds);
+    assert_eq!(db.get_file_by_id(second).unwrap().unwrap().path, nfc_upper);
+    assert_eq!(db.get_file_id_by_path(nfd).unwrap(), Some(first));
+    // One file is never a duplicate of itself.
+    assert_eq!(db.duplicate_groups(None).unwrap().is_empty(), folds);
+}
 
-    #[test]
-    fn test_set_prefs_invalid_thumbnail_size() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+#[cfg(unix)]
+#[test]
+fn test_symlinks_are_recorded_without_following() {
+    use std::os::unix::fs::symlink;
+
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().join("root");
+    let elsewhere = temp_dir.path().join("elsewhere");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&elsewhere).unwrap();
+    fs::write(root.join("target.txt"), "data").unwrap();
+    fs::write(elsewhere.join("inside.txt"), "data").unwrap();
+    symlink(root.join("target.txt"), root.join("live")).unwrap();
+    symlink(root.join("missing.txt"), root.join("dead")).unwrap();
+    symlink(&elsewhere, root.join("dir-link")).unwrap();
+    let root_path = root.to_string_lossy().to_string();
+    db.upsert_watched_root(&root_path).unwrap();
+    let indexed = |name: &str| {
+        db.get_file_id_by_path(&root.join(name).to_string_lossy()).unwrap()
+    };
+
+    let mut scanner = crate::scanner::Scanner::new();
+    scanner.index_paths(std::slice::from_ref(&root), 100, &db).unwrap().unwrap();
+    assert!(indexed("target.txt").is_some());
+    assert!(indexed("live").is_none() && indexed("dead").is_none());
+
+    db.set_preference(crate::scanner::RECORD_SYMLINKS_PREF, "true").unwrap();
+    scanner.index_paths(std::slice::from_ref(&root), 100, &db).unwrap().unwrap();
+    let live = db.get_file_by_id(indexed("live").unwrap()).unwrap().unwrap();
+    assert!(live.is_symlink && live.sha1.is_none());
+    assert_eq!(live.size_bytes, 0);
+    assert_eq!(
+        live.symlink_target.as_deref(),
+        Some(&*root.join("target.txt").to_string_lossy())
+    );
+    let dead = db.get_file_by_id(indexed("dead").unwrap()).unwrap().unwrap();
+    assert!(dead.is_symlink && dead.size_bytes > 0);
+    assert!(indexed("dir-link").is_some());
+    assert!(indexed("dir-link/inside.txt").is_none());
+
+    let reasons: Vec<(String, String)> = FileSelector::from_db(&db)
+        .daily_candidates_under_roots(&[root_path], Some(100), &db)
+        .unwrap()
+        .into_iter()
+        .filter(|c| c.path != root.join("target.txt").to_string_lossy())
+        .map(|c| (c.path, c.reason))
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![(
+            root.join("dead").to_string_lossy().to_string(),
+            "Broken Symlinks".to_string()
+        )]
+    );
+}
 
-        let prefs = PartialUserPrefs {
-            thumbnail_max_size: Some(0),
-            ..Default::default()
-        };
+#[test]
+fn test_legacy_sha1_rows_rehash_when_a_copy_appears() {
+    let (temp_dir, db) = setup_test_db();
+    let old = temp_dir.path().join("old.txt");
+    let copy = temp_dir.path().join("copy.txt");
+    fs::write(&old, "same contents").unwrap();
+    fs::write(&copy, "same contents").unwrap();
+    let sha1 = crate::scanner::hash::hash_full_with(&old, HashAlgo::Sha1).unwrap();
+    let legacy = |sha1: Option<String>, hash_algo: HashAlgo| NewFile {
+        parent_dir: temp_dir.path().to_string_lossy().to_string(),
+        size_bytes: 13,
+        partial_sha1: sha1.clone(),
+        sha1,
+        hash_algo,
+        ..new_file(old.to_string_lossy().to_string())
+    };
+    db.upsert_file(&legacy(Some(sha1.clone()), HashAlgo::Sha1)).unwrap();
+    assert_eq!(sha1.len(), 40);
+
+    crate::scanner::Scanner::new().index_paths(std::slice::from_ref(&copy), 100, &db).unwrap().unwrap();
+    let groups = db.duplicate_groups(None).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].1.len(), 2);
+    assert!(groups[0].1.iter().all(|file| file.hash_algo == HashAlgo::CURRENT));
+    assert_ne!(groups[0].0, sha1);
+
+    // A rescan under a new algorithm without a full hash drops the old one.
+    db.upsert_file(&legacy(Some(sha1.clone()), HashAlgo::Sha1)).unwrap();
+    db.upsert_file(&legacy(None, HashAlgo::CURRENT)).unwrap();
+    let id = db.get_file_id_by_path(&old.to_string_lossy()).unwrap().unwrap();
+    let file = db.get_file_by_id(id).unwrap().unwrap();
+    assert_eq!(file.hash_algo, HashAlgo::CURRENT);
+    assert!(file.sha1.is_none());
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_sampled_fingerprint_separates_same_header_files() {
+    let (temp_dir, db) = setup_test_db();
+    let header = vec![7u8; 512 * 1024];
+    let mut paths = Vec::new();
+    for (name, middle) in [("a.mkv", 1u8), ("b.mkv", 2u8), ("c.mkv", 1u8)] {
+        let mut contents = header.clone();
+        contents.extend(vec![middle; 4 * 1024 * 1024]);
+        contents.extend(&header);
+        let path = temp_dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        paths.push(path);
     }
 
-    #[test]
-    fn test_set_prefs_invalid_scan_interval() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    crate::scanner::Scanner::new().index_paths(&paths, 100, &db).unwrap().unwrap();
+    let files: Vec<_> = paths
+        .iter()
+        .map(|path| {
+            let id = db.get_file_id_by_path(&path.to_string_lossy()).unwrap().unwrap();
+            db.get_file_by_id(id).unwrap().unwrap()
+        })
+        .collect();
+    assert_ne!(files[0].partial_sha1, files[1].partial_sha1);
+    assert_eq!(files[0].partial_sha1, files[2].partial_sha1);
+    // Only the real copies were worth reading in full.
+    assert!(files[1].sha1.is_none());
+    assert!(files[0].sha1.is_some());
+    assert_eq!(files[0].sha1, files[2].sha1);
+}
 
-        let prefs = PartialUserPrefs {
-            scan_interval_hours: Some(0),
+#[test]
+fn test_bucket_prefilter_loads_only_possible_candidates() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let add = |path: &str, size_bytes: i64, sha1: Option<&str>| {
+        db.upsert_file(&NewFile {
+            size_bytes,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            sha1: sha1.map(str::to_string),
+            ..new_file(path)
+        })
+        .unwrap();
+    };
+    let big = 500 * 1024 * 1024;
+    add("/home/u/Documents/report.pdf", 1024, None);
+    add("/home/u/Documents/disk.iso", big, None);
+    add("/home/u/Documents/a.bin", 1024, Some("abc"));
+    add("/home/u/Music/b.bin", 1024, Some("abc"));
+    add("/home/u/Downloads/movie.mkv", big, None);
+    add("/home/u/Downloads/small.mkv", 1024, None);
+    add("/home/u/Pictures/Screen Shot 2024.png", 1024, None);
+    add("/home/u/Desktop/todo.txt", 1024, None);
+
+    let roots = vec!["/home/u".to_string()];
+    let selector = FileSelector::from_db(&db);
+    let mut loaded: Vec<String> = db
+        .bucket_prefiltered_files(&roots, &BucketPrefilter {
+            screenshots: true,
+            big_download_min_bytes: Some(100 * 1024 * 1024),
+            desktop: true,
+            duplicate_max_bytes: Some(big),
             ..Default::default()
-        };
+        })
+        .unwrap()
+        .into_iter()
+        .map(|file| file.path)
+        .collect();
+    loaded.sort();
+    assert_eq!(
+        loaded,
+        vec![
+            "/home/u/Desktop/todo.txt",
+            "/home/u/Documents/a.bin",
+            "/home/u/Downloads/movie.mkv",
+            "/home/u/Music/b.bin",
+            "/home/u/Pictures/Screen Shot 2024.png",
+        ]
+    );
+
+    let mut reasons: Vec<(String, String)> = selector
+        .daily_candidates_under_roots(&roots, Some(100), &db)
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.reason, c.path))
+        .collect();
+    reasons.sort();
+    assert_eq!(
+        reasons,
+        vec![
+            ("Big Downloads".to_string(), "/home/u/Downloads/movie.mkv".to_string()),
+            ("Duplicates".to_string(), "/home/u/Documents/a.bin".to_string()),
+            ("Duplicates".to_string(), "/home/u/Music/b.bin".to_string()),
+            ("Old Desktop".to_string(), "/home/u/Desktop/todo.txt".to_string()),
+            ("Screenshots".to_string(), "/home/u/Pictures/Screen Shot 2024.png".to_string()),
+        ]
+    );
+    // Four files were just seen in Documents, so it counts as busy.
+    let busy = db
+        .busy_dirs_under_roots(&roots, Utc::now() - chrono::Duration::hours(1), 3)
+        .unwrap();
+    assert_eq!(busy, vec!["/home/u/Documents".to_string()]);
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_review_staged_sorts_by_expiry_with_archive_paths() {
+    let (temp_dir, db) = setup_test_db();
+    let now = Utc::now();
+    // (name, staged days ago, expires in days, archived)
+    let cases = [
+        ("later.txt", 10, Some(20), true),
+        ("soon.txt", 8, Some(2), true),
+        ("forever.txt", 9, None, false),
+        ("fresh.txt", 1, Some(1), true),
+    ];
+    for (name, staged_days, expires_days, archived) in cases {
+        let path = temp_dir.path().join(name).to_string_lossy().to_string();
+        db.upsert_file(&NewFile {
+            parent_dir: temp_dir.path().to_string_lossy().to_string(),
+            size_bytes: 8,
+            ..new_file(path.clone())
+        })
+        .unwrap();
+        let file_id = db.get_file_id_by_path(&path).unwrap().unwrap();
+        let batch_id = format!("archive_{name}");
+        if archived {
+            db.insert_action(&NewAction {
+                file_id,
+                action: ActionType::Archive,
+                batch_id: Some(batch_id.clone()),
+                src_path: Some(format!("/home/me/{name}")),
+                dst_path: Some(path.clone()),
+                origin: None,
+                note: None,
+            })
+            .unwrap();
+        }
+        db.stage_files(&[crate::models::NewStagedFile {
+            file_id,
+            staged_at: now - chrono::Duration::days(staged_days),
+            expires_at: expires_days.map(|days| now + chrono::Duration::days(days)),
+            batch_id: Some(batch_id),
+            status: "staged".to_string(),
+            note: None,
+        }])
+        .unwrap();
     }
 
-    #[test]
-    fn test_set_prefs_invalid_age_thresholds() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
+    let names = |items: &[(StagedFileRecord, File, Option<String>)]| {
+        items
+            .iter()
+            .map(|(_, file, _)| file.path.rsplit('/').next().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let week_old = now - chrono::Duration::days(7);
+    let items = db.review_staged(&week_old, None).unwrap();
+    assert_eq!(names(&items), vec!["soon.txt", "later.txt", "forever.txt"]);
+    assert!(items[0].2.as_deref().unwrap().ends_with("soon.txt"));
+    assert_eq!(items[2].2, None);
+
+    let expiring = db.review_staged(&now, Some(&(now + chrono::Duration::days(3)))).unwrap();
+    assert_eq!(names(&expiring), vec!["fresh.txt", "soon.txt"]);
+}
 
-        let prefs = PartialUserPrefs {
-            archive_age_threshold_days: Some(366),
-            delete_age_threshold_days: Some(366),
-            ..Default::default()
-        };
+#[test]
+fn test_bucket_summaries_cover_every_page() {
+    let candidate = |file_id: i64, reason: &str, size_bytes: u64, cloud: bool| Candidate {
+        file_id,
+        path: format!("/home/me/{file_id}"),
+        parent_dir: "/home/me".to_string(),
+        size_bytes,
+        reason: reason.to_string(),
+        reason_message: crate::selector::bucket_message(reason),
+        score: 0.5,
+        confidence: 0.5,
+        preview_hint: "".to_string(),
+        hint_messages: Vec::new(),
+        age_days: 10.0,
+        is_cloud_placeholder: cloud,
+        factors: Default::default(),
+        explanation: String::new(),
+        explanation_messages: Default::default(),
+    };
+    let candidates = vec![
+        candidate(1, "Screenshots", 100, false),
+        candidate(2, "Big Downloads", 5000, false),
+        candidate(3, "Screenshots", 200, false),
+        candidate(4, "Screenshots", 300, true),
+        candidate(5, "Big Downloads", 7000, false),
+    ];
+
+    let summaries = summarize_buckets(&candidates);
+    assert_eq!(summaries["screenshot"], (3, 300));
+    assert_eq!(summaries["big_download"], (2, 12000));
+
+    let offsets = std::collections::HashMap::from([("screenshot".to_string(), 2)]);
+    let (by_bucket, paging) = page_buckets(&candidates, 1, &offsets);
+    let ids = |key: &str| by_bucket[key].iter().map(|c| c.id).collect::<Vec<_>>();
+    assert_eq!(ids("screenshot"), vec![4]);
+    assert!(!paging["screenshot"].has_more);
+    assert_eq!(ids("big_download"), vec![2]);
+    assert!(paging["big_download"].has_more);
+}
 
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ERR_VALIDATION"));
+#[test]
+fn test_selector_honors_confidence_state_and_bucket_caps() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(60);
+    for name in ["kept.mkv", "staged.mkv", "gone.mkv"] {
+        db.upsert_file(&NewFile {
+            size_bytes: 500 * 1024 * 1024,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            ..new_file(format!("/home/u/Downloads/{name}"))
+        })
+        .unwrap();
     }
+    let staged = db.get_file_id_by_path("/home/u/Downloads/staged.mkv").unwrap().unwrap();
+    db.stage_files(&[crate::models::NewStagedFile {
+        file_id: staged,
+        staged_at: Utc::now(),
+        expires_at: None,
+        batch_id: None,
+        status: "staged".to_string(),
+        note: None,
+    }])
+    .unwrap();
+    db.mark_path_missing("/home/u/Downloads/gone.mkv").unwrap();
+
+    let roots = vec!["/home/u".to_string()];
+    let paths = |selector: &FileSelector| {
+        let mut paths: Vec<String> = selector
+            .daily_candidates_under_roots(&roots, Some(100), &db)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.path)
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    let mut selector = FileSelector::from_db(&db);
+    assert_eq!(paths(&selector), vec!["/home/u/Downloads/kept.mkv"]);
+
+    selector.set_include_staged(true);
+    selector.set_include_deleted(true);
+    assert_eq!(paths(&selector).len(), 3);
+
+    selector.set_max_per_bucket(Some(2));
+    assert_eq!(paths(&selector).len(), 2);
+
+    selector.set_min_confidence(1.0);
+    assert!(paths(&selector).is_empty());
+}
 
-    #[test]
-    fn test_set_prefs_valid() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
-
-        let prefs = PartialUserPrefs {
-            dry_run_default: Some(true),
-            tidy_day: Some("Mon".to_string()),
-            tidy_hour: Some(9),
-            rolling_window_days: Some(7),
-            max_candidates_per_day: Some(10),
-            thumbnail_max_size: Some(512),
-            auto_scan_enabled: Some(true),
-            scan_interval_hours: Some(12),
-            archive_age_threshold_days: Some(7),
-            delete_age_threshold_days: Some(30),
-        };
-
-        let result = set_prefs(prefs, tauri::State::from(&app_state));
-        assert!(result.is_ok());
-    }
+#[test]
+fn test_candidate_cursor_resumes_after_rescore() {
+    let candidate = |file_id: i64, score: f64| Candidate {
+        file_id,
+        path: format!("/home/me/{file_id}"),
+        parent_dir: "/home/me".to_string(),
+        size_bytes: 1024,
+        reason: "Big Downloads".to_string(),
+        reason_message: Message::new("bucket.big_download"),
+        score,
+        confidence: 0.5,
+        preview_hint: "".to_string(),
+        hint_messages: Vec::new(),
+        age_days: 10.0,
+        is_cloud_placeholder: false,
+        factors: Default::default(),
+        explanation: String::new(),
+        explanation_messages: Default::default(),
+    };
+    let sorted = |mut list: Vec<Candidate>| {
+        list.sort_by(|a, b| {
+            compare_cursors(&candidate_cursor(a, "score", 0), &candidate_cursor(b, "score", 0))
+        });
+        list
+    };
+    let first = sorted(vec![candidate(1, 0.9), candidate(2, 0.8), candidate(3, 0.8)]);
+    let cursor = candidate_cursor(&first[1], "score", 0);
+    assert_eq!(cursor.id, 2);
+
+    // A rescore pushes a new file to the top; the next page starts right after file 2.
+    let second = sorted(vec![
+        candidate(4, 0.95),
+        candidate(1, 0.9),
+        candidate(2, 0.8),
+        candidate(3, 0.8),
+    ]);
+    let start = second.partition_point(|c| {
+        compare_cursors(&candidate_cursor(c, "score", 0), &cursor).is_le()
+    });
+    assert_eq!(second[start..].iter().map(|c| c.file_id).collect::<Vec<_>>(), vec![3]);
+    assert!(PageCursor::decode("not a cursor").is_err());
+}
 
-    #[test]
-    fn test_get_prefs_defaults() {
-        let (temp_dir, db) = setup_test_db();
-        let app_state = AppState { db };
-
-        let result = get_prefs(tauri::State::from(&app_state));
-        assert!(result.is_ok());
-
-        let prefs = result.unwrap();
-        assert_eq!(prefs.dry_run_default, true);
-        assert_eq!(prefs.tidy_day, "Fri");
-        assert_eq!(prefs.tidy_hour, 17);
-        assert_eq!(prefs.rolling_window_days, 7);
-        assert_eq!(prefs.max_candidates_per_day, 12);
-        assert_eq!(prefs.thumbnail_max_size, 256);
-        assert_eq!(prefs.auto_scan_enabled, false);
-        assert_eq!(prefs.scan_interval_hours, 24);
-        assert_eq!(prefs.archive_age_threshold_days, 7);
-        assert_eq!(prefs.delete_age_threshold_days, 30);
+#[test]
+fn test_bucket_file_ids_skip_protected_and_missing_files() {
+    let (temp_dir, db) = setup_test_db();
+    let downloads = temp_dir.path().join("Downloads");
+    let keep = downloads.join("keep");
+    fs::create_dir_all(&keep).unwrap();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let add = |path: &Path, on_disk: bool| {
+        if on_disk {
+            fs::write(path, b"movie").unwrap();
+        }
+        db.upsert_file(&NewFile {
+            size_bytes: 500 * 1024 * 1024,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            ..new_file(path.to_string_lossy().to_string())
+        })
+        .unwrap();
+        db.get_file_id_by_path(&path.to_string_lossy()).unwrap().unwrap()
+    };
+    let movie = add(&downloads.join("movie.mkv"), true);
+    add(&downloads.join("gone.mkv"), false);
+    add(&keep.join("kept.mkv"), true);
+    db.upsert_watched_root(&temp_dir.path().to_string_lossy()).unwrap();
+    db.insert_protected_path(&keep.to_string_lossy()).unwrap();
+
+    let (ids, remaining) =
+        bucket_file_ids(&db, "big_download", &StageBucketFilters::default()).unwrap();
+    assert_eq!(ids, vec![movie]);
+    assert_eq!(remaining, 0);
+
+    let (ids, _) = bucket_file_ids(&db, "screenshot", &StageBucketFilters::default()).unwrap();
+    assert!(ids.is_empty());
+}
+
+#[test]
+fn test_selection_gathers_ids_and_search_matches() {
+    let (_temp_dir, db) = setup_test_db();
+    let mut ids = Vec::new();
+    for (path, mime, size) in [
+        ("/watch/videos/a.mp4", "video/mp4", 3000),
+        ("/watch/videos/b.mov", "video/quicktime", 2000),
+        ("/watch/docs/c.pdf", "application/pdf", 100),
+        ("/elsewhere/d.mp4", "video/mp4", 5000),
+    ] {
+        ids.push(
+            db.upsert_file(&NewFile {
+                mime: Some(mime.to_string()),
+                size_bytes: size,
+                ..new_file(path)
+            })
+            .unwrap(),
+        );
     }
+    let roots = vec!["/watch".to_string()];
+    let selection = db.create_selection("Spring cleaning").unwrap();
+
+    let videos = FileSearchFilters {
+        mime: Some("video/*".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(db.add_search_matches_to_selection(selection, "", &videos, &roots).unwrap(), 2);
+    assert_eq!(db.add_files_to_selection(selection, &[ids[0], ids[2]]).unwrap(), 1);
+    db.remove_files_from_selection(selection, &[ids[1]]).unwrap();
+
+    let summary = db.selection_summary(selection).unwrap().unwrap();
+    assert_eq!(summary.name, "Spring cleaning");
+    assert_eq!((summary.file_count, summary.total_bytes), (2, 3100));
+    assert_eq!(db.selection_file_ids(selection, 1).unwrap(), vec![ids[0]]);
+
+    db.stage_files(&[crate::models::NewStagedFile {
+        file_id: ids[0],
+        staged_at: Utc::now(),
+        expires_at: None,
+        batch_id: None,
+        status: "staged".to_string(),
+        note: None,
+    }])
+    .unwrap();
+    assert_eq!(db.prune_selection(selection).unwrap(), 1);
+    assert_eq!(db.selection_file_ids(selection, 10).unwrap(), vec![ids[2]]);
+
+    db.clear_selections().unwrap();
+    assert!(db.selection_summary(selection).unwrap().is_none());
+}
 
-    #[test]
-    fn test_get_db_path() {
-        let result = get_db_path();
-        assert!(result.is_ok());
+#[test]
+fn test_index_paths_skips_archive_and_volume_trash() {
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().join("root");
+    let archive = root.join("Archive");
+    fs::create_dir_all(archive.join("2024-01-01")).unwrap();
+    fs::create_dir_all(root.join(".Trash-1000/files")).unwrap();
+    fs::write(root.join("keep.txt"), "keep").unwrap();
+    fs::write(archive.join("2024-01-01/old.txt"), "old").unwrap();
+    fs::write(root.join(".Trash-1000/files/gone.txt"), "gone").unwrap();
+    db.set_preference(
+        crate::ops::archive::ARCHIVE_PATH_PREF,
+        &archive.to_string_lossy(),
+    )
+    .unwrap();
+
+    let update = crate::scanner::Scanner::new()
+        .index_paths(std::slice::from_ref(&root), 100, &db)
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.updated, 1);
+    let paths: Vec<String> =
+        db.list_active_paths(None).unwrap().into_iter().map(|(_, p)| p).collect();
+    assert_eq!(paths, vec![root.join("keep.txt").to_string_lossy().to_string()]);
+}
 
-        let path = result.unwrap();
-        assert!(path.to_string_lossy().contains("white-space"));
-        assert!(path.to_string_lossy().contains("database.db"));
-    }
+#[test]
+fn test_candidates_explain_their_score() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(90);
+    let id = db
+        .upsert_file(&NewFile {
+            size_bytes: 500 * 1024 * 1024,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            ..new_file("/home/u/Downloads/movie.mkv")
+        })
+        .unwrap();
+
+    let candidates = FileSelector::from_db(&db)
+        .daily_candidates_under_roots(&["/home/u".to_string()], Some(10), &db)
+        .unwrap();
+    assert_eq!(candidates.len(), 1);
+    let candidate = &candidates[0];
+    assert!(candidate.factors.age_days >= 89.0);
+    assert!(!candidate.factors.is_unopened);
+    assert_eq!(candidate.explanation, "Not used in 3 months, large at 500.0 MB");
+    let ui = ui_candidate(candidate, candidate.reason.clone());
+    assert_eq!(ui.explanation.as_deref(), Some(candidate.explanation.as_str()));
+
+    let file = db.get_file_by_id(id).unwrap().unwrap();
+    let factors = ScoreFactors {
+        in_git_repo: true,
+        recent_sibling_burst: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        crate::selector::scoring::FileScorer::new().explain(&file, &factors),
+        "Matches this bucket; ranked lower because it sits in a git repository and its \
+         folder changed recently"
+    );
+}
 
-    #[test]
-    fn normalize_directory_path_handles_existing_directory() {
-        let temp_dir = TempDir::new().unwrap();
-        let nested = temp_dir.path().join("nested");
-        fs::create_dir(&nested).unwrap();
+#[test]
+fn test_feedback_demotes_rejected_buckets() {
+    use crate::models::{BucketFeedback, FeedbackVerdict};
+    use crate::selector::scoring::feedback_weight;
+
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let id = db
+        .upsert_file(&NewFile {
+            size_bytes: 500 * 1024 * 1024,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            ..new_file("/home/u/Downloads/movie.mkv")
+        })
+        .unwrap();
+    db.upsert_watched_root("/home/u").unwrap();
+    let file = db.get_file_by_id(id).unwrap().unwrap();
+    assert_eq!(feedback_bucket(&db, &file).unwrap().as_deref(), Some("big_download"));
+
+    let score = |db: &Database| {
+        FileSelector::from_db(db).daily_candidates(Some(10), db).unwrap()[0].score
+    };
+    let before = score(&db);
+    db.record_feedback(id, "big_download", FeedbackVerdict::Kept).unwrap();
+    db.record_feedback(id, "big_download", FeedbackVerdict::WrongSuggestion).unwrap();
+    db.record_feedback(id, "screenshot", FeedbackVerdict::Deleted).unwrap();
+
+    let feedback = db.feedback_by_bucket().unwrap();
+    let totals = BucketFeedback {
+        kept: 1,
+        deleted: 0,
+        wrong_suggestion: 1,
+    };
+    assert_eq!(feedback["big_download"], totals);
+    assert_eq!(feedback_weight(&BucketFeedback::default()), 1.0);
+    assert!(feedback_weight(&totals) < 1.0);
+    assert!(feedback_weight(&feedback["screenshot"]) > 1.0);
+    assert!((score(&db) - before * feedback_weight(&totals)).abs() < 1e-9);
+}
 
-        let normalized = normalize_directory_path(&nested).expect("should normalize directory");
+#[test]
+fn test_system_locations_are_never_touched() {
+    use crate::ops::system_paths::{is_system_path, system_locations};
+    use crate::ops::OpsError;
+
+    let (temp_dir, db) = setup_test_db();
+    let bundle = temp_dir.path().join("Downloads/Tool.app");
+    fs::create_dir_all(bundle.join("Contents")).unwrap();
+    let inside = bundle.join("Contents/engine.bin");
+    fs::write(&inside, vec![0u8; 1024]).unwrap();
+
+    assert!(is_system_path(&inside));
+    assert!(!is_system_path(&bundle));
+    assert!(!is_system_path(&temp_dir.path().join("Downloads/notes.txt")));
+    let system_file = system_locations()[0].join("some/file");
+    assert!(is_system_path(&system_file));
+
+    let inside_str = inside.to_string_lossy().to_string();
+    let err = ensure_not_protected(&inside_str, &[]).unwrap_err();
+    assert!(err.starts_with("ERR_SYSTEM_LOCATION"));
+
+    let mut delete_manager = DeleteManager::new();
+    delete_manager.set_permanent_delete(true);
+    let result = delete_manager.delete_files(vec![inside_str.clone()], &db);
+    assert!(matches!(result, Err(OpsError::SystemLocation(_))));
+    assert!(inside.exists());
+
+    let old = Utc::now() - chrono::Duration::days(60);
+    db.upsert_file(&NewFile {
+        parent_dir: bundle.join("Contents").to_string_lossy().to_string(),
+        size_bytes: 500 * 1024 * 1024,
+        created_at: Some(old),
+        modified_at: Some(old),
+        accessed_at: Some(old),
+        ..new_file(inside_str)
+    })
+    .unwrap();
+    let root = temp_dir.path().to_string_lossy().to_string();
+    let candidates = FileSelector::from_db(&db)
+        .daily_candidates_under_roots(&[root], Some(10), &db)
+        .unwrap();
+    assert!(candidates.is_empty());
+}
 
-        assert!(normalized.ends_with("nested"));
-    }
+#[test]
+fn test_corrupt_database_is_kept_aside_and_replaced() {
+    use crate::db::recovery::{check_file, open_checked};
+
+    let (temp_dir, mut db) = setup_test_db();
+    db.upsert_watched_root("/home/u").unwrap();
+    db.upsert_file(&new_file("/home/u/a.txt")).unwrap();
+    let live = db.file_path().unwrap().unwrap();
+
+    // In place: the live database is swapped for a fresh one.
+    let problems = vec!["page 3 is never used".to_string()];
+    let recovery = rebuild_database(&mut db, problems).unwrap();
+    assert!(Path::new(&recovery.corrupt_path).exists());
+    assert_eq!(recovery.roots, vec!["/home/u".to_string()]);
+    assert_eq!(db.list_watched_paths().unwrap(), vec!["/home/u".to_string()]);
+    assert!(db.list_active_paths(None).unwrap().is_empty());
+    assert_eq!(db.schema_version().unwrap(), crate::db::database::SCHEMA_VERSION);
+    assert!(db.integrity_problems(true).unwrap().is_empty());
+    drop(db);
+    assert!(check_file(&live, true).is_empty());
+
+    // At startup: an unreadable file is moved aside before opening.
+    let broken = temp_dir.path().join("broken.db");
+    fs::write(&broken, b"definitely not a sqlite database").unwrap();
+    assert!(!check_file(&broken, false).is_empty());
+    let (pool, recovery) = open_checked(&broken);
+    let recovery = recovery.unwrap();
+    assert_eq!(
+        fs::read(&recovery.corrupt_path).unwrap(),
+        b"definitely not a sqlite database"
+    );
+    assert!(recovery.roots.is_empty());
+    let fresh = Database::new(pool.get().unwrap());
+    fresh.run_migrations().unwrap();
+    assert!(fresh.integrity_problems(false).unwrap().is_empty());
+}
 
-    #[test]
-    fn watched_root_membership_checks_descendants() {
-        let temp_dir = TempDir::new().unwrap();
-        let root = temp_dir.path().join("root");
-        let child = root.join("child");
-        fs::create_dir_all(&child).unwrap();
+#[test]
+fn test_writes_wait_for_another_instance_lock() {
+    use crate::db::database::{is_busy, retry_busy};
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("shared.db");
+    let pool = init_pool(&db_path);
+    let db = Database::new(pool.get().unwrap());
+    db.run_migrations().unwrap();
+    db.upsert_watched_root("/home/u").unwrap();
+
+    // A second instance holds the write lock for a moment.
+    let other = rusqlite::Connection::open(&db_path).unwrap();
+    other.execute_batch("BEGIN IMMEDIATE").unwrap();
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        other.execute_batch("COMMIT").unwrap();
+    });
+    db.delete_watched_root("/home/u").unwrap();
+    holder.join().unwrap();
+    assert!(db.list_watched_paths().unwrap().is_empty());
+
+    // Without a busy timeout the lock surfaces as busy once retries run out.
+    let blocker = rusqlite::Connection::open(&db_path).unwrap();
+    blocker.execute_batch("BEGIN IMMEDIATE").unwrap();
+    let impatient = rusqlite::Connection::open(&db_path).unwrap();
+    let mut attempts = 0;
+    let err = retry_busy(|| {
+        attempts += 1;
+        impatient.execute_batch("BEGIN IMMEDIATE")
+    })
+    .unwrap_err();
+    assert!(is_busy(&err));
+    assert!(attempts > 1);
+    blocker.execute_batch("ROLLBACK").unwrap();
+}
 
-        let normalized_root = normalize_directory_path(&root).expect("normalize root");
-        let normalized_child = normalize_directory_path(&child).expect("normalize child");
+#[test]
+fn test_scan_mode_follows_power_source_and_pref() {
+    use crate::scanner::power::{mode_for, scan_mode, PowerSource, ScanMode};
+
+    assert_eq!(mode_for(None, PowerSource::Battery), ScanMode::Throttled);
+    assert_eq!(mode_for(Some("auto"), PowerSource::Ac), ScanMode::Normal);
+    assert_eq!(mode_for(None, PowerSource::Unknown), ScanMode::Normal);
+    assert_eq!(mode_for(Some("normal"), PowerSource::Battery), ScanMode::Normal);
+    assert_eq!(mode_for(Some("throttled"), PowerSource::Ac), ScanMode::Throttled);
+
+    let (_temp_dir, db) = setup_test_db();
+    db.set_preference("scan_power_mode", "throttled").unwrap();
+    assert_eq!(scan_mode(&db), ScanMode::Throttled);
+
+    let now = Utc::now();
+    db.insert_scan_record(&crate::models::NewScanRecord {
+        roots: vec!["/home/me".to_string()],
+        trigger: "scheduled".to_string(),
+        status: "completed".to_string(),
+        mode: ScanMode::Throttled.as_str().to_string(),
+        counted: 10,
+        skipped: 0,
+        errors: 0,
+        error_sample: None,
+        duration_ms: 1_000,
+        started_at: now,
+        finished_at: now,
+    })
+    .unwrap();
+    assert_eq!(db.list_scan_records(1).unwrap()[0].mode, "throttled");
+}
 
-        let roots = vec![WatchedRoot {
-            id: 1,
-            path: normalized_root.to_string_lossy().to_string(),
-            created_at: Utc::now(),
-        }];
+#[test]
+fn test_heavy_work_waits_for_idle_and_never_runs_while_busy() {
+    use crate::scanner::activity::{allowed_for, idle_settings, DEFAULT_IDLE_MINUTES};
+    use std::time::Duration;
+
+    let ten_minutes = Duration::from_secs(600);
+    let idle = |minutes: u64| Some(Duration::from_secs(minutes * 60));
+    assert!(allowed_for(false, false, None, ten_minutes));
+    assert!(!allowed_for(true, false, None, ten_minutes));
+    assert!(!allowed_for(true, true, idle(60), ten_minutes));
+    assert!(!allowed_for(false, true, idle(2), ten_minutes));
+    assert!(allowed_for(false, true, idle(10), ten_minutes));
+    // Without an idle reading the schedule is not held back.
+    assert!(allowed_for(false, true, None, ten_minutes));
+
+    let (_temp_dir, db) = setup_test_db();
+    let default_wait = Duration::from_secs(DEFAULT_IDLE_MINUTES as u64 * 60);
+    assert_eq!(idle_settings(&db), (false, default_wait));
+    db.set_preference("only_clean_when_idle", "true").unwrap();
+    db.set_preference("idle_minutes", "0").unwrap();
+    assert_eq!(idle_settings(&db), (true, default_wait));
+    db.set_preference("idle_minutes", "30").unwrap();
+    assert_eq!(idle_settings(&db), (true, Duration::from_secs(1800)));
+}
 
-        assert!(is_within_watched_roots(&normalized_child, &roots));
+#[test]
+fn test_packages_are_indexed_as_one_entry() {
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().join("root");
+    let app = root.join("Tool.app");
+    fs::create_dir_all(app.join("Contents/MacOS")).unwrap();
+    fs::write(app.join("Contents/MacOS/tool"), vec![0u8; 1000]).unwrap();
+    fs::write(app.join("Contents/Info.plist"), vec![0u8; 200]).unwrap();
+    fs::write(root.join("notes.txt"), "notes").unwrap();
+
+    let update = crate::scanner::Scanner::new()
+        .index_paths(std::slice::from_ref(&root), 100, &db)
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.updated, 2);
+    let app_str = app.to_string_lossy().to_string();
+    let app_id = db.get_file_id_by_path(&app_str).unwrap().unwrap();
+    let entry = db.get_file_by_id(app_id).unwrap().unwrap();
+    assert_eq!(entry.size_bytes, 1200);
+    assert_eq!(entry.mime.as_deref(), Some(DIRECTORY_MIME));
+    assert!(entry.sha1.is_none());
+
+    // A change inside the package re-indexes the package itself.
+    fs::write(app.join("Contents/MacOS/tool"), vec![0u8; 3000]).unwrap();
+    crate::scanner::Scanner::new()
+        .index_paths(&[app.join("Contents/MacOS/tool")], 100, &db)
+        .unwrap()
+        .unwrap();
+    let paths: Vec<String> =
+        db.list_active_paths(None).unwrap().into_iter().map(|(_, p)| p).collect();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(db.get_file_by_id(app_id).unwrap().unwrap().size_bytes, 3200);
+}
 
-        let outside = temp_dir.path().join("outside");
-        fs::create_dir(&outside).unwrap();
-        let normalized_outside = normalize_directory_path(&outside).expect("normalize outside");
+#[test]
+fn test_weekly_digest_lists_new_and_expiring_files() {
+    let (_temp_dir, db) = setup_test_db();
+    let download = |path: &str, size_bytes: i64| NewFile {
+        size_bytes,
+        ..new_file(path)
+    };
+    db.upsert_file(&download("/home/u/Downloads/small.txt", 10)).unwrap();
+    db.upsert_file(&download("/home/u/Downloads/big.iso", 5_000)).unwrap();
+    let staged_id = db.upsert_file(&download("/home/u/Downloads/old.zip", 700)).unwrap();
+    let now = Utc::now();
+    db.stage_files(&[NewStagedFile {
+        file_id: staged_id,
+        staged_at: now,
+        expires_at: Some(now + chrono::Duration::days(3)),
+        batch_id: Some("batch-1".to_string()),
+        status: "staged".to_string(),
+        note: None,
+    }])
+    .unwrap();
+
+    let digest = build_weekly_digest(&db, 7).unwrap();
+    assert_eq!(digest.staged_files, 1);
+    assert_eq!(digest.staged_bytes, 700);
+    assert_eq!(digest.expiring_soon[0].path, "/home/u/Downloads/old.zip");
+    let new_paths: Vec<&str> =
+        digest.biggest_new_files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(new_paths, vec!["/home/u/Downloads/big.iso", "/home/u/Downloads/small.txt"]);
+    assert_eq!(digest.freed_files, 0);
+
+    let html = reports::render_digest(&digest, DigestFormat::Html);
+    assert!(html.contains("/home/u/Downloads/old.zip"));
+}
 
-        assert!(!is_within_watched_roots(&normalized_outside, &roots));
-    }
+#[test]
+fn test_candidate_messages_carry_keys_and_render_in_english() {
+    use crate::selector::bucket_message;
+    use crate::selector::scoring::{FileScorer, ScoreFactors};
+
+    let (_temp_dir, db) = setup_test_db();
+    db.upsert_file(&NewFile {
+        mime: Some("video/x-matroska".to_string()),
+        size_bytes: 2048,
+        ..new_file("/home/me/Downloads/movie.mkv")
+    })
+    .unwrap();
+    let file_id = db
+        .get_file_id_by_path("/home/me/Downloads/movie.mkv")
+        .unwrap()
+        .unwrap();
+    let file = db.get_file_by_id(file_id).unwrap().unwrap();
+    let scorer = FileScorer::new();
+    let factors = ScoreFactors {
+        size_bytes: 2048,
+        age_days: 90.0,
+        is_duplicate: true,
+        in_git_repo: true,
+        ..Default::default()
+    };
+
+    let hints = scorer.preview_hint_messages(&file, &factors);
+    let keys: Vec<&str> = hints.iter().map(|m| m.key.as_str()).collect();
+    assert_eq!(keys, ["hint.duplicate", "hint.old", "hint.git_repo"]);
+    assert_eq!(scorer.generate_preview_hint(&file, &factors), "duplicate, old, git-repo");
+
+    let parts = scorer.explanation_messages(&file, &factors);
+    assert_eq!(parts.reasons[0].key, "explain.unused_months");
+    assert_eq!(parts.reasons[0].params["count"], "3");
+    assert_eq!(parts.penalties[0].key, "explain.penalty.git_repo");
+    assert!(scorer
+        .explain(&file, &factors)
+        .starts_with("Not used in 3 months"));
+
+    assert_eq!(bucket_message("Big Downloads"), Message::new("bucket.big_download"));
+    let custom = bucket_message("Old Invoices");
+    assert_eq!(custom.key, "bucket.custom");
+    assert_eq!(custom.render(), "Old Invoices");
+
+    // Locales without a backend catalog fall back to English.
+    let error = crate::ops::OpsError::PermissionError("/etc/hosts".to_string());
+    assert_eq!(i18n::translate("pt-BR", &error.to_message()), "Access denied: /etc/hosts");
+    assert_eq!(error.to_user_message().title, "Permission Denied");
+    assert_eq!(i18n::translate("en", &Message::new("no.such.key")), "no.such.key");
+    assert!(i18n::is_valid_locale("pt-BR"));
+    assert!(!i18n::is_valid_locale("english!"));
+}
+
+#[test]
+fn test_idempotent_retries_replay_the_first_outcome() {
+    let (_temp_dir, db) = setup_test_db();
+    let runs = std::cell::Cell::new(0);
+    let stage = || {
+        runs.set(runs.get() + 1);
+        Ok(StageOutcome {
+            success: true,
+            batch_id: Some("archive_retry-1".to_string()),
+            staged_files: 2,
+            total_bytes: 4096,
+            duration_ms: 5,
+            errors: Vec::new(),
+            expires_at: None,
+            note: None,
+            remaining: 0,
+        })
+    };
+    let batch_id = |outcome: &StageOutcome| outcome.batch_id.clone();
+
+    let first = run_idempotent(&db, Some("retry-1"), "stage_files", batch_id, stage).unwrap();
+    let second = run_idempotent(&db, Some("retry-1"), "stage_files", batch_id, stage).unwrap();
+    assert_eq!(runs.get(), 1);
+    assert_eq!(second.batch_id, first.batch_id);
+    assert_eq!(second.staged_files, 2);
+
+    // Without a key every call runs.
+    run_idempotent(&db, None, "stage_files", batch_id, stage).unwrap();
+    assert_eq!(runs.get(), 2);
+
+    let reused = run_idempotent(&db, Some("retry-1"), "delete_files", batch_id, stage);
+    assert!(reused.unwrap_err().contains("already used for stage_files"));
+
+    // A failed call frees its key for the retry.
+    let failed: Result<StageOutcome, String> =
+        run_idempotent(&db, Some("retry-2"), "stage_files", batch_id, || {
+            Err("ERR_ARCHIVE: disk full".to_string())
+        });
+    assert!(failed.is_err());
+    run_idempotent(&db, Some("retry-2"), "stage_files", batch_id, stage).unwrap();
+    assert_eq!(runs.get(), 3);
+
+    // A call still in flight makes the retry fail fast.
+    assert_eq!(
+        db.claim_idempotency_key("retry-3", "stage_files").unwrap(),
+        IdempotencyClaim::Claimed
+    );
+    let running = run_idempotent(&db, Some("retry-3"), "stage_files", batch_id, stage);
+    assert!(running.unwrap_err().starts_with("ERR_CONFLICT"));
+
+    assert!(validate_idempotency_key(Some("0b6c3f0e-7c1a-4c51-9a36-2f1f3b7d9e11")).is_ok());
+    assert!(validate_idempotency_key(Some("../archive")).is_err());
+    assert!(validate_idempotency_key(Some("")).is_err());
+}
 
-    #[test]
-    fn normalize_existing_path_handles_file() {
-        let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("file.txt");
-        fs::write(&file_path, "hello").unwrap();
+#[test]
+fn test_staging_batches_roll_back_as_a_unit() {
+    let (_temp_dir, db) = setup_test_db();
+    let desktop_file = |path: &str| NewFile {
+        size_bytes: 100,
+        ..new_file(path)
+    };
+    let staged = |file_id: i64| NewStagedFile {
+        file_id,
+        staged_at: Utc::now(),
+        expires_at: None,
+        batch_id: Some("batch-1".to_string()),
+        status: "staged".to_string(),
+        note: None,
+    };
+    let is_staged = |file_id: i64| db.get_file_by_id(file_id).unwrap().unwrap().is_staged;
+    let a = db.upsert_file(&desktop_file("/home/u/Desktop/a.txt")).unwrap();
+    let b = db.upsert_file(&desktop_file("/home/u/Desktop/b.txt")).unwrap();
+
+    let failed: rusqlite::Result<()> = db.transaction(|| {
+        db.stage_files(&[staged(a)])?;
+        Err(rusqlite::Error::QueryReturnedNoRows)
+    });
+    assert!(failed.is_err());
+    assert!(!is_staged(a));
+    assert!(db.list_staged_with_files(None).unwrap().is_empty());
+
+    // A failing inner batch only undoes its own writes.
+    db.transaction(|| {
+        db.stage_files(&[staged(a)])?;
+        let inner: rusqlite::Result<()> = db.transaction(|| {
+            db.stage_files(&[staged(b)])?;
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        });
+        assert!(inner.is_err());
+        Ok::<_, rusqlite::Error>(())
+    })
+    .unwrap();
+    assert!(is_staged(a));
+    assert!(!is_staged(b));
+
+    db.mark_files_unstaged(&[a]).unwrap();
+    assert!(!is_staged(a));
+    assert!(db.list_staged_with_files(None).unwrap().is_empty());
+}
 
-        let normalized = normalize_existing_path(&file_path).expect("normalize file");
+#[test]
+fn test_file_locks_reject_overlapping_batches() {
+    // The registry is process-wide; ids are chosen not to clash with other tests.
+    let stage = lock_files("stage", &[910_001, 910_002]).unwrap();
+    let stage_id = ops::locks::active_operations()
+        .into_iter()
+        .find(|op| op.kind == "stage" && op.file_ids == vec![910_001, 910_002])
+        .map(|op| op.id)
+        .unwrap();
+
+    let err = lock_files("delete", &[910_002, 910_003]).unwrap_err();
+    let busy: serde_json::Value =
+        serde_json::from_str(err.strip_prefix("ERR_FILE_BUSY: ").unwrap()).unwrap();
+    assert_eq!(busy["file_ids"], serde_json::json!([910_002]));
+    assert_eq!(busy["operation"], "stage");
+    assert_eq!(busy["operation_id"], stage_id);
+
+    // Disjoint files go through while the first batch is still running.
+    let other = lock_files("undo", &[910_003]).unwrap();
+    drop(stage);
+    assert!(lock_files("delete", &[910_002]).is_ok());
+    assert!(lock_files("delete", &[910_003]).is_err());
+    drop(other);
+}
 
-        assert!(normalized.ends_with("file.txt"));
+#[test]
+fn test_bucket_stats_aggregate_in_sql() {
+    let (_temp_dir, db) = setup_test_db();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let add = |path: &str, size_bytes: i64, sha1: Option<&str>| {
+        db.upsert_file(&NewFile {
+            size_bytes,
+            created_at: Some(old),
+            modified_at: Some(old),
+            accessed_at: Some(old),
+            sha1: sha1.map(str::to_string),
+            ..new_file(path)
+        })
+        .unwrap()
+    };
+    let big = 500 * 1024 * 1024;
+    add("/home/u/Documents/report.pdf", 1024, None);
+    add("/home/u/Documents/a.bin", 1024, Some("abc"));
+    add("/home/u/Music/b.bin", 1024, Some("abc"));
+    add("/home/u/Downloads/movie.mkv", big, None);
+    add("/home/u/Downloads/setup.dmg", 2048, None);
+    add("/home/u/Pictures/Screen Shot 2024.png", 1024, None);
+    add("/home/u/Desktop/todo.txt", 1024, None);
+    add("/home/u/Desktop/Keep/plan.txt", 1024, None);
+    db.insert_protected_path("/home/u/Desktop/Keep").unwrap();
+
+    let mut selector = FileSelector::from_db(&db);
+    selector.set_roots(vec!["/home/u".to_string()]);
+    let stats = selector.get_bucket_stats(&db).unwrap();
+    assert_eq!(stats["screenshot"], (1, 1024));
+    assert_eq!(stats["big_download"], (1, big as u64));
+    assert_eq!(stats["installer"], (1, 2048));
+    assert_eq!(stats["old_desktop"], (1, 1024));
+    assert_eq!(stats["duplicate"], (2, 2048));
+    assert_eq!(stats["broken_symlink"], (0, 0));
+
+    // The SQL counts agree with what the selector actually buckets.
+    let scored = selector
+        .daily_candidates_under_roots(&["/home/u".to_string()], Some(100), &db)
+        .unwrap();
+    let summaries = summarize_buckets(&scored);
+    for (key, (count, bytes)) in &summaries {
+        assert_eq!(stats[key], (*count, *bytes), "{key}");
     }
+}
 
-    #[test]
-    fn ensure_within_watched_rejects_outside() {
-        let temp_dir = TempDir::new().unwrap();
-        let root = temp_dir.path().join("root");
-        fs::create_dir(&root).unwrap();
-        let normalized_root = normalize_directory_path(&root).expect("normalize root");
+#[test]
+fn test_age_histogram_bins_files_by_metric() {
+    let (_temp_dir, db) = setup_test_db();
+    let days_ago = |days: i64| Some(Utc::now() - chrono::Duration::days(days));
+    let add = |name: &str, size_bytes: i64, modified: i64, accessed: Option<i64>| {
+        db.upsert_file(&NewFile {
+            size_bytes,
+            modified_at: days_ago(modified),
+            accessed_at: accessed.and_then(days_ago),
+            ..new_file(format!("/home/u/Documents/{name}"))
+        })
+        .unwrap();
+    };
+    add("fresh.txt", 10, 2, Some(1));
+    add("month.txt", 20, 45, Some(1));
+    add("season.txt", 30, 200, None);
+    add("ancient.txt", 40, 800, Some(100));
+    add("older.txt", 50, 400, Some(500));
+
+    let counts = |metric| -> Vec<(i64, Option<i64>, i64, i64)> {
+        db.age_histogram("/home/u", metric)
+            .unwrap()
+            .into_iter()
+            .map(|bin| (bin.min_days, bin.max_days, bin.file_count, bin.total_bytes))
+            .collect()
+    };
+    assert_eq!(
+        counts(AgeMetric::Modified),
+        vec![
+            (0, Some(30), 1, 10),
+            (30, Some(90), 1, 20),
+            (90, Some(365), 1, 30),
+            (365, None, 2, 90),
+        ]
+    );
+    // Without an access time the modified time stands in.
+    assert_eq!(
+        counts(AgeMetric::Accessed),
+        vec![
+            (0, Some(30), 2, 30),
+            (30, Some(90), 0, 0),
+            (90, Some(365), 2, 70),
+            (365, None, 1, 50),
+        ]
+    );
+}
 
-        let roots = vec![WatchedRoot {
-            id: 1,
-            path: normalized_root.to_string_lossy().to_string(),
-            created_at: Utc::now(),
-        }];
+#[test]
+fn test_saved_views_persist_filters_and_run_against_index() {
+    let (_temp_dir, db) = setup_test_db();
+    db.upsert_watched_root("/home/u/Downloads").unwrap();
+    let add = |name: &str, size_bytes: i64, days_old: i64| {
+        let modified = Utc::now() - chrono::Duration::days(days_old);
+        db.upsert_file(&NewFile {
+            size_bytes,
+            created_at: Some(modified),
+            modified_at: Some(modified),
+            ..new_file(format!("/home/u/Downloads/{name}"))
+        })
+        .unwrap()
+    };
+    let mb = 1024 * 1024;
+    add("old-big.iso", 900 * mb, 120);
+    add("new-big.iso", 900 * mb, 5);
+    add("old-small.txt", mb, 120);
+
+    let view = NewSavedView {
+        name: "  Old downloads  ".to_string(),
+        query: String::new(),
+        filters: FileSearchFilters {
+            min_size_bytes: Some(500 * mb),
+            root_path: Some("/home/u/Downloads".to_string()),
+            staged: Some(false),
+            min_age_days: Some(60),
+            ..Default::default()
+        },
+        notify_threshold: Some(1),
+    };
+    let view = validate_saved_view(view).unwrap();
+    assert_eq!(view.name, "Old downloads");
+    let id = db.insert_saved_view(&view).unwrap();
+
+    let stored = db.get_saved_view_by_id(id).unwrap().unwrap();
+    assert_eq!(stored.filters.min_age_days, Some(60));
+    assert_eq!(stored.last_count, None);
+    let page = search_page(&db, &stored.query, &stored.filters, 10, 0, None).unwrap();
+    assert_eq!(page.total_count, 1);
+    assert_eq!(page.items[0].path, "/home/u/Downloads/old-big.iso");
+
+    db.set_saved_view_count(id, 1).unwrap();
+    let broader = NewSavedView {
+        filters: FileSearchFilters {
+            min_age_days: Some(60),
+            ..Default::default()
+        },
+        ..view.clone()
+    };
+    assert_eq!(db.update_saved_view(id, &broader).unwrap(), 1);
+    let stored = db.get_saved_view_by_id(id).unwrap().unwrap();
+    // The old count described the old filters.
+    assert_eq!(stored.last_count, None);
+    let roots = vec!["/home/u/Downloads".to_string()];
+    assert_eq!(db.count_search_matches("", &stored.filters, &roots).unwrap(), 2);
+
+    assert!(validate_saved_view(NewSavedView {
+        notify_threshold: Some(0),
+        ..view.clone()
+    })
+    .is_err());
+    assert_eq!(db.list_saved_views().unwrap().len(), 1);
+    assert_eq!(db.delete_saved_view(id).unwrap(), 1);
+    assert!(db.list_saved_views().unwrap().is_empty());
+}
 
-        ensure_within_watched(&normalized_root, &roots).expect("root allowed");
+#[test]
+fn test_batches_keep_labels_and_filter() {
+    let (_temp_dir, db) = setup_test_db();
+    let batch = |batch_id: &str, action_type: ActionType, origin: &str| NewBatch {
+        batch_id: batch_id.to_string(),
+        action_type,
+        origin: origin.to_string(),
+        label: None,
+        file_count: 3,
+        total_bytes: 3072,
+    };
+    db.record_batch(&NewBatch {
+        label: Some("Old installers".to_string()),
+        ..batch("batch_a", ActionType::Archive, "bucket")
+    })
+    .unwrap();
+    db.record_batch(&batch("batch_b", ActionType::Delete, "expiry"))
+        .unwrap();
+
+    // Re-recording refreshes the counts but keeps the user's label.
+    db.record_batch(&NewBatch {
+        file_count: 2,
+        ..batch("batch_a", ActionType::Archive, "bucket")
+    })
+    .unwrap();
+    let a = db.get_batch("batch_a").unwrap().unwrap();
+    assert_eq!(a.label.as_deref(), Some("Old installers"));
+    assert_eq!((a.file_count, a.origin.as_str()), (2, "bucket"));
+
+    assert_eq!(db.update_batch_label("batch_b", Some("Trip photos")).unwrap(), 1);
+    assert_eq!(db.update_batch_label("missing", Some("x")).unwrap(), 0);
+    let by_label = db
+        .list_batches(&BatchFilters {
+            label: Some("trip".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(by_label.len(), 1);
+    assert_eq!(by_label[0].batch_id, "batch_b");
+
+    let archives = db
+        .list_batches(&BatchFilters {
+            action_type: Some("archive".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(archives.len(), 1);
+    assert_eq!(archives[0].batch_id, "batch_a");
+    assert!(db
+        .list_batches(&BatchFilters {
+            since: Some(Utc::now() + chrono::Duration::days(1)),
+            ..Default::default()
+        })
+        .unwrap()
+        .is_empty());
+
+    db.update_batch_label("batch_a", None).unwrap();
+    assert!(db.get_batch("batch_a").unwrap().unwrap().label.is_none());
+    let digest = build_weekly_digest(&db, 7).unwrap();
+    assert_eq!(digest.batches.len(), 2);
+}
 
-        let outside = temp_dir.path().join("outside");
-        fs::create_dir(&outside).unwrap();
-        let normalized_outside = normalize_directory_path(&outside).expect("normalize outside");
+#[test]
+fn test_duplicate_policy_picks_keeper_per_group() {
+    use ops::dedupe::{DuplicatePolicy, DUPLICATE_POLICY_PREF};
+
+    let (temp_dir, db) = setup_test_db();
+    let root = temp_dir.path().to_path_buf();
+    let add = |dir: &str, name: &str, sha1: &str, days_old: i64| {
+        let parent = root.join(dir);
+        fs::create_dir_all(&parent).unwrap();
+        let path = parent.join(name);
+        fs::write(&path, sha1).unwrap();
+        let modified = Utc::now() - chrono::Duration::days(days_old);
+        db.upsert_file(&NewFile {
+            parent_dir: parent.to_string_lossy().to_string(),
+            size_bytes: 100,
+            created_at: Some(modified),
+            modified_at: Some(modified),
+            sha1: Some(sha1.to_string()),
+            ..new_file(path.to_string_lossy().to_string())
+        })
+        .unwrap()
+    };
+    let docs_report = add("Documents", "report.pdf", "aaa", 30);
+    let downloads_report = add("Downloads", "report.pdf", "aaa", 1);
+    let old_photo = add("Pictures", "photo.jpg", "bbb", 90);
+    let new_photo = add("Downloads", "photo.jpg", "bbb", 2);
+
+    // (kept, staged) pairs, in a stable order.
+    let pairs = |policy: DuplicatePolicy, preferred: Vec<String>| {
+        let plan = duplicate_policy_plan(&db, policy, preferred).unwrap();
+        let mut pairs: Vec<(i64, i64)> = plan
+            .groups
+            .iter()
+            .flat_map(|g| g.stage.iter().map(move |f| (g.keep.id, f.id)))
+            .collect();
+        pairs.sort();
+        pairs
+    };
+    let sorted = |mut pairs: Vec<(i64, i64)>| {
+        pairs.sort();
+        pairs
+    };
+
+    assert_eq!(
+        pairs(DuplicatePolicy::KeepNewest, Vec::new()),
+        sorted(vec![(downloads_report, docs_report), (new_photo, old_photo)])
+    );
+    assert_eq!(
+        pairs(DuplicatePolicy::KeepOldest, Vec::new()),
+        sorted(vec![(docs_report, downloads_report), (old_photo, new_photo)])
+    );
+    // The photo group has no copy in Documents, so its newest copy stays.
+    let documents = root.join("Documents").to_string_lossy().to_string();
+    assert_eq!(
+        pairs(DuplicatePolicy::PreferPaths, vec![documents.clone()]),
+        sorted(vec![(docs_report, downloads_report), (new_photo, old_photo)])
+    );
+    let plan =
+        duplicate_policy_plan(&db, DuplicatePolicy::PreferPaths, vec![documents.clone()])
+            .unwrap();
+    assert_eq!((plan.file_count, plan.total_bytes), (2, 200));
+
+    db.set_preference(DUPLICATE_POLICY_PREF, "keep_oldest").unwrap();
+    let (policy, preferred) = resolve_duplicate_policy(&db, None, None).unwrap();
+    assert_eq!(policy, DuplicatePolicy::KeepOldest);
+    assert!(preferred.is_empty());
+    assert!(resolve_duplicate_policy(&db, Some("biggest".to_string()), None).is_err());
+
+    // A group whose chosen copy is gone from disk is left alone.
+    fs::remove_file(root.join("Documents/report.pdf")).unwrap();
+    assert_eq!(
+        pairs(DuplicatePolicy::PreferPaths, vec![documents]),
+        vec![(new_photo, old_photo)]
+    );
+}
 
-        assert!(ensure_within_watched(&normalized_outside, &roots).is_err());
-    }
+#[test]
+fn test_reconcile_marks_missing_archived_copies_lost() {
+    use crate::ops::expiry::{reconcile_staged, LOST_STATUS};
+
+    let (temp_dir, db) = setup_test_db();
+    let archive_dir = temp_dir.path().join("archive");
+    fs::create_dir_all(&archive_dir).unwrap();
+    let archived = |name: &str| {
+        let path = archive_dir.join(name);
+        fs::write(&path, b"copy").unwrap();
+        path
+    };
+    let kept = archived("kept.txt");
+    let vanished = archived("vanished.txt");
+    let now = Utc::now();
+    let stage = |path: &std::path::Path, expires_in: i64| {
+        let file_id = db
+            .upsert_file(&NewFile {
+                parent_dir: archive_dir.to_string_lossy().to_string(),
+                size_bytes: 4,
+                ..new_file(path.to_string_lossy().to_string())
+            })
+            .unwrap();
+        db.stage_files(&[NewStagedFile {
+            file_id,
+            staged_at: now,
+            expires_at: Some(now + chrono::Duration::days(expires_in)),
+            batch_id: Some("batch-1".to_string()),
+            status: "staged".to_string(),
+            note: None,
+        }])
+        .unwrap();
+        file_id
+    };
+    let kept_id = stage(&kept, 7);
+    let vanished_id = stage(&vanished, -1);
+    fs::remove_file(&vanished).unwrap();
+
+    let report = reconcile_staged(&db, now).unwrap();
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.lost_file_ids, vec![vanished_id]);
+    assert!(report.found_file_ids.is_empty());
+
+    let statuses = |db: &Database| {
+        db.list_staged_with_files(None)
+            .unwrap()
+            .into_iter()
+            .map(|(record, _)| (record.file_id, record.status))
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+    let current = statuses(&db);
+    assert_eq!(current[&kept_id], "staged");
+    assert_eq!(current[&vanished_id], LOST_STATUS);
+    let (record, file) = db
+        .list_staged_with_files(Some(&[LOST_STATUS.to_string()]))
+        .unwrap()
+        .remove(0);
+    let payload = staged_payload(&record, &file);
+    assert_eq!(payload.status_message.unwrap().key, "staged.lost");
+
+    // The copy coming back past its cooloff returns it as expired.
+    fs::write(&vanished, b"copy").unwrap();
+    let report = reconcile_staged(&db, now).unwrap();
+    assert_eq!(report.found_file_ids, vec![vanished_id]);
+    assert_eq!(statuses(&db)[&vanished_id], "expired");
+}
 
-    #[test]
-    fn test_command_error_display() {
-        let errors = vec![
-            CommandError::Database("test".to_string()),
-            CommandError::FileSystem("test".to_string()),
-            CommandError::Validation("test".to_string()),
-            CommandError::Permission("test".to_string()),
-            CommandError::NotFound("test".to_string()),
-            CommandError::Internal("test".to_string()),
-        ];
-
-        for error in errors {
-            let display = format!("{}", error);
-            assert!(display.contains("test"));
-        }
-    }
+#[test]
+fn test_settings_round_trip_between_databases() {
+    let (source_dir, source) = setup_test_db();
+    let root = source_dir.path().join("Downloads");
+    fs::create_dir_all(root.join("keep")).unwrap();
+    let root_path = normalize_directory_path(&root).unwrap().to_string_lossy().to_string();
+    source.upsert_watched_root(&root_path).unwrap();
+    let mut root_settings = source.get_root_settings(&root_path).unwrap().unwrap();
+    root_settings.scan_interval_hours = Some(12);
+    source.update_root_settings(&root_settings).unwrap();
+    source.insert_exclusion(None, "**/node_modules").unwrap();
+    source.insert_exclusion(Some("/elsewhere"), "*.tmp").unwrap();
+    source
+        .insert_user_rule(&NewUserRule {
+            name: "Old logs".to_string(),
+            path_glob: Some("**/*.log".to_string()),
+            min_size_bytes: None,
+            min_age_days: Some(30),
+            mime_filter: None,
+            max_per_day: Some(5),
+            enabled: Some(true),
+        })
+        .unwrap();
+    let keep = normalize_existing_path(&root.join("keep")).unwrap();
+    source.insert_protected_path(&keep.to_string_lossy()).unwrap();
+    source.set_preference("tidy_hour", "9").unwrap();
+
+    let raw = serde_json::to_string(&collect_settings(&source, Utc::now()).unwrap()).unwrap();
+    let (_target_dir, target) = setup_test_db();
+    let (summary, watchers) = apply_settings(&target, parse_settings(&raw).unwrap()).unwrap();
+
+    assert_eq!(summary.roots_added, vec![root_path.clone()]);
+    assert_eq!(watchers, vec![(root_path.clone(), root_settings.watcher_enabled)]);
+    assert_eq!(
+        target.get_root_settings(&root_path).unwrap().unwrap().scan_interval_hours,
+        Some(12)
+    );
+    assert_eq!(summary.exclusions_added, 1);
+    assert!(summary.skipped.iter().any(|entry| entry.contains("/elsewhere")));
+    assert_eq!(summary.rules_added, 1);
+    assert_eq!(summary.protected_paths_added, 1);
+    assert_eq!(read_prefs(&target).unwrap().tidy_hour, 9);
+
+    // A second import changes nothing new and renames nothing.
+    let (again, _) = apply_settings(&target, parse_settings(&raw).unwrap()).unwrap();
+    assert_eq!(again.roots_updated, vec![root_path]);
+    assert_eq!((again.exclusions_added, again.rules_added), (0, 0));
+    assert_eq!(target.list_user_rules().unwrap().len(), 1);
+
+    let mut newer: serde_json::Value = serde_json::from_str(&raw).unwrap();
+    newer["schema_version"] = serde_json::json!(SETTINGS_SCHEMA_VERSION + 1);
+    assert!(parse_settings(&newer.to_string()).is_err());
+    let mut invalid: serde_json::Value = serde_json::from_str(&raw).unwrap();
+    invalid["prefs"]["tidy_hour"] = serde_json::json!(30);
+    assert!(parse_settings(&invalid.to_string()).unwrap_err().starts_with("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_archive_outcome_serialization() {
-        let outcome = ArchiveOutcome {
-            success: true,
-            files_processed: 5,
-            total_bytes: 1024 * 1024,
-            duration_ms: 1000,
-            errors: vec!["test error".to_string()],
-            dry_run: false,
-        };
-
-        let json = serde_json::to_string(&outcome).unwrap();
-        assert!(json.contains("success"));
-        assert!(json.contains("files_processed"));
+#[test]
+fn test_restored_files_sit_out_the_grace_window() {
+    let (temp_dir, db) = setup_test_db();
+    let desktop = temp_dir.path().join("Desktop");
+    fs::create_dir_all(&desktop).unwrap();
+    let old = Utc::now() - chrono::Duration::days(60);
+    let add = |name: &str| {
+        let path = desktop.join(name);
+        fs::write(&path, name).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let id = db
+            .upsert_file(&NewFile {
+                created_at: Some(old),
+                modified_at: Some(old),
+                accessed_at: Some(old),
+                ..new_file(path.clone())
+            })
+            .unwrap();
+        (id, path)
+    };
+    let (restored, restored_path) = add("restored.txt");
+    let (picked, picked_path) = add("picked.txt");
+    let (untouched, _) = add("untouched.txt");
+
+    let mut archive_manager = ArchiveManager::new();
+    archive_manager.update_config(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        ..ArchiveConfig::default()
+    });
+    archive_manager.set_staging(Some(Utc::now() + chrono::Duration::days(7)), None);
+    // Keyed so the two batches cannot share a millisecond id.
+    let mut stage = |key: &str, path: String| {
+        archive_manager.set_idempotency_key(Some(key.to_string()));
+        archive_manager.archive_files(vec![path], &db).unwrap().batch_id
+    };
+    let whole_batch = stage("whole", restored_path.clone());
+    let picked_batch = stage("picked", picked_path.clone());
+
+    // Restoring drops the cooloff staging gave the files, so only the
+    // logged restores keep them from coming straight back.
+    let mut undo_manager = UndoManager::new();
+    assert!(undo_manager.undo_batch(&whole_batch, &db).unwrap().errors.is_empty());
+    assert!(undo_manager
+        .undo_files(&picked_batch, &[picked], &db)
+        .unwrap()
+        .errors
+        .is_empty());
+    for (id, path) in [(restored, &restored_path), (picked, &picked_path)] {
+        let file = db.get_file_by_id(id).unwrap().unwrap();
+        assert!(!file.is_staged && file.cooloff_until.is_none());
+        assert!(Path::new(path).exists());
     }
 
-    #[test]
-    fn test_delete_outcome_serialization() {
-        let outcome = DeleteOutcome {
-            success: true,
-            files_processed: 3,
-            total_bytes_freed: 512 * 1024,
-            duration_ms: 500,
-            errors: vec![],
-            to_trash: true,
-        };
-
-        let json = serde_json::to_string(&outcome).unwrap();
-        assert!(json.contains("success"));
-        assert!(json.contains("to_trash"));
-    }
+    let root = temp_dir.path().to_string_lossy().to_string();
+    let candidate_ids = |db: &Database| {
+        let mut selector = FileSelector::from_db(db);
+        selector.set_roots(vec![root.clone()]);
+        let stats = selector.get_bucket_stats(db).unwrap();
+        let ids: Vec<i64> = selector
+            .daily_candidates_under_roots(std::slice::from_ref(&root), Some(100), db)
+            .unwrap()
+            .iter()
+            .map(|c| c.file_id)
+            .collect();
+        assert_eq!(stats["old_desktop"].0, ids.len());
+        ids
+    };
+    assert_eq!(candidate_ids(&db), vec![untouched]);
+
+    db.set_preference(RESTORE_GRACE_DAYS_PREF, "0").unwrap();
+    let mut ids = candidate_ids(&db);
+    ids.sort();
+    assert_eq!(ids, vec![restored, picked, untouched]);
+
+    let prefs = PartialUserPrefs {
+        restore_grace_days: Some(MAX_RESTORE_GRACE_DAYS + 1),
+        ..Default::default()
+    };
+    assert!(validate_prefs(&prefs).unwrap_err().contains("ERR_VALIDATION"));
+}
 
-    #[test]
-    fn test_staged_file_serialization() {
-        let staged_file = StagedFile {
-            record_id: 1,
-            file_id: 1,
-            path: "/test/path".to_string(),
-            parent_dir: "/test".to_string(),
-            size_bytes: 1024,
-            status: "staged".to_string(),
-            staged_at: chrono::Utc::now().to_rfc3339(),
-            expires_at: None,
-            batch_id: Some("batch".to_string()),
-            note: None,
-            cooloff_until: None,
-        };
+#[test]
+fn test_moved_file_keeps_its_row_and_history() {
+    let (_temp_dir, db) = setup_test_db();
+    let add = |path: &str, sha1: Option<&str>| {
+        db.upsert_file(&NewFile {
+            size_bytes: 4096,
+            partial_sha1: Some("partial".to_string()),
+            sha1: sha1.map(str::to_string),
+            ..new_file(path)
+        })
+        .unwrap()
+    };
+    let old_id = add("/home/u/Downloads/big.iso", Some("full"));
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let scan_started_at = Utc::now();
+    let vanished = db
+        .mark_missing_for_root("/home/u/Downloads", &HashSet::new())
+        .unwrap();
+    assert_eq!(vanished, vec![old_id]);
+    let new_id = add("/home/u/Archive/big.iso", None);
+
+    let gone: Vec<_> = vanished
+        .iter()
+        .filter_map(|id| db.get_file_by_id(*id).unwrap())
+        .collect();
+    let arrived = db.files_first_seen_since(&scan_started_at).unwrap();
+    let moves = crate::scanner::moves::match_moves(&gone, &arrived);
+    assert_eq!(moves, vec![(old_id, new_id)]);
+    db.merge_moved_file(old_id, new_id).unwrap();
+
+    let moved = db.get_file_by_id(old_id).unwrap().unwrap();
+    assert_eq!(moved.path, "/home/u/Archive/big.iso");
+    assert_eq!(moved.parent_dir, "/home/u/Archive");
+    assert!(!moved.is_deleted);
+    assert_eq!(moved.sha1.as_deref(), Some("full"));
+    assert!(db.get_file_by_id(new_id).unwrap().is_none());
+    assert_eq!(
+        db.get_file_id_by_path("/home/u/Archive/big.iso").unwrap(),
+        Some(old_id)
+    );
+
+    let action = db.latest_action(old_id).unwrap().unwrap();
+    assert_eq!(action.action, ActionType::Move);
+    assert_eq!(action.src_path.as_deref(), Some("/home/u/Downloads/big.iso"));
+    assert_eq!(action.dst_path.as_deref(), Some("/home/u/Archive/big.iso"));
+    // A move has no batch, so it never becomes the batch to undo.
+    assert_eq!(db.get_latest_batch_id().unwrap(), None);
+}
 
-        let json = serde_json::to_string(&staged_file).unwrap();
-        assert!(json.contains("file_id"));
-        assert!(json.contains("path"));
+fn zip_archive_manager(temp_dir: &TempDir) -> ArchiveManager {
+    let mut archive_manager = ArchiveManager::new();
+    archive_manager.update_config(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        compression: ops::ArchiveCompression::Zip,
+        ..ArchiveConfig::default()
+    });
+    archive_manager
+}
+
+fn index_files(db: &Database, dir: &Path, names: &[&str]) -> (Vec<String>, Vec<i64>) {
+    fs::create_dir_all(dir).unwrap();
+    let mut paths = Vec::new();
+    let mut ids = Vec::new();
+    for name in names {
+        let path = dir.join(name);
+        fs::write(&path, name.repeat(100)).unwrap();
+        let path = path.to_string_lossy().to_string();
+        ids.push(
+            db.upsert_file(&NewFile {
+                size_bytes: name.len() as i64 * 100,
+                ..new_file(path.clone())
+            })
+            .unwrap(),
+        );
+        paths.push(path);
     }
+    (paths, ids)
+}
+
+#[test]
+fn test_zip_archive_writes_manifest_and_restores_members() {
+    let (temp_dir, db) = setup_test_db();
+    let downloads = temp_dir.path().join("Downloads");
+    let (paths, ids) = index_files(&db, &downloads, &["a.txt", "b.txt"]);
+
+    let result = zip_archive_manager(&temp_dir)
+        .archive_files(paths.clone(), &db)
+        .unwrap();
+    assert!(result.errors.is_empty());
+    assert_eq!(result.files_archived, 2);
+    assert!(paths.iter().all(|path| !Path::new(path).exists()));
+    let zip_path = PathBuf::from(result.archive_file.unwrap());
+    assert!(zip_path.is_file());
+
+    let manifest = ops::compress::read_manifest(&zip_path).unwrap();
+    assert_eq!(manifest.batch_id, result.batch_id);
+    let entries: Vec<(&str, &str, u64)> = manifest
+        .entries
+        .iter()
+        .map(|e| (e.name.as_str(), e.original_path.as_str(), e.size_bytes))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("a.txt", paths[0].as_str(), 500),
+            ("b.txt", paths[1].as_str(), 500)
+        ]
+    );
+    let archived = db.get_file_by_id(ids[0]).unwrap().unwrap();
+    assert_eq!(archived.path, ops::compress::member_path(&zip_path, "a.txt"));
+
+    let mut undo = UndoManager::new();
+    let first = undo.undo_files(&result.batch_id, &ids[..1], &db).unwrap();
+    assert!(first.errors.is_empty());
+    assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "a.txt".repeat(100));
+    assert_eq!(db.get_file_by_id(ids[0]).unwrap().unwrap().path, paths[0]);
+    // b.txt still lives only in the zip.
+    assert!(zip_path.is_file());
+
+    let second = undo.undo_files(&result.batch_id, &ids[1..], &db).unwrap();
+    assert!(second.errors.is_empty());
+    assert_eq!(fs::read_to_string(&paths[1]).unwrap(), "b.txt".repeat(100));
+    assert!(!zip_path.exists());
+}
+
+#[test]
+fn test_archive_location_must_not_overlap_a_watched_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let roots = vec![home.join("Downloads").to_string_lossy().to_string()];
+
+    let inside = ArchiveConfig::validate_base_path(&home.join("Downloads/Archive"), &roots);
+    assert!(inside.unwrap_err().to_string().contains("inside the watched folder"));
+    let around = ArchiveConfig::validate_base_path(&home, &roots);
+    assert!(around.unwrap_err().to_string().contains("contain the watched folder"));
+    // Only whole components count: a sibling sharing the prefix is fine.
+    assert!(ArchiveConfig::validate_base_path(&home.join("Downloads Archive"), &roots).is_ok());
+    assert!(home.join("Downloads Archive").is_dir());
+}
+
+#[test]
+fn test_zip_archive_drops_members_whose_original_stays() {
+    let (temp_dir, db) = setup_test_db();
+    let downloads = temp_dir.path().join("Downloads");
+    let (mut paths, ids) = index_files(&db, &downloads, &["a.txt"]);
+    // Never indexed, so it cannot be logged and its original stays put.
+    let stray = downloads.join("stray.txt");
+    fs::write(&stray, "stray".repeat(100)).unwrap();
+    paths.push(stray.to_string_lossy().to_string());
+
+    let result = zip_archive_manager(&temp_dir)
+        .archive_files(paths.clone(), &db)
+        .unwrap();
+    assert_eq!(result.files_archived, 1);
+    assert_eq!(result.total_bytes, 500);
+    assert_eq!(result.errors.len(), 1);
+    assert!(result.errors[0].contains("stray.txt"));
+    assert!(stray.exists());
+
+    let zip_path = PathBuf::from(result.archive_file.unwrap());
+    let manifest = ops::compress::read_manifest(&zip_path).unwrap();
+    let names: Vec<&str> = manifest.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["a.txt"]);
+    assert!(!ops::compress::has_entry(&zip_path, "stray.txt"));
+    assert!(ops::compress::has_entry(&zip_path, "a.txt"));
+    let compressed = fs::metadata(&zip_path).unwrap().len();
+    assert_eq!(result.bytes_saved, 500u64.saturating_sub(compressed));
+
+    // With nothing else left in it, undoing the batch removes the zip.
+    let undo = UndoManager::new().undo_batch(&result.batch_id, &db).unwrap();
+    assert!(undo.errors.is_empty());
+    assert_eq!(db.get_file_by_id(ids[0]).unwrap().unwrap().path, paths[0]);
+    assert!(!zip_path.exists());
+
+    // When no original could be archived the zip is not kept at all.
+    let unindexed = downloads.join("other.txt");
+    fs::write(&unindexed, "other").unwrap();
+    let result = zip_archive_manager(&temp_dir)
+        .archive_files(vec![unindexed.to_string_lossy().to_string()], &db)
+        .unwrap();
+    assert_eq!(result.files_archived, 0);
+    assert!(result.archive_file.is_none());
+    assert!(unindexed.exists());
+}
 
-    #[test]
-    fn test_user_prefs_serialization() {
-        let prefs = UserPrefs {
-            dry_run_default: true,
-            tidy_day: "Mon".to_string(),
-            tidy_hour: 9,
-            rolling_window_days: 7,
-            max_candidates_per_day: 10,
-            thumbnail_max_size: 512,
-            auto_scan_enabled: true,
-            scan_interval_hours: 12,
-            archive_age_threshold_days: 7,
-            delete_age_threshold_days: 30,
-        };
-
-        let json = serde_json::to_string(&prefs).unwrap();
-        assert!(json.contains("dry_run_default"));
-        assert!(json.contains("tidy_day"));
+#[test]
+fn test_cancelled_zip_archive_leaves_originals() {
+    let (temp_dir, db) = setup_test_db();
+    let downloads = temp_dir.path().join("Downloads");
+    let (paths, ids) = index_files(&db, &downloads, &["a.txt", "b.txt", "c.txt"]);
+
+    let mut archive_manager = zip_archive_manager(&temp_dir);
+    archive_manager.set_idempotency_key(Some("cancel-zip".to_string()));
+    archive_manager.set_progress_callback(Box::new(|_: &ops::OpsProgressPayload| {
+        ops::cancel::cancel_batch("archive_cancel-zip");
+    }));
+    let result = archive_manager.archive_files(paths.clone(), &db).unwrap();
+
+    assert!(result.cancelled);
+    assert_eq!(result.files_archived, 0);
+    assert!(result.archive_file.is_none());
+    assert!(paths.iter().all(|path| Path::new(path).exists()));
+    let leftovers: Vec<_> = fs::read_dir(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        ..ArchiveConfig::default()
     }
+    .get_daily_path())
+    .unwrap()
+    .collect();
+    assert!(leftovers.is_empty());
+    assert!(db.get_actions_by_batch_id("archive_cancel-zip").unwrap().is_empty());
+    for (id, path) in ids.iter().zip(&paths) {
+        assert_eq!(&db.get_file_by_id(*id).unwrap().unwrap().path, path);
+    }
+}
 
-    #[test]
-    fn test_partial_user_prefs_deserialization() {
-        let json = r#"{
-            "dry_run_default": true,
-            "tidy_day": "Mon",
-            "tidy_hour": 9,
-            "rolling_window_days": 7,
-            "max_candidates_per_day": 10,
-            "thumbnail_max_size": 512,
-            "auto_scan_enabled": true,
-            "scan_interval_hours": 12,
-            "archive_age_threshold_days": 7,
-            "delete_age_threshold_days": 30
-        }"#;
-
-        let prefs: PartialUserPrefs = serde_json::from_str(json).unwrap();
-        assert_eq!(prefs.dry_run_default, Some(true));
-        assert_eq!(prefs.tidy_day, Some("Mon".to_string()));
+#[test]
+fn test_retention_sweep_skips_files_held_by_another_operation() {
+    let (temp_dir, db) = setup_test_db();
+    let now = Utc::now();
+    let mut ids = Vec::new();
+    for name in ["held.txt", "free.txt"] {
+        let archived = temp_dir.path().join(name);
+        fs::write(&archived, b"archived").unwrap();
+        let file_id = db
+            .upsert_file(&NewFile {
+                size_bytes: 8,
+                ..new_file(archived.to_string_lossy().to_string())
+            })
+            .unwrap();
+        db.stage_files(&[crate::models::NewStagedFile {
+            file_id,
+            staged_at: now - chrono::Duration::days(47),
+            expires_at: Some(now - chrono::Duration::days(40)),
+            batch_id: Some(format!("archive_{name}")),
+            status: "expired".to_string(),
+            note: None,
+        }])
+        .unwrap();
+        ids.push(file_id);
     }
+
+    let guard = ops::locks::lock_files("undo", &ids[..1]).unwrap();
+    let payload = ops::expiry::sweep_retention(&db, now, 30, false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.files_purged, 1);
+    assert!(temp_dir.path().join("held.txt").exists());
+    assert!(!temp_dir.path().join("free.txt").exists());
+    // The sweep let go of what it claimed.
+    assert!(ops::locks::lock_files("stage", &ids[1..]).is_ok());
+
+    drop(guard);
+    let payload = ops::expiry::sweep_retention(&db, now, 30, false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.archive_batch_ids, vec!["archive_held.txt".to_string()]);
+    assert!(!temp_dir.path().join("held.txt").exists());
 }
 
-// Add Default implementation for PartialUserPrefs
-impl Default for PartialUserPrefs {
-    fn default() -> Self {
-        Self {
-            dry_run_default: None,
-            tidy_day: None,
-            tidy_hour: None,
-            rolling_window_days: None,
-            max_candidates_per_day: None,
-            thumbnail_max_size: None,
-            auto_scan_enabled: None,
-            scan_interval_hours: None,
-            archive_age_threshold_days: None,
-            delete_age_threshold_days: None,
-        }
+#[test]
+fn test_full_rescan_leaves_staged_and_archived_files_alone() {
+    let (temp_dir, _db) = setup_test_db();
+    let pool = init_pool(&temp_dir.path().join("test.db"));
+    let db = Database::new(pool.get().unwrap());
+    let root = temp_dir.path().join("Documents");
+    fs::create_dir_all(&root).unwrap();
+    for name in ["kept.txt", "staged.txt", "archived.txt"] {
+        fs::write(root.join(name), name.repeat(100)).unwrap();
     }
+    let root_path = root.to_string_lossy().to_string();
+    db.upsert_watched_root(&root_path).unwrap();
+    crate::scanner::run_headless_scan(&pool, vec![root_path.clone()]).unwrap();
+    let file_id = |name: &str| {
+        db.get_file_id_by_path(&root.join(name).to_string_lossy())
+            .unwrap()
+            .unwrap()
+    };
+    let (staged_id, archived_id) = (file_id("staged.txt"), file_id("archived.txt"));
+
+    let mut archive_manager = ArchiveManager::new();
+    archive_manager.update_config(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        ..ArchiveConfig::default()
+    });
+    archive_manager
+        .archive_files(vec![root.join("archived.txt").to_string_lossy().to_string()], &db)
+        .unwrap();
+    archive_manager.set_staging(Some(Utc::now() + chrono::Duration::days(7)), None);
+    let staged = archive_manager
+        .archive_files(vec![root.join("staged.txt").to_string_lossy().to_string()], &db)
+        .unwrap();
+    assert_eq!(staged.files_archived, 1);
+
+    crate::scanner::run_headless_scan(&pool, vec![root_path]).unwrap();
+
+    let staged_file = db.get_file_by_id(staged_id).unwrap().unwrap();
+    assert!(staged_file.is_staged);
+    assert!(!staged_file.is_deleted);
+    assert!(staged_file.cooloff_until.is_some());
+    let staged_rows = db.list_staged_with_files(None).unwrap();
+    assert_eq!(staged_rows.len(), 1);
+    assert_eq!(staged_rows[0].1.id, Some(staged_id));
+    assert_eq!(staged_rows[0].0.batch_id.as_deref(), Some(staged.batch_id.as_str()));
+    assert!(!db.get_file_by_id(archived_id).unwrap().unwrap().is_deleted);
+}
+
+#[test]
+fn test_new_file_at_an_archived_path_gets_its_own_row() {
+    let (temp_dir, db) = setup_test_db();
+    let downloads = temp_dir.path().join("Downloads");
+    let (paths, ids) = index_files(&db, &downloads, &["report.pdf"]);
+
+    let mut archive_manager = ArchiveManager::new();
+    archive_manager.update_config(ArchiveConfig {
+        base_path: temp_dir.path().join("archive"),
+        ..ArchiveConfig::default()
+    });
+    let result = archive_manager.archive_files(paths.clone(), &db).unwrap();
+    assert_eq!(result.files_archived, 1);
+    let archived_path = db.get_file_by_id(ids[0]).unwrap().unwrap().path;
+    assert_ne!(archived_path, paths[0]);
+
+    fs::write(&paths[0], "a new report").unwrap();
+    let new_id = db.upsert_file(&new_file(paths[0].clone())).unwrap();
+
+    assert_ne!(new_id, ids[0]);
+    assert_eq!(db.get_file_by_id(ids[0]).unwrap().unwrap().path, archived_path);
+    assert_eq!(db.get_file_id_by_path(&archived_path).unwrap(), Some(ids[0]));
+    assert_eq!(db.get_file_id_by_path(&paths[0]).unwrap(), Some(new_id));
 }
@@ -1,20 +1,304 @@
-use crate::models::{Action, File, NewAction, NewFile, NewMetric, NewStagedFile, StagedFileRecord, WatchedRoot, WeeklyTotals};
+use crate::models::{Action, AgeHistogramBin, AgeMetric, BatchFilters, BatchRecord, BucketFeedback, BucketPrefilter, BucketStatsQuery, DismissedFile, Exclusion, FeedbackVerdict, File, FileSearchFilters, HashAlgo, IdempotencyClaim, NewAction, NewBatch, NewFile, NewMetric, MimeFamilyTotals, NewSavedView, NewScanRecord, NewStagedFile, NewUserRule, PageCursor, ProtectedPath, RootSettings, SavedView, ScanRecord, SelectionSummary, StagedFileRecord, UserRule, VolumeKind, WatchedRoot, WeeklyTotals};
 use chrono::{DateTime, Utc};
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{params, OptionalExtension, Result as SqliteResult, Row};
-use std::collections::HashSet;
-use std::path::Path;
+use rusqlite::{params, Connection, DatabaseName, ErrorCode, OpenFlags, OptionalExtension, Result as SqliteResult, Row, Transaction, TransactionBehavior};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub struct Database {
     conn: PooledConnection<SqliteConnectionManager>,
 }
 
+/// Attempts `retry_busy` makes before giving up on a locked database.
+const BUSY_RETRIES: u32 = 5;
+/// First pause between attempts; doubled each time, plus jitter.
+const BUSY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Whether `err` means another connection (or another app instance) holds
+/// the lock, as opposed to a real failure.
+pub fn is_busy(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Runs `op`, retrying with exponential backoff while the database is busy.
+/// Other errors, and the last busy error, are returned as they are.
+pub fn retry_busy<T>(mut op: impl FnMut() -> SqliteResult<T>) -> SqliteResult<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(err) if is_busy(&err) && attempt + 1 < BUSY_RETRIES => {
+                let backoff = BUSY_BACKOFF * 2u32.pow(attempt);
+                tracing::debug!(attempt, "database busy, retrying in {:?}", backoff);
+                std::thread::sleep(backoff + jitter(backoff / 2));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Up to `max` of pseudo-random delay so waiting writers don't wake together.
+fn jitter(max: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let max_nanos = max.as_nanos().max(1) as u64;
+    Duration::from_nanos(u64::from(nanos) % max_nanos)
+}
+
+/// Latest schema version; `run_migrations` brings older databases up to it.
+pub const SCHEMA_VERSION: u32 = 20;
+
+/// Lower edges, in days, of the age histogram bins: 0-30, 30-90, 90-365
+/// and a year or more.
+pub const AGE_HISTOGRAM_EDGES: [i64; 4] = [0, 30, 90, 365];
+
+/// How long a retry with the same idempotency key gets the stored outcome.
+pub const IDEMPOTENCY_KEY_TTL_DAYS: i64 = 7;
+/// A claim still running after this long is treated as abandoned.
+pub const IDEMPOTENCY_PENDING_MINUTES: i64 = 60;
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    apply: fn(&Database) -> SqliteResult<()>,
+}
+
+/// Ordered by version. Append new migrations; never edit an applied one.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        apply: Database::migrate_baseline,
+    },
+    Migration {
+        version: 2,
+        name: "scan_history",
+        apply: Database::migrate_scan_history,
+    },
+    Migration {
+        version: 3,
+        name: "cloud_placeholders",
+        apply: Database::migrate_cloud_placeholders,
+    },
+    Migration {
+        version: 4,
+        name: "root_settings",
+        apply: Database::migrate_root_settings,
+    },
+    Migration {
+        version: 5,
+        name: "root_volumes",
+        apply: Database::migrate_root_volumes,
+    },
+    Migration {
+        version: 6,
+        name: "offline_roots",
+        apply: Database::migrate_offline_roots,
+    },
+    Migration {
+        version: 7,
+        name: "file_root_ids",
+        apply: Database::migrate_file_root_ids,
+    },
+    Migration {
+        version: 8,
+        name: "file_path_keys",
+        apply: Database::migrate_file_path_keys,
+    },
+    Migration {
+        version: 9,
+        name: "symlinks",
+        apply: Database::migrate_symlinks,
+    },
+    Migration {
+        version: 10,
+        name: "hash_algo",
+        apply: Database::migrate_hash_algo,
+    },
+    Migration {
+        version: 11,
+        name: "sampled_partial_hashes",
+        apply: Database::migrate_sampled_partial_hashes,
+    },
+    Migration {
+        version: 12,
+        name: "bucket_indexes",
+        apply: Database::migrate_bucket_indexes,
+    },
+    Migration {
+        version: 13,
+        name: "selections",
+        apply: Database::migrate_selections,
+    },
+    Migration {
+        version: 14,
+        name: "feedback",
+        apply: Database::migrate_feedback,
+    },
+    Migration {
+        version: 15,
+        name: "scan_mode",
+        apply: Database::migrate_scan_mode,
+    },
+    Migration {
+        version: 16,
+        name: "idempotency_keys",
+        apply: Database::migrate_idempotency_keys,
+    },
+    Migration {
+        version: 17,
+        name: "saved_views",
+        apply: Database::migrate_saved_views,
+    },
+    Migration {
+        version: 18,
+        name: "batches",
+        apply: Database::migrate_batches,
+    },
+    Migration {
+        version: 19,
+        name: "move_actions",
+        apply: Database::migrate_move_actions,
+    },
+    Migration {
+        version: 20,
+        name: "unique_staged_files",
+        apply: Database::migrate_unique_staged_files,
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The `schema_version` bookkeeping itself failed.
+    Setup(rusqlite::Error),
+    /// The database was written by a newer build of the app.
+    NewerSchema { found: u32, supported: u32 },
+    /// A migration failed and was rolled back.
+    Failed {
+        version: u32,
+        name: &'static str,
+        source: rusqlite::Error,
+    },
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Setup(err) => write!(f, "could not read schema version: {}", err),
+            MigrationError::NewerSchema { found, supported } => write!(
+                f,
+                "database schema version {} is newer than this app supports ({})",
+                found, supported
+            ),
+            MigrationError::Failed {
+                version,
+                name,
+                source,
+            } => write!(f, "migration {} ({}) failed: {}", version, name, source),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrationError::Setup(err) => Some(err),
+            MigrationError::NewerSchema { .. } => None,
+            MigrationError::Failed { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Tables a file must have to be restored as this app's database.
+const BACKUP_REQUIRED_TABLES: &[&str] = &["files", "actions", "prefs", "watched_roots"];
+
+/// Rows of `PRAGMA quick_check` / `integrity_check` other than "ok".
+pub(crate) fn integrity_problems(conn: &Connection, full: bool) -> SqliteResult<Vec<String>> {
+    let pragma = if full { "PRAGMA integrity_check" } else { "PRAGMA quick_check" };
+    let mut stmt = conn.prepare(pragma)?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let mut problems = Vec::new();
+    for row in rows {
+        let row = row?;
+        if row != "ok" {
+            problems.push(row);
+        }
+    }
+    Ok(problems)
+}
+
+/// Buckets a `mime` column into the families used by the usage dashboard.
+const MIME_FAMILY_SQL: &str = "CASE
+    WHEN mime LIKE 'image/%' THEN 'images'
+    WHEN mime LIKE 'video/%' THEN 'video'
+    WHEN mime LIKE 'audio/%' THEN 'audio'
+    WHEN mime IN ('application/zip', 'application/x-tar', 'application/gzip', 'application/x-7z-compressed',
+                  'application/x-rar-compressed', 'application/x-bzip2', 'application/x-xz',
+                  'application/x-apple-diskimage') THEN 'archives'
+    WHEN mime IN ('application/javascript', 'application/json', 'application/xml', 'text/html', 'text/css')
+         OR mime LIKE 'text/x-%' THEN 'code'
+    WHEN mime IN ('application/pdf', 'application/rtf', 'application/msword', 'text/plain', 'text/markdown', 'text/csv')
+         OR mime LIKE 'application/vnd.%' THEN 'documents'
+    ELSE 'other'
+END";
+
 impl Database {
     pub fn new(conn: PooledConnection<SqliteConnectionManager>) -> Self {
         Database { conn }
     }
 
+    /// Opens the database file at `path` on a pool of its own. Tests point it
+    /// at a temp dir and run migrations themselves.
+    #[cfg(test)]
+    pub fn open_db(path: impl AsRef<Path>) -> Result<Self, r2d2::Error> {
+        let pool = super::pool::init_pool(path.as_ref());
+        Ok(Database::new(pool.get()?))
+    }
+
+    /// Starts a write transaction that takes the write lock up front, so a
+    /// concurrent writer makes it wait (and retry) here rather than fail
+    /// halfway through.
+    pub fn write_transaction(&self) -> SqliteResult<Transaction<'_>> {
+        retry_busy(|| Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate))
+    }
+
+    /// Runs `op` as one unit: everything it wrote is committed together or,
+    /// when it fails, rolled back. Inside another `transaction` it nests as a
+    /// savepoint, so batch methods can be combined.
+    pub fn transaction<T, E>(&self, op: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+    {
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("SAVEPOINT batch_op")?;
+            return match op() {
+                Ok(value) => {
+                    self.conn.execute_batch("RELEASE batch_op")?;
+                    Ok(value)
+                }
+                Err(err) => {
+                    let undo = self.conn.execute_batch("ROLLBACK TO batch_op; RELEASE batch_op");
+                    if let Err(e) = undo {
+                        tracing::warn!("failed to roll back savepoint: {e}");
+                    }
+                    Err(err)
+                }
+            };
+        }
+        // Dropping an uncommitted transaction rolls it back.
+        let tx = self.write_transaction()?;
+        let value = op()?;
+        tx.commit()?;
+        Ok(value)
+    }
+
     fn map_row_to_file(row: &Row<'_>) -> SqliteResult<File> {
         let mime: Option<String> = row.get("mime").unwrap_or(None);
         let mime = mime.filter(|s| !s.is_empty());
@@ -27,6 +311,14 @@ impl Database {
 
         let is_deleted = row.get::<_, i64>("is_deleted").unwrap_or(0) != 0;
         let is_staged = row.get::<_, i64>("is_staged").unwrap_or(0) != 0;
+        let is_cloud_placeholder = row.get::<_, i64>("is_cloud_placeholder").unwrap_or(0) != 0;
+        let is_offline = row.get::<_, i64>("is_offline").unwrap_or(0) != 0;
+        let is_symlink = row.get::<_, i64>("is_symlink").unwrap_or(0) != 0;
+        let hash_algo = row
+            .get::<_, String>("hash_algo")
+            .ok()
+            .and_then(|algo| algo.parse().ok())
+            .unwrap_or_else(HashAlgo::legacy);
         let cooloff_until = row
             .get::<_, Option<DateTime<Utc>>>("cooloff_until")
             .unwrap_or(None);
@@ -48,6 +340,11 @@ impl Database {
             is_deleted,
             is_staged,
             cooloff_until,
+            is_cloud_placeholder,
+            is_offline,
+            is_symlink,
+            symlink_target: row.get("symlink_target").unwrap_or(None),
+            hash_algo,
         })
     }
 
@@ -63,12 +360,169 @@ impl Database {
         })
     }
 
-    pub fn run_migrations(&self) -> SqliteResult<()> {
-        // Enable WAL mode - use query instead of execute for PRAGMA
+    /// Copies the live database to `dest` with SQLite's online backup API,
+    /// so other pool connections keep working during the copy.
+    pub fn backup_to(&self, dest: &Path) -> SqliteResult<()> {
+        self.conn.backup(DatabaseName::Main, dest, None)
+    }
+
+    /// Replaces the contents of the live database with `src`. Callers should
+    /// check the file with `validate_backup` first and migrate afterwards.
+    pub fn restore_from(&mut self, src: &Path) -> SqliteResult<()> {
+        self.conn
+            .restore(DatabaseName::Main, src, None::<fn(rusqlite::backup::Progress)>)
+    }
+
+    /// On-disk location of the main database, `None` for in-memory ones.
+    pub fn file_path(&self) -> SqliteResult<Option<PathBuf>> {
+        let file: String =
+            self.conn.query_row("PRAGMA database_list", [], |row| row.get(2))?;
+        Ok((!file.is_empty()).then(|| PathBuf::from(file)))
+    }
+
+    /// Bytes held by free pages left behind by deleted rows, and the total
+    /// size of the main database in bytes.
+    pub fn free_space(&self) -> SqliteResult<(u64, u64)> {
+        let page_size: u64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let page_count: u64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let free_pages: u64 =
+            self.conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        Ok((free_pages * page_size, page_count * page_size))
+    }
+
+    /// Rebuilds the file without free pages. Needs exclusive use of this
+    /// connection and roughly the database size again in temporary space.
+    pub fn vacuum(&self) -> SqliteResult<()> {
+        self.conn.execute_batch("VACUUM")
+    }
+
+    pub fn analyze(&self) -> SqliteResult<()> {
+        self.conn.execute_batch("ANALYZE")
+    }
+
+    /// Moves the WAL back into the main file and truncates it to zero bytes.
+    pub fn checkpoint_truncate(&self) -> SqliteResult<()> {
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+    }
+
+    /// Problems reported by SQLite's consistency check; empty when healthy.
+    /// `full` runs `integrity_check`, which also cross-checks every index
+    /// against its table, instead of the much faster `quick_check`.
+    pub fn integrity_problems(&self, full: bool) -> SqliteResult<Vec<String>> {
+        integrity_problems(&self.conn, full)
+    }
+
+    /// Rebuilds every index from its table; fixes index-only corruption.
+    pub fn reindex(&self) -> SqliteResult<()> {
+        self.conn.execute_batch("REINDEX")
+    }
+
+    /// Problems that make `src` unusable as a backup; empty when it is an
+    /// intact database with this app's core tables.
+    pub fn validate_backup(src: &Path) -> SqliteResult<Vec<String>> {
+        // Not read-only: checking the FTS5 search index needs write access.
+        // Without SQLITE_OPEN_CREATE a missing file is still an error.
+        let conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        let mut problems = Vec::new();
+        let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if check != "ok" {
+            problems.push(format!("integrity check failed: {check}"));
+        }
+        for table in BACKUP_REQUIRED_TABLES {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                problems.push(format!("missing table {table}"));
+            }
+        }
+        let versioned: bool = conn.query_row(
+            "SELECT EXISTS(
+                SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+        if versioned {
+            let version: u32 = conn.query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+                [],
+                |row| row.get(0),
+            )?;
+            if version > SCHEMA_VERSION {
+                problems.push(format!(
+                    "schema version {version} is newer than this app supports ({SCHEMA_VERSION})"
+                ));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Applies every migration newer than the recorded schema version, each
+    /// in its own transaction, and records it in `schema_version`.
+    pub fn run_migrations(&self) -> Result<(), MigrationError> {
+        // WAL cannot be switched inside a transaction - use query for PRAGMA
         let _: String = self
             .conn
-            .query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))?;
+            .query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))
+            .map_err(MigrationError::Setup)?;
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS schema_version (
+                    version INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    applied_at TEXT NOT NULL
+                )",
+                [],
+            )
+            .map_err(MigrationError::Setup)?;
+
+        let current = self.schema_version().map_err(MigrationError::Setup)?;
+        if current > SCHEMA_VERSION {
+            return Err(MigrationError::NewerSchema {
+                found: current,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            self.apply_migration(migration)
+                .map_err(|source| MigrationError::Failed {
+                    version: migration.version,
+                    name: migration.name,
+                    source,
+                })?;
+            tracing::info!(
+                version = migration.version,
+                name = migration.name,
+                "applied migration"
+            );
+        }
+        Ok(())
+    }
+
+    /// Highest applied migration, 0 for a database that predates versioning.
+    pub fn schema_version(&self) -> SqliteResult<u32> {
+        self.conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+            row.get(0)
+        })
+    }
+
+    fn apply_migration(&self, migration: &Migration) -> SqliteResult<()> {
+        let tx = self.write_transaction()?;
+        (migration.apply)(self)?;
+        self.conn.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, Utc::now()],
+        )?;
+        tx.commit()
+    }
 
+    /// Everything created before migrations were versioned. Idempotent, so it
+    /// also brings unversioned databases of any age up to date.
+    fn migrate_baseline(&self) -> SqliteResult<()> {
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS files (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -131,6 +585,54 @@ impl Database {
             [],
         )?;
 
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS exclusions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                root_path TEXT,
+                pattern TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_exclusions_root_pattern ON exclusions(COALESCE(root_path, ''), pattern)",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS protected_paths (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT UNIQUE NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS dismissed_files (
+                file_id INTEGER PRIMARY KEY,
+                dismissed_at TEXT NOT NULL,
+                until TEXT,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS user_rules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT UNIQUE NOT NULL,
+                path_glob TEXT,
+                min_size_bytes INTEGER,
+                min_age_days INTEGER,
+                mime_filter TEXT,
+                max_per_day INTEGER NOT NULL DEFAULT 30,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         self.ensure_column("files", "modified_at", "TEXT")?;
         self.ensure_column("files", "accessed_at", "TEXT")?;
         self.ensure_column("files", "last_opened_at", "TEXT")?;
@@ -138,8 +640,12 @@ impl Database {
         self.ensure_column("files", "sha1", "TEXT")?;
         self.ensure_column("files", "is_staged", "INTEGER NOT NULL DEFAULT 0")?;
         self.ensure_column("files", "cooloff_until", "TEXT")?;
+        self.ensure_column("files", "inode_key", "TEXT")?;
+        self.ensure_column("files", "link_count", "INTEGER NOT NULL DEFAULT 1")?;
+        self.ensure_column("files", "phash", "TEXT")?;
         self.ensure_column("actions", "origin", "TEXT")?;
         self.ensure_column("actions", "note", "TEXT")?;
+        self.ensure_column("actions", "verified", "INTEGER NOT NULL DEFAULT 0")?;
 
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS staged_files (\n                id INTEGER PRIMARY KEY AUTOINCREMENT,\n                file_id INTEGER NOT NULL,\n                staged_at TEXT NOT NULL,\n                expires_at TEXT,\n                batch_id TEXT,\n                status TEXT NOT NULL DEFAULT 'pending',\n                note TEXT,\n                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE\n            )",
@@ -174,6 +680,368 @@ impl Database {
             [],
         )?;
 
+        self.ensure_search_index()?;
+
+        Ok(())
+    }
+
+    fn migrate_scan_history(&self) -> SqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS scans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                roots TEXT NOT NULL,
+                trigger_kind TEXT NOT NULL,
+                status TEXT NOT NULL,
+                counted INTEGER NOT NULL DEFAULT 0,
+                skipped INTEGER NOT NULL DEFAULT 0,
+                errors INTEGER NOT NULL DEFAULT 0,
+                error_sample TEXT,
+                duration_ms INTEGER NOT NULL DEFAULT 0,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_scans_finished_at ON scans(finished_at)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Files synced by OneDrive, iCloud and the like can exist only as
+    /// placeholders whose reported size takes no local space.
+    fn migrate_cloud_placeholders(&self) -> SqliteResult<()> {
+        self.ensure_column("files", "is_cloud_placeholder", "INTEGER NOT NULL DEFAULT 0")
+    }
+
+    /// Per-root scan cadence, watcher and I/O priority. A NULL interval means
+    /// the root is only scanned on demand.
+    fn migrate_root_settings(&self) -> SqliteResult<()> {
+        self.ensure_column("watched_roots", "scan_interval_hours", "INTEGER")?;
+        self.ensure_column("watched_roots", "watcher_enabled", "INTEGER NOT NULL DEFAULT 1")?;
+        self.ensure_column("watched_roots", "low_priority_io", "INTEGER NOT NULL DEFAULT 0")?;
+        self.ensure_column("watched_roots", "last_scanned_at", "TEXT")
+    }
+
+    /// Network and removable roots are recorded so they can default to
+    /// cheaper scanning.
+    fn migrate_root_volumes(&self) -> SqliteResult<()> {
+        self.ensure_column("watched_roots", "volume_kind", "TEXT NOT NULL DEFAULT 'local'")?;
+        self.ensure_column("watched_roots", "partial_hash_only", "INTEGER NOT NULL DEFAULT 0")
+    }
+
+    /// An unplugged drive or unmounted share takes its root offline; its
+    /// files are kept as they were instead of being marked deleted.
+    fn migrate_offline_roots(&self) -> SqliteResult<()> {
+        self.ensure_column("watched_roots", "is_offline", "INTEGER NOT NULL DEFAULT 0")?;
+        self.ensure_column("files", "is_offline", "INTEGER NOT NULL DEFAULT 0")
+    }
+
+    /// Ties every file to the watched root it was found under, so scans
+    /// reconcile by root rather than by matching path prefixes. Existing
+    /// rows are assigned from their paths once.
+    fn migrate_file_root_ids(&self) -> SqliteResult<()> {
+        self.ensure_column("files", "root_id", "INTEGER REFERENCES watched_roots(id)")?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_root_id ON files(root_id)",
+            [],
+        )?;
+        for root in self.list_watched_roots()? {
+            self.adopt_files_for_root(root.id, &root.path)?;
+        }
+        Ok(())
+    }
+
+    /// Keys every file by `path_key` and merges rows that turn out to be the
+    /// same file, keeping the staged, live or most recently seen row and
+    /// moving the others' history onto it.
+    fn migrate_file_path_keys(&self) -> SqliteResult<()> {
+        self.ensure_column("files", "path_key", "TEXT")?;
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare("SELECT id, path FROM files")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<SqliteResult<_>>()?
+        };
+        for (id, path) in rows {
+            self.conn.execute(
+                "UPDATE files SET path_key = ?1 WHERE id = ?2",
+                params![Self::path_key(&path), id],
+            )?;
+        }
+
+        let keys: Vec<String> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT path_key FROM files GROUP BY path_key HAVING COUNT(*) > 1",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<SqliteResult<_>>()?
+        };
+        for key in keys {
+            let ids: Vec<i64> = {
+                let mut stmt = self.conn.prepare(
+                    "SELECT id FROM files WHERE path_key = ?1
+                     ORDER BY is_staged DESC, is_deleted ASC, last_seen_at DESC, id DESC",
+                )?;
+                let rows = stmt.query_map([&key], |row| row.get(0))?;
+                rows.collect::<SqliteResult<_>>()?
+            };
+            let Some((&keep, merged)) = ids.split_first() else {
+                continue;
+            };
+            for id in merged {
+                self.conn.execute(
+                    "UPDATE actions SET file_id = ?1 WHERE file_id = ?2",
+                    params![keep, id],
+                )?;
+                for table in ["dismissed_files", "staged_files"] {
+                    self.conn.execute(
+                        &format!("UPDATE OR IGNORE {table} SET file_id = ?1 WHERE file_id = ?2"),
+                        params![keep, id],
+                    )?;
+                    self.conn
+                        .execute(&format!("DELETE FROM {table} WHERE file_id = ?1"), [id])?;
+                }
+                self.conn.execute("DELETE FROM files WHERE id = ?1", [id])?;
+            }
+        }
+
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_files_path_key ON files(path_key)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn migrate_symlinks(&self) -> SqliteResult<()> {
+        self.ensure_column("files", "is_symlink", "INTEGER NOT NULL DEFAULT 0")?;
+        self.ensure_column("files", "symlink_target", "TEXT")
+    }
+
+    /// Everything hashed so far used SHA1. Rows move to BLAKE3 as they are
+    /// hashed again rather than all at once.
+    fn migrate_hash_algo(&self) -> SqliteResult<()> {
+        self.ensure_column("files", "hash_algo", "TEXT NOT NULL DEFAULT 'sha1'")
+    }
+
+    /// Partial hashes used to cover only the head of a file and can never
+    /// equal a head+middle+tail fingerprint, so they are dropped. Rows are
+    /// re-keyed by the next scan, or sooner once a file of their size shows up.
+    fn migrate_sampled_partial_hashes(&self) -> SqliteResult<()> {
+        self.conn
+            .execute("UPDATE files SET partial_sha1 = NULL WHERE partial_sha1 IS NOT NULL", [])?;
+        Ok(())
+    }
+
+    /// Lets the selector's size and symlink checks run in SQL instead of
+    /// over every indexed file.
+    fn migrate_bucket_indexes(&self) -> SqliteResult<()> {
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_size_bytes ON files(size_bytes)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_symlinks ON files(is_symlink) WHERE is_symlink = 1",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Named selections built up across views. Pooled connections can't see
+    /// each other's TEMP tables, so these are plain tables emptied on startup.
+    fn migrate_selections(&self) -> SqliteResult<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS selections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS selection_files (
+                selection_id INTEGER NOT NULL,
+                file_id INTEGER NOT NULL,
+                PRIMARY KEY (selection_id, file_id)
+            ) WITHOUT ROWID;",
+        )
+    }
+
+    /// Verdicts on suggestions. The bucket is copied in so the totals
+    /// outlive the file rows they came from.
+    fn migrate_feedback(&self) -> SqliteResult<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS feedback (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                bucket TEXT NOT NULL,
+                verdict TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_feedback_bucket ON feedback(bucket);",
+        )
+    }
+
+    fn migrate_scan_mode(&self) -> SqliteResult<()> {
+        self.ensure_column("scans", "mode", "TEXT NOT NULL DEFAULT 'normal'")
+    }
+
+    /// Client keys for archive, delete and stage calls, so a retried call
+    /// returns the first call's outcome instead of running again. `outcome`
+    /// is NULL while the first call is still running.
+    fn migrate_idempotency_keys(&self) -> SqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS idempotency_keys (
+                key TEXT PRIMARY KEY,
+                command TEXT NOT NULL,
+                batch_id TEXT,
+                outcome TEXT,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Named searches; `filters` holds the `FileSearchFilters` JSON.
+    fn migrate_saved_views(&self) -> SqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS saved_views (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                query TEXT NOT NULL DEFAULT '',
+                filters TEXT NOT NULL,
+                notify_threshold INTEGER,
+                last_count INTEGER,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// One row per archive or delete batch; the per-file rows stay in
+    /// `actions`.
+    fn migrate_batches(&self) -> SqliteResult<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS batches (
+                batch_id TEXT PRIMARY KEY,
+                action_type TEXT NOT NULL,
+                origin TEXT NOT NULL,
+                label TEXT,
+                file_count INTEGER NOT NULL DEFAULT 0,
+                total_bytes INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_batches_created_at ON batches(created_at);",
+        )?;
+        Ok(())
+    }
+
+    /// Lets `actions` hold `move` rows. SQLite cannot alter a CHECK
+    /// constraint, so the table is rebuilt with its rows and indexes.
+    fn migrate_move_actions(&self) -> SqliteResult<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE actions_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                action TEXT NOT NULL
+                    CHECK (action IN ('archive', 'delete', 'restore', 'move')),
+                batch_id TEXT NOT NULL,
+                src_path TEXT NOT NULL,
+                dst_path TEXT,
+                created_at TEXT NOT NULL,
+                origin TEXT,
+                note TEXT,
+                verified INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (file_id) REFERENCES files (id)
+            );
+            INSERT INTO actions_new (
+                id, file_id, action, batch_id, src_path, dst_path, created_at,
+                origin, note, verified
+            )
+            SELECT id, file_id, action, COALESCE(batch_id, ''), COALESCE(src_path, ''),
+                   dst_path, created_at, origin, note, verified
+            FROM actions;
+            DROP TABLE actions;
+            ALTER TABLE actions_new RENAME TO actions;
+            CREATE INDEX IF NOT EXISTS idx_actions_batch_id ON actions(batch_id);
+            CREATE INDEX IF NOT EXISTS idx_actions_action_created_at
+                ON actions(action, created_at);",
+        )
+    }
+
+    /// `stage_files` upserts on `file_id`, which needs a unique index. Only
+    /// the most recently staged row of each file is kept; rows staged at the
+    /// same moment fall back to the later insert.
+    fn migrate_unique_staged_files(&self) -> SqliteResult<()> {
+        self.conn.execute_batch(
+            "DELETE FROM staged_files
+             WHERE EXISTS (
+                 SELECT 1 FROM staged_files newer
+                 WHERE newer.file_id = staged_files.file_id
+                   AND (newer.staged_at > staged_files.staged_at
+                        OR (newer.staged_at = staged_files.staged_at AND newer.id > staged_files.id))
+             );
+            DROP INDEX IF EXISTS idx_staged_files_file_id;
+            CREATE UNIQUE INDEX idx_staged_files_file_id ON staged_files(file_id);",
+        )
+    }
+
+    /// Identity of a file path. macOS and Windows treat paths that differ
+    /// only in case or Unicode normalization (NFC vs NFD) as the same file,
+    /// so those are folded together there; elsewhere the path is its own key.
+    pub fn path_key(path: &str) -> String {
+        #[cfg(any(target_os = "macos", windows))]
+        {
+            use unicode_normalization::UnicodeNormalization;
+            path.nfc().collect::<String>().to_lowercase()
+        }
+        #[cfg(not(any(target_os = "macos", windows)))]
+        {
+            path.to_string()
+        }
+    }
+
+    /// Trigram FTS5 index over file names and paths, kept in sync with `files`
+    /// by triggers. Backfilled from `files` the first time it is created.
+    fn ensure_search_index(&self) -> SqliteResult<()> {
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'files_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // The file name is whatever follows the last separator.
+        let name_of = |col: &str| {
+            format!("replace({col}, rtrim({col}, replace(replace({col}, '/', ''), '\\', '')), '')")
+        };
+
+        self.conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(name, path, tokenize = 'trigram')",
+            [],
+        )?;
+        self.conn.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS files_fts_ai AFTER INSERT ON files BEGIN
+                INSERT INTO files_fts(rowid, name, path) VALUES (new.id, {new_name}, new.path);
+             END;
+             CREATE TRIGGER IF NOT EXISTS files_fts_ad AFTER DELETE ON files BEGIN
+                DELETE FROM files_fts WHERE rowid = old.id;
+             END;
+             CREATE TRIGGER IF NOT EXISTS files_fts_au AFTER UPDATE OF path ON files BEGIN
+                DELETE FROM files_fts WHERE rowid = old.id;
+                INSERT INTO files_fts(rowid, name, path) VALUES (new.id, {new_name}, new.path);
+             END;",
+            new_name = name_of("new.path"),
+        ))?;
+
+        if !exists {
+            self.conn.execute(
+                &format!(
+                    "INSERT INTO files_fts(rowid, name, path) SELECT id, {}, path FROM files",
+                    name_of("path")
+                ),
+                [],
+            )?;
+        }
         Ok(())
     }
 
@@ -187,28 +1055,46 @@ impl Database {
             }
         }
         let sql = format!("ALTER TABLE {table} ADD COLUMN {column} {column_type}");
-        let _ = self.conn.execute(&sql, []);
+        self.conn.execute(&sql, [])?;
         Ok(())
     }
 
+    /// Inserts or refreshes the row for `file.path`. A stored full hash is
+    /// kept only while it was computed with the same algorithm as the new one.
     pub fn upsert_file(&self, file: &NewFile) -> SqliteResult<i64> {
         let now = Utc::now();
         let created_at = file.created_at.unwrap_or(now);
         self.conn.query_row(
             "INSERT INTO files (
                 path, parent_dir, mime, size_bytes, created_at, modified_at, accessed_at,
-                last_opened_at, partial_sha1, sha1, first_seen_at, last_seen_at, is_deleted
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0)
-            ON CONFLICT(path) DO UPDATE SET
+                last_opened_at, partial_sha1, sha1, first_seen_at, last_seen_at, is_deleted,
+                inode_key, link_count, phash, is_cloud_placeholder, root_id, path_key,
+                is_symlink, symlink_target, hash_algo
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, ?13, ?14, ?15, ?16, ?17, ?18,
+                ?19, ?20, ?21
+            )
+            ON CONFLICT(path_key) DO UPDATE SET
+                path = excluded.path,
                 parent_dir = excluded.parent_dir,
                 mime = excluded.mime,
                 size_bytes = excluded.size_bytes,
                 modified_at = excluded.modified_at,
                 accessed_at = excluded.accessed_at,
                 partial_sha1 = excluded.partial_sha1,
-                sha1 = COALESCE(excluded.sha1, files.sha1),
+                sha1 = CASE WHEN excluded.sha1 IS NULL AND files.hash_algo = excluded.hash_algo
+                    THEN files.sha1 ELSE excluded.sha1 END,
+                hash_algo = excluded.hash_algo,
                 last_seen_at = excluded.last_seen_at,
-                is_deleted = 0
+                is_deleted = 0,
+                inode_key = excluded.inode_key,
+                link_count = excluded.link_count,
+                phash = excluded.phash,
+                is_cloud_placeholder = excluded.is_cloud_placeholder,
+                is_offline = 0,
+                root_id = COALESCE(excluded.root_id, files.root_id),
+                is_symlink = excluded.is_symlink,
+                symlink_target = excluded.symlink_target
             RETURNING id",
             params![
                 &file.path,
@@ -223,24 +1109,63 @@ impl Database {
                 file.sha1.as_deref(),
                 now,
                 now,
+                file.inode_key.as_deref(),
+                file.link_count,
+                file.phash.as_deref(),
+                file.is_cloud_placeholder,
+                file.root_id,
+                Self::path_key(&file.path),
+                file.is_symlink,
+                file.symlink_target.as_deref(),
+                file.hash_algo.to_string(),
             ],
             |row| row.get(0),
         )
     }
 
+    /// Records hashes computed with `algo`. A row never mixes algorithms, so
+    /// `sha1: None` keeps the full hash only if it was made with `algo` too.
     pub fn update_file_hashes(
         &self,
         file_id: i64,
-        partial_sha1: Option<&str>,
+        partial_sha1: &str,
         sha1: Option<&str>,
+        algo: HashAlgo,
     ) -> SqliteResult<()> {
         self.conn.execute(
-            "UPDATE files SET partial_sha1 = COALESCE(?1, partial_sha1), sha1 = COALESCE(?2, sha1) WHERE id = ?3",
-            params![partial_sha1, sha1, file_id],
+            "UPDATE files SET partial_sha1 = ?1,
+                 sha1 = CASE WHEN ?2 IS NULL AND hash_algo = ?3 THEN sha1 ELSE ?2 END,
+                 hash_algo = ?3
+             WHERE id = ?4",
+            params![partial_sha1, sha1, algo.to_string(), file_id],
         )?;
         Ok(())
     }
 
+    /// Active rows hashed with an older algorithm than `current`, or left
+    /// without a partial hash by a change of fingerprint, whose size matches a
+    /// row keyed with `current`. Their hashes cannot be compared, so they are
+    /// the ones a duplicate could hide behind mid-transition.
+    pub fn legacy_hashed_size_matches(
+        &self,
+        current: HashAlgo,
+    ) -> SqliteResult<Vec<(i64, String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, size_bytes FROM files
+             WHERE is_deleted = 0 AND is_offline = 0
+               AND is_cloud_placeholder = 0 AND is_symlink = 0
+               AND (hash_algo != ?1 OR partial_sha1 IS NULL)
+               AND size_bytes IN (
+                 SELECT size_bytes FROM files
+                 WHERE is_deleted = 0 AND hash_algo = ?1 AND partial_sha1 IS NOT NULL
+               )",
+        )?;
+        let rows = stmt.query_map([current.to_string()], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64))
+        })?;
+        rows.collect()
+    }
+
     pub fn mark_missing_as_deleted(&self, existing_paths: &[String]) -> SqliteResult<u64> {
         let placeholders = existing_paths
             .iter()
@@ -277,11 +1202,30 @@ impl Database {
         }
     }
 
-    pub fn get_all_active_files(&self) -> SqliteResult<Vec<File>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM files WHERE is_deleted = 0 ORDER BY last_seen_at DESC")?;
-        let rows = stmt.query_map([], |row| Self::map_row_to_file(row))?;
+    /// Stamps `last_opened_at` when the user opens a file from the app.
+    pub fn record_file_opened(&self, file_id: i64, at: DateTime<Utc>) -> SqliteResult<bool> {
+        let updated = self.conn.execute(
+            "UPDATE files SET last_opened_at = ?2 WHERE id = ?1",
+            params![file_id, at],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Active files under any of `roots`. An empty root list matches nothing.
+    pub fn get_all_active_files_under_roots(&self, roots: &[String]) -> SqliteResult<Vec<File>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let clauses = (1..=roots.len())
+            .map(|i| format!("path LIKE ?{i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!(
+            "SELECT * FROM files WHERE is_deleted = 0 AND ({clauses}) ORDER BY last_seen_at DESC"
+        );
+        let patterns: Vec<String> = roots.iter().map(|r| Self::root_like_pattern(r)).collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(patterns.iter()), Self::map_row_to_file)?;
         let mut files = Vec::new();
         for row in rows {
             files.push(row?);
@@ -289,6 +1233,414 @@ impl Database {
         Ok(files)
     }
 
+    /// Active files under `roots` that could land in one of the selector's
+    /// buckets, newest first. Folder and name checks here are loose (any
+    /// case, anywhere in the path), so callers re-check every row.
+    pub fn bucket_prefiltered_files(
+        &self,
+        roots: &[String],
+        filter: &BucketPrefilter,
+    ) -> SqliteResult<Vec<File>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let roots_clause = Self::roots_clause(roots, &mut values);
+
+        let mut buckets: Vec<String> = Vec::new();
+        if filter.screenshots {
+            buckets.push("path LIKE '%screenshot%' OR path LIKE '%screen shot%'".to_string());
+        }
+        if let Some(min) = filter.big_download_min_bytes {
+            values.push(min.into());
+            buckets.push(format!(
+                "parent_dir LIKE '%downloads%' AND size_bytes > ?{}",
+                values.len()
+            ));
+        }
+        if filter.desktop {
+            buckets.push("parent_dir LIKE '%desktop%'".to_string());
+        }
+        if !filter.installer_extensions.is_empty() {
+            let mut names = Vec::new();
+            for ext in &filter.installer_extensions {
+                values.push(format!("%.{ext}").into());
+                names.push(format!("path LIKE ?{}", values.len()));
+            }
+            buckets.push(format!("parent_dir LIKE '%downloads%' AND ({})", names.join(" OR ")));
+        }
+        if let Some(max) = filter.duplicate_max_bytes {
+            values.push(max.into());
+            buckets.push(format!(
+                "size_bytes <= ?{} AND sha1 IN (
+                   SELECT sha1 FROM files WHERE is_deleted = 0 AND sha1 IS NOT NULL AND sha1 != ''
+                   GROUP BY sha1 HAVING COUNT(*) > 1
+                 )",
+                values.len()
+            ));
+        }
+        if filter.symlinks {
+            buckets.push("is_symlink = 1".to_string());
+        }
+        for (min_size, mime) in &filter.rules {
+            let mut rule = vec!["1".to_string()];
+            if let Some(min) = min_size {
+                values.push((*min).into());
+                rule.push(format!("size_bytes >= ?{}", values.len()));
+            }
+            if let Some(mime) = mime.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+                values.push(Self::mime_like_pattern(mime).into());
+                rule.push(format!("mime LIKE ?{}", values.len()));
+            }
+            buckets.push(rule.join(" AND "));
+        }
+        if buckets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut state = Vec::new();
+        if !filter.include_deleted {
+            state.push("is_deleted = 0 AND ");
+        }
+        if !filter.include_staged {
+            state.push("is_staged = 0 AND ");
+        }
+        let sql = format!(
+            "SELECT * FROM files WHERE {}{roots_clause} AND (({}))
+             ORDER BY last_seen_at DESC",
+            state.concat(),
+            buckets.join(") OR (")
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Self::map_row_to_file(row)
+        })?;
+        rows.collect()
+    }
+
+    /// `(bucket key, count, bytes)` of the built-in buckets under `roots`,
+    /// aggregated in one pass. Broken symlinks need the disk and user rules
+    /// need their globs, so both are left to the caller. Cloud placeholders
+    /// count but add no bytes.
+    pub fn bucket_stats(
+        &self,
+        roots: &[String],
+        query: &BucketStatsQuery,
+    ) -> SqliteResult<Vec<(String, usize, u64)>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let suggestable = Self::suggestable_clause(roots, query, &mut values);
+        let roots_clause = Self::roots_clause(roots, &mut values);
+        let mut param = |value: rusqlite::types::Value| {
+            values.push(value);
+            format!("?{}", values.len())
+        };
+        let screenshot_age = param(i64::from(query.screenshot_min_age_days).into());
+        let download_bytes = param(query.big_download_min_bytes.into());
+        let download_age = param(i64::from(query.big_download_min_age_days).into());
+        let desktop_age = param(i64::from(query.old_desktop_min_age_days).into());
+        let installer_age = param(i64::from(query.installer_min_age_days).into());
+        let duplicate_bytes = param(query.duplicate_max_bytes.into());
+        let installer_names = query
+            .installer_extensions
+            .iter()
+            .map(|ext| format!("name LIKE {}", param(format!("%.{ext}").into())))
+            .collect::<Vec<_>>();
+        let installer_names = if installer_names.is_empty() {
+            "0".to_string()
+        } else {
+            installer_names.join(" OR ")
+        };
+
+        // Age follows the scorer: the later of opened and accessed, else
+        // modified, else when the scan last saw the file, in whole days.
+        let sql = format!(
+            "WITH f AS (
+               SELECT size_bytes, is_cloud_placeholder, sha1, link_count, last_opened_at,
+                      '/' || replace(parent_dir, '\\', '/') || '/' AS dir,
+                      substr(path, length(parent_dir) + 2) AS name,
+                      CAST(julianday('now') - COALESCE(
+                        MAX(julianday(last_opened_at), julianday(accessed_at)),
+                        julianday(last_opened_at), julianday(accessed_at),
+                        julianday(modified_at), julianday(last_seen_at)
+                      ) AS INTEGER) AS age_days
+               FROM files WHERE {suggestable} AND is_symlink = 0
+             )
+             SELECT bucket, COUNT(*),
+                    COALESCE(SUM(CASE WHEN is_cloud_placeholder = 0 THEN size_bytes END), 0)
+             FROM (
+               SELECT 'screenshot' AS bucket, * FROM f
+               WHERE (name LIKE '%screenshot%' OR name LIKE '%screen shot%'
+                      OR dir LIKE '%/screenshots/%')
+                 AND age_days >= {screenshot_age}
+               UNION ALL
+               SELECT 'big_download', * FROM f
+               WHERE dir LIKE '%/downloads/%' AND size_bytes > {download_bytes}
+                 AND (last_opened_at IS NULL OR age_days > {download_age})
+               UNION ALL
+               SELECT 'old_desktop', * FROM f
+               WHERE dir LIKE '%/desktop/%' AND age_days > {desktop_age}
+               UNION ALL
+               SELECT 'installer', * FROM f
+               WHERE dir LIKE '%/downloads/%' AND ({installer_names})
+                 AND age_days > {installer_age}
+               UNION ALL
+               SELECT 'duplicate', * FROM f
+               WHERE size_bytes <= {duplicate_bytes} AND link_count <= 1 AND sha1 IN (
+                 SELECT sha1 FROM files
+                 WHERE is_deleted = 0 AND {roots_clause} AND link_count <= 1
+                   AND sha1 IS NOT NULL AND sha1 != ''
+                 GROUP BY sha1 HAVING COUNT(*) > 1
+               )
+             )
+             GROUP BY bucket"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as usize,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+        rows.collect()
+    }
+
+    /// `(path, size_bytes)` of the symlinks under `roots` that could be
+    /// suggested, for checking on disk whether they still resolve.
+    pub fn suggestable_symlinks(
+        &self,
+        roots: &[String],
+        query: &BucketStatsQuery,
+    ) -> SqliteResult<Vec<(String, u64)>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let suggestable = Self::suggestable_clause(roots, query, &mut values);
+        let sql = format!(
+            "SELECT path, CASE WHEN is_cloud_placeholder = 0 THEN size_bytes ELSE 0 END
+             FROM files WHERE {suggestable} AND is_symlink = 1"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as u64))
+        })?;
+        rows.collect()
+    }
+
+    /// Active, unstaged, online files under `roots` that are neither
+    /// protected, dismissed, cooling off, recently restored nor recently
+    /// opened.
+    fn suggestable_clause(
+        roots: &[String],
+        query: &BucketStatsQuery,
+        values: &mut Vec<rusqlite::types::Value>,
+    ) -> String {
+        let mut clauses = vec![
+            "is_deleted = 0 AND is_staged = 0 AND is_offline = 0".to_string(),
+            Self::roots_clause(roots, values),
+        ];
+        for path in &query.protected {
+            values.push(path.clone().into());
+            values.push(Self::root_like_pattern(path).into());
+            clauses.push(format!(
+                "NOT (path = ?{} OR path LIKE ?{})",
+                values.len() - 1,
+                values.len()
+            ));
+        }
+        let now = Utc::now();
+        values.push(now.to_rfc3339().into());
+        clauses.push(format!(
+            "id NOT IN (SELECT file_id FROM dismissed_files WHERE until IS NULL OR until > ?{})",
+            values.len()
+        ));
+        clauses.push(format!(
+            "(cooloff_until IS NULL OR julianday(cooloff_until) <= julianday(?{}))",
+            values.len()
+        ));
+        if query.restore_grace_days > 0 {
+            let since = now - chrono::Duration::days(query.restore_grace_days);
+            values.push(since.to_rfc3339().into());
+            clauses.push(format!(
+                "id NOT IN (SELECT file_id FROM actions \
+                 WHERE action = 'restore' AND created_at >= ?{0}) \
+                 AND path NOT IN (SELECT dst_path FROM actions \
+                 WHERE action = 'restore' AND created_at >= ?{0})",
+                values.len()
+            ));
+        }
+        let grace = now - chrono::Duration::days(query.recent_open_grace_days);
+        values.push(grace.to_rfc3339().into());
+        clauses.push(format!(
+            "(last_opened_at IS NULL OR julianday(last_opened_at) <= julianday(?{}))",
+            values.len()
+        ));
+        clauses.join(" AND ")
+    }
+
+    /// `(id, sha1)` of active files under `roots` whose full hash another
+    /// active file under `roots` shares.
+    pub fn shared_hash_files_under_roots(
+        &self,
+        roots: &[String],
+    ) -> SqliteResult<Vec<(i64, String)>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let roots_clause = Self::roots_clause(roots, &mut values);
+        let sql = format!(
+            "SELECT id, sha1 FROM files WHERE is_deleted = 0 AND {roots_clause} AND sha1 IN (
+               SELECT sha1 FROM files
+               WHERE is_deleted = 0 AND {roots_clause} AND sha1 IS NOT NULL AND sha1 != ''
+               GROUP BY sha1 HAVING COUNT(*) > 1
+             )"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Repositories under `roots`, found through the files indexed inside
+    /// their `.git` directory.
+    pub fn git_repo_paths_under_roots(&self, roots: &[String]) -> SqliteResult<Vec<String>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let roots_clause = Self::roots_clause(roots, &mut values);
+        let sql = format!(
+            "SELECT DISTINCT substr(path, 1, instr(path, '/.git/') - 1) FROM files
+             WHERE is_deleted = 0 AND {roots_clause} AND instr(path, '/.git/') > 0"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Folders under `roots` where at least `min_files` active files were
+    /// seen since `since`.
+    pub fn busy_dirs_under_roots(
+        &self,
+        roots: &[String],
+        since: DateTime<Utc>,
+        min_files: usize,
+    ) -> SqliteResult<Vec<String>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let roots_clause = Self::roots_clause(roots, &mut values);
+        values.push(since.to_rfc3339().into());
+        values.push((min_files as i64).into());
+        let sql = format!(
+            "SELECT parent_dir FROM files
+             WHERE is_deleted = 0 AND {roots_clause}
+                AND julianday(last_seen_at) > julianday(?{})
+             GROUP BY parent_dir HAVING COUNT(*) >= ?{}",
+            values.len() - 1,
+            values.len()
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Active file count and bytes per `parent_dir` under `root`, grouped on
+    /// the `parent_dir` index so usage reports never touch the disk.
+    pub fn folder_size_totals(&self, root: &str) -> SqliteResult<Vec<(String, u64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT parent_dir, COUNT(*), COALESCE(SUM(size_bytes), 0) FROM files
+             WHERE is_deleted = 0 AND (parent_dir = ?1 OR parent_dir LIKE ?2)
+             GROUP BY parent_dir",
+        )?;
+        let rows = stmt.query_map(params![root, Self::root_like_pattern(root)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64))
+        })?;
+        let mut totals = Vec::new();
+        for row in rows {
+            totals.push(row?);
+        }
+        Ok(totals)
+    }
+
+    /// Active file count and bytes per mime family under `root`, largest first.
+    pub fn mime_family_totals(&self, root: &str) -> SqliteResult<Vec<MimeFamilyTotals>> {
+        let sql = format!(
+            "SELECT {MIME_FAMILY_SQL} AS family, COUNT(*), COALESCE(SUM(size_bytes), 0) AS total
+             FROM files WHERE is_deleted = 0 AND path LIKE ?1
+             GROUP BY family ORDER BY total DESC"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([Self::root_like_pattern(root)], |row| {
+            Ok(MimeFamilyTotals {
+                family: row.get(0)?,
+                file_count: row.get(1)?,
+                total_bytes: row.get(2)?,
+            })
+        })?;
+        let mut totals = Vec::new();
+        for row in rows {
+            totals.push(row?);
+        }
+        Ok(totals)
+    }
+
+    /// Active file count and bytes under `root` per age bin, youngest first.
+    /// Every bin is returned, empty or not. Files missing the `metric`
+    /// timestamp fall back to their modified time, then to when the scan
+    /// last saw them.
+    pub fn age_histogram(
+        &self,
+        root: &str,
+        metric: AgeMetric,
+    ) -> SqliteResult<Vec<AgeHistogramBin>> {
+        let mut bins: Vec<AgeHistogramBin> = AGE_HISTOGRAM_EDGES
+            .iter()
+            .enumerate()
+            .map(|(i, &min_days)| AgeHistogramBin {
+                min_days,
+                max_days: AGE_HISTOGRAM_EDGES.get(i + 1).copied(),
+                file_count: 0,
+                total_bytes: 0,
+            })
+            .collect();
+        let sql = format!(
+            "SELECT CASE WHEN age < ?2 THEN 0 WHEN age < ?3 THEN 1 WHEN age < ?4 THEN 2 ELSE 3 END
+                      AS bin,
+                    COUNT(*), COALESCE(SUM(size_bytes), 0)
+             FROM (
+               SELECT size_bytes,
+                      julianday('now') - julianday(COALESCE({}, modified_at, last_seen_at)) AS age
+               FROM files WHERE is_deleted = 0 AND path LIKE ?1
+             )
+             GROUP BY bin",
+            metric.column()
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            params![
+                Self::root_like_pattern(root),
+                AGE_HISTOGRAM_EDGES[1],
+                AGE_HISTOGRAM_EDGES[2],
+                AGE_HISTOGRAM_EDGES[3]
+            ],
+            |row| Ok((row.get::<_, usize>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+        )?;
+        for row in rows {
+            let (bin, count, bytes) = row?;
+            bins[bin].file_count = count;
+            bins[bin].total_bytes = bytes;
+        }
+        Ok(bins)
+    }
+
     pub fn by_dir(&self, parent_dir: &str) -> SqliteResult<Vec<File>> {
         let mut stmt = self
             .conn
@@ -301,6 +1653,11 @@ impl Database {
         Ok(files)
     }
 
+    pub fn delete_action(&self, id: i64) -> SqliteResult<()> {
+        self.conn.execute("DELETE FROM actions WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
     pub fn insert_action(&self, action: &NewAction) -> SqliteResult<i64> {
         let now = Utc::now();
         self.conn.execute(
@@ -396,6 +1753,12 @@ impl Database {
         stmt.query_row([key], |row| row.get(0)).optional()
     }
 
+    pub fn delete_preference(&self, key: &str) -> SqliteResult<()> {
+        self.conn
+            .execute("DELETE FROM prefs WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
     pub fn get_all_preferences(&self) -> SqliteResult<std::collections::HashMap<String, String>> {
         let mut stmt = self.conn.prepare("SELECT key, value FROM prefs")?;
         let rows = stmt.query_map([], |row| {
@@ -409,6 +1772,198 @@ impl Database {
         Ok(prefs)
     }
 
+    pub fn insert_scan_record(&self, scan: &NewScanRecord) -> SqliteResult<i64> {
+        let roots = serde_json::to_string(&scan.roots).unwrap_or_else(|_| "[]".to_string());
+        self.conn.execute(
+            "INSERT INTO scans (roots, trigger_kind, status, counted, skipped, errors, error_sample,
+                                duration_ms, started_at, finished_at, mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                roots,
+                scan.trigger,
+                scan.status,
+                scan.counted as i64,
+                scan.skipped as i64,
+                scan.errors as i64,
+                scan.error_sample,
+                scan.duration_ms as i64,
+                scan.started_at,
+                scan.finished_at,
+                scan.mode,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Most recent scans first.
+    pub fn list_scan_records(&self, limit: usize) -> SqliteResult<Vec<ScanRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, roots, trigger_kind, status, counted, skipped, errors, error_sample,
+                    duration_ms, started_at, finished_at, mode
+             FROM scans ORDER BY finished_at DESC, id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit as i64], |row| {
+            let roots: String = row.get(1)?;
+            Ok(ScanRecord {
+                id: row.get(0)?,
+                roots: serde_json::from_str(&roots).unwrap_or_default(),
+                trigger: row.get(2)?,
+                status: row.get(3)?,
+                counted: row.get::<_, i64>(4)? as u64,
+                skipped: row.get::<_, i64>(5)? as u64,
+                errors: row.get::<_, i64>(6)? as u64,
+                error_sample: row.get(7)?,
+                duration_ms: row.get::<_, i64>(8)? as u64,
+                started_at: row.get(9)?,
+                finished_at: row.get(10)?,
+                mode: row.get(11)?,
+            })
+        })?;
+        let mut scans = Vec::new();
+        for row in rows {
+            scans.push(row?);
+        }
+        Ok(scans)
+    }
+
+    /// Drops scan records finished before `cutoff` and all but the newest
+    /// `keep` rows. Returns the number removed.
+    pub fn prune_scan_records(&self, cutoff: &DateTime<Utc>, keep: usize) -> SqliteResult<usize> {
+        let aged = self
+            .conn
+            .execute("DELETE FROM scans WHERE finished_at < ?1", params![cutoff])?;
+        let excess = self.conn.execute(
+            "DELETE FROM scans WHERE id NOT IN
+                (SELECT id FROM scans ORDER BY finished_at DESC, id DESC LIMIT ?1)",
+            params![keep as i64],
+        )?;
+        Ok(aged + excess)
+    }
+
+    /// Reserves `key` for `command`. Keys older than
+    /// `IDEMPOTENCY_KEY_TTL_DAYS` and claims left running longer than
+    /// `IDEMPOTENCY_PENDING_MINUTES` (e.g. by a crash) are dropped first.
+    pub fn claim_idempotency_key(
+        &self,
+        key: &str,
+        command: &str,
+    ) -> SqliteResult<IdempotencyClaim> {
+        let now = Utc::now();
+        self.conn.execute(
+            "DELETE FROM idempotency_keys
+             WHERE created_at < ?1 OR (outcome IS NULL AND created_at < ?2)",
+            params![
+                (now - chrono::Duration::days(IDEMPOTENCY_KEY_TTL_DAYS)).to_rfc3339(),
+                (now - chrono::Duration::minutes(IDEMPOTENCY_PENDING_MINUTES)).to_rfc3339()
+            ],
+        )?;
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO idempotency_keys (key, command, created_at)
+             VALUES (?1, ?2, ?3)",
+            params![key, command, now.to_rfc3339()],
+        )?;
+        if inserted == 1 {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+        let (command, outcome): (String, Option<String>) = self.conn.query_row(
+            "SELECT command, outcome FROM idempotency_keys WHERE key = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(match outcome {
+            Some(outcome) => IdempotencyClaim::Completed { command, outcome },
+            None => IdempotencyClaim::Pending { command },
+        })
+    }
+
+    /// Stores the outcome a retry with `key` gets back.
+    pub fn complete_idempotency_key(
+        &self,
+        key: &str,
+        batch_id: Option<&str>,
+        outcome: &str,
+    ) -> SqliteResult<()> {
+        self.conn.execute(
+            "UPDATE idempotency_keys SET batch_id = ?2, outcome = ?3 WHERE key = ?1",
+            params![key, batch_id, outcome],
+        )?;
+        Ok(())
+    }
+
+    /// Frees a claimed key after a failed call so the client can retry it.
+    pub fn release_idempotency_key(&self, key: &str) -> SqliteResult<()> {
+        self.conn.execute(
+            "DELETE FROM idempotency_keys WHERE key = ?1 AND outcome IS NULL",
+            params![key],
+        )?;
+        Ok(())
+    }
+
+    /// Drops undo history recorded before `cutoff`, except for files that
+    /// are still staged and may need it to be restored.
+    pub fn prune_actions_before(&self, cutoff: &DateTime<Utc>) -> SqliteResult<usize> {
+        self.conn.execute(
+            "DELETE FROM actions WHERE created_at < ?1
+             AND file_id NOT IN (SELECT id FROM files WHERE is_staged = 1)",
+            params![cutoff.to_rfc3339()],
+        )
+    }
+
+    pub fn prune_metrics_before(&self, cutoff: &DateTime<Utc>) -> SqliteResult<usize> {
+        self.conn.execute(
+            "DELETE FROM metrics WHERE created_at < ?1",
+            params![cutoff.to_rfc3339()],
+        )
+    }
+
+    /// Removes every row the index holds for files under `root`: the files
+    /// themselves, their undo history, dismissals and finished staging
+    /// records. Staged files are kept. Returns the number of files removed.
+    pub fn purge_root_index(&self, root: &str) -> SqliteResult<usize> {
+        let tx = self.write_transaction()?;
+        let ids: Vec<i64> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id FROM files WHERE is_staged = 0 AND (path = ?1 OR path LIKE ?2)",
+            )?;
+            let rows = stmt.query_map(params![root, Self::root_like_pattern(root)], |row| {
+                row.get(0)
+            })?;
+            rows.collect::<SqliteResult<_>>()?
+        };
+        for id in &ids {
+            self.conn.execute("DELETE FROM actions WHERE file_id = ?1", [id])?;
+            self.conn.execute("DELETE FROM dismissed_files WHERE file_id = ?1", [id])?;
+            self.conn.execute("DELETE FROM staged_files WHERE file_id = ?1", [id])?;
+            self.conn.execute("DELETE FROM files WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(ids.len())
+    }
+
+    /// Values recorded for `metric` since `since`, oldest first.
+    pub fn metric_samples(
+        &self,
+        metric: &str,
+        since: &DateTime<Utc>,
+    ) -> SqliteResult<Vec<(DateTime<Utc>, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT created_at, value FROM metrics
+             WHERE metric = ?1 AND created_at >= ?2
+             ORDER BY created_at ASC, id ASC",
+        )?;
+        let rows = stmt.query_map(params![metric, since.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?;
+        let mut samples = Vec::new();
+        for row in rows {
+            let (created_at, value) = row?;
+            if let Ok(at) = DateTime::parse_from_rfc3339(&created_at) {
+                samples.push((at.with_timezone(&Utc), value));
+            }
+        }
+        Ok(samples)
+    }
+
     pub fn insert_metric(&self, metric: &NewMetric) -> SqliteResult<i64> {
         let now = Utc::now();
         let mut stmt = self.conn.prepare(
@@ -431,50 +1986,129 @@ impl Database {
 
     pub fn upsert_watched_root(&self, path: &str) -> SqliteResult<i64> {
         let now = Utc::now();
-        self.conn.execute(
+        let inserted = self.conn.execute(
             "INSERT OR IGNORE INTO watched_roots (path, created_at) VALUES (?1, ?2)",
             params![path, now],
         )?;
-        self.conn.query_row(
+        let id = self.conn.query_row(
             "SELECT id FROM watched_roots WHERE path = ?1",
             [path],
             |row| row.get(0),
+        )?;
+        // A re-added root or one absorbing nested roots takes over the rows
+        // already indexed under it.
+        if inserted > 0 {
+            self.adopt_files_for_root(id, path)?;
+        }
+        Ok(id)
+    }
+
+    /// Points every file under `path` at root `id`.
+    fn adopt_files_for_root(&self, id: i64, path: &str) -> SqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE files SET root_id = ?1 WHERE path LIKE ?2",
+            params![id, Self::root_like_pattern(path)],
         )
     }
 
+    /// Id of the watched root `path` names: an exact match, or else the root
+    /// that resolves to the same directory (symlinks, trailing separators,
+    /// case on case-insensitive filesystems).
+    pub fn watched_root_id_for(&self, path: &str) -> SqliteResult<Option<i64>> {
+        let roots = self.list_watched_roots()?;
+        if let Some(root) = roots.iter().find(|root| root.path == path) {
+            return Ok(Some(root.id));
+        }
+        let Ok(target) = std::fs::canonicalize(path) else {
+            return Ok(None);
+        };
+        Ok(roots
+            .into_iter()
+            .find(|root| std::fs::canonicalize(&root.path).is_ok_and(|p| p == target))
+            .map(|root| root.id))
+    }
+
+    /// Forgets the watched root at `path`; its files stay indexed until a
+    /// root that contains them adopts them.
     pub fn delete_watched_root(&self, path: &str) -> SqliteResult<()> {
+        let tx = self.write_transaction()?;
+        self.conn.execute(
+            "UPDATE files SET root_id = NULL
+             WHERE root_id IN (SELECT id FROM watched_roots WHERE path = ?1)",
+            [path],
+        )?;
         self.conn
             .execute("DELETE FROM watched_roots WHERE path = ?1", [path])?;
-        Ok(())
+        tx.commit()
+    }
+
+    /// Removes watched root `id` together with the files that belong to it,
+    /// in one transaction. Files are soft-deleted, or with `purge` dropped
+    /// along with their history. Staged files stay restorable but no longer
+    /// point at the root. Returns the root's path and the number of files
+    /// removed, or `None` when there is no such root.
+    pub fn remove_watched_root(
+        &self,
+        id: i64,
+        purge: bool,
+    ) -> SqliteResult<Option<(String, usize)>> {
+        let Some(root) = self.get_watched_root_by_id(id)? else {
+            return Ok(None);
+        };
+        let tx = self.write_transaction()?;
+        let removed = if purge {
+            for table in ["actions", "dismissed_files", "staged_files"] {
+                self.conn.execute(
+                    &format!(
+                        "DELETE FROM {table} WHERE file_id IN
+                         (SELECT id FROM files WHERE root_id = ?1 AND is_staged = 0)"
+                    ),
+                    [id],
+                )?;
+            }
+            self.conn
+                .execute("DELETE FROM files WHERE root_id = ?1 AND is_staged = 0", [id])?
+        } else {
+            self.conn.execute(
+                "UPDATE files SET is_deleted = 1
+                 WHERE root_id = ?1 AND is_staged = 0 AND is_deleted = 0",
+                [id],
+            )?
+        };
+        self.conn
+            .execute("UPDATE files SET root_id = NULL WHERE root_id = ?1", [id])?;
+        self.conn.execute("DELETE FROM watched_roots WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(Some((root.path, removed)))
+    }
+
+    fn map_row_to_watched_root(row: &Row) -> SqliteResult<WatchedRoot> {
+        Ok(WatchedRoot {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            created_at: row.get(2)?,
+            volume_kind: row.get::<_, String>(3)?.parse().unwrap_or_default(),
+            is_offline: row.get::<_, i64>(4)? != 0,
+        })
     }
 
     pub fn get_watched_root_by_id(&self, id: i64) -> SqliteResult<Option<WatchedRoot>> {
         self.conn
             .query_row(
-                "SELECT id, path, created_at FROM watched_roots WHERE id = ?1",
+                "SELECT id, path, created_at, volume_kind, is_offline
+                 FROM watched_roots WHERE id = ?1",
                 [id],
-                |row| {
-                    Ok(WatchedRoot {
-                        id: row.get(0)?,
-                        path: row.get(1)?,
-                        created_at: row.get(2)?,
-                    })
-                },
+                Self::map_row_to_watched_root,
             )
             .optional()
     }
 
     pub fn list_watched_roots(&self) -> SqliteResult<Vec<WatchedRoot>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT id, path, created_at FROM watched_roots ORDER BY created_at ASC")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(WatchedRoot {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?;
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, created_at, volume_kind, is_offline
+             FROM watched_roots ORDER BY created_at ASC",
+        )?;
+        let rows = stmt.query_map([], Self::map_row_to_watched_root)?;
         let mut roots = Vec::new();
         for row in rows {
             roots.push(row?);
@@ -482,49 +2116,502 @@ impl Database {
         Ok(roots)
     }
 
-    pub fn list_watched_paths(&self) -> SqliteResult<Vec<String>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT path FROM watched_roots ORDER BY created_at ASC")?;
-        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
-        let mut paths = Vec::new();
-        for row in rows {
-            paths.push(row?);
-        }
-        Ok(paths)
+    fn map_row_to_root_settings(row: &Row) -> SqliteResult<RootSettings> {
+        Ok(RootSettings {
+            path: row.get("path")?,
+            scan_interval_hours: row.get("scan_interval_hours")?,
+            watcher_enabled: row.get::<_, i64>("watcher_enabled")? != 0,
+            low_priority_io: row.get::<_, i64>("low_priority_io")? != 0,
+            partial_hash_only: row.get::<_, i64>("partial_hash_only")? != 0,
+            last_scanned_at: row.get("last_scanned_at")?,
+            volume_kind: row.get::<_, String>("volume_kind")?.parse().unwrap_or_default(),
+        })
     }
 
-    // File ID lookup methods
-    pub fn get_file_id_by_path(&self, path: &str) -> SqliteResult<Option<i64>> {
+    pub fn get_root_settings(&self, path: &str) -> SqliteResult<Option<RootSettings>> {
         self.conn
             .query_row(
-                "SELECT id FROM files WHERE path = ?1",
+                "SELECT path, scan_interval_hours, watcher_enabled, low_priority_io,
+                        partial_hash_only, last_scanned_at, volume_kind
+                 FROM watched_roots WHERE path = ?1",
                 [path],
-                |row| row.get(0),
+                Self::map_row_to_root_settings,
             )
             .optional()
     }
 
-
-    // Action-related queries
-    pub fn get_actions_by_batch_id(&self, batch_id: &str) -> SqliteResult<Vec<Action>> {
+    pub fn list_root_settings(&self) -> SqliteResult<Vec<RootSettings>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, file_id, action, batch_id, src_path, dst_path, origin, note, created_at FROM actions WHERE batch_id = ?1 ORDER BY created_at ASC"
+            "SELECT path, scan_interval_hours, watcher_enabled, low_priority_io,
+                    partial_hash_only, last_scanned_at, volume_kind
+             FROM watched_roots ORDER BY created_at ASC",
         )?;
-        let rows = stmt.query_map([batch_id], |row| {
-            let action = row
-                .get::<_, String>(2)?
-                .parse()
-                .unwrap_or(crate::models::ActionType::Archive);
-            Ok(Action {
-                id: Some(row.get(0)?),
-                file_id: row.get(1)?,
-                action,
-                batch_id: row.get(3)?,
-                src_path: row.get(4)?,
-                dst_path: row.get(5)?,
-                origin: row.get(6)?,
-                note: row.get(7)?,
+        let rows = stmt.query_map([], Self::map_row_to_root_settings)?;
+        let mut settings = Vec::new();
+        for row in rows {
+            settings.push(row?);
+        }
+        Ok(settings)
+    }
+
+    /// Saves the editable settings of a watched root; `last_scanned_at` and
+    /// `volume_kind` are left alone. Returns false when `path` is not watched.
+    pub fn update_root_settings(&self, settings: &RootSettings) -> SqliteResult<bool> {
+        let changed = self.conn.execute(
+            "UPDATE watched_roots
+             SET scan_interval_hours = ?2, watcher_enabled = ?3, low_priority_io = ?4,
+                 partial_hash_only = ?5
+             WHERE path = ?1",
+            params![
+                settings.path,
+                settings.scan_interval_hours,
+                settings.watcher_enabled,
+                settings.low_priority_io,
+                settings.partial_hash_only,
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn set_root_volume_kind(&self, path: &str, kind: VolumeKind) -> SqliteResult<()> {
+        self.conn.execute(
+            "UPDATE watched_roots SET volume_kind = ?2 WHERE path = ?1",
+            params![path, kind.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Records a completed scan of each of `paths`, for scheduled rescans.
+    pub fn mark_roots_scanned(&self, paths: &[String], at: &DateTime<Utc>) -> SqliteResult<()> {
+        for path in paths {
+            self.conn.execute(
+                "UPDATE watched_roots SET last_scanned_at = ?2 WHERE path = ?1",
+                params![path, at],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Watched root paths with nested roots folded into their outermost
+    /// ancestor, so scans, watchers and totals never cover a subtree twice.
+    pub fn list_watched_paths(&self) -> SqliteResult<Vec<String>> {
+        Ok(Self::outermost_roots(&self.list_all_watched_paths()?))
+    }
+
+    /// Watched root paths, leaving out roots that are offline.
+    pub fn list_online_watched_paths(&self) -> SqliteResult<Vec<String>> {
+        let offline: HashSet<String> = self.list_offline_root_paths()?.into_iter().collect();
+        Ok(self
+            .list_watched_paths()?
+            .into_iter()
+            .filter(|path| !offline.contains(path))
+            .collect())
+    }
+
+    pub fn list_offline_root_paths(&self) -> SqliteResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM watched_roots WHERE is_offline = 1 ORDER BY created_at ASC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    /// Takes a root and its indexed files offline, or brings them back.
+    /// Returns the number of files changed.
+    pub fn set_root_offline(&self, root: &str, offline: bool) -> SqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE watched_roots SET is_offline = ?2 WHERE path = ?1",
+            params![root, offline],
+        )?;
+        self.conn.execute(
+            "UPDATE files SET is_offline = ?2
+             WHERE path LIKE ?1 AND is_deleted = 0 AND is_offline != ?2",
+            params![Self::root_like_pattern(root), offline],
+        )
+    }
+
+    /// Number of indexed, not deleted files under `root`.
+    pub fn count_active_files_under(&self, root: &str) -> SqliteResult<usize> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM files WHERE path LIKE ?1 AND is_deleted = 0",
+            [Self::root_like_pattern(root)],
+            |row| row.get::<_, i64>(0).map(|n| n as usize),
+        )
+    }
+
+    /// Watched root paths whose filesystem watcher is switched on.
+    pub fn list_watcher_paths(&self) -> SqliteResult<Vec<String>> {
+        let disabled: HashSet<String> = self
+            .list_root_settings()?
+            .into_iter()
+            .filter(|settings| !settings.watcher_enabled)
+            .map(|settings| settings.path)
+            .collect();
+        Ok(self
+            .list_watched_paths()?
+            .into_iter()
+            .filter(|path| !disabled.contains(path))
+            .collect())
+    }
+
+    fn list_all_watched_paths(&self) -> SqliteResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM watched_roots ORDER BY created_at ASC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    /// Drops duplicates and every root that sits inside another one, keeping
+    /// the input order. Paths compare by component, so `/a` does not contain `/ab`.
+    pub fn outermost_roots(roots: &[String]) -> Vec<String> {
+        let mut kept: Vec<String> = Vec::new();
+        for root in roots {
+            let path = Path::new(root);
+            let covered = roots
+                .iter()
+                .any(|other| other != root && path.starts_with(Path::new(other)));
+            if !covered && !kept.contains(root) {
+                kept.push(root.clone());
+            }
+        }
+        kept
+    }
+
+    /// Watched roots that already contain `path`, or that `path` would contain.
+    pub fn overlapping_watched_roots(&self, path: &str) -> SqliteResult<(Vec<String>, Vec<String>)> {
+        let target = Path::new(path);
+        let mut ancestors = Vec::new();
+        let mut nested = Vec::new();
+        for root in self.list_all_watched_paths()? {
+            if root == path {
+                continue;
+            }
+            if target.starts_with(Path::new(&root)) {
+                ancestors.push(root);
+            } else if Path::new(&root).starts_with(target) {
+                nested.push(root);
+            }
+        }
+        Ok((ancestors, nested))
+    }
+
+    /// Removes watched roots nested inside another watched root. Installs that
+    /// added `~/Downloads` and later `~/` keep only `~/`. Returns the removed paths.
+    pub fn merge_nested_watched_roots(&self) -> SqliteResult<Vec<String>> {
+        let all = self.list_all_watched_paths()?;
+        let kept = Self::outermost_roots(&all);
+        let mut removed = Vec::new();
+        for path in all {
+            if !kept.contains(&path) && !removed.contains(&path) {
+                self.delete_watched_root(&path)?;
+                removed.push(path);
+            }
+        }
+        if !removed.is_empty() {
+            for root in self.list_watched_roots()? {
+                self.adopt_files_for_root(root.id, &root.path)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn insert_exclusion(&self, root_path: Option<&str>, pattern: &str) -> SqliteResult<i64> {
+        let now = Utc::now();
+        self.conn.execute(
+            "INSERT OR IGNORE INTO exclusions (root_path, pattern, created_at) VALUES (?1, ?2, ?3)",
+            params![root_path, pattern, now],
+        )?;
+        self.conn.query_row(
+            "SELECT id FROM exclusions WHERE COALESCE(root_path, '') = COALESCE(?1, '') AND pattern = ?2",
+            params![root_path, pattern],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_exclusion_by_id(&self, id: i64) -> SqliteResult<Option<Exclusion>> {
+        self.conn
+            .query_row(
+                "SELECT id, root_path, pattern, created_at FROM exclusions WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok(Exclusion {
+                        id: row.get(0)?,
+                        root_path: row.get(1)?,
+                        pattern: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    pub fn list_exclusions(&self) -> SqliteResult<Vec<Exclusion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, root_path, pattern, created_at FROM exclusions ORDER BY created_at ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Exclusion {
+                id: row.get(0)?,
+                root_path: row.get(1)?,
+                pattern: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        let mut exclusions = Vec::new();
+        for row in rows {
+            exclusions.push(row?);
+        }
+        Ok(exclusions)
+    }
+
+    pub fn delete_exclusion(&self, id: i64) -> SqliteResult<()> {
+        self.conn
+            .execute("DELETE FROM exclusions WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn insert_protected_path(&self, path: &str) -> SqliteResult<i64> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO protected_paths (path, created_at) VALUES (?1, ?2)",
+            params![path, Utc::now()],
+        )?;
+        self.conn
+            .query_row("SELECT id FROM protected_paths WHERE path = ?1", [path], |row| row.get(0))
+    }
+
+    pub fn get_protected_path_by_id(&self, id: i64) -> SqliteResult<Option<ProtectedPath>> {
+        self.conn
+            .query_row(
+                "SELECT id, path, created_at FROM protected_paths WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok(ProtectedPath {
+                        id: row.get(0)?,
+                        path: row.get(1)?,
+                        created_at: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    pub fn list_protected_paths(&self) -> SqliteResult<Vec<ProtectedPath>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, path, created_at FROM protected_paths ORDER BY path ASC")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ProtectedPath {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    pub fn delete_protected_path(&self, id: i64) -> SqliteResult<usize> {
+        self.conn.execute("DELETE FROM protected_paths WHERE id = ?1", [id])
+    }
+
+    pub fn dismiss_file(&self, file_id: i64, until: Option<&DateTime<Utc>>) -> SqliteResult<()> {
+        self.conn.execute(
+            "INSERT INTO dismissed_files (file_id, dismissed_at, until) VALUES (?1, ?2, ?3)
+             ON CONFLICT(file_id) DO UPDATE SET dismissed_at = excluded.dismissed_at, until = excluded.until",
+            params![file_id, Utc::now(), until],
+        )?;
+        Ok(())
+    }
+
+    pub fn undismiss_file(&self, file_id: i64) -> SqliteResult<usize> {
+        self.conn.execute("DELETE FROM dismissed_files WHERE file_id = ?1", [file_id])
+    }
+
+    pub fn list_dismissed(&self) -> SqliteResult<Vec<DismissedFile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.file_id, f.path, d.dismissed_at, d.until FROM dismissed_files d \
+             JOIN files f ON f.id = d.file_id ORDER BY d.dismissed_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DismissedFile {
+                file_id: row.get(0)?,
+                path: row.get(1)?,
+                dismissed_at: row.get(2)?,
+                until: row.get(3)?,
+            })
+        })?;
+        let mut dismissed = Vec::new();
+        for row in rows {
+            dismissed.push(row?);
+        }
+        Ok(dismissed)
+    }
+
+    /// Ids of files whose dismissal is still in effect at `now`.
+    pub fn active_dismissed_ids(&self, now: &DateTime<Utc>) -> SqliteResult<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT file_id FROM dismissed_files WHERE until IS NULL OR until > ?1")?;
+        let rows = stmt.query_map([now.to_rfc3339()], |row| row.get::<_, i64>(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
+    }
+
+    /// Files restored since `since`: the restored row itself, and any row now
+    /// indexed at the path a file was restored to.
+    pub fn restored_file_ids_since(&self, since: &DateTime<Utc>) -> SqliteResult<HashSet<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_id FROM actions WHERE action = 'restore' AND created_at >= ?1
+             UNION
+             SELECT f.id FROM actions a JOIN files f ON f.path = a.dst_path
+             WHERE a.action = 'restore' AND a.created_at >= ?1 AND a.dst_path != ''",
+        )?;
+        let rows = stmt.query_map([since.to_rfc3339()], |row| row.get::<_, i64>(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
+    }
+
+    fn map_row_to_user_rule(row: &Row<'_>) -> SqliteResult<UserRule> {
+        Ok(UserRule {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            path_glob: row.get("path_glob")?,
+            min_size_bytes: row.get("min_size_bytes")?,
+            min_age_days: row.get("min_age_days")?,
+            mime_filter: row.get("mime_filter")?,
+            max_per_day: row.get("max_per_day")?,
+            enabled: row.get::<_, i64>("enabled")? != 0,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    pub fn insert_user_rule(&self, rule: &NewUserRule) -> SqliteResult<i64> {
+        self.conn.execute(
+            "INSERT INTO user_rules (name, path_glob, min_size_bytes, min_age_days, mime_filter, max_per_day, enabled, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                rule.name,
+                rule.path_glob,
+                rule.min_size_bytes,
+                rule.min_age_days,
+                rule.mime_filter,
+                rule.max_per_day.unwrap_or(30),
+                rule.enabled.unwrap_or(true) as i64,
+                Utc::now(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_user_rule(&self, id: i64, rule: &NewUserRule) -> SqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE user_rules SET name = ?1, path_glob = ?2, min_size_bytes = ?3, min_age_days = ?4,
+                mime_filter = ?5, max_per_day = ?6, enabled = ?7
+             WHERE id = ?8",
+            params![
+                rule.name,
+                rule.path_glob,
+                rule.min_size_bytes,
+                rule.min_age_days,
+                rule.mime_filter,
+                rule.max_per_day.unwrap_or(30),
+                rule.enabled.unwrap_or(true) as i64,
+                id,
+            ],
+        )
+    }
+
+    pub fn get_user_rule_by_id(&self, id: i64) -> SqliteResult<Option<UserRule>> {
+        self.conn
+            .query_row("SELECT * FROM user_rules WHERE id = ?1", [id], Self::map_row_to_user_rule)
+            .optional()
+    }
+
+    pub fn list_user_rules(&self) -> SqliteResult<Vec<UserRule>> {
+        let mut stmt = self.conn.prepare("SELECT * FROM user_rules ORDER BY created_at ASC")?;
+        let rows = stmt.query_map([], Self::map_row_to_user_rule)?;
+        let mut rules = Vec::new();
+        for row in rows {
+            rules.push(row?);
+        }
+        Ok(rules)
+    }
+
+    pub fn delete_user_rule(&self, id: i64) -> SqliteResult<usize> {
+        self.conn.execute("DELETE FROM user_rules WHERE id = ?1", [id])
+    }
+
+    /// Active `(id, path)` pairs, optionally limited to one root.
+    pub fn list_active_paths(&self, root: Option<&str>) -> SqliteResult<Vec<(i64, String)>> {
+        let pattern = root.map(Self::root_like_pattern).unwrap_or_else(|| "%".to_string());
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, path FROM files WHERE path LIKE ?1 AND is_deleted = 0")?;
+        let rows = stmt.query_map([pattern], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    pub fn mark_files_missing(&self, ids: &[i64]) -> SqliteResult<()> {
+        for id in ids {
+            self.conn.execute("UPDATE files SET is_deleted = 1, is_staged = 0, cooloff_until = NULL WHERE id = ?1", params![id])?;
+            self.conn.execute("DELETE FROM staged_files WHERE file_id = ?1", params![id])?;
+        }
+        Ok(())
+    }
+
+    // File ID lookup methods
+    pub fn get_file_id_by_path(&self, path: &str) -> SqliteResult<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT id FROM files WHERE path_key = ?1",
+                [Self::path_key(path)],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+
+    // Action-related queries
+    pub fn get_actions_by_batch_id(&self, batch_id: &str) -> SqliteResult<Vec<Action>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_id, action, batch_id, src_path, dst_path, origin, note, created_at FROM actions WHERE batch_id = ?1 ORDER BY created_at ASC"
+        )?;
+        let rows = stmt.query_map([batch_id], |row| {
+            let action = row
+                .get::<_, String>(2)?
+                .parse()
+                .unwrap_or(crate::models::ActionType::Archive);
+            Ok(Action {
+                id: Some(row.get(0)?),
+                file_id: row.get(1)?,
+                action,
+                batch_id: row.get(3)?,
+                src_path: row.get(4)?,
+                dst_path: row.get(5)?,
+                origin: row.get(6)?,
+                note: row.get(7)?,
                 created_at: row.get(8)?,
             })
         })?;
@@ -535,19 +2622,38 @@ impl Database {
         Ok(actions)
     }
 
+    /// The batch of the newest archive or delete. Restores are logged under
+    /// the batch they undo, so they never make it the latest.
     pub fn get_latest_batch_id(&self) -> SqliteResult<Option<String>> {
         self.conn
             .query_row(
-                "SELECT batch_id FROM actions ORDER BY created_at DESC LIMIT 1",
+                "SELECT batch_id FROM actions WHERE batch_id != '' AND action != 'restore'
+                 ORDER BY created_at DESC LIMIT 1",
                 [],
                 |row| row.get(0),
             )
             .optional()
     }
 
+    /// Archive and delete batches, newest first. Entries already restored on
+    /// their own, and archive entries whose file was deleted afterwards
+    /// (emptied or removed by retention), no longer count.
     pub fn get_undoable_batches(&self) -> SqliteResult<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT DISTINCT batch_id FROM actions WHERE action IN ('archive', 'delete') ORDER BY created_at DESC"
+            "SELECT a.batch_id FROM actions a
+             WHERE a.action IN ('archive', 'delete')
+             AND NOT EXISTS (
+                 SELECT 1 FROM actions r
+                 WHERE r.batch_id = a.batch_id AND r.file_id = a.file_id
+                 AND r.action = 'restore'
+             )
+             AND NOT (a.action = 'archive' AND EXISTS (
+                 SELECT 1 FROM actions later
+                 WHERE later.file_id = a.file_id AND later.action = 'delete'
+                 AND later.created_at > a.created_at
+             ))
+             GROUP BY a.batch_id
+             ORDER BY MAX(a.created_at) DESC",
         )?;
         let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
         let mut batches = Vec::new();
@@ -601,6 +2707,36 @@ impl Database {
         Ok(actions)
     }
 
+    /// `(deleted_at, file_id, size_bytes)` for every delete action since
+    /// `since`, oldest first.
+    pub fn deletes_with_sizes_since(
+        &self,
+        since: &DateTime<Utc>,
+    ) -> SqliteResult<Vec<(DateTime<Utc>, i64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.created_at, a.file_id, f.size_bytes
+             FROM actions a
+             JOIN files f ON f.id = a.file_id
+             WHERE a.action = 'delete' AND a.created_at >= ?1
+             ORDER BY a.created_at ASC",
+        )?;
+        let rows = stmt.query_map(params![since.to_rfc3339()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        let mut deletes = Vec::new();
+        for row in rows {
+            let (created_at, file_id, size_bytes) = row?;
+            if let Ok(at) = DateTime::parse_from_rfc3339(&created_at) {
+                deletes.push((at.with_timezone(&Utc), file_id, size_bytes.max(0) as u64));
+            }
+        }
+        Ok(deletes)
+    }
+
     // Staged-in-window queries (current staged state only)
     pub fn list_current_staged_files_in_period(
         &self,
@@ -611,7 +2747,7 @@ impl Database {
             "SELECT f.id, f.path, f.parent_dir, f.mime, f.size_bytes, f.created_at, f.modified_at, f.accessed_at, f.last_opened_at, f.partial_sha1, f.sha1, f.first_seen_at, f.last_seen_at, f.is_deleted \
              FROM staged_files s \
              JOIN files f ON f.id = s.file_id \
-             WHERE s.status = 'staged' AND s.staged_at BETWEEN ?1 AND ?2"
+             WHERE s.status IN ('staged', 'expired') AND s.staged_at BETWEEN ?1 AND ?2"
         )?;
         let rows = stmt.query_map([start_date, end_date], Self::map_row_to_file)?;
         let mut files = Vec::new();
@@ -619,55 +2755,79 @@ impl Database {
         Ok(files)
     }
 
+    /// Records a staging row for every entry and flags its file as staged,
+    /// all in one transaction.
     pub fn stage_files(&self, entries: &[NewStagedFile]) -> SqliteResult<()> {
         if entries.is_empty() {
             return Ok(());
         }
 
-        let mut insert_stmt = self.conn.prepare(
-            "INSERT INTO staged_files (file_id, staged_at, expires_at, batch_id, status, note)\n             VALUES (?1, ?2, ?3, ?4, ?5, ?6)\n             ON CONFLICT(file_id) DO UPDATE SET\n                staged_at = excluded.staged_at,\n                expires_at = excluded.expires_at,\n                batch_id = excluded.batch_id,\n                status = excluded.status,\n                note = excluded.note"
-        )?;
-        let mut update_stmt = self.conn.prepare("UPDATE files SET is_staged = 1, cooloff_until = ?2 WHERE id = ?1")?;
-
-        for entry in entries {
-            let staged_at = entry.staged_at.to_rfc3339();
-            let expires_at = entry.expires_at.map(|dt| dt.to_rfc3339());
-            insert_stmt.execute(params![
-                entry.file_id,
-                &staged_at,
-                expires_at.as_deref(),
-                entry.batch_id.as_deref().unwrap_or(""),
-                entry.status.as_str(),
-                entry.note.as_deref().unwrap_or(""),
-            ])?;
-            update_stmt.execute(params![entry.file_id, expires_at.as_deref()])?;
-        }
+        self.transaction(|| {
+            let mut insert_stmt = self.conn.prepare(
+                "INSERT INTO staged_files (file_id, staged_at, expires_at, batch_id, status, note)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(file_id) DO UPDATE SET
+                    staged_at = excluded.staged_at,
+                    expires_at = excluded.expires_at,
+                    batch_id = excluded.batch_id,
+                    status = excluded.status,
+                    note = excluded.note",
+            )?;
+            let mut update_stmt = self
+                .conn
+                .prepare("UPDATE files SET is_staged = 1, cooloff_until = ?2 WHERE id = ?1")?;
 
-        Ok(())
+            for entry in entries {
+                let staged_at = entry.staged_at.to_rfc3339();
+                let expires_at = entry.expires_at.map(|dt| dt.to_rfc3339());
+                insert_stmt.execute(params![
+                    entry.file_id,
+                    &staged_at,
+                    expires_at.as_deref(),
+                    entry.batch_id.as_deref().unwrap_or(""),
+                    entry.status.as_str(),
+                    entry.note.as_deref().unwrap_or(""),
+                ])?;
+                update_stmt.execute(params![entry.file_id, expires_at.as_deref()])?;
+            }
+            Ok(())
+        })
     }
 
     pub fn update_staged_status(&self, file_ids: &[i64], status: &str) -> SqliteResult<()> {
         if file_ids.is_empty() {
             return Ok(());
         }
-        let mut stmt = self.conn.prepare("UPDATE staged_files SET status = ?1 WHERE file_id = ?2")?;
-        for file_id in file_ids {
-            stmt.execute(params![status, file_id])?;
-        }
-        Ok(())
+        self.transaction(|| {
+            let mut stmt = self
+                .conn
+                .prepare("UPDATE staged_files SET status = ?1 WHERE file_id = ?2")?;
+            for file_id in file_ids {
+                stmt.execute(params![status, file_id])?;
+            }
+            Ok(())
+        })
     }
 
+    /// Clears the staged flag of `file_ids` and drops their staging rows in
+    /// one transaction, so no file is left flagged without a row.
     pub fn mark_files_unstaged(&self, file_ids: &[i64]) -> SqliteResult<()> {
         if file_ids.is_empty() {
             return Ok(());
         }
-        let mut reset_stmt = self.conn.prepare("UPDATE files SET is_staged = 0, cooloff_until = NULL WHERE id = ?1")?;
-        let mut delete_stmt = self.conn.prepare("DELETE FROM staged_files WHERE file_id = ?1")?;
-        for file_id in file_ids {
-            reset_stmt.execute(params![file_id])?;
-            delete_stmt.execute(params![file_id])?;
-        }
-        Ok(())
+        self.transaction(|| {
+            let mut reset_stmt = self
+                .conn
+                .prepare("UPDATE files SET is_staged = 0, cooloff_until = NULL WHERE id = ?1")?;
+            let mut delete_stmt = self
+                .conn
+                .prepare("DELETE FROM staged_files WHERE file_id = ?1")?;
+            for file_id in file_ids {
+                reset_stmt.execute(params![file_id])?;
+                delete_stmt.execute(params![file_id])?;
+            }
+            Ok(())
+        })
     }
 
     pub fn list_staged_with_files(&self, statuses: Option<&[String]>) -> SqliteResult<Vec<(StagedFileRecord, File)>> {
@@ -690,17 +2850,155 @@ impl Database {
         Ok(results)
     }
 
+    /// Files still waiting out their cooloff that were staged at or before
+    /// `staged_before`, for the Review screen. With `expiring_by` only those
+    /// whose cooloff ends by then are kept. Each comes with the archive
+    /// destination recorded by its batch; soonest expiry first, files that
+    /// never expire last.
+    pub fn review_staged(
+        &self,
+        staged_before: &DateTime<Utc>,
+        expiring_by: Option<&DateTime<Utc>>,
+    ) -> SqliteResult<Vec<(StagedFileRecord, File, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.file_id, s.staged_at, s.expires_at, s.batch_id, s.status, s.note,
+                    (SELECT a.dst_path FROM actions a
+                     WHERE a.file_id = s.file_id AND a.action = 'archive'
+                       AND a.batch_id = s.batch_id AND a.dst_path != ''
+                     ORDER BY a.created_at DESC LIMIT 1) AS archive_path
+             FROM staged_files s JOIN files f ON f.id = s.file_id
+             WHERE s.status = 'staged' AND s.staged_at <= ?1
+               AND (?2 IS NULL OR (s.expires_at IS NOT NULL AND s.expires_at != ''
+                                   AND s.expires_at <= ?2))
+             ORDER BY (s.expires_at IS NULL OR s.expires_at = ''), s.expires_at, s.staged_at",
+        )?;
+        let params = params![staged_before.to_rfc3339(), expiring_by.map(|at| at.to_rfc3339())];
+        let rows = stmt.query_map(params, |row| {
+            Ok((Self::map_row_to_staged(row)?, row.get::<_, Option<String>>("archive_path")?))
+        })?;
+        let mut results = Vec::new();
+        for row in rows {
+            let (record, archive_path) = row?;
+            if let Some(file) = self.get_file_by_id(record.file_id)? {
+                results.push((record, file, archive_path));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Staged entries with the given status whose cooloff ended at or before `now`.
+    pub fn list_expired_staged(&self, status: &str, now: &DateTime<Utc>) -> SqliteResult<Vec<(StagedFileRecord, File)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_id, staged_at, expires_at, batch_id, status, note FROM staged_files \
+             WHERE status = ?1 AND expires_at IS NOT NULL AND expires_at != '' AND expires_at <= ?2"
+        )?;
+        let rows = stmt.query_map(params![status, now.to_rfc3339()], Self::map_row_to_staged)?;
+        let mut results = Vec::new();
+        for row in rows {
+            let record = row?;
+            if let Some(file) = self.get_file_by_id(record.file_id)? {
+                results.push((record, file));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Staged batches with files expiring in `(now, cutoff]`, as
+    /// (batch_id, file count, total bytes).
+    pub fn staged_batches_expiring(&self, now: &DateTime<Utc>, cutoff: &DateTime<Utc>) -> SqliteResult<Vec<(String, usize, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.batch_id, COUNT(*), COALESCE(SUM(f.size_bytes), 0) FROM staged_files s \
+             JOIN files f ON f.id = s.file_id \
+             WHERE s.status = 'staged' AND s.batch_id IS NOT NULL \
+             AND s.expires_at IS NOT NULL AND s.expires_at != '' AND s.expires_at > ?1 AND s.expires_at <= ?2 \
+             GROUP BY s.batch_id ORDER BY MIN(s.expires_at) ASC",
+        )?;
+        let rows = stmt.query_map(params![now.to_rfc3339(), cutoff.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize, row.get::<_, i64>(2)?.max(0) as u64))
+        })?;
+        rows.collect()
+    }
+
     pub fn update_file_location(&self, file_id: i64, new_path: &str) -> SqliteResult<()> {
         let parent = Path::new(new_path)
             .parent()
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|| new_path.to_string());
-        self.conn.execute("UPDATE files SET path = ?1, parent_dir = ?2 WHERE id = ?3", params![new_path, parent, file_id])?;
+        self.conn.execute(
+            "UPDATE files SET path = ?1, parent_dir = ?2, path_key = ?3 WHERE id = ?4",
+            params![new_path, parent, Self::path_key(new_path), file_id],
+        )?;
+        Ok(())
+    }
+
+    /// Flags an action whose copy was checksum-verified before the source
+    /// was removed.
+    pub fn mark_action_verified(&self, action_id: i64) -> SqliteResult<()> {
+        self.conn.execute("UPDATE actions SET verified = 1 WHERE id = ?1", params![action_id])?;
+        Ok(())
+    }
+
+    /// Repoints archived files and their actions after the archive moved.
+    /// `old_path` is a file or a compressed batch; rows for its zip members
+    /// are rewritten by prefix. Returns the number of rows changed.
+    pub fn relocate_archived_path(&self, old_path: &str, new_path: &str) -> SqliteResult<usize> {
+        // Matched by exact prefix: LIKE would read `_` in batch names as a
+        // wildcard and ignore case, catching sibling entries.
+        let members = Self::descendant_prefix(old_path);
+        let parent = Path::new(new_path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| new_path.to_string());
+        self.transaction(|| {
+            let mut updated = self.conn.execute(
+                "UPDATE actions SET dst_path = ?2 || substr(dst_path, length(?1) + 1)
+                 WHERE dst_path = ?1 OR substr(dst_path, 1, length(?3)) = ?3",
+                params![old_path, new_path, members],
+            )?;
+            // Rewritten row by row so each file keeps a `path_key` matching
+            // its new path; a stale key would let a new file at the old path
+            // upsert over the archived row.
+            let moved: Vec<(i64, String, String)> = {
+                let mut stmt = self.conn.prepare(
+                    "SELECT id, path, parent_dir FROM files
+                     WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+                )?;
+                let rows = stmt.query_map(params![old_path, members], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?;
+                rows.collect::<SqliteResult<_>>()?
+            };
+            for (id, path, parent_dir) in moved {
+                let (path, parent_dir) = if path == old_path {
+                    (new_path.to_string(), parent.clone())
+                } else {
+                    (
+                        format!("{new_path}{}", &path[old_path.len()..]),
+                        format!("{new_path}{}", parent_dir.get(old_path.len()..).unwrap_or_default()),
+                    )
+                };
+                updated += self.conn.execute(
+                    "UPDATE files SET path = ?1, parent_dir = ?2, path_key = ?3 WHERE id = ?4",
+                    params![path, parent_dir, Self::path_key(&path), id],
+                )?;
+            }
+            Ok(updated)
+        })
+    }
+
+    pub fn mark_file_restored(&self, file_id: i64) -> SqliteResult<()> {
+        self.conn.execute(
+            "UPDATE files SET is_deleted = 0, last_seen_at = ?1 WHERE id = ?2",
+            params![Utc::now(), file_id],
+        )?;
         Ok(())
     }
 
     pub fn duplicate_groups(&self, limit: Option<usize>) -> SqliteResult<Vec<(String, Vec<File>)>> {
-        let base_sql = "SELECT sha1 FROM files WHERE sha1 IS NOT NULL AND sha1 != '' AND is_deleted = 0 GROUP BY sha1 HAVING COUNT(*) > 1 ORDER BY COUNT(*) DESC";
+        // Hard links share an inode, and one file seen under two spellings
+        // shares a path key, so each only counts once towards a group.
+        let base_sql = "SELECT sha1 FROM files WHERE sha1 IS NOT NULL AND sha1 != '' AND is_deleted = 0 GROUP BY sha1 \
+             HAVING COUNT(DISTINCT COALESCE(inode_key, path_key, 'id:' || id)) > 1 ORDER BY COUNT(*) DESC";
         let hashes = if let Some(limit) = limit {
             let mut stmt = self.conn.prepare(&format!("{base_sql} LIMIT ?"))?;
             let rows = stmt.query_map([limit as i64], |row| row.get::<_, String>(0))?;
@@ -720,22 +3018,69 @@ impl Database {
         };
 
         let mut results = Vec::with_capacity(hashes.len());
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM files WHERE sha1 = ?1 AND sha1 IS NOT NULL AND sha1 != '' AND is_deleted = 0 ORDER BY size_bytes DESC")?;
         for hash in hashes {
-            let rows = stmt.query_map([hash.as_str()], |row| Self::map_row_to_file(row))?;
-            let mut files = Vec::new();
-            for row in rows {
-                files.push(row?);
-            }
+            let files = self.files_with_sha1(&hash)?;
             results.push((hash, files));
         }
 
         Ok(results)
     }
 
-    pub fn mark_missing_for_root(&self, root: &str, seen_paths: &HashSet<String>) -> SqliteResult<()> {
+    /// Active files whose full hash is `sha1`, largest first. Hard links to
+    /// the same inode, and rows sharing a path key, collapse to the lowest
+    /// file id.
+    pub fn files_with_sha1(&self, sha1: &str) -> SqliteResult<Vec<File>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM files WHERE id IN (
+                SELECT MIN(id) FROM files WHERE sha1 = ?1 AND sha1 != '' AND is_deleted = 0
+                GROUP BY COALESCE(inode_key, path_key, 'id:' || id)
+             ) ORDER BY size_bytes DESC",
+        )?;
+        let rows = stmt.query_map([sha1], Self::map_row_to_file)?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+        Ok(files)
+    }
+
+    /// Marks files of root `root_id` that the scan did not see as missing,
+    /// returning their ids. Unlike
+    /// [`mark_missing_for_root`](Self::mark_missing_for_root) this does not
+    /// depend on how the root path is spelled. Staged and archived files keep
+    /// their root but live in the archive, so they are left alone.
+    pub fn mark_missing_for_root_id(
+        &self,
+        root_id: i64,
+        seen_paths: &HashSet<String>,
+    ) -> SqliteResult<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path FROM files
+             WHERE root_id = ?1 AND is_deleted = 0 AND is_staged = 0
+               AND NOT EXISTS (
+                   SELECT 1 FROM actions a
+                   WHERE a.file_id = files.id AND a.action = 'archive' AND a.dst_path = files.path
+               )",
+        )?;
+        let rows = stmt.query_map([root_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut missing = Vec::new();
+        for row in rows {
+            let (file_id, path) = row?;
+            if !seen_paths.contains(&path) {
+                missing.push(file_id);
+            }
+        }
+        self.mark_files_missing(&missing)?;
+        Ok(missing)
+    }
+
+    pub fn mark_missing_for_root(
+        &self,
+        root: &str,
+        seen_paths: &HashSet<String>,
+    ) -> SqliteResult<Vec<i64>> {
         let pattern = Self::root_like_pattern(root);
         let mut stmt = self.conn.prepare("SELECT id, path FROM files WHERE path LIKE ?1 AND is_deleted = 0")?;
         let rows = stmt.query_map([pattern], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
@@ -746,21 +3091,135 @@ impl Database {
                 missing.push(file_id);
             }
         }
-        for id in missing {
-            self.conn.execute("UPDATE files SET is_deleted = 1, is_staged = 0, cooloff_until = NULL WHERE id = ?1", params![id])?;
-            self.conn.execute("DELETE FROM staged_files WHERE file_id = ?1", params![id])?;
+        self.mark_files_missing(&missing)?;
+        Ok(missing)
+    }
+
+    /// Marks the file at `path`, or every file under it when it was a
+    /// directory, as missing. Used when the watcher sees a single path vanish.
+    pub fn mark_path_missing(&self, path: &str) -> SqliteResult<usize> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM files WHERE is_deleted = 0 AND (path = ?1 OR path LIKE ?2)",
+        )?;
+        let rows = stmt.query_map(params![path, Self::root_like_pattern(path)], |row| row.get(0))?;
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row?);
+        }
+        self.mark_files_missing(&ids)?;
+        Ok(ids.len())
+    }
+
+    /// Active files sharing a size and partial hash computed with `algo`,
+    /// with whether each already has a full hash.
+    pub fn active_files_with_partial_hash(
+        &self,
+        size_bytes: u64,
+        partial_sha1: &str,
+        algo: HashAlgo,
+    ) -> SqliteResult<Vec<(i64, String, bool)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, sha1 IS NOT NULL FROM files
+             WHERE is_deleted = 0 AND size_bytes = ?1 AND partial_sha1 = ?2 AND hash_algo = ?3",
+        )?;
+        let params = params![size_bytes as i64, partial_sha1, algo.to_string()];
+        let rows = stmt.query_map(params, |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+        Ok(files)
+    }
+
+    /// `(path LIKE ?n OR ...)` over `roots`, pushing their patterns onto
+    /// `values`.
+    fn roots_clause(roots: &[String], values: &mut Vec<rusqlite::types::Value>) -> String {
+        let mut clauses = Vec::new();
+        for root in roots {
+            values.push(Self::root_like_pattern(root).into());
+            clauses.push(format!("path LIKE ?{}", values.len()));
+        }
+        format!("({})", clauses.join(" OR "))
+    }
+
+    /// `video/*` and `video/` match the whole family; anything else must
+    /// match exactly (LIKE is case-insensitive for ASCII).
+    fn mime_like_pattern(mime: &str) -> String {
+        match mime.strip_suffix('*') {
+            Some(prefix) => format!("{prefix}%"),
+            None if mime.ends_with('/') => format!("{mime}%"),
+            None => mime.to_string(),
         }
-        Ok(())
     }
 
     fn root_like_pattern(root: &str) -> String {
+        format!("{}%", Self::descendant_prefix(root))
+    }
+
+    /// `root` with a trailing separator, the prefix every path below it has.
+    fn descendant_prefix(root: &str) -> String {
         if root.ends_with('/') || root.ends_with('\\') {
-            format!("{root}%")
+            root.to_string()
         } else if root.contains('\\') {
-            format!("{root}\\%")
+            format!("{root}\\")
         } else {
-            format!("{root}/%")
+            format!("{root}/")
+        }
+    }
+
+    /// Active, unstaged images under `roots` with their perceptual hash.
+    pub fn images_with_phash_under_roots(&self, roots: &[String]) -> SqliteResult<Vec<(File, u64)>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let clauses = (1..=roots.len())
+            .map(|i| format!("path LIKE ?{i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!(
+            "SELECT * FROM files WHERE is_deleted = 0 AND is_staged = 0 AND phash IS NOT NULL AND ({clauses})
+             ORDER BY size_bytes DESC"
+        );
+        let patterns: Vec<String> = roots.iter().map(|r| Self::root_like_pattern(r)).collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(patterns.iter()), |row| {
+            Ok((Self::map_row_to_file(row)?, row.get::<_, String>("phash")?))
+        })?;
+        let mut images = Vec::new();
+        for row in rows {
+            let (file, phash) = row?;
+            if let Ok(hash) = u64::from_str_radix(&phash, 16) {
+                images.push((file, hash));
+            }
         }
+        Ok(images)
+    }
+
+    /// Ids of files with other hard links; removing one of them frees nothing.
+    pub fn hard_linked_file_ids(&self) -> SqliteResult<HashSet<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM files WHERE link_count > 1")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
+    }
+
+    /// Ids of files whose removal frees no local space: other hard links
+    /// still hold the data, or the data only lives in the cloud.
+    pub fn unreclaimable_file_ids(&self) -> SqliteResult<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM files WHERE link_count > 1 OR is_cloud_placeholder = 1")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
     }
 
     pub fn get_total_file_size(&self) -> SqliteResult<i64> {
@@ -772,6 +3231,626 @@ impl Database {
             )
     }
 
+    /// Largest active files under any of `roots`, biggest first. Staged files
+    /// are left out unless `include_staged` is set.
+    pub fn largest_files_under_roots(
+        &self,
+        roots: &[String],
+        min_size_bytes: i64,
+        include_staged: bool,
+        limit: usize,
+    ) -> SqliteResult<Vec<File>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let clauses = (3..roots.len() + 3)
+            .map(|i| format!("path LIKE ?{i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let staged_clause = if include_staged { "" } else { " AND is_staged = 0" };
+        let sql = format!(
+            "SELECT * FROM files WHERE is_deleted = 0{staged_clause} AND size_bytes >= ?1 AND ({clauses})
+             ORDER BY size_bytes DESC, path ASC LIMIT ?2"
+        );
+        let mut values: Vec<rusqlite::types::Value> = vec![min_size_bytes.into(), (limit as i64).into()];
+        values.extend(roots.iter().map(|r| Self::root_like_pattern(r).into()));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), Self::map_row_to_file)?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+        Ok(files)
+    }
+
+    /// Largest active, unstaged files first seen since `since`.
+    pub fn largest_new_files(
+        &self,
+        since: &DateTime<Utc>,
+        limit: usize,
+    ) -> SqliteResult<Vec<File>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM files WHERE is_deleted = 0 AND is_staged = 0
+             AND julianday(first_seen_at) >= julianday(?1)
+             ORDER BY size_bytes DESC, path ASC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![since.to_rfc3339(), limit as i64], |row| {
+            Self::map_row_to_file(row)
+        })?;
+        rows.collect()
+    }
+
+    /// Unstaged active files under `roots` whose last known use (opened,
+    /// accessed, modified, then created) is before `cutoff`. `julianday` is
+    /// used so timestamps written in either SQLite or RFC 3339 form compare.
+    pub fn stale_files_under_roots(&self, roots: &[String], cutoff: &DateTime<Utc>) -> SqliteResult<Vec<File>> {
+        if roots.is_empty() {
+            return Ok(Vec::new());
+        }
+        let clauses = (2..roots.len() + 2)
+            .map(|i| format!("path LIKE ?{i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!(
+            "SELECT * FROM files WHERE is_deleted = 0 AND is_staged = 0 AND ({clauses})
+             AND julianday(COALESCE(last_opened_at, accessed_at, modified_at, created_at)) < julianday(?1)
+             ORDER BY parent_dir ASC, size_bytes DESC"
+        );
+        let mut values = vec![cutoff.to_rfc3339()];
+        values.extend(roots.iter().map(|r| Self::root_like_pattern(r)));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), Self::map_row_to_file)?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+        Ok(files)
+    }
+
+    /// Active files first indexed at or after `since`, staged or not.
+    pub fn files_first_seen_since(&self, since: &DateTime<Utc>) -> SqliteResult<Vec<File>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM files WHERE is_deleted = 0
+             AND julianday(first_seen_at) >= julianday(?1)",
+        )?;
+        let rows = stmt.query_map([since.to_rfc3339()], Self::map_row_to_file)?;
+        rows.collect()
+    }
+
+    /// Folds `arrived_id`, a row first indexed by this scan, into
+    /// `vanished_id`, the row of the file before the user moved it. The old
+    /// row takes the new path and metadata, keeps its id, history and full
+    /// hash, and a `move` action records the two paths.
+    pub fn merge_moved_file(&self, vanished_id: i64, arrived_id: i64) -> SqliteResult<()> {
+        const MOVED_COLUMNS: &[&str] = &[
+            "path",
+            "parent_dir",
+            "path_key",
+            "root_id",
+            "mime",
+            "modified_at",
+            "accessed_at",
+            "partial_sha1",
+            "inode_key",
+            "link_count",
+            "phash",
+            "is_cloud_placeholder",
+            "last_seen_at",
+        ];
+        self.transaction(|| {
+            let src_path: String = self.conn.query_row(
+                "SELECT path FROM files WHERE id = ?1",
+                [vanished_id],
+                |row| row.get(0),
+            )?;
+            let (values, sha1) = self.conn.query_row(
+                &format!("SELECT {}, sha1 FROM files WHERE id = ?1", MOVED_COLUMNS.join(", ")),
+                [arrived_id],
+                |row| {
+                    let values = (0..MOVED_COLUMNS.len())
+                        .map(|i| row.get::<_, rusqlite::types::Value>(i))
+                        .collect::<SqliteResult<Vec<_>>>()?;
+                    Ok((values, row.get::<_, Option<String>>(MOVED_COLUMNS.len())?))
+                },
+            )?;
+            // The new row holds the path key until it is gone.
+            self.conn.execute("DELETE FROM files WHERE id = ?1", [arrived_id])?;
+            let assignments = MOVED_COLUMNS
+                .iter()
+                .enumerate()
+                .map(|(i, column)| format!("{column} = ?{}", i + 3))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut params: Vec<rusqlite::types::Value> = vec![vanished_id.into(), sha1.into()];
+            params.extend(values);
+            self.conn.execute(
+                &format!(
+                    "UPDATE files SET {assignments}, sha1 = COALESCE(?2, sha1),
+                     is_deleted = 0, is_offline = 0 WHERE id = ?1"
+                ),
+                rusqlite::params_from_iter(params),
+            )?;
+            let dst_path: String = self.conn.query_row(
+                "SELECT path FROM files WHERE id = ?1",
+                [vanished_id],
+                |row| row.get(0),
+            )?;
+            self.insert_action(&NewAction {
+                file_id: vanished_id,
+                action: crate::models::ActionType::Move,
+                batch_id: None,
+                src_path: Some(src_path),
+                dst_path: Some(dst_path),
+                origin: Some("scanner".to_string()),
+                note: None,
+            })?;
+            Ok(())
+        })
+    }
+
+    /// Ids of files indexed for the first time after `since`.
+    pub fn file_ids_first_seen_after(&self, since: &DateTime<Utc>) -> SqliteResult<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM files WHERE julianday(first_seen_at) > julianday(?1)")?;
+        let rows = stmt.query_map([since.to_rfc3339()], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// WHERE clause shared by file search and selections: name/path match,
+    /// roots, size and mime filters, staged and deleted state.
+    fn search_clause(
+        query: &str,
+        filters: &FileSearchFilters,
+        roots: &[String],
+        values: &mut Vec<rusqlite::types::Value>,
+    ) -> String {
+        let mut clauses: Vec<String> = Vec::new();
+        let query = query.trim();
+        if query.chars().count() >= 3 {
+            values.push(format!("\"{}\"", query.replace('"', "\"\"")).into());
+            clauses.push(format!(
+                "id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?{})",
+                values.len()
+            ));
+        } else if !query.is_empty() {
+            values.push(format!("%{query}%").into());
+            clauses.push(format!("path LIKE ?{}", values.len()));
+        }
+
+        clauses.push(Self::roots_clause(roots, values));
+
+        if let Some(min) = filters.min_size_bytes {
+            values.push(min.into());
+            clauses.push(format!("size_bytes >= ?{}", values.len()));
+        }
+        if let Some(max) = filters.max_size_bytes {
+            values.push(max.into());
+            clauses.push(format!("size_bytes <= ?{}", values.len()));
+        }
+        if let Some(mime) = filters.mime.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            values.push(Self::mime_like_pattern(mime).into());
+            clauses.push(format!("mime LIKE ?{}", values.len()));
+        }
+        if let Some(staged) = filters.staged {
+            clauses.push(format!("is_staged = {}", staged as i64));
+        }
+        if !filters.include_deleted.unwrap_or(false) {
+            clauses.push("is_deleted = 0".to_string());
+        }
+        if let Some(days) = filters.min_age_days {
+            values.push((Utc::now() - chrono::Duration::days(days)).to_rfc3339().into());
+            clauses.push(format!(
+                "julianday(COALESCE(last_opened_at, accessed_at, modified_at, created_at)) \
+                 <= julianday(?{})",
+                values.len()
+            ));
+        }
+        clauses.join(" AND ")
+    }
+
+    /// Searches file names and paths, returning the total match count and one
+    /// page of results. Queries of three or more characters go through the
+    /// trigram index; shorter ones fall back to a `LIKE` scan. With `after`
+    /// the page starts past that cursor instead of at `offset`, and files
+    /// first seen after its snapshot are left out of the count and the page.
+    pub fn search_files(
+        &self,
+        query: &str,
+        filters: &FileSearchFilters,
+        roots: &[String],
+        limit: usize,
+        offset: usize,
+        after: Option<&PageCursor>,
+    ) -> SqliteResult<(usize, Vec<File>)> {
+        if roots.is_empty() {
+            return Ok((0, Vec::new()));
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let mut clauses = vec![Self::search_clause(query, filters, roots, &mut values)];
+        if let Some(cursor) = after {
+            values.push(cursor.snapshot().to_rfc3339().into());
+            clauses.push(format!("julianday(first_seen_at) <= julianday(?{})", values.len()));
+        }
+
+        let where_clause = clauses.join(" AND ");
+        let total: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM files WHERE {where_clause}"),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut where_clause = where_clause;
+        let offset = match after {
+            Some(cursor) => {
+                values.push((cursor.key.unwrap_or(0.0) as i64).into());
+                values.push(cursor.text.clone().unwrap_or_default().into());
+                where_clause.push_str(&format!(
+                    " AND (size_bytes < ?{0} OR (size_bytes = ?{0} AND path > ?{1}))",
+                    values.len() - 1,
+                    values.len()
+                ));
+                0
+            }
+            None => offset,
+        };
+
+        values.push((limit as i64).into());
+        values.push((offset as i64).into());
+        let sql = format!(
+            "SELECT * FROM files WHERE {where_clause} ORDER BY size_bytes DESC, path ASC LIMIT ?{} OFFSET ?{}",
+            values.len() - 1,
+            values.len()
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), Self::map_row_to_file)?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+        Ok((total as usize, files))
+    }
+
+    /// How many files `search_files` would match, without loading any.
+    pub fn count_search_matches(
+        &self,
+        query: &str,
+        filters: &FileSearchFilters,
+        roots: &[String],
+    ) -> SqliteResult<usize> {
+        if roots.is_empty() {
+            return Ok(0);
+        }
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let where_clause = Self::search_clause(query, filters, roots, &mut values);
+        self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM files WHERE {where_clause}"),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get::<_, i64>(0).map(|n| n as usize),
+        )
+    }
+
+    fn map_row_to_saved_view(row: &Row<'_>) -> SqliteResult<SavedView> {
+        let filters: String = row.get("filters")?;
+        Ok(SavedView {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            query: row.get("query")?,
+            filters: serde_json::from_str(&filters).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+            notify_threshold: row.get("notify_threshold")?,
+            last_count: row.get("last_count")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    pub fn insert_saved_view(&self, view: &NewSavedView) -> SqliteResult<i64> {
+        let filters = serde_json::to_string(&view.filters)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "INSERT INTO saved_views (name, query, filters, notify_threshold, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![view.name, view.query, filters, view.notify_threshold, Utc::now()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Replaces a view's definition. The recorded match count is cleared
+    /// since it described the old filters.
+    pub fn update_saved_view(&self, id: i64, view: &NewSavedView) -> SqliteResult<usize> {
+        let filters = serde_json::to_string(&view.filters)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "UPDATE saved_views SET name = ?1, query = ?2, filters = ?3, notify_threshold = ?4,
+                last_count = NULL
+             WHERE id = ?5",
+            params![view.name, view.query, filters, view.notify_threshold, id],
+        )
+    }
+
+    pub fn get_saved_view_by_id(&self, id: i64) -> SqliteResult<Option<SavedView>> {
+        self.conn
+            .query_row("SELECT * FROM saved_views WHERE id = ?1", [id], |row| {
+                Self::map_row_to_saved_view(row)
+            })
+            .optional()
+    }
+
+    pub fn list_saved_views(&self) -> SqliteResult<Vec<SavedView>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM saved_views ORDER BY name COLLATE NOCASE ASC, id ASC")?;
+        let rows = stmt.query_map([], Self::map_row_to_saved_view)?;
+        rows.collect()
+    }
+
+    pub fn delete_saved_view(&self, id: i64) -> SqliteResult<usize> {
+        self.conn.execute("DELETE FROM saved_views WHERE id = ?1", [id])
+    }
+
+    pub fn set_saved_view_count(&self, id: i64, count: i64) -> SqliteResult<()> {
+        self.conn.execute(
+            "UPDATE saved_views SET last_count = ?2 WHERE id = ?1",
+            params![id, count],
+        )?;
+        Ok(())
+    }
+
+    fn map_row_to_batch(row: &Row<'_>) -> SqliteResult<BatchRecord> {
+        Ok(BatchRecord {
+            batch_id: row.get("batch_id")?,
+            action_type: row.get("action_type")?,
+            origin: row.get("origin")?,
+            label: row.get("label")?,
+            file_count: row.get("file_count")?,
+            total_bytes: row.get("total_bytes")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    /// Records a finished batch. Recording the same batch again (a retried
+    /// delete, say) refreshes its counts but keeps an existing label.
+    pub fn record_batch(&self, batch: &NewBatch) -> SqliteResult<()> {
+        self.conn.execute(
+            "INSERT INTO batches
+                (batch_id, action_type, origin, label, file_count, total_bytes, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(batch_id) DO UPDATE SET
+                file_count = excluded.file_count,
+                total_bytes = excluded.total_bytes,
+                label = COALESCE(excluded.label, batches.label)",
+            params![
+                batch.batch_id,
+                batch.action_type.to_string(),
+                batch.origin,
+                batch.label,
+                batch.file_count,
+                batch.total_bytes,
+                Utc::now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Sets or clears a batch's label. Returns the number of rows touched.
+    pub fn update_batch_label(&self, batch_id: &str, label: Option<&str>) -> SqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE batches SET label = ?2 WHERE batch_id = ?1",
+            params![batch_id, label],
+        )
+    }
+
+    pub fn get_batch(&self, batch_id: &str) -> SqliteResult<Option<BatchRecord>> {
+        self.conn
+            .query_row("SELECT * FROM batches WHERE batch_id = ?1", [batch_id], |row| {
+                Self::map_row_to_batch(row)
+            })
+            .optional()
+    }
+
+    /// Batches newest first, narrowed by `filters`.
+    pub fn list_batches(&self, filters: &BatchFilters) -> SqliteResult<Vec<BatchRecord>> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        if let Some(action_type) = &filters.action_type {
+            conditions.push("action_type = ?");
+            values.push(action_type.clone().into());
+        }
+        if let Some(origin) = &filters.origin {
+            conditions.push("origin = ?");
+            values.push(origin.clone().into());
+        }
+        if let Some(label) = &filters.label {
+            conditions.push("instr(lower(label), lower(?)) > 0");
+            values.push(label.clone().into());
+        }
+        if let Some(since) = filters.since {
+            conditions.push("julianday(created_at) >= julianday(?)");
+            values.push(since.to_rfc3339().into());
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let limit = filters.limit.unwrap_or(100) as i64;
+        values.push(limit.into());
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM batches {where_clause}
+             ORDER BY julianday(created_at) DESC, batch_id DESC LIMIT ?"
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Self::map_row_to_batch(row)
+        })?;
+        rows.collect()
+    }
+
+    /// Drops every selection; they don't outlive the session.
+    pub fn clear_selections(&self) -> SqliteResult<()> {
+        self.conn
+            .execute_batch("DELETE FROM selection_files; DELETE FROM selections;")
+    }
+
+    pub fn record_feedback(
+        &self,
+        file_id: i64,
+        bucket: &str,
+        verdict: FeedbackVerdict,
+    ) -> SqliteResult<i64> {
+        self.conn.execute(
+            "INSERT INTO feedback (file_id, bucket, verdict, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![file_id, bucket, verdict.to_string(), Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Verdict totals per bucket.
+    pub fn feedback_by_bucket(&self) -> SqliteResult<HashMap<String, BucketFeedback>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bucket, verdict, COUNT(*) FROM feedback GROUP BY bucket, verdict",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, u32>(2)?))
+        })?;
+        let mut totals: HashMap<String, BucketFeedback> = HashMap::new();
+        for row in rows {
+            let (bucket, verdict, count) = row?;
+            let entry = totals.entry(bucket).or_default();
+            match verdict.parse() {
+                Ok(FeedbackVerdict::Kept) => entry.kept += count,
+                Ok(FeedbackVerdict::Deleted) => entry.deleted += count,
+                Ok(FeedbackVerdict::WrongSuggestion) => entry.wrong_suggestion += count,
+                Err(_) => {}
+            }
+        }
+        Ok(totals)
+    }
+
+    pub fn create_selection(&self, name: &str) -> SqliteResult<i64> {
+        self.conn.execute(
+            "INSERT INTO selections (name, created_at) VALUES (?1, ?2)",
+            params![name, Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn discard_selection(&self, selection_id: i64) -> SqliteResult<bool> {
+        self.conn.execute(
+            "DELETE FROM selection_files WHERE selection_id = ?1",
+            [selection_id],
+        )?;
+        let removed = self
+            .conn
+            .execute("DELETE FROM selections WHERE id = ?1", [selection_id])?;
+        Ok(removed > 0)
+    }
+
+    /// Adds the active files among `file_ids`; returns how many were new.
+    pub fn add_files_to_selection(
+        &self,
+        selection_id: i64,
+        file_ids: &[i64],
+    ) -> SqliteResult<usize> {
+        let mut stmt = self.conn.prepare(
+            "INSERT OR IGNORE INTO selection_files (selection_id, file_id)
+             SELECT ?1, id FROM files WHERE id = ?2 AND is_deleted = 0",
+        )?;
+        let mut added = 0;
+        for file_id in file_ids {
+            added += stmt.execute(params![selection_id, file_id])?;
+        }
+        Ok(added)
+    }
+
+    /// Adds every file `search_files` would match; returns how many were new.
+    pub fn add_search_matches_to_selection(
+        &self,
+        selection_id: i64,
+        query: &str,
+        filters: &FileSearchFilters,
+        roots: &[String],
+    ) -> SqliteResult<usize> {
+        if roots.is_empty() {
+            return Ok(0);
+        }
+        let mut values: Vec<rusqlite::types::Value> = vec![selection_id.into()];
+        let where_clause = Self::search_clause(query, filters, roots, &mut values);
+        self.conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO selection_files (selection_id, file_id)
+                 SELECT ?1, id FROM files WHERE {where_clause}"
+            ),
+            rusqlite::params_from_iter(values.iter()),
+        )
+    }
+
+    pub fn remove_files_from_selection(
+        &self,
+        selection_id: i64,
+        file_ids: &[i64],
+    ) -> SqliteResult<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("DELETE FROM selection_files WHERE selection_id = ?1 AND file_id = ?2")?;
+        let mut removed = 0;
+        for file_id in file_ids {
+            removed += stmt.execute(params![selection_id, file_id])?;
+        }
+        Ok(removed)
+    }
+
+    /// Forgets files that were staged or deleted since they were selected.
+    pub fn prune_selection(&self, selection_id: i64) -> SqliteResult<usize> {
+        self.conn.execute(
+            "DELETE FROM selection_files WHERE selection_id = ?1 AND file_id NOT IN (
+                SELECT id FROM files WHERE is_deleted = 0 AND is_staged = 0
+             )",
+            [selection_id],
+        )
+    }
+
+    pub fn selection_summary(&self, selection_id: i64) -> SqliteResult<Option<SelectionSummary>> {
+        self.conn
+            .query_row(
+                "SELECT s.id, s.name, s.created_at, COUNT(f.id), COALESCE(SUM(f.size_bytes), 0)
+                 FROM selections s
+                 LEFT JOIN selection_files sf ON sf.selection_id = s.id
+                 LEFT JOIN files f ON f.id = sf.file_id
+                 WHERE s.id = ?1
+                 GROUP BY s.id",
+                [selection_id],
+                |row| {
+                    Ok(SelectionSummary {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        created_at: row.get(2)?,
+                        file_count: row.get::<_, i64>(3)? as usize,
+                        total_bytes: row.get::<_, i64>(4)?.max(0) as u64,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Up to `limit` file ids from the selection, largest first.
+    pub fn selection_file_ids(&self, selection_id: i64, limit: usize) -> SqliteResult<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.id FROM selection_files sf JOIN files f ON f.id = sf.file_id
+             WHERE sf.selection_id = ?1 AND f.is_deleted = 0 AND f.is_staged = 0
+             ORDER BY f.size_bytes DESC, f.id
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![selection_id, limit as i64], |row| row.get(0))?;
+        rows.collect()
+    }
+
     pub fn get_candidate_files(&self, limit: i64) -> SqliteResult<Vec<File>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, path, parent_dir, mime, size_bytes, created_at, modified_at, accessed_at, last_opened_at, partial_sha1, sha1, first_seen_at, last_seen_at, is_deleted 
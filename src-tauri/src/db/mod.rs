@@ -1,4 +1,6 @@
 pub mod database;
 pub mod pool;
+pub mod recovery;
 pub use database::Database;
-pub use pool::{init_pool, DbPool};
+pub use pool::DbPool;
+pub use recovery::{DatabaseRecovery, RecoveryState, DATABASE_RECOVERED_EVENT};
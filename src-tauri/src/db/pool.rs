@@ -1,10 +1,16 @@
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use std::path::Path;
+use std::time::Duration;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 
+/// How long a statement waits on another connection's lock before SQLite
+/// reports the database as busy.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub fn init_pool(db_path: &Path) -> DbPool {
-    let manager = SqliteConnectionManager::file(db_path);
+    let manager =
+        SqliteConnectionManager::file(db_path).with_init(|conn| conn.busy_timeout(BUSY_TIMEOUT));
     Pool::new(manager).expect("failed to create sqlite pool")
 }
@@ -0,0 +1,115 @@
+use super::database::integrity_problems;
+use super::pool::{init_pool, DbPool};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Emitted when a damaged database was replaced and the index needs a
+/// rescan of the listed roots.
+pub const DATABASE_RECOVERED_EVENT: &str = "database://recovered";
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DatabaseRecovery {
+    /// Where the damaged file was kept.
+    pub corrupt_path: String,
+    pub problems: Vec<String>,
+    /// Watched roots read back from the damaged file and re-added.
+    pub roots: Vec<String>,
+    pub recovered_at: DateTime<Utc>,
+}
+
+/// Problems in the file at `db_path`; empty when it is healthy or does not
+/// exist yet. A file SQLite cannot read at all is one problem.
+pub fn check_file(db_path: &Path, full: bool) -> Vec<String> {
+    if !db_path.exists() {
+        return Vec::new();
+    }
+    // Read-write because the FTS5 part of the check writes; never creates.
+    Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .and_then(|conn| integrity_problems(&conn, full))
+        .unwrap_or_else(|err| vec![err.to_string()])
+}
+
+/// Name the damaged copy of `db_path` is kept under.
+pub fn corrupt_path_for(db_path: &Path, at: &DateTime<Utc>) -> PathBuf {
+    let stem = db_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "database".to_string());
+    db_path.with_file_name(format!("{stem}.corrupt-{}.db", at.format("%Y%m%d-%H%M%S")))
+}
+
+/// Moves a damaged database and its WAL and shared-memory files aside so a
+/// fresh one can take its place. Nothing is deleted.
+pub fn set_aside(db_path: &Path, at: &DateTime<Utc>) -> std::io::Result<PathBuf> {
+    let dest = corrupt_path_for(db_path, at);
+    fs::rename(db_path, &dest)?;
+    for suffix in ["-wal", "-shm"] {
+        let side = PathBuf::from(format!("{}{suffix}", db_path.display()));
+        if side.exists() {
+            let side_dest = PathBuf::from(format!("{}{suffix}", dest.display()));
+            if let Err(err) = fs::rename(&side, &side_dest) {
+                tracing::warn!("Failed to move {} aside: {}", side.display(), err);
+            }
+        }
+    }
+    Ok(dest)
+}
+
+/// Watched roots still readable from a damaged database, best effort.
+pub fn salvage_roots(corrupt: &Path) -> Vec<String> {
+    let read = || -> rusqlite::Result<Vec<String>> {
+        let conn = Connection::open_with_flags(corrupt, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare("SELECT path FROM watched_roots")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(rows.filter_map(Result::ok).collect())
+    };
+    read().unwrap_or_else(|err| {
+        tracing::warn!("Could not read watched roots from the damaged database: {}", err);
+        Vec::new()
+    })
+}
+
+/// Opens the pool for `db_path`, first replacing the file with a fresh one
+/// if it fails `quick_check`. The caller migrates as usual and re-adds
+/// `DatabaseRecovery::roots`.
+pub fn open_checked(db_path: &Path) -> (DbPool, Option<DatabaseRecovery>) {
+    let problems = check_file(db_path, false);
+    if problems.is_empty() {
+        return (init_pool(db_path), None);
+    }
+    tracing::error!(?problems, "database failed its integrity check");
+    let recovered_at = Utc::now();
+    match set_aside(db_path, &recovered_at) {
+        Ok(corrupt) => {
+            tracing::warn!(path = %corrupt.display(), "damaged database kept aside");
+            let recovery = DatabaseRecovery {
+                corrupt_path: corrupt.to_string_lossy().to_string(),
+                roots: salvage_roots(&corrupt),
+                problems,
+                recovered_at,
+            };
+            (init_pool(db_path), Some(recovery))
+        }
+        Err(err) => {
+            // Carry on with the damaged file; migrations report what fails.
+            tracing::error!("Failed to move the damaged database aside: {}", err);
+            (init_pool(db_path), None)
+        }
+    }
+}
+
+/// The recovery made this session, for a UI that loads after the event.
+#[derive(Default)]
+pub struct RecoveryState(std::sync::Mutex<Option<DatabaseRecovery>>);
+
+impl RecoveryState {
+    pub fn set(&self, recovery: DatabaseRecovery) {
+        *self.0.lock().expect("recovery state lock") = Some(recovery);
+    }
+
+    pub fn get(&self) -> Option<DatabaseRecovery> {
+        self.0.lock().expect("recovery state lock").clone()
+    }
+}
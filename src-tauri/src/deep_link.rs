@@ -0,0 +1,213 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Url};
+
+/// Registered with the OS through the deep-link plugin config, so links like
+/// `whitespace://staged?batch=batch_1` open the app.
+pub const DEEP_LINK_SCHEME: &str = "whitespace";
+/// Carries a [`DeepLinkTarget`] whenever the app is opened from a link.
+pub const DEEP_LINK_EVENT: &str = "deep-link://open";
+
+/// The last link opened, kept until the UI takes it: a link that launches
+/// the app arrives before the webview can listen for [`DEEP_LINK_EVENT`].
+static PENDING_LINK: Lazy<Mutex<Option<DeepLinkTarget>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeepLinkView {
+    /// Today's candidates.
+    Review,
+    /// One bucket of candidates; `whitespace://bucket/<key>`.
+    Bucket,
+    /// Staged files in their cooloff.
+    Staged,
+    /// Archive and delete batches.
+    History,
+}
+
+impl DeepLinkView {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "review" | "candidates" => Some(Self::Review),
+            "bucket" => Some(Self::Bucket),
+            "staged" => Some(Self::Staged),
+            "history" => Some(Self::History),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Review => "review",
+            Self::Bucket => "bucket",
+            Self::Staged => "staged",
+            Self::History => "history",
+        }
+    }
+
+    /// The UI route that shows the view.
+    fn route(self) -> &'static str {
+        match self {
+            Self::Review | Self::Bucket => "/",
+            Self::Staged => "/archive",
+            Self::History => "/activity",
+        }
+    }
+
+    /// Query parameters passed on to the UI; anything else in a link is
+    /// dropped.
+    fn params(self) -> &'static [&'static str] {
+        match self {
+            Self::Review => &["root", "saved_view"],
+            Self::Bucket => &["key", "root"],
+            Self::Staged => &["batch", "file"],
+            Self::History => &["batch"],
+        }
+    }
+}
+
+/// Where a link should take the UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeepLinkTarget {
+    pub view: DeepLinkView,
+    pub route: String,
+    pub params: BTreeMap<String, String>,
+}
+
+impl DeepLinkTarget {
+    pub fn new(view: DeepLinkView) -> Self {
+        Self {
+            view,
+            route: view.route().to_string(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `key` when the view takes it; other keys are ignored.
+    pub fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        if self.view.params().contains(&key) {
+            self.params.insert(key.to_string(), value.into());
+        }
+        self
+    }
+
+    /// The `whitespace://` link that opens this target.
+    pub fn url(&self) -> String {
+        let mut url = format!("{DEEP_LINK_SCHEME}://{}", self.view.name());
+        let mut params = self.params.clone();
+        if self.view == DeepLinkView::Bucket {
+            if let Some(key) = params.remove("key") {
+                url.push('/');
+                url.push_str(&key);
+            }
+        }
+        if !params.is_empty() {
+            let query = Url::parse(&format!("{DEEP_LINK_SCHEME}://q"))
+                .map(|mut base| {
+                    base.query_pairs_mut().extend_pairs(&params);
+                    base.query().unwrap_or_default().to_string()
+                })
+                .unwrap_or_default();
+            url.push('?');
+            url.push_str(&query);
+        }
+        url
+    }
+}
+
+/// Reads a `whitespace://` link. Unknown views, and bucket links without a
+/// key, are refused.
+pub fn parse(url: &Url) -> Result<DeepLinkTarget, String> {
+    if !url.scheme().eq_ignore_ascii_case(DEEP_LINK_SCHEME) {
+        return Err(format!("not a {DEEP_LINK_SCHEME}:// link: {url}"));
+    }
+    let host = url.host_str().unwrap_or_default();
+    let view = DeepLinkView::parse(host).ok_or_else(|| format!("unknown view: {host}"))?;
+    let mut target = DeepLinkTarget::new(view);
+    for (key, value) in url.query_pairs() {
+        if !value.trim().is_empty() {
+            target = target.with(&key, value.trim());
+        }
+    }
+    if view == DeepLinkView::Bucket {
+        let key = url
+            .path_segments()
+            .and_then(|mut segments| segments.find(|s| !s.is_empty()))
+            .map(str::to_string);
+        if let Some(key) = key {
+            target = target.with("key", key);
+        }
+        let valid_key = target.params.get("key").is_some_and(|key| {
+            key.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+        if !valid_key {
+            return Err(format!("bucket link needs a bucket key: {url}"));
+        }
+    }
+    Ok(target)
+}
+
+/// Handles links the app was opened with: the last valid one is kept for
+/// [`take_pending`], the window is brought forward and the UI is told.
+pub fn open_urls<R: tauri::Runtime>(app: &AppHandle<R>, urls: &[Url]) {
+    let Some(target) = urls
+        .iter()
+        .filter_map(|url| match parse(url) {
+            Ok(target) => Some(target),
+            Err(err) => {
+                tracing::warn!("Ignoring deep link: {err}");
+                None
+            }
+        })
+        .next_back()
+    else {
+        return;
+    };
+    tracing::info!(link = %target.url(), "opening deep link");
+    *PENDING_LINK.lock().expect("pending deep link lock") = Some(target.clone());
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit(DEEP_LINK_EVENT, target);
+}
+
+/// The link waiting to be shown, cleared once read.
+pub fn take_pending() -> Option<DeepLinkTarget> {
+    PENDING_LINK.lock().expect("pending deep link lock").take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(link: &str) -> Result<DeepLinkTarget, String> {
+        parse(&Url::parse(link).unwrap())
+    }
+
+    #[test]
+    fn test_links_round_trip_and_drop_unknown_params() {
+        let staged = DeepLinkTarget::new(DeepLinkView::Staged)
+            .with("batch", "batch 1&2")
+            .with("bucket", "ignored");
+        assert_eq!(staged.url(), "whitespace://staged?batch=batch+1%262");
+        assert_eq!(parse_str(&staged.url()), Ok(staged.clone()));
+        assert_eq!(staged.route, "/archive");
+
+        let bucket = parse_str("whitespace://bucket/large_files?root=%2Fhome%2Fu&x=1").unwrap();
+        assert_eq!(bucket.view, DeepLinkView::Bucket);
+        assert_eq!(bucket.params.get("key").map(String::as_str), Some("large_files"));
+        assert_eq!(bucket.params.get("root").map(String::as_str), Some("/home/u"));
+        assert!(!bucket.params.contains_key("x"));
+        assert_eq!(bucket.url(), "whitespace://bucket/large_files?root=%2Fhome%2Fu");
+
+        assert!(parse_str("whitespace://bucket").is_err());
+        assert!(parse_str("whitespace://bucket/..%2Fetc").is_err());
+        assert!(parse_str("whitespace://settings").is_err());
+        assert!(parse_str("https://staged").is_err());
+    }
+}
@@ -1,8 +1,97 @@
-use crate::db::Database;
+use crate::db::{Database, DbPool};
+use crate::i18n::Message;
 use crate::models::{ActionType, File};
 use crate::ops::error::{OpsError, OpsResult};
-use crate::selector::FileSelector;
+use crate::selector::scoring::Candidate;
+use crate::selector::{bucket_key, bucket_message, FileSelector};
 use chrono::{DateTime, Datelike, Duration, Timelike, Utc, Weekday};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const GAUGE_INVALIDATED_EVENT: &str = "gauge://invalidated";
+/// Carries a fresh [`GaugeState`] whenever something moved the numbers, so
+/// the UI can listen instead of polling `gauge_state`.
+pub const GAUGE_UPDATED_EVENT: &str = "gauge://updated";
+/// A clean cached gauge is still recomputed after this long: file ages and
+/// the window keep moving even when nothing is done.
+const GAUGE_CACHE_MAX_AGE_SECS: i64 = 300;
+
+/// Last gauge computed for the watched roots. Every invalidation bumps
+/// `generation`; the state is only reused if it was computed at the latest.
+#[derive(Default)]
+struct GaugeCache {
+    state: Option<GaugeState>,
+    generation: u64,
+    state_generation: u64,
+}
+
+static GAUGE_CACHE: Lazy<Mutex<GaugeCache>> = Lazy::new(|| Mutex::new(GaugeCache::default()));
+
+/// Marks the cached gauge stale; the next read recomputes it.
+pub fn mark_gauge_dirty() {
+    if let Ok(mut cache) = GAUGE_CACHE.lock() {
+        cache.generation += 1;
+    }
+}
+
+/// Gauge for the watched roots, reusing the cached state until it is marked
+/// dirty or ages out.
+pub fn cached_gauge_state(db: &Database) -> OpsResult<GaugeState> {
+    let generation = {
+        let cache = GAUGE_CACHE
+            .lock()
+            .map_err(|_| OpsError::GaugeError("Gauge cache poisoned".to_string()))?;
+        let fresh = |state: &GaugeState| {
+            Utc::now() - state.computed_at < Duration::seconds(GAUGE_CACHE_MAX_AGE_SECS)
+        };
+        match &cache.state {
+            Some(state) if cache.state_generation == cache.generation && fresh(state) => {
+                return Ok(state.clone());
+            }
+            _ => cache.generation,
+        }
+    };
+
+    let mut gauge_manager = GaugeManager::new();
+    gauge_manager.set_selector(FileSelector::from_db(db));
+    let state = gauge_manager.gauge_state(db)?;
+
+    // An invalidation that landed mid-computation keeps the cache dirty.
+    if let Ok(mut cache) = GAUGE_CACHE.lock() {
+        if cache.generation == generation {
+            cache.state = Some(state.clone());
+            cache.state_generation = generation;
+        }
+    }
+    Ok(state)
+}
+
+/// Invalidates the gauge, recomputes it and sends [`GAUGE_UPDATED_EVENT`].
+pub fn publish_gauge_update<R: tauri::Runtime>(app: &AppHandle<R>, db: &Database) {
+    mark_gauge_dirty();
+    match cached_gauge_state(db) {
+        Ok(state) => {
+            let _ = app.emit(GAUGE_UPDATED_EVENT, state);
+        }
+        Err(err) => tracing::warn!("Failed to recompute gauge: {err}"),
+    }
+}
+
+/// [`publish_gauge_update`] on a blocking thread, for callers that should
+/// not wait for the selector to run again.
+pub fn spawn_gauge_refresh<R: tauri::Runtime>(app: &AppHandle<R>) {
+    mark_gauge_dirty();
+    let Some(pool) = app.try_state::<DbPool>().map(|pool| pool.inner().clone()) else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || match pool.get() {
+        Ok(conn) => publish_gauge_update(&app, &Database::new(conn)),
+        Err(err) => tracing::warn!("Failed to recompute gauge: db pool: {err}"),
+    });
+}
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GaugeState {
@@ -14,6 +103,43 @@ pub struct GaugeState {
     pub window_end: DateTime<Utc>,
 }
 
+/// Where the gauge's numbers come from: potential bytes per bucket and the
+/// window's staged and freed bytes per watched root.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GaugeBreakdown {
+    pub buckets: Vec<BucketContribution>,
+    pub roots: Vec<RootContribution>,
+    pub computed_at: DateTime<Utc>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BucketContribution {
+    pub key: String,
+    pub label: Message,
+    pub file_count: usize,
+    pub potential_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct RootContribution {
+    pub root_path: String,
+    pub staged_files: usize,
+    pub staged_bytes: u64,
+    pub freed_files: usize,
+    pub freed_bytes: u64,
+}
+
+/// Sent when the watched roots change so the UI drops any cached gauge numbers.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GaugeInvalidatedPayload {
+    pub reason: String,
+    pub root_path: String,
+    pub files_removed: usize,
+    pub state: Option<GaugeState>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GaugeConfig {
     pub reset_on_tidy_day: bool,
@@ -36,6 +162,8 @@ impl Default for GaugeConfig {
 pub struct GaugeManager {
     config: GaugeConfig,
     selector: FileSelector,
+    /// Roots the gauge counts; `None` means the currently watched roots.
+    roots: Option<Vec<String>>,
 }
 
 impl GaugeManager {
@@ -43,21 +171,34 @@ impl GaugeManager {
         Self {
             config: GaugeConfig::default(),
             selector: FileSelector::new(),
+            roots: None,
         }
     }
 
+    /// Pins the roots instead of reading the watched roots on every call.
+    #[cfg(test)]
+    pub fn set_roots(&mut self, roots: Vec<String>) {
+        self.roots = Some(roots);
+    }
+
+    /// Swaps in a selector carrying the stored config and user rules.
+    pub fn set_selector(&mut self, selector: FileSelector) {
+        self.selector = selector;
+    }
+
     pub fn gauge_state(&self, db: &Database) -> OpsResult<GaugeState> {
         let now = Utc::now();
         let (window_start, window_end) = self.get_window_bounds(now);
+        let roots = self.current_roots(db)?;
 
         // Compute potential (current daily candidates)
-        let potential_today_bytes = self.compute_potential_today(db)?;
+        let potential_today_bytes = self.compute_potential_today(db, &roots)?;
 
         // Compute staged (archived but not deleted in window)
-        let staged_week_bytes = self.compute_staged_week(db, window_start, window_end)?;
+        let staged_week_bytes = self.compute_staged_week(db, &roots, window_start, window_end)?;
 
         // Compute freed (deleted in window)
-        let freed_week_bytes = self.compute_freed_week(db, window_start, window_end)?;
+        let freed_week_bytes = self.compute_freed_week(db, &roots, window_start, window_end)?;
 
         Ok(GaugeState {
             potential_today_bytes,
@@ -69,6 +210,74 @@ impl GaugeManager {
         })
     }
 
+    /// Splits the gauge by bucket and by root, over the same candidates and
+    /// window as [`Self::gauge_state`], so the parts add up to its totals. A
+    /// file under nested roots counts toward the innermost one.
+    pub fn gauge_breakdown(&self, db: &Database) -> OpsResult<GaugeBreakdown> {
+        let now = Utc::now();
+        let (window_start, window_end) = self.get_window_bounds(now);
+        let roots = self.current_roots(db)?;
+
+        let mut buckets: HashMap<String, BucketContribution> = HashMap::new();
+        for candidate in self.reclaimable_candidates(db, &roots)? {
+            let share = buckets
+                .entry(bucket_key(&candidate.reason))
+                .or_insert_with_key(|key| BucketContribution {
+                    key: key.clone(),
+                    label: bucket_message(&candidate.reason),
+                    file_count: 0,
+                    potential_bytes: 0,
+                });
+            share.file_count += 1;
+            share.potential_bytes += candidate.size_bytes;
+        }
+        let mut buckets: Vec<BucketContribution> = buckets.into_values().collect();
+        buckets.sort_by(|a, b| b.potential_bytes.cmp(&a.potential_bytes).then(a.key.cmp(&b.key)));
+
+        let mut shares: Vec<RootContribution> = roots
+            .iter()
+            .map(|root| RootContribution {
+                root_path: root.clone(),
+                ..Default::default()
+            })
+            .collect();
+        for file in self.staged_files_in_window(db, &roots, window_start, window_end)? {
+            if let Some(share) = Self::innermost_root(&mut shares, &file.path) {
+                share.staged_files += 1;
+                share.staged_bytes += file.size_bytes as u64;
+            }
+        }
+        for file in self.freed_files_in_window(db, &roots, window_start, window_end)? {
+            if let Some(share) = Self::innermost_root(&mut shares, &file.path) {
+                share.freed_files += 1;
+                share.freed_bytes += file.size_bytes as u64;
+            }
+        }
+        shares.sort_by(|a, b| {
+            (b.staged_bytes + b.freed_bytes)
+                .cmp(&(a.staged_bytes + a.freed_bytes))
+                .then(a.root_path.cmp(&b.root_path))
+        });
+
+        Ok(GaugeBreakdown {
+            buckets,
+            roots: shares,
+            computed_at: now,
+            window_start,
+            window_end,
+        })
+    }
+
+    fn innermost_root<'a>(
+        shares: &'a mut [RootContribution],
+        path: &str,
+    ) -> Option<&'a mut RootContribution> {
+        shares
+            .iter_mut()
+            .filter(|share| std::path::Path::new(path).starts_with(&share.root_path))
+            .max_by_key(|share| share.root_path.len())
+    }
+
     fn get_window_bounds(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
         if self.config.reset_on_tidy_day {
             self.get_tidy_day_bounds(now)
@@ -114,59 +323,121 @@ impl GaugeManager {
         (window_start, window_end)
     }
 
-    fn compute_potential_today(&self, db: &Database) -> OpsResult<u64> {
-        let candidates = self.selector.daily_candidates(Some(1000), db)?;
-        let roots = db.list_watched_paths().map_err(|e| OpsError::GaugeError(format!("Failed to list roots: {}", e)))?;
-        let total_bytes: u64 = candidates
+    fn current_roots(&self, db: &Database) -> OpsResult<Vec<String>> {
+        match &self.roots {
+            Some(roots) => Ok(roots.clone()),
+            None => db
+                .list_online_watched_paths()
+                .map_err(|e| OpsError::GaugeError(format!("Failed to list roots: {}", e))),
+        }
+    }
+
+    fn compute_potential_today(&self, db: &Database, roots: &[String]) -> OpsResult<u64> {
+        let total_bytes: u64 = self
+            .reclaimable_candidates(db, roots)?
             .into_iter()
-            .filter(|c| Self::path_in_any_root(&c.path, &roots))
-            .map(|c| c.size_bytes as u64)
+            .map(|c| c.size_bytes)
             .sum();
         Ok(total_bytes)
     }
 
+    fn reclaimable_candidates(&self, db: &Database, roots: &[String]) -> OpsResult<Vec<Candidate>> {
+        let candidates = self
+            .selector
+            .daily_candidates_under_roots(roots, Some(1000), db)?;
+        let unreclaimable = Self::unreclaimable_ids(db)?;
+        Ok(candidates
+            .into_iter()
+            .filter(|c| !unreclaimable.contains(&c.file_id))
+            .collect())
+    }
+
+    /// Hard-linked files and cloud placeholders free nothing when removed, so
+    /// byte estimates skip them.
+    fn unreclaimable_ids(db: &Database) -> OpsResult<HashSet<i64>> {
+        db.unreclaimable_file_ids().map_err(|e| {
+            OpsError::GaugeError(format!("Failed to list unreclaimable files: {}", e))
+        })
+    }
+
     fn compute_staged_week(
         &self,
         db: &Database,
+        roots: &[String],
         window_start: DateTime<Utc>,
         window_end: DateTime<Utc>,
     ) -> OpsResult<u64> {
-        // Compute staged by summing current staged records within the window (and under active roots)
-        let roots = db.list_watched_paths().map_err(|e| OpsError::GaugeError(format!("Failed to list roots: {}", e)))?;
-        let staged_files = db
-            .list_current_staged_files_in_period(&window_start.to_rfc3339(), &window_end.to_rfc3339())
-            .map_err(|e| OpsError::GaugeError(format!("Failed to list staged files: {}", e)))?;
-        let staged_bytes = staged_files
+        let staged_bytes = self
+            .staged_files_in_window(db, roots, window_start, window_end)?
             .into_iter()
-            .filter(|f| Self::path_in_any_root(&f.path, &roots))
             .map(|f| f.size_bytes as u64)
             .sum();
 
         Ok(staged_bytes)
     }
 
+    fn staged_files_in_window(
+        &self,
+        db: &Database,
+        roots: &[String],
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    ) -> OpsResult<Vec<File>> {
+        // Current staged records within the window (and under active roots)
+        let staged_files = db
+            .list_current_staged_files_in_period(&window_start.to_rfc3339(), &window_end.to_rfc3339())
+            .map_err(|e| OpsError::GaugeError(format!("Failed to list staged files: {}", e)))?;
+        let unreclaimable = Self::unreclaimable_ids(db)?;
+        Ok(staged_files
+            .into_iter()
+            .filter(|f| Self::path_in_any_root(&f.path, roots))
+            .filter(|f| !f.id.is_some_and(|id| unreclaimable.contains(&id)))
+            .collect())
+    }
+
     fn compute_freed_week(
         &self,
         db: &Database,
+        roots: &[String],
         window_start: DateTime<Utc>,
         window_end: DateTime<Utc>,
     ) -> OpsResult<u64> {
+        let freed_bytes = self
+            .freed_files_in_window(db, roots, window_start, window_end)?
+            .into_iter()
+            .map(|f| f.size_bytes as u64)
+            .sum();
+
+        Ok(freed_bytes)
+    }
+
+    /// The file behind each delete action in the window, one entry per action.
+    fn freed_files_in_window(
+        &self,
+        db: &Database,
+        roots: &[String],
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    ) -> OpsResult<Vec<File>> {
         // Get all delete actions in the window
         let delete_actions = self.get_delete_actions_in_window(db, window_start, window_end)?;
-        let roots = db.list_watched_paths().map_err(|e| OpsError::GaugeError(format!("Failed to list roots: {}", e)))?;
 
-        let mut freed_bytes = 0u64;
+        let unreclaimable = Self::unreclaimable_ids(db)?;
+        let mut freed = Vec::new();
 
         for action in delete_actions {
+            if unreclaimable.contains(&action.file_id) {
+                continue;
+            }
             // Get the file size from the action's file_id
             if let Some(file) = self.get_file_by_id(db, action.file_id)? {
-                if Self::path_in_any_root(&file.path, &roots) {
-                    freed_bytes += file.size_bytes as u64;
+                if Self::path_in_any_root(&file.path, roots) {
+                    freed.push(file);
                 }
             }
         }
 
-        Ok(freed_bytes)
+        Ok(freed)
     }
 
     fn get_archived_files_in_window(
@@ -279,12 +550,14 @@ impl GaugeManager {
     }
 
     pub fn get_gauge_summary(&self, state: &GaugeState) -> String {
-        format!(
-            "Potential: {}, Staged: {}, Freed: {}",
-            self.format_bytes(state.potential_today_bytes),
-            self.format_bytes(state.staged_week_bytes),
-            self.format_bytes(state.freed_week_bytes)
-        )
+        self.gauge_summary_message(state).render()
+    }
+
+    pub fn gauge_summary_message(&self, state: &GaugeState) -> Message {
+        Message::new("gauge.summary")
+            .with("potential", self.format_bytes(state.potential_today_bytes))
+            .with("staged", self.format_bytes(state.staged_week_bytes))
+            .with("freed", self.format_bytes(state.freed_week_bytes))
     }
 
     fn path_in_any_root(path: &str, roots: &[String]) -> bool {
@@ -332,11 +605,11 @@ impl Default for GaugeManager {
 mod tests {
     use super::*;
     use crate::db::Database;
-    use std::fs;
-    use tempfile::TempDir;
 
     fn create_test_database() -> Database {
-        Database::open_db(":memory:").unwrap()
+        let db = Database::open_db(":memory:").unwrap();
+        db.run_migrations().unwrap();
+        db
     }
 
     #[test]
@@ -348,9 +621,9 @@ mod tests {
         assert!(state.is_ok());
 
         let gauge_state = state.unwrap();
-        assert!(gauge_state.potential_today_bytes >= 0);
-        assert!(gauge_state.staged_week_bytes >= 0);
-        assert!(gauge_state.freed_week_bytes >= 0);
+        assert_eq!(gauge_state.potential_today_bytes, 0);
+        assert_eq!(gauge_state.staged_week_bytes, 0);
+        assert_eq!(gauge_state.freed_week_bytes, 0);
         assert!(gauge_state.computed_at <= Utc::now());
         assert!(gauge_state.window_start <= gauge_state.window_end);
     }
@@ -539,4 +812,89 @@ mod tests {
         assert_eq!(state.staged_week_bytes, deserialized.staged_week_bytes);
         assert_eq!(state.freed_week_bytes, deserialized.freed_week_bytes);
     }
+
+    #[test]
+    fn test_invalidated_payload_serialization() {
+        let payload = GaugeInvalidatedPayload {
+            reason: "root_removed".to_string(),
+            root_path: "/Users/test/Downloads".to_string(),
+            files_removed: 3,
+            state: None,
+        };
+
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["reason"], "root_removed");
+        assert_eq!(json["files_removed"], 3);
+        assert!(json["state"].is_null());
+    }
+
+    #[test]
+    fn test_breakdown_credits_innermost_root() {
+        use crate::models::{HashAlgo, NewFile, NewStagedFile};
+
+        let db = create_test_database();
+        db.run_migrations().unwrap();
+        let now = Utc::now();
+        let files = [("/home/u/Documents/a.pdf", 300), ("/home/u/Downloads/b.iso", 700)];
+        for (path, size_bytes) in files {
+            let parent = std::path::Path::new(path).parent().unwrap();
+            let file_id = db
+                .upsert_file(&NewFile {
+                    path: path.to_string(),
+                    parent_dir: parent.to_string_lossy().to_string(),
+                    mime: None,
+                    size_bytes,
+                    created_at: None,
+                    modified_at: None,
+                    accessed_at: None,
+                    partial_sha1: None,
+                    sha1: None,
+                    inode_key: None,
+                    link_count: 1,
+                    phash: None,
+                    is_cloud_placeholder: false,
+                    root_id: None,
+                    is_symlink: false,
+                    symlink_target: None,
+                    hash_algo: HashAlgo::CURRENT,
+                })
+                .unwrap();
+            db.stage_files(&[NewStagedFile {
+                file_id,
+                staged_at: now - Duration::hours(1),
+                expires_at: None,
+                batch_id: Some("batch-1".to_string()),
+                status: "staged".to_string(),
+                note: None,
+            }])
+            .unwrap();
+        }
+
+        let mut gauge_manager = GaugeManager::new();
+        gauge_manager.set_roots(vec!["/home/u".to_string(), "/home/u/Downloads".to_string()]);
+        let breakdown = gauge_manager.gauge_breakdown(&db).unwrap();
+        let state = gauge_manager.gauge_state(&db).unwrap();
+
+        let staged: Vec<(&str, u64)> = breakdown
+            .roots
+            .iter()
+            .map(|share| (share.root_path.as_str(), share.staged_bytes))
+            .collect();
+        assert_eq!(staged, vec![("/home/u/Downloads", 700), ("/home/u", 300)]);
+        let total: u64 = breakdown.roots.iter().map(|share| share.staged_bytes).sum();
+        assert_eq!(total, state.staged_week_bytes);
+    }
+
+    #[test]
+    fn test_cached_gauge_recomputes_once_dirty() {
+        let db = create_test_database();
+        db.run_migrations().unwrap();
+
+        let first = cached_gauge_state(&db).unwrap();
+        mark_gauge_dirty();
+        let second = cached_gauge_state(&db).unwrap();
+        assert!(second.computed_at > first.computed_at);
+        let third = cached_gauge_state(&db).unwrap();
+        assert_eq!(third.computed_at, second.computed_at);
+    }
 }
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// BCP 47 tag the UI renders messages in, e.g. "en" or "pt-BR".
+pub const LOCALE_PREF: &str = "locale";
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// A user-facing string as a catalog key plus named parameters, so the UI can
+/// render it in its own language instead of parsing English text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+
+impl Message {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// English text, used for the plain string fields kept alongside messages.
+    pub fn render(&self) -> String {
+        translate(DEFAULT_LOCALE, self)
+    }
+}
+
+/// English fallback catalog. Every key the backend emits must have an entry.
+const EN: &[(&str, &str)] = &[
+    ("bucket.screenshot", "Screenshots"),
+    ("bucket.big_download", "Big Downloads"),
+    ("bucket.old_desktop", "Old Desktop"),
+    ("bucket.duplicate", "Duplicates"),
+    ("bucket.installer", "Installers"),
+    ("bucket.broken_symlink", "Broken Symlinks"),
+    ("bucket.custom", "{name}"),
+    ("hint.candidate", "candidate"),
+    ("hint.cloud_only", "cloud-only"),
+    ("hint.link_to", "link to {target}"),
+    ("hint.duplicate", "duplicate"),
+    ("hint.unopened", "unopened"),
+    ("hint.large", "large"),
+    ("hint.old", "old"),
+    ("hint.git_repo", "git-repo"),
+    ("hint.flagged", "flagged"),
+    ("hint.recent_activity", "recent-activity"),
+    ("explain.matches_bucket", "Matches this bucket"),
+    ("explain.never_opened", "never opened"),
+    ("explain.unused_months", "not used in {count} months"),
+    ("explain.unused_days", "not used in {count} days"),
+    ("explain.cloud_only", "cloud-only, so removing it frees no space here"),
+    ("explain.large", "large at {size}"),
+    ("explain.size", "{size}"),
+    ("explain.duplicate", "another copy exists"),
+    ("explain.penalty.git_repo", "it sits in a git repository"),
+    ("explain.penalty.recent_activity", "its folder changed recently"),
+    ("explain.penalty.flagged", "its path suggests it is in use"),
+    ("gauge.summary", "Potential: {potential}, Staged: {staged}, Freed: {freed}"),
+    (
+        "staged.lost",
+        "The archived copy was moved or deleted outside White Space, so it can't be restored",
+    ),
+    ("error.archive.title", "Archive Failed"),
+    ("error.archive", "Unable to archive files: {detail}"),
+    ("error.archive.suggestion", "Check disk space and permissions, then try again."),
+    ("error.delete.title", "Delete Failed"),
+    ("error.delete", "Unable to delete files: {detail}"),
+    ("error.delete.suggestion", "Check file permissions and try again."),
+    ("error.undo.title", "Undo Failed"),
+    ("error.undo", "Unable to undo operation: {detail}"),
+    (
+        "error.undo.suggestion",
+        "Some files may have been moved or deleted outside the application.",
+    ),
+    ("error.space.title", "Insufficient Space"),
+    ("error.space", "Not enough disk space: {detail}"),
+    ("error.space.suggestion", "Free up disk space or choose a different location."),
+    ("error.permission.title", "Permission Denied"),
+    ("error.permission", "Access denied: {detail}"),
+    ("error.permission.suggestion", "Run as administrator or check file permissions."),
+    ("error.not_found.title", "File Not Found"),
+    ("error.not_found", "File not found: {detail}"),
+    ("error.not_found.suggestion", "The file may have been moved or deleted."),
+    ("error.invalid_path.title", "Invalid Path"),
+    ("error.invalid_path", "Invalid file path: {detail}"),
+    ("error.invalid_path.suggestion", "Check the file path and try again."),
+    ("error.cross_volume.title", "Cross Volume Operation"),
+    ("error.cross_volume", "Cannot move across volumes: {detail}"),
+    (
+        "error.cross_volume.suggestion",
+        "The operation will copy and delete instead of moving.",
+    ),
+    ("error.batch.title", "Batch Operation Failed"),
+    ("error.batch", "Batch operation failed: {detail}"),
+    (
+        "error.batch.suggestion",
+        "Some files in the batch may have failed. Check individual file status.",
+    ),
+    ("error.database.title", "Database Error"),
+    ("error.database", "Database operation failed: {detail}"),
+    ("error.database.suggestion", "Try restarting the application."),
+    ("error.gauge.title", "Gauge Error"),
+    ("error.gauge", "Gauge calculation failed: {detail}"),
+    ("error.gauge.suggestion", "Try refreshing the gauge data."),
+    ("error.system_location.title", "System Location"),
+    ("error.system_location", "{detail} belongs to the system or an installed app"),
+    (
+        "error.system_location.suggestion",
+        "Files there are left alone; remove the app through the system instead.",
+    ),
+];
+
+/// Catalog for a locale. Only English ships with the backend; the UI carries
+/// the other languages and renders from the keys.
+fn catalog(locale: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    match locale {
+        "en" => Some(EN),
+        _ => None,
+    }
+}
+
+fn lookup(locale: &str, key: &str) -> Option<&'static str> {
+    let language = locale.split('-').next().unwrap_or(locale);
+    [locale, language, DEFAULT_LOCALE]
+        .into_iter()
+        .filter_map(catalog)
+        .find_map(|entries| entries.iter().find(|(k, _)| *k == key).map(|(_, text)| *text))
+}
+
+/// Renders `message` in `locale`, falling back to the language without its
+/// region, then English, then the bare key.
+pub fn translate(locale: &str, message: &Message) -> String {
+    let Some(template) = lookup(locale, &message.key) else {
+        return message.key.clone();
+    };
+    message
+        .params
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Loosely checks a BCP 47 tag: a 2-3 letter language, then optional
+/// alphanumeric subtags of 2-8 characters.
+pub fn is_valid_locale(tag: &str) -> bool {
+    let mut parts = tag.split('-');
+    let Some(language) = parts.next() else {
+        return false;
+    };
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|p| (2..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
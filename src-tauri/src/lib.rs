@@ -1,15 +1,29 @@
+pub mod cli;
 mod commands;
 mod db;
+mod deep_link;
 mod gauge;
+mod i18n;
 mod licensing;
+mod logging;
+mod maintenance;
 mod models;
+mod notifications;
 mod ops;
+mod preview;
+mod reports;
 mod scanner;
 mod selector;
+mod thumbnail;
+mod tidy;
 
-use db::{init_pool, Database, DbPool};
+use db::{DbPool, RecoveryState, DATABASE_RECOVERED_EVENT};
 use licensing::LicenseStorage;
-use tauri::Manager;
+use std::path::PathBuf;
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use scanner::watcher;
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
@@ -17,28 +31,134 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-fn app_db_path() -> std::path::PathBuf {
+fn app_dir() -> PathBuf {
     let app_data_dir = dirs::data_dir().expect("Failed to get app data directory");
     let app_dir = app_data_dir.join("white-space");
     std::fs::create_dir_all(&app_dir).expect("Failed to create app directory");
-    app_dir.join("database.db")
+    app_dir
+}
+
+fn app_db_path() -> PathBuf {
+    app_dir().join("database.db")
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must come first: a second launch hands over to the running app and
+        // exits before it opens the database or starts watchers and scans.
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
-            // Initialize database pool
+            let log_dir = logging::log_dir(&app_dir());
+            if let Err(err) = logging::init(&log_dir, logging::DEFAULT_LOG_LEVEL) {
+                eprintln!("Failed to initialize logging: {err}");
+            }
+
+            // Initialize database pool, replacing a damaged file with a fresh one
             let db_path = app_db_path();
-            let pool = init_pool(&db_path);
+            tracing::info!(path = %db_path.display(), "opening database");
+            let (pool, recovery) = db::recovery::open_checked(&db_path);
+
+            // Run migrations on first connection
+            {
+                let conn = pool.get().expect("Failed to get database connection");
+                let db = db::Database::new(conn);
+                if let Err(e) = db.run_migrations() {
+                    tracing::error!("Database migration error: {}", e);
+                    // The window may never load, so report it natively before quitting.
+                    app.dialog()
+                        .message(format!(
+                            "White Space could not update its database:\n\n{}\n\n\
+                             Your data has not been changed. Logs are in {}.",
+                            e,
+                            log_dir.display()
+                        ))
+                        .title("Database error")
+                        .kind(MessageDialogKind::Error)
+                        .show(|_| std::process::exit(1));
+                    return Ok(());
+                }
+                if let Ok(Some(level)) = db.get_preference(logging::LOG_LEVEL_PREF) {
+                    if let Err(err) = logging::set_level(&level) {
+                        tracing::warn!("Ignoring log level preference: {err}");
+                    }
+                }
+                if let Some(recovery) = &recovery {
+                    for root in &recovery.roots {
+                        if let Err(e) = db.upsert_watched_root(root) {
+                            tracing::warn!("Failed to re-add watched root {}: {}", root, e);
+                        }
+                    }
+                }
+                if let Err(e) = db.clear_selections() {
+                    tracing::warn!("Failed to clear last session's selections: {}", e);
+                }
+                // Older installs could watch a folder and one of its parents.
+                match db.merge_nested_watched_roots() {
+                    Ok(removed) if !removed.is_empty() => {
+                        tracing::info!("Merged nested watched roots: {:?}", removed);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to merge nested watched roots: {}", e),
+                }
+            }
+
+            let pool_for_watcher = pool.clone();
+            let pool_for_license = pool.clone();
+            let pool_for_expiry = pool.clone();
+            let pool_for_tidy = pool.clone();
+            let pool_for_maintenance = pool.clone();
+            let pool_for_scans = pool.clone();
+            let pool_for_idle = pool.clone();
+
             app.manage::<DbPool>(pool);
+            let recovery_state = RecoveryState::default();
+            if let Some(recovery) = recovery {
+                let _ = app.emit(DATABASE_RECOVERED_EVENT, &recovery);
+                recovery_state.set(recovery);
+            }
+            app.manage(recovery_state);
 
-            // Initialize licensing storage (Send+Sync)
-            app.manage(LicenseStorage {
-                cache: tokio::sync::RwLock::new(Default::default()),
-            });
+            // Initialize licensing storage (Send+Sync), restoring any saved activation
+            app.manage(LicenseStorage::with_pool(pool_for_license));
+
+            if let Err(err) = watcher::start_watchers(app.handle().clone(), pool_for_watcher) {
+                tracing::error!("File watcher failed to start: {err}");
+            }
+
+            ops::expiry::start_expiry_sweeper(app.handle().clone(), pool_for_expiry);
+            tidy::start_tidy_scheduler(app.handle().clone(), pool_for_tidy);
+            maintenance::start_maintenance_scheduler(app.handle().clone(), pool_for_maintenance);
+            scanner::schedule::start_scan_scheduler(app.handle().clone(), pool_for_scans);
+            scanner::activity::start_idle_watcher(app.handle().clone(), pool_for_idle);
+
+            // Later launches with a link hand it over through the single
+            // instance plugin and arrive here as well.
+            let handle = app.handle().clone();
+            app.deep_link()
+                .on_open_url(move |event| deep_link::open_urls(&handle, &event.urls()));
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                deep_link::open_urls(app.handle(), &urls);
+            }
+            // The app bundle declares the scheme on macOS; on Windows and Linux
+            // it is registered at launch so portable and dev builds get it too.
+            #[cfg(any(windows, target_os = "linux"))]
+            {
+                if let Err(err) = app.deep_link().register_all() {
+                    tracing::warn!("Failed to register link scheme: {err}");
+                }
+            }
 
             Ok(())
         })
@@ -46,26 +166,112 @@ pub fn run() {
             greet,
             commands::add_folder,
             commands::list_folders,
+            commands::suggest_default_roots,
+            commands::remove_folder,
+            commands::get_root_settings,
+            commands::update_root_settings,
+            commands::add_exclusion,
+            commands::list_exclusions,
+            commands::remove_exclusion,
+            commands::add_protected_path,
+            commands::list_protected_paths,
+            commands::remove_protected_path,
+            commands::add_user_rule,
+            commands::update_user_rule,
+            commands::list_user_rules,
+            commands::remove_user_rule,
             commands::pick_directory,
             commands::list_dir,
             commands::open_in_system,
+            commands::open_file,
             commands::get_platform_info,
             commands::scan_roots,
             commands::start_scan,
+            commands::rescan_all,
+            commands::rescan_folder,
             commands::scan_status,
+            commands::scan_history,
+            commands::get_logs,
+            commands::backup_database,
+            commands::restore_database,
+            commands::repair_database,
+            commands::get_database_recovery,
+            commands::take_pending_deep_link,
+            commands::compact_database,
+            commands::clear_history,
+            commands::cancel_scan,
+            commands::pause_scan,
+            commands::resume_scan,
             commands::get_candidates,
             commands::daily_candidates,
             commands::get_candidates_bucketed,
+            commands::get_bucket_stats,
+            commands::stage_bucket,
+            commands::get_folder_sizes,
+            commands::get_mime_breakdown,
+            commands::get_age_histogram,
+            commands::top_largest_files,
+            commands::stale_files_report,
+            commands::screenshots_report,
+            commands::stage_screenshot_month,
+            commands::get_build_artifacts,
+            commands::stage_directory,
+            commands::get_metrics,
+            commands::freed_space_timeline,
+            commands::generate_weekly_digest,
+            commands::search_files,
+            commands::add_saved_view,
+            commands::update_saved_view,
+            commands::list_saved_views,
+            commands::remove_saved_view,
+            commands::run_view,
+            commands::dismiss_candidate,
+            commands::list_dismissed,
+            commands::undismiss_candidate,
+            commands::record_feedback,
             commands::gauge_state,
+            commands::gauge_breakdown,
+            commands::get_selector_config,
+            commands::set_selector_config,
+            commands::list_staged,
+            commands::stage_files,
+            commands::restore_staged,
+            commands::reconcile_staged,
+            commands::empty_staged,
+            commands::purge_archives,
+            commands::get_duplicate_groups,
+            commands::preview_duplicate_policy,
+            commands::apply_duplicate_policy,
+            commands::resolve_duplicate_group,
+            commands::get_similar_images,
             commands::archive_files,
+            commands::get_archive_config,
+            commands::set_archive_config,
+            commands::migrate_archive,
+            commands::check_space_for_files,
             commands::delete_files,
+            commands::create_selection,
+            commands::add_to_selection,
+            commands::remove_from_selection,
+            commands::get_selection_summary,
+            commands::discard_selection,
+            commands::stage_selection,
+            commands::delete_selection_files,
             commands::undo_last,
-            commands::list_undoable_batches,
             commands::undo_batch,
+            commands::undo_files,
+            commands::list_undoable_batches,
+            commands::update_batch_label,
+            commands::list_batches,
+            commands::cancel_operation,
+            commands::list_active_operations,
             commands::get_review_items,
             commands::get_thumbnail,
+            commands::get_file_preview,
             commands::get_prefs,
             commands::set_prefs,
+            commands::export_settings,
+            commands::import_settings,
             licensing::ls_activate,
             licensing::ls_validate,
             licensing::ls_deactivate,
@@ -1,8 +1,8 @@
-use crate::db::Database;
+use crate::db::{Database, DbPool};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
 // License API response types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,44 +49,297 @@ pub struct LicenseStatus {
     pub status_message: String,
 }
 
+// Result of checking whether the cached license should be re-validated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationCheck {
+    pub needed: bool,
+    pub is_offline_grace: bool,
+    pub grace_expires_at: Option<DateTime<Utc>>,
+    pub days_remaining: Option<i64>,
+    pub expired: bool,
+}
+
+// Re-validate once a week; keep working offline for up to two weeks
+pub const VALIDATION_INTERVAL_DAYS: i64 = 7;
+pub const OFFLINE_GRACE_DAYS: i64 = 14;
+
+// Warn this many days before the license itself expires
+pub const EXPIRY_WARNING_DAYS: i64 = 14;
+pub const LICENSE_EXPIRING_EVENT: &str = "license://expiring";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseExpiringPayload {
+    pub expires_at: DateTime<Utc>,
+    pub days_remaining: i64,
+}
+
+// Whole days left until `expires_at`, rounded up; 0 once it has passed
+pub fn days_until(expires_at: i64, now: i64) -> i64 {
+    let day = 24 * 60 * 60;
+    ((expires_at - now).max(0) + day - 1) / day
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraceWindow {
+    pub is_offline_grace: bool,
+    pub grace_expires_at: Option<DateTime<Utc>>,
+    pub days_remaining: Option<i64>,
+    pub expired: bool,
+}
+
+// Grace runs from the last successful validation. Past the validation interval
+// the license is in offline grace; past the grace window it is expired.
+pub fn grace_window(last_validated_at: Option<i64>, now: i64) -> GraceWindow {
+    let Some(last) = last_validated_at else {
+        return GraceWindow {
+            is_offline_grace: false,
+            grace_expires_at: None,
+            days_remaining: None,
+            expired: false,
+        };
+    };
+
+    let day = 24 * 60 * 60;
+    let expires_ts = last + OFFLINE_GRACE_DAYS * day;
+    let elapsed_days = (now - last) / day;
+    let expired = now >= expires_ts;
+
+    GraceWindow {
+        is_offline_grace: !expired && elapsed_days >= VALIDATION_INTERVAL_DAYS,
+        grace_expires_at: DateTime::from_timestamp(expires_ts, 0),
+        days_remaining: Some(((expires_ts - now).max(0) + day - 1) / day),
+        expired,
+    }
+}
+
+/// Where the license API lives unless overridden.
+pub const DEFAULT_API_BASE_URL: &str = "https://api.whitespace.app/v1";
+/// Environment override for the API base URL (staging, self-hosted).
+pub const API_BASE_URL_ENV: &str = "WHITESPACE_LICENSE_API_URL";
+/// Preference holding the API base URL; the environment wins over it.
+pub const PREF_API_BASE_URL: &str = "license_api_base_url";
+
+/// Longest Retry-After worth waiting for; beyond it the error is returned.
+const MAX_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(30);
+
+// How the license API is reached
+#[derive(Debug, Clone, PartialEq)]
+pub struct LicenseConfig {
+    pub api_base_url: String,
+    pub timeout: std::time::Duration,
+    /// Attempts after the first one for network errors, 429 and 5xx.
+    pub max_retries: u32,
+    /// First pause between attempts; doubled each time, plus jitter.
+    pub backoff: std::time::Duration,
+}
+
+impl Default for LicenseConfig {
+    fn default() -> Self {
+        Self {
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+            timeout: std::time::Duration::from_secs(15),
+            max_retries: 3,
+            backoff: std::time::Duration::from_millis(500),
+        }
+    }
+}
+
+impl LicenseConfig {
+    // Env first, then the saved preference, then the default
+    pub fn resolve(pref_base_url: Option<String>) -> Self {
+        let api_base_url = std::env::var(API_BASE_URL_ENV)
+            .ok()
+            .or(pref_base_url)
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_API_BASE_URL.to_string());
+        Self {
+            api_base_url,
+            ..Self::default()
+        }
+    }
+
+    // Pause before retry `attempt` (0-based): exponential, with up to half
+    // again as much jitter, or the server's Retry-After when it sent one
+    pub fn retry_delay(
+        &self,
+        attempt: u32,
+        retry_after: Option<std::time::Duration>,
+    ) -> std::time::Duration {
+        if let Some(wait) = retry_after {
+            return wait;
+        }
+        let base = self.backoff.saturating_mul(2u32.saturating_pow(attempt));
+        base + jitter(base / 2)
+    }
+}
+
+fn jitter(max: std::time::Duration) -> std::time::Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let max_nanos = max.as_nanos().max(1) as u64;
+    std::time::Duration::from_nanos(u64::from(nanos) % max_nanos)
+}
+
+// Licensing failures, serialized as { kind, detail } for the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum LicenseError {
+    /// Rejected before any request was made.
+    InvalidInput(String),
+    /// No activation is stored on this device.
+    NotActivated,
+    /// The server could not be reached.
+    Network(String),
+    Timeout,
+    /// 400 / 422: the server did not accept the request.
+    BadRequest(String),
+    /// 401 / 403: the key is unknown, revoked or not allowed.
+    Unauthorized(String),
+    /// 404: no such license or instance.
+    NotFound(String),
+    /// 409: every seat is in use.
+    SeatLimit(String),
+    /// 429, after retries ran out.
+    RateLimited { retry_after_secs: Option<u64> },
+    /// 5xx, after retries ran out.
+    Server { status: u16 },
+    Http { status: u16, message: String },
+    /// The response body was not what the API promises.
+    InvalidResponse(String),
+}
+
+impl std::fmt::Display for LicenseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseError::InvalidInput(msg) => write!(f, "{}", msg),
+            LicenseError::NotActivated => write!(f, "No license data found"),
+            LicenseError::Network(msg) => write!(f, "Network error: {}", msg),
+            LicenseError::Timeout => write!(f, "The license server did not respond in time"),
+            LicenseError::BadRequest(msg) => write!(f, "Request rejected: {}", msg),
+            LicenseError::Unauthorized(msg) => write!(f, "License not accepted: {}", msg),
+            LicenseError::NotFound(msg) => write!(f, "License not found: {}", msg),
+            LicenseError::SeatLimit(msg) => write!(f, "No seats left: {}", msg),
+            LicenseError::RateLimited { .. } => write!(f, "Too many requests, try again later"),
+            LicenseError::Server { status } => write!(f, "License server error ({})", status),
+            LicenseError::Http { status, message } => {
+                write!(f, "API error ({}): {}", status, message)
+            }
+            LicenseError::InvalidResponse(msg) => write!(f, "Unexpected response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LicenseError {}
+
+impl LicenseError {
+    // Maps a non-success status; `body` is the server's message or raw body
+    pub fn from_status(status: u16, body: &str, retry_after_secs: Option<u64>) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string());
+        match status {
+            400 | 422 => LicenseError::BadRequest(message),
+            401 | 403 => LicenseError::Unauthorized(message),
+            404 => LicenseError::NotFound(message),
+            409 => LicenseError::SeatLimit(message),
+            429 => LicenseError::RateLimited { retry_after_secs },
+            500..=599 => LicenseError::Server { status },
+            _ => LicenseError::Http { status, message },
+        }
+    }
+
+    // Worth another attempt: the request may succeed unchanged
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            LicenseError::Network(_)
+                | LicenseError::Timeout
+                | LicenseError::RateLimited { .. }
+                | LicenseError::Server { .. }
+        )
+    }
+
+    fn from_reqwest(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            LicenseError::Timeout
+        } else {
+            LicenseError::Network(err.to_string())
+        }
+    }
+}
+
 // License manager state
 pub struct LicenseManager {
-    api_base_url: String,
+    config: LicenseConfig,
 }
 
 impl LicenseManager {
-    pub fn new() -> Self {
-        Self {
-            api_base_url: "https://api.whitespace.app/v1".to_string(),
-        }
+    pub fn with_config(config: LicenseConfig) -> Self {
+        Self { config }
     }
 
-    // Make API request with form data
+    // POST form data, retrying transient failures with backoff
     async fn make_api_request<T: for<'de> Deserialize<'de>>(
         &self,
         endpoint: &str,
         form_data: HashMap<String, String>,
-    ) -> Result<T, String> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/{}", self.api_base_url, endpoint);
+    ) -> Result<T, LicenseError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.config.timeout)
+            .build()
+            .map_err(LicenseError::from_reqwest)?;
+        let url = format!("{}/{}", self.config.api_base_url, endpoint);
+
+        let mut attempt = 0;
+        loop {
+            let (err, retry_after) = match Self::send(&client, &url, &form_data).await {
+                Ok(result) => return Ok(result),
+                Err(failure) => failure,
+            };
+            let too_long = retry_after.is_some_and(|wait| wait > MAX_RETRY_AFTER);
+            if !err.is_transient() || too_long || attempt >= self.config.max_retries {
+                return Err(err);
+            }
+            let delay = self.config.retry_delay(attempt, retry_after);
+            tracing::debug!(endpoint, attempt, ?delay, "license request failed: {}", err);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
 
+    async fn send<T: for<'de> Deserialize<'de>>(
+        client: &reqwest::Client,
+        url: &str,
+        form_data: &HashMap<String, String>,
+    ) -> Result<T, (LicenseError, Option<std::time::Duration>)> {
         let response = client
-            .post(&url)
-            .form(&form_data)
+            .post(url)
+            .form(form_data)
             .send()
             .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("API error: {}", response.status()));
+            .map_err(|e| (LicenseError::from_reqwest(e), None))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after_secs = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok());
+            let body = response.text().await.unwrap_or_default();
+            let err = LicenseError::from_status(status.as_u16(), &body, retry_after_secs);
+            return Err((err, retry_after_secs.map(std::time::Duration::from_secs)));
         }
 
-        let result: T = response
+        response
             .json()
             .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
-
-        Ok(result)
+            .map_err(|e| (LicenseError::InvalidResponse(e.to_string()), None))
     }
 
     // Activate license
@@ -94,7 +347,7 @@ impl LicenseManager {
         &self,
         license_key: &str,
         instance_name: &str,
-    ) -> Result<ActivateResp, String> {
+    ) -> Result<ActivateResp, LicenseError> {
         let mut form_data = HashMap::new();
         form_data.insert("license_key".to_string(), license_key.to_string());
         form_data.insert("instance_name".to_string(), instance_name.to_string());
@@ -107,7 +360,7 @@ impl LicenseManager {
         &self,
         license_key: &str,
         instance_id: &str,
-    ) -> Result<ValidateResp, String> {
+    ) -> Result<ValidateResp, LicenseError> {
         let mut form_data = HashMap::new();
         form_data.insert("license_key".to_string(), license_key.to_string());
         form_data.insert("instance_id".to_string(), instance_id.to_string());
@@ -120,7 +373,7 @@ impl LicenseManager {
         &self,
         license_key: &str,
         instance_id: &str,
-    ) -> Result<DeactivateResp, String> {
+    ) -> Result<DeactivateResp, LicenseError> {
         let mut form_data = HashMap::new();
         form_data.insert("license_key".to_string(), license_key.to_string());
         form_data.insert("instance_id".to_string(), instance_id.to_string());
@@ -139,17 +392,66 @@ pub struct LicenseCache {
     pub instance_name: Option<String>,
     pub last_validated_at: Option<i64>,
     pub status: Option<String>, // e.g. "valid" | "invalid" | "grace" | "deactivated"
+    pub expires_at: Option<i64>,
+    pub max_seats: Option<u32>,
+    pub used_seats: Option<u32>,
+    // Not persisted: when license://expiring last went out this session
+    pub expiry_warned_at: Option<i64>,
 }
 
-// Keychain storage for license data
+const PREF_LICENSE_KEY: &str = "license_key";
+const PREF_INSTANCE_ID: &str = "instance_id";
+const PREF_INSTANCE_NAME: &str = "instance_name";
+const PREF_LAST_VALIDATED: &str = "last_validated";
+const PREF_LICENSE_STATUS: &str = "license_status";
+const PREF_EXPIRES_AT: &str = "license_expires_at";
+const PREF_MAX_SEATS: &str = "license_max_seats";
+const PREF_USED_SEATS: &str = "license_used_seats";
+
+// License data cached in memory and mirrored to the prefs table
 pub struct LicenseStorage {
     pub cache: RwLock<LicenseCache>,
+    pool: DbPool,
+    config: LicenseConfig,
 }
 
 impl LicenseStorage {
-    pub fn new() -> Self {
+    // Load persisted license data and keep writing changes back to prefs
+    pub fn with_pool(pool: DbPool) -> Self {
+        let cache = match load_cache(&pool) {
+            Ok(cache) => cache,
+            Err(e) => {
+                tracing::warn!("Failed to load license cache: {}", e);
+                LicenseCache::default()
+            }
+        };
+        let pref_base_url = match load_base_url(&pool) {
+            Ok(url) => url,
+            Err(e) => {
+                tracing::warn!("Failed to load license API URL: {}", e);
+                None
+            }
+        };
         Self {
-            cache: RwLock::new(Default::default()),
+            cache: RwLock::new(cache),
+            pool,
+            config: LicenseConfig::resolve(pref_base_url),
+        }
+    }
+
+    // Client for the configured API; the URL is read once at startup
+    pub fn manager(&self) -> LicenseManager {
+        LicenseManager::with_config(self.config.clone())
+    }
+
+    async fn persist(&self, cache: &LicenseCache) {
+        let pool = self.pool.clone();
+        let snapshot = cache.clone();
+        let result = tokio::task::spawn_blocking(move || save_cache(&pool, &snapshot)).await;
+        match result {
+            Ok(Err(e)) => tracing::warn!("Failed to persist license cache: {}", e),
+            Err(e) => tracing::warn!("Failed to persist license cache: {}", e),
+            Ok(Ok(())) => {}
         }
     }
 
@@ -166,6 +468,7 @@ impl LicenseStorage {
         cache.instance_name = Some(instance_name.to_string());
         cache.last_validated_at = Some(now_ts());
         cache.status = Some("valid".to_string());
+        self.persist(&cache).await;
     }
 
     // Get license data from cache
@@ -192,6 +495,61 @@ impl LicenseStorage {
         cache.instance_name = None;
         cache.last_validated_at = None;
         cache.status = None;
+        cache.expires_at = None;
+        cache.max_seats = None;
+        cache.used_seats = None;
+        cache.expiry_warned_at = None;
+        self.persist(&cache).await;
+    }
+
+    // Keep expiry and seats from an API response; fields it omits stay as
+    // they were
+    pub async fn store_entitlements(
+        &self,
+        expires_at: Option<DateTime<Utc>>,
+        max_seats: Option<u32>,
+        used_seats: Option<u32>,
+    ) {
+        let mut cache = self.cache.write().await;
+        if let Some(expires_at) = expires_at {
+            if cache.expires_at != Some(expires_at.timestamp()) {
+                cache.expiry_warned_at = None;
+            }
+            cache.expires_at = Some(expires_at.timestamp());
+        }
+        cache.max_seats = max_seats.or(cache.max_seats);
+        cache.used_seats = used_seats.or(cache.used_seats);
+        self.persist(&cache).await;
+    }
+
+    // The expiry warning to send now, if the license ends within
+    // EXPIRY_WARNING_DAYS and none went out in the last day
+    pub async fn take_expiry_warning(&self, now: i64) -> Option<LicenseExpiringPayload> {
+        let mut cache = self.cache.write().await;
+        cache.license_key.as_ref()?;
+        let expires_at = cache.expires_at?;
+        let days_remaining = days_until(expires_at, now);
+        if days_remaining >= EXPIRY_WARNING_DAYS {
+            return None;
+        }
+        if cache.expiry_warned_at.is_some_and(|at| now - at < 24 * 60 * 60) {
+            return None;
+        }
+        cache.expiry_warned_at = Some(now);
+        Some(LicenseExpiringPayload {
+            expires_at: DateTime::from_timestamp(expires_at, 0)?,
+            days_remaining,
+        })
+    }
+
+    // Mark the license invalid without touching the last validation time
+    pub async fn mark_invalid(&self) {
+        let mut cache = self.cache.write().await;
+        if cache.status.as_deref() == Some("invalid") {
+            return;
+        }
+        cache.status = Some("invalid".to_string());
+        self.persist(&cache).await;
     }
 
     // Update license status
@@ -199,24 +557,93 @@ impl LicenseStorage {
         let mut cache = self.cache.write().await;
         cache.status = Some(status.to_string());
         cache.last_validated_at = Some(now_ts());
+        self.persist(&cache).await;
     }
 }
 
+fn load_cache(pool: &DbPool) -> Result<LicenseCache, String> {
+    let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+    let db = Database::new(conn);
+    let get = |key: &str| {
+        db.get_preference(key)
+            .map(|value| value.filter(|v| !v.is_empty()))
+            .map_err(|e| e.to_string())
+    };
+
+    Ok(LicenseCache {
+        license_key: get(PREF_LICENSE_KEY)?,
+        instance_id: get(PREF_INSTANCE_ID)?,
+        instance_name: get(PREF_INSTANCE_NAME)?,
+        last_validated_at: get(PREF_LAST_VALIDATED)?
+            .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+            .map(|dt| dt.timestamp()),
+        status: get(PREF_LICENSE_STATUS)?,
+        expires_at: get(PREF_EXPIRES_AT)?
+            .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+            .map(|dt| dt.timestamp()),
+        max_seats: get(PREF_MAX_SEATS)?.and_then(|v| v.parse().ok()),
+        used_seats: get(PREF_USED_SEATS)?.and_then(|v| v.parse().ok()),
+        expiry_warned_at: None,
+    })
+}
+
+fn load_base_url(pool: &DbPool) -> Result<Option<String>, String> {
+    let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+    Database::new(conn)
+        .get_preference(PREF_API_BASE_URL)
+        .map_err(|e| e.to_string())
+}
+
+fn save_cache(pool: &DbPool, cache: &LicenseCache) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| format!("db pool: {e}"))?;
+    let db = Database::new(conn);
+    let last_validated = cache
+        .last_validated_at
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.to_rfc3339());
+    let expires_at = cache
+        .expires_at
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.to_rfc3339());
+    let max_seats = cache.max_seats.map(|n| n.to_string());
+    let used_seats = cache.used_seats.map(|n| n.to_string());
+
+    let entries = [
+        (PREF_LICENSE_KEY, cache.license_key.as_deref()),
+        (PREF_INSTANCE_ID, cache.instance_id.as_deref()),
+        (PREF_INSTANCE_NAME, cache.instance_name.as_deref()),
+        (PREF_LAST_VALIDATED, last_validated.as_deref()),
+        (PREF_LICENSE_STATUS, cache.status.as_deref()),
+        (PREF_EXPIRES_AT, expires_at.as_deref()),
+        (PREF_MAX_SEATS, max_seats.as_deref()),
+        (PREF_USED_SEATS, used_seats.as_deref()),
+    ];
+    for (key, value) in entries {
+        match value {
+            Some(value) => db.set_preference(key, value),
+            None => db.delete_preference(key),
+        }
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 // Tauri Commands
 
 #[tauri::command]
 pub async fn ls_activate(
     license_key: String,
     instance_name: String,
+    app: AppHandle,
     state: State<'_, LicenseStorage>,
-) -> Result<ActivateResp, String> {
+) -> Result<ActivateResp, LicenseError> {
     // Validate inputs
     if license_key.trim().is_empty() {
-        return Err("License key cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("License key cannot be empty".to_string()));
     }
 
     if instance_name.trim().is_empty() {
-        return Err("Instance name cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("Instance name cannot be empty".to_string()));
     }
 
     // Sanitize inputs
@@ -224,7 +651,7 @@ pub async fn ls_activate(
     let instance_name = instance_name.trim().to_string();
 
     // Create license manager and attempt activation
-    let manager = LicenseManager::new();
+    let manager = state.manager();
     let response = manager.activate(&license_key, &instance_name).await?;
 
     if response.success {
@@ -233,6 +660,10 @@ pub async fn ls_activate(
             state
                 .store_license_data(&license_key, instance_id, &instance_name)
                 .await;
+            state
+                .store_entitlements(response.expires_at, response.max_seats, response.used_seats)
+                .await;
+            warn_if_expiring(&app, &state).await;
         }
     }
 
@@ -243,15 +674,16 @@ pub async fn ls_activate(
 pub async fn ls_validate(
     license_key: String,
     instance_id: String,
+    app: AppHandle,
     state: State<'_, LicenseStorage>,
-) -> Result<ValidateResp, String> {
+) -> Result<ValidateResp, LicenseError> {
     // Validate inputs
     if license_key.trim().is_empty() {
-        return Err("License key cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("License key cannot be empty".to_string()));
     }
 
     if instance_id.trim().is_empty() {
-        return Err("Instance ID cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("Instance ID cannot be empty".to_string()));
     }
 
     // Sanitize inputs
@@ -259,12 +691,16 @@ pub async fn ls_validate(
     let instance_id = instance_id.trim().to_string();
 
     // Create license manager and attempt validation
-    let manager = LicenseManager::new();
+    let manager = state.manager();
     let response = manager.validate(&license_key, &instance_id).await?;
 
     if response.success && response.valid {
         // Update license status in cache
         state.update_status("valid").await;
+        state
+            .store_entitlements(response.expires_at, response.max_seats, response.used_seats)
+            .await;
+        warn_if_expiring(&app, &state).await;
     }
 
     Ok(response)
@@ -275,14 +711,14 @@ pub async fn ls_deactivate(
     license_key: String,
     instance_id: String,
     state: State<'_, LicenseStorage>,
-) -> Result<DeactivateResp, String> {
+) -> Result<DeactivateResp, LicenseError> {
     // Validate inputs
     if license_key.trim().is_empty() {
-        return Err("License key cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("License key cannot be empty".to_string()));
     }
 
     if instance_id.trim().is_empty() {
-        return Err("Instance ID cannot be empty".to_string());
+        return Err(LicenseError::InvalidInput("Instance ID cannot be empty".to_string()));
     }
 
     // Sanitize inputs
@@ -290,7 +726,7 @@ pub async fn ls_deactivate(
     let instance_id = instance_id.trim().to_string();
 
     // Create license manager and attempt deactivation
-    let manager = LicenseManager::new();
+    let manager = state.manager();
     let response = manager.deactivate(&license_key, &instance_id).await?;
 
     if response.success {
@@ -302,65 +738,119 @@ pub async fn ls_deactivate(
 }
 
 #[tauri::command]
-pub async fn ls_get_status(state: State<'_, LicenseStorage>) -> Result<LicenseStatus, String> {
+pub async fn ls_get_status(
+    app: AppHandle,
+    state: State<'_, LicenseStorage>,
+) -> Result<LicenseStatus, String> {
+    let now = now_ts();
+    let (has_license, license_days) = {
+        let cache = state.cache.read().await;
+        (
+            cache.license_key.is_some() && cache.instance_id.is_some(),
+            cache.expires_at.map(|ts| days_until(ts, now)),
+        )
+    };
+    let grace = grace_window(state.get_last_validated().await, now);
+    let license_expired = license_days == Some(0);
+    if has_license && (grace.expired || license_expired) {
+        state.mark_invalid().await;
+    }
+    warn_if_expiring(&app, &state).await;
+
     let cache = state.cache.read().await;
+    let status_message = if license_expired && has_license {
+        "License expired".to_string()
+    } else if grace.expired && has_license {
+        "Offline grace period expired".to_string()
+    } else if grace.is_offline_grace {
+        "grace".to_string()
+    } else {
+        cache
+            .status
+            .clone()
+            .unwrap_or_else(|| "No license".to_string())
+    };
 
     // Create a basic status response
     let status = LicenseStatus {
-        is_licensed: cache.license_key.is_some() && cache.instance_id.is_some(),
+        is_licensed: has_license && !grace.expired && cache.status.as_deref() != Some("invalid"),
         license_key: cache.license_key.clone(),
         instance_id: cache.instance_id.clone(),
         instance_name: cache.instance_name.clone(),
-        expires_at: None, // TODO: implement expiration tracking
-        max_seats: None,  // TODO: implement seat tracking
-        used_seats: None, // TODO: implement seat tracking
+        expires_at: cache.expires_at.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        max_seats: cache.max_seats,
+        used_seats: cache.used_seats,
         last_validated: cache
             .last_validated_at
             .map(|ts| chrono::DateTime::from_timestamp(ts, 0).unwrap_or_default()),
-        is_offline_grace: false, // TODO: implement offline grace logic
-        grace_expires_at: None,
-        days_remaining: None,
-        status_message: cache
-            .status
-            .clone()
-            .unwrap_or_else(|| "No license".to_string()),
+        is_offline_grace: has_license && grace.is_offline_grace,
+        grace_expires_at: grace.grace_expires_at.filter(|_| has_license),
+        // Days until features lock: the sooner of grace end and expiry
+        days_remaining: [grace.days_remaining, license_days]
+            .into_iter()
+            .flatten()
+            .min()
+            .filter(|_| has_license),
+        status_message,
     };
 
     Ok(status)
 }
 
 #[tauri::command]
-pub async fn ls_check_validation_needed(state: State<'_, LicenseStorage>) -> Result<bool, String> {
-    let cache = state.cache.read().await;
+pub async fn ls_check_validation_needed(
+    state: State<'_, LicenseStorage>,
+) -> Result<ValidationCheck, String> {
+    let (has_license, last_validated) = {
+        let cache = state.cache.read().await;
+        (
+            cache.license_key.is_some() && cache.instance_id.is_some(),
+            cache.last_validated_at,
+        )
+    };
 
-    // Check if we have license data
-    if cache.license_key.is_none() || cache.instance_id.is_none() {
-        return Ok(true); // Need validation if no license data
+    // Need validation if no license data or never validated
+    if !has_license || last_validated.is_none() {
+        return Ok(ValidationCheck {
+            needed: true,
+            is_offline_grace: false,
+            grace_expires_at: None,
+            days_remaining: None,
+            expired: false,
+        });
     }
 
-    // Check if last validation was more than 7 days ago
-    if let Some(last_validated) = cache.last_validated_at {
-        let now = now_ts();
-        let days_since_validation = (now - last_validated) / (24 * 60 * 60);
-        Ok(days_since_validation >= 7)
-    } else {
-        Ok(true) // Never validated, needs validation
+    let grace = grace_window(last_validated, now_ts());
+    if grace.expired {
+        state.mark_invalid().await;
     }
+
+    // Past the weekly interval (grace or expired) a validation is due
+    Ok(ValidationCheck {
+        needed: grace.is_offline_grace || grace.expired,
+        is_offline_grace: grace.is_offline_grace,
+        grace_expires_at: grace.grace_expires_at,
+        days_remaining: grace.days_remaining,
+        expired: grace.expired,
+    })
 }
 
 #[tauri::command]
-pub async fn ls_auto_validate(state: State<'_, LicenseStorage>) -> Result<ValidateResp, String> {
+pub async fn ls_auto_validate(
+    app: AppHandle,
+    state: State<'_, LicenseStorage>,
+) -> Result<ValidateResp, LicenseError> {
     let (license_key, instance_id, _) = state.get_license_data().await;
 
     if license_key.is_none() || instance_id.is_none() {
-        return Err("No license data found".to_string());
+        return Err(LicenseError::NotActivated);
     }
 
     let license_key = license_key.unwrap();
     let instance_id = instance_id.unwrap();
 
     // Perform validation
-    ls_validate(license_key, instance_id, state).await
+    ls_validate(license_key, instance_id, app, state).await
 }
 
 #[tauri::command]
@@ -369,9 +859,11 @@ pub async fn ls_clear_license(state: State<'_, LicenseStorage>) -> Result<(), St
     Ok(())
 }
 
-// Helper function to create license storage
-pub fn create_license_storage() -> LicenseStorage {
-    LicenseStorage::new()
+// Tell the UI the license ends soon, at most once a day
+async fn warn_if_expiring(app: &AppHandle, state: &LicenseStorage) {
+    if let Some(payload) = state.take_expiry_warning(now_ts()).await {
+        let _ = app.emit(LICENSE_EXPIRING_EVENT, payload);
+    }
 }
 
 fn now_ts() -> i64 {
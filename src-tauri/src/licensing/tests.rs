@@ -1,431 +1,379 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::Database;
-    use chrono::{Duration, Utc};
-    use std::path::PathBuf;
-    use tempfile::TempDir;
-
-    fn setup_test_db() -> (TempDir, Database) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let db = Database::open_db(&db_path).unwrap();
-        db.run_migrations().unwrap();
-        (temp_dir, db)
-    }
+use super::*;
+use crate::db::pool::init_pool;
+use tauri::async_runtime::block_on;
+use tauri::test::mock_app;
+use tauri::Manager;
+use tempfile::TempDir;
+
+/// Storage backed by a migrated database in a temp dir. The pool is handed
+/// back so tests can reopen the storage as the next launch would.
+fn setup_test_storage() -> (TempDir, DbPool, LicenseStorage) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    Database::open_db(&db_path).unwrap().run_migrations().unwrap();
+    let pool = init_pool(&db_path);
+    let storage = LicenseStorage::with_pool(pool.clone());
+    (temp_dir, pool, storage)
+}
 
-    #[test]
-    fn test_license_manager_new() {
-        let manager = LicenseManager::new();
-        assert_eq!(manager.api_base_url, "https://api.whitespace.app/v1");
-    }
+/// Runs `ls_check_validation_needed` against `storage` through a mock app.
+fn check_validation(storage: LicenseStorage) -> ValidationCheck {
+    let app = mock_app();
+    app.manage(storage);
+    block_on(ls_check_validation_needed(app.state())).unwrap()
+}
 
-    #[test]
-    fn test_license_storage_new() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-        assert!(storage.db.get_preference("license_key").is_ok());
-    }
+#[test]
+fn test_license_config_defaults() {
+    let config = LicenseConfig::default();
+    assert_eq!(config.api_base_url, DEFAULT_API_BASE_URL);
+    assert_eq!(config.max_retries, 3);
+}
 
-    #[test]
-    fn test_license_storage_store_and_get() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+#[test]
+fn test_license_storage_store_and_get() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    block_on(async {
+        assert_eq!(storage.get_license_data().await, (None, None, None));
 
-        // Store license data
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
+            .await;
 
-        // Retrieve license data
-        let (license_key, instance_id, instance_name) = storage.get_license_data().unwrap();
+        let (license_key, instance_id, instance_name) = storage.get_license_data().await;
         assert_eq!(license_key, Some("test-key".to_string()));
         assert_eq!(instance_id, Some("test-instance".to_string()));
         assert_eq!(instance_name, Some("Test Device".to_string()));
-    }
-
-    #[test]
-    fn test_license_storage_clear() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+        assert!(storage.get_last_validated().await.is_some());
+        assert_eq!(storage.cache.read().await.status.as_deref(), Some("valid"));
+    });
+}
 
-        // Store license data
+#[test]
+fn test_license_storage_clear() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    block_on(async {
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-
-        // Clear license data
-        storage.clear_license_data().unwrap();
-
-        // Verify data is cleared
-        let (license_key, instance_id, instance_name) = storage.get_license_data().unwrap();
-        assert_eq!(license_key, Some("".to_string()));
-        assert_eq!(instance_id, Some("".to_string()));
-        assert_eq!(instance_name, Some("".to_string()));
-    }
-
-    #[test]
-    fn test_license_storage_details() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-
-        let expires_at = Utc::now() + Duration::days(30);
-
-        // Store license details
+            .await;
         storage
-            .store_license_details(Some(expires_at), Some(5), Some(2))
-            .unwrap();
-
-        // Retrieve license details
-        let (stored_expires_at, max_seats, used_seats) = storage.get_license_details().unwrap();
-        assert!(stored_expires_at.is_some());
-        assert_eq!(max_seats, Some(5));
-        assert_eq!(used_seats, Some(2));
-    }
-
-    #[test]
-    fn test_license_checker_new() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-        let checker = LicenseChecker::new(storage);
-        assert!(checker.is_license_valid().is_ok());
-    }
-
-    #[test]
-    fn test_license_checker_no_license() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-        let checker = LicenseChecker::new(storage);
-
-        let is_valid = checker.is_license_valid().unwrap();
-        assert!(!is_valid);
-    }
-
-    #[test]
-    fn test_license_checker_with_license() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-
-        // Store license data
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let is_valid = checker.is_license_valid().unwrap();
-        assert!(is_valid); // Should be valid due to offline grace
-    }
-
-    #[test]
-    fn test_offline_grace_period() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+            .store_entitlements(Some(Utc::now() + Duration::days(30)), Some(5), Some(2))
+            .await;
 
-        // Store license data with recent validation
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let now = Utc::now().to_rfc3339();
-        storage.db.set_preference("last_validated", &now).unwrap();
+        storage.clear_license_data().await;
 
-        let checker = LicenseChecker::new(storage);
-        let is_in_grace = checker.is_in_offline_grace().unwrap();
-        assert!(is_in_grace);
-    }
+        assert_eq!(storage.get_license_data().await, (None, None, None));
+        assert!(storage.get_last_validated().await.is_none());
+        let cache = storage.cache.read().await;
+        assert_eq!((cache.expires_at, cache.max_seats, cache.used_seats), (None, None, None));
+    });
+}
 
-    #[test]
-    fn test_offline_grace_expired() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+#[test]
+fn test_license_storage_entitlements_keep_omitted_fields() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    let expires_at = DateTime::from_timestamp(1_700_000_000, 0);
+    block_on(async {
+        storage.store_entitlements(expires_at, Some(5), Some(2)).await;
+        storage.store_entitlements(None, None, Some(3)).await;
+
+        let cache = storage.cache.read().await;
+        assert_eq!(cache.expires_at, Some(1_700_000_000));
+        assert_eq!((cache.max_seats, cache.used_seats), (Some(5), Some(3)));
+    });
+}
 
-        // Store license data with old validation (beyond grace period)
+#[test]
+fn test_license_storage_persists_across_restarts() {
+    let (_temp_dir, pool, storage) = setup_test_storage();
+    let expires_at = DateTime::from_timestamp(1_700_000_000, 0);
+    block_on(async {
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let old_time = (Utc::now() - Duration::days(15)).to_rfc3339();
-        storage
-            .db
-            .set_preference("last_validated", &old_time)
-            .unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let is_in_grace = checker.is_in_offline_grace().unwrap();
-        assert!(!is_in_grace);
-    }
-
-    #[test]
-    fn test_license_status_unlicensed() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-        let checker = LicenseChecker::new(storage);
-
-        let status = checker.get_license_status().unwrap();
-        assert!(!status.is_licensed);
-        assert!(!status.is_offline_grace);
-        assert_eq!(status.status_message, "No license found");
-    }
+            .await;
+        storage.store_entitlements(expires_at, Some(5), Some(2)).await;
+    });
 
-    #[test]
-    fn test_license_status_licensed() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+    let reopened = LicenseStorage::with_pool(pool.clone());
+    block_on(async {
+        let (license_key, instance_id, instance_name) = reopened.get_license_data().await;
+        assert_eq!(license_key, Some("test-key".to_string()));
+        assert_eq!(instance_id, Some("test-instance".to_string()));
+        assert_eq!(instance_name, Some("Test Device".to_string()));
+        assert_eq!(
+            reopened.get_last_validated().await,
+            storage.get_last_validated().await
+        );
+        let cache = reopened.cache.read().await;
+        assert_eq!(cache.expires_at, Some(1_700_000_000));
+        assert_eq!((cache.max_seats, cache.used_seats), (Some(5), Some(2)));
+    });
+
+    block_on(reopened.clear_license_data());
+    let cleared = LicenseStorage::with_pool(pool);
+    assert_eq!(block_on(cleared.get_license_data()), (None, None, None));
+}
 
-        // Store license data
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let expires_at = Utc::now() + Duration::days(30);
-        storage
-            .store_license_details(Some(expires_at), Some(5), Some(2))
-            .unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
-
-        assert!(status.is_licensed);
-        assert_eq!(status.license_key, Some("test-key".to_string()));
-        assert_eq!(status.instance_id, Some("test-instance".to_string()));
-        assert_eq!(status.instance_name, Some("Test Device".to_string()));
-        assert_eq!(status.max_seats, Some(5));
-        assert_eq!(status.used_seats, Some(2));
-        assert!(status.days_remaining.is_some());
+#[test]
+fn test_license_storage_reads_saved_base_url() {
+    if std::env::var_os(API_BASE_URL_ENV).is_some() {
+        return;
     }
+    let (_temp_dir, pool, _storage) = setup_test_storage();
+    Database::new(pool.get().unwrap())
+        .set_preference(PREF_API_BASE_URL, "https://staging.example.com/v1")
+        .unwrap();
 
-    #[test]
-    fn test_license_status_offline_grace() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+    let storage = LicenseStorage::with_pool(pool);
+    assert_eq!(storage.config.api_base_url, "https://staging.example.com/v1");
+}
 
-        // Store license data with recent validation
+#[test]
+fn test_mark_invalid_keeps_last_validation() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    block_on(async {
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let now = Utc::now().to_rfc3339();
-        storage.db.set_preference("last_validated", &now).unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
-
-        assert!(status.is_licensed);
-        assert!(status.is_offline_grace);
-        assert_eq!(status.status_message, "Offline grace period active");
-        assert!(status.grace_expires_at.is_some());
-    }
-
-    #[test]
-    fn test_needs_validation_never_validated() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-        let checker = LicenseChecker::new(storage);
+            .await;
+        let validated = storage.get_last_validated().await;
 
-        let needs_validation = checker.needs_validation().unwrap();
-        assert!(needs_validation);
-    }
+        storage.mark_invalid().await;
 
-    #[test]
-    fn test_needs_validation_recent() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-
-        // Store recent validation
-        let now = Utc::now().to_rfc3339();
-        storage.db.set_preference("last_validated", &now).unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let needs_validation = checker.needs_validation().unwrap();
-        assert!(!needs_validation);
-    }
-
-    #[test]
-    fn test_needs_validation_old() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-
-        // Store old validation (beyond 7 days)
-        let old_time = (Utc::now() - Duration::days(8)).to_rfc3339();
-        storage
-            .db
-            .set_preference("last_validated", &old_time)
-            .unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let needs_validation = checker.needs_validation().unwrap();
-        assert!(needs_validation);
-    }
-
-    #[test]
-    fn test_activate_resp_serialization() {
-        let response = ActivateResp {
-            success: true,
-            instance_id: Some("test-instance".to_string()),
-            message: "Activation successful".to_string(),
-            expires_at: Some(Utc::now() + Duration::days(30)),
-            max_seats: Some(5),
-            used_seats: Some(2),
-        };
-
-        let json = serde_json::to_string(&response).unwrap();
-        assert!(json.contains("success"));
-        assert!(json.contains("instance_id"));
-        assert!(json.contains("message"));
-    }
-
-    #[test]
-    fn test_validate_resp_serialization() {
-        let response = ValidateResp {
-            success: true,
-            valid: true,
-            message: "Validation successful".to_string(),
-            expires_at: Some(Utc::now() + Duration::days(30)),
-            max_seats: Some(5),
-            used_seats: Some(2),
-            instance_name: Some("Test Device".to_string()),
-        };
-
-        let json = serde_json::to_string(&response).unwrap();
-        assert!(json.contains("success"));
-        assert!(json.contains("valid"));
-        assert!(json.contains("message"));
-    }
-
-    #[test]
-    fn test_deactivate_resp_serialization() {
-        let response = DeactivateResp {
-            success: true,
-            message: "Deactivation successful".to_string(),
-        };
+        assert_eq!(storage.cache.read().await.status.as_deref(), Some("invalid"));
+        assert_eq!(storage.get_last_validated().await, validated);
+    });
+}
 
-        let json = serde_json::to_string(&response).unwrap();
-        assert!(json.contains("success"));
-        assert!(json.contains("message"));
-    }
+#[test]
+fn test_needs_validation_without_license() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    let check = check_validation(storage);
+    assert!(check.needed);
+    assert!(!check.is_offline_grace);
+    assert!(!check.expired);
+}
 
-    #[test]
-    fn test_license_status_serialization() {
-        let status = LicenseStatus {
-            is_licensed: true,
-            license_key: Some("test-key".to_string()),
-            instance_id: Some("test-instance".to_string()),
-            instance_name: Some("Test Device".to_string()),
-            expires_at: Some(Utc::now() + Duration::days(30)),
-            max_seats: Some(5),
-            used_seats: Some(2),
-            last_validated: Some(Utc::now()),
-            is_offline_grace: false,
-            grace_expires_at: None,
-            days_remaining: Some(30),
-            status_message: "License valid".to_string(),
-        };
-
-        let json = serde_json::to_string(&status).unwrap();
-        assert!(json.contains("is_licensed"));
-        assert!(json.contains("license_key"));
-        assert!(json.contains("status_message"));
-    }
+#[test]
+fn test_needs_validation_recent() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    block_on(storage.store_license_data("test-key", "test-instance", "Test Device"));
 
-    #[test]
-    fn test_create_license_storage() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = create_license_storage(db);
+    let check = check_validation(storage);
+    assert!(!check.needed);
+    assert!(!check.is_offline_grace);
+    assert_eq!(check.days_remaining, Some(OFFLINE_GRACE_DAYS));
+}
 
-        // Test that storage works
+#[test]
+fn test_needs_validation_in_offline_grace() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    block_on(async {
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let (license_key, instance_id, instance_name) = storage.get_license_data().unwrap();
-        assert_eq!(license_key, Some("test-key".to_string()));
-        assert_eq!(instance_id, Some("test-instance".to_string()));
-        assert_eq!(instance_name, Some("Test Device".to_string()));
-    }
-
-    #[test]
-    fn test_license_status_expired() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+            .await;
+        let eight_days_ago = (Utc::now() - Duration::days(8)).timestamp();
+        storage.cache.write().await.last_validated_at = Some(eight_days_ago);
+    });
+
+    let check = check_validation(storage);
+    assert!(check.needed);
+    assert!(check.is_offline_grace);
+    assert!(!check.expired);
+    assert_eq!(check.days_remaining, Some(6));
+}
 
-        // Store expired license
+#[test]
+fn test_needs_validation_grace_expired_marks_invalid() {
+    let (_temp_dir, pool, storage) = setup_test_storage();
+    block_on(async {
         storage
             .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let expired_time = Utc::now() - Duration::days(1);
-        storage
-            .store_license_details(Some(expired_time), Some(5), Some(2))
-            .unwrap();
-
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
+            .await;
+        let fifteen_days_ago = (Utc::now() - Duration::days(15)).timestamp();
+        storage.cache.write().await.last_validated_at = Some(fifteen_days_ago);
+    });
+
+    let check = check_validation(storage);
+    assert!(check.needed);
+    assert!(check.expired);
+    assert!(!check.is_offline_grace);
+    let reopened = LicenseStorage::with_pool(pool);
+    assert_eq!(block_on(reopened.cache.read()).status.as_deref(), Some("invalid"));
+}
 
-        assert!(status.is_licensed);
-        assert_eq!(status.status_message, "License expired");
-        assert!(status.days_remaining.is_some());
-        assert!(status.days_remaining.unwrap() < 0);
-    }
+#[test]
+fn test_activate_resp_serialization() {
+    let response = ActivateResp {
+        success: true,
+        instance_id: Some("test-instance".to_string()),
+        message: "Activation successful".to_string(),
+        expires_at: Some(Utc::now() + Duration::days(30)),
+        max_seats: Some(5),
+        used_seats: Some(2),
+    };
+
+    let json = serde_json::to_string(&response).unwrap();
+    assert!(json.contains("success"));
+    assert!(json.contains("instance_id"));
+    assert!(json.contains("message"));
+}
 
-    #[test]
-    fn test_license_status_expiring_soon() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+#[test]
+fn test_validate_resp_serialization() {
+    let response = ValidateResp {
+        success: true,
+        valid: true,
+        message: "Validation successful".to_string(),
+        expires_at: Some(Utc::now() + Duration::days(30)),
+        max_seats: Some(5),
+        used_seats: Some(2),
+        instance_name: Some("Test Device".to_string()),
+    };
+
+    let json = serde_json::to_string(&response).unwrap();
+    assert!(json.contains("success"));
+    assert!(json.contains("valid"));
+    assert!(json.contains("message"));
+}
 
-        // Store license expiring in 3 days
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let expiring_time = Utc::now() + Duration::days(3);
-        storage
-            .store_license_details(Some(expiring_time), Some(5), Some(2))
-            .unwrap();
+#[test]
+fn test_deactivate_resp_serialization() {
+    let response = DeactivateResp {
+        success: true,
+        message: "Deactivation successful".to_string(),
+    };
 
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
+    let json = serde_json::to_string(&response).unwrap();
+    assert!(json.contains("success"));
+    assert!(json.contains("message"));
+}
 
-        assert!(status.is_licensed);
-        assert_eq!(status.status_message, "License valid");
-        assert_eq!(status.days_remaining, Some(3));
-    }
+#[test]
+fn test_license_status_serialization() {
+    let status = LicenseStatus {
+        is_licensed: true,
+        license_key: Some("test-key".to_string()),
+        instance_id: Some("test-instance".to_string()),
+        instance_name: Some("Test Device".to_string()),
+        expires_at: Some(Utc::now() + Duration::days(30)),
+        max_seats: Some(5),
+        used_seats: Some(2),
+        last_validated: Some(Utc::now()),
+        is_offline_grace: false,
+        grace_expires_at: None,
+        days_remaining: Some(30),
+        status_message: "License valid".to_string(),
+    };
+
+    let json = serde_json::to_string(&status).unwrap();
+    assert!(json.contains("is_licensed"));
+    assert!(json.contains("license_key"));
+    assert!(json.contains("status_message"));
+}
 
-    #[test]
-    fn test_license_status_unknown() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
+#[test]
+fn test_grace_window_recently_validated() {
+    let now = 1_700_000_000;
+    let grace = crate::licensing::grace_window(Some(now - 2 * 86_400), now);
+    assert!(!grace.is_offline_grace);
+    assert!(!grace.expired);
+    assert_eq!(grace.days_remaining, Some(12));
+}
 
-        // Store license without expiration
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
+#[test]
+fn test_grace_window_in_offline_grace() {
+    let now = 1_700_000_000;
+    let grace = crate::licensing::grace_window(Some(now - 10 * 86_400), now);
+    assert!(grace.is_offline_grace);
+    assert!(!grace.expired);
+    assert_eq!(grace.days_remaining, Some(4));
+}
 
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
+#[test]
+fn test_grace_window_expired() {
+    let now = 1_700_000_000;
+    let grace = crate::licensing::grace_window(Some(now - 15 * 86_400), now);
+    assert!(!grace.is_offline_grace);
+    assert!(grace.expired);
+    assert_eq!(grace.days_remaining, Some(0));
+}
 
-        assert!(status.is_licensed);
-        assert_eq!(status.status_message, "License status unknown");
-        assert!(status.days_remaining.is_none());
+#[test]
+fn test_license_config_prefers_saved_base_url() {
+    if std::env::var_os(crate::licensing::API_BASE_URL_ENV).is_some() {
+        return;
     }
+    let config = LicenseConfig::resolve(Some("https://staging.example.com/v1/".to_string()));
+    assert_eq!(config.api_base_url, "https://staging.example.com/v1");
+    let config = LicenseConfig::resolve(Some("  ".to_string()));
+    assert_eq!(config.api_base_url, crate::licensing::DEFAULT_API_BASE_URL);
+}
 
-    #[test]
-    fn test_grace_period_calculation() {
-        let (temp_dir, db) = setup_test_db();
-        let storage = LicenseStorage::new(db);
-
-        // Store license with validation 5 days ago
-        storage
-            .store_license_data("test-key", "test-instance", "Test Device")
-            .unwrap();
-        let validation_time = (Utc::now() - Duration::days(5)).to_rfc3339();
-        storage
-            .db
-            .set_preference("last_validated", &validation_time)
-            .unwrap();
+#[test]
+fn test_retry_delay_backs_off_with_jitter() {
+    let config = LicenseConfig::default();
+    for attempt in 0..3 {
+        let base = config.backoff * 2u32.pow(attempt);
+        let delay = config.retry_delay(attempt, None);
+        assert!(delay >= base && delay <= base + base / 2);
+    }
+    let wait = std::time::Duration::from_secs(7);
+    assert_eq!(config.retry_delay(0, Some(wait)), wait);
+}
 
-        let checker = LicenseChecker::new(storage);
-        let status = checker.get_license_status().unwrap();
+#[test]
+fn test_license_error_from_status() {
+    let body = r#"{"message":"All seats are in use"}"#;
+    assert_eq!(
+        LicenseError::from_status(409, body, None),
+        LicenseError::SeatLimit("All seats are in use".to_string())
+    );
+    assert_eq!(
+        LicenseError::from_status(401, "revoked", None),
+        LicenseError::Unauthorized("revoked".to_string())
+    );
+    assert_eq!(
+        LicenseError::from_status(429, "", Some(12)),
+        LicenseError::RateLimited { retry_after_secs: Some(12) }
+    );
+    assert_eq!(
+        LicenseError::from_status(503, "", None),
+        LicenseError::Server { status: 503 }
+    );
+    assert!(LicenseError::Server { status: 503 }.is_transient());
+    assert!(!LicenseError::NotFound(String::new()).is_transient());
+
+    let json = serde_json::to_value(LicenseError::Server { status: 502 }).unwrap();
+    assert_eq!(json["kind"], "server");
+    assert_eq!(json["detail"]["status"], 502);
+}
 
-        assert!(status.is_licensed);
-        assert!(status.is_offline_grace);
-        assert!(status.grace_expires_at.is_some());
+#[test]
+fn test_days_until_rounds_up() {
+    let now = 1_700_000_000;
+    assert_eq!(crate::licensing::days_until(now + 86_400 + 1, now), 2);
+    assert_eq!(crate::licensing::days_until(now + 86_400, now), 1);
+    assert_eq!(crate::licensing::days_until(now - 5, now), 0);
+}
 
-        // Grace should expire in 9 days (14 - 5)
-        let grace_remaining = status.grace_expires_at.unwrap() - Utc::now();
-        assert!(grace_remaining.num_days() <= 9);
-        assert!(grace_remaining.num_days() >= 8);
-    }
+#[test]
+fn test_expiry_warning_sent_once_a_day() {
+    let (_temp_dir, _pool, storage) = setup_test_storage();
+    let now = 1_700_000_000;
+    tauri::async_runtime::block_on(async {
+        storage.store_license_data("key", "inst", "Mac").await;
+        let expires_at = DateTime::from_timestamp(now + 30 * 86_400, 0);
+        storage.store_entitlements(expires_at, Some(3), Some(1)).await;
+        assert!(storage.take_expiry_warning(now).await.is_none());
+
+        let expires_at = DateTime::from_timestamp(now + 5 * 86_400, 0);
+        storage.store_entitlements(expires_at, None, None).await;
+        let warning = storage.take_expiry_warning(now).await.unwrap();
+        assert_eq!(warning.days_remaining, 5);
+        assert!(storage.take_expiry_warning(now + 3_600).await.is_none());
+        assert!(storage.take_expiry_warning(now + 86_400).await.is_some());
+
+        let cache = storage.cache.read().await;
+        assert_eq!((cache.max_seats, cache.used_seats), (Some(3), Some(1)));
+    });
 }
@@ -0,0 +1,139 @@
+use once_cell::sync::OnceCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+pub const LOG_LEVEL_PREF: &str = "log_level";
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+pub const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+/// Upper bound for `get_logs` so the UI never pulls whole log files.
+pub const MAX_TAIL_LINES: usize = 5_000;
+
+const LOG_FILE_PREFIX: &str = "white-space";
+const LOG_FILE_SUFFIX: &str = "log";
+/// Daily files kept before the oldest is removed.
+const MAX_LOG_FILES: usize = 7;
+
+struct LoggingRuntime {
+    dir: PathBuf,
+    level: reload::Handle<LevelFilter, Registry>,
+    // Flushes the background writer when the app exits.
+    _guard: WorkerGuard,
+}
+
+static LOGGING: OnceCell<LoggingRuntime> = OnceCell::new();
+
+pub fn log_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("logs")
+}
+
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "error" => Some(LevelFilter::ERROR),
+        "warn" => Some(LevelFilter::WARN),
+        "info" => Some(LevelFilter::INFO),
+        "debug" => Some(LevelFilter::DEBUG),
+        "trace" => Some(LevelFilter::TRACE),
+        _ => None,
+    }
+}
+
+/// Installs the global subscriber: a daily rotating file under `dir` plus
+/// stderr for development. Calling it again is a no-op.
+pub fn init(dir: &Path, level: &str) -> anyhow::Result<()> {
+    if LOGGING.get().is_some() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let (filter, handle) = reload::Layer::new(parse_level(level).unwrap_or(LevelFilter::INFO));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init()?;
+
+    let _ = LOGGING.set(LoggingRuntime {
+        dir: dir.to_path_buf(),
+        level: handle,
+        _guard: guard,
+    });
+    Ok(())
+}
+
+/// Changes the level of the running subscriber.
+pub fn set_level(level: &str) -> anyhow::Result<()> {
+    let filter = parse_level(level).ok_or_else(|| anyhow::anyhow!("unknown log level {level}"))?;
+    if let Some(runtime) = LOGGING.get() {
+        runtime.level.modify(|current| *current = filter)?;
+    }
+    Ok(())
+}
+
+pub fn current_dir() -> Option<PathBuf> {
+    LOGGING.get().map(|runtime| runtime.dir.clone())
+}
+
+/// The last `lines` lines across the rotated files, oldest first.
+pub fn tail(dir: &Path, lines: usize) -> anyhow::Result<Vec<String>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX))
+        })
+        .collect();
+    // Rotated names end in the date, so newest sorts last.
+    files.sort();
+
+    let mut collected: Vec<String> = Vec::new();
+    for file in files.iter().rev() {
+        if collected.len() >= lines {
+            break;
+        }
+        let content = fs::read_to_string(file)?;
+        let needed = lines - collected.len();
+        let mut chunk: Vec<String> = content
+            .lines()
+            .rev()
+            .take(needed)
+            .map(str::to_string)
+            .collect();
+        chunk.reverse();
+        chunk.append(&mut collected);
+        collected = chunk;
+    }
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tail_reads_across_rotated_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("white-space.2024-05-01.log"), "a\nb\nc\n").unwrap();
+        fs::write(dir.path().join("white-space.2024-05-02.log"), "d\ne\n").unwrap();
+        fs::write(dir.path().join("other.txt"), "x\n").unwrap();
+
+        assert_eq!(tail(dir.path(), 3).unwrap(), vec!["c", "d", "e"]);
+        assert_eq!(tail(dir.path(), 10).unwrap().len(), 5);
+        assert_eq!(parse_level("WARN"), Some(LevelFilter::WARN));
+        assert_eq!(parse_level("verbose"), None);
+    }
+}
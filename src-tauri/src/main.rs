@@ -1,119 +1,12 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-mod commands;
-mod db;
-mod gauge;
-mod licensing;
-mod models;
-mod ops;
-mod scanner;
-mod selector;
 
-use db::{init_pool, DbPool};
-use licensing::LicenseStorage;
-use std::path::PathBuf;
-use tauri::Manager;
-use scanner::watcher;
-
-// Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
-
-fn app_db_path() -> PathBuf {
-    let app_data_dir = dirs::data_dir().expect("Failed to get app data directory");
-    let app_dir = app_data_dir.join("white-space");
-    std::fs::create_dir_all(&app_dir).expect("Failed to create app directory");
-    app_dir.join("database.db")
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_notification::init())
-        .setup(|app| {
-            // Initialize database pool
-            let db_path = app_db_path();
-            println!("Database path: {:?}", db_path);
-            println!(
-                "Database directory exists: {}",
-                db_path.parent().unwrap().exists()
-            );
-            let pool = init_pool(&db_path);
-
-            // Run migrations on first connection
-            {
-                let conn = pool.get().expect("Failed to get database connection");
-                let db = db::Database::new(conn);
-                if let Err(e) = db.run_migrations() {
-                    eprintln!("Database migration error: {}", e);
-                    eprintln!("This might be due to database file permissions or corruption.");
-                    eprintln!("Try deleting the database file and restarting the application.");
-                    return Err(e.into());
-                }
-            }
-
-            let pool_for_watcher = pool.clone();
-
-            app.manage::<DbPool>(pool);
-
-            // Initialize licensing storage (Send+Sync)
-            app.manage(LicenseStorage {
-                cache: tokio::sync::RwLock::new(Default::default()),
-            });
-
-            if let Err(err) = watcher::start_watchers(app.handle().clone(), pool_for_watcher) {
-                eprintln!("File watcher failed to start: {err}");
-            }
-
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            greet,
-            commands::add_folder,
-            commands::list_folders,
-            commands::remove_folder,
-            commands::pick_directory,
-            commands::list_dir,
-            commands::open_in_system,
-            commands::get_platform_info,
-            commands::scan_roots,
-            commands::start_scan,
-            commands::rescan_all,
-            commands::rescan_folder,
-            commands::scan_status,
-            commands::get_candidates,
-            commands::daily_candidates,
-            commands::get_candidates_bucketed,
-            commands::gauge_state,
-            commands::list_staged,
-            commands::stage_files,
-            commands::restore_staged,
-            commands::empty_staged,
-            commands::get_duplicate_groups,
-            commands::archive_files,
-            commands::delete_files,
-            commands::undo_last,
-            commands::get_review_items,
-            commands::get_thumbnail,
-            commands::get_prefs,
-            commands::set_prefs,
-            licensing::ls_activate,
-            licensing::ls_validate,
-            licensing::ls_deactivate,
-            licensing::ls_get_status,
-            licensing::ls_check_validation_needed,
-            licensing::ls_auto_validate,
-            licensing::ls_clear_license
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+use white_space_lib::cli;
 
 fn main() {
-    run();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some(cli::HEADLESS_FLAG) {
+        std::process::exit(cli::run(&args[1..]));
+    }
+    white_space_lib::run();
 }
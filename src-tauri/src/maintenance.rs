@@ -0,0 +1,257 @@
+use crate::db::{Database, DbPool};
+use crate::ops;
+use crate::scanner;
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub const COMPACT_PROGRESS_EVENT: &str = "maintenance://compact-progress";
+pub const COMPACT_DONE_EVENT: &str = "maintenance://compact-done";
+/// Days of actions, metrics and scan history to keep; 0 keeps everything.
+pub const HISTORY_RETENTION_PREF: &str = "history_retention_days";
+/// When set, removing a watched folder drops its files from the index
+/// instead of only hiding them.
+pub const PURGE_REMOVED_ROOTS_PREF: &str = "purge_removed_roots";
+
+/// Free pages left by deleted rows must reach both limits before an
+/// automatic compaction runs; small files are not worth the rewrite.
+const AUTO_COMPACT_MIN_FREE_BYTES: u64 = 32 * 1024 * 1024;
+const AUTO_COMPACT_MIN_FREE_RATIO: f64 = 0.25;
+const AUTO_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Gives startup scans and watchers a head start before the first check.
+const AUTO_CHECK_DELAY: Duration = Duration::from_secs(5 * 60);
+
+const COMPACT_STEPS: [&str; 3] = ["vacuum", "analyze", "checkpoint"];
+
+static COMPACTING: AtomicBool = AtomicBool::new(false);
+static SCHEDULER_STARTED: OnceCell<()> = OnceCell::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactProgressPayload {
+    pub step: &'static str,
+    pub index: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactReport {
+    /// `manual` or `auto`.
+    pub trigger: String,
+    /// Main file plus WAL, in bytes.
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub reclaimed_bytes: u64,
+    pub duration_ms: u64,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistoryCleared {
+    pub actions_removed: usize,
+    pub metrics_removed: usize,
+    pub scans_removed: usize,
+    pub files_removed: usize,
+}
+
+impl HistoryCleared {
+    pub fn total(&self) -> usize {
+        self.actions_removed + self.metrics_removed + self.scans_removed + self.files_removed
+    }
+}
+
+/// Releases the compaction slot when dropped.
+struct CompactGuard;
+
+impl CompactGuard {
+    fn acquire() -> Option<Self> {
+        COMPACTING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| CompactGuard)
+    }
+}
+
+impl Drop for CompactGuard {
+    fn drop(&mut self) {
+        COMPACTING.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn is_compacting() -> bool {
+    COMPACTING.load(Ordering::SeqCst)
+}
+
+pub fn should_auto_compact(free_bytes: u64, total_bytes: u64) -> bool {
+    total_bytes > 0
+        && free_bytes >= AUTO_COMPACT_MIN_FREE_BYTES
+        && free_bytes as f64 / total_bytes as f64 >= AUTO_COMPACT_MIN_FREE_RATIO
+}
+
+fn on_disk_size(db_path: Option<&Path>) -> u64 {
+    let Some(path) = db_path else {
+        return 0;
+    };
+    let wal = format!("{}-wal", path.display());
+    [path, Path::new(&wal)]
+        .iter()
+        .filter_map(|file| fs::metadata(file).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Runs VACUUM, ANALYZE and a truncating WAL checkpoint on `db`, calling
+/// `on_step` before each one.
+pub fn compact(
+    db: &Database,
+    trigger: &str,
+    mut on_step: impl FnMut(CompactProgressPayload),
+) -> anyhow::Result<CompactReport> {
+    let started = Instant::now();
+    let db_path = db.file_path()?;
+    let before_bytes = on_disk_size(db_path.as_deref());
+
+    for (index, &step) in COMPACT_STEPS.iter().enumerate() {
+        on_step(CompactProgressPayload {
+            step,
+            index,
+            total: COMPACT_STEPS.len(),
+        });
+        match step {
+            "vacuum" => db.vacuum()?,
+            "analyze" => db.analyze()?,
+            _ => db.checkpoint_truncate()?,
+        }
+    }
+
+    let after_bytes = on_disk_size(db_path.as_deref());
+    Ok(CompactReport {
+        trigger: trigger.to_string(),
+        before_bytes,
+        after_bytes,
+        reclaimed_bytes: before_bytes.saturating_sub(after_bytes),
+        duration_ms: started.elapsed().as_millis() as u64,
+        finished_at: Utc::now(),
+    })
+}
+
+/// Deletes actions, metrics and scan records from before `cutoff`.
+pub fn clear_history(db: &Database, cutoff: &DateTime<Utc>) -> anyhow::Result<HistoryCleared> {
+    Ok(HistoryCleared {
+        actions_removed: db.prune_actions_before(cutoff)?,
+        metrics_removed: db.prune_metrics_before(cutoff)?,
+        scans_removed: db.prune_scan_records(cutoff, scanner::SCAN_HISTORY_MAX_ROWS)?,
+        files_removed: 0,
+    })
+}
+
+/// Applies the retention pref, if one is set.
+pub fn apply_retention(db: &Database) -> anyhow::Result<HistoryCleared> {
+    let days: i64 = db
+        .get_preference(HISTORY_RETENTION_PREF)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    if days <= 0 {
+        return Ok(HistoryCleared::default());
+    }
+    clear_history(db, &(Utc::now() - chrono::Duration::days(days)))
+}
+
+/// Compacts through a pool connection and reports progress and the result
+/// as events. Only one compaction runs at a time.
+pub fn run_compaction<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    pool: &DbPool,
+    trigger: &str,
+) -> anyhow::Result<CompactReport> {
+    let _guard = CompactGuard::acquire()
+        .ok_or_else(|| anyhow::anyhow!("a compaction is already running"))?;
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+    let db = Database::new(conn);
+
+    let span = tracing::info_span!("compact", trigger);
+    let _entered = span.enter();
+    let report = compact(&db, trigger, |payload| {
+        let _ = app.emit(COMPACT_PROGRESS_EVENT, payload);
+    })?;
+    tracing::info!(
+        before = report.before_bytes,
+        after = report.after_bytes,
+        duration_ms = report.duration_ms,
+        "database compacted"
+    );
+    let _ = app.emit(COMPACT_DONE_EVENT, report.clone());
+    Ok(report)
+}
+
+/// Compacts when deleted rows have left enough free pages behind and no
+/// scan or file operation is writing. Returns the report if it ran.
+pub fn maybe_auto_compact<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    pool: &DbPool,
+) -> anyhow::Result<Option<CompactReport>> {
+    if is_compacting()
+        || scanner::current_status().state != "idle"
+        || !ops::cancel::active_batch_ids().is_empty()
+    {
+        return Ok(None);
+    }
+    let (free_bytes, total_bytes) = {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+        Database::new(conn).free_space()?
+    };
+    if !should_auto_compact(free_bytes, total_bytes) {
+        return Ok(None);
+    }
+    run_compaction(app, pool, "auto").map(Some)
+}
+
+fn run_retention(pool: &DbPool) -> anyhow::Result<()> {
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+    let cleared = apply_retention(&Database::new(conn))?;
+    if cleared.total() > 0 {
+        tracing::info!(?cleared, "applied history retention");
+    }
+    Ok(())
+}
+
+/// Applies history retention and checks for reclaimable space shortly after
+/// launch and then periodically.
+pub fn start_maintenance_scheduler<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    if SCHEDULER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(AUTO_CHECK_DELAY);
+        loop {
+            if let Err(err) = run_retention(&pool) {
+                tracing::error!("history retention failed: {err}");
+            }
+            if let Err(err) = maybe_auto_compact(&app, &pool) {
+                tracing::error!("automatic compaction failed: {err}");
+            }
+            std::thread::sleep(AUTO_CHECK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_compact_thresholds() {
+        const MB: u64 = 1024 * 1024;
+        assert!(should_auto_compact(40 * MB, 100 * MB));
+        // Plenty of free space in relative terms, but too little to matter.
+        assert!(!should_auto_compact(2 * MB, 4 * MB));
+        // Large in absolute terms, but a small share of the file.
+        assert!(!should_auto_compact(40 * MB, 1000 * MB));
+        assert!(!should_auto_compact(0, 0));
+    }
+}
@@ -19,6 +19,20 @@ pub struct File {
     pub is_deleted: bool,
     pub is_staged: bool,
     pub cooloff_until: Option<DateTime<Utc>>,
+    /// Cloud-synced file whose data is not stored locally.
+    #[serde(default)]
+    pub is_cloud_placeholder: bool,
+    /// Lives under a root whose volume is currently unavailable.
+    #[serde(default)]
+    pub is_offline: bool,
+    /// Symbolic link, recorded without following it.
+    #[serde(default)]
+    pub is_symlink: bool,
+    #[serde(default)]
+    pub symlink_target: Option<String>,
+    /// Algorithm behind `partial_sha1` and `sha1`.
+    #[serde(default = "HashAlgo::legacy")]
+    pub hash_algo: HashAlgo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +56,9 @@ pub enum ActionType {
     Delete,
     #[serde(rename = "restore")]
     Restore,
+    /// The scanner found the file at a new path; recorded without a batch.
+    #[serde(rename = "move")]
+    Move,
 }
 
 impl std::fmt::Display for ActionType {
@@ -50,6 +67,7 @@ impl std::fmt::Display for ActionType {
             ActionType::Archive => write!(f, "archive"),
             ActionType::Delete => write!(f, "delete"),
             ActionType::Restore => write!(f, "restore"),
+            ActionType::Move => write!(f, "move"),
         }
     }
 }
@@ -62,6 +80,7 @@ impl std::str::FromStr for ActionType {
             "archive" => Ok(ActionType::Archive),
             "delete" => Ok(ActionType::Delete),
             "restore" => Ok(ActionType::Restore),
+            "move" => Ok(ActionType::Move),
             _ => Err(format!("Invalid action type: {}", s)),
         }
     }
@@ -97,6 +116,43 @@ pub struct WeeklyTotals {
     pub restored_files: i64,
 }
 
+/// Active file count and bytes for one mime family (`images`, `video`,
+/// `audio`, `archives`, `code`, `documents` or `other`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MimeFamilyTotals {
+    pub family: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
+/// Timestamp an age histogram is measured from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeMetric {
+    #[default]
+    Modified,
+    Accessed,
+}
+
+impl AgeMetric {
+    pub fn column(self) -> &'static str {
+        match self {
+            AgeMetric::Modified => "modified_at",
+            AgeMetric::Accessed => "accessed_at",
+        }
+    }
+}
+
+/// Active files whose age falls in `[min_days, max_days)`; the last bin
+/// is open-ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgeHistogramBin {
+    pub min_days: i64,
+    pub max_days: Option<i64>,
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileAgeStats {
     pub age_days: i64,
@@ -114,6 +170,18 @@ pub struct NewFile {
     pub accessed_at: Option<DateTime<Utc>>,
     pub partial_sha1: Option<String>,
     pub sha1: Option<String>,
+    /// `device:inode` on Unix; `None` where the platform gives no stable id.
+    pub inode_key: Option<String>,
+    pub link_count: i64,
+    /// Hex dHash for images, see `scanner::hash::dhash`.
+    pub phash: Option<String>,
+    pub is_cloud_placeholder: bool,
+    /// Watched root the file was found under; `None` keeps the stored one.
+    pub root_id: Option<i64>,
+    pub is_symlink: bool,
+    /// Where a symlink points, as stored in the link.
+    pub symlink_target: Option<String>,
+    pub hash_algo: HashAlgo,
 }
 
 #[derive(Debug, Clone)]
@@ -139,6 +207,363 @@ pub struct WatchedRoot {
     pub id: i64,
     pub path: String,
     pub created_at: DateTime<Utc>,
+    pub volume_kind: VolumeKind,
+    /// The root's drive or share is not mounted; its files are kept as is.
+    pub is_offline: bool,
+}
+
+/// Algorithm a file's content hashes were computed with. Despite their
+/// names, the `partial_sha1` and `sha1` columns hold either kind; rows
+/// hashed before BLAKE3 keep SHA1 until they are hashed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgo {
+    Sha1,
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Used for everything hashed from now on.
+    pub const CURRENT: HashAlgo = HashAlgo::Blake3;
+
+    /// What rows without a recorded algorithm were hashed with.
+    pub fn legacy() -> Self {
+        HashAlgo::Sha1
+    }
+}
+
+impl std::fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashAlgo::Sha1 => write!(f, "sha1"),
+            HashAlgo::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+impl std::str::FromStr for HashAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha1" => Ok(HashAlgo::Sha1),
+            "blake3" => Ok(HashAlgo::Blake3),
+            _ => Err(format!("Invalid hash algorithm: {}", s)),
+        }
+    }
+}
+
+/// Kind of volume a watched root lives on, detected when the root is added.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeKind {
+    #[default]
+    Local,
+    Network,
+    Removable,
+}
+
+impl VolumeKind {
+    pub fn is_local(self) -> bool {
+        self == VolumeKind::Local
+    }
+}
+
+impl std::fmt::Display for VolumeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VolumeKind::Local => write!(f, "local"),
+            VolumeKind::Network => write!(f, "network"),
+            VolumeKind::Removable => write!(f, "removable"),
+        }
+    }
+}
+
+impl std::str::FromStr for VolumeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(VolumeKind::Local),
+            "network" => Ok(VolumeKind::Network),
+            "removable" => Ok(VolumeKind::Removable),
+            _ => Err(format!("Invalid volume kind: {}", s)),
+        }
+    }
+}
+
+/// Scan settings kept with a watched root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootSettings {
+    pub path: String,
+    /// Hours between scheduled rescans; `None` scans only on demand.
+    pub scan_interval_hours: Option<u32>,
+    /// Whether filesystem changes under the root are picked up live.
+    pub watcher_enabled: bool,
+    /// Scans the root with a single worker at background I/O priority.
+    pub low_priority_io: bool,
+    /// Reads only the head of each file; duplicates are not confirmed.
+    pub partial_hash_only: bool,
+    pub last_scanned_at: Option<DateTime<Utc>>,
+    /// Detected when the root was added; not editable.
+    pub volume_kind: VolumeKind,
+}
+
+/// Glob pattern excluded from indexing. A `None` root applies to every root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exclusion {
+    pub id: i64,
+    pub root_path: Option<String>,
+    pub pattern: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// File or folder that must never be suggested, archived or deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectedPath {
+    pub id: i64,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Candidate the user dismissed. `until` of `None` means "never suggest".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DismissedFile {
+    pub file_id: i64,
+    pub path: String,
+    pub dismissed_at: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// What the user made of a suggestion.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FeedbackVerdict {
+    #[serde(rename = "kept")]
+    Kept,
+    #[serde(rename = "deleted")]
+    Deleted,
+    #[serde(rename = "wrong_suggestion")]
+    WrongSuggestion,
+}
+
+impl std::fmt::Display for FeedbackVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedbackVerdict::Kept => write!(f, "kept"),
+            FeedbackVerdict::Deleted => write!(f, "deleted"),
+            FeedbackVerdict::WrongSuggestion => write!(f, "wrong_suggestion"),
+        }
+    }
+}
+
+impl std::str::FromStr for FeedbackVerdict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kept" => Ok(FeedbackVerdict::Kept),
+            "deleted" => Ok(FeedbackVerdict::Deleted),
+            "wrong_suggestion" => Ok(FeedbackVerdict::WrongSuggestion),
+            _ => Err(format!("Invalid feedback verdict: {}", s)),
+        }
+    }
+}
+
+/// Feedback totals for one bucket.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BucketFeedback {
+    pub kept: u32,
+    pub deleted: u32,
+    pub wrong_suggestion: u32,
+}
+
+/// User-defined candidate bucket. Filters left unset match every file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRule {
+    pub id: i64,
+    pub name: String,
+    pub path_glob: Option<String>,
+    pub min_size_bytes: Option<i64>,
+    pub min_age_days: Option<i64>,
+    pub mime_filter: Option<String>,
+    pub max_per_day: i64,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewUserRule {
+    pub name: String,
+    pub path_glob: Option<String>,
+    pub min_size_bytes: Option<i64>,
+    pub min_age_days: Option<i64>,
+    pub mime_filter: Option<String>,
+    pub max_per_day: Option<i64>,
+    pub enabled: Option<bool>,
+}
+
+/// What one archive or delete batch was for and how big it was. `origin`
+/// names the flow that ran it (`stage`, `bucket`, `dedupe`, `expiry`, ...);
+/// `label` is the user's own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub batch_id: String,
+    pub action_type: String,
+    pub origin: String,
+    pub label: Option<String>,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewBatch {
+    pub batch_id: String,
+    pub action_type: ActionType,
+    pub origin: String,
+    pub label: Option<String>,
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
+/// Optional narrowing for `list_batches`. `label` matches any part of the
+/// label, ignoring case.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchFilters {
+    pub action_type: Option<String>,
+    pub origin: Option<String>,
+    pub label: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+/// Optional narrowing for `search_files`. `staged` of `None` matches both
+/// staged and unstaged files; deleted rows are hidden unless asked for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileSearchFilters {
+    pub min_size_bytes: Option<i64>,
+    pub max_size_bytes: Option<i64>,
+    pub mime: Option<String>,
+    pub root_path: Option<String>,
+    pub staged: Option<bool>,
+    pub include_deleted: Option<bool>,
+    /// Last used (opened, accessed, modified, then created) at least this
+    /// many days ago.
+    pub min_age_days: Option<i64>,
+}
+
+/// A named search kept for later: the query and filters `search_files`
+/// takes. With `notify_threshold` set, a scan that brings the match count up
+/// to it sends a notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedView {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    pub filters: FileSearchFilters,
+    pub notify_threshold: Option<i64>,
+    /// Match count after the last scan, `None` until a scan has run.
+    pub last_count: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewSavedView {
+    pub name: String,
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub filters: FileSearchFilters,
+    pub notify_threshold: Option<i64>,
+}
+
+/// A named set of files gathered across views, acted on in one go. Lives
+/// only for the current session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionSummary {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Position after the last item of a page, handed to the UI as an opaque
+/// token. `key` and `text` hold that item's sort key and `id` breaks ties.
+/// Files first seen after `snapshot_ms` stay out of later pages, so a scan
+/// finishing mid-browse neither repeats nor skips items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub sort: String,
+    pub key: Option<f64>,
+    pub text: Option<String>,
+    pub id: i64,
+    pub snapshot_ms: i64,
+}
+
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        use base64::Engine;
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        use base64::Engine;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| "invalid cursor".to_string())
+    }
+
+    pub fn snapshot(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.snapshot_ms).unwrap_or_else(Utc::now)
+    }
+}
+
+/// What the selector's buckets can match, so only rows that might land in
+/// one are loaded. Each set field adds rows; the selector still makes the
+/// exact call on everything that comes back.
+#[derive(Debug, Clone, Default)]
+pub struct BucketPrefilter {
+    /// Paths mentioning "screenshot" or "screen shot".
+    pub screenshots: bool,
+    /// Files in a Downloads folder larger than this.
+    pub big_download_min_bytes: Option<i64>,
+    /// Anything in a Desktop folder.
+    pub desktop: bool,
+    /// Files in a Downloads folder with one of these extensions.
+    pub installer_extensions: Vec<String>,
+    /// Files up to this size whose full hash another active file shares.
+    pub duplicate_max_bytes: Option<i64>,
+    pub symlinks: bool,
+    /// Minimum size and mime filter of each user rule. A rule with neither
+    /// can match any file.
+    pub rules: Vec<(Option<i64>, Option<String>)>,
+    /// Keep files already staged in the archive.
+    pub include_staged: bool,
+    /// Keep files marked deleted.
+    pub include_deleted: bool,
+}
+
+/// Thresholds the built-in bucket checks use, for counting matches in SQL
+/// without loading the files.
+#[derive(Debug, Clone, Default)]
+pub struct BucketStatsQuery {
+    pub screenshot_min_age_days: u32,
+    pub big_download_min_bytes: i64,
+    pub big_download_min_age_days: u32,
+    pub old_desktop_min_age_days: u32,
+    /// Empty when the installers bucket is off.
+    pub installer_extensions: Vec<String>,
+    pub installer_min_age_days: u32,
+    pub duplicate_max_bytes: i64,
+    pub protected: Vec<String>,
+    /// Files opened within this many days are never suggested.
+    pub recent_open_grace_days: i64,
+    /// Files restored within this many days are never suggested; 0 = off.
+    pub restore_grace_days: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -161,3 +586,51 @@ pub struct NewStagedFile {
     pub status: String,
     pub note: Option<String>,
 }
+
+/// One finished, cancelled or failed scan. `roots` are the paths walked,
+/// which for a folder rescan is a subtree of a watched root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub id: i64,
+    pub roots: Vec<String>,
+    /// "manual", "watcher", "scheduled" or "cli".
+    pub trigger: String,
+    /// "completed", "cancelled" or "failed".
+    pub status: String,
+    /// "normal", or "throttled" for a battery-friendly scan.
+    pub mode: String,
+    pub counted: u64,
+    pub skipped: u64,
+    pub errors: u64,
+    /// First error message, to explain a failed or partial scan.
+    pub error_sample: Option<String>,
+    pub duration_ms: u64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewScanRecord {
+    pub roots: Vec<String>,
+    pub trigger: String,
+    pub status: String,
+    pub mode: String,
+    pub counted: u64,
+    pub skipped: u64,
+    pub errors: u64,
+    pub error_sample: Option<String>,
+    pub duration_ms: u64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// What claiming an idempotency key found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// First use of the key; the caller runs the operation.
+    Claimed,
+    /// Another call with the key is still running.
+    Pending { command: String },
+    /// The key already finished; `outcome` is the stored JSON result.
+    Completed { command: String, outcome: String },
+}
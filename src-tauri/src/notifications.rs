@@ -0,0 +1,370 @@
+use crate::db::Database;
+use crate::deep_link::{DeepLinkTarget, DeepLinkView};
+use crate::gauge;
+use crate::ops::expiry::StagedExpiredPayload;
+use crate::tidy::TidyReminderPayload;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+/// Emitted alongside every OS notification so the UI can open `link` when
+/// the user comes back to the app from it.
+pub const NOTIFICATION_EVENT: &str = "notifications://sent";
+
+pub const NOTIFICATIONS_ENABLED_PREF: &str = "notifications_enabled";
+pub const NOTIFY_SCAN_THRESHOLD_PREF: &str = "notify_scan_threshold_gb";
+pub const NOTIFY_EXPIRY_PREF: &str = "notify_expiry";
+pub const NOTIFY_AUTO_EMPTY_PREF: &str = "notify_auto_empty";
+pub const NOTIFY_TIDY_PREF: &str = "notify_tidy_reminder";
+
+/// Candidate bytes seen after the previous scan.
+const LAST_CANDIDATE_BYTES_PREF: &str = "notify_last_candidate_bytes";
+/// Batches already warned about, as a JSON array of batch ids.
+const EXPIRY_WARNED_PREF: &str = "notify_expiry_warned";
+const EXPIRY_WARNING_HOURS: i64 = 24;
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    ScanCandidates,
+    ExpiringSoon,
+    AutoEmptied,
+    TidyReminder,
+    SavedView,
+}
+
+impl NotificationKind {
+    fn pref(self) -> &'static str {
+        match self {
+            NotificationKind::ScanCandidates => NOTIFY_SCAN_THRESHOLD_PREF,
+            NotificationKind::ExpiringSoon => NOTIFY_EXPIRY_PREF,
+            NotificationKind::AutoEmptied => NOTIFY_AUTO_EMPTY_PREF,
+            NotificationKind::TidyReminder => NOTIFY_TIDY_PREF,
+            // Each view opts in through its own threshold.
+            NotificationKind::SavedView => NOTIFICATIONS_ENABLED_PREF,
+        }
+    }
+
+    /// The view a notification of this kind opens, narrowed to its batch
+    /// when it has one.
+    fn target(self, batch_id: Option<&str>) -> DeepLinkTarget {
+        let view = match self {
+            NotificationKind::ScanCandidates => DeepLinkView::Review,
+            NotificationKind::ExpiringSoon => DeepLinkView::Staged,
+            NotificationKind::AutoEmptied => DeepLinkView::History,
+            NotificationKind::TidyReminder => DeepLinkView::Review,
+            NotificationKind::SavedView => DeepLinkView::Review,
+        };
+        match batch_id {
+            Some(batch_id) => DeepLinkTarget::new(view).with("batch", batch_id),
+            None => DeepLinkTarget::new(view),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationPayload {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    /// View the UI should open for this notification.
+    pub route: String,
+    /// The same view as a `whitespace://` link.
+    pub link: String,
+    pub batch_id: Option<String>,
+}
+
+fn pref_bool(db: &Database, key: &str, default: bool) -> bool {
+    db.get_preference(key)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn kind_enabled(db: &Database, kind: NotificationKind) -> bool {
+    if !pref_bool(db, NOTIFICATIONS_ENABLED_PREF, true) {
+        return false;
+    }
+    match kind {
+        NotificationKind::ScanCandidates => scan_threshold_gb(db) > 0,
+        NotificationKind::SavedView => true,
+        _ => pref_bool(db, kind.pref(), true),
+    }
+}
+
+/// GB of new candidates a scan must surface before it is worth a
+/// notification; 0 turns scan notifications off.
+pub fn scan_threshold_gb(db: &Database) -> u32 {
+    db.get_preference(NOTIFY_SCAN_THRESHOLD_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1)
+}
+
+fn send<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    batch_id: Option<String>,
+) {
+    send_with_target(app, kind, title, body, batch_id, None);
+}
+
+/// Like [`send`], opening `target` instead of the kind's default view.
+fn send_with_target<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    batch_id: Option<String>,
+    target: Option<DeepLinkTarget>,
+) {
+    if let Err(err) = app
+        .notification()
+        .builder()
+        .title(&title)
+        .body(&body)
+        .show()
+    {
+        tracing::warn!("Failed to show notification: {err}");
+    }
+    let target = target.unwrap_or_else(|| kind.target(batch_id.as_deref()));
+    let payload = NotificationPayload {
+        kind,
+        title,
+        body,
+        route: target.route.clone(),
+        link: target.url(),
+        batch_id,
+    };
+    let _ = app.emit(NOTIFICATION_EVENT, payload);
+}
+
+/// New candidate bytes since the previous scan, if they reach the threshold.
+fn candidate_growth(previous: u64, current: u64, threshold_gb: u32) -> Option<u64> {
+    let grown = current.saturating_sub(previous);
+    (threshold_gb > 0 && grown >= u64::from(threshold_gb) * GIB).then_some(grown)
+}
+
+/// Called after a completed scan. Compares today's candidate total with the
+/// one recorded after the previous scan and notifies on a large jump.
+pub fn notify_scan_finished<R: tauri::Runtime>(app: &AppHandle<R>, db: &Database) {
+    let current = match gauge::cached_gauge_state(db) {
+        Ok(state) => state.potential_today_bytes,
+        Err(err) => {
+            tracing::warn!("Failed to compute candidates for notification: {err}");
+            return;
+        }
+    };
+    let previous = db
+        .get_preference(LAST_CANDIDATE_BYTES_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let _ = db.set_preference(LAST_CANDIDATE_BYTES_PREF, &current.to_string());
+
+    let kind = NotificationKind::ScanCandidates;
+    if !kind_enabled(db, kind) {
+        return;
+    }
+    if let Some(grown) = candidate_growth(previous, current, scan_threshold_gb(db)) {
+        send(
+            app,
+            kind,
+            "Scan complete".to_string(),
+            format!(
+                "Found {:.1} GB of new files to review",
+                grown as f64 / GIB as f64
+            ),
+            None,
+        );
+    }
+}
+
+/// Whether a view's match count went from below `threshold` to at or above
+/// it. A view that has never been counted starts from zero.
+fn view_threshold_crossed(previous: Option<i64>, current: i64, threshold: i64) -> bool {
+    previous.unwrap_or(0) < threshold && current >= threshold
+}
+
+/// Called after a completed scan. Recounts every saved view with a
+/// threshold and notifies for those whose count just reached it.
+pub fn notify_saved_views<R: tauri::Runtime>(app: &AppHandle<R>, db: &Database) {
+    let views = match db.list_saved_views() {
+        Ok(views) => views,
+        Err(err) => {
+            tracing::warn!("Failed to list saved views: {err}");
+            return;
+        }
+    };
+    let watched = match db.list_watched_paths() {
+        Ok(watched) => watched,
+        Err(err) => {
+            tracing::warn!("Failed to list watched roots: {err}");
+            return;
+        }
+    };
+    let kind = NotificationKind::SavedView;
+    let enabled = kind_enabled(db, kind);
+    for view in views {
+        let Some(threshold) = view.notify_threshold else {
+            continue;
+        };
+        let roots = match view.filters.root_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(root) if watched.iter().any(|w| std::path::Path::new(root).starts_with(w)) => {
+                vec![root.to_string()]
+            }
+            Some(_) => Vec::new(),
+            None => watched.clone(),
+        };
+        let count = match db.count_search_matches(&view.query, &view.filters, &roots) {
+            Ok(count) => count as i64,
+            Err(err) => {
+                tracing::warn!(view = view.id, "Failed to count saved view: {err}");
+                continue;
+            }
+        };
+        let _ = db.set_saved_view_count(view.id, count);
+        if enabled && view_threshold_crossed(view.last_count, count, threshold) {
+            let target = kind.target(None).with("saved_view", view.id.to_string());
+            send_with_target(
+                app,
+                kind,
+                format!("\"{}\" has {} matches", view.name, count),
+                format!("{count} file(s) now match this saved view"),
+                None,
+                Some(target),
+            );
+        }
+    }
+}
+
+/// Warns once per batch when staged files are within a day of expiring.
+pub fn notify_expiring_batches<R: tauri::Runtime>(app: &AppHandle<R>, db: &Database) {
+    let kind = NotificationKind::ExpiringSoon;
+    if !kind_enabled(db, kind) {
+        return;
+    }
+    let now = Utc::now();
+    let cutoff = now + Duration::hours(EXPIRY_WARNING_HOURS);
+    let expiring = match db.staged_batches_expiring(&now, &cutoff) {
+        Ok(expiring) => expiring,
+        Err(err) => {
+            tracing::warn!("Failed to list expiring batches: {err}");
+            return;
+        }
+    };
+
+    let mut warned: HashSet<String> = db
+        .get_preference(EXPIRY_WARNED_PREF)
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    let current: HashSet<&str> = expiring.iter().map(|(id, _, _)| id.as_str()).collect();
+    // Forget batches that expired or were restored so the set stays small.
+    warned.retain(|id| current.contains(id.as_str()));
+
+    for (batch_id, file_count, total_bytes) in expiring {
+        if !warned.insert(batch_id.clone()) {
+            continue;
+        }
+        send(
+            app,
+            kind,
+            "Staged files expire soon".to_string(),
+            format!(
+                "{} staged file(s) ({:.1} MB) leave the cooloff within {} hours",
+                file_count,
+                total_bytes as f64 / (1024.0 * 1024.0),
+                EXPIRY_WARNING_HOURS
+            ),
+            Some(batch_id),
+        );
+    }
+    if let Ok(raw) = serde_json::to_string(&warned) {
+        let _ = db.set_preference(EXPIRY_WARNED_PREF, &raw);
+    }
+}
+
+pub fn notify_auto_emptied<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    db: &Database,
+    payload: &StagedExpiredPayload,
+) {
+    let kind = NotificationKind::AutoEmptied;
+    if !payload.auto_emptied || payload.files_emptied == 0 || !kind_enabled(db, kind) {
+        return;
+    }
+    send(
+        app,
+        kind,
+        "Expired files emptied".to_string(),
+        format!(
+            "Moved {} expired file(s) to the trash, freeing {:.1} MB",
+            payload.files_emptied,
+            payload.bytes_freed as f64 / (1024.0 * 1024.0)
+        ),
+        payload.batch_id.clone(),
+    );
+}
+
+pub fn notify_tidy_reminder<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    db: &Database,
+    payload: &TidyReminderPayload,
+) {
+    let kind = NotificationKind::TidyReminder;
+    if !kind_enabled(db, kind) {
+        return;
+    }
+    let body = if payload.candidate_count == 0 {
+        "Nothing new to review this week".to_string()
+    } else {
+        format!(
+            "{} file(s) ({:.1} MB) ready to review",
+            payload.candidate_count,
+            payload.candidate_bytes as f64 / (1024.0 * 1024.0)
+        )
+    };
+    send(app, kind, "Time to tidy up".to_string(), body, None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_growth_respects_threshold() {
+        assert_eq!(candidate_growth(0, 2 * GIB, 1), Some(2 * GIB));
+        assert_eq!(candidate_growth(GIB, GIB + GIB / 2, 1), None);
+        // Shrinking candidate totals never notify.
+        assert_eq!(candidate_growth(5 * GIB, GIB, 1), None);
+        assert_eq!(candidate_growth(0, 10 * GIB, 0), None);
+    }
+
+    #[test]
+    fn test_view_threshold_only_fires_on_crossing() {
+        assert!(view_threshold_crossed(None, 12, 10));
+        assert!(view_threshold_crossed(Some(9), 10, 10));
+        // Staying above the threshold stays quiet until it drops back below.
+        assert!(!view_threshold_crossed(Some(11), 15, 10));
+        assert!(!view_threshold_crossed(Some(15), 3, 10));
+        assert!(view_threshold_crossed(Some(3), 10, 10));
+    }
+
+    #[test]
+    fn test_expiry_notifications_link_to_their_batch() {
+        let target = NotificationKind::ExpiringSoon.target(Some("batch_7"));
+        assert_eq!(target.route, "/archive");
+        assert_eq!(target.url(), "whitespace://staged?batch=batch_7");
+        assert_eq!(NotificationKind::ScanCandidates.target(None).url(), "whitespace://review");
+    }
+}
@@ -1,18 +1,33 @@
 use crate::db::Database;
-use crate::models::{ActionType, NewAction};
+use crate::i18n::Message;
+use crate::models::{ActionType, HashAlgo, NewAction, NewBatch, NewStagedFile};
+use crate::ops::cancel;
+use crate::ops::compress::{self, ArchiveCompression, ZipBatch};
 use crate::ops::error::{OpsError, OpsResult};
-use crate::ops::space::SpaceManager;
+use crate::ops::progress::{ProgressCallback, ProgressReporter};
+use crate::ops::space::{SpaceCheck, SpaceManager};
+use crate::ops::system_paths::ensure_not_system_path;
+use crate::scanner::{hash, CancellationToken};
 use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Preference holding the [`ArchiveCompression`] mode for new batches.
+pub const ARCHIVE_COMPRESSION_PREF: &str = "archive_compression";
+/// Preference holding a user-chosen archive location; unset or empty means
+/// the default under the home directory.
+pub const ARCHIVE_PATH_PREF: &str = "archive_path";
+
 #[derive(Debug, Clone)]
 pub struct ArchiveConfig {
     pub base_path: PathBuf,
     pub date_format: String,
     pub free_space_buffer: f64,  // Percentage (5.0 = 5%)
     pub progress_threshold: u64, // Bytes (500MB)
+    pub compression: ArchiveCompression,
 }
 
 impl Default for ArchiveConfig {
@@ -22,11 +37,74 @@ impl Default for ArchiveConfig {
             date_format: "%Y-%m-%d".to_string(),
             free_space_buffer: 5.0,
             progress_threshold: 500 * 1024 * 1024, // 500MB
+            compression: ArchiveCompression::None,
         }
     }
 }
 
 impl ArchiveConfig {
+    /// Defaults overlaid with the archive preferences stored in `db`.
+    pub fn load(db: &Database) -> OpsResult<Self> {
+        let mut config = Self::default();
+        if let Some(value) = db.get_preference(ARCHIVE_COMPRESSION_PREF)? {
+            config.compression = value.parse().unwrap_or_default();
+        }
+        if let Some(value) = db.get_preference(ARCHIVE_PATH_PREF)? {
+            if !value.trim().is_empty() {
+                config.base_path = PathBuf::from(value.trim());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Checks that `path` can hold the archive: absolute, neither inside nor
+    /// around a watched root (or archived files would be rescanned as
+    /// clutter, or the root archived along with them) and writable. Creates
+    /// the directory if it does not exist yet.
+    pub fn validate_base_path(path: &Path, watched_roots: &[String]) -> OpsResult<()> {
+        let traverses = path
+            .components()
+            .any(|component| matches!(component, std::path::Component::ParentDir));
+        if !path.is_absolute() || traverses {
+            return Err(OpsError::InvalidPath(format!(
+                "Archive location must be an absolute path: {}",
+                path.display()
+            )));
+        }
+        if let Some(root) = watched_roots
+            .iter()
+            .find(|root| path.starts_with(Path::new(root.as_str())))
+        {
+            return Err(OpsError::InvalidPath(format!(
+                "Archive location cannot be inside the watched folder {}",
+                root
+            )));
+        }
+        if let Some(root) = watched_roots
+            .iter()
+            .find(|root| Path::new(root.as_str()).starts_with(path))
+        {
+            return Err(OpsError::InvalidPath(format!(
+                "Archive location cannot contain the watched folder {}",
+                root
+            )));
+        }
+
+        fs::create_dir_all(path).map_err(|e| {
+            OpsError::PermissionError(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+        let probe = path.join(".test_write_permission");
+        fs::write(&probe, "test").map_err(|e| {
+            OpsError::PermissionError(format!("Cannot write to {}: {}", path.display(), e))
+        })?;
+        let _ = fs::remove_file(&probe);
+        Ok(())
+    }
+
+    pub fn default_base_path() -> PathBuf {
+        Self::get_default_archive_path()
+    }
+
     fn get_default_archive_path() -> PathBuf {
         if let Some(home) = dirs::home_dir() {
             #[cfg(target_os = "windows")]
@@ -63,11 +141,55 @@ pub struct ArchiveResult {
     pub total_bytes: u64,
     pub duration_ms: u64,
     pub errors: Vec<String>,
+    /// Per-file failures as catalog messages, with the file in `path`.
+    pub error_messages: Vec<Message>,
+    pub cancelled: bool,
+    /// Zip holding the batch when compression is on.
+    pub archive_file: Option<String>,
+    /// `total_bytes` minus the size of the zip; 0 for uncompressed batches.
+    pub bytes_saved: u64,
+}
+
+/// Outcome of moving the archive to a new location.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveMigration {
+    pub from_path: String,
+    pub to_path: String,
+    pub files_moved: usize,
+    pub bytes_moved: u64,
+    /// Action and file rows repointed at the new location.
+    pub records_updated: usize,
+    /// Files left behind, e.g. because the target already had that name.
+    pub errors: Vec<String>,
+}
+
+/// What a compressed batch produced before rollback handling.
+#[derive(Default)]
+struct ZipOutcome {
+    files_archived: usize,
+    total_bytes: u64,
+    errors: Vec<String>,
+    cancelled: bool,
+    archive_file: Option<String>,
+    bytes_saved: u64,
 }
 
 pub struct ArchiveManager {
     config: ArchiveConfig,
     space_manager: SpaceManager,
+    progress: Option<ProgressCallback>,
+    action_note: Option<String>,
+    idempotency_key: Option<String>,
+    batch_origin: String,
+    batch_label: Option<String>,
+    staging: Option<Staging>,
+}
+
+/// Staging recorded along with each archive action; see
+/// [`ArchiveManager::set_staging`].
+struct Staging {
+    expires_at: Option<DateTime<Utc>>,
+    note: Option<String>,
 }
 
 impl ArchiveManager {
@@ -75,9 +197,45 @@ impl ArchiveManager {
         Self {
             config: ArchiveConfig::default(),
             space_manager: SpaceManager::new(),
+            progress: None,
+            action_note: None,
+            idempotency_key: None,
+            batch_origin: "archive".to_string(),
+            batch_label: None,
+            staging: None,
         }
     }
 
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress = Some(callback);
+    }
+
+    /// Note recorded on every action this manager logs.
+    pub fn set_action_note(&mut self, note: Option<String>) {
+        self.action_note = note;
+    }
+
+    /// Derives the batch id from a client key, so a retried call files its
+    /// actions under the same batch as the first attempt.
+    pub fn set_idempotency_key(&mut self, key: Option<String>) {
+        self.idempotency_key = key;
+    }
+
+    /// The flow recorded as the batch's origin, e.g. `stage` or `dedupe`.
+    pub fn set_batch_origin(&mut self, origin: &str) {
+        self.batch_origin = origin.to_string();
+    }
+
+    pub fn set_batch_label(&mut self, label: Option<String>) {
+        self.batch_label = label;
+    }
+
+    /// Stages every file this manager archives until `expires_at`, in the
+    /// same transaction as its action.
+    pub fn set_staging(&mut self, expires_at: Option<DateTime<Utc>>, note: Option<String>) {
+        self.staging = Some(Staging { expires_at, note });
+    }
+
     pub fn archive_files(
         &mut self,
         file_paths: Vec<String>,
@@ -85,6 +243,7 @@ impl ArchiveManager {
     ) -> OpsResult<ArchiveResult> {
         let start_time = SystemTime::now();
         let batch_id = self.generate_batch_id();
+        let _span = tracing::info_span!("archive", batch_id = %batch_id).entered();
         let archive_path = self.config.get_daily_path();
 
         // Preflight checks
@@ -93,39 +252,343 @@ impl ArchiveManager {
         let mut files_archived = 0;
         let mut total_bytes = 0u64;
         let mut errors = Vec::new();
+        let mut error_messages = Vec::new();
 
         // Create archive directory
         fs::create_dir_all(&archive_path).map_err(|e| {
             OpsError::ArchiveError(format!("Failed to create archive directory: {}", e))
         })?;
 
-        for file_path in file_paths {
+        let mut reporter =
+            ProgressReporter::new(self.progress.as_ref(), "archive", &batch_id, &file_paths);
+        let token = cancel::register_batch(&batch_id);
+        let mut cancelled = false;
+        let mut archive_file = None;
+        let mut bytes_saved = 0;
+
+        // Directories are always moved as they are; only files go into a zip.
+        let mut loose_paths = file_paths;
+        if self.config.compression == ArchiveCompression::Zip {
+            let (dirs, files): (Vec<String>, Vec<String>) = loose_paths
+                .into_iter()
+                .partition(|path| Path::new(path).is_dir());
+            loose_paths = dirs;
+            if !files.is_empty() {
+                let outcome = self.archive_into_zip(
+                    files,
+                    &archive_path,
+                    &batch_id,
+                    db,
+                    &mut reporter,
+                    &token,
+                );
+                files_archived = outcome.files_archived;
+                total_bytes = outcome.total_bytes;
+                errors = outcome.errors;
+                cancelled = outcome.cancelled;
+                archive_file = outcome.archive_file;
+                bytes_saved = outcome.bytes_saved;
+            }
+        }
+        for file_path in loose_paths {
+            if cancelled || token.is_cancelled() {
+                cancelled = true;
+                break;
+            }
             match self.archive_single_file(&file_path, &archive_path, &batch_id, db) {
                 Ok(bytes) => {
                     files_archived += 1;
                     total_bytes += bytes;
+                    reporter.file_done(&file_path, bytes);
                 }
                 Err(e) => {
                     errors.push(format!("Failed to archive {}: {}", file_path, e));
+                    error_messages.push(e.to_message().with("path", &file_path));
+                    reporter.file_done(&file_path, 0);
                 }
             }
         }
+        cancel::finish_batch(&batch_id);
+
+        if cancelled {
+            let (restored, rollback_errors) = cancel::rollback_batch(&batch_id, db);
+            files_archived = files_archived.saturating_sub(restored);
+            if files_archived == 0 {
+                total_bytes = 0;
+            }
+            errors.extend(rollback_errors);
+        }
 
         let duration = start_time
             .elapsed()
             .unwrap_or(std::time::Duration::from_secs(0));
         let duration_ms = duration.as_millis() as u64;
 
+        tracing::info!(
+            files_archived,
+            total_bytes,
+            errors = errors.len(),
+            cancelled,
+            "archive batch finished"
+        );
+        if files_archived > 0 {
+            let batch = NewBatch {
+                batch_id: batch_id.clone(),
+                action_type: ActionType::Archive,
+                origin: self.batch_origin.clone(),
+                label: self.batch_label.clone(),
+                file_count: files_archived as i64,
+                total_bytes: total_bytes as i64,
+            };
+            if let Err(e) = db.record_batch(&batch) {
+                tracing::warn!(error = %e, "could not record archive batch");
+            }
+        }
         Ok(ArchiveResult {
             batch_id,
             files_archived,
             total_bytes,
             duration_ms,
             errors,
+            error_messages,
+            cancelled,
+            archive_file,
+            bytes_saved,
         })
     }
 
+    /// Writes the whole batch into one zip and only removes the originals once
+    /// the zip is complete and synced. A cancel drops the zip and leaves every
+    /// original in place, so nothing needs rolling back. Originals that could
+    /// not be archived after all are taken back out of the zip.
+    fn archive_into_zip(
+        &self,
+        file_paths: Vec<String>,
+        archive_dir: &Path,
+        batch_id: &str,
+        db: &Database,
+        reporter: &mut ProgressReporter<'_>,
+        token: &CancellationToken,
+    ) -> ZipOutcome {
+        let mut outcome = ZipOutcome::default();
+        let mut batch = match ZipBatch::create(archive_dir, batch_id) {
+            Ok(batch) => batch,
+            Err(e) => {
+                outcome.errors.push(e.to_string());
+                return outcome;
+            }
+        };
+
+        let mut added = Vec::new();
+        for file_path in file_paths {
+            if token.is_cancelled() {
+                batch.abandon();
+                outcome.cancelled = true;
+                return outcome;
+            }
+            match batch.add_file(Path::new(&file_path)) {
+                Ok((entry, bytes)) => {
+                    reporter.file_done(&file_path, bytes);
+                    added.push((file_path, entry, bytes));
+                }
+                Err(e) => {
+                    outcome
+                        .errors
+                        .push(format!("Failed to archive {}: {}", file_path, e));
+                    reporter.file_done(&file_path, 0);
+                }
+            }
+        }
+        if added.is_empty() {
+            batch.abandon();
+            return outcome;
+        }
+
+        let partial_zip = batch.path().to_path_buf();
+        let zip_path = match batch.finish() {
+            Ok(path) => path,
+            Err(e) => {
+                let _ = fs::remove_file(&partial_zip);
+                outcome
+                    .errors
+                    .push(format!("Failed to write compressed archive: {}", e));
+                return outcome;
+            }
+        };
+
+        let mut unarchived = HashSet::new();
+        for (source, entry, bytes) in added {
+            let dest = compress::member_path(&zip_path, &entry);
+            let result = self.archive_zip_member(&source, &dest, batch_id, db);
+            match result {
+                Ok(()) => {
+                    outcome.files_archived += 1;
+                    outcome.total_bytes += bytes;
+                }
+                Err(e) => {
+                    outcome
+                        .errors
+                        .push(format!("Failed to archive {}: {}", source, e));
+                    unarchived.insert(entry);
+                }
+            }
+        }
+        if outcome.files_archived == 0 {
+            let _ = fs::remove_file(&zip_path);
+            return outcome;
+        }
+        // The original is still in place; a copy nothing points at would
+        // only cost space and keep the zip from being discarded on undo.
+        if !unarchived.is_empty() {
+            if let Err(e) = compress::remove_entries(&zip_path, &unarchived) {
+                tracing::warn!(
+                    "Failed to drop unarchived entries from {}: {}",
+                    zip_path.display(),
+                    e
+                );
+            }
+        }
+
+        let compressed = fs::metadata(&zip_path).map(|m| m.len()).unwrap_or(0);
+        outcome.bytes_saved = outcome.total_bytes.saturating_sub(compressed);
+        outcome.archive_file = Some(zip_path.to_string_lossy().to_string());
+        outcome
+    }
+
+    /// Logs `source` as archived to its zip member, then removes it. The row
+    /// comes first so undo can always find the copy; when the source cannot
+    /// be removed the row is taken back out and the file stays indexed where
+    /// it is.
+    fn archive_zip_member(
+        &self,
+        source: &str,
+        dest: &str,
+        batch_id: &str,
+        db: &Database,
+    ) -> OpsResult<()> {
+        let file_id = self.get_file_id_from_path(source, db)?;
+        let action_id = self.log_archive_action(source, dest, batch_id, false, db)?;
+        let Err(remove_err) = fs::remove_file(source) else {
+            return Ok(());
+        };
+        self.unlog_archive_action(action_id, file_id, source, db);
+        Err(OpsError::ArchiveError(format!(
+            "Failed to remove original: {}",
+            remove_err
+        )))
+    }
+
+    /// Moves everything under the configured base path into `target`,
+    /// keeping the dated folder layout, and repoints the archived files and
+    /// their actions. Anything that cannot be moved stays where it is and
+    /// keeps its old records.
+    pub fn migrate_to(&self, target: &Path, db: &Database) -> OpsResult<ArchiveMigration> {
+        let source = self.config.base_path.clone();
+        if target == source || target.starts_with(&source) {
+            return Err(OpsError::InvalidPath(format!(
+                "{} is already the archive location or inside it",
+                target.display()
+            )));
+        }
+
+        let mut migration = ArchiveMigration {
+            from_path: source.to_string_lossy().to_string(),
+            to_path: target.to_string_lossy().to_string(),
+            ..ArchiveMigration::default()
+        };
+        if !source.exists() {
+            return Ok(migration);
+        }
+
+        let entries: Vec<PathBuf> = fs::read_dir(&source)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        for entry in entries {
+            let Some(name) = entry.file_name() else {
+                continue;
+            };
+            self.migrate_entry(&entry, &target.join(name), db, &mut migration);
+        }
+        Ok(migration)
+    }
+
+    fn migrate_entry(
+        &self,
+        source: &Path,
+        dest: &Path,
+        db: &Database,
+        migration: &mut ArchiveMigration,
+    ) {
+        if source.is_dir() {
+            if let Err(e) = fs::create_dir_all(dest) {
+                migration
+                    .errors
+                    .push(format!("Failed to create {}: {}", dest.display(), e));
+                return;
+            }
+            let children: Vec<PathBuf> = match fs::read_dir(source) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .collect(),
+                Err(e) => {
+                    migration
+                        .errors
+                        .push(format!("Failed to read {}: {}", source.display(), e));
+                    return;
+                }
+            };
+            for child in children {
+                if let Some(name) = child.file_name() {
+                    self.migrate_entry(&child, &dest.join(name), db, migration);
+                }
+            }
+            // Only succeeds once every child has been moved out.
+            let _ = fs::remove_dir(source);
+            return;
+        }
+
+        if dest.exists() {
+            migration.errors.push(format!(
+                "Skipped {}: {} already exists",
+                source.display(),
+                dest.display()
+            ));
+            return;
+        }
+        let result = fs::metadata(source)
+            .map_err(OpsError::from)
+            .and_then(|metadata| {
+                let size = metadata.len();
+                if fs::rename(source, dest).is_err() {
+                    self.copy_and_delete(source, dest, size, db)?;
+                }
+                Ok(size)
+            });
+        match result {
+            Ok(size) => {
+                migration.files_moved += 1;
+                migration.bytes_moved += size;
+                match db.relocate_archived_path(&source.to_string_lossy(), &dest.to_string_lossy())
+                {
+                    Ok(updated) => migration.records_updated += updated,
+                    Err(e) => migration.errors.push(format!(
+                        "Moved {} but failed to update records: {}",
+                        source.display(),
+                        e
+                    )),
+                }
+            }
+            Err(e) => migration
+                .errors
+                .push(format!("Failed to move {}: {}", source.display(), e)),
+        }
+    }
+
     fn preflight_checks(&self, file_paths: &[String], archive_path: &Path) -> OpsResult<()> {
+        for file_path in file_paths {
+            ensure_not_system_path(Path::new(file_path))?;
+        }
+
         // Check if archive directory can be created
         if let Some(parent) = archive_path.parent() {
             if !parent.exists() {
@@ -138,13 +601,18 @@ impl ArchiveManager {
         // Check permissions
         self.check_permissions(archive_path)?;
 
-        // Calculate total size and check free space
-        let total_size = self.calculate_total_size(file_paths)?;
-        self.check_free_space(archive_path, total_size)?;
+        // Check free space on the archive volume
+        let check = self.check_space(file_paths)?;
+        if !check.sufficient {
+            return Err(OpsError::SpaceError(format!(
+                "Insufficient disk space. Required: {} bytes, Available: {} bytes",
+                check.required_bytes, check.available_bytes
+            )));
+        }
 
-        // Verify all source files exist
+        // Verify all source files exist; a broken symlink is still a file
         for file_path in file_paths {
-            if !Path::new(file_path).exists() {
+            if fs::symlink_metadata(file_path).is_err() {
                 return Err(OpsError::ArchiveError(format!(
                     "Source file does not exist: {}",
                     file_path
@@ -185,35 +653,31 @@ impl ArchiveManager {
         }
     }
 
-    fn calculate_total_size(&self, file_paths: &[String]) -> OpsResult<u64> {
-        let mut total = 0u64;
+    /// Space the batch needs on the archive volume, buffer included. Files
+    /// already on that volume are renamed and cost nothing, unless they are
+    /// being compressed into a new zip.
+    pub fn check_space(&self, file_paths: &[String]) -> OpsResult<SpaceCheck> {
+        let archive_path = self.config.get_daily_path();
+        let mut required = 0u64;
 
         for file_path in file_paths {
-            let metadata = fs::metadata(file_path).map_err(|e| {
+            let metadata = fs::symlink_metadata(file_path).map_err(|e| {
                 OpsError::ArchiveError(format!(
                     "Failed to read file metadata for {}: {}",
                     file_path, e
                 ))
             })?;
-            total += metadata.len();
-        }
-
-        Ok(total)
-    }
-
-    fn check_free_space(&self, archive_path: &Path, required_bytes: u64) -> OpsResult<()> {
-        let available_space = self.space_manager.get_available_space(archive_path)?;
-        let buffer_bytes = (required_bytes as f64 * self.config.free_space_buffer / 100.0) as u64;
-        let required_with_buffer = required_bytes + buffer_bytes;
-
-        if available_space < required_with_buffer {
-            return Err(OpsError::ArchiveError(format!(
-                "Insufficient disk space. Required: {} bytes, Available: {} bytes",
-                required_with_buffer, available_space
-            )));
+            let renamed = self.config.compression == ArchiveCompression::None
+                && self
+                    .space_manager
+                    .same_volume(Path::new(file_path), &archive_path);
+            if !renamed {
+                required += metadata.len();
+            }
         }
 
-        Ok(())
+        self.space_manager
+            .check_destination(&archive_path, required, self.config.free_space_buffer)
     }
 
     fn archive_single_file(
@@ -248,40 +712,114 @@ impl ArchiveManager {
         }
 
         // Get file size for progress tracking
-        let file_size = fs::metadata(source)?.len();
+        let is_link = source.is_symlink();
+        let is_dir = source.is_dir() && !is_link;
+        let file_size = if is_dir {
+            self.space_manager.calculate_directory_size(source)?
+        } else {
+            fs::symlink_metadata(source)?.len()
+        };
 
         // Try to move first (fastest)
         match fs::rename(source, &dest_path) {
             Ok(_) => {
-                // Success - log the action
-                self.log_archive_action(source_path, &dest_path.to_string_lossy(), batch_id, db)?;
+                // Success - log the action, or put the file back without one
+                let dest = dest_path.to_string_lossy();
+                if let Err(e) = self.log_archive_action(source_path, &dest, batch_id, false, db) {
+                    if let Err(undo) = fs::rename(&dest_path, source) {
+                        tracing::error!("Failed to move {} back: {}", dest, undo);
+                    }
+                    return Err(e);
+                }
                 Ok(file_size)
             }
+            Err(_) if is_dir => Err(OpsError::ArchiveError(
+                "Directories can only be archived on the same volume".to_string(),
+            )),
+            // Copying would follow the link instead of moving it.
+            Err(_) if is_link => Err(OpsError::ArchiveError(
+                "Symlinks can only be archived on the same volume".to_string(),
+            )),
             Err(_) => {
-                // Cross-volume move failed, fallback to copy + delete
-                self.copy_and_delete(source, &dest_path, file_size)?;
-                self.log_archive_action(source_path, &dest_path.to_string_lossy(), batch_id, db)?;
+                // Cross-volume move failed, fallback to copy + verify + delete
+                self.archive_by_copy(source_path, &dest_path, file_size, batch_id, db)?;
                 Ok(file_size)
             }
         }
     }
 
-    fn copy_and_delete(&self, source: &Path, dest: &Path, file_size: u64) -> OpsResult<()> {
-        // Copy file
-        fs::copy(source, dest)
-            .map_err(|e| OpsError::ArchiveError(format!("Failed to copy file: {}", e)))?;
-
-        // Force sync to ensure data is written
-        self.sync_file(dest)?;
-
-        // Verify copy
-        self.verify_copy(source, dest)?;
+    /// Copies and verifies `source` into `dest`, logs the verified action and
+    /// only then removes the source. If the action cannot be logged the copy
+    /// is dropped; if the source cannot be removed the action is taken back
+    /// out as well, so the file is never moved without an action to undo it.
+    fn archive_by_copy(
+        &self,
+        source_path: &str,
+        dest: &Path,
+        file_size: u64,
+        batch_id: &str,
+        db: &Database,
+    ) -> OpsResult<()> {
+        let source = Path::new(source_path);
+        let file_id = self.get_file_id_from_path(source_path, db)?;
+        self.copy_verified(source, dest, file_size, db)?;
+
+        let dest_path = dest.to_string_lossy();
+        let action_id = match self.log_archive_action(source_path, &dest_path, batch_id, true, db) {
+            Ok(action_id) => action_id,
+            Err(e) => {
+                let _ = fs::remove_file(dest);
+                return Err(e);
+            }
+        };
+        let Err(remove_err) = fs::remove_file(source) else {
+            return Ok(());
+        };
+        self.unlog_archive_action(action_id, file_id, source_path, db);
+        let _ = fs::remove_file(dest);
+        Err(OpsError::ArchiveError(format!(
+            "Failed to delete original file: {}",
+            remove_err
+        )))
+    }
 
-        // Delete original
+    /// Moves `source` to `dest` through [`Self::copy_verified`], removing
+    /// the source once the copy is in place.
+    fn copy_and_delete(
+        &self,
+        source: &Path,
+        dest: &Path,
+        file_size: u64,
+        db: &Database,
+    ) -> OpsResult<()> {
+        self.copy_verified(source, dest, file_size, db)?;
         fs::remove_file(source).map_err(|e| {
             OpsError::ArchiveError(format!("Failed to delete original file: {}", e))
-        })?;
+        })
+    }
 
+    /// Copies to a temporary name next to `dest`, syncs and verifies the copy
+    /// and only then renames it into place. The source is never touched.
+    fn copy_verified(
+        &self,
+        source: &Path,
+        dest: &Path,
+        file_size: u64,
+        db: &Database,
+    ) -> OpsResult<()> {
+        let mut partial = dest.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let copied = fs::copy(source, &partial)
+            .map_err(|e| OpsError::ArchiveError(format!("Failed to copy file: {}", e)))
+            .and_then(|_| self.sync_file(&partial))
+            .and_then(|_| self.verify_copy(source, &partial, file_size, db))
+            .and_then(|_| fs::rename(&partial, dest).map_err(OpsError::from));
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
         Ok(())
     }
 
@@ -308,27 +846,65 @@ impl ArchiveManager {
         Ok(())
     }
 
-    fn verify_copy(&self, source: &Path, dest: &Path) -> OpsResult<()> {
-        let source_size = fs::metadata(source)?.len();
+    /// Checks the copy's size and full hash. The hash is compared with the one
+    /// stored at scan time; if that no longer matches, the source is hashed
+    /// too so a file edited since the last scan is not rejected.
+    fn verify_copy(
+        &self,
+        source: &Path,
+        dest: &Path,
+        file_size: u64,
+        db: &Database,
+    ) -> OpsResult<()> {
         let dest_size = fs::metadata(dest)?.len();
-
-        if source_size != dest_size {
-            return Err(OpsError::ArchiveError(format!(
+        if file_size != dest_size {
+            return Err(OpsError::CrossVolumeError(format!(
                 "Copy verification failed: source size {} != dest size {}",
-                source_size, dest_size
+                file_size, dest_size
+            )));
+        }
+
+        // Hash with the stored row's algorithm so a legacy hash still counts.
+        let stored = db
+            .get_file_id_by_path(&source.to_string_lossy())?
+            .and_then(|id| db.get_file_by_id(id).ok().flatten());
+        let algo = stored.as_ref().map_or(HashAlgo::CURRENT, |file| file.hash_algo);
+        let dest_hash = hash::hash_full_with(dest, algo).map_err(|e| {
+            OpsError::CrossVolumeError(format!(
+                "Failed to hash copy of {}: {}",
+                source.display(),
+                e
+            ))
+        })?;
+        let stored_hash = stored.and_then(|file| file.sha1).filter(|sha1| !sha1.is_empty());
+        if stored_hash.as_deref() == Some(dest_hash.as_str()) {
+            return Ok(());
+        }
+
+        let source_hash = hash::hash_full_with(source, algo).map_err(|e| {
+            OpsError::CrossVolumeError(format!("Failed to hash {}: {}", source.display(), e))
+        })?;
+        if source_hash != dest_hash {
+            return Err(OpsError::CrossVolumeError(format!(
+                "Copy verification failed for {}: checksum mismatch",
+                source.display()
             )));
         }
 
         Ok(())
     }
 
+    /// Inserts the archive action, flagged `verified` when the copy was
+    /// checksummed, points the file at its new location and, when staging,
+    /// stages it.
     fn log_archive_action(
         &self,
         src_path: &str,
         dst_path: &str,
         batch_id: &str,
+        verified: bool,
         db: &Database,
-    ) -> OpsResult<()> {
+    ) -> OpsResult<i64> {
         // Find file_id in database
         let file_id = self.get_file_id_from_path(src_path, db)?;
 
@@ -339,16 +915,54 @@ impl ArchiveManager {
             src_path: Some(src_path.to_string()),
             dst_path: Some(dst_path.to_string()),
             origin: Some("archive_manager".to_string()),
-            note: None,
+            note: self.action_note.clone(),
         };
 
-        db.insert_action(&action)
-            .map_err(|e| OpsError::ArchiveError(format!("Failed to log action: {}", e)))?;
-        db.update_file_location(file_id, dst_path).map_err(|e| {
-            OpsError::ArchiveError(format!("Failed to update file location: {}", e))
-        })?;
+        // The action, the new location and the staging land together or not
+        // at all.
+        db.transaction(|| {
+            let action_id = db
+                .insert_action(&action)
+                .map_err(|e| OpsError::ArchiveError(format!("Failed to log action: {}", e)))?;
+            if verified {
+                db.mark_action_verified(action_id).map_err(|e| {
+                    OpsError::ArchiveError(format!("Failed to record verification: {}", e))
+                })?;
+            }
+            db.update_file_location(file_id, dst_path).map_err(|e| {
+                OpsError::ArchiveError(format!("Failed to update file location: {}", e))
+            })?;
+            if let Some(staging) = &self.staging {
+                let staged = NewStagedFile {
+                    file_id,
+                    staged_at: Utc::now(),
+                    expires_at: staging.expires_at,
+                    batch_id: Some(batch_id.to_string()),
+                    status: "staged".to_string(),
+                    note: staging.note.clone(),
+                };
+                db.stage_files(&[staged]).map_err(|e| {
+                    OpsError::ArchiveError(format!("Failed to stage file: {}", e))
+                })?;
+            }
+            Ok(action_id)
+        })
+    }
 
-        Ok(())
+    /// Takes back an action whose source could not be removed, leaving the
+    /// file indexed where it still is.
+    fn unlog_archive_action(&self, action_id: i64, file_id: i64, source: &str, db: &Database) {
+        let unlogged: rusqlite::Result<()> = db.transaction(|| {
+            db.delete_action(action_id)?;
+            db.update_file_location(file_id, source)?;
+            if self.staging.is_some() {
+                db.mark_files_unstaged(&[file_id])?;
+            }
+            Ok(())
+        });
+        if let Err(e) = unlogged {
+            tracing::warn!("Failed to drop archive action {action_id} for {source}: {e}");
+        }
     }
 
     fn get_file_id_from_path(&self, path: &str, db: &Database) -> OpsResult<i64> {
@@ -358,6 +972,9 @@ impl ArchiveManager {
     }
 
     fn generate_batch_id(&self) -> String {
+        if let Some(key) = &self.idempotency_key {
+            return format!("archive_{key}");
+        }
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or(std::time::Duration::from_secs(0))
@@ -0,0 +1,83 @@
+use crate::db::Database;
+use crate::ops::undo::UndoManager;
+use crate::scanner::CancellationToken;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+static ACTIVE_BATCHES: Lazy<Mutex<HashMap<String, CancellationToken>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Registers a running batch so `cancel_batch` can reach it.
+pub fn register_batch(batch_id: &str) -> CancellationToken {
+    let token = CancellationToken::new();
+    ACTIVE_BATCHES
+        .lock()
+        .expect("active batches lock")
+        .insert(batch_id.to_string(), token.clone());
+    token
+}
+
+pub fn finish_batch(batch_id: &str) {
+    ACTIVE_BATCHES
+        .lock()
+        .expect("active batches lock")
+        .remove(batch_id);
+}
+
+/// Flags a running batch for cancellation. Returns `false` if the batch is not
+/// running (already finished or unknown).
+pub fn cancel_batch(batch_id: &str) -> bool {
+    match ACTIVE_BATCHES
+        .lock()
+        .expect("active batches lock")
+        .get(batch_id)
+    {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn active_batch_ids() -> Vec<String> {
+    ACTIVE_BATCHES
+        .lock()
+        .expect("active batches lock")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Best-effort reversal of everything a cancelled batch already did. Reversed
+/// actions are removed so the actions table only lists what still applies.
+/// Returns how many files were put back plus any per-file errors.
+pub fn rollback_batch(batch_id: &str, db: &Database) -> (usize, Vec<String>) {
+    let actions = match db.get_actions_by_batch_id(batch_id) {
+        Ok(actions) => actions,
+        Err(e) => return (0, vec![format!("Failed to load batch {}: {}", batch_id, e)]),
+    };
+
+    let undo_manager = UndoManager::new();
+    let mut restored = 0;
+    let mut errors = Vec::new();
+    for action in actions {
+        match undo_manager.reverse_action(&action, db) {
+            Ok(()) => {
+                restored += 1;
+                if let Some(id) = action.id {
+                    if let Err(e) = db.delete_action(id) {
+                        errors.push(format!("Failed to remove action {}: {}", id, e));
+                    }
+                }
+            }
+            Err(e) => errors.push(format!(
+                "Rollback failed for {}: {}",
+                action.src_path.as_deref().unwrap_or("unknown"),
+                e
+            )),
+        }
+    }
+    (restored, errors)
+}
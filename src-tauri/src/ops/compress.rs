@@ -0,0 +1,286 @@
+use crate::models::HashAlgo;
+use crate::ops::error::{OpsError, OpsResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Entry written last into every compressed batch.
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    /// Files are moved into the dated archive folder as-is.
+    #[default]
+    None,
+    /// Each batch becomes one deflated `<batch_id>.zip` in the dated folder.
+    Zip,
+}
+
+impl std::fmt::Display for ArchiveCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveCompression::None => write!(f, "none"),
+            ArchiveCompression::Zip => write!(f, "zip"),
+        }
+    }
+}
+
+impl std::str::FromStr for ArchiveCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "none" | "" => Ok(ArchiveCompression::None),
+            "zip" => Ok(ArchiveCompression::Zip),
+            other => Err(format!("Invalid archive compression: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub original_path: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub batch_id: String,
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// A zip being written for one archive batch. Originals must only be removed
+/// after [`ZipBatch::finish`] has returned.
+pub struct ZipBatch {
+    path: PathBuf,
+    writer: ZipWriter<fs::File>,
+    manifest: ArchiveManifest,
+    names: HashSet<String>,
+}
+
+impl ZipBatch {
+    pub fn create(archive_dir: &Path, batch_id: &str) -> OpsResult<Self> {
+        let path = archive_dir.join(format!("{}.zip", batch_id));
+        let file = fs::File::create(&path).map_err(|e| {
+            OpsError::ArchiveError(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            path,
+            writer: ZipWriter::new(file),
+            manifest: ArchiveManifest {
+                batch_id: batch_id.to_string(),
+                created_at: Utc::now(),
+                entries: Vec::new(),
+            },
+            names: HashSet::new(),
+        })
+    }
+
+    /// Where the zip is being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Streams `source` into the zip and returns its entry name and size.
+    pub fn add_file(&mut self, source: &Path) -> OpsResult<(String, u64)> {
+        let name = self.unique_name(source)?;
+        let mut input = fs::File::open(source)?;
+        // Entries of 4 GiB and over need the zip64 extra field up front.
+        let large = input.metadata()?.len() >= u32::MAX as u64;
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(large);
+        self.writer
+            .start_file(name.as_str(), options)
+            .map_err(zip_error)?;
+        let size_bytes = io::copy(&mut input, &mut self.writer)?;
+
+        self.names.insert(name.clone());
+        self.manifest.entries.push(ManifestEntry {
+            name: name.clone(),
+            original_path: source.to_string_lossy().to_string(),
+            size_bytes,
+        });
+        Ok((name, size_bytes))
+    }
+
+    /// Writes the manifest, closes the zip and syncs it to disk.
+    pub fn finish(mut self) -> OpsResult<PathBuf> {
+        write_manifest(&mut self.writer, &self.manifest)?;
+        let file = self.writer.finish().map_err(zip_error)?;
+        file.sync_all()?;
+        Ok(self.path)
+    }
+
+    /// Drops a half-written zip, e.g. after a cancel.
+    pub fn abandon(self) {
+        let path = self.path.clone();
+        drop(self.writer);
+        let _ = fs::remove_file(path);
+    }
+
+    fn unique_name(&self, source: &Path) -> OpsResult<String> {
+        let stem = source
+            .file_stem()
+            .ok_or_else(|| OpsError::ArchiveError("Invalid file name".to_string()))?
+            .to_string_lossy();
+        let extension = source
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut name = format!("{}{}", stem, extension);
+        let mut counter = 1;
+        while name == MANIFEST_NAME || self.names.contains(&name) {
+            name = format!("{} ({}){}", stem, counter, extension);
+            counter += 1;
+        }
+        Ok(name)
+    }
+}
+
+/// Path recorded for a file stored inside a compressed batch, e.g.
+/// `~/Archive/White Space/2024-05-01/archive_1714.zip/report.pdf`.
+pub fn member_path(zip_path: &Path, entry: &str) -> String {
+    zip_path.join(entry).to_string_lossy().to_string()
+}
+
+/// Splits a [`member_path`] back into the zip file and the entry name.
+/// Returns `None` for ordinary paths.
+pub fn split_member_path(path: &Path) -> Option<(PathBuf, String)> {
+    let zip_path = zip_ancestor(path).filter(|zip| zip.is_file())?;
+    let entry = path
+        .strip_prefix(&zip_path)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((zip_path, entry))
+}
+
+/// Nearest ancestor of `path` with a `.zip` extension, whether or not it is
+/// currently on disk (a trashed batch no longer is).
+pub fn zip_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|ancestor| {
+            ancestor
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Extracts one entry to `dest`, creating its parent directory if needed.
+pub fn extract_entry(zip_path: &Path, entry: &str, dest: &Path) -> OpsResult<()> {
+    if dest.exists() {
+        return Err(OpsError::UndoError(format!(
+            "Destination already exists: {}",
+            dest.display()
+        )));
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            OpsError::UndoError(format!("Failed to create parent directory: {}", e))
+        })?;
+    }
+
+    let mut archive = open_archive(zip_path)?;
+    let mut source = archive
+        .by_name(entry)
+        .map_err(|e| OpsError::UndoError(format!("Missing {} in archive: {}", entry, e)))?;
+    let mut output = fs::File::create(dest)?;
+    if let Err(e) = io::copy(&mut source, &mut output).and_then(|_| output.sync_all()) {
+        let _ = fs::remove_file(dest);
+        return Err(OpsError::UndoError(format!(
+            "Failed to extract {}: {}",
+            entry, e
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the zip can be opened and holds `entry`.
+pub fn has_entry(zip_path: &Path, entry: &str) -> bool {
+    open_archive(zip_path).is_ok_and(|mut archive| archive.by_name(entry).is_ok())
+}
+
+/// Hash of one entry's uncompressed contents, in the scanner's hex format.
+pub fn hash_entry(zip_path: &Path, entry: &str, algo: HashAlgo) -> OpsResult<String> {
+    let mut archive = open_archive(zip_path)?;
+    let source = archive.by_name(entry).map_err(zip_error)?;
+    crate::scanner::hash::hash_reader(source, algo).map_err(|e| {
+        OpsError::ArchiveError(format!("Failed to hash {} in archive: {}", entry, e))
+    })
+}
+
+/// Rewrites the zip without `entries`, copying the other entries as they are
+/// stored and dropping the removed ones from the manifest. The new zip is
+/// synced under a temporary name before it replaces the old one.
+pub fn remove_entries(zip_path: &Path, entries: &HashSet<String>) -> OpsResult<()> {
+    let mut manifest = read_manifest(zip_path)?;
+    manifest.entries.retain(|entry| !entries.contains(&entry.name));
+    let mut archive = open_archive(zip_path)?;
+
+    let mut partial = zip_path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let written = fs::File::create(&partial)
+        .map_err(OpsError::from)
+        .and_then(|file| {
+            let mut writer = ZipWriter::new(file);
+            for index in 0..archive.len() {
+                let entry = archive.by_index_raw(index).map_err(zip_error)?;
+                if entry.name() != MANIFEST_NAME && !entries.contains(entry.name()) {
+                    writer.raw_copy_file(entry).map_err(zip_error)?;
+                }
+            }
+            write_manifest(&mut writer, &manifest)?;
+            writer.finish().map_err(zip_error)?.sync_all()?;
+            Ok(())
+        })
+        .and_then(|_| fs::rename(&partial, zip_path).map_err(OpsError::from));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+pub fn read_manifest(zip_path: &Path) -> OpsResult<ArchiveManifest> {
+    let mut archive = open_archive(zip_path)?;
+    let mut entry = archive.by_name(MANIFEST_NAME).map_err(zip_error)?;
+    let mut raw = Vec::new();
+    entry.read_to_end(&mut raw)?;
+    serde_json::from_slice(&raw)
+        .map_err(|e| OpsError::ArchiveError(format!("Invalid archive manifest: {}", e)))
+}
+
+fn write_manifest(writer: &mut ZipWriter<fs::File>, manifest: &ArchiveManifest) -> OpsResult<()> {
+    let encoded = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| OpsError::ArchiveError(format!("Failed to encode manifest: {}", e)))?;
+    writer
+        .start_file(MANIFEST_NAME, FileOptions::default())
+        .map_err(zip_error)?;
+    writer.write_all(&encoded)?;
+    Ok(())
+}
+
+fn open_archive(zip_path: &Path) -> OpsResult<ZipArchive<fs::File>> {
+    let file = fs::File::open(zip_path).map_err(|e| {
+        OpsError::ArchiveError(format!("Failed to open {}: {}", zip_path.display(), e))
+    })?;
+    ZipArchive::new(file).map_err(zip_error)
+}
+
+fn zip_error(err: zip::result::ZipError) -> OpsError {
+    OpsError::ArchiveError(format!("Zip error: {}", err))
+}
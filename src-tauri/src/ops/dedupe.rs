@@ -0,0 +1,104 @@
+use crate::db::Database;
+use crate::models::File;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// `keep_newest`, `keep_oldest` or `prefer_paths`.
+pub const DUPLICATE_POLICY_PREF: &str = "duplicate_policy";
+/// JSON list of folders, most preferred first, used by `prefer_paths`.
+pub const DUPLICATE_PREFERRED_PATHS_PREF: &str = "duplicate_preferred_paths";
+pub const DUPLICATE_POLICIES: &[&str] = &["keep_newest", "keep_oldest", "prefer_paths"];
+
+/// Which copy of a duplicate group to keep when resolving groups in bulk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    KeepNewest,
+    KeepOldest,
+    PreferPaths,
+}
+
+impl DuplicatePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "keep_newest" => Some(Self::KeepNewest),
+            "keep_oldest" => Some(Self::KeepOldest),
+            "prefer_paths" => Some(Self::PreferPaths),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KeepNewest => "keep_newest",
+            Self::KeepOldest => "keep_oldest",
+            Self::PreferPaths => "prefer_paths",
+        }
+    }
+}
+
+/// The stored policy and folder order; an unset or unknown policy keeps the
+/// newest copy.
+pub fn load_policy(db: &Database) -> (DuplicatePolicy, Vec<String>) {
+    let policy = db
+        .get_preference(DUPLICATE_POLICY_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| DuplicatePolicy::parse(&value))
+        .unwrap_or(DuplicatePolicy::KeepNewest);
+    let preferred = db
+        .get_preference(DUPLICATE_PREFERRED_PATHS_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str::<Vec<String>>(&value).ok())
+        .unwrap_or_default();
+    (policy, preferred)
+}
+
+/// Index of the copy `policy` keeps. Under `PreferPaths` the copy in the
+/// earliest listed folder wins; copies in the same folder, or groups with no
+/// copy in any of them, fall back to the newest. Equal timestamps keep the
+/// lowest file id so the choice is stable between preview and apply.
+pub fn pick_keeper(policy: DuplicatePolicy, preferred: &[String], files: &[File]) -> Option<usize> {
+    let newest = |a: &File, b: &File| {
+        modified(b)
+            .cmp(&modified(a))
+            .then(a.id.unwrap_or(i64::MAX).cmp(&b.id.unwrap_or(i64::MAX)))
+    };
+    let oldest = |a: &File, b: &File| {
+        modified(a)
+            .cmp(&modified(b))
+            .then(a.id.unwrap_or(i64::MAX).cmp(&b.id.unwrap_or(i64::MAX)))
+    };
+    let folders: Vec<PathBuf> = preferred.iter().map(|p| expand_home(p)).collect();
+    let rank = |file: &File| {
+        folders
+            .iter()
+            .position(|folder| Path::new(&file.path).starts_with(folder))
+            .unwrap_or(folders.len())
+    };
+
+    (0..files.len()).min_by(|&a, &b| {
+        let (a, b) = (&files[a], &files[b]);
+        match policy {
+            DuplicatePolicy::KeepNewest => newest(a, b),
+            DuplicatePolicy::KeepOldest => oldest(a, b),
+            DuplicatePolicy::PreferPaths => rank(a).cmp(&rank(b)).then_with(|| newest(a, b)),
+        }
+    })
+}
+
+fn modified(file: &File) -> DateTime<Utc> {
+    file.modified_at.unwrap_or(file.created_at)
+}
+
+/// A leading `~` stands for the home directory, as in rule globs.
+fn expand_home(path: &str) -> PathBuf {
+    let path = path.trim();
+    match path.strip_prefix('~') {
+        Some(rest) => {
+            let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        None => PathBuf::from(path),
+    }
+}
@@ -1,7 +1,15 @@
 use crate::db::Database;
-use crate::models::{ActionType, NewAction};
+use crate::i18n::Message;
+use crate::models::{ActionType, NewAction, NewBatch};
+use crate::ops::cancel;
+use crate::ops::compress;
 use crate::ops::error::{OpsError, OpsResult};
+use crate::ops::progress::{ProgressCallback, ProgressReporter};
+use crate::ops::space::SpaceManager;
+use crate::ops::system_paths::ensure_not_system_path;
+use crate::ops::trash;
 use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -32,7 +40,10 @@ pub struct DeleteResult {
     pub total_bytes_freed: u64,
     pub duration_ms: u64,
     pub errors: Vec<String>,
+    /// Per-file failures as catalog messages, with the file in `path`.
+    pub error_messages: Vec<Message>,
     pub trash_path: Option<String>,
+    pub cancelled: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -47,15 +58,43 @@ pub struct DeleteCandidate {
 
 pub struct DeleteManager {
     config: DeleteConfig,
+    progress: Option<ProgressCallback>,
+    action_note: Option<String>,
+    idempotency_key: Option<String>,
+    batch_origin: String,
 }
 
 impl DeleteManager {
     pub fn new() -> Self {
         Self {
             config: DeleteConfig::default(),
+            progress: None,
+            action_note: None,
+            idempotency_key: None,
+            batch_origin: "delete".to_string(),
         }
     }
 
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress = Some(callback);
+    }
+
+    /// Note recorded on every action this manager logs.
+    pub fn set_action_note(&mut self, note: Option<String>) {
+        self.action_note = note;
+    }
+
+    /// Derives the batch id from a client key, so a retried call files its
+    /// actions under the same batch as the first attempt.
+    pub fn set_idempotency_key(&mut self, key: Option<String>) {
+        self.idempotency_key = key;
+    }
+
+    /// The flow recorded as the batch's origin, e.g. `empty` or `expiry`.
+    pub fn set_batch_origin(&mut self, origin: &str) {
+        self.batch_origin = origin.to_string();
+    }
+
     pub fn delete_files(
         &mut self,
         file_paths: Vec<String>,
@@ -63,13 +102,33 @@ impl DeleteManager {
     ) -> OpsResult<DeleteResult> {
         let start_time = SystemTime::now();
         let batch_id = self.generate_batch_id();
+        let _span = tracing::info_span!("delete", batch_id = %batch_id).entered();
+        for file_path in &file_paths {
+            ensure_not_system_path(Path::new(file_path))?;
+        }
 
         let mut files_deleted = 0;
         let mut total_bytes_freed = 0u64;
         let mut errors = Vec::new();
+        let mut error_messages = Vec::new();
         let mut trash_path = None;
 
+        let mut reporter =
+            ProgressReporter::new(self.progress.as_ref(), "delete", &batch_id, &file_paths);
+        let token = cancel::register_batch(&batch_id);
+        let mut cancelled = false;
+        // Files inside a compressed batch are removed together with their zip.
+        let mut compressed: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+
         for file_path in file_paths {
+            if token.is_cancelled() {
+                cancelled = true;
+                break;
+            }
+            if let Some((zip_path, _)) = compress::split_member_path(Path::new(&file_path)) {
+                compressed.entry(zip_path).or_default().push(file_path);
+                continue;
+            }
             match self.delete_single_file(&file_path, &batch_id, db) {
                 Ok((bytes_freed, trash)) => {
                     files_deleted += 1;
@@ -77,25 +136,88 @@ impl DeleteManager {
                     if trash.is_some() && trash_path.is_none() {
                         trash_path = trash;
                     }
+                    reporter.file_done(&file_path, bytes_freed);
                 }
                 Err(e) => {
                     errors.push(format!("Failed to delete {}: {}", file_path, e));
+                    error_messages.push(e.to_message().with("path", &file_path));
+                    reporter.file_done(&file_path, 0);
                 }
             }
         }
 
+        for (zip_path, members) in compressed {
+            if cancelled || token.is_cancelled() {
+                cancelled = true;
+                break;
+            }
+            match self.delete_compressed_batch(&zip_path, &members, &batch_id, db) {
+                Ok((bytes_freed, trash)) => {
+                    files_deleted += members.len();
+                    total_bytes_freed += bytes_freed;
+                    if trash.is_some() && trash_path.is_none() {
+                        trash_path = trash;
+                    }
+                    for member in &members {
+                        reporter.file_done(member, 0);
+                    }
+                }
+                Err(e) => {
+                    errors.push(format!("Failed to delete {}: {}", zip_path.display(), e));
+                    error_messages.push(e.to_message().with("path", zip_path.display()));
+                    for member in &members {
+                        reporter.file_done(member, 0);
+                    }
+                }
+            }
+        }
+        cancel::finish_batch(&batch_id);
+
+        // Permanently deleted files cannot come back; rollback reports them
+        // as errors and restores whatever is still in the trash.
+        if cancelled {
+            let (restored, rollback_errors) = cancel::rollback_batch(&batch_id, db);
+            files_deleted = files_deleted.saturating_sub(restored);
+            if files_deleted == 0 {
+                total_bytes_freed = 0;
+            }
+            errors.extend(rollback_errors);
+        }
+
         let duration = start_time
             .elapsed()
             .unwrap_or(std::time::Duration::from_secs(0));
         let duration_ms = duration.as_millis() as u64;
 
+        tracing::info!(
+            files_deleted,
+            total_bytes_freed,
+            errors = errors.len(),
+            cancelled,
+            "delete batch finished"
+        );
+        if files_deleted > 0 {
+            let batch = NewBatch {
+                batch_id: batch_id.clone(),
+                action_type: ActionType::Delete,
+                origin: self.batch_origin.clone(),
+                label: None,
+                file_count: files_deleted as i64,
+                total_bytes: total_bytes_freed as i64,
+            };
+            if let Err(e) = db.record_batch(&batch) {
+                tracing::warn!(error = %e, "could not record delete batch");
+            }
+        }
         Ok(DeleteResult {
             batch_id,
             files_deleted,
             total_bytes_freed,
             duration_ms,
             errors,
+            error_messages,
             trash_path,
+            cancelled,
         })
     }
 
@@ -107,14 +229,21 @@ impl DeleteManager {
     ) -> OpsResult<(u64, Option<String>)> {
         let path = Path::new(file_path);
 
-        if !path.exists() {
+        // A broken symlink does not "exist", but it can still be removed.
+        let Ok(link_metadata) = fs::symlink_metadata(path) else {
             return Err(OpsError::DeleteError(format!(
                 "File does not exist: {}",
                 file_path
             )));
-        }
+        };
 
-        let file_size = fs::metadata(path)?.len();
+        let file_size = if link_metadata.is_symlink() {
+            crate::scanner::file_walker::symlink_len(path, &link_metadata)
+        } else if path.is_dir() {
+            SpaceManager::new().calculate_directory_size(path)?
+        } else {
+            reclaimable_len(&fs::metadata(path)?)
+        };
 
         // Determine deletion method
         let (deleted_path, trash_path) = if self.config.use_trash && !self.config.permanent_delete {
@@ -129,132 +258,56 @@ impl DeleteManager {
         Ok((file_size, trash_path))
     }
 
-    fn move_to_trash(&self, path: &Path) -> OpsResult<(String, Option<String>)> {
-        let trash_dir = self.get_trash_directory()?;
-        let filename = path
-            .file_name()
-            .ok_or_else(|| OpsError::DeleteError("Invalid file path".to_string()))?
-            .to_string_lossy();
-
-        let mut trash_path = trash_dir.join(&*filename);
-
-        // Handle conflicts by appending " (n)" suffix
-        let mut counter = 1;
-        while trash_path.exists() {
-            let stem = path
-                .file_stem()
-                .ok_or_else(|| OpsError::DeleteError("Invalid file name".to_string()))?
-                .to_string_lossy();
-            let extension = path
-                .extension()
-                .map(|ext| format!(".{}", ext.to_string_lossy()))
-                .unwrap_or_default();
-
-            trash_path = trash_dir.join(format!("{} ({}){}", stem, counter, extension));
-            counter += 1;
+    /// Removes a whole compressed batch. Every file still indexed inside the
+    /// zip must be part of the request, otherwise nothing is touched.
+    fn delete_compressed_batch(
+        &self,
+        zip_path: &Path,
+        members: &[String],
+        batch_id: &str,
+        db: &Database,
+    ) -> OpsResult<(u64, Option<String>)> {
+        let requested: HashSet<&str> = members.iter().map(String::as_str).collect();
+        let zip_str = zip_path.to_string_lossy();
+        let remaining = db
+            .list_active_paths(Some(&zip_str))?
+            .into_iter()
+            .filter(|(_, path)| !requested.contains(path.as_str()))
+            .count();
+        if remaining > 0 {
+            return Err(OpsError::DeleteError(format!(
+                "{} other file(s) in this compressed archive must be emptied with it",
+                remaining
+            )));
         }
 
-        // Move to trash
-        fs::rename(path, &trash_path)
-            .map_err(|e| OpsError::DeleteError(format!("Failed to move to trash: {}", e)))?;
+        let file_size = reclaimable_len(&fs::metadata(zip_path)?);
+        let (deleted_path, trash_path) = if self.config.use_trash && !self.config.permanent_delete {
+            self.move_to_trash(zip_path)?
+        } else {
+            self.permanent_delete(zip_path)?
+        };
+        for member in members {
+            self.log_delete_action(member, &deleted_path, batch_id, db)?;
+        }
 
-        Ok((
-            trash_path.to_string_lossy().to_string(),
-            Some(trash_dir.to_string_lossy().to_string()),
-        ))
+        Ok((file_size, trash_path))
     }
 
-    fn permanent_delete(&self, path: &Path) -> OpsResult<(String, Option<String>)> {
-        fs::remove_file(path)
-            .map_err(|e| OpsError::DeleteError(format!("Failed to delete file: {}", e)))?;
-
-        Ok((path.to_string_lossy().to_string(), None))
+    fn move_to_trash(&self, path: &Path) -> OpsResult<(String, Option<String>)> {
+        trash::move_to_trash(path)
     }
 
-    fn get_trash_directory(&self) -> OpsResult<PathBuf> {
-        #[cfg(target_os = "windows")]
-        {
-            // Windows Recycle Bin
-            if let Some(user_profile) = std::env::var_os("USERPROFILE") {
-                let recycle_bin = PathBuf::from(user_profile)
-                    .join("AppData")
-                    .join("Local")
-                    .join("Microsoft")
-                    .join("Windows")
-                    .join("Explorer");
-                if !recycle_bin.exists() {
-                    fs::create_dir_all(&recycle_bin).map_err(|e| {
-                        OpsError::DeleteError(format!(
-                            "Failed to create recycle bin directory: {}",
-                            e
-                        ))
-                    })?;
-                }
-                Ok(recycle_bin)
-            } else {
-                Err(OpsError::DeleteError(
-                    "Cannot determine user profile directory".to_string(),
-                ))
-            }
-        }
-
-        #[cfg(target_os = "macos")]
-        {
-            // macOS Trash
-            if let Some(home) = dirs::home_dir() {
-                let trash = home.join(".Trash");
-                if !trash.exists() {
-                    fs::create_dir_all(&trash).map_err(|e| {
-                        OpsError::DeleteError(format!("Failed to create trash directory: {}", e))
-                    })?;
-                }
-                Ok(trash)
-            } else {
-                Err(OpsError::DeleteError(
-                    "Cannot determine home directory".to_string(),
-                ))
-            }
-        }
-
-        #[cfg(target_os = "linux")]
-        {
-            // Linux Trash
-            if let Some(home) = dirs::home_dir() {
-                let trash = home
-                    .join(".local")
-                    .join("share")
-                    .join("Trash")
-                    .join("files");
-                if !trash.exists() {
-                    fs::create_dir_all(&trash).map_err(|e| {
-                        OpsError::DeleteError(format!("Failed to create trash directory: {}", e))
-                    })?;
-                }
-                Ok(trash)
-            } else {
-                Err(OpsError::DeleteError(
-                    "Cannot determine home directory".to_string(),
-                ))
-            }
-        }
+    fn permanent_delete(&self, path: &Path) -> OpsResult<(String, Option<String>)> {
+        // Staged build artifact folders are indexed as a single entry.
+        let removed = if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        removed.map_err(|e| OpsError::DeleteError(format!("Failed to delete file: {}", e)))?;
 
-        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-        {
-            // Fallback for other systems
-            if let Some(home) = dirs::home_dir() {
-                let trash = home.join(".trash");
-                if !trash.exists() {
-                    fs::create_dir_all(&trash).map_err(|e| {
-                        OpsError::DeleteError(format!("Failed to create trash directory: {}", e))
-                    })?;
-                }
-                Ok(trash)
-            } else {
-                Err(OpsError::DeleteError(
-                    "Cannot determine home directory".to_string(),
-                ))
-            }
-        }
+        Ok((path.to_string_lossy().to_string(), None))
     }
 
     fn log_delete_action(
@@ -274,7 +327,7 @@ impl DeleteManager {
             src_path: Some(src_path.to_string()),
             dst_path: Some(dst_path.to_string()),
             origin: Some("delete_manager".to_string()),
-            note: None,
+            note: self.action_note.clone(),
         };
 
         db.insert_action(&action)
@@ -290,6 +343,9 @@ impl DeleteManager {
     }
 
     fn generate_batch_id(&self) -> String {
+        if let Some(key) = &self.idempotency_key {
+            return format!("delete_{key}");
+        }
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or(std::time::Duration::from_secs(0))
@@ -354,3 +410,19 @@ impl Default for DeleteManager {
         Self::new()
     }
 }
+
+/// Removing one name of a hard-linked file frees nothing while other links
+/// to the same data remain, and neither does removing a cloud placeholder.
+fn reclaimable_len(metadata: &fs::Metadata) -> u64 {
+    if crate::scanner::file_walker::is_cloud_placeholder(metadata) {
+        return 0;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.nlink() > 1 {
+            return 0;
+        }
+    }
+    metadata.len()
+}
@@ -1,3 +1,4 @@
+use crate::i18n::Message;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -13,6 +14,8 @@ pub enum OpsError {
     BatchError(String),
     DatabaseError(String),
     GaugeError(String),
+    /// The path is inside an OS or application folder.
+    SystemLocation(String),
 }
 
 pub type OpsResult<T> = Result<T, OpsError>;
@@ -31,6 +34,7 @@ impl fmt::Display for OpsError {
             OpsError::BatchError(msg) => write!(f, "Batch Error: {}", msg),
             OpsError::DatabaseError(msg) => write!(f, "Database Error: {}", msg),
             OpsError::GaugeError(msg) => write!(f, "Gauge Error: {}", msg),
+            OpsError::SystemLocation(msg) => write!(f, "System Location: {}", msg),
         }
     }
 }
@@ -74,82 +78,57 @@ pub struct ErrorMessage {
 }
 
 impl OpsError {
-    pub fn to_user_message(&self) -> ErrorMessage {
+    /// Catalog key for this error; `.title` and `.suggestion` suffixes name
+    /// the heading and the advice.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            OpsError::ArchiveError(_) => "error.archive",
+            OpsError::DeleteError(_) => "error.delete",
+            OpsError::UndoError(_) => "error.undo",
+            OpsError::SpaceError(_) => "error.space",
+            OpsError::PermissionError(_) => "error.permission",
+            OpsError::FileNotFound(_) => "error.not_found",
+            OpsError::InvalidPath(_) => "error.invalid_path",
+            OpsError::CrossVolumeError(_) => "error.cross_volume",
+            OpsError::BatchError(_) => "error.batch",
+            OpsError::DatabaseError(_) => "error.database",
+            OpsError::GaugeError(_) => "error.gauge",
+            OpsError::SystemLocation(_) => "error.system_location",
+        }
+    }
+
+    fn detail(&self) -> &str {
         match self {
-            OpsError::ArchiveError(msg) => ErrorMessage {
-                title: "Archive Failed".to_string(),
-                message: format!("Unable to archive files: {}", msg),
-                suggestion: Some("Check disk space and permissions, then try again.".to_string()),
-                recoverable: true,
-            },
-            OpsError::DeleteError(msg) => ErrorMessage {
-                title: "Delete Failed".to_string(),
-                message: format!("Unable to delete files: {}", msg),
-                suggestion: Some("Check file permissions and try again.".to_string()),
-                recoverable: true,
-            },
-            OpsError::UndoError(msg) => ErrorMessage {
-                title: "Undo Failed".to_string(),
-                message: format!("Unable to undo operation: {}", msg),
-                suggestion: Some(
-                    "Some files may have been moved or deleted outside the application."
-                        .to_string(),
-                ),
-                recoverable: false,
-            },
-            OpsError::SpaceError(msg) => ErrorMessage {
-                title: "Insufficient Space".to_string(),
-                message: format!("Not enough disk space: {}", msg),
-                suggestion: Some("Free up disk space or choose a different location.".to_string()),
-                recoverable: true,
-            },
-            OpsError::PermissionError(msg) => ErrorMessage {
-                title: "Permission Denied".to_string(),
-                message: format!("Access denied: {}", msg),
-                suggestion: Some("Run as administrator or check file permissions.".to_string()),
-                recoverable: true,
-            },
-            OpsError::FileNotFound(msg) => ErrorMessage {
-                title: "File Not Found".to_string(),
-                message: format!("File not found: {}", msg),
-                suggestion: Some("The file may have been moved or deleted.".to_string()),
-                recoverable: false,
-            },
-            OpsError::InvalidPath(msg) => ErrorMessage {
-                title: "Invalid Path".to_string(),
-                message: format!("Invalid file path: {}", msg),
-                suggestion: Some("Check the file path and try again.".to_string()),
-                recoverable: true,
-            },
-            OpsError::CrossVolumeError(msg) => ErrorMessage {
-                title: "Cross Volume Operation".to_string(),
-                message: format!("Cannot move across volumes: {}", msg),
-                suggestion: Some(
-                    "The operation will copy and delete instead of moving.".to_string(),
-                ),
-                recoverable: true,
-            },
-            OpsError::BatchError(msg) => ErrorMessage {
-                title: "Batch Operation Failed".to_string(),
-                message: format!("Batch operation failed: {}", msg),
-                suggestion: Some(
-                    "Some files in the batch may have failed. Check individual file status."
-                        .to_string(),
-                ),
-                recoverable: true,
-            },
-            OpsError::DatabaseError(msg) => ErrorMessage {
-                title: "Database Error".to_string(),
-                message: format!("Database operation failed: {}", msg),
-                suggestion: Some("Try restarting the application.".to_string()),
-                recoverable: true,
-            },
-            OpsError::GaugeError(msg) => ErrorMessage {
-                title: "Gauge Error".to_string(),
-                message: format!("Gauge calculation failed: {}", msg),
-                suggestion: Some("Try refreshing the gauge data.".to_string()),
-                recoverable: true,
-            },
+            OpsError::ArchiveError(msg)
+            | OpsError::DeleteError(msg)
+            | OpsError::UndoError(msg)
+            | OpsError::SpaceError(msg)
+            | OpsError::PermissionError(msg)
+            | OpsError::FileNotFound(msg)
+            | OpsError::InvalidPath(msg)
+            | OpsError::CrossVolumeError(msg)
+            | OpsError::BatchError(msg)
+            | OpsError::DatabaseError(msg)
+            | OpsError::GaugeError(msg)
+            | OpsError::SystemLocation(msg) => msg,
+        }
+    }
+
+    /// The error as a catalog message with its detail as the `detail` param.
+    pub fn to_message(&self) -> Message {
+        Message::new(self.message_key()).with("detail", self.detail())
+    }
+
+    pub fn to_user_message(&self) -> ErrorMessage {
+        let key = self.message_key();
+        ErrorMessage {
+            title: Message::new(format!("{key}.title")).render(),
+            message: self.to_message().render(),
+            suggestion: Some(Message::new(format!("{key}.suggestion")).render()),
+            recoverable: !matches!(
+                self,
+                OpsError::UndoError(_) | OpsError::FileNotFound(_) | OpsError::SystemLocation(_)
+            ),
         }
     }
 
@@ -199,6 +178,10 @@ pub fn batch_error(msg: &str) -> OpsError {
     OpsError::BatchError(msg.to_string())
 }
 
+pub fn system_location(msg: &str) -> OpsError {
+    OpsError::SystemLocation(msg.to_string())
+}
+
 pub fn database_error(msg: &str) -> OpsError {
     OpsError::DatabaseError(msg.to_string())
 }
@@ -260,14 +243,14 @@ pub fn log_error(error: &OpsError, context: &ErrorContext) {
     let user_message = error.to_user_message();
     let context_str = context.to_string();
 
-    eprintln!("ERROR: {} - {}", user_message.title, user_message.message);
-    eprintln!("CONTEXT: {}", context_str);
-
-    if let Some(suggestion) = &user_message.suggestion {
-        eprintln!("SUGGESTION: {}", suggestion);
-    }
-
-    eprintln!("RECOVERABLE: {}", user_message.recoverable);
+    tracing::error!(
+        context = %context_str,
+        suggestion = user_message.suggestion.as_deref().unwrap_or(""),
+        recoverable = user_message.recoverable,
+        "{} - {}",
+        user_message.title,
+        user_message.message
+    );
 }
 
 // Error recovery strategies
@@ -0,0 +1,357 @@
+use crate::db::{Database, DbPool};
+use crate::models::{ActionType, File, StagedFileRecord};
+use crate::notifications;
+use crate::ops::compress;
+use crate::scanner::activity;
+use crate::ops::delete::DeleteManager;
+use crate::ops::error::{OpsError, OpsResult};
+use crate::ops::locks::{self, OperationGuard};
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+pub const STAGED_EXPIRED_EVENT: &str = "staged://expired";
+pub const STAGED_PURGED_EVENT: &str = "staged://purged";
+pub const STAGED_RECONCILED_EVENT: &str = "staged://reconciled";
+/// Status of a staged file whose archived copy was moved or deleted outside
+/// the app.
+pub const LOST_STATUS: &str = "lost";
+pub const AUTO_EMPTY_PREF: &str = "auto_empty_after_cooloff";
+/// Days after the cooloff ends before archived files are removed; 0 = never.
+pub const ARCHIVE_RETENTION_DAYS_PREF: &str = "archive_retention_days";
+/// `trash` or `purge`.
+pub const ARCHIVE_RETENTION_MODE_PREF: &str = "archive_retention_mode";
+pub const ARCHIVE_RETENTION_MODES: &[&str] = &["trash", "purge"];
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+static SWEEPER_STARTED: OnceCell<()> = OnceCell::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedExpiredPayload {
+    /// Files whose cooloff ended since the previous sweep.
+    pub expired_file_ids: Vec<i64>,
+    pub auto_emptied: bool,
+    pub batch_id: Option<String>,
+    pub files_emptied: usize,
+    pub bytes_freed: u64,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivePurgedPayload {
+    /// Archive batches the removed files came from; they can no longer be undone.
+    pub archive_batch_ids: Vec<String>,
+    /// Delete batch that recorded the removal.
+    pub batch_id: String,
+    pub to_trash: bool,
+    pub files_purged: usize,
+    pub bytes_freed: u64,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StagedReconcilePayload {
+    pub checked: usize,
+    /// Files whose archived copy is gone; now `lost`.
+    pub lost_file_ids: Vec<i64>,
+    /// Lost files whose copy is back, returned to staging.
+    pub found_file_ids: Vec<i64>,
+}
+
+impl StagedReconcilePayload {
+    pub fn changed(&self) -> bool {
+        !self.lost_file_ids.is_empty() || !self.found_file_ids.is_empty()
+    }
+}
+
+/// Reconciles staged files with the archive once on launch, since it can
+/// change while the app is closed, then sweeps hourly for staged files past
+/// their cooloff.
+pub fn start_expiry_sweeper<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    if SWEEPER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        match run_reconcile(&pool) {
+            Ok(payload) if payload.changed() => {
+                let _ = app.emit(STAGED_RECONCILED_EVENT, payload);
+                crate::gauge::spawn_gauge_refresh(&app);
+            }
+            Ok(_) => {}
+            Err(err) => tracing::error!("staged reconciliation failed: {err}"),
+        }
+        sweep_loop(app, pool)
+    });
+}
+
+fn sweep_loop<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    loop {
+        run_sweeps_now(&app, &pool);
+        std::thread::sleep(SWEEP_INTERVAL);
+    }
+}
+
+/// One expiry and retention sweep, also run by the idle watcher so emptying
+/// deferred while the user was busy happens once they step away.
+pub fn run_sweeps_now<R: tauri::Runtime>(app: &AppHandle<R>, pool: &DbPool) {
+    match run_sweep(pool) {
+        Ok(Some(payload)) => {
+            if let Ok(conn) = pool.get() {
+                notifications::notify_auto_emptied(app, &Database::new(conn), &payload);
+            }
+            let _ = app.emit(STAGED_EXPIRED_EVENT, payload);
+            crate::gauge::spawn_gauge_refresh(app);
+        }
+        Ok(None) => {}
+        Err(err) => tracing::error!("staged expiry sweep failed: {err}"),
+    }
+    match run_retention(pool) {
+        Ok(Some(payload)) => {
+            let _ = app.emit(STAGED_PURGED_EVENT, payload);
+            crate::gauge::spawn_gauge_refresh(app);
+        }
+        Ok(None) => {}
+        Err(err) => tracing::error!("archive retention sweep failed: {err}"),
+    }
+    if let Ok(conn) = pool.get() {
+        notifications::notify_expiring_batches(app, &Database::new(conn));
+    }
+}
+
+fn run_reconcile(pool: &DbPool) -> OpsResult<StagedReconcilePayload> {
+    let conn = pool
+        .get()
+        .map_err(|e| OpsError::DatabaseError(format!("db pool: {}", e)))?;
+    reconcile_staged(&Database::new(conn), Utc::now())
+}
+
+/// Whether a staged file's archived copy is still on disk, looking inside
+/// compressed batches for zip members.
+pub fn archived_copy_exists(path: &str) -> bool {
+    let path = Path::new(path);
+    match compress::split_member_path(path) {
+        Some((zip_path, entry)) => compress::has_entry(&zip_path, &entry),
+        None => fs::symlink_metadata(path).is_ok(),
+    }
+}
+
+/// Checks each staged file's archived copy. Missing ones become `lost`, which
+/// restore, expiry and the gauge all leave out; lost files whose copy has
+/// reappeared go back to `staged`, or `expired` if their cooloff has ended.
+pub fn reconcile_staged(db: &Database, now: DateTime<Utc>) -> OpsResult<StagedReconcilePayload> {
+    let statuses = ["staged", "expired", LOST_STATUS].map(String::from);
+    let records = db.list_staged_with_files(Some(&statuses))?;
+    let mut payload = StagedReconcilePayload {
+        checked: records.len(),
+        ..Default::default()
+    };
+    let mut staged = Vec::new();
+    let mut expired = Vec::new();
+    for (record, file) in records {
+        let exists = archived_copy_exists(&file.path);
+        if record.status.eq_ignore_ascii_case(LOST_STATUS) {
+            if !exists {
+                continue;
+            }
+            if record.expires_at.is_some_and(|at| at <= now) {
+                expired.push(record.file_id);
+            } else {
+                staged.push(record.file_id);
+            }
+            payload.found_file_ids.push(record.file_id);
+        } else if !exists {
+            payload.lost_file_ids.push(record.file_id);
+        }
+    }
+    db.update_staged_status(&payload.lost_file_ids, LOST_STATUS)?;
+    db.update_staged_status(&staged, "staged")?;
+    db.update_staged_status(&expired, "expired")?;
+    Ok(payload)
+}
+
+fn run_sweep(pool: &DbPool) -> OpsResult<Option<StagedExpiredPayload>> {
+    let conn = pool
+        .get()
+        .map_err(|e| OpsError::DatabaseError(format!("db pool: {}", e)))?;
+    let db = Database::new(conn);
+    let auto_empty = db
+        .get_preference(AUTO_EMPTY_PREF)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(false);
+    // Expired files stay expired, so a deferred empty happens on a later sweep.
+    let auto_empty = auto_empty && activity::heavy_work_allowed(&db);
+    sweep_expired(&db, Utc::now(), auto_empty)
+}
+
+fn run_retention(pool: &DbPool) -> OpsResult<Option<ArchivePurgedPayload>> {
+    let conn = pool
+        .get()
+        .map_err(|e| OpsError::DatabaseError(format!("db pool: {}", e)))?;
+    let db = Database::new(conn);
+    let days: i64 = db
+        .get_preference(ARCHIVE_RETENTION_DAYS_PREF)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    if days <= 0 || !activity::heavy_work_allowed(&db) {
+        return Ok(None);
+    }
+    sweep_retention(&db, Utc::now(), days, retention_to_trash(&db)?)
+}
+
+/// Whether the retention mode pref sends files to the trash (the default)
+/// rather than deleting them permanently.
+pub fn retention_to_trash(db: &Database) -> OpsResult<bool> {
+    Ok(db.get_preference(ARCHIVE_RETENTION_MODE_PREF)?.as_deref() != Some("purge"))
+}
+
+/// Removes archived files whose cooloff ended more than `retention_days`
+/// before `now`.
+pub fn sweep_retention(
+    db: &Database,
+    now: DateTime<Utc>,
+    retention_days: i64,
+    to_trash: bool,
+) -> OpsResult<Option<ArchivePurgedPayload>> {
+    let cutoff = now - chrono::Duration::days(retention_days);
+    let mut records = db.list_expired_staged("staged", &cutoff)?;
+    records.extend(db.list_expired_staged("expired", &cutoff)?);
+    let _lock = lock_idle_records("retention", &mut records);
+    purge_staged(db, records, to_trash)
+}
+
+/// Claims the files of `records` for a sweep. Files another operation holds
+/// are dropped from `records` and wait for a later sweep.
+fn lock_idle_records(
+    kind: &str,
+    records: &mut Vec<(StagedFileRecord, File)>,
+) -> Option<OperationGuard> {
+    loop {
+        let file_ids: Vec<i64> = records.iter().map(|(record, _)| record.file_id).collect();
+        if file_ids.is_empty() {
+            return None;
+        }
+        match locks::lock_files(kind, &file_ids) {
+            Ok(guard) => return Some(guard),
+            Err(busy) => {
+                tracing::info!(?busy, "{kind} sweep skipping busy files");
+                records.retain(|(record, _)| !busy.file_ids.contains(&record.file_id));
+            }
+        }
+    }
+}
+
+/// Deletes the given staged files through [`DeleteManager`], so the removal
+/// is recorded as a delete batch, and takes them out of the staging area.
+/// Callers hold the files' lock.
+pub fn purge_staged(
+    db: &Database,
+    records: Vec<(StagedFileRecord, File)>,
+    to_trash: bool,
+) -> OpsResult<Option<ArchivePurgedPayload>> {
+    if records.is_empty() {
+        return Ok(None);
+    }
+    let file_paths: Vec<String> = records.iter().map(|(_, file)| file.path.clone()).collect();
+    let mut delete_manager = DeleteManager::new();
+    delete_manager.set_use_trash(to_trash);
+    delete_manager.set_action_note(Some("archive retention".to_string()));
+    delete_manager.set_batch_origin("retention");
+    let result = delete_manager.delete_files(file_paths, db)?;
+
+    let purged: HashSet<i64> = db
+        .get_actions_by_batch_id(&result.batch_id)?
+        .into_iter()
+        .filter(|action| action.action == ActionType::Delete)
+        .map(|action| action.file_id)
+        .collect();
+    let mut archive_batch_ids: Vec<String> = records
+        .iter()
+        .filter(|(record, _)| purged.contains(&record.file_id))
+        .filter_map(|(record, _)| record.batch_id.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    archive_batch_ids.sort();
+    let purged: Vec<i64> = purged.into_iter().collect();
+    db.mark_files_unstaged(&purged)?;
+
+    Ok(Some(ArchivePurgedPayload {
+        archive_batch_ids,
+        batch_id: result.batch_id,
+        to_trash,
+        files_purged: result.files_deleted,
+        bytes_freed: result.total_bytes_freed,
+        errors: result.errors,
+    }))
+}
+
+/// Moves newly expired staged files to the `expired` status and, when
+/// `auto_empty` is set, sends every expired file to the trash through
+/// [`DeleteManager`] so the deletes show up in undo and the gauge.
+///
+/// Returns `None` when there was nothing to report.
+pub fn sweep_expired(
+    db: &Database,
+    now: DateTime<Utc>,
+    auto_empty: bool,
+) -> OpsResult<Option<StagedExpiredPayload>> {
+    let newly_expired: Vec<i64> = db
+        .list_expired_staged("staged", &now)?
+        .into_iter()
+        .map(|(record, _)| record.file_id)
+        .collect();
+    db.update_staged_status(&newly_expired, "expired")?;
+
+    if !auto_empty {
+        if newly_expired.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(StagedExpiredPayload {
+            expired_file_ids: newly_expired,
+            auto_emptied: false,
+            batch_id: None,
+            files_emptied: 0,
+            bytes_freed: 0,
+            errors: Vec::new(),
+        }));
+    }
+
+    let mut expired = db.list_expired_staged("expired", &now)?;
+    let _lock = lock_idle_records("expiry", &mut expired);
+    if expired.is_empty() {
+        return Ok(None);
+    }
+    let file_paths: Vec<String> = expired.iter().map(|(_, file)| file.path.clone()).collect();
+
+    let mut delete_manager = DeleteManager::new();
+    delete_manager.set_use_trash(true);
+    delete_manager.set_batch_origin("expiry");
+    let result = delete_manager.delete_files(file_paths, db)?;
+
+    // Only entries that actually made it to the trash leave the staging area.
+    let emptied: Vec<i64> = db
+        .get_actions_by_batch_id(&result.batch_id)?
+        .into_iter()
+        .filter(|action| action.action == ActionType::Delete)
+        .map(|action| action.file_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    db.mark_files_unstaged(&emptied)?;
+
+    Ok(Some(StagedExpiredPayload {
+        expired_file_ids: newly_expired,
+        auto_emptied: true,
+        batch_id: Some(result.batch_id),
+        files_emptied: result.files_deleted,
+        bytes_freed: result.total_bytes_freed,
+        errors: result.errors,
+    }))
+}
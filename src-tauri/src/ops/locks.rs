@@ -0,0 +1,89 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static ACTIVE_OPERATIONS: Lazy<Mutex<HashMap<u64, ActiveOperation>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A stage, delete, restore or undo currently holding its files.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveOperation {
+    pub id: u64,
+    pub kind: String,
+    pub file_ids: Vec<i64>,
+    pub started_at: i64,
+}
+
+/// Why a batch was refused: the files another operation already holds.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileBusy {
+    pub file_ids: Vec<i64>,
+    pub operation: String,
+    pub operation_id: u64,
+}
+
+/// Holds the files of one operation until dropped.
+#[derive(Debug)]
+pub struct OperationGuard {
+    id: u64,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        ACTIVE_OPERATIONS
+            .lock()
+            .expect("active operations lock")
+            .remove(&self.id);
+    }
+}
+
+/// Claims `file_ids` for `kind`. Fails without claiming anything if any of
+/// them belong to a running operation; the first such operation is reported.
+pub fn lock_files(kind: &str, file_ids: &[i64]) -> Result<OperationGuard, FileBusy> {
+    let mut active = ACTIVE_OPERATIONS.lock().expect("active operations lock");
+    for operation in active.values() {
+        let busy: Vec<i64> = file_ids
+            .iter()
+            .filter(|id| operation.file_ids.contains(id))
+            .copied()
+            .collect();
+        if !busy.is_empty() {
+            return Err(FileBusy {
+                file_ids: busy,
+                operation: operation.kind.clone(),
+                operation_id: operation.id,
+            });
+        }
+    }
+
+    let id = NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed);
+    let mut held = file_ids.to_vec();
+    held.sort_unstable();
+    held.dedup();
+    active.insert(
+        id,
+        ActiveOperation {
+            id,
+            kind: kind.to_string(),
+            file_ids: held,
+            started_at: Utc::now().timestamp(),
+        },
+    );
+    Ok(OperationGuard { id })
+}
+
+/// Running operations, oldest first.
+pub fn active_operations() -> Vec<ActiveOperation> {
+    let mut operations: Vec<ActiveOperation> = ACTIVE_OPERATIONS
+        .lock()
+        .expect("active operations lock")
+        .values()
+        .cloned()
+        .collect();
+    operations.sort_by_key(|operation| operation.id);
+    operations
+}
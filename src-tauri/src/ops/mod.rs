@@ -1,12 +1,27 @@
 pub mod archive;
+pub mod cancel;
+pub mod compress;
+pub mod dedupe;
 pub mod delete;
 pub mod error;
+pub mod expiry;
+pub mod locks;
+pub mod progress;
 pub mod space;
+pub mod system_paths;
+pub mod trash;
 pub mod undo;
+pub mod verify;
 
-pub use archive::{ArchiveConfig, ArchiveManager, ArchiveProgress, ArchiveResult};
+pub use archive::{
+    ArchiveConfig, ArchiveManager, ArchiveMigration, ArchiveProgress, ArchiveResult,
+};
+pub use compress::ArchiveCompression;
 pub use delete::{DeleteCandidate, DeleteConfig, DeleteManager, DeleteResult};
 pub use error::{ErrorContext, ErrorMessage, OpsError, OpsResult};
+pub use progress::{
+    OpsDonePayload, OpsProgressPayload, ProgressCallback, OPS_DONE_EVENT, OPS_PROGRESS_EVENT,
+};
 pub use space::{SpaceCheck, SpaceInfo, SpaceManager};
 pub use undo::{BatchInfo, UndoManager, UndoResult};
 
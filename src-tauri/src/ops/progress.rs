@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::fs;
+
+pub const OPS_PROGRESS_EVENT: &str = "ops://progress";
+pub const OPS_DONE_EVENT: &str = "ops://done";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsProgressPayload {
+    pub batch_id: String,
+    pub operation: String,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsDonePayload {
+    pub batch_id: String,
+    pub operation: String,
+    pub files_processed: usize,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Invoked by the managers after each file; commands forward it to the UI.
+pub type ProgressCallback = Box<dyn Fn(&OpsProgressPayload) + Send + Sync>;
+
+/// Tracks per-batch counters and reports them through an optional callback.
+pub struct ProgressReporter<'a> {
+    callback: Option<&'a ProgressCallback>,
+    payload: OpsProgressPayload,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(
+        callback: Option<&'a ProgressCallback>,
+        operation: &str,
+        batch_id: &str,
+        file_paths: &[String],
+    ) -> Self {
+        let bytes_total = if callback.is_some() {
+            file_paths
+                .iter()
+                .filter_map(|path| fs::metadata(path).ok())
+                .map(|meta| meta.len())
+                .sum()
+        } else {
+            0
+        };
+
+        Self {
+            callback,
+            payload: OpsProgressPayload {
+                batch_id: batch_id.to_string(),
+                operation: operation.to_string(),
+                files_done: 0,
+                files_total: file_paths.len(),
+                bytes_done: 0,
+                bytes_total,
+                current_path: None,
+            },
+        }
+    }
+
+    pub fn file_done(&mut self, path: &str, bytes: u64) {
+        self.payload.files_done += 1;
+        self.payload.bytes_done += bytes;
+        self.payload.current_path = Some(path.to_string());
+        if let Some(callback) = self.callback {
+            callback(&self.payload);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::ops::error::{OpsError, OpsResult};
+use serde::Serialize;
 use std::fs;
+#[cfg(windows)]
 use std::os::windows::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 
@@ -11,10 +13,12 @@ pub struct SpaceInfo {
     pub free_percentage: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SpaceCheck {
     pub path: String,
+    /// Bytes needed including `buffer_bytes`.
     pub required_bytes: u64,
+    pub buffer_bytes: u64,
     pub available_bytes: u64,
     pub sufficient: bool,
     pub free_percentage: f64,
@@ -119,22 +123,35 @@ impl SpaceManager {
     }
 
     #[cfg(unix)]
-    fn find_mount_point(&self, path: &Path, device_id: u64) -> OpsResult<PathBuf> {
-        // Simple implementation - in practice, you'd parse /proc/mounts
-        // For now, just return the path itself
+    fn find_mount_point(&self, path: &Path, _device_id: u64) -> OpsResult<PathBuf> {
+        // statvfs reports on whichever filesystem holds the path, so any
+        // existing path on the device is good enough
         Ok(path.to_path_buf())
     }
 
     #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)] // statvfs field widths differ between platforms
     fn read_filesystem_info(&self, mount_point: &Path) -> OpsResult<u64> {
-        use std::os::unix::fs::MetadataExt;
-
-        let metadata = fs::metadata(mount_point)
-            .map_err(|e| OpsError::SpaceError(format!("Failed to get metadata: {}", e)))?;
+        let stats = self.statvfs(mount_point)?;
+        Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
 
-        // For Unix systems, we can use statvfs for more accurate space info
-        // For now, return the available space from metadata
-        Ok(metadata.blocks() * 512) // blocks * block_size
+    #[cfg(unix)]
+    fn statvfs(&self, path: &Path) -> OpsResult<libc::statvfs> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| OpsError::InvalidPath(path.display().to_string()))?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return Err(OpsError::SpaceError(format!(
+                "Failed to read filesystem info for {}: {}",
+                path.display(),
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(stats)
     }
 
     pub fn get_space_info(&self, path: &Path) -> OpsResult<SpaceInfo> {
@@ -204,15 +221,10 @@ impl SpaceManager {
     }
 
     #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)] // statvfs field widths differ between platforms
     fn get_total_space_unix(&self, path: &Path) -> OpsResult<u64> {
-        use std::os::unix::fs::MetadataExt;
-
-        let metadata = fs::metadata(path)
-            .map_err(|e| OpsError::SpaceError(format!("Failed to get metadata: {}", e)))?;
-
-        // For Unix systems, we can use statvfs for more accurate space info
-        // For now, return the total space from metadata
-        Ok(metadata.blocks() * 512) // blocks * block_size
+        let stats = self.statvfs(path)?;
+        Ok(stats.f_blocks as u64 * stats.f_frsize as u64)
     }
 
     pub fn check_space_requirements(
@@ -231,6 +243,7 @@ impl SpaceManager {
             checks.push(SpaceCheck {
                 path: path_str,
                 required_bytes,
+                buffer_bytes: 0,
                 available_bytes: available,
                 sufficient: available >= required_bytes,
                 free_percentage,
@@ -240,6 +253,62 @@ impl SpaceManager {
         Ok(checks)
     }
 
+    /// Checks that the volume holding `destination` can take `bytes` more
+    /// plus `buffer_percentage` of it. `destination` does not have to exist
+    /// yet; its nearest existing ancestor decides the volume.
+    pub fn check_destination(
+        &self,
+        destination: &Path,
+        bytes: u64,
+        buffer_percentage: f64,
+    ) -> OpsResult<SpaceCheck> {
+        let volume = Self::existing_ancestor(destination);
+        let available = self.get_available_space(&volume)?;
+        let total = self.get_total_space(&volume)?;
+        let buffer_bytes = (bytes as f64 * buffer_percentage / 100.0) as u64;
+        let required_bytes = bytes + buffer_bytes;
+
+        Ok(SpaceCheck {
+            path: destination.to_string_lossy().to_string(),
+            required_bytes,
+            buffer_bytes,
+            available_bytes: available,
+            sufficient: available >= required_bytes,
+            free_percentage: if total > 0 {
+                (available as f64 / total as f64) * 100.0
+            } else {
+                0.0
+            },
+        })
+    }
+
+    /// Whether moving `source` into `destination` is a rename on one volume,
+    /// which needs no extra space.
+    pub fn same_volume(&self, source: &Path, destination: &Path) -> bool {
+        let destination = Self::existing_ancestor(destination);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            match (fs::symlink_metadata(source), fs::metadata(&destination)) {
+                (Ok(a), Ok(b)) => a.dev() == b.dev(),
+                _ => false,
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            source.components().next() == destination.components().next()
+        }
+    }
+
+    fn existing_ancestor(path: &Path) -> PathBuf {
+        path.ancestors()
+            .find(|ancestor| ancestor.exists())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
     pub fn format_bytes(&self, bytes: u64) -> String {
         const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
         const THRESHOLD: u64 = 1024;
@@ -0,0 +1,111 @@
+use crate::ops::error::{OpsError, OpsResult};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+
+/// Bundle extensions; everything inside such a folder belongs to its app.
+const BUNDLE_EXTENSIONS: &[&str] = &["app", "framework", "bundle", "kext", "plugin"];
+
+static SYSTEM_LOCATIONS: Lazy<Vec<PathBuf>> = Lazy::new(platform_locations);
+
+#[cfg(target_os = "macos")]
+fn platform_locations() -> Vec<PathBuf> {
+    let mut locations: Vec<PathBuf> = [
+        "/System",
+        "/Library",
+        "/Applications",
+        "/usr",
+        "/bin",
+        "/sbin",
+        "/etc",
+        "/private/etc",
+        "/opt",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    if let Some(home) = dirs::home_dir() {
+        locations.push(home.join("Library"));
+        locations.push(home.join("Applications"));
+    }
+    locations
+}
+
+#[cfg(windows)]
+fn platform_locations() -> Vec<PathBuf> {
+    let mut locations: Vec<PathBuf> =
+        ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)", "ProgramData"]
+            .iter()
+            .filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+            .collect();
+    if locations.is_empty() {
+        locations.push(PathBuf::from(r"C:\Windows"));
+        locations.push(PathBuf::from(r"C:\Program Files"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        locations.push(home.join("AppData"));
+    }
+    locations
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_locations() -> Vec<PathBuf> {
+    let mut locations: Vec<PathBuf> = [
+        "/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/opt", "/proc", "/sbin",
+        "/snap", "/sys", "/usr", "/var",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    if let Some(home) = dirs::home_dir() {
+        locations.push(home.join(".config"));
+        locations.push(home.join(".local/share/flatpak"));
+        locations.push(home.join(".var/app"));
+    }
+    locations
+}
+
+/// Folders that are off limits on this platform.
+pub fn system_locations() -> &'static [PathBuf] {
+    &SYSTEM_LOCATIONS
+}
+
+/// Whether `path` is inside a system location or an application bundle.
+/// A broad watched root (`~`, a whole drive) reaches into these, and what
+/// lives there is never suggested, archived or deleted.
+pub fn is_system_path(path: &Path) -> bool {
+    system_locations().iter().any(|dir| within(path, dir)) || inside_bundle(path)
+}
+
+/// Refuses `path` when it is inside a system location or bundle.
+pub fn ensure_not_system_path(path: &Path) -> OpsResult<()> {
+    if is_system_path(path) {
+        return Err(OpsError::SystemLocation(path.display().to_string()));
+    }
+    Ok(())
+}
+
+/// True for anything below a `.app`-style folder; the bundle itself is
+/// fine to remove as a whole.
+fn inside_bundle(path: &Path) -> bool {
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    parent.ancestors().any(|ancestor| {
+        ancestor
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| BUNDLE_EXTENSIONS.iter().any(|b| b.eq_ignore_ascii_case(ext)))
+    })
+}
+
+#[cfg(windows)]
+fn within(path: &Path, dir: &Path) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    let dir = dir.to_string_lossy().to_lowercase();
+    Path::new(&path).starts_with(Path::new(&dir))
+}
+
+#[cfg(not(windows))]
+fn within(path: &Path, dir: &Path) -> bool {
+    path.starts_with(dir)
+}
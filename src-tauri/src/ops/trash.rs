@@ -0,0 +1,190 @@
+use crate::ops::error::{OpsError, OpsResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefix for trash locations that are platform trash item ids rather than
+/// plain file paths (XDG `.trashinfo` paths, Recycle Bin parsing names).
+pub const TRASH_ITEM_PREFIX: &str = "trash-item:";
+
+/// Moves a file to the platform trash.
+///
+/// Returns `(location, trash_dir)` where `location` is what gets stored in the
+/// action's `dst_path` and later handed back to [`restore_from_trash`].
+pub fn move_to_trash(path: &Path) -> OpsResult<(String, Option<String>)> {
+    #[cfg(target_os = "macos")]
+    {
+        move_to_finder_trash(path)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        move_to_system_trash(path)
+    }
+}
+
+/// Puts a trashed file back at `original`.
+pub fn restore_from_trash(location: &str, original: &Path) -> OpsResult<()> {
+    if original.exists() {
+        return Err(OpsError::UndoError(format!(
+            "Destination already exists: {}",
+            original.display()
+        )));
+    }
+
+    if let Some(parent) = original.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| {
+                OpsError::UndoError(format!("Failed to create parent directory: {}", e))
+            })?;
+        }
+    }
+
+    match location.strip_prefix(TRASH_ITEM_PREFIX) {
+        Some(id) => restore_trash_item(id, original),
+        None => {
+            if !Path::new(location).exists() {
+                return Err(OpsError::UndoError(format!(
+                    "Trash file not found: {}",
+                    location
+                )));
+            }
+            fs::rename(location, original)
+                .map_err(|e| OpsError::UndoError(format!("Failed to restore from trash: {}", e)))
+        }
+    }
+}
+
+/// Whether the trashed file is still present (i.e. the trash was not emptied).
+pub fn trash_entry_exists(location: &str) -> bool {
+    match location.strip_prefix(TRASH_ITEM_PREFIX) {
+        Some(id) => find_trash_item(id).is_some(),
+        None => Path::new(location).exists(),
+    }
+}
+
+/// Per-user trash folders on this platform. Volume trashes (`.Trashes`,
+/// `.Trash-<uid>`, `$RECYCLE.BIN`) are recognised by name in the scanner.
+pub fn trash_dirs() -> Vec<PathBuf> {
+    #[allow(unused_mut)] // Nothing to add on Windows.
+    let mut found = Vec::new();
+    #[cfg(target_os = "macos")]
+    if let Some(home) = dirs::home_dir() {
+        found.push(home.join(".Trash"));
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if let Some(data) = dirs::data_dir() {
+        found.push(data.join("Trash"));
+    }
+    found
+}
+
+#[cfg(target_os = "macos")]
+fn move_to_finder_trash(path: &Path) -> OpsResult<(String, Option<String>)> {
+    let trash_dir = dirs::home_dir()
+        .map(|home| home.join(".Trash"))
+        .ok_or_else(|| OpsError::DeleteError("Cannot determine home directory".to_string()))?;
+    if !trash_dir.exists() {
+        fs::create_dir_all(&trash_dir).map_err(|e| {
+            OpsError::DeleteError(format!("Failed to create trash directory: {}", e))
+        })?;
+    }
+
+    let trash_path = unique_destination(&trash_dir, path)?;
+    fs::rename(path, &trash_path)
+        .map_err(|e| OpsError::DeleteError(format!("Failed to move to trash: {}", e)))?;
+
+    Ok((
+        trash_path.to_string_lossy().to_string(),
+        Some(trash_dir.to_string_lossy().to_string()),
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn unique_destination(dir: &Path, path: &Path) -> OpsResult<PathBuf> {
+    let filename = path
+        .file_name()
+        .ok_or_else(|| OpsError::DeleteError("Invalid file path".to_string()))?;
+    let mut candidate = dir.join(filename);
+
+    // Handle conflicts by appending " (n)" suffix
+    let mut counter = 1;
+    while candidate.exists() {
+        let stem = path
+            .file_stem()
+            .ok_or_else(|| OpsError::DeleteError("Invalid file name".to_string()))?
+            .to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        candidate = dir.join(format!("{} ({}){}", stem, counter, extension));
+        counter += 1;
+    }
+
+    Ok(candidate)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn move_to_system_trash(path: &Path) -> OpsResult<(String, Option<String>)> {
+    let original: PathBuf = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    trash::delete(&original)
+        .map_err(|e| OpsError::DeleteError(format!("Failed to move to trash: {}", e)))?;
+
+    // The trash API does not hand back the new location, so look up the most
+    // recent item that came from this path.
+    let item = trash::os_limited::list()
+        .ok()
+        .and_then(|items| {
+            items
+                .into_iter()
+                .filter(|item| item.original_path() == original)
+                .max_by_key(|item| item.time_deleted)
+        })
+        .ok_or_else(|| {
+            OpsError::DeleteError(format!(
+                "Moved {} to trash but could not locate the trash entry",
+                original.display()
+            ))
+        })?;
+
+    Ok((
+        format!("{}{}", TRASH_ITEM_PREFIX, item.id.to_string_lossy()),
+        None,
+    ))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn find_trash_item(id: &str) -> Option<trash::TrashItem> {
+    trash::os_limited::list()
+        .ok()?
+        .into_iter()
+        .find(|item| item.id.to_string_lossy() == id)
+}
+
+#[cfg(target_os = "macos")]
+fn find_trash_item(_id: &str) -> Option<()> {
+    None
+}
+
+#[cfg(not(target_os = "macos"))]
+fn restore_trash_item(id: &str, original: &Path) -> OpsResult<()> {
+    let item = find_trash_item(id)
+        .ok_or_else(|| OpsError::UndoError(format!("Trash entry not found: {}", id)))?;
+    if item.original_path() != original {
+        return Err(OpsError::UndoError(format!(
+            "Trash entry {} belongs to {}",
+            id,
+            item.original_path().display()
+        )));
+    }
+    trash::os_limited::restore_all(vec![item])
+        .map_err(|e| OpsError::UndoError(format!("Failed to restore from trash: {}", e)))
+}
+
+#[cfg(target_os = "macos")]
+fn restore_trash_item(id: &str, _original: &Path) -> OpsResult<()> {
+    Err(OpsError::UndoError(format!(
+        "Trash entry {} cannot be restored on this platform",
+        id
+    )))
+}
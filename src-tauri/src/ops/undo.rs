@@ -1,8 +1,10 @@
 use crate::db::Database;
 use crate::models::{Action, ActionType, NewAction};
+use crate::ops::compress;
 use crate::ops::error::{OpsError, OpsResult};
+use crate::ops::trash;
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
@@ -36,63 +38,7 @@ impl UndoManager {
         }
     }
 
-    pub fn undo_last(&mut self, db: &Database) -> OpsResult<UndoResult> {
-        let start_time = std::time::SystemTime::now();
-
-        // Get the most recent batch
-        let batch_info = self.get_last_batch(db)?;
-
-        if !self.supported_actions.contains(&batch_info.action_type) {
-            return Err(OpsError::UndoError(format!(
-                "Cannot undo action type: {:?}",
-                batch_info.action_type
-            )));
-        }
-
-        let mut actions_reversed = 0;
-        let mut files_restored = 0;
-        let mut errors = Vec::new();
-        let mut rollback_performed = false;
-
-        // Attempt to reverse each action in the batch
-        for action in &batch_info.actions {
-            match self.reverse_action(action, db) {
-                Ok(_) => {
-                    actions_reversed += 1;
-                    files_restored += 1;
-                }
-                Err(e) => {
-                    errors.push(format!(
-                        "Failed to reverse action {}: {}",
-                        action.id.unwrap_or(0),
-                        e
-                    ));
-
-                    // If any action fails, perform rollback
-                    if !rollback_performed {
-                        self.rollback_batch(&batch_info, db)?;
-                        rollback_performed = true;
-                    }
-                }
-            }
-        }
-
-        let duration = start_time
-            .elapsed()
-            .unwrap_or(std::time::Duration::from_secs(0));
-        let duration_ms = duration.as_millis() as u64;
-
-        Ok(UndoResult {
-            batch_id: batch_info.batch_id.clone(),
-            actions_reversed,
-            files_restored,
-            duration_ms,
-            errors,
-            rollback_performed,
-        })
-    }
-
-    fn get_last_batch(&self, db: &Database) -> OpsResult<BatchInfo> {
+    pub fn get_last_batch(&self, db: &Database) -> OpsResult<BatchInfo> {
         let batch_id = db
             .get_latest_batch_id()
             .map_err(|e| OpsError::UndoError(format!("Failed to get latest batch: {}", e)))?
@@ -119,8 +65,8 @@ impl UndoManager {
     }
 
     pub fn undo_batch(&mut self, target_batch_id: &str, db: &Database) -> OpsResult<UndoResult> {
-        // Fetch the batch by id and then reuse the same reverse logic as undo_last
         let start_time = std::time::SystemTime::now();
+        let _span = tracing::info_span!("undo", batch_id = %target_batch_id).entered();
 
         let batch_info = self.get_batch_by_id(target_batch_id, db)?;
 
@@ -137,8 +83,11 @@ impl UndoManager {
         let mut rollback_performed = false;
 
         for action in &batch_info.actions {
-            match self.reverse_action(action, db) {
-                Ok(_) => {
+            match self
+                .reverse_action(action, db)
+                .and_then(|_| self.log_restore_action(action, db))
+            {
+                Ok(()) => {
                     actions_reversed += 1;
                     files_restored += 1;
                 }
@@ -157,6 +106,10 @@ impl UndoManager {
             }
         }
 
+        if errors.is_empty() {
+            self.discard_restored_archives(&batch_info);
+        }
+
         let duration = start_time
             .elapsed()
             .unwrap_or(std::time::Duration::from_secs(0));
@@ -172,25 +125,157 @@ impl UndoManager {
         })
     }
 
-    fn reverse_action(&self, action: &Action, db: &Database) -> OpsResult<()> {
+    /// Restores only `file_ids` from the batch. Every chosen file must still
+    /// have its archived or trashed copy, otherwise nothing is touched. The
+    /// restored files get `restore` actions so the rest of the batch stays
+    /// undoable on its own.
+    pub fn undo_files(
+        &mut self,
+        target_batch_id: &str,
+        file_ids: &[i64],
+        db: &Database,
+    ) -> OpsResult<UndoResult> {
+        let start_time = std::time::SystemTime::now();
+        let _span = tracing::info_span!("undo", batch_id = %target_batch_id).entered();
+
+        let batch_info = self.get_batch_by_id(target_batch_id, db)?;
+        if !self.supported_actions.contains(&batch_info.action_type) {
+            return Err(OpsError::UndoError(format!(
+                "Cannot undo action type: {:?}",
+                batch_info.action_type
+            )));
+        }
+
+        let wanted: HashSet<i64> = file_ids.iter().copied().collect();
+        let chosen: Vec<Action> = batch_info
+            .actions
+            .iter()
+            .filter(|action| wanted.contains(&action.file_id))
+            .cloned()
+            .collect();
+        let found: HashSet<i64> = chosen.iter().map(|action| action.file_id).collect();
+        let mut problems: Vec<String> = wanted
+            .difference(&found)
+            .map(|file_id| {
+                format!(
+                    "File {} is not undoable in batch {}",
+                    file_id, target_batch_id
+                )
+            })
+            .collect();
+        problems.extend(
+            chosen
+                .iter()
+                .filter(|action| !self.can_restore_action(action))
+                .map(|action| {
+                    format!(
+                        "Archived copy of {} is missing or its original location is taken",
+                        action.src_path.as_deref().unwrap_or("?")
+                    )
+                }),
+        );
+        if !problems.is_empty() {
+            problems.sort();
+            return Err(OpsError::UndoError(problems.join("; ")));
+        }
+
+        let mut actions_reversed = 0;
+        let mut errors = Vec::new();
+        let mut restored = Vec::new();
+        for action in &chosen {
+            match self
+                .reverse_action(action, db)
+                .and_then(|_| self.log_restore_action(action, db))
+            {
+                Ok(()) => {
+                    actions_reversed += 1;
+                    restored.push(action.clone());
+                }
+                Err(e) => errors.push(format!(
+                    "Failed to reverse action {}: {}",
+                    action.id.unwrap_or(0),
+                    e
+                )),
+            }
+        }
+
+        // Members restored by earlier calls count too, so the zip goes once
+        // its last file is out.
+        if let Ok(actions) = db.get_actions_by_batch_id(target_batch_id) {
+            let restored_ids: HashSet<i64> = actions
+                .iter()
+                .filter(|action| action.action == ActionType::Restore)
+                .map(|action| action.file_id)
+                .collect();
+            restored.extend(actions.into_iter().filter(|action| {
+                action.action == ActionType::Archive && restored_ids.contains(&action.file_id)
+            }));
+        }
+        self.discard_restored_archives(&BatchInfo {
+            actions: restored,
+            ..batch_info
+        });
+
+        let duration_ms = start_time
+            .elapsed()
+            .unwrap_or(std::time::Duration::from_secs(0))
+            .as_millis() as u64;
+
+        Ok(UndoResult {
+            batch_id: target_batch_id.to_string(),
+            actions_reversed,
+            files_restored: actions_reversed,
+            duration_ms,
+            errors,
+            rollback_performed: false,
+        })
+    }
+
+    pub(crate) fn reverse_action(&self, action: &Action, db: &Database) -> OpsResult<()> {
         match action.action {
             ActionType::Archive => {
                 self.restore_from_archive(action)?;
-                if let Some(original_path) = action.src_path.as_ref() {
-                    db.update_file_location(action.file_id, original_path)
-                        .map_err(|e| {
-                            OpsError::UndoError(format!("Failed to reset file location: {}", e))
-                        })?;
-                }
-                db.mark_files_unstaged(&[action.file_id]).map_err(|e| {
-                    OpsError::UndoError(format!("Failed to clear staged flag: {}", e))
-                })?;
-                Ok(())
+                db.transaction(|| {
+                    if let Some(original_path) = action.src_path.as_ref() {
+                        db.update_file_location(action.file_id, original_path)
+                            .map_err(|e| {
+                                OpsError::UndoError(format!(
+                                    "Failed to reset file location: {}",
+                                    e
+                                ))
+                            })?;
+                    }
+                    db.mark_files_unstaged(&[action.file_id]).map_err(|e| {
+                        OpsError::UndoError(format!("Failed to clear staged flag: {}", e))
+                    })
+                })
+            }
+            ActionType::Delete => {
+                self.restore_from_trash(action)?;
+                db.transaction(|| {
+                    if let Some(original_path) = action.src_path.as_ref() {
+                        db.update_file_location(action.file_id, original_path)
+                            .map_err(|e| {
+                                OpsError::UndoError(format!(
+                                    "Failed to reset file location: {}",
+                                    e
+                                ))
+                            })?;
+                    }
+                    db.mark_file_restored(action.file_id).map_err(|e| {
+                        OpsError::UndoError(format!("Failed to clear deleted flag: {}", e))
+                    })?;
+                    db.mark_files_unstaged(&[action.file_id]).map_err(|e| {
+                        OpsError::UndoError(format!("Failed to clear staged flag: {}", e))
+                    })
+                })
             }
-            ActionType::Delete => self.restore_from_trash(action),
             ActionType::Restore => Err(OpsError::UndoError(
                 "Cannot undo restore action".to_string(),
             )),
+            ActionType::Move => Err(OpsError::UndoError(
+                "Cannot undo a move made outside the app".to_string(),
+            )),
         }
     }
 
@@ -211,6 +296,10 @@ impl UndoManager {
             )));
         }
 
+        if let Some((zip_path, entry)) = compress::split_member_path(Path::new(src_path)) {
+            return compress::extract_entry(&zip_path, &entry, Path::new(dst_path));
+        }
+
         // Check if archive file exists
         if !Path::new(src_path).exists() {
             return Err(OpsError::UndoError(format!(
@@ -236,44 +325,26 @@ impl UndoManager {
     }
 
     fn restore_from_trash(&self, action: &Action) -> OpsResult<()> {
-        let src_path = action.dst_path.as_ref().ok_or_else(|| {
+        let trash_location = action.dst_path.as_ref().ok_or_else(|| {
             OpsError::UndoError("No destination path for delete action".to_string())
         })?;
-        let dst_path = action
+        let original = action
             .src_path
             .as_ref()
             .ok_or_else(|| OpsError::UndoError("No source path for delete action".to_string()))?;
 
-        // Check if destination already exists
-        if Path::new(dst_path).exists() {
-            return Err(OpsError::UndoError(format!(
-                "Destination already exists: {}",
-                dst_path
-            )));
-        }
-
-        // Check if trash file exists
-        if !Path::new(src_path).exists() {
-            return Err(OpsError::UndoError(format!(
-                "Trash file not found: {}",
-                src_path
-            )));
-        }
-
-        // Create parent directory if it doesn't exist
-        if let Some(parent) = Path::new(dst_path).parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
-                    OpsError::UndoError(format!("Failed to create parent directory: {}", e))
-                })?;
+        // Members of a compressed batch were trashed as one zip; the first
+        // member restored brings it back for the rest.
+        if let Some(zip_path) = compress::zip_ancestor(Path::new(original)) {
+            if zip_path.is_file() {
+                return Ok(());
+            }
+            if !zip_path.is_dir() {
+                return trash::restore_from_trash(trash_location, &zip_path);
             }
         }
 
-        // Move file back to original location
-        fs::rename(src_path, dst_path)
-            .map_err(|e| OpsError::UndoError(format!("Failed to restore from trash: {}", e)))?;
-
-        Ok(())
+        trash::restore_from_trash(trash_location, Path::new(original))
     }
 
     fn rollback_batch(&self, batch_info: &BatchInfo, db: &Database) -> OpsResult<()> {
@@ -281,8 +352,11 @@ impl UndoManager {
         for action in &batch_info.actions {
             // Only rollback if the action was successful (file was moved)
             if self.was_action_successful(action) {
-                if let Err(e) = self.reverse_action(action, db) {
-                    eprintln!(
+                if let Err(e) = self
+                    .reverse_action(action, db)
+                    .and_then(|_| self.log_restore_action(action, db))
+                {
+                    tracing::error!(
                         "Failed to rollback action {}: {}",
                         action.id.unwrap_or(0),
                         e
@@ -294,12 +368,46 @@ impl UndoManager {
         Ok(())
     }
 
+    /// Removes compressed batches whose every entry was just extracted.
+    /// Zips that still hold files the batch did not log are left alone.
+    fn discard_restored_archives(&self, batch_info: &BatchInfo) {
+        let mut restored: HashMap<std::path::PathBuf, HashSet<String>> = HashMap::new();
+        for action in &batch_info.actions {
+            if action.action != ActionType::Archive {
+                continue;
+            }
+            let member = action
+                .dst_path
+                .as_deref()
+                .and_then(|dst| compress::split_member_path(Path::new(dst)));
+            if let Some((zip_path, entry)) = member {
+                restored.entry(zip_path).or_default().insert(entry);
+            }
+        }
+
+        for (zip_path, entries) in restored {
+            let fully_restored = compress::read_manifest(&zip_path)
+                .map(|manifest| manifest.entries.iter().all(|e| entries.contains(&e.name)))
+                .unwrap_or(false);
+            if fully_restored {
+                if let Err(e) = fs::remove_file(&zip_path) {
+                    tracing::warn!("Failed to remove {}: {}", zip_path.display(), e);
+                }
+            }
+        }
+    }
+
     fn was_action_successful(&self, action: &Action) -> bool {
         // Check if the destination file exists (indicating successful move)
-        if let Some(dst_path) = &action.dst_path {
-            Path::new(dst_path).exists()
-        } else {
-            false
+        match (&action.action, &action.dst_path) {
+            (ActionType::Delete, Some(dst_path)) => trash::trash_entry_exists(dst_path),
+            (ActionType::Archive, Some(dst_path))
+                if compress::split_member_path(Path::new(dst_path)).is_some() =>
+            {
+                true
+            }
+            (_, Some(dst_path)) => Path::new(dst_path).exists(),
+            _ => false,
         }
     }
 
@@ -331,7 +439,7 @@ impl UndoManager {
                 Ok(batch_info) => batches.push(batch_info),
                 Err(e) => {
                     // Log error but continue with other batches
-                    eprintln!("Failed to get batch {}: {}", batch_id, e);
+                    tracing::warn!("Failed to get batch {}: {}", batch_id, e);
                 }
             }
         }
@@ -352,6 +460,8 @@ impl UndoManager {
         Ok(true)
     }
 
+    /// The batch's archive and delete actions, minus files already restored
+    /// on their own with [`UndoManager::undo_files`].
     pub fn get_batch_by_id(&self, batch_id: &str, db: &Database) -> OpsResult<BatchInfo> {
         let actions = db
             .get_actions_by_batch_id(batch_id)
@@ -361,6 +471,24 @@ impl UndoManager {
             return Err(OpsError::UndoError(format!("Batch {} not found", batch_id)));
         }
 
+        let restored: HashSet<i64> = actions
+            .iter()
+            .filter(|action| action.action == ActionType::Restore)
+            .map(|action| action.file_id)
+            .collect();
+        let actions: Vec<Action> = actions
+            .into_iter()
+            .filter(|action| {
+                action.action != ActionType::Restore && !restored.contains(&action.file_id)
+            })
+            .collect();
+        if actions.is_empty() {
+            return Err(OpsError::UndoError(format!(
+                "Batch {} has already been restored",
+                batch_id
+            )));
+        }
+
         let action_type = actions[0].action.clone();
         let created_at = actions[0].created_at;
 
@@ -378,20 +506,25 @@ impl UndoManager {
             ActionType::Archive => {
                 // Can restore if archive file exists and destination doesn't
                 if let (Some(src_path), Some(dst_path)) = (&action.dst_path, &action.src_path) {
-                    Path::new(src_path).exists() && !Path::new(dst_path).exists()
+                    let archived = match compress::split_member_path(Path::new(src_path)) {
+                        Some((zip_path, _)) => zip_path.is_file(),
+                        None => Path::new(src_path).exists(),
+                    };
+                    archived && !Path::new(dst_path).exists()
                 } else {
                     false
                 }
             }
             ActionType::Delete => {
-                // Can restore if trash file exists and destination doesn't
+                // Can restore if trash entry exists and destination doesn't
                 if let (Some(src_path), Some(dst_path)) = (&action.dst_path, &action.src_path) {
-                    Path::new(src_path).exists() && !Path::new(dst_path).exists()
+                    trash::trash_entry_exists(src_path) && !Path::new(dst_path).exists()
                 } else {
                     false
                 }
             }
             ActionType::Restore => false, // Cannot undo restore actions
+            ActionType::Move => false,    // The user moved the file themselves
         }
     }
 
@@ -406,11 +539,14 @@ impl UndoManager {
                         preview.push(format!("Restore {} from archive", dst_path));
                     }
                     ActionType::Delete => {
-                        preview.push(format!("Restore {} from trash", dst_path));
+                        preview.push(format!("Restore {} from trash", src_path));
                     }
                     ActionType::Restore => {
                         preview.push(format!("Cannot undo restore of {}", dst_path));
                     }
+                    ActionType::Move => {
+                        preview.push(format!("Cannot undo move of {}", src_path));
+                    }
                 }
             }
         }
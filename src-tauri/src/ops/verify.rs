@@ -0,0 +1,50 @@
+use crate::models::{File, HashAlgo};
+use crate::ops::compress;
+use crate::scanner::hash;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// An archived copy whose contents no longer match the hash recorded when it
+/// was indexed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorruptedEntry {
+    pub file_id: i64,
+    pub path: String,
+    pub expected_hash: String,
+    /// `None` when the copy could not be read at all.
+    pub actual_hash: Option<String>,
+    pub reason: String,
+}
+
+/// Hash of an archived copy with `algo`, reading zip members from inside
+/// their batch.
+pub fn archived_hash(path: &str, algo: HashAlgo) -> anyhow::Result<String> {
+    match compress::split_member_path(Path::new(path)) {
+        Some((zip_path, entry)) => Ok(compress::hash_entry(&zip_path, &entry, algo)?),
+        None => hash::hash_full_with(Path::new(path), algo),
+    }
+}
+
+/// Rehashes every file that has a recorded hash, with the algorithm it was
+/// recorded with, and returns the ones that do not match. Files indexed
+/// without a full hash cannot be checked and pass.
+pub fn find_corrupted(files: &[File]) -> Vec<CorruptedEntry> {
+    files
+        .iter()
+        .filter_map(|file| {
+            let expected = file.sha1.as_deref().filter(|sha1| !sha1.is_empty())?;
+            let (actual, reason) = match archived_hash(&file.path, file.hash_algo) {
+                Ok(actual) if actual == expected => return None,
+                Ok(actual) => (Some(actual), "contents changed since indexing".to_string()),
+                Err(err) => (None, format!("could not be read: {err}")),
+            };
+            Some(CorruptedEntry {
+                file_id: file.id.unwrap_or_default(),
+                path: file.path.clone(),
+                expected_hash: expected.to_string(),
+                actual_hash: actual,
+                reason,
+            })
+        })
+        .collect()
+}
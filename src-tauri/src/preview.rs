@@ -0,0 +1,211 @@
+use crate::thumbnail::{extension_of, ThumbnailCache, IMAGE_EXTENSIONS, VIDEO_EXTENSIONS};
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::process::Command;
+
+pub const DEFAULT_PREVIEW_BYTES: usize = 64 * 1024;
+pub const MAX_PREVIEW_BYTES: usize = 1024 * 1024;
+/// Longest edge of the downscaled image sent to the review UI.
+const PREVIEW_IMAGE_PX: u32 = 512;
+
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "aac", "wav", "flac", "ogg", "opus"];
+/// Extensions treated as text even when the mime type is unknown.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "csv", "tsv", "log", "json", "yaml", "yml", "toml", "xml", "html", "css", "ini",
+    "rs", "py", "js", "ts", "tsx", "jsx", "go", "java", "c", "h", "cpp", "hpp", "sh", "sql",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FilePreview {
+    Text {
+        content: String,
+        /// True when the file is longer than the bytes returned.
+        truncated: bool,
+    },
+    Image {
+        /// Downscaled PNG as a `data:` URL.
+        data_url: String,
+        width: u32,
+        height: u32,
+        /// EXIF `DateTimeOriginal`, in the camera's local time.
+        captured_at: Option<NaiveDateTime>,
+    },
+    Media {
+        duration_secs: Option<f64>,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    Unsupported,
+}
+
+/// Builds the preview for the indexed file `file_id` at `path`, reading at
+/// most `max_bytes` of text.
+pub fn build_preview(
+    file_id: i64,
+    path: &Path,
+    mime: Option<&str>,
+    max_bytes: usize,
+) -> Result<FilePreview> {
+    let ext = extension_of(path);
+    if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return image_preview(file_id, path);
+    }
+    if VIDEO_EXTENSIONS.contains(&ext.as_str()) || AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        // Without ffprobe we still know what the file is, just not its shape.
+        return Ok(probe_media(path).unwrap_or(FilePreview::Media {
+            duration_secs: None,
+            width: None,
+            height: None,
+        }));
+    }
+    let (head, truncated) = read_head(path, max_bytes)?;
+    // Known text types in another encoding still get a lossy preview.
+    let content = decode_text(&head).or_else(|| {
+        let texty = TEXT_EXTENSIONS.contains(&ext.as_str())
+            || mime.is_some_and(|m| m.starts_with("text/"));
+        (texty && !head.contains(&0)).then(|| String::from_utf8_lossy(&head).into_owned())
+    });
+    Ok(match content {
+        Some(content) => FilePreview::Text { content, truncated },
+        None => FilePreview::Unsupported,
+    })
+}
+
+fn image_preview(file_id: i64, path: &Path) -> Result<FilePreview> {
+    let (width, height) = image::image_dimensions(path).context("read image dimensions")?;
+    let data_url = ThumbnailCache::new().get_or_create(file_id, path, PREVIEW_IMAGE_PX)?;
+    Ok(FilePreview::Image {
+        data_url,
+        width,
+        height,
+        captured_at: exif_capture_date(path),
+    })
+}
+
+/// The first `max_bytes` of the file and whether anything was cut off.
+fn read_head(path: &Path, max_bytes: usize) -> Result<(Vec<u8>, bool)> {
+    let file = File::open(path).context("open file for preview")?;
+    let mut buf = Vec::with_capacity(max_bytes.min(MAX_PREVIEW_BYTES));
+    // One byte past the limit tells us whether anything was cut off.
+    file.take(max_bytes as u64 + 1)
+        .read_to_end(&mut buf)
+        .context("read file for preview")?;
+    let truncated = buf.len() > max_bytes;
+    buf.truncate(max_bytes);
+    Ok((buf, truncated))
+}
+
+/// UTF-8 text, tolerating a multi-byte character split by the read limit.
+fn decode_text(bytes: &[u8]) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text.to_string()),
+        Err(err) if err.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&bytes[..err.valid_up_to()]).into_owned())
+        }
+        Err(_) => None,
+    }
+}
+
+pub(crate) fn exif_capture_date(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    match &field.value {
+        exif::Value::Ascii(values) => parse_exif_datetime(values.first()?),
+        _ => None,
+    }
+}
+
+fn parse_exif_datetime(raw: &[u8]) -> Option<NaiveDateTime> {
+    let text = std::str::from_utf8(raw).ok()?;
+    NaiveDateTime::parse_from_str(text.trim_end_matches('\0').trim(), "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// Asks `ffprobe`, when installed, for the duration and first video
+/// stream's dimensions.
+fn probe_media(path: &Path) -> Option<FilePreview> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "format=duration:stream=width,height"])
+        .args(["-of", "default=noprint_wrappers=1"])
+        .arg(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_probe_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_probe_output(output: &str) -> FilePreview {
+    let mut duration_secs = None;
+    let mut width = None;
+    let mut height = None;
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "duration" => duration_secs = value.trim().parse().ok(),
+            "width" => width = value.trim().parse().ok(),
+            "height" => height = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    FilePreview::Media {
+        duration_secs,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_text_preview_and_metadata_parsing() {
+        let dir = TempDir::new().unwrap();
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "héllo world").unwrap();
+        // The limit splits the two-byte "é".
+        assert_eq!(
+            build_preview(1, &notes, None, 2).unwrap(),
+            FilePreview::Text {
+                content: "h".to_string(),
+                truncated: true,
+            }
+        );
+
+        let blob = dir.path().join("blob.bin");
+        std::fs::write(&blob, [0u8, 159, 146, 150]).unwrap();
+        assert_eq!(
+            build_preview(2, &blob, None, 64).unwrap(),
+            FilePreview::Unsupported
+        );
+
+        assert_eq!(
+            parse_exif_datetime(b"2024:05:01 14:30:00\0").map(|d| d.to_string()),
+            Some("2024-05-01 14:30:00".to_string())
+        );
+        assert_eq!(
+            parse_probe_output("width=1920\nheight=1080\nduration=12.5\n"),
+            FilePreview::Media {
+                duration_secs: Some(12.5),
+                width: Some(1920),
+                height: Some(1080),
+            }
+        );
+    }
+}
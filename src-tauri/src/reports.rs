@@ -0,0 +1,934 @@
+use crate::deep_link::{DeepLinkTarget, DeepLinkView};
+use crate::models::{BatchRecord, File};
+use crate::ops::space::SpaceManager;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// One directory in a disk usage breakdown. `size_bytes` and `file_count`
+/// include every descendant, `direct_bytes` only the files in the directory
+/// itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderSize {
+    pub path: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+    pub direct_bytes: u64,
+    /// Bytes in subdirectories left out by the depth or top-N limits.
+    pub other_bytes: u64,
+    pub children: Vec<FolderSize>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Totals {
+    bytes: u64,
+    count: u64,
+    direct_bytes: u64,
+}
+
+/// Rolls per-directory totals (`parent_dir`, file count, bytes) up into a tree
+/// rooted at `root`. Each level keeps its `top_n` largest subdirectories and
+/// the tree stops at `max_depth` levels below the root.
+pub fn build_folder_tree(
+    root: &Path,
+    dir_totals: &[(String, u64, u64)],
+    max_depth: usize,
+    top_n: usize,
+) -> FolderSize {
+    let mut totals: HashMap<PathBuf, Totals> = HashMap::new();
+    let mut children: HashMap<PathBuf, BTreeSet<PathBuf>> = HashMap::new();
+    totals.insert(root.to_path_buf(), Totals::default());
+
+    for (dir, count, bytes) in dir_totals {
+        let dir = PathBuf::from(dir);
+        if !dir.starts_with(root) {
+            continue;
+        }
+        totals.entry(dir.clone()).or_default().direct_bytes += bytes;
+
+        let mut current = dir.as_path();
+        loop {
+            let entry = totals.entry(current.to_path_buf()).or_default();
+            entry.bytes += bytes;
+            entry.count += count;
+            if current == root {
+                break;
+            }
+            let Some(parent) = current.parent() else {
+                break;
+            };
+            children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .insert(current.to_path_buf());
+            current = parent;
+        }
+    }
+
+    build_node(root, &totals, &children, 0, max_depth, top_n)
+}
+
+fn build_node(
+    path: &Path,
+    totals: &HashMap<PathBuf, Totals>,
+    children: &HashMap<PathBuf, BTreeSet<PathBuf>>,
+    depth: usize,
+    max_depth: usize,
+    top_n: usize,
+) -> FolderSize {
+    let own = totals.get(path).copied().unwrap_or_default();
+    let mut nodes = Vec::new();
+    if depth < max_depth {
+        let mut subdirs: Vec<&PathBuf> = children
+            .get(path)
+            .map(|set| set.iter().collect())
+            .unwrap_or_default();
+        subdirs
+            .sort_by_key(|dir| std::cmp::Reverse(totals.get(*dir).map(|t| t.bytes).unwrap_or(0)));
+        nodes = subdirs
+            .into_iter()
+            .take(top_n)
+            .map(|dir| build_node(dir, totals, children, depth + 1, max_depth, top_n))
+            .collect();
+    }
+    let shown: u64 = nodes.iter().map(|node| node.size_bytes).sum();
+
+    FolderSize {
+        path: path.to_string_lossy().to_string(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+        size_bytes: own.bytes,
+        file_count: own.count,
+        direct_bytes: own.direct_bytes,
+        other_bytes: own.bytes.saturating_sub(own.direct_bytes + shown),
+        children: nodes,
+    }
+}
+
+/// Files under one directory that have not been used since the report cutoff.
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleGroup {
+    pub parent_dir: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub files: Vec<File>,
+}
+
+/// Groups files by `parent_dir`, largest groups first. Files inside a group
+/// keep their incoming order.
+pub fn group_by_parent(files: Vec<File>) -> Vec<StaleGroup> {
+    let mut groups: HashMap<String, StaleGroup> = HashMap::new();
+    for file in files {
+        let group = groups
+            .entry(file.parent_dir.clone())
+            .or_insert_with(|| StaleGroup {
+                parent_dir: file.parent_dir.clone(),
+                file_count: 0,
+                total_bytes: 0,
+                files: Vec::new(),
+            });
+        group.file_count += 1;
+        group.total_bytes += file.size_bytes.max(0) as u64;
+        group.files.push(file);
+    }
+
+    let mut groups: Vec<StaleGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.parent_dir.cmp(&b.parent_dir))
+    });
+    groups
+}
+
+/// Images within the requested Hamming distance of one another.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarImageGroup {
+    pub total_bytes: u64,
+    /// Largest distance between any directly linked pair in the group.
+    pub max_distance: u32,
+    pub files: Vec<File>,
+}
+
+/// Clusters images whose dHashes differ by at most `max_distance` bits.
+/// Groups are transitive (A~B and B~C puts all three together), contain at
+/// least two files and come back largest first.
+pub fn group_similar_images(images: Vec<(File, u64)>, max_distance: u32) -> Vec<SimilarImageGroup> {
+    let mut parent: Vec<usize> = (0..images.len()).collect();
+    let mut link_distance = vec![0u32; images.len()];
+
+    for i in 0..images.len() {
+        for j in (i + 1)..images.len() {
+            let distance = (images[i].1 ^ images[j].1).count_ones();
+            if distance > max_distance {
+                continue;
+            }
+            let (a, b) = (find_root(&mut parent, i), find_root(&mut parent, j));
+            let combined = link_distance[a].max(link_distance[b]).max(distance);
+            if a != b {
+                parent[b] = a;
+            }
+            link_distance[a] = combined;
+        }
+    }
+
+    let mut groups: HashMap<usize, SimilarImageGroup> = HashMap::new();
+    for (i, (file, _)) in images.into_iter().enumerate() {
+        let root = find_root(&mut parent, i);
+        let group = groups.entry(root).or_insert_with(|| SimilarImageGroup {
+            total_bytes: 0,
+            max_distance: link_distance[root],
+            files: Vec::new(),
+        });
+        group.total_bytes += file.size_bytes.max(0) as u64;
+        group.files.push(file);
+    }
+
+    let mut groups: Vec<SimilarImageGroup> =
+        groups.into_values().filter(|g| g.files.len() > 1).collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.total_bytes));
+    groups
+}
+
+/// Screenshots taken in one calendar month.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotMonth {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    /// The whole month ended before the report threshold.
+    pub is_old: bool,
+    pub file_ids: Vec<i64>,
+}
+
+/// The first date in a file name written the way screenshot tools do:
+/// `YYYY-MM-DD`, `YYYY_MM_DD`, `YYYY.MM.DD` or `YYYYMMDD`, as in
+/// "Screenshot 2024-05-01 at 10.15.00" or "Screenshot_20240501-101500".
+pub fn filename_date(name: &str) -> Option<NaiveDate> {
+    let bytes = name.as_bytes();
+    (0..bytes.len())
+        .filter(|&start| start == 0 || !bytes[start - 1].is_ascii_digit())
+        .find_map(|start| date_at(&bytes[start..]))
+}
+
+fn date_at(bytes: &[u8]) -> Option<NaiveDate> {
+    let year = digits(bytes, 0, 4)?;
+    let sep = match bytes.get(4) {
+        Some(b'-' | b'_' | b'.') => 1,
+        _ => 0,
+    };
+    let month = digits(bytes, 4 + sep, 2)?;
+    if sep == 1 && bytes.get(6 + sep) != bytes.get(4) {
+        return None;
+    }
+    let day = digits(bytes, 6 + 2 * sep, 2)?;
+    if !(1990..=2100).contains(&year) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+fn digits(bytes: &[u8], at: usize, len: usize) -> Option<u32> {
+    let slice = bytes.get(at..at + len)?;
+    if !slice.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(slice).ok()?.parse().ok()
+}
+
+/// Groups screenshots by the month of their capture date, newest month
+/// first. A month is old once its last day falls before `old_before`.
+pub fn group_screenshots_by_month(
+    shots: Vec<(File, NaiveDate)>,
+    old_before: NaiveDate,
+) -> Vec<ScreenshotMonth> {
+    let mut months: BTreeMap<(i32, u32), ScreenshotMonth> = BTreeMap::new();
+    for (file, taken) in shots {
+        let key = (taken.year(), taken.month());
+        let month = months.entry(key).or_insert_with(|| {
+            let (next_year, next_month) = if key.1 == 12 {
+                (key.0 + 1, 1)
+            } else {
+                (key.0, key.1 + 1)
+            };
+            let month_end = NaiveDate::from_ymd_opt(next_year, next_month, 1)
+                .and_then(|first| first.pred_opt());
+            ScreenshotMonth {
+                month: format!("{:04}-{:02}", key.0, key.1),
+                file_count: 0,
+                total_bytes: 0,
+                is_old: month_end.is_some_and(|end| end < old_before),
+                file_ids: Vec::new(),
+            }
+        });
+        month.file_count += 1;
+        month.total_bytes += file.size_bytes.max(0) as u64;
+        month.file_ids.extend(file.id);
+    }
+    months.into_values().rev().collect()
+}
+
+/// Bucket size for metric series. `Sample` keeps one point per recorded value,
+/// e.g. `files_counted` per scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricWindow {
+    Sample,
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl MetricWindow {
+    pub const NAMES: &'static [&'static str] = &["sample", "hour", "day", "week", "month"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sample" | "scan" => Some(Self::Sample),
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricAggregation {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+    Last,
+}
+
+impl MetricAggregation {
+    pub const NAMES: &'static [&'static str] = &["sum", "avg", "min", "max", "count", "last"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "count" => Some(Self::Count),
+            "last" => Some(Self::Last),
+            _ => None,
+        }
+    }
+
+    fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            Self::Sum => values.iter().sum(),
+            Self::Avg => values.iter().sum::<f64>() / values.len().max(1) as f64,
+            Self::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Self::Count => values.len() as f64,
+            Self::Last => values.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricPoint {
+    pub bucket_start: DateTime<Utc>,
+    pub value: f64,
+    /// Recorded values that went into this point.
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricSeries {
+    pub metric: String,
+    pub points: Vec<MetricPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FreedSpacePoint {
+    pub bucket_start: DateTime<Utc>,
+    pub bytes: u64,
+    pub files: usize,
+    /// Bytes freed from the start of the window through this bucket.
+    pub cumulative_bytes: u64,
+}
+
+/// Sums `(deleted_at, bytes)` pairs per bucket and carries a running total.
+pub fn freed_space_points<Tz: TimeZone>(
+    deletes: &[(DateTime<Utc>, u64)],
+    window: MetricWindow,
+    tz: &Tz,
+) -> Vec<FreedSpacePoint> {
+    let samples: Vec<(DateTime<Utc>, f64)> = deletes
+        .iter()
+        .map(|(at, bytes)| (*at, *bytes as f64))
+        .collect();
+    let mut cumulative_bytes = 0u64;
+    bucket_metrics(&samples, window, MetricAggregation::Sum, tz)
+        .into_iter()
+        .map(|point| {
+            let bytes = point.value as u64;
+            cumulative_bytes += bytes;
+            FreedSpacePoint {
+                bucket_start: point.bucket_start,
+                bytes,
+                files: point.samples,
+                cumulative_bytes,
+            }
+        })
+        .collect()
+}
+
+/// Start of the bucket holding `at`. Buckets follow calendar boundaries in
+/// `tz`, with weeks starting on Monday.
+fn bucket_start<Tz: TimeZone>(at: DateTime<Utc>, window: MetricWindow, tz: &Tz) -> DateTime<Utc> {
+    let local = at.with_timezone(tz).naive_local();
+    let date = local.date();
+    let start = match window {
+        MetricWindow::Sample => return at,
+        MetricWindow::Hour => date.and_hms_opt(local.hour(), 0, 0),
+        MetricWindow::Day => date.and_hms_opt(0, 0, 0),
+        MetricWindow::Week => {
+            let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            monday.and_hms_opt(0, 0, 0)
+        }
+        MetricWindow::Month => date
+            .with_day(1)
+            .and_then(|first| first.and_hms_opt(0, 0, 0)),
+    };
+    start
+        .and_then(|naive| tz.from_local_datetime(&naive).earliest())
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or(at)
+}
+
+/// Groups time-ordered samples into buckets, oldest first. Empty buckets are
+/// left out rather than reported as zero.
+pub fn bucket_metrics<Tz: TimeZone>(
+    samples: &[(DateTime<Utc>, f64)],
+    window: MetricWindow,
+    aggregation: MetricAggregation,
+    tz: &Tz,
+) -> Vec<MetricPoint> {
+    if window == MetricWindow::Sample {
+        return samples
+            .iter()
+            .map(|(at, value)| MetricPoint {
+                bucket_start: *at,
+                value: aggregation.apply(&[*value]),
+                samples: 1,
+            })
+            .collect();
+    }
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<f64>> = BTreeMap::new();
+    for (at, value) in samples {
+        buckets
+            .entry(bucket_start(*at, window, tz))
+            .or_default()
+            .push(*value);
+    }
+    buckets
+        .into_iter()
+        .map(|(bucket_start, values)| MetricPoint {
+            bucket_start,
+            value: aggregation.apply(&values),
+            samples: values.len(),
+        })
+        .collect()
+}
+
+/// Union-find lookup with path halving.
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// One file line in the weekly digest.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestFile {
+    pub path: String,
+    pub size_bytes: u64,
+    /// First seen for new files, cooloff end for staged ones.
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// What happened on disk over the digest window, and what is coming up.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyDigest {
+    pub generated_at: DateTime<Utc>,
+    pub since: DateTime<Utc>,
+    pub window_days: i64,
+    pub freed_bytes: u64,
+    pub freed_files: usize,
+    pub staged_files: usize,
+    pub staged_bytes: u64,
+    pub biggest_new_files: Vec<DigestFile>,
+    /// Staged files whose cooloff ends within the next window.
+    pub expiring_soon: Vec<DigestFile>,
+    /// Archive and delete batches run during the window, newest first.
+    pub batches: Vec<BatchRecord>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFormat {
+    Markdown,
+    Html,
+    Csv,
+}
+
+impl DigestFormat {
+    pub const NAMES: &'static [&'static str] = &["markdown", "html", "csv"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Csv => "csv",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Renders the digest as a self-contained document: Markdown for notes
+/// apps, HTML that survives being pasted into an email, or CSV for
+/// spreadsheets and scripts.
+pub fn render_digest(digest: &WeeklyDigest, format: DigestFormat) -> String {
+    if format == DigestFormat::Csv {
+        return render_digest_csv(digest);
+    }
+    let size = |bytes: u64| SpaceManager::new().format_bytes(bytes);
+    let date = |at: &DateTime<Utc>| at.format("%Y-%m-%d").to_string();
+    let title = format!(
+        "Disk hygiene report, {} to {}",
+        date(&digest.since),
+        date(&digest.generated_at)
+    );
+    let summary = [
+        format!("Space freed: {} across {} files", size(digest.freed_bytes), digest.freed_files),
+        format!(
+            "Staged and waiting for review: {} files, {}",
+            digest.staged_files,
+            size(digest.staged_bytes)
+        ),
+    ];
+    let new_files: Vec<String> = digest
+        .biggest_new_files
+        .iter()
+        .map(|file| format!("{} ({})", file.path, size(file.size_bytes)))
+        .collect();
+    let expiring: Vec<String> = digest
+        .expiring_soon
+        .iter()
+        .map(|file| {
+            let when = file.at.as_ref().map(date).unwrap_or_default();
+            format!("{} ({}, cooloff ends {})", file.path, size(file.size_bytes), when)
+        })
+        .collect();
+    let batches: Vec<String> = digest
+        .batches
+        .iter()
+        .map(|batch| {
+            format!(
+                "{} ({} via {}, {} files, {}, {})",
+                batch.label.as_deref().unwrap_or(&batch.batch_id),
+                batch.action_type,
+                batch.origin,
+                batch.file_count,
+                size(batch.total_bytes.max(0) as u64),
+                date(&batch.created_at)
+            )
+        })
+        .collect();
+    // Each section links to the view in the app where it can be acted on.
+    let link = |view| DeepLinkTarget::new(view).url();
+    let sections = [
+        ("Biggest new files", new_files, "No new large files.", link(DeepLinkView::Review)),
+        (
+            "Cooloff ending soon",
+            expiring,
+            "Nothing is about to leave the archive.",
+            link(DeepLinkView::Staged),
+        ),
+        (
+            "Batches",
+            batches,
+            "No files were archived or deleted.",
+            link(DeepLinkView::History),
+        ),
+    ];
+
+    match format {
+        DigestFormat::Markdown => {
+            let mut out = format!("# {title}\n\n");
+            for line in &summary {
+                out.push_str(&format!("- {line}\n"));
+            }
+            for (heading, lines, empty, link) in &sections {
+                out.push_str(&format!("\n## {heading}\n\n"));
+                if lines.is_empty() {
+                    out.push_str(&format!("{empty}\n"));
+                }
+                for line in lines {
+                    out.push_str(&format!("- {line}\n"));
+                }
+                if !lines.is_empty() {
+                    out.push_str(&format!("\n[Open in White Space]({link})\n"));
+                }
+            }
+            out
+        }
+        DigestFormat::Html | DigestFormat::Csv => {
+            let mut out = format!(
+                "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n\
+                 <body style=\"font-family: sans-serif\">\n<h1>{0}</h1>\n<ul>\n",
+                escape_html(&title)
+            );
+            for line in &summary {
+                out.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+            }
+            out.push_str("</ul>\n");
+            for (heading, lines, empty, link) in &sections {
+                out.push_str(&format!("<h2>{}</h2>\n", escape_html(heading)));
+                if lines.is_empty() {
+                    out.push_str(&format!("<p>{}</p>\n", escape_html(empty)));
+                    continue;
+                }
+                out.push_str("<ul>\n");
+                for line in lines {
+                    out.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+                }
+                out.push_str("</ul>\n");
+                out.push_str(&format!(
+                    "<p><a href=\"{}\">Open in White Space</a></p>\n",
+                    escape_html(link)
+                ));
+            }
+            out.push_str("</body></html>\n");
+            out
+        }
+    }
+}
+
+/// One row per summary line, file and batch, with raw byte counts and
+/// RFC 3339 timestamps so the numbers can be summed without parsing.
+fn render_digest_csv(digest: &WeeklyDigest) -> String {
+    let date = |at: Option<&DateTime<Utc>>| at.map(|at| at.to_rfc3339()).unwrap_or_default();
+    let mut rows = vec![
+        ["section", "item", "files", "bytes", "date"].map(String::from),
+        [
+            "freed".to_string(),
+            String::new(),
+            digest.freed_files.to_string(),
+            digest.freed_bytes.to_string(),
+            date(Some(&digest.since)),
+        ],
+        [
+            "staged".to_string(),
+            String::new(),
+            digest.staged_files.to_string(),
+            digest.staged_bytes.to_string(),
+            date(Some(&digest.generated_at)),
+        ],
+    ];
+    let files = [("new_file", &digest.biggest_new_files), ("expiring", &digest.expiring_soon)];
+    for (section, list) in files {
+        rows.extend(list.iter().map(|file| {
+            [
+                section.to_string(),
+                file.path.clone(),
+                "1".to_string(),
+                file.size_bytes.to_string(),
+                date(file.at.as_ref()),
+            ]
+        }));
+    }
+    rows.extend(digest.batches.iter().map(|batch| {
+        [
+            format!("batch_{}", batch.action_type),
+            batch.label.clone().unwrap_or_else(|| batch.batch_id.clone()),
+            batch.file_count.to_string(),
+            batch.total_bytes.max(0).to_string(),
+            date(Some(&batch.created_at)),
+        ]
+    }));
+
+    let mut out = String::new();
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|field| escape_csv(field)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Quotes fields holding a comma, quote or line break, doubling any quotes.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, parent_dir: &str, size_bytes: i64) -> File {
+        let now = chrono::Utc::now();
+        File {
+            id: None,
+            path: path.to_string(),
+            parent_dir: parent_dir.to_string(),
+            mime: None,
+            size_bytes,
+            created_at: now,
+            modified_at: None,
+            accessed_at: None,
+            last_opened_at: None,
+            partial_sha1: None,
+            sha1: None,
+            first_seen_at: now,
+            last_seen_at: now,
+            is_deleted: false,
+            is_staged: false,
+            cooloff_until: None,
+            is_cloud_placeholder: false,
+            is_offline: false,
+            is_symlink: false,
+            symlink_target: None,
+            hash_algo: crate::models::HashAlgo::CURRENT,
+        }
+    }
+
+    #[test]
+    fn test_group_by_parent_orders_by_bytes() {
+        let groups = group_by_parent(vec![
+            file("/r/a/1", "/r/a", 10),
+            file("/r/b/1", "/r/b", 40),
+            file("/r/a/2", "/r/a", 20),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].parent_dir, "/r/b");
+        assert_eq!(groups[1].file_count, 2);
+        assert_eq!(groups[1].total_bytes, 30);
+    }
+
+    #[test]
+    fn test_group_similar_images_is_transitive() {
+        let images = vec![
+            (file("/s/a.png", "/s", 10), 0b0000),
+            (file("/s/b.png", "/s", 20), 0b0011),
+            (file("/s/c.png", "/s", 30), 0b1111),
+            (file("/s/d.png", "/s", 40), u64::MAX),
+        ];
+        let groups = group_similar_images(images, 2);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 3);
+        assert_eq!(groups[0].total_bytes, 60);
+        assert_eq!(groups[0].max_distance, 2);
+    }
+
+    #[test]
+    fn test_screenshot_months_from_file_names() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            filename_date("Screenshot 2024-05-01 at 10.15.00.png"),
+            Some(date(2024, 5, 1))
+        );
+        assert_eq!(
+            filename_date("Screenshot_20231231-235959.png"),
+            Some(date(2023, 12, 31))
+        );
+        assert_eq!(filename_date("Screenshot 2024-13-01.png"), None);
+        assert_eq!(filename_date("Screenshot (12345678).png"), None);
+
+        let shot = |id, size| File {
+            id: Some(id),
+            ..file("/s/shot.png", "/s", size)
+        };
+        let months = group_screenshots_by_month(
+            vec![
+                (shot(1, 10), date(2024, 4, 3)),
+                (shot(2, 20), date(2024, 5, 1)),
+                (shot(3, 30), date(2024, 4, 30)),
+            ],
+            date(2024, 5, 1),
+        );
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].month, "2024-05");
+        assert!(!months[0].is_old);
+        assert_eq!(months[1].file_ids, vec![1, 3]);
+        assert_eq!(months[1].total_bytes, 40);
+        assert!(months[1].is_old);
+    }
+
+    #[test]
+    fn test_bucket_metrics_by_day_and_week() {
+        let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        let at = |day: u32, hour: u32| {
+            tz.with_ymd_and_hms(2024, 5, day, hour, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        // Wednesday 1 May morning and late evening, Thursday, then next Monday.
+        let samples = vec![
+            (at(1, 1), 10.0),
+            (at(1, 23), 30.0),
+            (at(2, 12), 5.0),
+            (at(6, 9), 7.0),
+        ];
+
+        let days = bucket_metrics(&samples, MetricWindow::Day, MetricAggregation::Avg, &tz);
+        assert_eq!(days.len(), 3);
+        assert_eq!(days[0].bucket_start, at(1, 0));
+        assert_eq!(days[0].value, 20.0);
+        assert_eq!(days[0].samples, 2);
+
+        let weeks = bucket_metrics(&samples, MetricWindow::Week, MetricAggregation::Sum, &tz);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].bucket_start, at(1, 0) - Duration::days(2));
+        assert_eq!(weeks[0].value, 45.0);
+        assert_eq!(weeks[1].value, 7.0);
+
+        let raw = bucket_metrics(&samples, MetricWindow::Sample, MetricAggregation::Max, &tz);
+        assert_eq!(raw.len(), 4);
+        assert_eq!(raw[3].bucket_start, at(6, 9));
+        assert_eq!(MetricWindow::parse("scan"), Some(MetricWindow::Sample));
+        assert_eq!(MetricAggregation::parse("median"), None);
+    }
+
+    #[test]
+    fn test_freed_space_points_accumulate() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 5, d, 12, 0, 0).unwrap();
+        let points = freed_space_points(
+            &[(day(1), 100), (day(1), 50), (day(3), 25)],
+            MetricWindow::Day,
+            &Utc,
+        );
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].bytes, points[0].files), (150, 2));
+        assert_eq!(points[1].cumulative_bytes, 175);
+    }
+
+    #[test]
+    fn test_build_folder_tree_rolls_up_children() {
+        let totals = vec![
+            ("/root".to_string(), 1, 10),
+            ("/root/a".to_string(), 2, 100),
+            ("/root/a/deep/er".to_string(), 1, 1000),
+            ("/root/b".to_string(), 1, 50),
+            ("/root/c".to_string(), 1, 5),
+            ("/elsewhere".to_string(), 1, 999),
+        ];
+
+        let tree = build_folder_tree(Path::new("/root"), &totals, 2, 2);
+        assert_eq!(tree.size_bytes, 1165);
+        assert_eq!(tree.file_count, 6);
+        assert_eq!(tree.direct_bytes, 10);
+        // Only the two largest children are listed; `c` is rolled into other.
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[0].name, "a");
+        assert_eq!(tree.children[0].size_bytes, 1100);
+        assert_eq!(tree.children[1].name, "b");
+        assert_eq!(tree.other_bytes, 5);
+
+        // `deep` has no files of its own but still carries its subtree.
+        let deep = &tree.children[0].children[0];
+        assert_eq!(deep.name, "deep");
+        assert_eq!(deep.direct_bytes, 0);
+        assert!(deep.children.is_empty());
+        assert_eq!(deep.other_bytes, 1000);
+    }
+
+    #[test]
+    fn test_render_digest_escapes_html() {
+        let at = Utc.with_ymd_and_hms(2024, 5, 8, 9, 0, 0).unwrap();
+        let digest = WeeklyDigest {
+            generated_at: at,
+            since: at - Duration::days(7),
+            window_days: 7,
+            freed_bytes: 2048,
+            freed_files: 3,
+            staged_files: 1,
+            staged_bytes: 1024,
+            biggest_new_files: vec![DigestFile {
+                path: "/home/u/<big> & bold.iso".to_string(),
+                size_bytes: 1024,
+                at: Some(at),
+            }],
+            expiring_soon: Vec::new(),
+            batches: vec![BatchRecord {
+                batch_id: "batch_1".to_string(),
+                action_type: "archive".to_string(),
+                origin: "bucket".to_string(),
+                label: Some("Old <installers>".to_string()),
+                file_count: 4,
+                total_bytes: 4096,
+                created_at: at,
+            }],
+        };
+
+        let markdown = render_digest(&digest, DigestFormat::Markdown);
+        assert!(markdown.starts_with("# Disk hygiene report, 2024-05-01 to 2024-05-08"));
+        assert!(markdown.contains("- Space freed: 2.0 KB across 3 files"));
+        assert!(markdown.contains("Nothing is about to leave the archive."));
+        assert!(markdown.contains("- Old <installers> (archive via bucket, 4 files, 4.0 KB"));
+        assert!(markdown.contains("[Open in White Space](whitespace://history)"));
+        assert!(!markdown.contains("whitespace://staged"));
+
+        let html = render_digest(&digest, DigestFormat::Html);
+        assert!(html.contains("/home/u/&lt;big&gt; &amp; bold.iso"));
+        assert!(!html.contains("<big>"));
+        assert!(html.contains("<a href=\"whitespace://review\">"));
+        assert_eq!(DigestFormat::parse("MD"), Some(DigestFormat::Markdown));
+
+        let csv = render_digest(&digest, DigestFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "section,item,files,bytes,date");
+        assert!(lines[1].starts_with("freed,,3,2048,2024-05-01T09:00:00"));
+        assert!(csv.contains("new_file,/home/u/<big> & bold.iso,1,1024,"));
+        assert!(csv.contains("batch_archive,Old <installers>,4,4096,"));
+        assert_eq!(escape_csv("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -1,8 +1,27 @@
 use crate::models::{ActionType, NewMetric};
 use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directory names holding build output or caches that a project can
+/// regenerate.
+pub const ARTIFACT_DIR_NAMES: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".pytest_cache",
+    ".next",
+    ".cache",
+];
+/// Monorepos keep artifacts a few levels down (`packages/app/node_modules`).
+const ARTIFACT_SEARCH_DEPTH: usize = 4;
 
 #[derive(Debug, Clone)]
 pub struct DevRepo {
@@ -13,6 +32,26 @@ pub struct DevRepo {
     pub is_active: bool,
 }
 
+/// One build output or cache directory, sized from disk because the index
+/// never walks into these.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactDir {
+    pub path: String,
+    /// The matching entry of [`ARTIFACT_DIR_NAMES`].
+    pub kind: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+}
+
+/// Artifact directories of one repo, largest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactProject {
+    pub repo_path: String,
+    pub last_activity: DateTime<Utc>,
+    pub total_bytes: u64,
+    pub dirs: Vec<ArtifactDir>,
+}
+
 #[derive(Debug, Clone)]
 pub struct RecentBurst {
     pub directory: PathBuf,
@@ -145,15 +184,95 @@ impl ActiveProjectDetector {
         &self,
         repo_path: &Path,
     ) -> Result<DateTime<Utc>, Box<dyn std::error::Error>> {
-        // Try to get the last commit date from git
-        // For now, we'll use the directory's modification time as a fallback
-        let metadata = fs::metadata(repo_path)?;
-        let modified = metadata.modified()?;
-        let duration = modified.duration_since(std::time::UNIX_EPOCH)?;
+        // Commits, checkouts and fetches touch these; the directory's own
+        // modification time is the fallback when none can be read.
+        let git_dir = repo_path.join(".git");
+        let candidates = [
+            git_dir.join("logs").join("HEAD"),
+            git_dir.join("index"),
+            git_dir.join("FETCH_HEAD"),
+            repo_path.to_path_buf(),
+        ];
+        let latest = candidates
+            .iter()
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max()
+            .ok_or("no readable git metadata")?;
+        let duration = latest.duration_since(std::time::UNIX_EPOCH)?;
 
         Ok(DateTime::from_timestamp(duration.as_secs() as i64, 0).unwrap_or_else(Utc::now))
     }
 
+    /// Build output and cache directories inside `repo`. Matches are not
+    /// descended into, so `node_modules/foo/dist` is counted once.
+    pub fn find_artifact_dirs(&self, repo: &Path) -> Vec<ArtifactDir> {
+        let mut dirs = Vec::new();
+        let mut walker = WalkDir::new(repo)
+            .min_depth(1)
+            .max_depth(ARTIFACT_SEARCH_DEPTH)
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == ".git" {
+                walker.skip_current_dir();
+                continue;
+            }
+            let Some(kind) = ARTIFACT_DIR_NAMES.iter().find(|k| **k == name) else {
+                continue;
+            };
+            walker.skip_current_dir();
+
+            let (size_bytes, file_count) = WalkDir::new(entry.path())
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| e.metadata().ok())
+                .fold((0u64, 0u64), |(bytes, count), meta| {
+                    (bytes + meta.len(), count + 1)
+                });
+            dirs.push(ArtifactDir {
+                path: entry.path().to_string_lossy().to_string(),
+                kind: kind.to_string(),
+                size_bytes,
+                file_count,
+            });
+        }
+        dirs.sort_by_key(|d| Reverse(d.size_bytes));
+        dirs
+    }
+
+    /// Artifact directories of repos under `roots` with no activity in the
+    /// last `inactive_days`, largest project first. Active projects are left
+    /// alone: their caches are about to be rebuilt anyway.
+    pub fn build_artifacts(&self, roots: &[String], inactive_days: i64) -> Vec<ArtifactProject> {
+        let cutoff = Utc::now() - Duration::days(inactive_days);
+        let mut projects: Vec<ArtifactProject> = self
+            .detect_dev_repos(roots)
+            .into_iter()
+            .filter(|repo| repo.last_activity < cutoff)
+            .filter_map(|repo| {
+                let dirs = self.find_artifact_dirs(&repo.git_root);
+                if dirs.is_empty() {
+                    return None;
+                }
+                Some(ArtifactProject {
+                    repo_path: repo.git_root.to_string_lossy().to_string(),
+                    last_activity: repo.last_activity,
+                    total_bytes: dirs.iter().map(|d| d.size_bytes).sum(),
+                    dirs,
+                })
+            })
+            .collect();
+        projects.sort_by_key(|p| Reverse(p.total_bytes));
+        projects
+    }
+
     fn is_repo_active(
         &self,
         repo_path: &Path,
@@ -0,0 +1,195 @@
+use crate::db::{Database, DbPool};
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// "true" holds scheduled scans and automatic emptying until the user has
+/// been away from the keyboard and mouse for `idle_minutes`.
+pub const ONLY_CLEAN_WHEN_IDLE_PREF: &str = "only_clean_when_idle";
+pub const IDLE_MINUTES_PREF: &str = "idle_minutes";
+pub const DEFAULT_IDLE_MINUTES: u32 = 10;
+pub const MAX_IDLE_MINUTES: u32 = 240;
+/// How often the idle watcher samples input idle time.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static IDLE_WATCHER_STARTED: OnceCell<()> = OnceCell::new();
+
+/// The stored idle settings: whether cleaning waits for idle, and for how long
+/// input must have been idle.
+pub fn idle_settings(db: &Database) -> (bool, Duration) {
+    let only_when_idle = db
+        .get_preference(ONLY_CLEAN_WHEN_IDLE_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(false);
+    let minutes = db
+        .get_preference(IDLE_MINUTES_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<u32>().ok())
+        .filter(|minutes| (1..=MAX_IDLE_MINUTES).contains(minutes))
+        .unwrap_or(DEFAULT_IDLE_MINUTES);
+    (only_when_idle, Duration::from_secs(minutes as u64 * 60))
+}
+
+/// Whether scheduled scans and the auto-empty and retention sweeps may start
+/// now. Manual actions never ask; watcher scans only check [`user_busy`].
+pub fn heavy_work_allowed(db: &Database) -> bool {
+    let (only_when_idle, threshold) = idle_settings(db);
+    let idle = if only_when_idle { idle_time() } else { None };
+    allowed_for(user_busy(), only_when_idle, idle, threshold)
+}
+
+/// Full-screen apps and presentations always defer work. With the idle pref,
+/// work also waits for idle input; where idle time can't be read it is not
+/// held back, so cleaning still happens on schedule.
+pub fn allowed_for(
+    busy: bool,
+    only_when_idle: bool,
+    idle: Option<Duration>,
+    threshold: Duration,
+) -> bool {
+    if busy {
+        return false;
+    }
+    !only_when_idle || idle.is_none_or(|idle| idle >= threshold)
+}
+
+/// With the idle pref on, runs the scan scheduler and the expiry sweep as
+/// soon as input goes idle, rather than leaving deferred work to their next
+/// interval.
+pub fn start_idle_watcher<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    if IDLE_WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let mut was_idle = false;
+        loop {
+            std::thread::sleep(IDLE_CHECK_INTERVAL);
+            let Ok(conn) = pool.get() else {
+                continue;
+            };
+            let (only_when_idle, threshold) = idle_settings(&Database::new(conn));
+            let idle = only_when_idle
+                && idle_time().is_some_and(|idle| idle >= threshold)
+                && !user_busy();
+            if idle && !was_idle {
+                tracing::info!("input idle, running deferred work");
+                super::schedule::run_scheduler_now(&app, &pool);
+                crate::ops::expiry::run_sweeps_now(&app, &pool);
+            }
+            was_idle = idle;
+        }
+    });
+}
+
+/// Whether the user is presenting or has a full-screen app in front, e.g. a
+/// game, a video call or a slideshow.
+#[cfg(windows)]
+pub fn user_busy() -> bool {
+    use windows::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) => [QUNS_BUSY, QUNS_RUNNING_D3D_FULL_SCREEN, QUNS_PRESENTATION_MODE]
+            .contains(&state),
+        Err(_) => false,
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn user_busy() -> bool {
+    // Presentations, video calls and full-screen playback keep the display
+    // awake; the system-wide summary lists the assertion as "1".
+    let Ok(output) = std::process::Command::new("pmset").args(["-g", "assertions"]).output()
+    else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        parts.next() == Some("PreventUserIdleDisplaySleep") && parts.next() == Some("1")
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn user_busy() -> bool {
+    // Flag 8 asks whether anything inhibits the session going idle, which
+    // video players and presentation tools do.
+    let Ok(output) = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.SessionManager",
+            "--object-path",
+            "/org/gnome/SessionManager",
+            "--method",
+            "org.gnome.SessionManager.IsInhibited",
+            "8",
+        ])
+        .output()
+    else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).trim() == "(true,)"
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn user_busy() -> bool {
+    false
+}
+
+/// Time since the last keyboard or mouse input, if the platform reports it.
+#[cfg(windows)]
+pub fn idle_time() -> Option<Duration> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return None;
+    }
+    let now = unsafe { GetTickCount() };
+    Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+}
+
+#[cfg(target_os = "macos")]
+pub fn idle_time() -> Option<Duration> {
+    // The HID system reports idle time in nanoseconds: "HIDIdleTime" = 1234
+    let output = std::process::Command::new("ioreg")
+        .args(["-c", "IOHIDSystem", "-d", "4"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))
+        .and_then(|line| line.rsplit('=').next())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_nanos)
+}
+
+#[cfg(target_os = "linux")]
+pub fn idle_time() -> Option<Duration> {
+    // xprintidle prints milliseconds since the last X input.
+    let output = std::process::Command::new("xprintidle").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_millis)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn idle_time() -> Option<Duration> {
+    None
+}
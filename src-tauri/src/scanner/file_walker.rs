@@ -1,9 +1,23 @@
+use crate::models::Exclusion;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use globset::{Glob, GlobMatcher};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Mime of entries that stand for a whole folder.
+pub const DIRECTORY_MIME: &str = "inode/directory";
+
+/// Folders the OS presents as a single app or document. They are indexed as
+/// one entry sized by everything inside, and archived or deleted whole.
+const PACKAGE_EXTENSIONS: &[&str] = &[
+    "app", "bundle", "framework", "plugin", "kext", "pkg", "mpkg", "xcarchive", "dsym",
+    "photoslibrary", "musiclibrary", "fcpbundle", "logicx", "band", "rtfd", "pages", "numbers",
+    "key", "sparsebundle",
+];
 
 #[derive(Debug, Clone)]
 pub struct FileMetadata {
@@ -14,11 +28,56 @@ pub struct FileMetadata {
     pub modified_at: Option<DateTime<Utc>>,
     pub accessed_at: Option<DateTime<Utc>>,
     pub mime_type: Option<String>,
+    pub inode_key: Option<String>,
+    pub link_count: u64,
+    /// Synced-folder stub whose data lives in the cloud.
+    pub is_cloud_placeholder: bool,
+    /// Symbolic link; everything above describes the link, not its target.
+    pub is_symlink: bool,
+    pub symlink_target: Option<PathBuf>,
+}
+
+struct ExclusionRule {
+    root: Option<PathBuf>,
+    matcher: GlobMatcher,
+    match_full_path: bool,
+}
+
+impl ExclusionRule {
+    fn matches(&self, path: &Path) -> bool {
+        let relative = match &self.root {
+            Some(root) => match path.strip_prefix(root) {
+                Ok(rel) => rel,
+                Err(_) => return false,
+            },
+            None => path,
+        };
+
+        if self.match_full_path {
+            self.matcher.is_match(relative) || self.matcher.is_match(path)
+        } else {
+            path.file_name()
+                .map(|name| self.matcher.is_match(name))
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// Compiles a user exclusion pattern. Patterns without a separator (`*.iso`,
+/// `node_modules`) match entry names anywhere; patterns with one match paths
+/// relative to the root.
+pub fn compile_exclusion(pattern: &str) -> Result<GlobMatcher> {
+    Ok(Glob::new(pattern)?.compile_matcher())
 }
 
 pub struct FileWalker {
     skip_dirs: HashSet<String>,
     skip_files: HashSet<String>,
+    exclusions: Vec<ExclusionRule>,
+    /// Folders the app writes to itself; never indexed whatever the user
+    /// exclusions say.
+    app_dirs: Vec<PathBuf>,
+    record_symlinks: bool,
 }
 
 impl FileWalker {
@@ -28,6 +87,9 @@ impl FileWalker {
         skip_dirs.insert("node_modules".to_string());
         skip_dirs.insert(".DS_Store".to_string());
         skip_dirs.insert("Thumbs.db".to_string());
+        skip_dirs.insert(".Trash".to_string());
+        skip_dirs.insert(".Trashes".to_string());
+        skip_dirs.insert("$RECYCLE.BIN".to_string());
 
         let mut skip_files = HashSet::new();
         skip_files.insert(".DS_Store".to_string());
@@ -36,14 +98,59 @@ impl FileWalker {
         Self {
             skip_dirs,
             skip_files,
+            exclusions: Vec::new(),
+            app_dirs: Vec::new(),
+            record_symlinks: false,
         }
     }
 
+    /// Replaces the app-owned folders (archive, trash, app data) skipped on
+    /// every walk.
+    pub fn set_app_dirs(&mut self, dirs: Vec<PathBuf>) {
+        self.app_dirs = dirs;
+    }
+
+    pub fn is_app_dir(&self, path: &Path) -> bool {
+        self.app_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Whether symlinks are indexed as entries of their own. They are never
+    /// followed either way.
+    pub fn set_record_symlinks(&mut self, record: bool) {
+        self.record_symlinks = record;
+    }
+
+    pub fn records_symlinks(&self) -> bool {
+        self.record_symlinks
+    }
+
+    /// Replaces the user exclusion rules. Invalid patterns are ignored.
+    pub fn set_exclusions(&mut self, exclusions: &[Exclusion]) {
+        self.exclusions = exclusions
+            .iter()
+            .filter_map(|exclusion| {
+                let matcher = compile_exclusion(&exclusion.pattern).ok()?;
+                Some(ExclusionRule {
+                    root: exclusion.root_path.as_ref().map(PathBuf::from),
+                    matcher,
+                    match_full_path: exclusion.pattern.contains('/')
+                        || exclusion.pattern.contains('\\'),
+                })
+            })
+            .collect();
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.exclusions.iter().any(|rule| rule.matches(path))
+    }
+
     pub fn should_skip_dir(&self, path: &Path) -> bool {
         path.file_name()
             .and_then(|n| n.to_str())
-            .map(|name| self.skip_dirs.contains(name))
+            .map(|name| self.skip_dirs.contains(name) || name.starts_with(".Trash-"))
             .unwrap_or(false)
+            || self.is_app_dir(path)
+            || self.is_excluded(path)
     }
 
     pub fn should_skip_file(&self, path: &Path) -> bool {
@@ -51,15 +158,43 @@ impl FileWalker {
             .and_then(|n| n.to_str())
             .map(|name| self.skip_files.contains(name))
             .unwrap_or(false)
+            || self.is_app_dir(path)
+            || self.is_excluded(path)
+    }
+
+    /// Whether `path` is a package folder such as `Foo.app`.
+    pub fn is_package_dir(&self, path: &Path) -> bool {
+        let is_package_name = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PACKAGE_EXTENSIONS.iter().any(|p| p.eq_ignore_ascii_case(ext)));
+        is_package_name && fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+    }
+
+    /// Outermost package folder containing `path`, or `path` itself when it
+    /// is one. Changes inside a package re-index the package.
+    pub fn package_root(&self, path: &Path) -> Option<PathBuf> {
+        path.ancestors()
+            .filter(|ancestor| self.is_package_dir(ancestor))
+            .last()
+            .map(Path::to_path_buf)
     }
 
     pub fn extract_metadata(&self, file_path: &Path) -> Result<FileMetadata> {
+        let link_metadata = fs::symlink_metadata(file_path)?;
+        if link_metadata.is_symlink() {
+            return Ok(self.symlink_metadata(file_path, &link_metadata));
+        }
+        if link_metadata.is_dir() {
+            return Ok(self.package_metadata(file_path, &link_metadata));
+        }
         let metadata = fs::metadata(file_path)?;
         let parent_dir = file_path.parent().unwrap_or(Path::new("/")).to_path_buf();
 
         let created_at = metadata.created().ok().and_then(|t| self.to_datetime(t));
         let modified_at = metadata.modified().ok().and_then(|t| self.to_datetime(t));
         let accessed_at = metadata.accessed().ok().and_then(|t| self.to_datetime(t));
+        let (inode_key, link_count) = file_identity(&metadata);
 
         Ok(FileMetadata {
             path: file_path.to_path_buf(),
@@ -69,9 +204,68 @@ impl FileWalker {
             modified_at,
             accessed_at,
             mime_type: self.detect_mime_type(file_path),
+            inode_key,
+            link_count,
+            is_cloud_placeholder: is_cloud_placeholder(&metadata),
+            is_symlink: false,
+            symlink_target: None,
         })
     }
 
+    /// Describes the link itself. A working link takes no space of its own
+    /// worth counting; a broken one is sized as the link entry.
+    fn symlink_metadata(&self, file_path: &Path, link_metadata: &fs::Metadata) -> FileMetadata {
+        let parent_dir = file_path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let (inode_key, link_count) = file_identity(link_metadata);
+        FileMetadata {
+            path: file_path.to_path_buf(),
+            parent_dir,
+            size_bytes: symlink_len(file_path, link_metadata),
+            created_at: link_metadata.created().ok().and_then(|t| self.to_datetime(t)),
+            modified_at: link_metadata.modified().ok().and_then(|t| self.to_datetime(t)),
+            accessed_at: link_metadata.accessed().ok().and_then(|t| self.to_datetime(t)),
+            mime_type: None,
+            inode_key,
+            link_count,
+            is_cloud_placeholder: false,
+            is_symlink: true,
+            symlink_target: fs::read_link(file_path).ok(),
+        }
+    }
+
+    /// Describes a package folder as one entry: the total size of its files
+    /// and the newest modification and access inside it.
+    fn package_metadata(&self, dir_path: &Path, dir_metadata: &fs::Metadata) -> FileMetadata {
+        let parent_dir = dir_path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let mut size_bytes = 0;
+        let mut modified = dir_metadata.modified().ok();
+        let mut accessed = dir_metadata.accessed().ok();
+        for entry in WalkDir::new(dir_path).follow_links(false).into_iter().flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_file() {
+                size_bytes += metadata.len();
+            }
+            modified = modified.max(metadata.modified().ok());
+            accessed = accessed.max(metadata.accessed().ok());
+        }
+        FileMetadata {
+            path: dir_path.to_path_buf(),
+            parent_dir,
+            size_bytes,
+            created_at: dir_metadata.created().ok().and_then(|t| self.to_datetime(t)),
+            modified_at: modified.and_then(|t| self.to_datetime(t)),
+            accessed_at: accessed.and_then(|t| self.to_datetime(t)),
+            mime_type: Some(DIRECTORY_MIME.to_string()),
+            inode_key: None,
+            link_count: 1,
+            is_cloud_placeholder: false,
+            is_symlink: false,
+            symlink_target: None,
+        }
+    }
+
     fn to_datetime(&self, time: std::time::SystemTime) -> Option<DateTime<Utc>> {
         time.duration_since(UNIX_EPOCH)
             .ok()
@@ -101,6 +295,69 @@ impl FileWalker {
     }
 }
 
+/// Logical size of the symlink at `path`: 0 while its target exists, else
+/// the size of the dangling link entry.
+pub fn symlink_len(path: &Path, link_metadata: &fs::Metadata) -> u64 {
+    if fs::metadata(path).is_ok() {
+        0
+    } else {
+        link_metadata.len()
+    }
+}
+
+/// Identifies the file on disk so hard links to the same data can be told
+/// apart from real copies. Windows file indexes are not exposed by stable std,
+/// so every file there counts as its own copy.
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> (Option<String>, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (
+        Some(format!("{}:{}", metadata.dev(), metadata.ino())),
+        metadata.nlink(),
+    )
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> (Option<String>, u64) {
+    (None, 1)
+}
+
+/// True for files whose contents are not on local disk: dataless files on
+/// macOS (iCloud, File Provider), recall-on-access/offline files on Windows
+/// (OneDrive) and sparse stubs with no allocated blocks elsewhere. Reading
+/// one would download it, and removing it frees next to nothing locally.
+#[cfg(target_os = "macos")]
+pub fn is_cloud_placeholder(metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const SF_DATALESS: u32 = 0x4000_0000;
+    metadata.st_flags() & SF_DATALESS != 0
+        || (metadata.len() > 0 && metadata.st_blocks() == 0)
+}
+
+#[cfg(windows)]
+pub fn is_cloud_placeholder(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x0000_1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+    metadata.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE
+            | FILE_ATTRIBUTE_RECALL_ON_OPEN
+            | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_cloud_placeholder(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.is_file() && metadata.len() > 0 && metadata.blocks() == 0
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn is_cloud_placeholder(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
 impl Default for FileWalker {
     fn default() -> Self {
         Self::new()
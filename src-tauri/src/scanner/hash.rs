@@ -1,37 +1,195 @@
+use crate::models::HashAlgo;
 use anyhow::{Context, Result};
+use image::imageops::FilterType;
 use sha1::{Digest, Sha1};
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{BufReader, Read, Seek, SeekFrom},
     path::Path,
 };
 
 const BUFFER_SIZE: usize = 8192;
+/// Bytes read between progress callbacks when streaming a full hash.
+const STREAM_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
 
-pub fn hash_first_n(path: &Path, n: usize) -> Result<String> {
+/// Incremental hasher for either algorithm, finishing as lowercase hex.
+enum Hasher {
+    Sha1(Sha1),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// Like `update`, but BLAKE3 spreads a large buffer over several threads.
+    fn update_parallel(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update_rayon(data);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Fingerprint of the file's size plus `sample` bytes each from its head,
+/// middle and tail, with the current algorithm. Videos that share a
+/// container header still differ further in, so far fewer unrelated files
+/// end up needing a full hash. Files up to three samples long are read whole.
+pub fn hash_sampled(path: &Path, sample: u64) -> Result<String> {
     let mut file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
-    let mut buffer = vec![0u8; n];
-    let read = file
-        .read(&mut buffer)
-        .with_context(|| format!("reading {}", path.display()))?;
-    let mut hasher = Sha1::new();
-    hasher.update(&buffer[..read]);
-    Ok(format!("{:x}", hasher.finalize()))
+    let len = file
+        .metadata()
+        .with_context(|| format!("reading {}", path.display()))?
+        .len();
+    let spans = if len <= sample * 3 {
+        vec![(0, len)]
+    } else {
+        vec![(0, sample), ((len - sample) / 2, sample), (len - sample, sample)]
+    };
+
+    let mut hasher = Hasher::new(HashAlgo::CURRENT);
+    hasher.update(&len.to_le_bytes());
+    let mut buffer = Vec::new();
+    for (offset, span) in spans {
+        buffer.clear();
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| (&mut file).take(span).read_to_end(&mut buffer))
+            .with_context(|| format!("reading {}", path.display()))?;
+        hasher.update(&buffer);
+    }
+    Ok(hasher.finalize_hex())
 }
 
+/// Hash of the whole file with the current algorithm.
 pub fn hash_full(path: &Path) -> Result<String> {
+    hash_full_with(path, HashAlgo::CURRENT)
+}
+
+/// Hash of the whole file with `algo`, for checking against a stored hash.
+/// BLAKE3 memory-maps large files and hashes them on several threads.
+pub fn hash_full_with(path: &Path, algo: HashAlgo) -> Result<String> {
+    if algo == HashAlgo::Blake3 {
+        let mut hasher = blake3::Hasher::new();
+        hasher
+            .update_mmap_rayon(path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        return Ok(hasher.finalize().to_hex().to_string());
+    }
     let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
-    let mut reader = BufReader::new(file);
-    let mut hasher = Sha1::new();
-    let mut buffer = [0u8; BUFFER_SIZE];
+    hash_reader(BufReader::new(file), algo).with_context(|| format!("reading {}", path.display()))
+}
+
+/// Hash of the whole file with the current algorithm, read in chunks so a
+/// very large file can report progress and be abandoned part way. `on_chunk`
+/// gets the number of bytes just hashed and returns false to stop, which
+/// yields `Ok(None)`.
+pub fn hash_full_streaming(
+    path: &Path,
+    mut on_chunk: impl FnMut(u64) -> bool,
+) -> Result<Option<String>> {
+    let mut file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut hasher = Hasher::new(HashAlgo::CURRENT);
+    let mut buffer = Vec::with_capacity(STREAM_CHUNK_SIZE as usize);
     loop {
-        let read = reader
-            .read(&mut buffer)
+        buffer.clear();
+        let read = (&mut file)
+            .take(STREAM_CHUNK_SIZE)
+            .read_to_end(&mut buffer)
             .with_context(|| format!("reading {}", path.display()))?;
         if read == 0 {
             break;
         }
+        hasher.update_parallel(&buffer);
+        if !on_chunk(read as u64) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(hasher.finalize_hex()))
+}
+
+/// Hash of everything `reader` yields, such as a zip entry.
+pub fn hash_reader(mut reader: impl Read, algo: HashAlgo) -> Result<String> {
+    let mut hasher = Hasher::new(algo);
+    let mut buffer = [0u8; BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
         hasher.update(&buffer[..read]);
     }
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(hasher.finalize_hex())
+}
+
+/// 64-bit difference hash: the image is shrunk to 9x8 grey pixels and each
+/// bit records whether a pixel is brighter than its right-hand neighbour.
+/// Near-identical images end up a small Hamming distance apart.
+pub fn dhash(path: &Path) -> Result<u64> {
+    let image = image::open(path).with_context(|| format!("decoding {}", path.display()))?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_streaming_full_hash_matches_and_stops() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.bin");
+        let data: Vec<u8> = (0..9 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &data).unwrap();
+
+        let mut chunks = Vec::new();
+        let streamed = hash_full_streaming(&path, |read| {
+            chunks.push(read);
+            true
+        })
+        .unwrap();
+        assert_eq!(streamed, Some(hash_full(&path).unwrap()));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.iter().sum::<u64>(), data.len() as u64);
+
+        let mut calls = 0;
+        let stopped = hash_full_streaming(&path, |_| {
+            calls += 1;
+            false
+        })
+        .unwrap();
+        assert_eq!((stopped, calls), (None, 1));
+    }
 }
@@ -1,26 +1,50 @@
 pub mod active_project;
+pub mod activity;
 pub mod file_walker;
 pub mod watcher;
-mod hash;
+pub mod hash;
+pub mod moves;
+pub mod power;
+pub mod schedule;
+pub mod volume;
 
 use self::active_project::{ActiveProjectDetector, DevRepo};
-use self::file_walker::FileWalker;
-use self::hash::{hash_first_n, hash_full};
+use self::file_walker::{FileMetadata, FileWalker, DIRECTORY_MIME};
+use self::hash::{dhash, hash_full, hash_full_streaming, hash_sampled};
+use self::power::{ScanMode, THROTTLE_PAUSE};
 use crate::db::{Database, DbPool};
-use crate::models::{NewFile, NewMetric};
+use crate::models::{HashAlgo, NewFile, NewMetric, NewScanRecord, RootSettings};
+use crate::notifications;
+use crate::ops::archive::ArchiveConfig;
+use crate::ops::trash::trash_dirs;
 use chrono::{DateTime, Utc};
+use crossbeam_channel::{bounded, unbounded, Sender};
 use once_cell::sync::Lazy;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tauri::{AppHandle, Emitter};
 use walkdir::WalkDir;
 
 const PROGRESS_EMIT_INTERVAL: u64 = 250;
-const PARTIAL_SAMPLE_SIZE: usize = 256 * 1024; // 256KB
+const PARTIAL_SAMPLE_SIZE: u64 = 64 * 1024; // 64KB each from head, middle and tail
 const SMALL_FILE_THRESHOLD: u64 = 4 * 1024 * 1024; // 4MB
+const PHASH_MAX_BYTES: u64 = 64 * 1024 * 1024; // 64MB
+const SCAN_CHECKPOINT_PREF: &str = "scan_checkpoint";
+pub const SCAN_CONCURRENCY_PREF: &str = "scan_concurrency";
+/// Set to "true" to index symlinks as entries of their own.
+pub const RECORD_SYMLINKS_PREF: &str = "record_symlinks";
+/// Files larger than this many GB never get a full hash; 0 = no limit.
+pub const MAX_FULL_HASH_GB_PREF: &str = "max_full_hash_gb";
+/// Shortest gap between progress events while full-hashing.
+const HASH_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+pub const MAX_SCAN_CONCURRENCY: usize = 8;
+const JOB_QUEUE_DEPTH: usize = 64;
+pub const SCAN_HISTORY_MAX_ROWS: usize = 500;
+const SCAN_HISTORY_MAX_DAYS: i64 = 90;
 
 fn sanitize_string(input: &str) -> String {
     let mut sanitized = String::with_capacity(input.len());
@@ -61,6 +85,46 @@ pub struct ScanResult {
     pub skipped: u64,
     pub duration_ms: u64,
     pub errors: Vec<String>,
+    pub cancelled: bool,
+    pub paused: bool,
+}
+
+/// What a targeted watcher update changed, emitted as `scan://incremental`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IncrementalUpdate {
+    pub updated: u64,
+    pub removed: u64,
+    pub skipped: u64,
+    pub errors: Vec<String>,
+}
+
+/// Walker position persisted when a scan is paused so it can be resumed later,
+/// even across app restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub roots: Vec<String>,
+    pub root_index: usize,
+    pub last_path: Option<String>,
+    pub counted: u64,
+    pub skipped: u64,
+}
+
+/// Cooperative cancellation flag shared between the scan worker and the UI.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -69,6 +133,10 @@ pub struct ScanProgressPayload {
     pub skipped: u64,
     pub errors: u64,
     pub path_sample: Option<String>,
+    /// Bytes full-hashed so far and in total once the walk is done and
+    /// duplicates are being confirmed; both 0 before that.
+    pub hash_bytes_done: u64,
+    pub hash_bytes_total: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -86,6 +154,23 @@ pub struct ScanErrorPayload {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanPausedPayload {
+    pub scanned: u64,
+    pub skipped: u64,
+    pub errors: u64,
+    pub last_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanCancelledPayload {
+    pub scanned: u64,
+    pub skipped: u64,
+    pub errors: u64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ScanStatusPayload {
     pub state: String,
@@ -97,6 +182,7 @@ pub struct ScanStatusPayload {
     pub roots: usize,
     pub current_path: Option<String>,
     pub last_error: Option<String>,
+    pub cancelled: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -110,12 +196,14 @@ struct ScanStatusInternal {
     roots: usize,
     current_path: Option<String>,
     last_error: Option<String>,
+    cancelled: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum ScanState {
     Idle,
     Running,
+    Paused,
 }
 
 impl Default for ScanStatusInternal {
@@ -130,6 +218,7 @@ impl Default for ScanStatusInternal {
             roots: 0,
             current_path: None,
             last_error: None,
+            cancelled: false,
         }
     }
 }
@@ -141,21 +230,41 @@ static SCAN_STATUS: Lazy<Mutex<ScanStatusInternal>> =
 enum ScanTrigger {
     Manual,
     Watcher,
+    Scheduled,
+    /// `white-space --headless scan`, outside the app.
+    Cli,
 }
 
 impl ScanTrigger {
     fn emit_queued(self) -> bool {
         true
     }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ScanTrigger::Manual => "manual",
+            ScanTrigger::Watcher => "watcher",
+            ScanTrigger::Scheduled => "scheduled",
+            ScanTrigger::Cli => "cli",
+        }
+    }
 }
 
 #[derive(Clone)]
 struct ScanJob {
     roots: Vec<String>,
     trigger: ScanTrigger,
+    resume: Option<ScanCheckpoint>,
+}
+
+#[derive(Clone, Default)]
+struct ActiveScan {
+    cancel: CancellationToken,
+    pause: CancellationToken,
 }
 
 static SCAN_QUEUE: Lazy<Mutex<VecDeque<ScanJob>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+static ACTIVE_SCAN: Lazy<Mutex<Option<ActiveScan>>> = Lazy::new(|| Mutex::new(None));
 
 fn enqueue_scan_job<R: tauri::Runtime>(
     app: &AppHandle<R>,
@@ -172,7 +281,11 @@ fn enqueue_scan_job<R: tauri::Runtime>(
         if queue.iter().any(|job| job.roots == roots) {
             return Ok(());
         }
-        queue.push_back(ScanJob { roots, trigger });
+        queue.push_back(ScanJob {
+            roots,
+            trigger,
+            resume: None,
+        });
     }
 
     process_queue(app, pool);
@@ -183,19 +296,26 @@ fn process_queue<R: tauri::Runtime>(app: &AppHandle<R>, pool: &DbPool) {
     let job_opt = {
         let mut queue = SCAN_QUEUE.lock().expect("scan queue lock");
         let mut status = SCAN_STATUS.lock().expect("scan status lock");
-        if status.state == ScanState::Running {
+        // A paused scan holds the queue until it is resumed or cancelled.
+        if status.state != ScanState::Idle {
             None
         } else {
             queue.pop_front().map(|job| {
+                let (scanned, skipped) = job
+                    .resume
+                    .as_ref()
+                    .map(|checkpoint| (checkpoint.counted, checkpoint.skipped))
+                    .unwrap_or((0, 0));
                 status.state = ScanState::Running;
-                status.scanned = 0;
-                status.skipped = 0;
+                status.scanned = scanned;
+                status.skipped = skipped;
                 status.errors = 0;
                 status.started_at = Some(Utc::now());
                 status.finished_at = None;
                 status.roots = job.roots.len();
                 status.current_path = None;
                 status.last_error = None;
+                status.cancelled = false;
                 job
             })
         }
@@ -206,28 +326,53 @@ fn process_queue<R: tauri::Runtime>(app: &AppHandle<R>, pool: &DbPool) {
             emit_queued(app, job.roots.len());
         }
 
+        let active = ActiveScan::default();
+        *ACTIVE_SCAN.lock().expect("active scan lock") = Some(active.clone());
+
         let app_handle = app.clone();
         let pool_clone = pool.clone();
         let roots = job.roots.clone();
+        let resume = job.resume.clone();
+        let trigger = job.trigger;
+        let scan_id = format!("scan_{}", Utc::now().timestamp_millis());
+        let mode = pool
+            .get()
+            .map(|conn| power::scan_mode(&Database::new(conn)))
+            .unwrap_or_default();
         tauri::async_runtime::spawn_blocking(move || {
+            let _span = tracing::info_span!("scan", scan_id = %scan_id, trigger = trigger.as_str())
+                .entered();
+            tracing::info!(roots = ?roots, mode = mode.as_str(), "scan started");
             let result = (|| {
                 let conn = pool_clone
                     .get()
                     .map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
                 let db = Database::new(conn);
-                let mut scanner = Scanner::new();
+                let mut scanner = Scanner::new()
+                    .with_mode(mode)
+                    .with_cancel_token(active.cancel)
+                    .with_pause_token(active.pause)
+                    .with_concurrency(scan_concurrency_pref(&db))
+                    .with_low_priority_roots(roots_where(&db, |root| root.low_priority_io))
+                    .with_partial_hash_roots(roots_where(&db, |root| root.partial_hash_only));
+                if let Some(checkpoint) = resume {
+                    scanner = scanner.resume_from(checkpoint);
+                }
                 scanner.run_scan(&app_handle, roots.clone(), &db)
             })();
 
+            *ACTIVE_SCAN.lock().expect("active scan lock") = None;
+
             match result {
-                Ok(summary) => finalize_status(
+                Ok(summary) if summary.paused => finalize_status_paused(
                     summary.counted,
                     summary.skipped,
                     summary.errors.len() as u64,
                 ),
+                Ok(summary) => finalize_status(&pool_clone, &roots, trigger, mode, &summary),
                 Err(err) => {
                     let message = err.to_string();
-                    finalize_status_error(message.clone());
+                    finalize_status_error(&pool_clone, &roots, trigger, mode, message.clone());
                     emit_error(&app_handle, message);
                 }
             }
@@ -245,10 +390,21 @@ pub(crate) fn queue_scan_from_watcher<R: tauri::Runtime>(
     enqueue_scan_job(app, pool, roots, ScanTrigger::Watcher)
 }
 
+pub(crate) fn queue_scheduled_scan<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    pool: &DbPool,
+    roots: Vec<String>,
+) -> anyhow::Result<()> {
+    enqueue_scan_job(app, pool, roots, ScanTrigger::Scheduled)
+}
+
 pub const SCAN_PROGRESS_EVENT: &str = "scan://progress";
 pub const SCAN_DONE_EVENT: &str = "scan://done";
 pub const SCAN_ERROR_EVENT: &str = "scan://error";
 pub const SCAN_QUEUED_EVENT: &str = "scan://queued";
+pub const SCAN_CANCELLED_EVENT: &str = "scan://cancelled";
+pub const SCAN_PAUSED_EVENT: &str = "scan://paused";
+pub const SCAN_INCREMENTAL_EVENT: &str = "scan://incremental";
 
 pub fn start_scan<R: tauri::Runtime>(
     app: AppHandle<R>,
@@ -271,16 +427,106 @@ pub fn start_scan<R: tauri::Runtime>(
             sanitized.push(clean);
         }
     }
+    let sanitized = Database::outermost_roots(&sanitized);
 
     enqueue_scan_job(&app, &pool, sanitized, ScanTrigger::Manual)
 }
 
+/// Requests cancellation of the running scan and drops any queued jobs.
+/// A paused scan is discarded along with its checkpoint.
+/// Returns `false` when no scan was running or paused.
+pub fn cancel_scan(pool: &DbPool) -> bool {
+    SCAN_QUEUE.lock().expect("scan queue lock").clear();
+    if let Some(active) = ACTIVE_SCAN.lock().expect("active scan lock").as_ref() {
+        active.cancel.cancel();
+        return true;
+    }
+
+    let mut status = SCAN_STATUS.lock().expect("scan status lock");
+    if status.state != ScanState::Paused {
+        return false;
+    }
+    if let Ok(conn) = pool.get() {
+        let _ = Database::new(conn).delete_preference(SCAN_CHECKPOINT_PREF);
+    }
+    status.state = ScanState::Idle;
+    status.finished_at = Some(Utc::now());
+    status.current_path = None;
+    status.cancelled = true;
+    true
+}
+
+/// Asks the running scan to stop at the next file boundary and persist its
+/// walker position. Returns `false` when no scan is running.
+pub fn pause_scan() -> bool {
+    match ACTIVE_SCAN.lock().expect("active scan lock").as_ref() {
+        Some(active) => {
+            active.pause.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Re-queues the persisted checkpoint ahead of any pending jobs.
+/// Returns `false` when there is nothing to resume.
+pub fn resume_scan<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) -> anyhow::Result<bool> {
+    let checkpoint = {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+        load_checkpoint(&Database::new(conn))?
+    };
+    let Some(checkpoint) = checkpoint else {
+        return Ok(false);
+    };
+
+    {
+        let mut queue = SCAN_QUEUE.lock().expect("scan queue lock");
+        let mut status = SCAN_STATUS.lock().expect("scan status lock");
+        if status.state == ScanState::Running {
+            anyhow::bail!("a scan is already running");
+        }
+        status.state = ScanState::Idle;
+        queue.retain(|job| job.roots != checkpoint.roots);
+        queue.push_front(ScanJob {
+            roots: checkpoint.roots.clone(),
+            trigger: ScanTrigger::Manual,
+            resume: Some(checkpoint),
+        });
+    }
+
+    process_queue(&app, &pool);
+    Ok(true)
+}
+
+fn load_checkpoint(db: &Database) -> anyhow::Result<Option<ScanCheckpoint>> {
+    match db.get_preference(SCAN_CHECKPOINT_PREF)? {
+        Some(raw) => Ok(serde_json::from_str(&raw).ok()),
+        None => Ok(None),
+    }
+}
+
+fn save_checkpoint(db: &Database, checkpoint: &ScanCheckpoint) -> anyhow::Result<()> {
+    let raw = serde_json::to_string(checkpoint)?;
+    db.set_preference(SCAN_CHECKPOINT_PREF, &raw)?;
+    Ok(())
+}
+
+/// Drops the stored checkpoint once a scan over the same roots has finished.
+fn clear_checkpoint_for(db: &Database, roots: &[String]) {
+    if let Ok(Some(checkpoint)) = load_checkpoint(db) {
+        if checkpoint.roots == roots {
+            let _ = db.delete_preference(SCAN_CHECKPOINT_PREF);
+        }
+    }
+}
+
 pub fn current_status() -> ScanStatusPayload {
     let status = SCAN_STATUS.lock().expect("scan status lock");
     ScanStatusPayload {
         state: match status.state {
             ScanState::Idle => "idle".to_string(),
             ScanState::Running => "running".to_string(),
+            ScanState::Paused => "paused".to_string(),
         },
         scanned: status.scanned,
         skipped: status.skipped,
@@ -290,6 +536,7 @@ pub fn current_status() -> ScanStatusPayload {
         roots: status.roots,
         current_path: status.current_path.clone(),
         last_error: status.last_error.clone(),
+        cancelled: status.cancelled,
     }
 }
 
@@ -303,32 +550,466 @@ fn update_progress(scanned: u64, skipped: u64, errors: u64, current: Option<Path
     }
 }
 
-fn finalize_status(scanned: u64, skipped: u64, errors: u64) {
+/// Scans `roots` on the calling thread for the headless CLI, set up and
+/// recorded in the scan history like a queued scan but without any events.
+pub fn run_headless_scan(pool: &DbPool, roots: Vec<String>) -> anyhow::Result<ScanResult> {
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+    let db = Database::new(conn);
+    let mode = power::scan_mode(&db);
     if let Ok(mut status) = SCAN_STATUS.lock() {
-        status.scanned = scanned;
-        status.skipped = skipped;
-        status.errors = errors;
+        status.state = ScanState::Running;
+        status.started_at = Some(Utc::now());
+        status.roots = roots.len();
+    }
+    let _span = tracing::info_span!("scan", trigger = ScanTrigger::Cli.as_str()).entered();
+    tracing::info!(roots = ?roots, mode = mode.as_str(), "scan started");
+
+    let mut scanner = Scanner::new()
+        .with_mode(mode)
+        .with_concurrency(scan_concurrency_pref(&db))
+        .with_low_priority_roots(roots_where(&db, |root| root.low_priority_io))
+        .with_partial_hash_roots(roots_where(&db, |root| root.partial_hash_only));
+    match scanner.scan::<tauri::Wry>(None, roots.clone(), &db) {
+        Ok(summary) => {
+            finalize_status(pool, &roots, ScanTrigger::Cli, mode, &summary);
+            Ok(summary)
+        }
+        Err(err) => {
+            finalize_status_error(pool, &roots, ScanTrigger::Cli, mode, err.to_string());
+            Err(err)
+        }
+    }
+}
+
+fn finalize_status(
+    pool: &DbPool,
+    roots: &[String],
+    trigger: ScanTrigger,
+    mode: ScanMode,
+    summary: &ScanResult,
+) {
+    let outcome = if summary.cancelled {
+        "cancelled"
+    } else {
+        "completed"
+    };
+    let mut started_at = None;
+    if let Ok(mut status) = SCAN_STATUS.lock() {
+        status.scanned = summary.counted;
+        status.skipped = summary.skipped;
+        status.errors = summary.errors.len() as u64;
         status.finished_at = Some(Utc::now());
         status.state = ScanState::Idle;
         status.current_path = None;
         status.last_error = None;
+        status.cancelled = summary.cancelled;
+        started_at = status.started_at;
+    }
+    tracing::info!(
+        status = outcome,
+        counted = summary.counted,
+        skipped = summary.skipped,
+        errors = summary.errors.len(),
+        duration_ms = summary.duration_ms,
+        "scan finished"
+    );
+    record_scan(
+        pool,
+        NewScanRecord {
+            roots: roots.to_vec(),
+            trigger: trigger.as_str().to_string(),
+            status: outcome.to_string(),
+            mode: mode.as_str().to_string(),
+            counted: summary.counted,
+            skipped: summary.skipped,
+            errors: summary.errors.len() as u64,
+            error_sample: summary.errors.first().cloned(),
+            duration_ms: summary.duration_ms,
+            started_at: started_at.unwrap_or_else(Utc::now),
+            finished_at: Utc::now(),
+        },
+    );
+    if !summary.cancelled {
+        mark_roots_scanned(pool, roots);
+    }
+}
+
+/// Stamps each root's `last_scanned_at`, which scheduled rescans count from.
+fn mark_roots_scanned(pool: &DbPool, roots: &[String]) {
+    let result = pool
+        .get()
+        .map_err(|e| anyhow::anyhow!("db pool: {e}"))
+        .and_then(|conn| Ok(Database::new(conn).mark_roots_scanned(roots, &Utc::now())?));
+    if let Err(err) = result {
+        tracing::warn!("Failed to record root scan time: {err}");
+    }
+}
+
+fn finalize_status_paused(scanned: u64, skipped: u64, errors: u64) {
+    if let Ok(mut status) = SCAN_STATUS.lock() {
+        status.scanned = scanned;
+        status.skipped = skipped;
+        status.errors = errors;
+        status.state = ScanState::Paused;
+        status.current_path = None;
     }
 }
 
-fn finalize_status_error(message: String) {
+fn finalize_status_error(
+    pool: &DbPool,
+    roots: &[String],
+    trigger: ScanTrigger,
+    mode: ScanMode,
+    message: String,
+) {
+    let mut record = NewScanRecord {
+        roots: roots.to_vec(),
+        trigger: trigger.as_str().to_string(),
+        status: "failed".to_string(),
+        mode: mode.as_str().to_string(),
+        counted: 0,
+        skipped: 0,
+        errors: 0,
+        error_sample: Some(message.clone()),
+        duration_ms: 0,
+        started_at: Utc::now(),
+        finished_at: Utc::now(),
+    };
+    tracing::error!("scan failed: {message}");
     if let Ok(mut status) = SCAN_STATUS.lock() {
         status.errors += 1;
         status.finished_at = Some(Utc::now());
         status.state = ScanState::Idle;
         status.current_path = None;
         status.last_error = Some(message);
+        record.counted = status.scanned;
+        record.skipped = status.skipped;
+        record.errors = status.errors;
+        if let Some(started_at) = status.started_at {
+            record.started_at = started_at;
+            record.duration_ms = (record.finished_at - started_at).num_milliseconds().max(0) as u64;
+        }
+    }
+    record_scan(pool, record);
+}
+
+/// Keeps scan history to the last `SCAN_HISTORY_MAX_DAYS` days and at most
+/// `SCAN_HISTORY_MAX_ROWS` entries.
+fn record_scan(pool: &DbPool, record: NewScanRecord) {
+    let result = pool
+        .get()
+        .map_err(|e| anyhow::anyhow!("db pool: {e}"))
+        .and_then(|conn| {
+            let db = Database::new(conn);
+            db.insert_scan_record(&record)?;
+            let cutoff = Utc::now() - chrono::Duration::days(SCAN_HISTORY_MAX_DAYS);
+            db.prune_scan_records(&cutoff, SCAN_HISTORY_MAX_ROWS)?;
+            Ok(())
+        });
+    if let Err(err) = result {
+        tracing::warn!("Failed to record scan history: {err}");
+    }
+}
+
+struct HashedFile {
+    metadata: FileMetadata,
+    partial_hash: Option<String>,
+    full_hash: Option<String>,
+    phash: Option<String>,
+    /// Hashed under a partial-hash-only root; never confirmed by a full hash.
+    partial_only: bool,
+}
+
+enum ScanMessage {
+    Hashed(anyhow::Result<Box<HashedFile>>),
+    Skipped,
+    WalkError(String),
+}
+
+#[derive(Default)]
+struct WalkOutcome {
+    last_path: Option<PathBuf>,
+    cancelled: bool,
+    paused: bool,
+}
+
+/// Default worker count for the hashing stage, capped so a scan does not
+/// saturate slower disks.
+pub fn default_scan_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(2)
+        .clamp(1, MAX_SCAN_CONCURRENCY)
+}
+
+fn scan_concurrency_pref(db: &Database) -> usize {
+    db.get_preference(SCAN_CONCURRENCY_PREF)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .map(|n| n.min(MAX_SCAN_CONCURRENCY))
+        .unwrap_or_else(default_scan_concurrency)
+}
+
+/// True when `root` is missing, or is an empty directory although files were
+/// indexed under it: the usual sign of an unplugged drive or an unmounted
+/// share whose mount point stayed behind.
+pub(crate) fn root_went_offline(db: &Database, root: &str) -> bool {
+    let path = Path::new(root);
+    if !path.exists() {
+        return true;
+    }
+    let is_empty = std::fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none());
+    is_empty && db.count_active_files_under(root).unwrap_or(0) > 0
+}
+
+/// Paths of the watched roots whose settings match `filter`.
+pub(crate) fn roots_where(db: &Database, filter: fn(&RootSettings) -> bool) -> HashSet<String> {
+    match db.list_root_settings() {
+        Ok(settings) => settings
+            .into_iter()
+            .filter(filter)
+            .map(|root| root.path)
+            .collect(),
+        Err(err) => {
+            tracing::warn!("Failed to load root settings: {err}");
+            HashSet::new()
+        }
+    }
+}
+
+/// Watched roots with their ids, innermost first.
+fn roots_by_depth(db: &Database) -> Vec<(PathBuf, i64)> {
+    let mut roots: Vec<(PathBuf, i64)> = match db.list_watched_roots() {
+        Ok(roots) => roots
+            .into_iter()
+            .map(|root| (PathBuf::from(root.path), root.id))
+            .collect(),
+        Err(err) => {
+            tracing::warn!("Failed to load watched roots: {err}");
+            Vec::new()
+        }
+    };
+    roots.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+    roots
+}
+
+/// Id of the innermost root in `roots` that contains `path`.
+fn owning_root_id(roots: &[(PathBuf, i64)], path: &Path) -> Option<i64> {
+    roots
+        .iter()
+        .find(|(root, _)| path.starts_with(root))
+        .map(|(_, id)| *id)
+}
+
+/// Moves the calling thread to background I/O priority so a scan of a
+/// low-priority root yields the disk to everything else. Threads are scoped
+/// to one root, so the priority never outlives it.
+#[cfg(target_os = "linux")]
+fn lower_io_priority() {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    // `who = 0` with IOPRIO_WHO_PROCESS targets the calling thread.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if result != 0 {
+        tracing::debug!("ioprio_set failed: {}", std::io::Error::last_os_error());
     }
 }
 
+#[cfg(target_os = "macos")]
+fn lower_io_priority() {
+    let result =
+        unsafe { libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG) };
+    if result != 0 {
+        tracing::debug!("setpriority failed: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn lower_io_priority() {}
+
+/// Reads metadata and hashes `path`. With `partial_only` just the sampled
+/// fingerprint is read, for roots on slow network or removable volumes.
+fn hash_file(
+    file_walker: &FileWalker,
+    path: &Path,
+    partial_only: bool,
+) -> anyhow::Result<HashedFile> {
+    let metadata = file_walker.extract_metadata(path)?;
+    // Reading a cloud placeholder would download it and reading a symlink
+    // would follow it, so both are indexed unhashed, as are package folders.
+    let is_package = metadata.mime_type.as_deref() == Some(DIRECTORY_MIME);
+    if metadata.is_cloud_placeholder || metadata.is_symlink || is_package {
+        return Ok(HashedFile {
+            metadata,
+            partial_hash: None,
+            full_hash: None,
+            phash: None,
+            partial_only,
+        });
+    }
+    let partial_hash = hash_sampled(&metadata.path, PARTIAL_SAMPLE_SIZE).ok();
+    if partial_only {
+        return Ok(HashedFile {
+            metadata,
+            partial_hash,
+            full_hash: None,
+            phash: None,
+            partial_only,
+        });
+    }
+    let mut full_hash = None;
+    if metadata.size_bytes <= SMALL_FILE_THRESHOLD {
+        full_hash = hash_full(&metadata.path).ok();
+    }
+    // Undecodable images simply get no perceptual hash.
+    let is_image = metadata
+        .mime_type
+        .as_deref()
+        .is_some_and(|mime| mime.starts_with("image/"));
+    let phash = if is_image && metadata.size_bytes <= PHASH_MAX_BYTES {
+        dhash(&metadata.path).ok().map(|hash| format!("{hash:016x}"))
+    } else {
+        None
+    };
+    Ok(HashedFile {
+        metadata,
+        partial_hash,
+        full_hash,
+        phash,
+        partial_only,
+    })
+}
+
+/// Walk stage of the scan pipeline. Hands files to the hash workers and
+/// reports skips/errors straight to the writer.
+fn walk_root(
+    root_path: &Path,
+    mut resume_after: Option<PathBuf>,
+    file_walker: &FileWalker,
+    cancel_token: &CancellationToken,
+    pause_token: &CancellationToken,
+    job_tx: Sender<PathBuf>,
+    msg_tx: Sender<ScanMessage>,
+) -> WalkOutcome {
+    let mut outcome = WalkOutcome::default();
+    // Sorted traversal keeps the walk order stable so a checkpoint path
+    // can be compared against entries on resume.
+    let mut entries = WalkDir::new(root_path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = entries.next() {
+        if cancel_token.is_cancelled() {
+            outcome.cancelled = true;
+            break;
+        }
+        if pause_token.is_cancelled() {
+            outcome.paused = true;
+            break;
+        }
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+
+                if let Some(resume) = resume_after.as_ref() {
+                    if resume.starts_with(path) && path != resume.as_path() {
+                        // Ancestor of the checkpoint: descend into it.
+                        continue;
+                    }
+                    if path <= resume.as_path() {
+                        if entry.file_type().is_dir() {
+                            entries.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    resume_after = None;
+                }
+
+                if entry.file_type().is_dir() {
+                    if file_walker.should_skip_dir(path) {
+                        let _ = msg_tx.send(ScanMessage::Skipped);
+                        entries.skip_current_dir();
+                        continue;
+                    }
+                    // A package is one entry; its contents are never walked.
+                    if path != root_path && file_walker.is_package_dir(path) {
+                        entries.skip_current_dir();
+                        outcome.last_path = Some(path.to_path_buf());
+                        if job_tx.send(path.to_path_buf()).is_err() {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+
+                let skipped_link =
+                    entry.file_type().is_symlink() && !file_walker.records_symlinks();
+                if skipped_link || file_walker.should_skip_file(path) {
+                    let _ = msg_tx.send(ScanMessage::Skipped);
+                    continue;
+                }
+
+                outcome.last_path = Some(path.to_path_buf());
+                if job_tx.send(path.to_path_buf()).is_err() {
+                    break;
+                }
+            }
+            Err(err) => {
+                let _ = msg_tx.send(ScanMessage::WalkError(err.to_string()));
+            }
+        }
+    }
+    outcome
+}
+
+/// Folders the app itself writes into: the archive, the trash and the app
+/// data dir. Indexing them would suggest our own archive as clutter, and
+/// watching them would turn every stage into a burst of events.
+pub fn app_owned_dirs(db: &Database) -> Vec<PathBuf> {
+    let archive = match ArchiveConfig::load(db) {
+        Ok(config) => config.base_path,
+        Err(err) => {
+            tracing::warn!("Failed to load archive location: {}", err);
+            ArchiveConfig::default_base_path()
+        }
+    };
+    let mut owned = vec![archive];
+    owned.extend(trash_dirs());
+    if let Some(data) = dirs::data_dir() {
+        owned.push(data.join("white-space"));
+    }
+    // Watcher events arrive canonicalised; compare like with like.
+    for dir in owned.clone() {
+        if let Ok(canonical) = dir.canonicalize() {
+            if canonical != dir {
+                owned.push(canonical);
+            }
+        }
+    }
+    owned
+}
+
 pub struct Scanner {
     file_walker: FileWalker,
     project_detector: ActiveProjectDetector,
     performance_target_ms: u64,
+    cancel_token: CancellationToken,
+    pause_token: CancellationToken,
+    checkpoint: Option<ScanCheckpoint>,
+    concurrency: usize,
+    low_priority_roots: HashSet<String>,
+    partial_hash_roots: HashSet<String>,
+    mode: ScanMode,
 }
 
 impl Scanner {
@@ -337,125 +1018,374 @@ impl Scanner {
             file_walker: FileWalker::new(),
             project_detector: ActiveProjectDetector::new(),
             performance_target_ms: 90_000,
+            cancel_token: CancellationToken::new(),
+            pause_token: CancellationToken::new(),
+            checkpoint: None,
+            concurrency: default_scan_concurrency(),
+            low_priority_roots: HashSet::new(),
+            partial_hash_roots: HashSet::new(),
+            mode: ScanMode::Normal,
         }
     }
 
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.clamp(1, MAX_SCAN_CONCURRENCY);
+        self
+    }
+
+    /// Roots scanned with one worker at background I/O priority.
+    pub fn with_low_priority_roots(mut self, roots: HashSet<String>) -> Self {
+        self.low_priority_roots = roots;
+        self
+    }
+
+    /// Roots indexed from partial hashes alone.
+    pub fn with_partial_hash_roots(mut self, roots: HashSet<String>) -> Self {
+        self.partial_hash_roots = roots;
+        self
+    }
+
+    /// In `ScanMode::Throttled` every root is scanned like a low-priority,
+    /// partial-hash-only root, with a pause after each file.
+    pub fn with_mode(mut self, mode: ScanMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel_token = token;
+        self
+    }
+
+    pub fn with_pause_token(mut self, token: CancellationToken) -> Self {
+        self.pause_token = token;
+        self
+    }
+
+    pub fn resume_from(mut self, checkpoint: ScanCheckpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
     pub fn run_scan<R: tauri::Runtime>(
         &mut self,
         app: &AppHandle<R>,
         roots: Vec<String>,
         db: &Database,
+    ) -> anyhow::Result<ScanResult> {
+        self.scan(Some(app), roots, db)
+    }
+
+    /// Without `app` nothing is emitted and no notifications are shown; the
+    /// index, metrics and checkpoints are written all the same.
+    fn scan<R: tauri::Runtime>(
+        &mut self,
+        app: Option<&AppHandle<R>>,
+        roots: Vec<String>,
+        db: &Database,
     ) -> anyhow::Result<ScanResult> {
         let start_time = SystemTime::now();
 
+        self.load_walk_settings(db);
+
         let repos = self.project_detector.detect_dev_repos(&roots);
         self.record_project_metrics(&repos, db);
 
+        let checkpoint = self.checkpoint.take();
         let mut summary = ScanResult {
-            counted: 0,
-            skipped: 0,
+            counted: checkpoint.as_ref().map(|c| c.counted).unwrap_or(0),
+            skipped: checkpoint.as_ref().map(|c| c.skipped).unwrap_or(0),
             duration_ms: 0,
             errors: Vec::new(),
+            cancelled: false,
+            paused: false,
         };
+        let mut paused_checkpoint: Option<ScanCheckpoint> = None;
 
+        let watched_roots = roots_by_depth(db);
         let mut hash_candidates: HashMap<(u64, String), Vec<(i64, String)>> = HashMap::new();
-        for root in roots.iter() {
+        let scan_started_at = Utc::now();
+        let mut vanished: Vec<i64> = Vec::new();
+        for (root_index, root) in roots.iter().enumerate() {
+            if summary.cancelled || summary.paused {
+                break;
+            }
+
+            let mut resume_after: Option<PathBuf> = None;
+            if let Some(checkpoint) = checkpoint.as_ref() {
+                if root_index < checkpoint.root_index {
+                    continue;
+                }
+                if root_index == checkpoint.root_index {
+                    resume_after = checkpoint.last_path.as_ref().map(PathBuf::from);
+                }
+            }
+            let resumed_root = resume_after.is_some();
+
             let root_path = Path::new(root);
-            if !root_path.exists() {
+            if root_went_offline(db, root) {
+                // Keep the index as it was until the drive comes back.
+                if let Err(err) = db.set_root_offline(root, true) {
+                    summary
+                        .errors
+                        .push(format!("Failed to mark {} offline: {}", root, err));
+                }
+                summary.errors.push(format!("Root is offline: {}", root));
+                continue;
+            }
+            if let Err(err) = db.set_root_offline(root, false) {
                 summary
                     .errors
-                    .push(format!("Root path does not exist: {}", root));
-                continue;
+                    .push(format!("Failed to bring {} back online: {}", root, err));
             }
 
+            // Reconciling by id rather than path prefix survives symlinked,
+            // renamed and re-added roots. Files under a nested watched root
+            // keep belonging to it.
+            let root_id = db.watched_root_id_for(root).ok().flatten();
+            let nested_roots: Vec<(PathBuf, i64)> = watched_roots
+                .iter()
+                .filter(|(path, id)| Some(*id) != root_id && path.starts_with(root_path))
+                .cloned()
+                .collect();
             let mut root_seen: HashSet<String> = HashSet::new();
-            let mut entries = WalkDir::new(root_path).follow_links(false).into_iter();
-            while let Some(entry) = entries.next() {
-                match entry {
-                    Ok(entry) => {
-                        let path = entry.path();
-
-                        if entry.file_type().is_dir() {
-                            if self.file_walker.should_skip_dir(path) {
-                                summary.skipped += 1;
-                                entries.skip_current_dir();
+            let resume_from = resume_after.clone();
+            let throttled = self.mode == ScanMode::Throttled;
+            let low_priority = throttled || self.low_priority_roots.contains(root);
+            let partial_only = throttled || self.partial_hash_roots.contains(root);
+            let concurrency = if low_priority {
+                1
+            } else {
+                self.concurrency.max(1)
+            };
+            let (job_tx, job_rx) = bounded::<PathBuf>(concurrency * JOB_QUEUE_DEPTH);
+            let (msg_tx, msg_rx) = unbounded::<ScanMessage>();
+            let file_walker = &self.file_walker;
+            let cancel_token = &self.cancel_token;
+            let pause_token = &self.pause_token;
+
+            // Three-stage pipeline: one thread walks, `concurrency` workers read
+            // metadata and hash, and this thread performs every DB write so
+            // progress counts stay monotonic.
+            let outcome = std::thread::scope(|scope| {
+                for _ in 0..concurrency {
+                    let job_rx = job_rx.clone();
+                    let msg_tx = msg_tx.clone();
+                    scope.spawn(move || {
+                        if low_priority {
+                            lower_io_priority();
+                        }
+                        for path in job_rx {
+                            let hashed = hash_file(file_walker, &path, partial_only);
+                            if msg_tx.send(ScanMessage::Hashed(hashed.map(Box::new))).is_err() {
+                                break;
+                            }
+                            if throttled {
+                                std::thread::sleep(THROTTLE_PAUSE);
                             }
-                            continue;
                         }
+                    });
+                }
+                drop(job_rx);
 
-                        if entry.file_type().is_symlink() {
-                            summary.skipped += 1;
-                            continue;
+                let walker = scope.spawn(move || {
+                    if low_priority {
+                        lower_io_priority();
+                    }
+                    walk_root(
+                        root_path,
+                        resume_after,
+                        file_walker,
+                        cancel_token,
+                        pause_token,
+                        job_tx,
+                        msg_tx,
+                    )
+                });
+
+                for message in msg_rx {
+                    match message {
+                        ScanMessage::Hashed(Ok(hashed)) => {
+                            let path = hashed.metadata.path.clone();
+                            let file_root = owning_root_id(&nested_roots, &path).or(root_id);
+                            match self.store_hashed(*hashed, file_root, db, &mut hash_candidates) {
+                                Ok(stored_path) => {
+                                    root_seen.insert(stored_path);
+                                    summary.counted += 1;
+                                    if summary.counted.is_multiple_of(PROGRESS_EMIT_INTERVAL) {
+                                        if let Some(app) = app {
+                                            emit_progress(
+                                                app,
+                                                summary.counted,
+                                                summary.skipped,
+                                                summary.errors.len() as u64,
+                                                Some(path.as_path()),
+                                            );
+                                        }
+                                        update_progress(
+                                            summary.counted,
+                                            summary.skipped,
+                                            summary.errors.len() as u64,
+                                            Some(path),
+                                        );
+                                    }
+                                }
+                                Err(err) => {
+                                    summary.errors.push(err.to_string());
+                                }
+                            }
                         }
-
-                        if self.file_walker.should_skip_file(path) {
+                        ScanMessage::Hashed(Err(err)) => {
+                            summary.errors.push(err.to_string());
+                        }
+                        ScanMessage::Skipped => {
                             summary.skipped += 1;
-                            continue;
                         }
-
-                        match self.process_file(path, db, &mut hash_candidates) {
-                            Ok(stored_path) => {
-                                root_seen.insert(stored_path);
-                                summary.counted += 1;
-                                if summary.counted % PROGRESS_EMIT_INTERVAL == 0 {
-                                    emit_progress(
-                                        app,
-                                        summary.counted,
-                                        summary.skipped,
-                                        summary.errors.len() as u64,
-                                        Some(path),
-                                    );
-                                    update_progress(
-                                        summary.counted,
-                                        summary.skipped,
-                                        summary.errors.len() as u64,
-                                        Some(path.to_path_buf()),
-                                    );
-                                }
-                            }
-                            Err(err) => {
-                                summary.errors.push(err.to_string());
-                            }
+                        ScanMessage::WalkError(message) => {
+                            summary.errors.push(message);
+                            summary.skipped += 1;
                         }
                     }
-                    Err(err) => {
-                        summary.errors.push(err.to_string());
-                        summary.skipped += 1;
-                    }
                 }
+
+                walker.join().unwrap_or_default()
+            });
+
+            if outcome.cancelled {
+                summary.cancelled = true;
+            }
+            if outcome.paused {
+                // Every path handed to the workers has been written by now, so
+                // the last dispatched path is a safe resume point.
+                summary.paused = true;
+                paused_checkpoint = Some(ScanCheckpoint {
+                    roots: roots.clone(),
+                    root_index,
+                    last_path: outcome
+                        .last_path
+                        .or(resume_from)
+                        .map(|p| p.to_string_lossy().to_string()),
+                    counted: summary.counted,
+                    skipped: summary.skipped,
+                });
             }
 
-            if let Err(err) = db.mark_missing_for_root(root, &root_seen) {
+            // A partial walk has not seen every file, so reconciling would
+            // wrongly flag the unvisited remainder as deleted.
+            if summary.cancelled || summary.paused {
+                break;
+            }
+            if resumed_root {
+                continue;
+            }
+
+            let reconciled = match root_id {
+                Some(id) => std::iter::once(id)
+                    .chain(nested_roots.iter().map(|(_, nested)| *nested))
+                    .try_for_each(|id| {
+                        vanished.extend(db.mark_missing_for_root_id(id, &root_seen)?);
+                        Ok::<_, rusqlite::Error>(())
+                    }),
+                None => db
+                    .mark_missing_for_root(root, &root_seen)
+                    .map(|ids| vanished.extend(ids)),
+            };
+            if let Err(err) = reconciled {
                 summary.errors.push(format!("Failed to reconcile missing entries for {}: {}", root, err));
             }
         }
 
-        self.populate_full_hashes(db, &mut hash_candidates, &mut summary);
+        let duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+        summary.duration_ms = duration.as_millis() as u64;
+
+        if let Some(checkpoint) = paused_checkpoint {
+            save_checkpoint(db, &checkpoint)?;
+            update_progress(
+                summary.counted,
+                summary.skipped,
+                summary.errors.len() as u64,
+                None,
+            );
+            if let Some(app) = app {
+                emit_paused(
+                    app,
+                    ScanPausedPayload {
+                        scanned: summary.counted,
+                        skipped: summary.skipped,
+                        errors: summary.errors.len() as u64,
+                        last_path: checkpoint.last_path.clone(),
+                    },
+                );
+            }
+            return Ok(summary);
+        }
+
+        clear_checkpoint_for(db, &roots);
+
+        if !summary.cancelled {
+            self.reconcile_moves(
+                db,
+                &vanished,
+                &scan_started_at,
+                &mut hash_candidates,
+                &mut summary,
+            );
+        }
+
+        // Full hashing waits for a scan on mains power.
+        if self.mode == ScanMode::Normal && !summary.cancelled {
+            self.rehash_legacy_matches(db);
+            self.populate_full_hashes(app, db, &mut hash_candidates, &mut summary);
+        }
+
+        if summary.cancelled {
+            update_progress(
+                summary.counted,
+                summary.skipped,
+                summary.errors.len() as u64,
+                None,
+            );
+            if let Some(app) = app {
+                emit_cancelled(
+                    app,
+                    ScanCancelledPayload {
+                        scanned: summary.counted,
+                        skipped: summary.skipped,
+                        errors: summary.errors.len() as u64,
+                        started_at: Some(DateTime::<Utc>::from(start_time)),
+                        cancelled_at: Some(Utc::now()),
+                    },
+                );
+            }
+            return Ok(summary);
+        }
 
         let duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
         summary.duration_ms = duration.as_millis() as u64;
 
-        emit_progress(
-            app,
-            summary.counted,
-            summary.skipped,
-            summary.errors.len() as u64,
-            None,
-        );
+        if let Some(app) = app {
+            emit_progress(
+                app,
+                summary.counted,
+                summary.skipped,
+                summary.errors.len() as u64,
+                None,
+            );
+        }
         let finished_at = Utc::now();
         let started_at = DateTime::<Utc>::from(start_time);
-        emit_done(
-            app,
-            ScanFinishedPayload {
-                scanned: summary.counted,
-                skipped: summary.skipped,
-                errors: summary.errors.len() as u64,
-                error_messages: summary.errors.clone(),
-                started_at: Some(started_at),
-                finished_at: Some(finished_at),
-            },
-        );
-        if !summary.errors.is_empty() {
+        if let Some(app) = app {
+            emit_done(
+                app,
+                ScanFinishedPayload {
+                    scanned: summary.counted,
+                    skipped: summary.skipped,
+                    errors: summary.errors.len() as u64,
+                    error_messages: summary.errors.clone(),
+                    started_at: Some(started_at),
+                    finished_at: Some(finished_at),
+                },
+            );
             for message in &summary.errors {
                 emit_error(app, message.clone());
             }
@@ -468,26 +1398,45 @@ impl Scanner {
         );
 
         self.record_performance_metrics(&summary, duration, db);
+        if let Some(app) = app {
+            crate::gauge::publish_gauge_update(app, db);
+            notifications::notify_scan_finished(app, db);
+            notifications::notify_saved_views(app, db);
+        }
 
         Ok(summary)
     }
 
-    fn process_file(
+    /// Loads exclusions and the symlink setting into the walker.
+    fn load_walk_settings(&mut self, db: &Database) {
+        self.file_walker.set_app_dirs(app_owned_dirs(db));
+        match db.list_exclusions() {
+            Ok(exclusions) => self.file_walker.set_exclusions(&exclusions),
+            Err(err) => tracing::warn!("Failed to load scan exclusions: {}", err),
+        }
+        match db.get_preference(RECORD_SYMLINKS_PREF) {
+            Ok(value) => self.file_walker.set_record_symlinks(value.as_deref() == Some("true")),
+            Err(err) => tracing::warn!("Failed to load symlink pref: {}", err),
+        }
+    }
+
+    fn store_hashed(
         &self,
-        path: &Path,
+        hashed: HashedFile,
+        root_id: Option<i64>,
         db: &Database,
         hash_candidates: &mut HashMap<(u64, String), Vec<(i64, String)>>,
     ) -> anyhow::Result<String> {
-        let metadata = self.file_walker.extract_metadata(path)?;
+        let HashedFile {
+            metadata,
+            partial_hash,
+            full_hash,
+            phash,
+            partial_only,
+        } = hashed;
         let path_str = metadata.path.to_string_lossy().to_string();
         let parent_dir = metadata.parent_dir.to_string_lossy().to_string();
 
-        let partial_hash = hash_first_n(&metadata.path, PARTIAL_SAMPLE_SIZE).ok();
-        let mut full_hash = None;
-        if metadata.size_bytes <= SMALL_FILE_THRESHOLD {
-            full_hash = hash_full(&metadata.path).ok();
-        }
-
         let new_file = NewFile {
             path: path_str.clone(),
             parent_dir,
@@ -498,11 +1447,22 @@ impl Scanner {
             accessed_at: metadata.accessed_at,
             partial_sha1: partial_hash.clone(),
             sha1: full_hash.clone(),
+            inode_key: metadata.inode_key,
+            link_count: metadata.link_count as i64,
+            phash,
+            is_cloud_placeholder: metadata.is_cloud_placeholder,
+            root_id,
+            is_symlink: metadata.is_symlink,
+            symlink_target: metadata
+                .symlink_target
+                .map(|target| target.to_string_lossy().to_string()),
+            hash_algo: HashAlgo::CURRENT,
         };
 
         let file_id = db.upsert_file(&new_file)?;
+        crate::thumbnail::invalidate_if_stale(file_id, metadata.modified_at);
 
-        if full_hash.is_none() {
+        if full_hash.is_none() && !partial_only {
             if let Some(partial) = partial_hash {
                 hash_candidates
                     .entry((metadata.size_bytes, partial))
@@ -514,29 +1474,164 @@ impl Scanner {
         Ok(path_str)
     }
 
-    fn populate_full_hashes(
+    /// Turns a vanished file and a new one with the same content into a move
+    /// of the old row, so history and hashes follow a file the user moved
+    /// between or within roots.
+    fn reconcile_moves(
         &self,
         db: &Database,
+        vanished: &[i64],
+        since: &DateTime<Utc>,
         hash_candidates: &mut HashMap<(u64, String), Vec<(i64, String)>>,
         summary: &mut ScanResult,
     ) {
-        for ((_, partial), entries) in hash_candidates.drain() {
-            if entries.len() < 2 {
+        if vanished.is_empty() {
+            return;
+        }
+        let gone = vanished
+            .iter()
+            .filter_map(|id| db.get_file_by_id(*id).transpose())
+            .collect::<Result<Vec<_>, _>>();
+        let arrived = db.files_first_seen_since(since);
+        let (gone, arrived) = match (gone, arrived) {
+            (Ok(gone), Ok(arrived)) => (gone, arrived),
+            (Err(err), _) | (_, Err(err)) => {
+                summary.errors.push(format!("Failed to look for moved files: {}", err));
+                return;
+            }
+        };
+
+        let mut moved = 0;
+        for (from, to) in moves::match_moves(&gone, &arrived) {
+            if let Err(err) = db.merge_moved_file(from, to) {
+                summary.errors.push(format!("Failed to record moved file {}: {}", from, err));
                 continue;
             }
+            moved += 1;
+            for (file_id, _) in hash_candidates.values_mut().flatten() {
+                if *file_id == to {
+                    *file_id = from;
+                }
+            }
+        }
+        if moved > 0 {
+            tracing::info!(moved, "matched moved files to their old rows");
+        }
+    }
 
+    /// Re-keys rows hashed with an older algorithm or fingerprint when their
+    /// size matches a current row, so no duplicate hides behind the switch.
+    /// Everything else moves over when it is next hashed.
+    fn rehash_legacy_matches(&self, db: &Database) {
+        let legacy = match db.legacy_hashed_size_matches(HashAlgo::CURRENT) {
+            Ok(legacy) => legacy,
+            Err(err) => {
+                tracing::warn!("Failed to load files with legacy hashes: {}", err);
+                return;
+            }
+        };
+        let max_bytes = max_full_hash_bytes(db);
+        for (file_id, path, size) in legacy {
+            let partial = match hash_sampled(Path::new(&path), PARTIAL_SAMPLE_SIZE) {
+                Ok(partial) => partial,
+                Err(err) => {
+                    tracing::warn!("Failed to rehash {}: {}", path, err);
+                    continue;
+                }
+            };
+            if let Err(err) = db.update_file_hashes(file_id, &partial, None, HashAlgo::CURRENT) {
+                tracing::warn!("Failed to update hash for {}: {}", path, err);
+                continue;
+            }
+            let matches = db
+                .active_files_with_partial_hash(size, &partial, HashAlgo::CURRENT)
+                .unwrap_or_default();
+            if matches.len() < 2 || max_bytes.is_some_and(|max| size > max) {
+                continue;
+            }
+            for (match_id, match_path, has_full) in matches {
+                let partial_only = self
+                    .partial_hash_roots
+                    .iter()
+                    .any(|root| match_path.starts_with(root));
+                if has_full || partial_only {
+                    continue;
+                }
+                let full = match hash_full(Path::new(&match_path)) {
+                    Ok(full) => full,
+                    Err(err) => {
+                        tracing::warn!("Failed to hash {}: {}", match_path, err);
+                        continue;
+                    }
+                };
+                if let Err(err) =
+                    db.update_file_hashes(match_id, &partial, Some(&full), HashAlgo::CURRENT)
+                {
+                    tracing::warn!("Failed to update hash for {}: {}", match_path, err);
+                }
+            }
+        }
+    }
+
+    /// Full-hashes every file that shares its size and partial hash with
+    /// another, streaming each one so progress is reported in bytes and a
+    /// cancel stops mid-file. Files over the size pref are left partial.
+    fn populate_full_hashes<R: tauri::Runtime>(
+        &self,
+        app: Option<&AppHandle<R>>,
+        db: &Database,
+        hash_candidates: &mut HashMap<(u64, String), Vec<(i64, String)>>,
+        summary: &mut ScanResult,
+    ) {
+        let max_bytes = max_full_hash_bytes(db);
+        let groups: Vec<_> = hash_candidates
+            .drain()
+            .filter(|((size, _), entries)| {
+                entries.len() >= 2 && max_bytes.is_none_or(|max| *size <= max)
+            })
+            .collect();
+        let total: u64 = groups
+            .iter()
+            .map(|((size, _), entries)| size.saturating_mul(entries.len() as u64))
+            .sum();
+        let mut done = 0u64;
+        let mut last_emit = Instant::now();
+
+        for ((size, partial), entries) in groups {
             for (file_id, path) in entries {
+                if self.cancel_token.is_cancelled() {
+                    summary.cancelled = true;
+                    return;
+                }
                 let path_buf = PathBuf::from(&path);
-                match hash_full(&path_buf) {
-                    Ok(full) => {
+                let file_start = done;
+                let hashed = hash_full_streaming(&path_buf, |read| {
+                    done += read;
+                    if last_emit.elapsed() >= HASH_PROGRESS_INTERVAL {
+                        last_emit = Instant::now();
+                        if let Some(app) = app {
+                            emit_hash_progress(app, summary, done, total, Some(&path_buf));
+                        }
+                    }
+                    !self.cancel_token.is_cancelled()
+                });
+                // The file may have changed size since the walk; keep the
+                // running count in step with the total.
+                done = file_start + size;
+                match hashed {
+                    Ok(Some(full)) => {
                         if let Err(err) =
-                            db.update_file_hashes(file_id, Some(&partial), Some(&full))
+                            db.update_file_hashes(file_id, &partial, Some(&full), HashAlgo::CURRENT)
                         {
                             summary
                                 .errors
                                 .push(format!("Failed to update hash for {}: {}", path, err));
                         }
                     }
+                    Ok(None) => {
+                        summary.cancelled = true;
+                        return;
+                    }
                     Err(err) => {
                         summary
                             .errors
@@ -545,6 +1640,188 @@ impl Scanner {
                 }
             }
         }
+        if let Some(app) = app.filter(|_| total > 0) {
+            emit_hash_progress(app, summary, total, total, None);
+        }
+    }
+
+    /// Re-indexes only `paths` after watcher events: existing files are
+    /// hashed and upserted, directories are walked and reconciled, and
+    /// vanished paths are marked missing along with anything under them.
+    /// Returns `None` without writing anything when the paths expand to more
+    /// than `max_files` files, so the caller can queue a root scan instead.
+    pub fn index_paths(
+        &mut self,
+        paths: &[PathBuf],
+        max_files: usize,
+        db: &Database,
+    ) -> anyhow::Result<Option<IncrementalUpdate>> {
+        self.load_walk_settings(db);
+        let records_links = self.file_walker.records_symlinks();
+
+        // An unplugged drive looks like its whole tree vanishing; take the
+        // root offline rather than marking every file under it missing.
+        let mut offline_roots = Vec::new();
+        for root in db.list_watched_paths()? {
+            if paths.iter().any(|path| path.starts_with(&root)) && root_went_offline(db, &root) {
+                db.set_root_offline(&root, true)?;
+                offline_roots.push(PathBuf::from(root));
+            }
+        }
+        let mut unique = HashSet::new();
+        let paths: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| !offline_roots.iter().any(|root| path.starts_with(root)))
+            .map(|path| self.file_walker.package_root(path).unwrap_or_else(|| path.clone()))
+            .filter(|path| unique.insert(path.clone()))
+            .collect();
+
+        let mut update = IncrementalUpdate::default();
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        let mut missing = Vec::new();
+        for path in &paths {
+            let Ok(metadata) = std::fs::symlink_metadata(path) else {
+                missing.push(path);
+                continue;
+            };
+            // A root walk never descends into skipped folders; match that.
+            if path
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| self.file_walker.should_skip_dir(ancestor))
+            {
+                update.skipped += 1;
+                continue;
+            }
+            if metadata.is_dir() && self.file_walker.is_package_dir(path) {
+                if self.file_walker.should_skip_dir(path) {
+                    update.skipped += 1;
+                    continue;
+                }
+                files.push(path.clone());
+                if files.len() > max_files {
+                    return Ok(None);
+                }
+            } else if metadata.is_dir() {
+                if self.file_walker.should_skip_dir(path) {
+                    update.skipped += 1;
+                    continue;
+                }
+                let mut entries = WalkDir::new(path).follow_links(false).into_iter();
+                while let Some(entry) = entries.next() {
+                    let Ok(entry) = entry else {
+                        update.skipped += 1;
+                        continue;
+                    };
+                    if entry.file_type().is_dir() {
+                        if entry.path() == path.as_path() {
+                            continue;
+                        }
+                        if self.file_walker.should_skip_dir(entry.path()) {
+                            entries.skip_current_dir();
+                        } else if self.file_walker.is_package_dir(entry.path()) {
+                            entries.skip_current_dir();
+                            files.push(entry.into_path());
+                            if files.len() > max_files {
+                                return Ok(None);
+                            }
+                        }
+                    } else if (entry.file_type().is_file()
+                        || (records_links && entry.file_type().is_symlink()))
+                        && !self.file_walker.should_skip_file(entry.path())
+                    {
+                        files.push(entry.into_path());
+                        if files.len() > max_files {
+                            return Ok(None);
+                        }
+                    }
+                }
+                dirs.push(path);
+            } else if (metadata.is_file() || (records_links && metadata.is_symlink()))
+                && !self.file_walker.should_skip_file(path)
+            {
+                files.push(path.clone());
+                if files.len() > max_files {
+                    return Ok(None);
+                }
+            } else {
+                update.skipped += 1;
+            }
+        }
+
+        let roots = roots_by_depth(db);
+        let mut seen = HashSet::new();
+        let mut hash_candidates: HashMap<(u64, String), Vec<(i64, String)>> = HashMap::new();
+        for path in &files {
+            let partial_only = self
+                .partial_hash_roots
+                .iter()
+                .any(|root| path.starts_with(root));
+            let root_id = owning_root_id(&roots, path);
+            match hash_file(&self.file_walker, path, partial_only)
+                .and_then(|hashed| self.store_hashed(hashed, root_id, db, &mut hash_candidates))
+            {
+                Ok(stored_path) => {
+                    seen.insert(stored_path);
+                    update.updated += 1;
+                }
+                // The file vanished between the event and the read.
+                Err(_) if !path.exists() => missing.push(path),
+                Err(err) => update.errors.push(err.to_string()),
+            }
+        }
+
+        for dir in dirs {
+            let dir = dir.to_string_lossy();
+            if let Err(err) = db.mark_missing_for_root(&dir, &seen) {
+                update.errors.push(format!(
+                    "Failed to reconcile missing entries for {}: {}",
+                    dir, err
+                ));
+            }
+        }
+        for path in missing {
+            match db.mark_path_missing(&path.to_string_lossy()) {
+                Ok(count) => update.removed += count as u64,
+                Err(err) => update.errors.push(err.to_string()),
+            }
+        }
+
+        // Large files only get a full hash once another file shares their
+        // partial hash; here the match can be an already indexed file.
+        self.rehash_legacy_matches(db);
+        let max_bytes = max_full_hash_bytes(db);
+        for ((size, partial), _) in hash_candidates.drain() {
+            if max_bytes.is_some_and(|max| size > max) {
+                continue;
+            }
+            let matches = db.active_files_with_partial_hash(size, &partial, HashAlgo::CURRENT)?;
+            if matches.len() < 2 {
+                continue;
+            }
+            for (file_id, path, has_full) in matches {
+                if has_full {
+                    continue;
+                }
+                match hash_full(Path::new(&path)) {
+                    Ok(full) => {
+                        if let Err(err) =
+                            db.update_file_hashes(file_id, &partial, Some(&full), HashAlgo::CURRENT)
+                        {
+                            update
+                                .errors
+                                .push(format!("Failed to update hash for {}: {}", path, err));
+                        }
+                    }
+                    Err(err) => update
+                        .errors
+                        .push(format!("Failed to hash {}: {}", path, err)),
+                }
+            }
+        }
+
+        Ok(Some(update))
     }
 
     fn record_project_metrics(&self, repos: &[DevRepo], db: &Database) {
@@ -556,7 +1833,7 @@ impl Scanner {
                 context: Some("scan".to_string()),
             };
             if let Err(e) = db.insert_metric(&new_metric) {
-                eprintln!("Failed to record project metric: {}", e);
+                tracing::warn!("Failed to record project metric: {}", e);
             }
         }
 
@@ -566,7 +1843,7 @@ impl Scanner {
             context: Some("scan".to_string()),
         };
         if let Err(e) = db.insert_metric(&total_repos) {
-            eprintln!("Failed to record total repos metric: {}", e);
+            tracing::warn!("Failed to record total repos metric: {}", e);
         }
     }
 
@@ -579,7 +1856,7 @@ impl Scanner {
             context: Some("performance".to_string()),
         };
         if let Err(e) = db.insert_metric(&performance_metric) {
-            eprintln!("Failed to record performance metric: {}", e);
+            tracing::warn!("Failed to record performance metric: {}", e);
         }
 
         let files_counted = NewMetric {
@@ -588,7 +1865,7 @@ impl Scanner {
             context: Some("scan".to_string()),
         };
         if let Err(e) = db.insert_metric(&files_counted) {
-            eprintln!("Failed to record files counted metric: {}", e);
+            tracing::warn!("Failed to record files counted metric: {}", e);
         }
 
         let files_skipped = NewMetric {
@@ -597,7 +1874,7 @@ impl Scanner {
             context: Some("scan".to_string()),
         };
         if let Err(e) = db.insert_metric(&files_skipped) {
-            eprintln!("Failed to record files skipped metric: {}", e);
+            tracing::warn!("Failed to record files skipped metric: {}", e);
         }
 
         let files_per_second = if duration_ms > 0 {
@@ -611,7 +1888,7 @@ impl Scanner {
             context: Some("performance".to_string()),
         };
         if let Err(e) = db.insert_metric(&throughput_metric) {
-            eprintln!("Failed to record throughput metric: {}", e);
+            tracing::warn!("Failed to record throughput metric: {}", e);
         }
 
         let target_met = duration_ms <= self.performance_target_ms;
@@ -621,7 +1898,7 @@ impl Scanner {
             context: Some("performance".to_string()),
         };
         if let Err(e) = db.insert_metric(&target_metric) {
-            eprintln!("Failed to record target metric: {}", e);
+            tracing::warn!("Failed to record target metric: {}", e);
         }
     }
 }
@@ -644,10 +1921,41 @@ fn emit_progress<R: tauri::Runtime>(
         skipped,
         errors,
         path_sample: path.map(|p| p.to_string_lossy().to_string()),
+        hash_bytes_done: 0,
+        hash_bytes_total: 0,
     };
     let _ = app.emit(SCAN_PROGRESS_EVENT, payload);
 }
 
+fn emit_hash_progress<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    summary: &ScanResult,
+    done: u64,
+    total: u64,
+    path: Option<&Path>,
+) {
+    let payload = ScanProgressPayload {
+        scanned: summary.counted,
+        skipped: summary.skipped,
+        errors: summary.errors.len() as u64,
+        path_sample: path.map(|p| p.to_string_lossy().to_string()),
+        hash_bytes_done: done,
+        hash_bytes_total: total,
+    };
+    let _ = app.emit(SCAN_PROGRESS_EVENT, payload);
+}
+
+/// The largest file that may be full-hashed, from `MAX_FULL_HASH_GB_PREF`.
+fn max_full_hash_bytes(db: &Database) -> Option<u64> {
+    let gb: u64 = db
+        .get_preference(MAX_FULL_HASH_GB_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    (gb > 0).then(|| gb.saturating_mul(1024 * 1024 * 1024))
+}
+
 fn emit_done<R: tauri::Runtime>(app: &AppHandle<R>, payload: ScanFinishedPayload) {
     let _ = app.emit(SCAN_DONE_EVENT, payload);
 }
@@ -657,6 +1965,14 @@ fn emit_error<R: tauri::Runtime>(app: &AppHandle<R>, message: String) {
     let _ = app.emit(SCAN_ERROR_EVENT, payload);
 }
 
+fn emit_cancelled<R: tauri::Runtime>(app: &AppHandle<R>, payload: ScanCancelledPayload) {
+    let _ = app.emit(SCAN_CANCELLED_EVENT, payload);
+}
+
+fn emit_paused<R: tauri::Runtime>(app: &AppHandle<R>, payload: ScanPausedPayload) {
+    let _ = app.emit(SCAN_PAUSED_EVENT, payload);
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ScanQueuedPayload {
     pub roots: usize,
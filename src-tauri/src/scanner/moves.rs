@@ -0,0 +1,101 @@
+use crate::models::File;
+use std::collections::HashMap;
+
+/// Pairs files a scan found missing with files it saw for the first time,
+/// as `(vanished_id, arrived_id)`, when the two share a size and partial
+/// hash and nothing else does. Full hashes must agree when both are known.
+/// Empty files and symlinks say too little about their content to count.
+pub fn match_moves(vanished: &[File], arrived: &[File]) -> Vec<(i64, i64)> {
+    let mut groups: HashMap<_, (Vec<&File>, Vec<&File>)> = HashMap::new();
+    for (file, is_arrival) in vanished
+        .iter()
+        .map(|file| (file, false))
+        .chain(arrived.iter().map(|file| (file, true)))
+    {
+        let (Some(_), Some(partial)) = (file.id, file.partial_sha1.as_deref()) else {
+            continue;
+        };
+        if file.size_bytes <= 0 || file.is_symlink {
+            continue;
+        }
+        let group = groups
+            .entry((file.size_bytes, partial, file.hash_algo))
+            .or_default();
+        if is_arrival {
+            group.1.push(file);
+        } else {
+            group.0.push(file);
+        }
+    }
+
+    let mut moves: Vec<(i64, i64)> = groups
+        .into_values()
+        .filter_map(|(gone, new)| match (gone.as_slice(), new.as_slice()) {
+            ([from], [to]) => match (from.sha1.as_deref(), to.sha1.as_deref()) {
+                (Some(a), Some(b)) if a != b => None,
+                _ => Some((from.id?, to.id?)),
+            },
+            _ => None,
+        })
+        .collect();
+    moves.sort_unstable();
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashAlgo;
+    use chrono::Utc;
+
+    fn file(id: i64, size: i64, partial: &str, sha1: Option<&str>) -> File {
+        File {
+            id: Some(id),
+            path: format!("/f/{id}"),
+            parent_dir: "/f".to_string(),
+            mime: None,
+            size_bytes: size,
+            created_at: Utc::now(),
+            modified_at: None,
+            accessed_at: None,
+            last_opened_at: None,
+            partial_sha1: Some(partial.to_string()),
+            sha1: sha1.map(str::to_string),
+            first_seen_at: Utc::now(),
+            last_seen_at: Utc::now(),
+            is_deleted: false,
+            is_staged: false,
+            cooloff_until: None,
+            is_cloud_placeholder: false,
+            is_offline: false,
+            is_symlink: false,
+            symlink_target: None,
+            hash_algo: HashAlgo::CURRENT,
+        }
+    }
+
+    #[test]
+    fn test_only_unambiguous_matches_are_moves() {
+        let vanished = vec![
+            file(1, 4096, "aa", Some("full-a")),
+            // Two copies gone, one back: which one moved is unknown.
+            file(2, 100, "bb", None),
+            file(3, 100, "bb", None),
+            file(4, 50, "cc", Some("full-c")),
+            file(5, 0, "empty", None),
+        ];
+        let arrived = vec![
+            file(10, 4096, "aa", None),
+            file(11, 100, "bb", None),
+            // Same sampled bytes, different content.
+            file(12, 50, "cc", Some("other")),
+            file(13, 0, "empty", None),
+            file(14, 4096, "zz", None),
+        ];
+        assert_eq!(match_moves(&vanished, &arrived), vec![(1, 10)]);
+
+        let mut legacy = file(20, 4096, "aa", None);
+        legacy.hash_algo = HashAlgo::Sha1;
+        assert!(match_moves(&[legacy], &arrived[..1]).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::db::Database;
+use serde::Serialize;
+use std::time::Duration;
+
+/// "auto" (default) throttles on battery; "normal" and "throttled" force a
+/// mode regardless of the power source.
+pub const SCAN_POWER_MODE_PREF: &str = "scan_power_mode";
+pub const SCAN_POWER_MODES: &[&str] = &["auto", "normal", "throttled"];
+/// Pause a throttled worker takes after each file.
+pub const THROTTLE_PAUSE: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+/// How hard a scan works the disk and CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    #[default]
+    Normal,
+    /// One worker at background I/O priority, pausing between files and
+    /// reading only sampled hashes.
+    Throttled,
+}
+
+impl ScanMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanMode::Normal => "normal",
+            ScanMode::Throttled => "throttled",
+        }
+    }
+}
+
+/// Mode for the next scan: the pref when it forces one, otherwise throttled
+/// while the machine runs on battery.
+pub fn scan_mode(db: &Database) -> ScanMode {
+    let pref = db.get_preference(SCAN_POWER_MODE_PREF).ok().flatten();
+    mode_for(pref.as_deref(), power_source())
+}
+
+pub fn mode_for(pref: Option<&str>, source: PowerSource) -> ScanMode {
+    match pref.map(str::trim) {
+        Some("normal") => ScanMode::Normal,
+        Some("throttled") => ScanMode::Throttled,
+        _ if source == PowerSource::Battery => ScanMode::Throttled,
+        _ => ScanMode::Normal,
+    }
+}
+
+/// Whether the machine is on mains power. Desktops without a battery report
+/// `Ac` or `Unknown`, and both scan normally.
+#[cfg(target_os = "linux")]
+pub fn power_source() -> PowerSource {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return PowerSource::Unknown;
+    };
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path)
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let supply = entry.path();
+        match read(supply.join("type")).as_str() {
+            "Mains" | "USB" if read(supply.join("online")) == "1" => return PowerSource::Ac,
+            "Battery" if read(supply.join("status")) == "Discharging" => discharging = true,
+            _ => {}
+        }
+    }
+    if discharging {
+        PowerSource::Battery
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn power_source() -> PowerSource {
+    // First line reads "Now drawing from 'AC Power'" or "'Battery Power'".
+    let Ok(output) = std::process::Command::new("pmset").args(["-g", "batt"]).output() else {
+        return PowerSource::Unknown;
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    if text.contains("'Battery Power'") {
+        PowerSource::Battery
+    } else if text.contains("'AC Power'") {
+        PowerSource::Ac
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+#[cfg(windows)]
+pub fn power_source() -> PowerSource {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerSource::Unknown;
+    }
+    match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn power_source() -> PowerSource {
+    PowerSource::Unknown
+}
@@ -0,0 +1,141 @@
+use crate::db::{Database, DbPool};
+use crate::models::RootSettings;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::OnceCell;
+use std::path::Path;
+use std::time::Duration as StdDuration;
+use tauri::{AppHandle, Emitter};
+
+use super::activity;
+use super::watcher::rewatch_root;
+use super::{current_status, queue_scheduled_scan, root_went_offline};
+
+/// Shortest and longest accepted gap between scheduled scans of a root.
+pub const MIN_SCAN_INTERVAL_HOURS: u32 = 1;
+pub const MAX_SCAN_INTERVAL_HOURS: u32 = 24 * 30;
+/// How often root schedules are checked.
+const CHECK_INTERVAL: StdDuration = StdDuration::from_secs(15 * 60);
+/// Leaves startup to manual scans and the watcher before the first check.
+const FIRST_CHECK_DELAY: StdDuration = StdDuration::from_secs(2 * 60);
+
+static SCHEDULER_STARTED: OnceCell<()> = OnceCell::new();
+
+/// Periodically queues a scan of every root whose scan interval has passed
+/// since it was last scanned, and of offline roots that have come back.
+pub fn start_scan_scheduler<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    if SCHEDULER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_CHECK_DELAY);
+        loop {
+            run_scheduler_now(&app, &pool);
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// One scheduler check, also run by the idle watcher once input goes idle.
+pub fn run_scheduler_now<R: tauri::Runtime>(app: &AppHandle<R>, pool: &DbPool) {
+    if let Err(err) = tick(app, pool) {
+        tracing::error!("scheduled scan failed: {err}");
+    }
+}
+
+fn tick<R: tauri::Runtime>(app: &AppHandle<R>, pool: &DbPool) -> anyhow::Result<()> {
+    // A running or paused scan will stamp its roots; check again next time.
+    if current_status().state != "idle" {
+        return Ok(());
+    }
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+    let db = Database::new(conn);
+    // Due roots stay due, so deferred scans run on a later check.
+    if !activity::heavy_work_allowed(&db) {
+        tracing::debug!("scheduled scans deferred until the user is idle");
+        return Ok(());
+    }
+    let settings = db.list_root_settings()?;
+
+    // Offline roots come back as soon as their volume is mounted again and
+    // are rescanned right away.
+    let mut still_offline = Vec::new();
+    let mut reconnected = Vec::new();
+    for root in db.list_offline_root_paths()? {
+        if root_went_offline(&db, &root) {
+            still_offline.push(root);
+            continue;
+        }
+        let files = db.set_root_offline(&root, false)?;
+        tracing::info!(root = %root, files, "root back online");
+        let watcher_enabled = settings.iter().any(|s| s.path == root && s.watcher_enabled);
+        if watcher_enabled {
+            if let Err(err) = rewatch_root(&root) {
+                tracing::warn!("Failed to watch {} again: {}", root, err);
+            }
+        }
+        reconnected.push(root);
+    }
+    if !reconnected.is_empty() {
+        let _ = app.emit("roots://changed", serde_json::json!({ "count": reconnected.len() }));
+    }
+
+    let mut due: Vec<String> = due_roots(&settings, &Utc::now())
+        .into_iter()
+        .filter(|root| Path::new(root).is_dir() && !still_offline.contains(root))
+        .collect();
+    due.extend(reconnected);
+    let due = Database::outermost_roots(&due);
+    if due.is_empty() {
+        return Ok(());
+    }
+    tracing::info!(roots = ?due, "queueing scheduled scan");
+    queue_scheduled_scan(app, pool, due)
+}
+
+/// Roots with a scan interval that have not completed a scan within it.
+/// A root that was never scanned is due straight away.
+pub fn due_roots(settings: &[RootSettings], now: &DateTime<Utc>) -> Vec<String> {
+    settings
+        .iter()
+        .filter(|root| {
+            let Some(hours) = root.scan_interval_hours.filter(|h| *h > 0) else {
+                return false;
+            };
+            root.last_scanned_at
+                .is_none_or(|at| *now - at >= Duration::hours(hours as i64))
+        })
+        .map(|root| root.path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(path: &str, hours: Option<u32>, last_scanned_hours_ago: Option<i64>) -> RootSettings {
+        RootSettings {
+            path: path.to_string(),
+            scan_interval_hours: hours,
+            watcher_enabled: true,
+            low_priority_io: false,
+            partial_hash_only: false,
+            last_scanned_at: last_scanned_hours_ago.map(|h| Utc::now() - Duration::hours(h)),
+            volume_kind: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_due_roots_follow_each_interval() {
+        let settings = vec![
+            root("/nas", Some(24), Some(30)),
+            root("/desktop", Some(1), Some(0)),
+            root("/manual", None, Some(1000)),
+            root("/new", Some(6), None),
+        ];
+        assert_eq!(
+            due_roots(&settings, &Utc::now()),
+            vec!["/nas".to_string(), "/new".to_string()]
+        );
+    }
+}
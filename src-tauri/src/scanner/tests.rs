@@ -296,9 +296,3 @@ mod tests {
         assert!(result.skipped >= skip_dirs.len() as u64);
     }
 }
-
-
-
-
-
-
@@ -0,0 +1,131 @@
+use crate::models::VolumeKind;
+use std::path::Path;
+
+/// Classifies the volume holding `path`. Anything that cannot be inspected
+/// counts as local, so detection never stops a folder from being added.
+#[cfg(target_os = "linux")]
+pub fn detect_volume_kind(path: &Path) -> VolumeKind {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // statfs(2) magic numbers of network filesystems.
+    const NETWORK_MAGIC: &[u32] = &[
+        0x6969, // NFS
+        0x517B, // SMB
+        0xFF53_4D42, // CIFS
+        0xFE53_4D42, // SMB2
+        0x5346_414F, // AFS
+        0x00C3_6400, // Ceph
+        0x0102_1997, // 9P
+    ];
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return VolumeKind::Local;
+    };
+    let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return VolumeKind::Local;
+    }
+    // `f_type` is signed on some targets; compare the raw 32 bits.
+    if NETWORK_MAGIC.contains(&(stats.f_type as u32)) {
+        return VolumeKind::Network;
+    }
+    match linux_mount_for(path) {
+        Some((mount_point, device)) if is_removable_mount(&mount_point, &device) => {
+            VolumeKind::Removable
+        }
+        _ => VolumeKind::Local,
+    }
+}
+
+/// Mount point and source device of the longest `/proc/mounts` entry that
+/// contains `path`.
+#[cfg(target_os = "linux")]
+fn linux_mount_for(path: &Path) -> Option<(String, String)> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            // Spaces in mount points are written as `\040`.
+            let mount_point = fields.next()?.replace("\\040", " ");
+            Some((mount_point, device.to_string()))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+}
+
+#[cfg(target_os = "linux")]
+fn is_removable_mount(mount_point: &str, device: &str) -> bool {
+    if mount_point.starts_with("/media/") || mount_point.starts_with("/run/media/") {
+        return true;
+    }
+    let Some(name) = device.strip_prefix("/dev/") else {
+        return false;
+    };
+    // Partitions carry no flag of their own; their parent disk does.
+    let block = Path::new("/sys/class/block").join(name);
+    [block.join("removable"), block.join("../removable")]
+        .iter()
+        .any(|flag| std::fs::read_to_string(flag).is_ok_and(|v| v.trim() == "1"))
+}
+
+#[cfg(target_os = "macos")]
+pub fn detect_volume_kind(path: &Path) -> VolumeKind {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    const NETWORK_FS: &[&str] = &["nfs", "smbfs", "afpfs", "webdav", "cifs"];
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return VolumeKind::Local;
+    };
+    let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return VolumeKind::Local;
+    }
+    let fs_type = unsafe { CStr::from_ptr(stats.f_fstypename.as_ptr()) }.to_string_lossy();
+    if NETWORK_FS.contains(&fs_type.as_ref()) || stats.f_flags & libc::MNT_LOCAL as u32 == 0 {
+        return VolumeKind::Network;
+    }
+    // Local volumes other than the startup disk are mounted under /Volumes.
+    let mount_point = unsafe { CStr::from_ptr(stats.f_mntonname.as_ptr()) }.to_string_lossy();
+    if mount_point.starts_with("/Volumes/") {
+        VolumeKind::Removable
+    } else {
+        VolumeKind::Local
+    }
+}
+
+#[cfg(windows)]
+pub fn detect_volume_kind(path: &Path) -> VolumeKind {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, Prefix};
+
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_REMOTE: u32 = 4;
+
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return VolumeKind::Local;
+    };
+    if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..)) {
+        return VolumeKind::Network;
+    }
+    let mut root = prefix.as_os_str().to_os_string();
+    root.push("\\");
+    let wide: Vec<u16> = root.encode_wide().chain(std::iter::once(0)).collect();
+    let drive_type = unsafe {
+        windows::Win32::Storage::FileSystem::GetDriveTypeW(windows::core::PCWSTR(wide.as_ptr()))
+    };
+    match drive_type {
+        DRIVE_REMOTE => VolumeKind::Network,
+        DRIVE_REMOVABLE => VolumeKind::Removable,
+        _ => VolumeKind::Local,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn detect_volume_kind(_path: &Path) -> VolumeKind {
+    VolumeKind::Local
+}
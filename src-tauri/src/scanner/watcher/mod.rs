@@ -1,15 +1,174 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use anyhow::Context;
+use notify::event::{MetadataKind, ModifyKind};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::{Lazy, OnceCell};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, Manager};
 
 use crate::db::{Database, DbPool};
-use super::queue_scan_from_watcher;
+use super::{
+    activity, app_owned_dirs, queue_scan_from_watcher, roots_where, Scanner,
+    SCAN_INCREMENTAL_EVENT,
+};
+
+/// Quiet period after a directory's last event before its changes are applied.
+const DEBOUNCE: Duration = Duration::from_millis(750);
+/// A directory's changes this old are applied even while events keep arriving.
+const MAX_BATCH_AGE: Duration = Duration::from_secs(5);
+/// Changed entries in one directory above which the directory is re-walked.
+const MAX_DIR_PATHS: usize = 100;
+/// Files a batch may expand to (new folders are walked) before falling back.
+const MAX_INCREMENTAL_FILES: usize = 2_000;
+/// Window the per-root event rate is measured over.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Events per root per minute that mean a mass change, e.g. a big build.
+const MAX_EVENTS_PER_MINUTE: usize = 5_000;
+/// Rename events per root per minute treated as a storm, e.g. a large folder move.
+const RENAME_STORM_EVENTS: usize = 200;
+/// Minimum gap between root scans of the same root; a later one waits.
+const RESCAN_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Changes under one directory, debounced on their own so a busy build
+/// folder doesn't hold back edits elsewhere under the same root.
+struct DirChanges {
+    root: PathBuf,
+    paths: HashSet<PathBuf>,
+    /// Too many entries changed; the directory is re-walked instead.
+    whole_dir: bool,
+    first_at: Instant,
+    last_at: Instant,
+}
+
+/// Events collected since the last flush. Roots whose event rate shows a
+/// mass change drop their per-path changes and wait for one root scan.
+#[derive(Default)]
+struct PendingChanges {
+    dirs: HashMap<PathBuf, DirChanges>,
+    /// Roots due a scan, with the time of their latest event.
+    rescan_roots: HashMap<PathBuf, Instant>,
+    activity: HashMap<PathBuf, RootActivity>,
+    last_rescan: HashMap<PathBuf, Instant>,
+}
+
+/// Events seen under one root within the last `RATE_WINDOW`.
+#[derive(Default)]
+struct RootActivity {
+    /// Time of each event and whether it was a rename.
+    events: VecDeque<(Instant, bool)>,
+    renames: usize,
+}
+
+/// What a flush applies: changed paths with their root, and roots to scan.
+#[derive(Debug, Default)]
+struct DueChanges {
+    paths: HashMap<PathBuf, PathBuf>,
+    roots: Vec<PathBuf>,
+}
+
+impl PendingChanges {
+    /// Counts one event against `root`'s rate, escalating on a mass change.
+    fn note_event(&mut self, root: &Path, rename: bool, now: Instant) {
+        let activity = self.activity.entry(root.to_path_buf()).or_default();
+        activity.events.push_back((now, rename));
+        activity.renames += rename as usize;
+        while let Some(&(at, was_rename)) = activity.events.front() {
+            if now.duration_since(at) <= RATE_WINDOW {
+                break;
+            }
+            activity.events.pop_front();
+            activity.renames -= was_rename as usize;
+        }
+        if activity.events.len() > MAX_EVENTS_PER_MINUTE || activity.renames > RENAME_STORM_EVENTS {
+            self.escalate(root, now);
+        }
+    }
+
+    /// Files `path` under its parent directory's batch.
+    fn record_path(&mut self, root: &Path, path: PathBuf, now: Instant) {
+        if let Some(last) = self.rescan_roots.get_mut(root) {
+            *last = now;
+            return;
+        }
+        let dir = path
+            .parent()
+            .filter(|dir| dir.starts_with(root))
+            .unwrap_or(root)
+            .to_path_buf();
+        let batch = self.dirs.entry(dir).or_insert_with(|| DirChanges {
+            root: root.to_path_buf(),
+            paths: HashSet::new(),
+            whole_dir: false,
+            first_at: now,
+            last_at: now,
+        });
+        batch.last_at = now;
+        if !batch.whole_dir {
+            batch.paths.insert(path);
+            if batch.paths.len() > MAX_DIR_PATHS {
+                batch.whole_dir = true;
+                batch.paths.clear();
+            }
+        }
+    }
+
+    /// Replaces everything pending under `root` with one root scan.
+    fn escalate(&mut self, root: &Path, now: Instant) {
+        self.dirs.retain(|_, batch| batch.root != root);
+        self.rescan_roots.insert(root.to_path_buf(), now);
+    }
+
+    /// Takes the directories whose window closed and the roots that have
+    /// gone quiet and are past their backoff.
+    fn take_due(&mut self, now: Instant) -> DueChanges {
+        let mut due = DueChanges::default();
+        let ready: Vec<PathBuf> = self
+            .dirs
+            .iter()
+            .filter(|(_, batch)| {
+                now.duration_since(batch.last_at) >= DEBOUNCE
+                    || now.duration_since(batch.first_at) >= MAX_BATCH_AGE
+            })
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        for dir in ready {
+            let Some(batch) = self.dirs.remove(&dir) else {
+                continue;
+            };
+            if batch.whole_dir {
+                due.paths.insert(dir, batch.root);
+            } else {
+                for path in batch.paths {
+                    due.paths.insert(path, batch.root.clone());
+                }
+            }
+        }
+
+        let roots: Vec<PathBuf> = self
+            .rescan_roots
+            .iter()
+            .filter(|(root, last)| {
+                now.duration_since(**last) >= DEBOUNCE
+                    && self
+                        .last_rescan
+                        .get(*root)
+                        .is_none_or(|at| now.duration_since(*at) >= RESCAN_BACKOFF)
+            })
+            .map(|(root, _)| root.clone())
+            .collect();
+        for root in roots {
+            self.rescan_roots.remove(&root);
+            self.activity.remove(&root);
+            self.last_rescan.insert(root.clone(), now);
+            due.roots.push(root);
+        }
+        due
+    }
+}
 
 struct WatcherRuntime {
     watcher: RecommendedWatcher,
@@ -38,7 +197,7 @@ pub fn start_watchers<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) -> any
             .context("watcher db pool")?;
         let db = Database::new(conn);
         let existing_roots = db
-            .list_watched_paths()
+            .list_watcher_paths()
             .context("list watched roots for watcher")?;
         for path in existing_roots {
             let path_buf = PathBuf::from(&path);
@@ -59,14 +218,26 @@ pub fn start_watchers<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) -> any
         });
     }
 
+    // The database, archive and trash may all sit under a watched root;
+    // our own writes must not turn into events.
+    let app_data = app.path().app_data_dir().ok();
     let thread_app = app.clone();
     let thread_pool = pool.clone();
     std::thread::spawn(move || {
-        let mut backoff: HashMap<PathBuf, Instant> = HashMap::new();
-        while let Ok(event_res) = rx.recv() {
-            match event_res {
-                Ok(event) => handle_event(&thread_app, &thread_pool, &roots_arc, &mut backoff, event),
-                Err(err) => eprintln!("watcher error: {err}"),
+        let mut pending = PendingChanges::default();
+        let mut ignored = ignored_dirs(&thread_pool, app_data.as_deref());
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => record_event(&roots_arc, &ignored, &mut pending, event),
+                Ok(Err(err)) => tracing::error!("watcher error: {err}"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            let due = pending.take_due(Instant::now());
+            if !due.paths.is_empty() || !due.roots.is_empty() {
+                flush_changes(&thread_app, &thread_pool, &mut pending, due);
+                // Picks up a moved archive location.
+                ignored = ignored_dirs(&thread_pool, app_data.as_deref());
             }
         }
     });
@@ -110,50 +281,143 @@ pub fn unregister_root(path: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn handle_event<R: tauri::Runtime>(
-    app: &AppHandle<R>,
-    pool: &DbPool,
+/// Watches `path` again after its volume was remounted; the old watch died
+/// with the mount.
+pub fn rewatch_root(path: &str) -> anyhow::Result<()> {
+    let path_buf = PathBuf::from(path);
+    {
+        let mut state = WATCHER_STATE.lock().expect("watcher state lock");
+        if let Some(runtime) = state.as_mut() {
+            let mut roots = runtime.roots.lock().expect("watcher roots lock");
+            if let Some(index) = roots.iter().position(|existing| existing == &path_buf) {
+                // Unwatching a dead mount may fail; the entry goes either way.
+                let _ = runtime.watcher.unwatch(&path_buf);
+                roots.remove(index);
+            }
+        }
+    }
+    register_root(path)
+}
+
+/// App-owned folders whose events are dropped before they count towards a
+/// root's activity.
+fn ignored_dirs(pool: &DbPool, app_data: Option<&Path>) -> Vec<PathBuf> {
+    let mut ignored = match pool.get() {
+        Ok(conn) => app_owned_dirs(&Database::new(conn)),
+        Err(err) => {
+            tracing::warn!("watcher could not load app folders: {err}");
+            Vec::new()
+        }
+    };
+    ignored.extend(app_data.map(canonicalize_best_effort));
+    ignored
+}
+
+fn record_event(
     roots: &Arc<Mutex<Vec<PathBuf>>>,
-    backoff: &mut HashMap<PathBuf, Instant>,
+    ignored: &[PathBuf],
+    pending: &mut PendingChanges,
     event: Event,
 ) {
-    if !matches!(
-        event.kind,
-        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any
-    ) {
+    let known_roots = roots.lock().expect("watcher roots lock").clone();
+    if known_roots.is_empty() {
         return;
     }
+    let now = Instant::now();
 
-    let known_roots = roots.lock().expect("watcher roots lock").clone();
-    if known_roots.is_empty() {
+    // The OS dropped events (queue overflow): only a root scan is reliable.
+    if event.need_rescan() {
+        for root in known_roots.iter().filter(|root| {
+            event.paths.is_empty() || event.paths.iter().any(|p| p.starts_with(root))
+        }) {
+            pending.escalate(root, now);
+        }
         return;
     }
 
-    let mut affected = HashSet::new();
+    let rename = match event.kind {
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)) => return,
+        EventKind::Modify(ModifyKind::Name(_)) => true,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Any => false,
+        EventKind::Modify(_) => false,
+        _ => return,
+    };
+    let mut counted: HashSet<PathBuf> = HashSet::new();
     for raw_path in event.paths {
         let canonical = canonicalize_best_effort(&raw_path);
-        for root in &known_roots {
-            if canonical.starts_with(root) {
-                affected.insert(root.clone());
+        if ignored.iter().any(|dir| canonical.starts_with(dir)) {
+            continue;
+        }
+        if let Some(root) = known_roots.iter().find(|root| canonical.starts_with(root)) {
+            if counted.insert(root.clone()) {
+                pending.note_event(root, rename, now);
             }
+            pending.record_path(root, canonical, now);
         }
     }
+}
 
-    if affected.is_empty() {
-        return;
-    }
+/// Applies due changes: paths are re-indexed in place, and roots get a
+/// full scan after an overflow, a mass change or an update too large to
+/// handle path by path.
+fn flush_changes<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    pool: &DbPool,
+    pending: &mut PendingChanges,
+    due: DueChanges,
+) {
+    let DueChanges { paths, roots } = due;
 
-    let now = Instant::now();
-    for root in affected {
-        if let Some(last) = backoff.get(&root) {
-            if now.duration_since(*last) < Duration::from_secs(5) {
-                continue;
+    if !paths.is_empty() {
+        let _span = tracing::info_span!("watcher_update", paths = paths.len()).entered();
+        let changed: Vec<PathBuf> = paths.keys().cloned().collect();
+        let result = pool
+            .get()
+            .map_err(|e| anyhow::anyhow!("db pool: {e}"))
+            .and_then(|conn| {
+                let db = Database::new(conn);
+                Scanner::new()
+                    .with_partial_hash_roots(roots_where(&db, |root| root.partial_hash_only))
+                    .index_paths(&changed, MAX_INCREMENTAL_FILES, &db)
+            });
+        let fallback = match result {
+            Ok(Some(update)) => {
+                for message in &update.errors {
+                    tracing::warn!("watcher update error: {message}");
+                }
+                if update.updated > 0 || update.removed > 0 {
+                    // Files come and go constantly; the gauge just recomputes
+                    // on its next read.
+                    crate::gauge::mark_gauge_dirty();
+                    let _ = app.emit(SCAN_INCREMENTAL_EVENT, update);
+                }
+                HashSet::new()
             }
+            Ok(None) => paths.into_values().collect(),
+            Err(err) => {
+                tracing::warn!("watcher update failed, rescanning: {err}");
+                paths.into_values().collect()
+            }
+        };
+        let now = Instant::now();
+        for root in fallback {
+            pending.escalate(&root, now);
+        }
+    }
+
+    // A full-screen app or presentation puts root scans off; the roots go
+    // back to pending and are retried after the rescan backoff.
+    if !roots.is_empty() && activity::user_busy() {
+        let now = Instant::now();
+        for root in &roots {
+            pending.escalate(root, now);
         }
-        backoff.insert(root.clone(), now);
+        return;
+    }
+    for root in roots {
         let root_str = root.to_string_lossy().to_string();
         if let Err(err) = queue_scan_from_watcher(app, pool, vec![root_str]) {
-            eprintln!("failed to queue watcher scan: {err}");
+            tracing::warn!("failed to queue watcher scan: {err}");
         }
     }
 }
@@ -161,3 +425,71 @@ fn handle_event<R: tauri::Runtime>(
 fn canonicalize_best_effort(path: &Path) -> PathBuf {
     path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_directory_coalesces_without_holding_back_others() {
+        let root = PathBuf::from("/home/me");
+        let build = root.join("project/build");
+        let t0 = Instant::now();
+        let mut pending = PendingChanges::default();
+
+        pending.record_path(&root, root.join("Documents/notes.txt"), t0);
+        for i in 0..=MAX_DIR_PATHS {
+            let at = t0 + Duration::from_millis(i as u64 * 5);
+            pending.record_path(&root, build.join(format!("obj{i}.o")), at);
+        }
+
+        // The build is still churning, but the quiet directory flushes.
+        let due = pending.take_due(t0 + DEBOUNCE);
+        assert_eq!(
+            due.paths.keys().cloned().collect::<Vec<_>>(),
+            vec![root.join("Documents/notes.txt")]
+        );
+
+        let due = pending.take_due(t0 + DEBOUNCE + Duration::from_secs(1));
+        assert_eq!(due.paths.keys().cloned().collect::<Vec<_>>(), vec![build]);
+        assert!(due.roots.is_empty());
+    }
+
+    #[test]
+    fn mass_change_waits_for_one_root_scan() {
+        let root = PathBuf::from("/home/me");
+        let t0 = Instant::now();
+        let mut pending = PendingChanges::default();
+        for i in 0..=MAX_EVENTS_PER_MINUTE {
+            pending.note_event(&root, false, t0);
+            pending.record_path(&root, root.join(format!("dir{}/f{i}", i % 50)), t0);
+        }
+        assert!(pending.dirs.is_empty());
+        assert!(pending.take_due(t0).roots.is_empty());
+
+        let first = t0 + DEBOUNCE;
+        assert_eq!(pending.take_due(first).roots, vec![root.clone()]);
+
+        // Another storm inside the backoff is deferred, not dropped.
+        pending.escalate(&root, first);
+        assert!(pending.take_due(first + DEBOUNCE).roots.is_empty());
+        assert_eq!(pending.take_due(first + RESCAN_BACKOFF).roots, vec![root]);
+    }
+
+    #[test]
+    fn archive_writes_do_not_count_as_activity() {
+        let root = PathBuf::from("/home/me");
+        let archive = root.join("Archive/White Space");
+        let roots = Arc::new(Mutex::new(vec![root.clone()]));
+        let mut pending = PendingChanges::default();
+        for i in 0..=RENAME_STORM_EVENTS {
+            let event = Event::new(EventKind::Modify(ModifyKind::Name(
+                notify::event::RenameMode::Any,
+            )))
+            .add_path(archive.join(format!("2024-01-01/f{i}")));
+            record_event(&roots, std::slice::from_ref(&archive), &mut pending, event);
+        }
+        assert!(pending.dirs.is_empty());
+        assert!(pending.take_due(Instant::now() + RESCAN_BACKOFF).roots.is_empty());
+    }
+}
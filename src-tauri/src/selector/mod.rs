@@ -1,19 +1,47 @@
+pub mod rules;
 pub mod scoring;
 
 use crate::db::Database;
-use crate::models::{ActionType, File};
+use crate::i18n::Message;
+use crate::models::{ActionType, BucketPrefilter, BucketStatsQuery, File, UserRule};
+use crate::ops::system_paths::is_system_path;
 use chrono::{DateTime, Duration, Utc};
-use scoring::{Candidate, FileScorer, ScoringContext};
+use rules::CompiledRule;
+use scoring::{
+    Candidate, FileScorer, ScoringConfig, ScoringContext, RECENT_OPEN_GRACE_DAYS,
+};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-#[derive(Debug, Clone)]
+/// Preference key holding the JSON-encoded [`SelectorConfig`].
+pub const SELECTOR_CONFIG_PREF: &str = "selector_config";
+/// Set to "false" to leave installers and disk images out of suggestions.
+pub const INSTALLERS_BUCKET_PREF: &str = "installers_bucket_enabled";
+/// Days a restored file stays off the candidate list; 0 suggests it again
+/// right away.
+pub const RESTORE_GRACE_DAYS_PREF: &str = "restore_grace_days";
+pub const DEFAULT_RESTORE_GRACE_DAYS: u32 = 30;
+pub const MAX_RESTORE_GRACE_DAYS: u32 = 365;
+/// Installer and disk image extensions, compared against lowercased names.
+pub const INSTALLER_EXTENSIONS: &[&str] = &["dmg", "pkg", "msi", "exe", "appimage", "deb"];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct BucketConfig {
     pub screenshots_max: usize,
     pub big_downloads_max: usize,
     pub old_desktop_max: usize,
     pub duplicates_max: usize,
+    pub installers_max: usize,
+    pub broken_symlinks_max: usize,
     pub daily_total_max: usize,
+    pub screenshot_min_age_days: u32,
+    pub big_download_min_mb: u64,
+    pub big_download_min_age_days: u32,
+    pub old_desktop_min_age_days: u32,
+    pub installer_min_age_days: u32,
+    /// Files above this size are skipped for duplicate detection.
+    pub duplicate_max_mb: u64,
 }
 
 impl Default for BucketConfig {
@@ -23,8 +51,77 @@ impl Default for BucketConfig {
             big_downloads_max: 30,
             old_desktop_max: 30,
             duplicates_max: 30,
+            installers_max: 30,
+            broken_symlinks_max: 30,
             daily_total_max: 30, // Mix cap per day
+            screenshot_min_age_days: 0,
+            big_download_min_mb: 100,
+            big_download_min_age_days: 30,
+            old_desktop_min_age_days: 14,
+            installer_min_age_days: 14,
+            duplicate_max_mb: 2 * 1024,
+        }
+    }
+}
+
+/// Everything the selector and scorer read from prefs.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SelectorConfig {
+    pub buckets: BucketConfig,
+    pub scoring: ScoringConfig,
+}
+
+impl SelectorConfig {
+    /// Stored config, or the defaults when nothing (or something unreadable)
+    /// is stored.
+    pub fn load(db: &Database) -> Self {
+        db.get_preference(SELECTOR_CONFIG_PREF)
+            .ok()
+            .flatten()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        let raw = serde_json::to_string(self)?;
+        db.set_preference(SELECTOR_CONFIG_PREF, &raw)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let b = &self.buckets;
+        for (name, value) in [
+            ("screenshots_max", b.screenshots_max),
+            ("big_downloads_max", b.big_downloads_max),
+            ("old_desktop_max", b.old_desktop_max),
+            ("duplicates_max", b.duplicates_max),
+            ("installers_max", b.installers_max),
+            ("broken_symlinks_max", b.broken_symlinks_max),
+            ("daily_total_max", b.daily_total_max),
+        ] {
+            if value == 0 || value > 1000 {
+                return Err(format!("{name} must be 1-1000"));
+            }
+        }
+        for (name, value) in [
+            ("screenshot_min_age_days", b.screenshot_min_age_days),
+            ("big_download_min_age_days", b.big_download_min_age_days),
+            ("old_desktop_min_age_days", b.old_desktop_min_age_days),
+            ("installer_min_age_days", b.installer_min_age_days),
+            ("scoring.stale_age_days", self.scoring.stale_age_days),
+        ] {
+            if value > 3650 {
+                return Err(format!("{name} must be 0-3650"));
+            }
         }
+        if self.scoring.max_age_days == 0 || self.scoring.max_age_days > 3650 {
+            return Err("scoring.max_age_days must be 1-3650".to_string());
+        }
+        if self.scoring.max_size_mb == 0 {
+            return Err("scoring.max_size_mb must be greater than 0".to_string());
+        }
+        Ok(())
     }
 }
 
@@ -34,11 +131,73 @@ pub struct FileBucket {
     pub big_downloads: Vec<File>,
     pub old_desktop: Vec<File>,
     pub duplicates: Vec<File>,
+    pub installers: Vec<File>,
+    pub broken_symlinks: Vec<File>,
+    /// Matches per user rule, in rule order: `(rule name, max per day, files)`.
+    pub custom: Vec<(String, usize, Vec<File>)>,
 }
 
 pub struct FileSelector {
     scorer: FileScorer,
     config: BucketConfig,
+    /// Roots to draw candidates from; `None` means the currently watched roots.
+    roots: Option<Vec<String>>,
+    rules: Vec<CompiledRule>,
+    protected: Vec<String>,
+    dismissed: HashSet<i64>,
+    /// Files restored within the grace window; the user wanted them back.
+    recently_restored: HashSet<i64>,
+    restore_grace_days: u32,
+    /// Files with other hard links; deleting one frees nothing.
+    hard_linked: HashSet<i64>,
+    installers_enabled: bool,
+    /// Candidates scored below this confidence are dropped.
+    min_confidence: f64,
+    /// Caps every bucket below its configured daily maximum.
+    max_per_bucket: Option<usize>,
+    include_staged: bool,
+    include_deleted: bool,
+}
+
+/// Stable key for a bucket's display name ("Big Downloads" -> "big_download").
+pub fn bucket_key(reason: &str) -> String {
+    let lower = reason.to_lowercase();
+    match lower.as_str() {
+        "screenshots" => "screenshot".to_string(),
+        "big downloads" => "big_download".to_string(),
+        "old desktop" => "old_desktop".to_string(),
+        "executable" | "executables" => "executable".to_string(),
+        "installers" => "installer".to_string(),
+        "broken symlinks" => "broken_symlink".to_string(),
+        "duplicates" => "duplicate".to_string(),
+        other => other.replace(' ', "_"),
+    }
+}
+
+/// Catalog message for a bucket's display name; user rules keep their own
+/// name as a parameter.
+pub fn bucket_message(reason: &str) -> Message {
+    let key = bucket_key(reason);
+    match key.as_str() {
+        "screenshot" | "big_download" | "old_desktop" | "installer" | "broken_symlink"
+        | "duplicate" => Message::new(format!("bucket.{key}")),
+        _ => Message::new("bucket.custom").with("name", reason),
+    }
+}
+
+/// Whether `path` is, or sits inside, one of the protected paths.
+pub fn is_protected(path: &Path, protected: &[String]) -> bool {
+    protected.iter().any(|p| path.starts_with(p))
+}
+
+/// The stored grace window for restored files, in days.
+pub fn restore_grace_days(db: &Database) -> u32 {
+    db.get_preference(RESTORE_GRACE_DAYS_PREF)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<u32>().ok())
+        .map(|days| days.min(MAX_RESTORE_GRACE_DAYS))
+        .unwrap_or(DEFAULT_RESTORE_GRACE_DAYS)
 }
 
 impl FileSelector {
@@ -46,7 +205,133 @@ impl FileSelector {
         Self {
             scorer: FileScorer::new(),
             config: BucketConfig::default(),
+            roots: None,
+            rules: Vec::new(),
+            protected: Vec::new(),
+            dismissed: HashSet::new(),
+            recently_restored: HashSet::new(),
+            restore_grace_days: 0,
+            hard_linked: HashSet::new(),
+            installers_enabled: true,
+            min_confidence: 0.0,
+            max_per_bucket: None,
+            include_staged: false,
+            include_deleted: false,
+        }
+    }
+
+    pub fn with_config(config: SelectorConfig) -> Self {
+        Self {
+            scorer: FileScorer::with_config(&config.scoring),
+            config: config.buckets,
+            roots: None,
+            rules: Vec::new(),
+            protected: Vec::new(),
+            dismissed: HashSet::new(),
+            recently_restored: HashSet::new(),
+            restore_grace_days: 0,
+            hard_linked: HashSet::new(),
+            installers_enabled: true,
+            min_confidence: 0.0,
+            max_per_bucket: None,
+            include_staged: false,
+            include_deleted: false,
+        }
+    }
+
+    /// Selector using the stored config and the enabled user rules.
+    pub fn from_db(db: &Database) -> Self {
+        let mut selector = Self::with_config(SelectorConfig::load(db));
+        match db.list_user_rules() {
+            Ok(rules) => selector.set_user_rules(rules),
+            Err(err) => tracing::warn!("Failed to load user rules: {err}"),
+        }
+        match db.list_protected_paths() {
+            Ok(paths) => selector.set_protected_paths(paths.into_iter().map(|p| p.path).collect()),
+            Err(err) => tracing::warn!("Failed to load protected paths: {err}"),
+        }
+        match db.active_dismissed_ids(&Utc::now()) {
+            Ok(ids) => selector.set_dismissed(ids),
+            Err(err) => tracing::warn!("Failed to load dismissed files: {err}"),
+        }
+        selector.restore_grace_days = restore_grace_days(db);
+        if selector.restore_grace_days > 0 {
+            let since = Utc::now() - Duration::days(selector.restore_grace_days as i64);
+            match db.restored_file_ids_since(&since) {
+                Ok(ids) => selector.set_recently_restored(ids),
+                Err(err) => tracing::warn!("Failed to load restored files: {err}"),
+            }
+        }
+        match db.hard_linked_file_ids() {
+            Ok(ids) => selector.set_hard_linked(ids),
+            Err(err) => tracing::warn!("Failed to load hard-linked files: {err}"),
         }
+        match db.get_preference(INSTALLERS_BUCKET_PREF) {
+            Ok(value) => selector.set_installers_enabled(value.as_deref() != Some("false")),
+            Err(err) => tracing::warn!("Failed to load installers pref: {err}"),
+        }
+        if selector.scorer.learns_from_feedback() {
+            match db.feedback_by_bucket() {
+                Ok(feedback) => selector.scorer.set_bucket_feedback(&feedback),
+                Err(err) => tracing::warn!("Failed to load feedback: {err}"),
+            }
+        }
+        selector
+    }
+
+    /// Files the user dismissed; they are left out until the dismissal ends.
+    pub fn set_dismissed(&mut self, file_ids: HashSet<i64>) {
+        self.dismissed = file_ids;
+    }
+
+    /// Restored files; they are left out until the grace window ends.
+    pub fn set_recently_restored(&mut self, file_ids: HashSet<i64>) {
+        self.recently_restored = file_ids;
+    }
+
+    /// Hard-linked files are never reported as duplicates of each other.
+    pub fn set_hard_linked(&mut self, file_ids: HashSet<i64>) {
+        self.hard_linked = file_ids;
+    }
+
+    /// Turns the installers bucket on or off.
+    pub fn set_installers_enabled(&mut self, enabled: bool) {
+        self.installers_enabled = enabled;
+    }
+
+    pub fn set_min_confidence(&mut self, min_confidence: f64) {
+        self.min_confidence = min_confidence;
+    }
+
+    pub fn set_max_per_bucket(&mut self, max: Option<usize>) {
+        self.max_per_bucket = max;
+    }
+
+    /// Staged (archived) files are left out unless asked for.
+    pub fn set_include_staged(&mut self, include: bool) {
+        self.include_staged = include;
+    }
+
+    /// Files marked deleted are left out unless asked for.
+    pub fn set_include_deleted(&mut self, include: bool) {
+        self.include_deleted = include;
+    }
+
+    pub fn set_protected_paths(&mut self, paths: Vec<String>) {
+        self.protected = paths;
+    }
+
+    /// Replaces the user rules. Disabled rules and invalid globs are skipped.
+    pub fn set_user_rules(&mut self, rules: Vec<UserRule>) {
+        self.rules = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| CompiledRule::compile(rule).ok())
+            .collect();
+    }
+
+    pub fn set_roots(&mut self, roots: Vec<String>) {
+        self.roots = Some(roots);
     }
 
     pub fn daily_candidates(
@@ -54,14 +339,26 @@ impl FileSelector {
         max_total: Option<usize>,
         db: &Database,
     ) -> Result<Vec<Candidate>, Box<dyn std::error::Error>> {
-        // Get all files from database
-        let all_files = self.get_all_files(db)?;
+        let roots = self.current_roots(db)?;
+        self.daily_candidates_under_roots(&roots, max_total, db)
+    }
+
+    /// Like [`daily_candidates`](Self::daily_candidates) but for an explicit
+    /// set of roots instead of the configured or watched ones.
+    pub fn daily_candidates_under_roots(
+        &self,
+        roots: &[String],
+        max_total: Option<usize>,
+        db: &Database,
+    ) -> Result<Vec<Candidate>, Box<dyn std::error::Error>> {
+        // Only files that could land in a bucket are loaded
+        let files = self.load_files(roots, db)?;
 
         // Create scoring context
-        let context = self.create_scoring_context(&all_files, db)?;
+        let context = self.create_scoring_context(roots, db)?;
 
         // Bucket files
-        let buckets = self.bucket_files(&all_files, &context);
+        let buckets = self.bucket_files(&files, &context);
 
         // Score and select candidates
         let candidates = self.select_candidates(&buckets, &context, max_total);
@@ -69,29 +366,92 @@ impl FileSelector {
         Ok(candidates)
     }
 
-    fn get_all_files(&self, db: &Database) -> Result<Vec<File>, Box<dyn std::error::Error>> {
-        db.get_all_active_files()
+    fn current_roots(&self, db: &Database) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        match &self.roots {
+            Some(roots) => Ok(roots.clone()),
+            None => Ok(db.list_online_watched_paths()?),
+        }
+    }
+
+    fn load_files(
+        &self,
+        roots: &[String],
+        db: &Database,
+    ) -> Result<Vec<File>, Box<dyn std::error::Error>> {
+        db.bucket_prefiltered_files(roots, &self.prefilter())
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
     }
 
+    /// The SQL side of every bucket check, loose enough never to drop a
+    /// file the exact checks in `bucket_files` would keep.
+    fn prefilter(&self) -> BucketPrefilter {
+        const MB: u64 = 1024 * 1024;
+        let installer_extensions = if self.installers_enabled {
+            INSTALLER_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        BucketPrefilter {
+            screenshots: true,
+            big_download_min_bytes: Some((self.config.big_download_min_mb * MB) as i64),
+            desktop: true,
+            installer_extensions,
+            duplicate_max_bytes: Some((self.config.duplicate_max_mb * MB) as i64),
+            symlinks: true,
+            rules: self
+                .rules
+                .iter()
+                .map(|rule| (rule.rule.min_size_bytes, rule.rule.mime_filter.clone()))
+                .collect(),
+            include_staged: self.include_staged,
+            include_deleted: self.include_deleted,
+        }
+    }
+
+    /// Protected, system, dismissed, cooling off, restored and recently opened
+    /// files still inform the scoring context (a protected original keeps its
+    /// copies flagged as duplicates) but never land in a bucket themselves.
+    fn is_suggestable(&self, file: &File) -> bool {
+        if file
+            .id
+            .is_some_and(|id| self.dismissed.contains(&id) || self.recently_restored.contains(&id))
+        {
+            return false;
+        }
+        // Still inside a cooloff it was given when staged.
+        if file.cooloff_until.is_some_and(|until| until > Utc::now()) {
+            return false;
+        }
+        // Its drive is unplugged; nothing can be done with it right now.
+        if file.is_offline {
+            return false;
+        }
+        if self.scorer.opened_recently(file) {
+            return false;
+        }
+        let path = Path::new(&file.path);
+        !is_protected(path, &self.protected) && !is_system_path(path)
+    }
+
+    /// Built from every file under `roots`, not just the bucketed ones, so
+    /// the queries run in SQL.
     fn create_scoring_context(
         &self,
-        files: &[File],
+        roots: &[String],
         db: &Database,
     ) -> Result<ScoringContext, Box<dyn std::error::Error>> {
         let mut context = ScoringContext::new();
 
         // Find duplicate files (same SHA1)
-        let duplicates = self.find_duplicates(files);
+        let duplicates = self.find_duplicates(db.shared_hash_files_under_roots(roots)?);
         context.add_duplicate_files(duplicates);
 
         // Find Git repositories
-        let git_repos = self.find_git_repos(files);
-        context.add_git_repos(git_repos);
+        context.add_git_repos(db.git_repo_paths_under_roots(roots)?);
 
-        // Find directories with recent burst activity
-        let burst_dirs = self.find_burst_directories(files);
-        context.add_burst_directories(burst_dirs);
+        // Find directories with 3+ files seen in the last 72 hours
+        let cutoff_time = Utc::now() - Duration::hours(72);
+        context.add_burst_directories(db.busy_dirs_under_roots(roots, cutoff_time, 3)?);
 
         Ok(context)
     }
@@ -101,8 +461,23 @@ impl FileSelector {
         let mut big_downloads = Vec::new();
         let mut old_desktop = Vec::new();
         let mut duplicates = Vec::new();
+        let mut installers = Vec::new();
+        let mut broken_symlinks = Vec::new();
+        let mut custom: Vec<Vec<File>> = vec![Vec::new(); self.rules.len()];
 
         for file in files {
+            if !self.is_suggestable(file) {
+                continue;
+            }
+
+            // A link is only worth suggesting once it points nowhere.
+            if file.is_symlink {
+                if Self::is_broken_symlink(file) {
+                    broken_symlinks.push(file.clone());
+                }
+                continue;
+            }
+
             // Screenshots bucket
             if self.is_screenshot(&file) {
                 screenshots.push(file.clone());
@@ -122,13 +497,44 @@ impl FileSelector {
             if self.is_duplicate(&file, context) {
                 duplicates.push(file.clone());
             }
+
+            // Installers bucket
+            if self.is_installer(&file) {
+                installers.push(file.clone());
+            }
+
+            // User rules
+            if !self.rules.is_empty() {
+                let age_days = self.scorer.calculate_age_days(file);
+                for (rule, matches) in self.rules.iter().zip(custom.iter_mut()) {
+                    if rule.matches(file, age_days) {
+                        matches.push(file.clone());
+                    }
+                }
+            }
         }
 
+        let custom = self
+            .rules
+            .iter()
+            .zip(custom)
+            .map(|(rule, files)| {
+                (
+                    rule.rule.name.clone(),
+                    rule.rule.max_per_day.max(0) as usize,
+                    files,
+                )
+            })
+            .collect();
+
         FileBucket {
             screenshots,
             big_downloads,
             old_desktop,
             duplicates,
+            installers,
+            broken_symlinks,
+            custom,
         }
     }
 
@@ -151,50 +557,89 @@ impl FileSelector {
             .unwrap_or(false)
     }
 
-    fn is_screenshot(&self, file: &File) -> bool {
-        // Name contains "screenshot" OR parent has a segment named "screenshots"
+    /// Name contains "screenshot" (or macOS's older "Screen Shot") OR the
+    /// parent has a segment named "screenshots". Ignores age.
+    pub fn looks_like_screenshot(file: &File) -> bool {
         Self::filename_contains(&file.path, "screenshot")
+            || Self::filename_contains(&file.path, "screen shot")
             || Self::path_has_segment(&file.parent_dir, "screenshots")
     }
 
+    fn is_screenshot(&self, file: &File) -> bool {
+        Self::looks_like_screenshot(file)
+            && self.scorer.calculate_age_days(file) >= self.config.screenshot_min_age_days as f64
+    }
+
     fn is_big_download(&self, file: &File) -> bool {
         let in_downloads = Self::path_has_segment(&file.parent_dir, "downloads");
         let size_mb = file.size_bytes as f64 / (1024.0 * 1024.0);
         let age_days = self.scorer.calculate_age_days(file);
 
-        // Under Downloads, size > 100MB, unopened OR age > 30d
-        in_downloads && size_mb > 100.0 && (file.last_opened_at.is_none() || age_days > 30.0)
+        // Under Downloads, size > 100MB, unopened OR age > 30d (by default)
+        in_downloads
+            && size_mb > self.config.big_download_min_mb as f64
+            && (file.last_opened_at.is_none()
+                || age_days > self.config.big_download_min_age_days as f64)
     }
 
     fn is_old_desktop(&self, file: &File) -> bool {
         let in_desktop = Self::path_has_segment(&file.parent_dir, "desktop");
         let age_days = self.scorer.calculate_age_days(file);
 
-        // Under Desktop, age > 14d
-        in_desktop && age_days > 14.0
+        // Under Desktop, age > 14d (by default)
+        in_desktop && age_days > self.config.old_desktop_min_age_days as f64
+    }
+
+    /// Whether the file name ends in a known installer or disk image
+    /// extension, whatever folder it sits in.
+    pub fn has_installer_extension(path: &str) -> bool {
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        INSTALLER_EXTENSIONS
+            .iter()
+            .any(|ext| name.ends_with(&format!(".{ext}")))
+    }
+
+    fn is_installer(&self, file: &File) -> bool {
+        if !self.installers_enabled {
+            return false;
+        }
+        let in_downloads = Self::path_has_segment(&file.parent_dir, "downloads");
+        let age_days = self.scorer.calculate_age_days(file);
+
+        // Under Downloads, installer extension, age > 14d (by default)
+        in_downloads
+            && Self::has_installer_extension(&file.path)
+            && age_days > self.config.installer_min_age_days as f64
+    }
+
+    /// Recorded symlink whose target no longer exists.
+    pub fn is_broken_symlink(file: &File) -> bool {
+        let path = Path::new(&file.path);
+        file.is_symlink && path.is_symlink() && !path.exists()
     }
 
     fn is_duplicate(&self, file: &File, context: &ScoringContext) -> bool {
-        // Skip files > 2GB for duplicate detection (lazy)
-        if file.size_bytes as u64 > 2 * 1024 * 1024 * 1024 {
+        // Skip files > 2GB (by default) for duplicate detection (lazy)
+        if file.size_bytes as u64 > self.config.duplicate_max_mb * 1024 * 1024 {
             return false;
         }
 
         context.duplicate_files.contains(&file.id.unwrap_or(0))
     }
 
-    fn find_duplicates(&self, files: &[File]) -> Vec<i64> {
+    /// `files` are `(id, sha1)` pairs already known to share a hash; hard
+    /// links drop out, which can leave a group of one.
+    fn find_duplicates(&self, files: Vec<(i64, String)>) -> Vec<i64> {
         let mut sha1_groups: HashMap<String, Vec<i64>> = HashMap::new();
 
-        for file in files {
-            if let Some(sha1) = &file.sha1 {
-                if !sha1.is_empty() {
-                    sha1_groups
-                        .entry(sha1.clone())
-                        .or_insert_with(Vec::new)
-                        .push(file.id.unwrap_or(0));
-                }
+        for (id, sha1) in files {
+            if self.hard_linked.contains(&id) {
+                continue;
             }
+            sha1_groups.entry(sha1).or_default().push(id);
         }
 
         // Return file IDs that have duplicates (more than 1 file with same SHA1)
@@ -206,41 +651,6 @@ impl FileSelector {
             .collect()
     }
 
-    fn find_git_repos(&self, files: &[File]) -> Vec<String> {
-        let mut git_repos = HashSet::new();
-
-        for file in files {
-            let path = &file.path;
-            if let Some(git_dir_pos) = path.find("/.git/") {
-                let repo_path = &path[..git_dir_pos];
-                git_repos.insert(repo_path.to_string());
-            }
-        }
-
-        git_repos.into_iter().collect()
-    }
-
-    fn find_burst_directories(&self, files: &[File]) -> Vec<String> {
-        let mut dir_activity: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
-        let cutoff_time = Utc::now() - Duration::hours(72);
-
-        for file in files {
-            if file.last_seen_at > cutoff_time {
-                dir_activity
-                    .entry(file.parent_dir.clone())
-                    .or_insert_with(Vec::new)
-                    .push(file.last_seen_at);
-            }
-        }
-
-        // Find directories with 3+ recent modifications
-        dir_activity
-            .into_iter()
-            .filter(|(_, timestamps)| timestamps.len() >= 3)
-            .map(|(dir, _)| dir)
-            .collect()
-    }
-
     fn select_candidates(
         &self,
         buckets: &FileBucket,
@@ -274,6 +684,21 @@ impl FileSelector {
             self.config.duplicates_max,
             "Duplicates",
         ));
+        candidates.extend(self.select_from_bucket(
+            &buckets.installers,
+            context,
+            self.config.installers_max,
+            "Installers",
+        ));
+        candidates.extend(self.select_from_bucket(
+            &buckets.broken_symlinks,
+            context,
+            self.config.broken_symlinks_max,
+            "Broken Symlinks",
+        ));
+        for (name, max_per_day, files) in &buckets.custom {
+            candidates.extend(self.select_from_bucket(files, context, *max_per_day, name));
+        }
 
         // Sort by score (highest first) and limit to max_total
         candidates.sort_by(|a, b| {
@@ -294,13 +719,17 @@ impl FileSelector {
         max_count: usize,
         reason: &str,
     ) -> Vec<Candidate> {
+        let weight = self.scorer.bucket_weight(&bucket_key(reason));
         let mut scored_candidates: Vec<(Candidate, DateTime<Utc>)> = files
             .iter()
             .map(|file| {
                 let factors = self.scorer.extract_score_factors(file, context);
-                let score = self.scorer.calculate_score(file, &factors);
+                let score = (self.scorer.calculate_score(file, &factors) * weight).min(1.0);
                 let confidence = self.scorer.calculate_confidence(file, &factors);
                 let preview_hint = self.scorer.generate_preview_hint(file, &factors);
+                let hint_messages = self.scorer.preview_hint_messages(file, &factors);
+                let explanation = self.scorer.explain(file, &factors);
+                let explanation_messages = self.scorer.explanation_messages(file, &factors);
 
                 (
                     Candidate {
@@ -309,14 +738,21 @@ impl FileSelector {
                         parent_dir: file.parent_dir.clone(),
                         size_bytes: file.size_bytes as u64,
                         reason: reason.to_string(),
+                        reason_message: bucket_message(reason),
                         score,
                         confidence,
                         preview_hint,
+                        hint_messages,
                         age_days: factors.age_days,
+                        is_cloud_placeholder: file.is_cloud_placeholder,
+                        factors,
+                        explanation,
+                        explanation_messages,
                     },
                     file.last_seen_at,
                 )
             })
+            .filter(|(candidate, _)| candidate.confidence >= self.min_confidence)
             .collect();
 
         scored_candidates.sort_by(|a, b| {
@@ -325,7 +761,8 @@ impl FileSelector {
                 .unwrap_or(std::cmp::Ordering::Equal)
                 .then_with(|| b.1.cmp(&a.1))
         });
-        scored_candidates.truncate(max_count);
+        let cap = self.max_per_bucket.map_or(max_count, |cap| cap.min(max_count));
+        scored_candidates.truncate(cap);
 
         scored_candidates
             .into_iter()
@@ -333,19 +770,53 @@ impl FileSelector {
             .collect()
     }
 
+    /// Count and bytes of every built-in bucket, keyed like [`bucket_key`],
+    /// aggregated in SQL instead of loading and scoring each file. Counts
+    /// ignore `min_confidence` and the daily caps, and user rules are not
+    /// counted since their globs only run in Rust.
     pub fn get_bucket_stats(
         &self,
         db: &Database,
-    ) -> Result<HashMap<String, usize>, Box<dyn std::error::Error>> {
-        let all_files = self.get_all_files(db)?;
-        let context = self.create_scoring_context(&all_files, db)?;
-        let buckets = self.bucket_files(&all_files, &context);
-
-        let mut stats = HashMap::new();
-        stats.insert("screenshots".to_string(), buckets.screenshots.len());
-        stats.insert("big_downloads".to_string(), buckets.big_downloads.len());
-        stats.insert("old_desktop".to_string(), buckets.old_desktop.len());
-        stats.insert("duplicates".to_string(), buckets.duplicates.len());
+    ) -> Result<HashMap<String, (usize, u64)>, Box<dyn std::error::Error>> {
+        const MB: u64 = 1024 * 1024;
+        let roots = self.current_roots(db)?;
+        let installer_extensions = if self.installers_enabled {
+            INSTALLER_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        let query = BucketStatsQuery {
+            screenshot_min_age_days: self.config.screenshot_min_age_days,
+            big_download_min_bytes: (self.config.big_download_min_mb * MB) as i64,
+            big_download_min_age_days: self.config.big_download_min_age_days,
+            old_desktop_min_age_days: self.config.old_desktop_min_age_days,
+            installer_extensions,
+            installer_min_age_days: self.config.installer_min_age_days,
+            duplicate_max_bytes: (self.config.duplicate_max_mb * MB) as i64,
+            protected: self.protected.clone(),
+            recent_open_grace_days: RECENT_OPEN_GRACE_DAYS,
+            restore_grace_days: self.restore_grace_days as i64,
+        };
+
+        let mut keys = vec!["screenshot", "big_download", "old_desktop", "duplicate"];
+        if self.installers_enabled {
+            keys.push("installer");
+        }
+        keys.push("broken_symlink");
+        let mut stats: HashMap<String, (usize, u64)> =
+            keys.into_iter().map(|key| (key.to_string(), (0, 0))).collect();
+        for (key, count, bytes) in db.bucket_stats(&roots, &query)? {
+            stats.insert(key, (count, bytes));
+        }
+
+        let broken = stats.entry("broken_symlink".to_string()).or_default();
+        for (path, bytes) in db.suggestable_symlinks(&roots, &query)? {
+            let path = Path::new(&path);
+            if path.is_symlink() && !path.exists() {
+                broken.0 += 1;
+                broken.1 += bytes;
+            }
+        }
 
         Ok(stats)
     }
@@ -0,0 +1,87 @@
+use crate::models::{File, UserRule};
+use anyhow::Result;
+use globset::{Glob, GlobMatcher};
+use std::path::{Path, PathBuf};
+
+/// A user rule with its glob compiled once per selection run.
+pub struct CompiledRule {
+    pub rule: UserRule,
+    matcher: Option<GlobMatcher>,
+    match_full_path: bool,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: UserRule) -> Result<Self> {
+        let (matcher, match_full_path) = match rule.path_glob.as_deref() {
+            Some(pattern) if !pattern.trim().is_empty() => {
+                let (matcher, full) = compile_rule_glob(pattern)?;
+                (Some(matcher), full)
+            }
+            _ => (None, false),
+        };
+        Ok(Self {
+            rule,
+            matcher,
+            match_full_path,
+        })
+    }
+
+    pub fn matches(&self, file: &File, age_days: f64) -> bool {
+        if let Some(min_size) = self.rule.min_size_bytes {
+            if file.size_bytes < min_size {
+                return false;
+            }
+        }
+        if let Some(min_age) = self.rule.min_age_days {
+            if age_days < min_age as f64 {
+                return false;
+            }
+        }
+        if let Some(filter) = self.rule.mime_filter.as_deref() {
+            if !mime_matches(filter, file.mime.as_deref()) {
+                return false;
+            }
+        }
+        match &self.matcher {
+            Some(matcher) if self.match_full_path => matcher.is_match(&file.path),
+            Some(matcher) => Path::new(&file.path)
+                .file_name()
+                .map(|name| matcher.is_match(name))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+/// Compiles a rule glob. Like exclusions, patterns without a separator
+/// (`*.dmg`) match file names anywhere; patterns with one match the full path,
+/// with a leading `~` expanded to the home directory.
+pub fn compile_rule_glob(pattern: &str) -> Result<(GlobMatcher, bool)> {
+    let pattern = pattern.trim();
+    if !pattern.contains('/') && !pattern.contains('\\') {
+        return Ok((Glob::new(pattern)?.compile_matcher(), false));
+    }
+
+    let expanded = match pattern.strip_prefix('~') {
+        Some(rest) => {
+            let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+            format!("{}{}", home.to_string_lossy().trim_end_matches('/'), rest)
+        }
+        None => pattern.to_string(),
+    };
+    Ok((Glob::new(&expanded)?.compile_matcher(), true))
+}
+
+/// `video/*` and `video/` match by prefix, anything else must match exactly.
+fn mime_matches(filter: &str, mime: Option<&str>) -> bool {
+    let Some(mime) = mime else {
+        return false;
+    };
+    let filter = filter.trim().to_lowercase();
+    let mime = mime.to_lowercase();
+    match filter.strip_suffix('*') {
+        Some(prefix) => mime.starts_with(prefix),
+        None if filter.ends_with('/') => mime.starts_with(&filter),
+        None => mime == filter,
+    }
+}
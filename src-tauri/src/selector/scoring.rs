@@ -1,8 +1,25 @@
-use crate::models::{ActionType, File};
+use crate::i18n::Message;
+use crate::models::{ActionType, BucketFeedback, File};
+use crate::ops::space::SpaceManager;
 use chrono::{DateTime, Duration, Utc};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone)]
+/// Files opened from the app are left out of suggestions for this long.
+pub const RECENT_OPEN_GRACE_DAYS: i64 = 7;
+
+/// Verdicts a bucket needs before its weight moves halfway to an extreme.
+const FEEDBACK_PRIOR: f64 = 10.0;
+
+/// Score multiplier learned from a bucket's feedback: 1.0 with none or a
+/// balanced record, towards 0.5 when its files keep being kept and 1.5 when
+/// they keep being deleted. "Wrong suggestion" counts as two keeps.
+pub fn feedback_weight(feedback: &BucketFeedback) -> f64 {
+    let accepted = feedback.deleted as f64;
+    let rejected = feedback.kept as f64 + 2.0 * feedback.wrong_suggestion as f64;
+    1.0 + 0.5 * (accepted - rejected) / (accepted + rejected + FEEDBACK_PRIOR)
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct ScoreFactors {
     pub size_bytes: u64,
     pub age_days: f64,
@@ -13,6 +30,14 @@ pub struct ScoreFactors {
     pub recent_sibling_burst: bool,
 }
 
+/// Why a file is suggested, as catalog messages: what counts for it, then
+/// what ranked it lower.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct Explanation {
+    pub reasons: Vec<Message>,
+    pub penalties: Vec<Message>,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct Candidate {
     pub file_id: i64,
@@ -20,25 +45,93 @@ pub struct Candidate {
     pub parent_dir: String,
     pub size_bytes: u64,
     pub reason: String,
+    /// `reason` as a catalog message.
+    pub reason_message: Message,
     pub score: f64,
     pub confidence: f64,
     pub preview_hint: String,
+    pub hint_messages: Vec<Message>,
     pub age_days: f64,
+    /// Cloud-only file; removing it frees no local space.
+    pub is_cloud_placeholder: bool,
+    /// What the score was built from, for "why is this suggested?".
+    pub factors: ScoreFactors,
+    /// The factors in a sentence, e.g. "Not used in 5 months, 1.2 GB".
+    pub explanation: String,
+    pub explanation_messages: Explanation,
+}
+
+/// Tunable scoring thresholds, persisted as part of the selector config.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// Size at which the size component saturates.
+    pub max_size_mb: u64,
+    /// Age at which the age component saturates.
+    pub max_age_days: u32,
+    /// Files above this size are flagged "large" and gain confidence.
+    pub large_file_mb: u64,
+    /// Unopened files older than this are flagged "old" and gain confidence.
+    pub stale_age_days: u32,
+    /// Weight each bucket's scores by what the user did with past picks.
+    pub learn_from_feedback: bool,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 2 * 1024, // 2GB
+            max_age_days: 365,     // 1 year
+            large_file_mb: 100,
+            stale_age_days: 30,
+            learn_from_feedback: true,
+        }
+    }
 }
 
 pub struct FileScorer {
     max_size_bytes: u64,
     max_age_days: f64,
+    large_file_bytes: u64,
+    stale_age_days: f64,
+    learn_from_feedback: bool,
+    bucket_weights: HashMap<String, f64>,
 }
 
 impl FileScorer {
     pub fn new() -> Self {
+        Self::with_config(&ScoringConfig::default())
+    }
+
+    pub fn with_config(config: &ScoringConfig) -> Self {
         Self {
-            max_size_bytes: 2 * 1024 * 1024 * 1024, // 2GB
-            max_age_days: 365.0,                    // 1 year
+            max_size_bytes: config.max_size_mb * 1024 * 1024,
+            max_age_days: config.max_age_days as f64,
+            large_file_bytes: config.large_file_mb * 1024 * 1024,
+            stale_age_days: config.stale_age_days as f64,
+            learn_from_feedback: config.learn_from_feedback,
+            bucket_weights: HashMap::new(),
         }
     }
 
+    pub fn learns_from_feedback(&self) -> bool {
+        self.learn_from_feedback
+    }
+
+    /// Replaces the per-bucket weights with ones learned from `feedback`.
+    pub fn set_bucket_feedback(&mut self, feedback: &HashMap<String, BucketFeedback>) {
+        self.bucket_weights = feedback
+            .iter()
+            .map(|(bucket, totals)| (bucket.clone(), feedback_weight(totals)))
+            .collect();
+    }
+
+    /// Multiplier applied to scores in the bucket with key `bucket`; 1.0
+    /// without feedback.
+    pub fn bucket_weight(&self, bucket: &str) -> f64 {
+        self.bucket_weights.get(bucket).copied().unwrap_or(1.0)
+    }
+
     pub fn calculate_score(&self, file: &File, factors: &ScoreFactors) -> f64 {
         // Normalize size (0-1 scale, log scale for better distribution)
         let norm_size = self.normalize_size(factors.size_bytes);
@@ -103,12 +196,11 @@ impl FileScorer {
             confidence += 0.2;
         }
 
-        if factors.is_unopened && factors.age_days > 30.0 {
+        if factors.is_unopened && factors.age_days > self.stale_age_days {
             confidence += 0.15;
         }
 
-        if factors.size_bytes > 100 * 1024 * 1024 {
-            // 100MB
+        if factors.size_bytes > self.large_file_bytes {
             confidence += 0.1;
         }
 
@@ -130,41 +222,118 @@ impl FileScorer {
     }
 
     pub fn generate_preview_hint(&self, file: &File, factors: &ScoreFactors) -> String {
+        self.preview_hint_messages(file, factors)
+            .iter()
+            .map(Message::render)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The preview hint as catalog messages; never empty.
+    pub fn preview_hint_messages(&self, file: &File, factors: &ScoreFactors) -> Vec<Message> {
         let mut hints = Vec::new();
 
+        if file.is_cloud_placeholder {
+            hints.push(Message::new("hint.cloud_only"));
+        }
+
+        if let Some(target) = file.symlink_target.as_deref().filter(|_| file.is_symlink) {
+            hints.push(Message::new("hint.link_to").with("target", target));
+        }
+
         if factors.is_duplicate {
-            hints.push("duplicate".to_string());
+            hints.push(Message::new("hint.duplicate"));
         }
 
         if factors.is_unopened {
-            hints.push("unopened".to_string());
+            hints.push(Message::new("hint.unopened"));
         }
 
-        if factors.size_bytes > 100 * 1024 * 1024 {
-            hints.push("large".to_string());
+        if factors.size_bytes > self.large_file_bytes {
+            hints.push(Message::new("hint.large"));
         }
 
-        if factors.age_days > 30.0 {
-            hints.push("old".to_string());
+        if factors.age_days > self.stale_age_days {
+            hints.push(Message::new("hint.old"));
         }
 
         if factors.in_git_repo {
-            hints.push("git-repo".to_string());
+            hints.push(Message::new("hint.git_repo"));
         }
 
         if factors.has_keyword_flag {
-            hints.push("flagged".to_string());
+            hints.push(Message::new("hint.flagged"));
         }
 
         if factors.recent_sibling_burst {
-            hints.push("recent-activity".to_string());
+            hints.push(Message::new("hint.recent_activity"));
         }
 
         if hints.is_empty() {
-            "candidate".to_string()
+            hints.push(Message::new("hint.candidate"));
+        }
+        hints
+    }
+
+    /// Spells out the factors behind a suggestion: what counts for it, then
+    /// what held it back.
+    pub fn explain(&self, file: &File, factors: &ScoreFactors) -> String {
+        let parts = self.explanation_messages(file, factors);
+        let reasons: Vec<String> = parts.reasons.iter().map(Message::render).collect();
+        let mut explanation = if reasons.is_empty() {
+            Message::new("explain.matches_bucket").render()
         } else {
-            hints.join(", ")
+            let mut text = reasons.join(", ");
+            text[..1].make_ascii_uppercase();
+            text
+        };
+        if !parts.penalties.is_empty() {
+            let penalties: Vec<String> = parts.penalties.iter().map(Message::render).collect();
+            explanation.push_str("; ranked lower because ");
+            explanation.push_str(&penalties.join(" and "));
         }
+        explanation
+    }
+
+    /// The parts of [`explain`](Self::explain) as catalog messages, so the UI
+    /// can join them in its own language.
+    pub fn explanation_messages(&self, file: &File, factors: &ScoreFactors) -> Explanation {
+        let mut reasons = Vec::new();
+        if factors.is_unopened {
+            reasons.push(Message::new("explain.never_opened"));
+        }
+        if factors.age_days >= 60.0 {
+            let months = (factors.age_days / 30.0) as u64;
+            reasons.push(Message::new("explain.unused_months").with("count", months));
+        } else if factors.age_days >= 1.0 {
+            let days = factors.age_days as u64;
+            reasons.push(Message::new("explain.unused_days").with("count", days));
+        }
+        if file.is_cloud_placeholder {
+            reasons.push(Message::new("explain.cloud_only"));
+        } else if factors.size_bytes > 0 {
+            let size = SpaceManager::new().format_bytes(factors.size_bytes);
+            if factors.size_bytes > self.large_file_bytes {
+                reasons.push(Message::new("explain.large").with("size", size));
+            } else {
+                reasons.push(Message::new("explain.size").with("size", size));
+            }
+        }
+        if factors.is_duplicate {
+            reasons.push(Message::new("explain.duplicate"));
+        }
+
+        let mut penalties = Vec::new();
+        if factors.in_git_repo {
+            penalties.push(Message::new("explain.penalty.git_repo"));
+        }
+        if factors.recent_sibling_burst {
+            penalties.push(Message::new("explain.penalty.recent_activity"));
+        }
+        if factors.has_keyword_flag {
+            penalties.push(Message::new("explain.penalty.flagged"));
+        }
+        Explanation { reasons, penalties }
     }
 
     pub fn extract_score_factors(&self, file: &File, context: &ScoringContext) -> ScoreFactors {
@@ -176,7 +345,12 @@ impl FileScorer {
         let recent_sibling_burst = context.burst_directories.contains(&file.parent_dir);
 
         ScoreFactors {
-            size_bytes: file.size_bytes as u64,
+            // A placeholder's bytes are not on this disk, so size earns it nothing.
+            size_bytes: if file.is_cloud_placeholder {
+                0
+            } else {
+                file.size_bytes as u64
+            },
             age_days,
             is_duplicate,
             is_unopened,
@@ -186,10 +360,13 @@ impl FileScorer {
         }
     }
 
+    /// Days since the file was last used. An open from the app counts the
+    /// same as a filesystem access, whichever is later.
     pub fn calculate_age_days(&self, file: &File) -> f64 {
         let now = Utc::now();
         let reference = file
-            .accessed_at
+            .last_opened_at
+            .max(file.accessed_at)
             .or(file.modified_at)
             .unwrap_or(file.last_seen_at);
         let file_time = reference;
@@ -197,6 +374,12 @@ impl FileScorer {
         duration.num_days() as f64
     }
 
+    /// True when the file was opened from the app within the grace window.
+    pub fn opened_recently(&self, file: &File) -> bool {
+        file.last_opened_at
+            .is_some_and(|at| Utc::now() - at < Duration::days(RECENT_OPEN_GRACE_DAYS))
+    }
+
     fn has_keyword_flag(&self, path: &str) -> bool {
         let keywords = ["current", "project", "active", "wip", "final"];
         let path_lower = path.to_lowercase();
@@ -251,37 +434,3 @@ impl Default for ScoringContext {
         Self::new()
     }
 }
-
-// Edge case testing utilities
-#[cfg(test)]
-mod test_utils {
-    use super::*;
-    use chrono::Utc;
-
-    pub fn create_test_file(id: i64, path: String, size_bytes: i64, age_days: i64) -> File {
-        let now = Utc::now();
-        let file_time = now - Duration::days(age_days);
-
-        File {
-            id: Some(id),
-            path,
-            parent_dir: "/test".to_string(),
-            mime: Some("text/plain".to_string()),
-            size_bytes,
-            created_at: file_time,
-            last_opened_at: None,
-            sha1: Some("test_hash".to_string()),
-            first_seen_at: file_time,
-            last_seen_at: file_time,
-            is_deleted: false,
-        }
-    }
-
-    pub fn create_test_context() -> ScoringContext {
-        let mut context = ScoringContext::new();
-        context.add_duplicate_files(vec![1, 2, 3]);
-        context.add_git_repos(vec!["/test/git-repo".to_string()]);
-        context.add_burst_directories(vec!["/test/burst-dir".to_string()]);
-        context
-    }
-}
@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use image::imageops::FilterType;
+use image::ImageFormat;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub(crate) const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "ico",
+];
+pub(crate) const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "m4v", "avi", "mkv", "webm"];
+
+/// On-disk thumbnail cache. Entries live under `<cache>/<file_id>/<mtime>_<px>.png`
+/// so a changed file never hits a stale entry and invalidation is a single
+/// directory lookup per file.
+pub struct ThumbnailCache {
+    root: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new() -> Self {
+        let root = dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("white-space")
+            .join("thumbnails");
+        Self { root }
+    }
+
+    /// Returns a `data:image/png;base64,...` URL for the file, generating and
+    /// caching the thumbnail on first request.
+    pub fn get_or_create(&self, file_id: i64, path: &Path, max_px: u32) -> Result<String> {
+        let mtime = file_mtime(path)?;
+        let entry = self.entry_path(file_id, mtime, max_px);
+
+        if !entry.exists() {
+            self.invalidate_if_stale(file_id, mtime);
+            let thumb = render_thumbnail(path, max_px)?;
+            if let Some(parent) = entry.parent() {
+                fs::create_dir_all(parent).context("create thumbnail cache dir")?;
+            }
+            thumb
+                .save_with_format(&entry, ImageFormat::Png)
+                .context("write thumbnail")?;
+        }
+
+        let bytes = fs::read(&entry).context("read cached thumbnail")?;
+        Ok(format!("data:image/png;base64,{}", BASE64.encode(bytes)))
+    }
+
+    /// Drops cached thumbnails for `file_id` that were rendered from a
+    /// different modification time.
+    pub fn invalidate_if_stale(&self, file_id: i64, mtime: i64) {
+        let dir = self.root.join(file_id.to_string());
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+        let prefix = format!("{}_", mtime);
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fn entry_path(&self, file_id: i64, mtime: i64, max_px: u32) -> PathBuf {
+        self.root
+            .join(file_id.to_string())
+            .join(format!("{}_{}.png", mtime, max_px))
+    }
+}
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Called by the scanner after upserting a file so edits are reflected the
+/// next time the UI asks for a preview.
+pub fn invalidate_if_stale(file_id: i64, modified_at: Option<DateTime<Utc>>) {
+    if let Some(modified) = modified_at {
+        ThumbnailCache::new().invalidate_if_stale(file_id, modified.timestamp());
+    }
+}
+
+fn file_mtime(path: &Path) -> Result<i64> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .context("read file mtime")?;
+    Ok(DateTime::<Utc>::from(modified).timestamp())
+}
+
+pub(crate) fn extension_of(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn render_thumbnail(path: &Path, max_px: u32) -> Result<image::DynamicImage> {
+    let ext = extension_of(path);
+    let source = if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        image::open(path).context("decode image")?
+    } else if ext == "pdf" {
+        render_with_tool(path, ToolKind::Pdf)?
+    } else if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        render_with_tool(path, ToolKind::Video)?
+    } else {
+        return Err(anyhow!("unsupported file type for thumbnail: .{ext}"));
+    };
+
+    Ok(source.resize(max_px, max_px, FilterType::Triangle))
+}
+
+enum ToolKind {
+    Pdf,
+    Video,
+}
+
+/// Renders the first page / first frame through an external tool when one is
+/// installed (`pdftoppm` for PDFs, `ffmpeg` for video).
+fn render_with_tool(path: &Path, kind: ToolKind) -> Result<image::DynamicImage> {
+    let scratch = tempfile::tempdir().context("create scratch dir")?;
+    let output = scratch.path().join("frame.png");
+
+    let status = match kind {
+        ToolKind::Pdf => Command::new("pdftoppm")
+            .args(["-png", "-singlefile", "-f", "1", "-l", "1"])
+            .arg(path)
+            .arg(scratch.path().join("frame"))
+            .status(),
+        ToolKind::Video => Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y", "-i"])
+            .arg(path)
+            .args(["-frames:v", "1"])
+            .arg(&output)
+            .status(),
+    }
+    .context("thumbnail tool not available")?;
+
+    if !status.success() || !output.exists() {
+        return Err(anyhow!("thumbnail tool failed for {}", path.display()));
+    }
+
+    image::open(&output).context("decode rendered frame")
+}
@@ -0,0 +1,191 @@
+use crate::db::{Database, DbPool};
+use crate::gauge::{GaugeManager, GaugeState};
+use crate::i18n::Message;
+use crate::notifications;
+use crate::selector::FileSelector;
+use chrono::{DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, TimeZone, Utc, Weekday};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::time::Duration as StdDuration;
+use tauri::{AppHandle, Emitter};
+
+pub const TIDY_REMINDER_EVENT: &str = "tidy://reminder";
+
+/// UTC instant of the last reminder, so restarts and clock changes never
+/// fire the same slot twice.
+const LAST_REMINDER_PREF: &str = "tidy_last_reminder_at";
+/// A slot missed while the app was closed still fires if it is this recent.
+const MISSED_GRACE_HOURS: i64 = 12;
+/// Upper bound on one sleep so timezone, DST and pref changes are noticed.
+const MAX_SLEEP: StdDuration = StdDuration::from_secs(15 * 60);
+
+static SCHEDULER_STARTED: OnceCell<()> = OnceCell::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TidyReminderPayload {
+    pub scheduled_for: DateTime<Utc>,
+    pub candidate_count: usize,
+    pub candidate_bytes: u64,
+    pub summary: String,
+    pub summary_message: Message,
+    pub gauge: GaugeState,
+}
+
+/// Wakes at the configured local tidy day and hour. Every wake re-reads the
+/// prefs and the local timezone, so edits and DST shifts apply right away.
+pub fn start_tidy_scheduler<R: tauri::Runtime>(app: AppHandle<R>, pool: DbPool) {
+    if SCHEDULER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || loop {
+        let now = Local::now();
+        let wait = match tick(&app, &pool, &now) {
+            Ok(next) => (next - now).to_std().unwrap_or(StdDuration::ZERO),
+            Err(err) => {
+                tracing::error!("tidy reminder failed: {err}");
+                MAX_SLEEP
+            }
+        };
+        std::thread::sleep(wait.clamp(StdDuration::from_secs(1), MAX_SLEEP));
+    });
+}
+
+/// Fires the reminder if its latest slot has not been handled yet and
+/// returns the next slot.
+fn tick<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    pool: &DbPool,
+    now: &DateTime<Local>,
+) -> anyhow::Result<DateTime<Local>> {
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("db pool: {e}"))?;
+    let db = Database::new(conn);
+    let (day, hour) = schedule_prefs(&db)?;
+
+    let slot = previous_tidy_at(now, day, hour);
+    let last_fired = db
+        .get_preference(LAST_REMINDER_PREF)?
+        .and_then(|raw| DateTime::parse_from_rfc3339(&raw).ok())
+        .map(|at| at.with_timezone(&Utc));
+    let slot_utc = slot.with_timezone(&Utc);
+    let missed_recently = *now - slot <= Duration::hours(MISSED_GRACE_HOURS);
+    if missed_recently && last_fired.is_none_or(|at| at < slot_utc) {
+        let payload = build_reminder(&db, slot_utc, day, hour)?;
+        db.set_preference(LAST_REMINDER_PREF, &Utc::now().to_rfc3339())?;
+        notifications::notify_tidy_reminder(app, &db, &payload);
+        let _ = app.emit(TIDY_REMINDER_EVENT, payload);
+    }
+
+    Ok(next_tidy_at(now, day, hour))
+}
+
+fn schedule_prefs(db: &Database) -> anyhow::Result<(Weekday, u32)> {
+    let day = db
+        .get_preference("tidy_day")?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(Weekday::Fri);
+    let hour = db
+        .get_preference("tidy_hour")?
+        .and_then(|value| value.parse().ok())
+        .filter(|hour| *hour < 24)
+        .unwrap_or(17);
+    Ok((day, hour))
+}
+
+fn build_reminder(
+    db: &Database,
+    scheduled_for: DateTime<Utc>,
+    day: Weekday,
+    hour: u32,
+) -> anyhow::Result<TidyReminderPayload> {
+    let max_candidates = db
+        .get_preference("max_candidates_per_day")?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(12);
+    let candidates = FileSelector::from_db(db)
+        .daily_candidates(Some(max_candidates), db)
+        .map_err(|e| anyhow::anyhow!("candidates: {e}"))?;
+
+    let mut gauge_manager = GaugeManager::new();
+    gauge_manager.set_selector(FileSelector::from_db(db));
+    gauge_manager.set_tidy_day(day);
+    gauge_manager.set_tidy_hour(hour);
+    let gauge = gauge_manager.gauge_state(db)?;
+
+    Ok(TidyReminderPayload {
+        scheduled_for,
+        candidate_count: candidates.len(),
+        candidate_bytes: candidates.iter().map(|c| c.size_bytes).sum(),
+        summary: gauge_manager.get_gauge_summary(&gauge),
+        summary_message: gauge_manager.gauge_summary_message(&gauge),
+        gauge,
+    })
+}
+
+/// The instant `hour:00` local time on `date`. A time skipped by a DST jump
+/// moves to the first hour that exists; a repeated one uses its first pass.
+fn slot_on<Tz: TimeZone>(tz: &Tz, date: NaiveDate, hour: u32) -> Option<DateTime<Tz>> {
+    (hour..24).find_map(
+        |h| match tz.from_local_datetime(&date.and_hms_opt(h, 0, 0)?) {
+            LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => Some(at),
+            LocalResult::None => None,
+        },
+    )
+}
+
+/// Latest tidy slot at or before `now`.
+fn previous_tidy_at<Tz: TimeZone>(now: &DateTime<Tz>, day: Weekday, hour: u32) -> DateTime<Tz> {
+    let today = now.date_naive();
+    (0..=7)
+        .filter_map(|back| {
+            let date = today - Duration::days(back);
+            (date.weekday() == day)
+                .then(|| slot_on(&now.timezone(), date, hour))
+                .flatten()
+        })
+        .find(|slot| slot <= now)
+        .unwrap_or_else(|| now.clone() - Duration::days(7))
+}
+
+/// First tidy slot strictly after `now`.
+fn next_tidy_at<Tz: TimeZone>(now: &DateTime<Tz>, day: Weekday, hour: u32) -> DateTime<Tz> {
+    let today = now.date_naive();
+    (0..=7)
+        .filter_map(|ahead| {
+            let date = today + Duration::days(ahead);
+            (date.weekday() == day)
+                .then(|| slot_on(&now.timezone(), date, hour))
+                .flatten()
+        })
+        .find(|slot| slot > now)
+        .unwrap_or_else(|| now.clone() + Duration::days(7))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    #[test]
+    fn test_next_and_previous_tidy_slots() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        // Wednesday 2024-05-01 10:00 local
+        let now = tz.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+
+        let next = next_tidy_at(&now, Weekday::Fri, 17);
+        assert_eq!(next, tz.with_ymd_and_hms(2024, 5, 3, 17, 0, 0).unwrap());
+        let previous = previous_tidy_at(&now, Weekday::Fri, 17);
+        assert_eq!(
+            previous,
+            tz.with_ymd_and_hms(2024, 4, 26, 17, 0, 0).unwrap()
+        );
+
+        // Exactly on the slot: it counts as the previous one, the next is a week out.
+        let on_slot = tz.with_ymd_and_hms(2024, 5, 3, 17, 0, 0).unwrap();
+        assert_eq!(previous_tidy_at(&on_slot, Weekday::Fri, 17), on_slot);
+        assert_eq!(
+            next_tidy_at(&on_slot, Weekday::Fri, 17),
+            tz.with_ymd_and_hms(2024, 5, 10, 17, 0, 0).unwrap()
+        );
+    }
+}